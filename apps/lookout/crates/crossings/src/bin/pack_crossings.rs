@@ -3,16 +3,22 @@
 //!
 //! Every country the store holds is packed unless a window is given, since the device does
 //! not know where it will be switched on.
+//!
+//! Beside the buffer goes its manifest — its length and SHA-256, signed with the key in
+//! `--signing-key` if one is given — which is what a device checks before it accepts the
+//! buffer. See [`crossings::manifest`].
 
 use std::collections::BTreeSet;
 use std::error::Error;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use clap::Parser;
-use crossings::{Bbox, Point, pointset, silver};
+use crossings::manifest::{self, Hex};
+use crossings::{Bbox, Output, Point, pointset, silver};
 use medallion::MedallionArgs;
+use medallion::lineage::{self, Producer};
 
 /// What the packed buffer is called in gold, and the file each version of it holds.
 const ARTIFACT: &str = "crossings";
@@ -29,6 +35,10 @@ struct Args {
     /// Keep only crossings inside this `west,south,east,north` window. Omit to keep them all.
     #[arg(long)]
     bbox: Option<Bbox>,
+    /// A file holding the Ed25519 secret key to sign the manifest with, as 64 hex digits.
+    /// Without one the manifest is still written, as a checksum nobody has signed.
+    #[arg(long)]
+    signing_key: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -41,15 +51,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .init();
 
     let args = Args::parse();
-    let root = args.medallion.root()?;
-    let output = match args.output {
-        Some(path) => path,
-        None => root.gold_artefact(ARTIFACT, Utc::now(), FILE)?,
+    let mut producer = Producer::new(env!("CARGO_BIN_NAME"));
+    if let Some(window) = args.bbox {
+        producer = producer.parameter("bbox", window);
+    }
+    let root = args.medallion.root()?.recording(producer);
+    // Read before anything is packed, so a missing key fails the run rather than its end.
+    let key = match &args.signing_key {
+        Some(path) => Some(manifest::signing_key(&fs::read_to_string(path)?)?),
+        None => None,
     };
+    let output = Output::new(&root, ARTIFACT, FILE, Utc::now(), args.output)?;
 
     tracing::info!(
         medallion_root = %root.path().display(),
-        output = %output.display(),
+        output = %output.path().display(),
         bbox = args.bbox.map(|bbox| bbox.to_string()),
         "packing crossings",
     );
@@ -66,10 +82,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let points: Vec<_> = crossings.iter().map(Point::of).collect();
 
     let packed = pointset::pack(&points)?;
-    if let Some(directory) = output.parent() {
-        fs::create_dir_all(directory)?;
+    let bytes = packed.len();
+    let signed = manifest::write(&packed, key.as_ref());
+    output.write(Path::to_path_buf, packed).await?;
+    output.write(manifest::sidecar, signed).await?;
+    // The format has no room for where its points came from, so that is kept beside it.
+    if let Some(recorded) = lineage::recorded(&root).await? {
+        output
+            .write(lineage::sidecar, recorded.to_json().into_bytes())
+            .await?;
+    }
+    if let Some(version) = output.record().await? {
+        tracing::info!(version = %version.version, "recorded in the artefact's index");
     }
-    fs::write(&output, &packed)?;
 
     tracing::info!(
         crossings = crossings.len(),
@@ -79,7 +104,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .iter()
             .map(|crossing| crossing.extract_id.as_str())
             .collect::<BTreeSet<_>>(),
-        bytes = packed.len(),
+        bytes,
+        signed_by = key.map(|key| Hex(key.verifying_key().to_bytes()).to_string()),
         "packed crossings",
     );
 
@@ -104,6 +130,7 @@ mod tests {
 
         assert_eq!(args.output, None);
         assert_eq!(args.bbox, None);
+        assert_eq!(args.signing_key, None);
     }
 
     /// The buffer belongs in the store it was derived from, under the run that produced it,
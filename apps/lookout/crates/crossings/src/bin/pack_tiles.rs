@@ -0,0 +1,141 @@
+//! `pack_tiles`: read the silver water crossings out of the store and write them as a
+//! PMTiles archive of vector tiles, to be looked at on a map.
+//!
+//! The archive is the same crossings [`pack_crossings`](../pack_crossings) packs for the
+//! device, drawn rather than scanned, so a crossing picked on the map is named by the same
+//! `crossing_short_id` a device reports. With `--rail`, the track they were derived from is
+//! drawn beneath them, read from the bronze extracts the crossings name.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use clap::Parser;
+use crossings::{Output, Zooms, rail, silver, tiles};
+use medallion::MedallionArgs;
+use medallion::lineage::{self, Producer};
+
+/// What the archive is called in gold, and the file each version of it holds.
+const ARTIFACT: &str = "crossings_tiles";
+const FILE: &str = "crossings.pmtiles";
+
+#[derive(Parser)]
+#[command(about = "Cut silver water crossings into a PMTiles archive of vector tiles")]
+struct Args {
+    #[command(flatten)]
+    medallion: MedallionArgs,
+    /// Where to write the archive. Defaults to the store's own gold layer.
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// The shallowest zoom to cut tiles at.
+    #[arg(long, default_value_t = 0)]
+    min_zoom: u8,
+    /// The deepest zoom to cut tiles at.
+    #[arg(long, default_value_t = 14)]
+    max_zoom: u8,
+    /// Draw the rail the crossings lie on, from the bronze extracts they were derived from.
+    #[arg(long)]
+    rail: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "pack_tiles=info".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+    let zooms = Zooms::new(args.min_zoom, args.max_zoom)?;
+    let producer = Producer::new(env!("CARGO_BIN_NAME"))
+        .parameter("min_zoom", zooms.min())
+        .parameter("max_zoom", zooms.max())
+        .parameter("rail", args.rail);
+    let root = args.medallion.root()?.recording(producer);
+    let output = Output::new(&root, ARTIFACT, FILE, Utc::now(), args.output)?;
+
+    tracing::info!(
+        medallion_root = %root.path().display(),
+        output = %output.path().display(),
+        min_zoom = zooms.min(),
+        max_zoom = zooms.max(),
+        rail = args.rail,
+        "cutting tiles",
+    );
+
+    let crossings = silver::read_described(&root).await?;
+    let extracts: BTreeSet<String> = crossings
+        .iter()
+        .map(|described| described.crossing.extract_id.clone())
+        .collect();
+    let rails = match args.rail {
+        true => rail::read(&root, &extracts).await?,
+        false => Vec::new(),
+    };
+
+    // The archive is cut a tile at a time, so the pyramid's features are never held whole:
+    // only the finished tiles are, which are what is put into the store in one go.
+    let archive = tiles::pack(&crossings, &rails, zooms, Cursor::new(Vec::new()))?.into_inner();
+    let bytes = archive.len();
+    output.write(Path::to_path_buf, archive).await?;
+    // The archive's metadata names the extracts; the full lineage is kept beside it, as it
+    // is for every gold artefact.
+    if let Some(recorded) = lineage::recorded(&root).await? {
+        output
+            .write(lineage::sidecar, recorded.to_json().into_bytes())
+            .await?;
+    }
+    if let Some(version) = output.record().await? {
+        tracing::info!(version = %version.version, "recorded in the artefact's index");
+    }
+
+    tracing::info!(
+        crossings = crossings.len(),
+        rails = rails.len(),
+        extracts = ?extracts,
+        bytes,
+        "cut tiles",
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn the_arguments_are_well_formed() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn the_defaults_need_no_arguments() {
+        let args = Args::parse_from(["pack_tiles"]);
+
+        assert_eq!(args.output, None);
+        assert_eq!((args.min_zoom, args.max_zoom), (0, 14));
+        assert!(!args.rail);
+    }
+
+    #[test]
+    fn the_default_output_is_a_versioned_gold_artefact_of_whichever_store_is_read() {
+        let args = Args::parse_from(["pack_tiles", "--medallion-root", "/somewhere/store"]);
+        let root = args.medallion.root().unwrap();
+        let run = Utc.with_ymd_and_hms(2026, 8, 1, 19, 48, 57).unwrap();
+
+        assert_eq!(
+            root.gold_artefact(ARTIFACT, run, FILE).unwrap(),
+            PathBuf::from(
+                "/somewhere/store/gold/artifact=crossings_tiles/version=20260801T194857000Z/crossings.pmtiles"
+            )
+        );
+    }
+}
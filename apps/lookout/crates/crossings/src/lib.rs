@@ -3,12 +3,27 @@
 //! The device holds every crossing in RAM and brute-force scans the lot against each GPS fix,
 //! so what it needs is not a queryable dataset but a packed array of coordinates. Deriving
 //! that is this crate's whole job.
+//!
+//! The same crossings are also cut into vector tiles, for looking at them on a map rather
+//! than carrying them on a device: see [`tiles`].
 
 pub mod bbox;
+pub mod mvt;
+pub mod output;
+pub mod pmtiles;
 pub mod pointset;
+pub mod rail;
 pub mod random;
 pub mod silver;
+pub mod tiles;
+
+/// The manifest vouching for a packed buffer, in a crate of its own so that a device can
+/// verify one without `std`.
+pub use manifest;
 
 pub use bbox::{Bbox, BboxError};
+pub use output::{Output, OutputError};
 pub use pointset::{FormatError, PackedId, Point};
-pub use silver::{Crossing, ReadError};
+pub use rail::{Rail, RailError};
+pub use silver::{Crossing, Described, ReadError};
+pub use tiles::{TileError, Zooms};
@@ -0,0 +1,517 @@
+//! Mapbox Vector Tiles: the protobuf a map renderer draws one tile of features from.
+//!
+//! Written by hand rather than through a protobuf crate, because the format is four small
+//! messages and what a tile holds here is two layers of points and lines: a generated
+//! decoder and its build step would be most of the code. The layout is version 2 of the
+//! specification, which is the one every renderer reads.
+//!
+//! [`decode`] is the same format read back. Nothing in the packing path needs it; it is
+//! what a test, or anyone holding an archive, checks a tile's contents with, and it reads
+//! only what [`encode`] writes — points and lines, with the value types below.
+
+use std::collections::HashMap;
+
+/// The width of a tile in its own coordinates, which is what every renderer assumes when a
+/// layer does not say otherwise.
+pub const EXTENT: u32 = 4096;
+/// The specification's version, which a layer carries.
+const VERSION: u64 = 2;
+
+/// Command ids in a feature's geometry.
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+
+/// Protobuf wire types.
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const DELIMITED: u8 = 2;
+const FIXED32: u8 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("the tile ends inside a field")]
+    Truncated,
+    #[error("wire type {0}, which a vector tile does not use")]
+    WireType(u8),
+    #[error("a feature refers to {kind} {index}, which its layer does not hold")]
+    NoSuchTag { kind: &'static str, index: usize },
+    #[error("a feature's geometry is not a point or a line: {0}")]
+    Geometry(String),
+    #[error("a property value holds none of the types a vector tile has")]
+    NoValue,
+    #[error("a layer or property name is not UTF-8")]
+    NotUtf8,
+}
+
+/// A property's value, as the format types it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Double(f64),
+    Int(i64),
+    Uint(u64),
+    Bool(bool),
+}
+
+/// Where a feature is, in tile coordinates: `0..EXTENT` across the tile, `y` downwards.
+///
+/// A line may run outside the tile — a renderer clips it — so coordinates are signed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shape {
+    Point((i32, i32)),
+    Line(Vec<(i32, i32)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub id: Option<u64>,
+    pub properties: Vec<(String, Value)>,
+    pub shape: Shape,
+}
+
+impl Feature {
+    /// The value of property `key`, if the feature has one.
+    pub fn property(&self, key: &str) -> Option<&Value> {
+        self.properties
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub extent: u32,
+    pub features: Vec<Feature>,
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            extent: EXTENT,
+            features: Vec::new(),
+        }
+    }
+}
+
+/// The tile holding `layers`. A layer with no features is left out, as a renderer would
+/// draw nothing from it anyway.
+pub fn encode(layers: &[Layer]) -> Vec<u8> {
+    let mut tile = Vec::new();
+    for layer in layers.iter().filter(|layer| !layer.features.is_empty()) {
+        delimited(&mut tile, 3, &encode_layer(layer));
+    }
+    tile
+}
+
+fn encode_layer(layer: &Layer) -> Vec<u8> {
+    // Keys and values are held once per layer and referred to by index from each feature.
+    let mut keys: Vec<&str> = Vec::new();
+    let mut key_index: HashMap<&str, u32> = HashMap::new();
+    let mut values: Vec<Vec<u8>> = Vec::new();
+    let mut value_index: HashMap<Vec<u8>, u32> = HashMap::new();
+
+    let mut features = Vec::new();
+    for feature in &layer.features {
+        let mut tags = Vec::new();
+        for (key, value) in &feature.properties {
+            let k = *key_index.entry(key.as_str()).or_insert_with(|| {
+                keys.push(key.as_str());
+                keys.len() as u32 - 1
+            });
+            let encoded = encode_value(value);
+            let v = match value_index.get(&encoded) {
+                Some(v) => *v,
+                None => {
+                    values.push(encoded.clone());
+                    value_index.insert(encoded, values.len() as u32 - 1);
+                    values.len() as u32 - 1
+                }
+            };
+            tags.push(k);
+            tags.push(v);
+        }
+
+        let mut encoded = Vec::new();
+        if let Some(id) = feature.id {
+            field(&mut encoded, 1, VARINT);
+            varint(&mut encoded, id);
+        }
+        packed(&mut encoded, 2, &tags);
+        let (kind, geometry) = geometry(&feature.shape);
+        field(&mut encoded, 3, VARINT);
+        varint(&mut encoded, kind);
+        packed(&mut encoded, 4, &geometry);
+        features.push(encoded);
+    }
+
+    let mut encoded = Vec::new();
+    field(&mut encoded, 15, VARINT);
+    varint(&mut encoded, VERSION);
+    delimited(&mut encoded, 1, layer.name.as_bytes());
+    for feature in &features {
+        delimited(&mut encoded, 2, feature);
+    }
+    for key in &keys {
+        delimited(&mut encoded, 3, key.as_bytes());
+    }
+    for value in &values {
+        delimited(&mut encoded, 4, value);
+    }
+    field(&mut encoded, 5, VARINT);
+    varint(&mut encoded, u64::from(layer.extent));
+    encoded
+}
+
+fn encode_value(value: &Value) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    match value {
+        Value::String(string) => delimited(&mut encoded, 1, string.as_bytes()),
+        Value::Double(double) => {
+            field(&mut encoded, 3, FIXED64);
+            encoded.extend_from_slice(&double.to_le_bytes());
+        }
+        Value::Int(int) => {
+            field(&mut encoded, 4, VARINT);
+            varint(&mut encoded, *int as u64);
+        }
+        Value::Uint(uint) => {
+            field(&mut encoded, 5, VARINT);
+            varint(&mut encoded, *uint);
+        }
+        Value::Bool(bool) => {
+            field(&mut encoded, 7, VARINT);
+            varint(&mut encoded, u64::from(*bool));
+        }
+    }
+    encoded
+}
+
+/// The geometry type and command stream for `shape`. Each coordinate is a delta from the
+/// last, zigzag-encoded, starting from the tile's origin.
+fn geometry(shape: &Shape) -> (u64, Vec<u32>) {
+    let mut cursor = (0, 0);
+    let mut step = |commands: &mut Vec<u32>, (x, y): (i32, i32)| {
+        commands.push(zigzag(x - cursor.0));
+        commands.push(zigzag(y - cursor.1));
+        cursor = (x, y);
+    };
+    let mut commands = Vec::new();
+    match shape {
+        Shape::Point(point) => {
+            commands.push(command(MOVE_TO, 1));
+            step(&mut commands, *point);
+            (1, commands)
+        }
+        Shape::Line(line) => {
+            if let Some((first, rest)) = line.split_first() {
+                commands.push(command(MOVE_TO, 1));
+                step(&mut commands, *first);
+                commands.push(command(LINE_TO, rest.len() as u32));
+                for point in rest {
+                    step(&mut commands, *point);
+                }
+            }
+            (2, commands)
+        }
+    }
+}
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+fn zigzag(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+fn unzigzag(n: u32) -> i32 {
+    ((n >> 1) as i32) ^ -((n & 1) as i32)
+}
+
+fn field(out: &mut Vec<u8>, number: u32, wire: u8) {
+    varint(out, u64::from(number << 3 | u32::from(wire)));
+}
+
+fn delimited(out: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    field(out, number, DELIMITED);
+    varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn packed(out: &mut Vec<u8>, number: u32, values: &[u32]) {
+    let mut encoded = Vec::new();
+    for value in values {
+        varint(&mut encoded, u64::from(*value));
+    }
+    delimited(out, number, &encoded);
+}
+
+pub(crate) fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// The varint at the start of `bytes`, and how many bytes it took.
+pub(crate) fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (index, byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+/// One field of a message as read off the wire.
+enum Wire<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Fixed32([u8; 4]),
+    Delimited(&'a [u8]),
+}
+
+/// The fields of one message, in the order they were written.
+fn fields(mut bytes: &[u8]) -> Result<Vec<(u32, Wire<'_>)>, DecodeError> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let (key, used) = read_varint(bytes).ok_or(DecodeError::Truncated)?;
+        bytes = &bytes[used..];
+        let number = (key >> 3) as u32;
+        let wire = match (key & 0x7) as u8 {
+            VARINT => {
+                let (value, used) = read_varint(bytes).ok_or(DecodeError::Truncated)?;
+                bytes = &bytes[used..];
+                Wire::Varint(value)
+            }
+            FIXED64 => {
+                let value = bytes.get(..8).ok_or(DecodeError::Truncated)?;
+                bytes = &bytes[8..];
+                Wire::Fixed64(value.try_into().expect("8 bytes"))
+            }
+            FIXED32 => {
+                let value = bytes.get(..4).ok_or(DecodeError::Truncated)?;
+                bytes = &bytes[4..];
+                Wire::Fixed32(value.try_into().expect("4 bytes"))
+            }
+            DELIMITED => {
+                let (length, used) = read_varint(bytes).ok_or(DecodeError::Truncated)?;
+                let end = used + length as usize;
+                let value = bytes.get(used..end).ok_or(DecodeError::Truncated)?;
+                bytes = &bytes[end..];
+                Wire::Delimited(value)
+            }
+            other => return Err(DecodeError::WireType(other)),
+        };
+        fields.push((number, wire));
+    }
+    Ok(fields)
+}
+
+fn unpacked(bytes: &[u8]) -> Result<Vec<u32>, DecodeError> {
+    let mut values = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (value, used) = read_varint(rest).ok_or(DecodeError::Truncated)?;
+        values.push(value as u32);
+        rest = &rest[used..];
+    }
+    Ok(values)
+}
+
+fn utf8(bytes: &[u8]) -> Result<String, DecodeError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::NotUtf8)
+}
+
+/// The layers a tile holds, with each feature's properties looked up by name.
+pub fn decode(tile: &[u8]) -> Result<Vec<Layer>, DecodeError> {
+    let mut layers = Vec::new();
+    for (number, wire) in fields(tile)? {
+        if let (3, Wire::Delimited(layer)) = (number, wire) {
+            layers.push(decode_layer(layer)?);
+        }
+    }
+    Ok(layers)
+}
+
+/// A feature as it sits in its layer, before its tags are resolved.
+struct Stored {
+    id: Option<u64>,
+    tags: Vec<u32>,
+    kind: u64,
+    geometry: Vec<u32>,
+}
+
+fn decode_layer(bytes: &[u8]) -> Result<Layer, DecodeError> {
+    let mut layer = Layer::new("");
+    let mut stored = Vec::new();
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+    for (number, wire) in fields(bytes)? {
+        match (number, wire) {
+            (1, Wire::Delimited(name)) => layer.name = utf8(name)?,
+            (2, Wire::Delimited(feature)) => stored.push(decode_feature(feature)?),
+            (3, Wire::Delimited(key)) => keys.push(utf8(key)?),
+            (4, Wire::Delimited(value)) => values.push(decode_value(value)?),
+            (5, Wire::Varint(extent)) => layer.extent = extent as u32,
+            _ => {}
+        }
+    }
+
+    for feature in stored {
+        let mut properties = Vec::new();
+        for pair in feature.tags.chunks(2) {
+            let (k, v) = (pair[0] as usize, *pair.get(1).unwrap_or(&u32::MAX) as usize);
+            let key = keys.get(k).ok_or(DecodeError::NoSuchTag {
+                kind: "key",
+                index: k,
+            })?;
+            let value = values.get(v).ok_or(DecodeError::NoSuchTag {
+                kind: "value",
+                index: v,
+            })?;
+            properties.push((key.clone(), value.clone()));
+        }
+        layer.features.push(Feature {
+            id: feature.id,
+            properties,
+            shape: decode_geometry(feature.kind, &feature.geometry)?,
+        });
+    }
+    Ok(layer)
+}
+
+fn decode_feature(bytes: &[u8]) -> Result<Stored, DecodeError> {
+    let mut feature = Stored {
+        id: None,
+        tags: Vec::new(),
+        kind: 0,
+        geometry: Vec::new(),
+    };
+    for (number, wire) in fields(bytes)? {
+        match (number, wire) {
+            (1, Wire::Varint(id)) => feature.id = Some(id),
+            (2, Wire::Delimited(tags)) => feature.tags = unpacked(tags)?,
+            (3, Wire::Varint(kind)) => feature.kind = kind,
+            (4, Wire::Delimited(geometry)) => feature.geometry = unpacked(geometry)?,
+            _ => {}
+        }
+    }
+    Ok(feature)
+}
+
+fn decode_value(bytes: &[u8]) -> Result<Value, DecodeError> {
+    for (number, wire) in fields(bytes)? {
+        let value = match (number, wire) {
+            (1, Wire::Delimited(string)) => Value::String(utf8(string)?),
+            (2, Wire::Fixed32(float)) => Value::Double(f64::from(f32::from_le_bytes(float))),
+            (3, Wire::Fixed64(double)) => Value::Double(f64::from_le_bytes(double)),
+            (4, Wire::Varint(int)) => Value::Int(int as i64),
+            (5, Wire::Varint(uint)) => Value::Uint(uint),
+            (6, Wire::Varint(sint)) => Value::Int(((sint >> 1) as i64) ^ -((sint & 1) as i64)),
+            (7, Wire::Varint(bool)) => Value::Bool(bool != 0),
+            _ => continue,
+        };
+        return Ok(value);
+    }
+    Err(DecodeError::NoValue)
+}
+
+fn decode_geometry(kind: u64, commands: &[u32]) -> Result<Shape, DecodeError> {
+    let mut cursor = (0, 0);
+    let mut points = Vec::new();
+    let mut rest = commands;
+    while let Some((header, tail)) = rest.split_first() {
+        let (id, count) = (header & 0x7, (header >> 3) as usize);
+        if id != MOVE_TO && id != LINE_TO {
+            return Err(DecodeError::Geometry(format!("command {id}")));
+        }
+        let parameters = tail
+            .get(..count * 2)
+            .ok_or_else(|| DecodeError::Geometry("a command runs past the end".into()))?;
+        for pair in parameters.chunks(2) {
+            cursor = (cursor.0 + unzigzag(pair[0]), cursor.1 + unzigzag(pair[1]));
+            points.push(cursor);
+        }
+        rest = &tail[count * 2..];
+    }
+    match (kind, points.len()) {
+        (1, 1) => Ok(Shape::Point(points[0])),
+        (2, 2..) => Ok(Shape::Line(points)),
+        (kind, count) => Err(DecodeError::Geometry(format!(
+            "type {kind} with {count} points"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crossing(id: u64, at: (i32, i32), class: &str) -> Feature {
+        Feature {
+            id: Some(id),
+            properties: vec![
+                ("water_class".into(), Value::String(class.into())),
+                ("overlap_m".into(), Value::Double(12.5)),
+            ],
+            shape: Shape::Point(at),
+        }
+    }
+
+    /// What a renderer would draw from the tile is what was put in it.
+    #[test]
+    fn a_tile_decodes_to_the_layers_it_was_encoded_from() {
+        let mut points = Layer::new("crossings");
+        points.features.push(crossing(7, (100, 4000), "river"));
+        points.features.push(crossing(8, (2048, 0), "canal"));
+        let mut lines = Layer::new("rail");
+        lines.features.push(Feature {
+            id: None,
+            properties: vec![("class".into(), Value::String("standard_gauge".into()))],
+            shape: Shape::Line(vec![(-50, 10), (300, 20), (5000, 4200)]),
+        });
+
+        let layers = [points, lines];
+        assert_eq!(decode(&encode(&layers)).unwrap(), layers);
+    }
+
+    /// Keys and values are shared across a layer's features, so a property every feature
+    /// has costs its name once.
+    #[test]
+    fn a_repeated_property_is_stored_once_per_layer() {
+        let mut layer = Layer::new("crossings");
+        for id in 0..10 {
+            layer.features.push(crossing(id, (0, 0), "river"));
+        }
+
+        let tile = encode(&[layer]);
+        let occurrences = |needle: &[u8]| {
+            tile.windows(needle.len())
+                .filter(|at| *at == needle)
+                .count()
+        };
+        assert_eq!(occurrences(b"water_class"), 1);
+        assert_eq!(occurrences(b"river"), 1);
+    }
+
+    #[test]
+    fn an_empty_layer_is_left_out() {
+        assert!(encode(&[Layer::new("crossings")]).is_empty());
+    }
+
+    #[test]
+    fn coordinates_are_zigzag_encoded() {
+        for n in [0, 1, -1, 4095, -4096, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+}
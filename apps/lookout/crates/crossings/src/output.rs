@@ -0,0 +1,118 @@
+//! Where a packer writes what it packed: a file named on its command line, or the next
+//! version of one of the store's gold artefacts.
+//!
+//! A gold version is written through the store it belongs to, so a store in a bucket gets
+//! its artefacts in the bucket, where recording the version then finds them. Writing to the
+//! local path the version would have on disk would put them somewhere nothing reads.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use medallion::gold::{Artefact, ArtefactVersion, GoldError};
+use medallion::{PathError, Root};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutputError {
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Gold(#[from] GoldError),
+}
+
+/// Where one run's output goes.
+#[derive(Debug, Clone)]
+pub enum Output {
+    /// A file named on the command line, written to disk and recorded nowhere.
+    File(PathBuf),
+    /// The file `file` of the version a run at `run` writes of `artefact`.
+    Gold {
+        artefact: Artefact,
+        run: DateTime<Utc>,
+        file: String,
+        path: PathBuf,
+    },
+}
+
+impl Output {
+    /// `explicit` if one was given, and otherwise the file `file` of the version a run at
+    /// `run` writes of the gold artefact `artifact` in `root`.
+    pub fn new(
+        root: &Root,
+        artifact: &str,
+        file: &str,
+        run: DateTime<Utc>,
+        explicit: Option<PathBuf>,
+    ) -> Result<Self, PathError> {
+        if let Some(path) = explicit {
+            return Ok(Self::File(path));
+        }
+        let artefact = root.artefact(artifact)?;
+        let path = artefact.file(run, file)?;
+        Ok(Self::Gold {
+            artefact,
+            run,
+            file: file.to_string(),
+            path,
+        })
+    }
+
+    /// Where the output itself goes — in the store's own terms for a gold version, which is
+    /// a key in a bucket rather than a path on disk when the store is in one.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Gold { path, .. } => path,
+        }
+    }
+
+    /// Write `bytes` as the file `beside` makes of the output's path: the output itself
+    /// given [`Path::to_path_buf`], or a sidecar such as [`medallion::lineage::sidecar`].
+    pub async fn write(
+        &self,
+        beside: fn(&Path) -> PathBuf,
+        bytes: Vec<u8>,
+    ) -> Result<(), OutputError> {
+        match self {
+            Self::File(path) => {
+                let path = beside(path);
+                if let Some(directory) = path.parent() {
+                    std::fs::create_dir_all(directory).map_err(|source| io_error(&path, source))?;
+                }
+                std::fs::write(&path, bytes).map_err(|source| io_error(&path, source))
+            }
+            Self::Gold {
+                artefact,
+                run,
+                file,
+                ..
+            } => {
+                let name = beside(Path::new(file));
+                artefact.put(*run, &name.to_string_lossy(), bytes).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Record a gold version in its artefact's index, once every file of it is written. A
+    /// file named on the command line is nobody's version, and is recorded nowhere.
+    ///
+    /// Only a version written into the store is one its index can vouch for; it is live once
+    /// it is promoted, which is a decision made after looking at it, not by the run.
+    pub async fn record(&self) -> Result<Option<ArtefactVersion>, GoldError> {
+        match self {
+            Self::File(_) => Ok(None),
+            Self::Gold { artefact, run, .. } => Ok(Some(artefact.record(*run).await?)),
+        }
+    }
+}
+
+fn io_error(path: &Path, source: io::Error) -> OutputError {
+    OutputError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
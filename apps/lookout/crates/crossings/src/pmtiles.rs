@@ -0,0 +1,561 @@
+//! PMTiles version 3: a whole pyramid of tiles in one file, read by byte range.
+//!
+//! The format is what lets the crossings be put on a map from a static file: a renderer
+//! fetches the header, then the directory, then only the tiles in view, so the archive can
+//! sit in a bucket or a gold directory with no tile server in front of it. See the
+//! specification at <https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md>.
+//!
+//! **Nothing is compressed**, neither the tiles nor the directories. The format allows it,
+//! every reader accepts it, and the archives this writes are small enough that gzip would
+//! save little beside the dependency it costs; the header says so, so a reader never guesses.
+//!
+//! Tiles with the same bytes are stored once, and a run of them under consecutive ids is one
+//! directory entry, which is most of the ocean in any archive and most of the empty
+//! countryside in this one.
+//!
+//! **Tiles are streamed.** A [`Writer`] puts each tile in the file as it is handed one, in id
+//! order, and holds only its directory entry and a digest of its bytes to find a repeat by;
+//! the header and root directory are written last, into the first fetch's worth of bytes
+//! held back for them at the start, and the metadata and leaf directories after the tiles.
+//! Every section is found through the offsets in the header, so the order they sit in is the
+//! writer's to choose.
+//!
+//! Written by hand, as the tiles themselves are — see [`crate::mvt`]: the format is a fixed
+//! header and varint directories, and [`Archive`] reads back exactly what is written here,
+//! which is what the tests hold the writer to.
+
+use std::collections::HashMap;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::Bbox;
+use crate::mvt::{read_varint, varint};
+
+/// The first bytes of every archive, and the version this writes and reads.
+const MAGIC: &[u8; 7] = b"PMTiles";
+const VERSION: u8 = 3;
+/// The header's fixed size.
+pub const HEADER_LEN: usize = 127;
+/// How much of the file a reader fetches first, which the header and root directory have
+/// to fit in between them. Anything that does not goes in leaf directories.
+const FIRST_FETCH: usize = 16_384;
+/// The entries per leaf directory an archive starts with, doubled until the root fits.
+const LEAF_ENTRIES: usize = 4096;
+
+/// Compression ids, as the header names them.
+const UNCOMPRESSED: u8 = 1;
+/// The tile type id of a Mapbox Vector Tile.
+const MVT: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("{0} bytes is too short to hold a {HEADER_LEN}-byte header")]
+    NoHeader(usize),
+    #[error("does not start with {:?}", String::from_utf8_lossy(MAGIC))]
+    NotAnArchive,
+    #[error("version {0}, which this reader does not know (it reads {VERSION})")]
+    UnsupportedVersion(u8),
+    #[error("compressed with {0}, and this reader reads only uncompressed archives")]
+    Compressed(u8),
+    #[error("a directory or tile lies outside the archive")]
+    Truncated,
+}
+
+/// A failure writing an archive.
+#[derive(Debug, thiserror::Error)]
+pub enum WriteError {
+    #[error("writing the archive: {0}")]
+    Io(#[from] io::Error),
+    #[error("tile {id} was handed over after tile {after}, and an archive is written in id order")]
+    OutOfOrder { id: u64, after: u64 },
+}
+
+/// What an archive covers, as its header describes it to a renderer before any tile is read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coverage {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub bounds: Bbox,
+}
+
+/// The id a tile is addressed by: its position along a Hilbert curve over its zoom level,
+/// counted on from every tile at the zooms above it. Neighbouring tiles get nearby ids,
+/// which is what lets a run of them share a directory entry and a viewport read one range.
+pub fn tile_id(z: u8, x: u32, y: u32) -> u64 {
+    let mut id = ((1u64 << (2 * u32::from(z))) - 1) / 3;
+    let (mut x, mut y) = (u64::from(x), u64::from(y));
+    for level in (0..z).rev() {
+        let side = 1u64 << level;
+        let (rx, ry) = (x & side, y & side);
+        id += ((3 * rx) ^ ry) * side;
+        if ry == 0 {
+            if rx != 0 {
+                x = side.wrapping_sub(1).wrapping_sub(x);
+                y = side.wrapping_sub(1).wrapping_sub(y);
+            }
+            (x, y) = (y, x);
+        }
+    }
+    id
+}
+
+/// One directory entry: `run_length` tiles from `tile_id` on, all stored at `offset`, or
+/// with a `run_length` of 0, a leaf directory covering ids from `tile_id` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    run_length: u32,
+}
+
+/// An archive being written to `out`, a tile at a time.
+pub struct Writer<W: Write + Seek> {
+    out: W,
+    /// Where in `out` the archive starts, and so where its header goes.
+    start: u64,
+    /// Where the next tile's bytes go, counted from the start of the tile data.
+    data_len: u64,
+    entries: Vec<Entry>,
+    /// Each distinct tile written so far, by the MD5 of its bytes, and where it was put.
+    stored: HashMap<[u8; 16], (u64, u32)>,
+    last_id: Option<u64>,
+}
+
+impl<W: Write + Seek> Writer<W> {
+    /// Start an archive at the current position of `out`, which is taken as its first byte.
+    pub fn new(mut out: W) -> Result<Self, WriteError> {
+        let start = out.stream_position()?;
+        out.write_all(&[0; FIRST_FETCH])?;
+        Ok(Self {
+            out,
+            start,
+            data_len: 0,
+            entries: Vec::new(),
+            stored: HashMap::new(),
+            last_id: None,
+        })
+    }
+
+    /// Add the vector tile `tile` under `id`, a [`tile_id`] greater than any added before.
+    /// An empty tile is left out, since a renderer treats a missing tile as an empty one.
+    pub fn add(&mut self, id: u64, tile: &[u8]) -> Result<(), WriteError> {
+        if let Some(after) = self.last_id.filter(|after| id <= *after) {
+            return Err(WriteError::OutOfOrder { id, after });
+        }
+        self.last_id = Some(id);
+        if tile.is_empty() {
+            return Ok(());
+        }
+
+        let digest = md5::compute(tile).0;
+        let (offset, length) = match self.stored.get(&digest) {
+            Some(stored) => *stored,
+            None => {
+                self.out.write_all(tile)?;
+                let stored = (self.data_len, tile.len() as u32);
+                self.data_len += tile.len() as u64;
+                self.stored.insert(digest, stored);
+                stored
+            }
+        };
+        match self.entries.last_mut() {
+            Some(last)
+                if last.offset == offset && last.tile_id + u64::from(last.run_length) == id =>
+            {
+                last.run_length += 1;
+            }
+            _ => self.entries.push(Entry {
+                tile_id: id,
+                offset,
+                length,
+                run_length: 1,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Finish the archive with `metadata` — a JSON object — and hand back where it was
+    /// written, flushed.
+    pub fn finish(mut self, coverage: Coverage, metadata: &str) -> Result<W, WriteError> {
+        let (root, leaves) = directories(&self.entries);
+        let root_offset = HEADER_LEN as u64;
+        let data_offset = FIRST_FETCH as u64;
+        let metadata_offset = data_offset + self.data_len;
+        let leaves_offset = metadata_offset + metadata.len() as u64;
+        self.out.write_all(metadata.as_bytes())?;
+        self.out.write_all(&leaves)?;
+        let end = self.out.stream_position()?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        for section in [
+            (root_offset, root.len() as u64),
+            (metadata_offset, metadata.len() as u64),
+            (leaves_offset, leaves.len() as u64),
+            (data_offset, self.data_len),
+        ] {
+            header.extend_from_slice(&section.0.to_le_bytes());
+            header.extend_from_slice(&section.1.to_le_bytes());
+        }
+        let addressed: u64 = self.entries.iter().map(|e| u64::from(e.run_length)).sum();
+        header.extend_from_slice(&addressed.to_le_bytes());
+        header.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(self.stored.len() as u64).to_le_bytes());
+        // Clustered: tile data is laid out in id order, so a reader can fetch runs of it.
+        header.push(1);
+        header.push(UNCOMPRESSED);
+        header.push(UNCOMPRESSED);
+        header.push(MVT);
+        header.push(coverage.min_zoom);
+        header.push(coverage.max_zoom);
+        let (min, max) = (coverage.bounds.min(), coverage.bounds.max());
+        for degrees in [min.x, min.y, max.x, max.y] {
+            header.extend_from_slice(&e7(degrees).to_le_bytes());
+        }
+        header.push(coverage.min_zoom);
+        header.extend_from_slice(&e7((min.x + max.x) / 2.0).to_le_bytes());
+        header.extend_from_slice(&e7((min.y + max.y) / 2.0).to_le_bytes());
+        debug_assert_eq!(header.len(), HEADER_LEN);
+
+        self.out.seek(SeekFrom::Start(self.start))?;
+        self.out.write_all(&header)?;
+        self.out.write_all(&root)?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Degrees as the header holds them, in ten-millionths.
+fn e7(degrees: f64) -> i32 {
+    (degrees * 10_000_000.0).round() as i32
+}
+
+/// The root directory and the leaf directories beneath it, if the entries do not all fit in
+/// the root.
+fn directories(entries: &[Entry]) -> (Vec<u8>, Vec<u8>) {
+    let root = directory(entries);
+    if HEADER_LEN + root.len() <= FIRST_FETCH {
+        return (root, Vec::new());
+    }
+    let mut per_leaf = LEAF_ENTRIES;
+    loop {
+        let mut leaves = Vec::new();
+        let mut pointers = Vec::new();
+        for chunk in entries.chunks(per_leaf) {
+            let leaf = directory(chunk);
+            pointers.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+        let root = directory(&pointers);
+        if HEADER_LEN + root.len() <= FIRST_FETCH {
+            return (root, leaves);
+        }
+        per_leaf *= 2;
+    }
+}
+
+/// One directory's bytes: the entry count, then each field of every entry in turn, ids as
+/// deltas and an offset that follows straight on from the previous tile as 0.
+fn directory(entries: &[Entry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    varint(&mut out, entries.len() as u64);
+    let mut last = 0;
+    for entry in entries {
+        varint(&mut out, entry.tile_id - last);
+        last = entry.tile_id;
+    }
+    for entry in entries {
+        varint(&mut out, u64::from(entry.run_length));
+    }
+    for entry in entries {
+        varint(&mut out, u64::from(entry.length));
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        let follows = index > 0 && {
+            let previous = entries[index - 1];
+            entry.offset == previous.offset + u64::from(previous.length)
+        };
+        match follows {
+            true => varint(&mut out, 0),
+            false => varint(&mut out, entry.offset + 1),
+        }
+    }
+    out
+}
+
+fn read_directory(mut bytes: &[u8]) -> Result<Vec<Entry>, ArchiveError> {
+    let mut next = || -> Result<u64, ArchiveError> {
+        let (value, used) = read_varint(bytes).ok_or(ArchiveError::Truncated)?;
+        bytes = &bytes[used..];
+        Ok(value)
+    };
+    let count = next()? as usize;
+    let mut entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        };
+        count
+    ];
+    let mut last = 0;
+    for entry in entries.iter_mut() {
+        last += next()?;
+        entry.tile_id = last;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = next()? as u32;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = next()? as u32;
+    }
+    let mut previous: Option<Entry> = None;
+    for entry in entries.iter_mut() {
+        entry.offset = match (next()?, previous) {
+            (0, Some(previous)) => previous.offset + u64::from(previous.length),
+            (offset, _) => offset.checked_sub(1).ok_or(ArchiveError::Truncated)?,
+        };
+        previous = Some(*entry);
+    }
+    Ok(entries)
+}
+
+/// An archive opened for reading tiles back out of it.
+pub struct Archive<'a> {
+    bytes: &'a [u8],
+    root: Vec<Entry>,
+    coverage: Coverage,
+    metadata: &'a [u8],
+    leaves_offset: u64,
+    data_offset: u64,
+}
+
+impl<'a> Archive<'a> {
+    pub fn open(bytes: &'a [u8]) -> Result<Self, ArchiveError> {
+        let header = bytes
+            .get(..HEADER_LEN)
+            .ok_or(ArchiveError::NoHeader(bytes.len()))?;
+        if &header[..7] != MAGIC {
+            return Err(ArchiveError::NotAnArchive);
+        }
+        if header[7] != VERSION {
+            return Err(ArchiveError::UnsupportedVersion(header[7]));
+        }
+        for compression in [header[97], header[98]] {
+            if compression != UNCOMPRESSED {
+                return Err(ArchiveError::Compressed(compression));
+            }
+        }
+        let word = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().expect("8"));
+        let degrees = |at: usize| {
+            f64::from(i32::from_le_bytes(
+                header[at..at + 4].try_into().expect("4"),
+            )) / 10_000_000.0
+        };
+        let section = |at: usize| -> Result<&'a [u8], ArchiveError> {
+            let (offset, length) = (word(at) as usize, word(at + 8) as usize);
+            bytes
+                .get(offset..offset + length)
+                .ok_or(ArchiveError::Truncated)
+        };
+
+        let bounds = Bbox::new(degrees(102), degrees(106), degrees(110), degrees(114))
+            .map_err(|_| ArchiveError::Truncated)?;
+        Ok(Self {
+            bytes,
+            root: read_directory(section(8)?)?,
+            coverage: Coverage {
+                min_zoom: header[100],
+                max_zoom: header[101],
+                bounds,
+            },
+            metadata: section(24)?,
+            leaves_offset: word(40),
+            data_offset: word(56),
+        })
+    }
+
+    pub fn coverage(&self) -> Coverage {
+        self.coverage
+    }
+
+    /// The archive's metadata, as the JSON it was written as.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.metadata
+    }
+
+    /// The tile at `z/x/y`, or `None` where the archive holds nothing there.
+    pub fn tile(&self, z: u8, x: u32, y: u32) -> Result<Option<&'a [u8]>, ArchiveError> {
+        let id = tile_id(z, x, y);
+        let mut directory = self.root.clone();
+        loop {
+            let found = match directory.partition_point(|entry| entry.tile_id <= id) {
+                0 => return Ok(None),
+                after => directory[after - 1],
+            };
+            match found.run_length {
+                0 => {
+                    let start = (self.leaves_offset + found.offset) as usize;
+                    let leaf = self
+                        .bytes
+                        .get(start..start + found.length as usize)
+                        .ok_or(ArchiveError::Truncated)?;
+                    directory = read_directory(leaf)?;
+                }
+                run if id < found.tile_id + u64::from(run) => {
+                    let start = (self.data_offset + found.offset) as usize;
+                    return self
+                        .bytes
+                        .get(start..start + found.length as usize)
+                        .map(Some)
+                        .ok_or(ArchiveError::Truncated);
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage() -> Coverage {
+        Coverage {
+            min_zoom: 0,
+            max_zoom: 14,
+            bounds: Bbox::new(5.87, 47.27, 15.04, 55.06).unwrap(),
+        }
+    }
+
+    /// The archive of `tiles`, handed to a writer in id order.
+    fn write(tiles: &[(u64, Vec<u8>)], metadata: &str) -> Vec<u8> {
+        let mut ordered: Vec<_> = tiles.iter().collect();
+        ordered.sort_by_key(|(id, _)| *id);
+        let mut writer = Writer::new(io::Cursor::new(Vec::new())).unwrap();
+        for (id, tile) in ordered {
+            writer.add(*id, tile).unwrap();
+        }
+        writer.finish(coverage(), metadata).unwrap().into_inner()
+    }
+
+    /// The ids the specification gives for the first two zoom levels, which fix both the
+    /// curve and which way round it runs.
+    #[test]
+    fn tile_ids_follow_the_specifications_hilbert_curve() {
+        assert_eq!(tile_id(0, 0, 0), 0);
+        assert_eq!(tile_id(1, 0, 0), 1);
+        assert_eq!(tile_id(1, 0, 1), 2);
+        assert_eq!(tile_id(1, 1, 1), 3);
+        assert_eq!(tile_id(1, 1, 0), 4);
+        assert_eq!(tile_id(2, 0, 0), 5);
+        assert_eq!(tile_id(12, 3423, 1763), 19_078_479);
+    }
+
+    #[test]
+    fn a_tile_is_read_back_from_where_it_was_written() {
+        let tiles = vec![
+            (tile_id(14, 8800, 5400), b"berlin".to_vec()),
+            (tile_id(0, 0, 0), b"world".to_vec()),
+        ];
+        let archive = write(&tiles, r#"{"name":"crossings"}"#);
+
+        let archive = Archive::open(&archive).unwrap();
+        assert_eq!(archive.tile(0, 0, 0).unwrap(), Some(&b"world"[..]));
+        assert_eq!(archive.tile(14, 8800, 5400).unwrap(), Some(&b"berlin"[..]));
+        assert_eq!(archive.tile(14, 8800, 5401).unwrap(), None);
+        assert_eq!(archive.metadata(), br#"{"name":"crossings"}"#);
+        assert_eq!(archive.coverage(), coverage());
+    }
+
+    /// Identical tiles are stored once, and a run of them under consecutive ids is one entry.
+    #[test]
+    fn repeated_tiles_share_their_bytes() {
+        let mut tiles: Vec<_> = (0..1000).map(|id| (id, b"same".to_vec())).collect();
+        tiles.push((2000, b"other".to_vec()));
+        tiles.push((3000, b"same".to_vec()));
+        let archive = write(&tiles, "{}");
+
+        let data_length = u64::from_le_bytes(archive[64..72].try_into().unwrap());
+        assert_eq!(data_length, 9);
+        let entries = u64::from_le_bytes(archive[80..88].try_into().unwrap());
+        assert_eq!(entries, 3);
+        let archive = Archive::open(&archive).unwrap();
+        assert_eq!(archive.tile(2, 3, 3).unwrap(), Some(&b"same"[..]));
+        let (z, x, y) = zxy(3000);
+        assert_eq!(archive.tile(z, x, y).unwrap(), Some(&b"same"[..]));
+    }
+
+    /// An archive is written in id order; a tile handed over out of it is refused rather
+    /// than left where no directory search would find it.
+    #[test]
+    fn tiles_out_of_order_are_refused() {
+        let mut writer = Writer::new(io::Cursor::new(Vec::new())).unwrap();
+        writer.add(5, b"later").unwrap();
+
+        assert!(matches!(
+            writer.add(5, b"again"),
+            Err(WriteError::OutOfOrder { id: 5, after: 5 })
+        ));
+        assert!(matches!(
+            writer.add(2, b"earlier"),
+            Err(WriteError::OutOfOrder { id: 2, after: 5 })
+        ));
+    }
+
+    /// More entries than the first fetch can hold go in leaf directories, and are found
+    /// through them.
+    #[test]
+    fn a_large_archive_is_read_through_its_leaf_directories() {
+        let tiles: Vec<_> = (0..20_000u64)
+            .map(|n| (n * 2, n.to_le_bytes().to_vec()))
+            .collect();
+        let archive = write(&tiles, "{}");
+        let root_length = u64::from_le_bytes(archive[16..24].try_into().unwrap());
+        assert!(HEADER_LEN + root_length as usize <= FIRST_FETCH);
+        assert!(u64::from_le_bytes(archive[48..56].try_into().unwrap()) > 0);
+
+        let archive = Archive::open(&archive).unwrap();
+        for n in [0u64, 1, 9_999, 19_999] {
+            let (z, x, y) = zxy(n * 2);
+            assert_eq!(
+                archive.tile(z, x, y).unwrap(),
+                Some(&n.to_le_bytes()[..]),
+                "tile {n}"
+            );
+        }
+    }
+
+    /// The inverse of [`tile_id`], by search, for the handful of ids a test asks about.
+    fn zxy(id: u64) -> (u8, u32, u32) {
+        let z = (0..32u8)
+            .take_while(|z| tile_id(*z, 0, 0) <= id)
+            .last()
+            .unwrap();
+        let side = 1u32 << z;
+        (0..side)
+            .flat_map(|x| (0..side).map(move |y| (z, x, y)))
+            .find(|(z, x, y)| tile_id(*z, *x, *y) == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn another_file_is_refused() {
+        assert_eq!(
+            Archive::open(&[0; HEADER_LEN]).err(),
+            Some(ArchiveError::NotAnArchive)
+        );
+        assert_eq!(
+            Archive::open(b"PMTiles").err(),
+            Some(ArchiveError::NoHeader(7))
+        );
+    }
+}
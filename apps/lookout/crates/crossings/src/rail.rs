@@ -0,0 +1,98 @@
+//! Read rail segments out of the bronze Overture extracts, to draw the crossings against.
+//!
+//! Only ever context: nothing is derived from these lines, and a map of crossings is
+//! complete without them. They are read from the extracts the crossings themselves were
+//! derived from — every `extract_id` the crossings carry — so the track on the map is the
+//! track the crossings were found on, not whatever a newer release says it is.
+
+use std::collections::BTreeSet;
+
+use geo_types::{Geometry, LineString};
+use medallion::{GEOMETRY, Query, Root};
+use serde::Deserialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RailError {
+    #[error("reading the store: {0}")]
+    Query(#[from] medallion::QueryError),
+    #[error(transparent)]
+    Path(#[from] medallion::PathError),
+    #[error(transparent)]
+    Geo(#[from] medallion::GeoError),
+    #[error("extract {extract_id} gave {found} rail geometries for {expected} segments")]
+    Misaligned {
+        extract_id: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// One line of one rail segment. A segment whose geometry is several lines is several of
+/// these, each with the segment's attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rail {
+    pub rail_id: String,
+    pub class: Option<String>,
+    /// Longitude in `x`, latitude in `y`, in WGS84 degrees.
+    pub line: LineString<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoredRail {
+    id: String,
+    class: Option<String>,
+}
+
+/// Every rail segment in each of `extracts`. A segment in two of them is read twice, once
+/// for each, as the two runs that read it saw it.
+pub async fn read(root: &Root, extracts: &BTreeSet<String>) -> Result<Vec<Rail>, RailError> {
+    let mut rails = Vec::new();
+    for extract_id in extracts {
+        let query = Query::new(root.clone());
+        let segments = root
+            .dataset(model::OVERTURE_EXTRACT)
+            .for_id(extract_id)?
+            .partition("theme", "transportation")?
+            .partition("type", "segment")?;
+        query.register_at(&segments, "segment").await?;
+
+        let rail = "subtype = 'rail'";
+        let stored: Vec<StoredRail> = query
+            .rows(&format!(
+                "SELECT id, class FROM segment WHERE {rail} ORDER BY id"
+            ))
+            .await?;
+        let mut lines = Vec::with_capacity(stored.len());
+        for batch in &query
+            .sql(&format!(
+                "SELECT ST_AsBinary({GEOMETRY}) AS {GEOMETRY} FROM segment
+                 WHERE {rail} ORDER BY id"
+            ))
+            .await?
+        {
+            lines.extend(medallion::geometries(batch, GEOMETRY)?);
+        }
+        if lines.len() != stored.len() {
+            return Err(RailError::Misaligned {
+                extract_id: extract_id.clone(),
+                expected: stored.len(),
+                found: lines.len(),
+            });
+        }
+
+        for (segment, geometry) in stored.into_iter().zip(lines) {
+            let parts = match geometry {
+                Geometry::LineString(line) => vec![line],
+                Geometry::MultiLineString(lines) => lines.0,
+                // A rail segment is a line; anything else is not one to draw.
+                _ => Vec::new(),
+            };
+            rails.extend(parts.into_iter().map(|line| Rail {
+                rail_id: segment.id.clone(),
+                class: segment.class.clone(),
+                line,
+            }));
+        }
+    }
+    Ok(rails)
+}
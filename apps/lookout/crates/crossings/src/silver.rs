@@ -14,7 +14,7 @@
 
 use geo_types::{Coord, coord};
 use medallion::{Query, Root};
-use model::CrossingId;
+use model::{CrossingId, OverlapKind};
 use serde::Deserialize;
 
 use crate::pointset::PackedId;
@@ -44,6 +44,19 @@ pub struct Crossing {
     pub extract_id: String,
 }
 
+/// A crossing with what a map shows of it: the kind of water, and how the track meets it.
+///
+/// Read apart from [`Crossing`] because the device has no use for any of it, and a buffer
+/// should not depend on columns it does not carry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Described {
+    pub crossing: Crossing,
+    pub water_class: Option<String>,
+    pub overlap_kind: OverlapKind,
+    /// The representative part's overlap in metres; zero for a point overlap.
+    pub overlap_m: f64,
+}
+
 /// The columns as the query returns them, with the position as plain numbers — packing needs
 /// two coordinates, not a geometry to decode.
 #[derive(Debug, Deserialize)]
@@ -55,24 +68,28 @@ struct StoredCrossing {
     lat: f64,
 }
 
+/// The same, with the attributes [`Described`] adds.
+#[derive(Debug, Deserialize)]
+struct StoredDescribed {
+    crossing_id: CrossingId,
+    crossing_short_id: u32,
+    extract_id: String,
+    lon: f64,
+    lat: f64,
+    water_class: Option<String>,
+    overlap_kind: OverlapKind,
+    overlap_m: f64,
+}
+
+/// The columns every read takes, the position among them as plain numbers.
+const COLUMNS: &str = "crossing_id, crossing_short_id, extract_id,
+                       ST_X(geometry) AS lon, ST_Y(geometry) AS lat";
+
 /// Every crossing the store holds, in every country.
 pub async fn read(root: &Root) -> Result<Vec<Crossing>, ReadError> {
-    let query = Query::new(root.clone());
-    if !query
-        .register_if_present(model::WATER_CROSSING, "water_crossing")
+    let stored: Vec<StoredCrossing> = registered(root)
         .await?
-    {
-        return Err(ReadError::Missing {
-            dataset: model::WATER_CROSSING.name,
-        });
-    }
-
-    let stored: Vec<StoredCrossing> = query
-        .rows(
-            "SELECT crossing_id, crossing_short_id, extract_id,
-                    ST_X(geometry) AS lon, ST_Y(geometry) AS lat
-             FROM water_crossing",
-        )
+        .rows(&format!("SELECT {COLUMNS} FROM water_crossing"))
         .await?;
 
     Ok(stored
@@ -85,3 +102,42 @@ pub async fn read(root: &Root) -> Result<Vec<Crossing>, ReadError> {
         })
         .collect())
 }
+
+/// Every crossing the store holds, in every country, with what a map shows of each.
+pub async fn read_described(root: &Root) -> Result<Vec<Described>, ReadError> {
+    let stored: Vec<StoredDescribed> = registered(root)
+        .await?
+        .rows(&format!(
+            "SELECT {COLUMNS}, water_class, overlap_kind, overlap_m FROM water_crossing"
+        ))
+        .await?;
+
+    Ok(stored
+        .into_iter()
+        .map(|crossing| Described {
+            crossing: Crossing {
+                crossing_id: crossing.crossing_id,
+                short_id: PackedId::from_bits(crossing.crossing_short_id),
+                position: coord! { x: crossing.lon, y: crossing.lat },
+                extract_id: crossing.extract_id,
+            },
+            water_class: crossing.water_class,
+            overlap_kind: crossing.overlap_kind,
+            overlap_m: crossing.overlap_m,
+        })
+        .collect())
+}
+
+/// A query with the crossings registered as `water_crossing`, or [`ReadError::Missing`].
+async fn registered(root: &Root) -> Result<Query, ReadError> {
+    let query = Query::new(root.clone());
+    match query
+        .register_if_present(model::WATER_CROSSING, "water_crossing")
+        .await?
+    {
+        true => Ok(query),
+        false => Err(ReadError::Missing {
+            dataset: model::WATER_CROSSING.name,
+        }),
+    }
+}
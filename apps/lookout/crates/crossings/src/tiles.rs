@@ -0,0 +1,478 @@
+//! Cut the crossings, and optionally the rail they lie on, into a pyramid of vector tiles.
+//!
+//! Tiles are the web-mercator ones every slippy map uses — `z/x/y`, `y` counted down from
+//! the north — so an archive of them opens in any renderer that reads PMTiles, and the
+//! crossings can be looked at on a map without a tile server or the store behind it.
+//!
+//! **Two layers.** [`CROSSINGS`] holds a point per crossing, at every zoom, with the
+//! attributes a reader of the map asks about as properties and `crossing_short_id` as the
+//! feature id as well, so a feature picked on the map names the crossing a device reports.
+//! [`RAIL`] holds the track, and only from [`RAIL_MIN_ZOOM`] down: a country's rail at the
+//! zoom a country fits on a screen is a tile of megabytes showing a grey smear, whereas a
+//! crossing is one point at any zoom.
+//!
+//! A line is clipped to each tile it reaches, grown by a buffer so that a renderer's stroke
+//! carries on past the edge rather than ending in a cap at it, and a line that leaves a tile
+//! and comes back into it is drawn there as a feature per piece. Points that round to the
+//! same tile coordinate are dropped, which is all the simplification a line gets.
+//!
+//! **Tiles are streamed.** A zoom is worked out as which crossings and lines each tile
+//! draws, by index, and each tile is then encoded and handed to the archive in turn, so what
+//! is held at once is a zoom's worth of indices and one tile's bytes, not the pyramid.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Seek, Write};
+
+use geo_types::Coord;
+use model::OverlapKind;
+use serde_json::json;
+
+use crate::mvt::{self, EXTENT, Feature, Layer, Shape, Value};
+use crate::pmtiles::{self, Coverage};
+use crate::rail::Rail;
+use crate::silver::Described;
+use crate::{Bbox, BboxError};
+
+/// The layer the crossings are drawn from.
+pub const CROSSINGS: &str = "crossings";
+/// The layer the track is drawn from, when there is one.
+pub const RAIL: &str = "rail";
+/// The shallowest zoom the track is drawn at: about a city across a screen.
+pub const RAIL_MIN_ZOOM: u8 = 10;
+/// The deepest zoom an archive is cut to. At 16 a tile coordinate is under a metre across
+/// in Germany, finer than the GPS a crossing is matched against resolves.
+pub const MAX_ZOOM: u8 = 16;
+/// How far past its edge, in tile coordinates, a tile takes in a line, so a line just
+/// outside is drawn up to the edge rather than stopping short of it.
+const BUFFER: f64 = 64.0;
+/// The furthest north or south web mercator reaches.
+const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TileError {
+    #[error("zoom {min} to {max} is not a range: the first is deeper than the second")]
+    Zooms { min: u8, max: u8 },
+    #[error("zoom {0} is deeper than the {MAX_ZOOM} an archive is cut to")]
+    TooDeep(u8),
+    #[error("there are no crossings to tile")]
+    Empty,
+    #[error("the crossings lie outside any window: {0}")]
+    Bounds(#[from] BboxError),
+    #[error(transparent)]
+    Write(#[from] pmtiles::WriteError),
+}
+
+/// The zoom levels an archive holds, shallowest and deepest both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zooms {
+    min: u8,
+    max: u8,
+}
+
+impl Zooms {
+    pub fn new(min: u8, max: u8) -> Result<Self, TileError> {
+        if max > MAX_ZOOM {
+            return Err(TileError::TooDeep(max));
+        }
+        if min > max {
+            return Err(TileError::Zooms { min, max });
+        }
+        Ok(Self { min, max })
+    }
+
+    pub fn min(&self) -> u8 {
+        self.min
+    }
+
+    pub fn max(&self) -> u8 {
+        self.max
+    }
+}
+
+/// Write the PMTiles archive of `crossings`, and of `rails` as context, over `zooms` to
+/// `out`, and hand `out` back once it is finished.
+///
+/// Features are laid out in `crossing_short_id` order, so the same crossings cut to the same
+/// bytes however the dataset that held them was ordered.
+pub fn pack<W: Write + Seek>(
+    crossings: &[Described],
+    rails: &[Rail],
+    zooms: Zooms,
+    out: W,
+) -> Result<W, TileError> {
+    let mut ordered: Vec<&Described> = crossings.iter().collect();
+    ordered.sort_by_key(|described| described.crossing.short_id);
+    let bounds = bounds(&ordered)?;
+
+    let mut archive = pmtiles::Writer::new(out)?;
+    // Every tile at a zoom has a lower id than any at the next, so walking the zooms in
+    // order and each zoom's tiles by id hands the archive its tiles in the order it needs.
+    for z in zooms.min..=zooms.max {
+        let mut drawn: BTreeMap<u64, Drawn> = BTreeMap::new();
+
+        let positions: Vec<(f64, f64)> = ordered
+            .iter()
+            .map(|described| world(described.crossing.position, z))
+            .collect();
+        for (index, (x, y)) in positions.iter().enumerate() {
+            Drawn::at(&mut drawn, z, (tile_of(*x, z), tile_of(*y, z)))
+                .crossings
+                .push(index);
+        }
+
+        let lines: Vec<Vec<(f64, f64)>> = match z >= RAIL_MIN_ZOOM {
+            true => rails
+                .iter()
+                .map(|rail| rail.line.coords().map(|at| world(*at, z)).collect())
+                .collect(),
+            false => Vec::new(),
+        };
+        for (index, line) in lines.iter().enumerate() {
+            for tile in reached(line, z) {
+                Drawn::at(&mut drawn, z, tile).rails.push(index);
+            }
+        }
+
+        for (id, drawn) in drawn {
+            let mut layers = [Layer::new(CROSSINGS), Layer::new(RAIL)];
+            for index in drawn.crossings {
+                let described = ordered[index];
+                layers[0].features.push(Feature {
+                    id: Some(u64::from(described.crossing.short_id.get())),
+                    properties: properties(described),
+                    shape: Shape::Point(local(positions[index], drawn.tile)),
+                });
+            }
+            for index in drawn.rails {
+                for piece in clip(&lines[index], drawn.tile) {
+                    layers[1].features.push(Feature {
+                        id: None,
+                        properties: rail_properties(&rails[index]),
+                        shape: Shape::Line(piece),
+                    });
+                }
+            }
+            archive.add(id, &mvt::encode(&layers))?;
+        }
+    }
+
+    let coverage = Coverage {
+        min_zoom: zooms.min,
+        max_zoom: zooms.max,
+        bounds,
+    };
+    Ok(archive.finish(coverage, &metadata(&ordered, !rails.is_empty(), zooms))?)
+}
+
+/// What one tile draws, as indices into the crossings and the lines of its zoom.
+struct Drawn {
+    tile: (u32, u32),
+    crossings: Vec<usize>,
+    rails: Vec<usize>,
+}
+
+impl Drawn {
+    /// What `tile` at zoom `z` draws, nothing until something is found in it.
+    fn at(drawn: &mut BTreeMap<u64, Drawn>, z: u8, tile: (u32, u32)) -> &mut Drawn {
+        drawn
+            .entry(pmtiles::tile_id(z, tile.0, tile.1))
+            .or_insert_with(|| Drawn {
+                tile,
+                crossings: Vec::new(),
+                rails: Vec::new(),
+            })
+    }
+}
+
+/// What a map shows of one crossing, under the names the dataset gives them.
+fn properties(described: &Described) -> Vec<(String, Value)> {
+    let mut properties = vec![(
+        "crossing_short_id".into(),
+        Value::Uint(u64::from(described.crossing.short_id.get())),
+    )];
+    // A crossing whose water has no class has no such property, rather than an empty one.
+    if let Some(class) = &described.water_class {
+        properties.push(("water_class".into(), Value::String(class.clone())));
+    }
+    let kind = match described.overlap_kind {
+        OverlapKind::Line => "line",
+        OverlapKind::Point => "point",
+    };
+    properties.push(("overlap_kind".into(), Value::String(kind.into())));
+    properties.push(("overlap_m".into(), Value::Double(described.overlap_m)));
+    properties
+}
+
+/// What a map shows of one line of track.
+fn rail_properties(rail: &Rail) -> Vec<(String, Value)> {
+    let mut properties = vec![("rail_id".into(), Value::String(rail.rail_id.clone()))];
+    if let Some(class) = &rail.class {
+        properties.push(("class".into(), Value::String(class.clone())));
+    }
+    properties
+}
+
+/// The window the crossings fill, which a renderer opens the archive on.
+fn bounds(crossings: &[&Described]) -> Result<Bbox, TileError> {
+    let mut positions = crossings
+        .iter()
+        .map(|described| described.crossing.position);
+    let first = positions.next().ok_or(TileError::Empty)?;
+    let (min, max) = positions.fold((first, first), |(min, max), at| {
+        (
+            Coord {
+                x: min.x.min(at.x),
+                y: min.y.min(at.y),
+            },
+            Coord {
+                x: max.x.max(at.x),
+                y: max.y.max(at.y),
+            },
+        )
+    });
+    Ok(Bbox::new(min.x, min.y, max.x, max.y)?)
+}
+
+/// The archive's metadata: what a renderer needs to style its layers before reading a tile,
+/// and which extractions the crossings came from.
+fn metadata(crossings: &[&Described], rail: bool, zooms: Zooms) -> String {
+    let extracts: BTreeSet<&str> = crossings
+        .iter()
+        .map(|described| described.crossing.extract_id.as_str())
+        .collect();
+    let mut layers = vec![json!({
+        "id": CROSSINGS,
+        "fields": {
+            "crossing_short_id": "Number",
+            "water_class": "String",
+            "overlap_kind": "String",
+            "overlap_m": "Number",
+        },
+        "minzoom": zooms.min,
+        "maxzoom": zooms.max,
+    })];
+    if rail && zooms.max >= RAIL_MIN_ZOOM {
+        layers.push(json!({
+            "id": RAIL,
+            "fields": { "rail_id": "String", "class": "String" },
+            "minzoom": zooms.min.max(RAIL_MIN_ZOOM),
+            "maxzoom": zooms.max,
+        }));
+    }
+    json!({
+        "name": CROSSINGS,
+        "format": "pbf",
+        "vector_layers": layers,
+        "extracts": extracts,
+    })
+    .to_string()
+}
+
+/// Where `position` falls at zoom `z`, in tiles from the north-west corner of the world.
+fn world(position: Coord<f64>, z: u8) -> (f64, f64) {
+    let side = f64::from(1u32 << z);
+    let latitude = position.y.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (position.x + 180.0) / 360.0 * side;
+    let y = (1.0 - latitude.tan().asinh() / std::f64::consts::PI) / 2.0 * side;
+    (x, y)
+}
+
+/// The tile `at` falls in along one axis, with the far edge of the world in the last tile.
+fn tile_of(at: f64, z: u8) -> u32 {
+    (at.floor().max(0.0) as u32).min((1u32 << z) - 1)
+}
+
+/// `at`, in tiles, as a coordinate within `tile`.
+fn local((x, y): (f64, f64), (tx, ty): (u32, u32)) -> (i32, i32) {
+    let extent = f64::from(EXTENT);
+    (
+        ((x - f64::from(tx)) * extent).round() as i32,
+        ((y - f64::from(ty)) * extent).round() as i32,
+    )
+}
+
+/// Every tile at zoom `z` that the bounding box of a segment of `line`, in tiles, reaches
+/// into once grown by the buffer.
+fn reached(line: &[(f64, f64)], z: u8) -> Vec<(u32, u32)> {
+    let buffer = BUFFER / f64::from(EXTENT);
+    let mut tiles = BTreeSet::new();
+    for segment in line.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let columns = tile_of(a.0.min(b.0) - buffer, z)..=tile_of(a.0.max(b.0) + buffer, z);
+        let rows = tile_of(a.1.min(b.1) - buffer, z)..=tile_of(a.1.max(b.1) + buffer, z);
+        for x in columns {
+            tiles.extend(rows.clone().map(|y| (x, y)));
+        }
+    }
+    tiles.into_iter().collect()
+}
+
+/// The pieces of `line`, in tiles, that lie within `tile` grown by the buffer, each in the
+/// tile's own coordinates. A piece rounded to a single point is no line, and is left out.
+fn clip(line: &[(f64, f64)], tile: (u32, u32)) -> Vec<Vec<(i32, i32)>> {
+    let buffer = BUFFER / f64::from(EXTENT);
+    let (west, north) = (f64::from(tile.0) - buffer, f64::from(tile.1) - buffer);
+    let (east, south) = (
+        f64::from(tile.0) + 1.0 + buffer,
+        f64::from(tile.1) + 1.0 + buffer,
+    );
+
+    let mut pieces: Vec<Vec<(f64, f64)>> = Vec::new();
+    let mut piece: Vec<(f64, f64)> = Vec::new();
+    for segment in line.windows(2) {
+        match clip_segment(segment[0], segment[1], (west, north, east, south)) {
+            Some((from, to)) => {
+                // A segment carries on the piece before it only if it starts where that
+                // ended; one that starts on the edge has come back in from outside.
+                if piece.last() != Some(&from) {
+                    if !piece.is_empty() {
+                        pieces.push(std::mem::take(&mut piece));
+                    }
+                    piece.push(from);
+                }
+                piece.push(to);
+            }
+            None if !piece.is_empty() => pieces.push(std::mem::take(&mut piece)),
+            None => {}
+        }
+    }
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+
+    pieces
+        .into_iter()
+        .filter_map(|piece| {
+            let mut points: Vec<(i32, i32)> = piece.into_iter().map(|at| local(at, tile)).collect();
+            points.dedup();
+            (points.len() >= 2).then_some(points)
+        })
+        .collect()
+}
+
+/// The part of the segment from `a` to `b` inside the box `(west, north, east, south)`,
+/// found by Liang–Barsky: each edge narrows the span of the segment that can lie inside.
+/// An end that is inside is kept exactly, so consecutive segments still meet.
+fn clip_segment(
+    a: (f64, f64),
+    b: (f64, f64),
+    (west, north, east, south): (f64, f64, f64, f64),
+) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut enter, mut leave) = (0.0_f64, 1.0_f64);
+    for (towards, room) in [
+        (-dx, a.0 - west),
+        (dx, east - a.0),
+        (-dy, a.1 - north),
+        (dy, south - a.1),
+    ] {
+        if towards == 0.0 {
+            // Parallel to this edge: wholly inside it or wholly outside.
+            if room < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let at = room / towards;
+        match towards < 0.0 {
+            true => enter = enter.max(at),
+            false => leave = leave.min(at),
+        }
+        if enter > leave {
+            return None;
+        }
+    }
+    let along = |t: f64| (a.0 + t * dx, a.1 + t * dy);
+    let from = if enter == 0.0 { a } else { along(enter) };
+    let to = if leave == 1.0 { b } else { along(leave) };
+    Some((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::coord;
+
+    use super::*;
+
+    /// The tile the crossing at Ruhland falls in at zoom 14, as any slippy map numbers it.
+    #[test]
+    fn a_position_falls_in_the_tile_every_map_puts_it_in() {
+        let (x, y) = world(coord! { x: 13.548209, y: 51.617567 }, 14);
+
+        assert_eq!((tile_of(x, 14), tile_of(y, 14)), (8808, 5440));
+    }
+
+    #[test]
+    fn the_corners_of_the_world_are_the_corner_tiles() {
+        let (x, y) = world(coord! { x: -180.0, y: 90.0 }, 3);
+        assert_eq!((tile_of(x, 3), tile_of(y, 3)), (0, 0));
+        let (x, y) = world(coord! { x: 180.0, y: -90.0 }, 3);
+        assert_eq!((tile_of(x, 3), tile_of(y, 3)), (7, 7));
+    }
+
+    /// A line is drawn in the tiles it passes through and in those it only just misses, so
+    /// it reaches the edge of a tile rather than stopping inside the buffer.
+    #[test]
+    fn a_line_reaches_every_tile_its_box_touches() {
+        let line = [(1.5, 1.5), (2.5, 1.99)];
+        assert_eq!(reached(&line, 4), vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+        assert_eq!(reached(&[(1.5, 1.5), (1.6, 1.6)], 4), vec![(1, 1)]);
+    }
+
+    /// A line across many tiles is cut at the buffer's edge in each, rather than drawn
+    /// whole in every one of them.
+    #[test]
+    fn a_line_is_cut_at_the_edge_of_the_buffer() {
+        let buffer = BUFFER as i32;
+        let extent = EXTENT as i32;
+        let line = [(0.5, 2.5), (9.5, 2.5)];
+
+        assert_eq!(
+            clip(&line, (2, 2)),
+            vec![vec![(-buffer, extent / 2), (extent + buffer, extent / 2)]]
+        );
+        assert_eq!(
+            clip(&line, (0, 2)),
+            vec![vec![
+                (extent / 2, extent / 2),
+                (extent + buffer, extent / 2)
+            ]]
+        );
+        assert_eq!(clip(&line, (2, 3)), Vec::<Vec<(i32, i32)>>::new());
+    }
+
+    /// A line that leaves a tile and comes back is drawn there as two pieces, not joined up
+    /// across the stretch it spends outside.
+    #[test]
+    fn a_line_that_comes_back_into_a_tile_is_drawn_in_pieces() {
+        let line = [(0.5, 0.5), (3.5, 0.5), (3.5, 0.75), (0.5, 0.75)];
+
+        let pieces = clip(&line, (0, 0));
+
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().all(|piece| piece.len() == 2));
+    }
+
+    #[test]
+    fn a_zoom_range_is_validated() {
+        assert!(Zooms::new(0, 14).is_ok());
+        assert!(matches!(
+            Zooms::new(9, 8),
+            Err(TileError::Zooms { min: 9, max: 8 })
+        ));
+        assert!(matches!(Zooms::new(0, 17), Err(TileError::TooDeep(17))));
+    }
+
+    #[test]
+    fn nothing_to_tile_is_refused() {
+        assert!(matches!(
+            pack(
+                &[],
+                &[],
+                Zooms::new(0, 4).unwrap(),
+                std::io::Cursor::new(Vec::new())
+            ),
+            Err(TileError::Empty)
+        ));
+    }
+}
@@ -5,14 +5,20 @@
 //! same code that writes the real ones, and taking a position out of the geometry column.
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 
-use crossings::{PackedId, Point, pointset, silver};
+use chrono::{TimeZone, Utc};
+use crossings::manifest::{self, ManifestError};
+use crossings::{Output, PackedId, Point, pointset, silver};
 use geo_types::Point as GeoPoint;
 use medallion::{
     COUNTRY, Country, GEOMETRY, PROJECTED_GEOMETRY, Projector, Root, geo_batch,
     projected_wkb_field, wkb_field,
 };
 use model::{CrossingId, OverlapKind, WaterCrossingRow};
+use object_store::memory::InMemory;
+use url::Url;
 
 /// The four-byte name the store gives the nth crossing of a test store. Distinct per crossing,
 /// which is all the dataset promises and all the packer relies on; how the real derivation
@@ -27,13 +33,16 @@ const LAT: f64 = 51.617567;
 
 const EXTRACT: &str = "20260727T193628Z";
 
+/// A key for tests only, which anyone reading this can sign with.
+const SIGNING_KEY: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
 /// Write `positions` as the crossings of one country, the way the crossings pipeline writes
 /// them: one file per country, both geometries, as GeoParquet.
 ///
 /// `country` is the partition value rather than a [`Country`], so a test can write a store
 /// holding more countries than the code knows how to project into.
 async fn store_with_crossings(root: &Root, country: &str, positions: &[(f64, f64)]) {
-    let projector = Projector::for_country(Country::Germany).expect("projector");
+    let projector = Projector::for_country(Country::GERMANY).expect("projector");
     let rows: Vec<WaterCrossingRow> = positions
         .iter()
         .enumerate()
@@ -71,7 +80,7 @@ async fn store_with_crossings(root: &Root, country: &str, positions: &[(f64, f64
         &[
             (wkb_field(GEOMETRY).expect("field"), points.as_slice()),
             (
-                projected_wkb_field(PROJECTED_GEOMETRY, Country::Germany).expect("field"),
+                projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).expect("field"),
                 projected.as_slice(),
             ),
         ],
@@ -181,6 +190,63 @@ async fn every_packed_id_maps_back_to_exactly_one_crossing_the_store_named() {
     }
 }
 
+/// The case the manifest exists for: a buffer cut at a point boundary, with its count
+/// rewritten to match, is a buffer the format accepts — and one the manifest refuses.
+#[tokio::test]
+async fn a_buffer_the_format_accepts_is_still_refused_if_it_is_not_the_one_signed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with_crossings(&root, "DE", &[(LON, LAT), (LON + 0.01, LAT + 0.01)]).await;
+    let key = manifest::signing_key(SIGNING_KEY).unwrap();
+
+    let crossings = silver::read(&root).await.unwrap();
+    let buffer = packed(&crossings);
+    let signed = manifest::write(&buffer, Some(&key));
+    assert_eq!(
+        manifest::verify(&signed, &buffer, &key.verifying_key()),
+        Ok(())
+    );
+
+    let one = packed(&crossings[..1]);
+    assert_eq!(pointset::unpack(&one).unwrap().len(), 1);
+    assert!(matches!(
+        manifest::verify(&signed, &one, &key.verifying_key()),
+        Err(ManifestError::WrongLength { .. })
+    ));
+}
+
+/// A store in a bucket gets its gold artefacts in the bucket: packed, written and recorded
+/// through the store, with nothing landing on the local disk at the path a version would
+/// have there.
+#[tokio::test]
+async fn a_buffer_packed_from_a_store_in_a_bucket_is_written_and_recorded_there() {
+    let root = Root::in_object_store(
+        Arc::new(InMemory::new()),
+        &Url::parse("memory:///medallion").unwrap(),
+    );
+    store_with_crossings(&root, "DE", &[(LON, LAT), (LON + 0.01, LAT + 0.01)]).await;
+    let run = Utc.with_ymd_and_hms(2026, 8, 1, 19, 48, 57).unwrap();
+    let output = Output::new(&root, "crossings", "crossings.pointset", run, None).unwrap();
+
+    let buffer = packed(&silver::read(&root).await.unwrap());
+    let signed = manifest::write(&buffer, None);
+    output
+        .write(Path::to_path_buf, buffer.clone())
+        .await
+        .unwrap();
+    output.write(manifest::sidecar, signed).await.unwrap();
+    let version = output.record().await.unwrap().expect("a gold version");
+
+    assert!(!output.path().exists());
+    let names: Vec<_> = version
+        .files
+        .iter()
+        .map(|file| file.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["crossings.pointset", "crossings.pointset.sig"]);
+    assert_eq!(version.files[0].bytes, buffer.len() as u64);
+}
+
 /// The buffer for these crossings, packed the way the bin packs it.
 fn packed(crossings: &[silver::Crossing]) -> Vec<u8> {
     let points: Vec<Point> = crossings.iter().map(Point::of).collect();
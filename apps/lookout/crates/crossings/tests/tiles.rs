@@ -0,0 +1,272 @@
+//! Cutting what the store holds into tiles, and reading it back out of them.
+//!
+//! The formats are checked against themselves in the unit tests; what is checked here is
+//! the path a map takes — crossings written as the pipeline writes them, read back with their
+//! attributes, cut into an archive, and found again in the tile a renderer would ask for.
+
+use std::io::Cursor;
+
+use crossings::mvt::{self, Shape, Value};
+use crossings::pmtiles::Archive;
+use crossings::tiles::{CROSSINGS, RAIL, RAIL_MIN_ZOOM};
+use crossings::{Described, Rail, Zooms, silver, tiles};
+use geo_types::{LineString, Point as GeoPoint};
+use medallion::{
+    COUNTRY, Country, GEOMETRY, PROJECTED_GEOMETRY, Projector, Root, geo_batch,
+    projected_wkb_field, wkb_field,
+};
+use model::{CrossingId, OverlapKind, WaterCrossingRow};
+
+/// Ruhland, where a line crosses the Schwarze Elster, and the tile it falls in at zoom 14.
+const RUHLAND: (f64, f64) = (13.548209, 51.617567);
+const RUHLAND_TILE: (u32, u32) = (8808, 5440);
+
+const EXTRACT: &str = "20260727T193628Z";
+
+/// A crossing's attributes as a test varies them: what the water is, and how it is met.
+struct Crossing {
+    at: (f64, f64),
+    short_id: u32,
+    water_class: Option<&'static str>,
+    overlap_kind: OverlapKind,
+    overlap_m: f64,
+}
+
+fn row(n: usize, crossing: &Crossing) -> WaterCrossingRow {
+    WaterCrossingRow {
+        crossing_id: CrossingId::new(format!("water:track:rail@{n}")).expect("id"),
+        crossing_short_id: crossing.short_id,
+        water_id: "water".into(),
+        water_subtype: Some("river".into()),
+        water_class: crossing.water_class.map(Into::into),
+        track_id: "track".into(),
+        rail_id: format!("rail-{n}"),
+        rail_class: Some("rail".into()),
+        overlap_kind: crossing.overlap_kind,
+        overlap_m: crossing.overlap_m,
+        total_overlap_m: crossing.overlap_m,
+        merged_parts: 1,
+        frac: 0.5,
+        extract_id: EXTRACT.into(),
+        merge_distance_m: 100.0,
+        min_crossing_m: 5.0,
+    }
+}
+
+/// Write `crossings` as the German crossings, the way the crossings pipeline writes them.
+async fn store_with(root: &Root, crossings: &[Crossing]) {
+    let projector = Projector::for_country(Country::GERMANY).expect("projector");
+    let rows: Vec<WaterCrossingRow> = crossings
+        .iter()
+        .enumerate()
+        .map(|(n, crossing)| row(n, crossing))
+        .collect();
+    let points: Vec<GeoPoint<f64>> = crossings
+        .iter()
+        .map(|crossing| GeoPoint::new(crossing.at.0, crossing.at.1))
+        .collect();
+    let projected: Vec<GeoPoint<f64>> = points
+        .iter()
+        .map(|point| projector.project(point).expect("project"))
+        .collect();
+
+    let batch = geo_batch(
+        &rows,
+        &[
+            (wkb_field(GEOMETRY).expect("field"), points.as_slice()),
+            (
+                projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).expect("field"),
+                projected.as_slice(),
+            ),
+        ],
+    )
+    .expect("build the batch");
+
+    root.dataset(model::WATER_CROSSING)
+        .partition(COUNTRY, "DE")
+        .expect("partition")
+        .replace_with_geo(&[batch])
+        .await
+        .expect("write the crossings");
+}
+
+fn bridge() -> Crossing {
+    Crossing {
+        at: RUHLAND,
+        short_id: 0x292e_417a,
+        water_class: Some("river"),
+        overlap_kind: OverlapKind::Line,
+        overlap_m: 41.5,
+    }
+}
+
+/// The archive of `crossings` and `rails` over `zooms`, as the bytes written.
+fn pack(crossings: &[Described], rails: &[Rail], zooms: Zooms) -> Vec<u8> {
+    tiles::pack(crossings, rails, zooms, Cursor::new(Vec::new()))
+        .expect("pack the tiles")
+        .into_inner()
+}
+
+/// The layers of tile `z/x/y` in `archive`, decoded.
+fn layers(archive: &[u8], z: u8, x: u32, y: u32) -> Vec<mvt::Layer> {
+    let archive = Archive::open(archive).expect("open the archive");
+    let tile = archive
+        .tile(z, x, y)
+        .expect("read the tile")
+        .unwrap_or_else(|| panic!("no tile at {z}/{x}/{y}"));
+    mvt::decode(tile).expect("decode the tile")
+}
+
+#[tokio::test]
+async fn a_crossing_is_found_in_its_tile_with_its_attributes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with(&root, &[bridge()]).await;
+
+    let crossings = silver::read_described(&root).await.unwrap();
+    let archive = pack(&crossings, &[], Zooms::new(0, 14).unwrap());
+
+    let (x, y) = RUHLAND_TILE;
+    let layers = layers(&archive, 14, x, y);
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, CROSSINGS);
+    let feature = &layers[0].features[0];
+    assert_eq!(feature.id, Some(0x292e_417a));
+    assert_eq!(
+        feature.property("crossing_short_id"),
+        Some(&Value::Uint(0x292e_417a))
+    );
+    assert_eq!(
+        feature.property("water_class"),
+        Some(&Value::String("river".into()))
+    );
+    assert_eq!(
+        feature.property("overlap_kind"),
+        Some(&Value::String("line".into()))
+    );
+    assert_eq!(feature.property("overlap_m"), Some(&Value::Double(41.5)));
+    assert!(matches!(
+        feature.shape,
+        Shape::Point((x, y)) if (0..4096).contains(&x) && (0..4096).contains(&y)
+    ));
+}
+
+/// A crossing is drawn at every zoom the archive holds, in the tile that contains it there.
+#[tokio::test]
+async fn a_crossing_is_drawn_at_every_zoom() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with(&root, &[bridge()]).await;
+
+    let crossings = silver::read_described(&root).await.unwrap();
+    let archive = pack(&crossings, &[], Zooms::new(4, 14).unwrap());
+
+    let (x, y) = RUHLAND_TILE;
+    for z in 4..=14 {
+        let shift = 14 - z;
+        let layers = layers(&archive, z, x >> shift, y >> shift);
+        assert_eq!(layers[0].features.len(), 1, "zoom {z}");
+    }
+    let opened = Archive::open(&archive).unwrap();
+    assert_eq!(opened.tile(3, x >> 11, y >> 11).unwrap(), None);
+    assert_eq!(opened.coverage().min_zoom, 4);
+    assert_eq!(opened.coverage().max_zoom, 14);
+}
+
+/// A water with no class has no `water_class` property, rather than an empty one a map
+/// would style as a class of its own.
+#[tokio::test]
+async fn an_unclassed_water_has_no_class_property() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with(
+        &root,
+        &[Crossing {
+            water_class: None,
+            overlap_kind: OverlapKind::Point,
+            overlap_m: 0.0,
+            ..bridge()
+        }],
+    )
+    .await;
+
+    let crossings = silver::read_described(&root).await.unwrap();
+    let archive = pack(&crossings, &[], Zooms::new(14, 14).unwrap());
+
+    let (x, y) = RUHLAND_TILE;
+    let feature = &layers(&archive, 14, x, y)[0].features[0];
+    assert_eq!(feature.property("water_class"), None);
+    assert_eq!(
+        feature.property("overlap_kind"),
+        Some(&Value::String("point".into()))
+    );
+}
+
+/// The archive's metadata describes its layers to a renderer, and names the extraction the
+/// crossings came from.
+#[tokio::test]
+async fn the_metadata_describes_the_layers_and_the_extract() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with(&root, &[bridge()]).await;
+
+    let crossings = silver::read_described(&root).await.unwrap();
+    let archive = pack(&crossings, &[], Zooms::new(0, 12).unwrap());
+
+    let metadata: serde_json::Value =
+        serde_json::from_slice(Archive::open(&archive).unwrap().metadata()).unwrap();
+    assert_eq!(metadata["vector_layers"][0]["id"], CROSSINGS);
+    assert_eq!(
+        metadata["vector_layers"][0]["fields"]["overlap_m"],
+        "Number"
+    );
+    assert_eq!(metadata["vector_layers"].as_array().unwrap().len(), 1);
+    assert_eq!(metadata["extracts"][0], EXTRACT);
+}
+
+/// The track is drawn beneath the crossings from the zoom it is legible at, and not above.
+#[tokio::test]
+async fn rail_is_drawn_as_context_from_its_own_zoom() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with(&root, &[bridge()]).await;
+    let rail = Rail {
+        rail_id: "rail-0".into(),
+        class: Some("standard_gauge".into()),
+        line: LineString::from(vec![
+            (RUHLAND.0 - 0.001, RUHLAND.1 - 0.001),
+            (RUHLAND.0 + 0.001, RUHLAND.1 + 0.001),
+        ]),
+    };
+
+    let crossings = silver::read_described(&root).await.unwrap();
+    let archive = pack(&crossings, &[rail], Zooms::new(0, 14).unwrap());
+
+    let (x, y) = RUHLAND_TILE;
+    let layers_at_14 = layers(&archive, 14, x, y);
+    let track = layers_at_14
+        .iter()
+        .find(|layer| layer.name == RAIL)
+        .expect("a rail layer");
+    assert_eq!(
+        track.features[0].property("class"),
+        Some(&Value::String("standard_gauge".into()))
+    );
+    assert!(matches!(&track.features[0].shape, Shape::Line(points) if points.len() == 2));
+
+    let shift = 14 - (RAIL_MIN_ZOOM - 1);
+    let above = layers(&archive, RAIL_MIN_ZOOM - 1, x >> shift, y >> shift);
+    assert!(above.iter().all(|layer| layer.name != RAIL));
+}
+
+/// Tiling before the crossings exist is a run out of order, not an empty map to publish.
+#[tokio::test]
+async fn tiling_without_the_dataset_says_so() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+
+    assert!(matches!(
+        silver::read_described(&root).await,
+        Err(crossings::ReadError::Missing { .. })
+    ));
+}
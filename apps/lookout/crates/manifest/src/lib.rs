@@ -0,0 +1,427 @@
+//! A manifest vouching for a packed buffer: its length, its SHA-256, and an Ed25519
+//! signature over both.
+//!
+//! The buffer's own header says what format it is and how many points it holds, which catches
+//! the wrong file and a file cut short mid-column. It cannot catch a file cut short at a point
+//! boundary with its count rewritten, a file whose bytes were flipped in transit, or a
+//! perfectly good buffer that the packer never wrote. The manifest is what does:
+//! written beside the buffer by the packer, checked by whoever is about to accept the buffer —
+//! the device before it flashes one, the server before it serves one.
+//!
+//! Verifying uses nothing but `core`: no allocation, no I/O, no clock, so that the device core
+//! can run the same code the server does. The crate is `no_std` without its default `std`
+//! feature, which is all that writing a manifest — the packer's half — needs, and which a
+//! device builds without. The manifest is therefore a fixed binary layout rather than json,
+//! read in place like the buffer it vouches for:
+//!
+//! ```text
+//! offset  bytes  field
+//!      0      4  magic "XSIG"
+//!      4      4  version, u32 (currently 1)
+//!      8      8  length of the artefact, u64
+//!     16     32  SHA-256 of the artefact
+//!     48     32  the signer's Ed25519 public key   } only in a signed manifest
+//!     80     64  Ed25519 signature over bytes 0..48 }
+//! ```
+//!
+//! Little-endian, as the buffer is. The signature covers the header, which names the hash,
+//! which names the bytes — so it is a signature over the artefact without the signer or the
+//! verifier having to hold the artefact and the signature in one message. A manifest with no
+//! signature is still a checksum, and [`Manifest::check`] takes one; [`verify`] refuses it,
+//! since a device that trusts a key has no reason to accept something nobody signed.
+//!
+//! The key in the manifest says who signed, so that a refusal can say "signed by someone
+//! else" rather than "bad signature"; it is never what a signature is checked against. That
+//! is the key the verifier already trusts.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::fmt::{self, Display};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::Signature;
+#[cfg(feature = "std")]
+use ed25519_dalek::Signer;
+use sha2::{Digest, Sha256};
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// Names the format in the first bytes of the file.
+pub const MAGIC: [u8; 4] = *b"XSIG";
+/// Bumped whenever the layout changes in a way an existing reader would misread.
+pub const VERSION: u32 = 1;
+/// Magic, version, length, hash — everything the signature covers.
+pub const HEADER_LEN: usize = 48;
+/// The header, the signer's public key and the signature.
+pub const SIGNED_LEN: usize = HEADER_LEN + 32 + 64;
+/// What a manifest is called beside the artefact it vouches for: `crossings.pointset` has
+/// `crossings.pointset.sig`.
+pub const SUFFIX: &str = ".sig";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ManifestError {
+    #[error("{0} bytes is too short to hold a {HEADER_LEN}-byte manifest header")]
+    NoHeader(usize),
+    #[error("does not start with {:?}", core::str::from_utf8(&MAGIC).unwrap_or_default())]
+    NotAManifest,
+    #[error("version {found}, which this reader does not know (it reads {VERSION})")]
+    UnsupportedVersion { found: u32 },
+    #[error("{0} bytes is neither an unsigned ({HEADER_LEN}) nor a signed ({SIGNED_LEN}) manifest")]
+    Malformed(usize),
+    #[error("the manifest is for {claimed} bytes, but there are {got}")]
+    WrongLength { claimed: u64, got: usize },
+    #[error("the bytes do not hash to what the manifest says they do")]
+    WrongHash,
+    #[error("the manifest is not signed")]
+    Unsigned,
+    #[error("signed by {signer}, which is not the key this trusts")]
+    UntrustedSigner { signer: Hex<32> },
+    #[error("the signature does not match the manifest")]
+    BadSignature,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum KeyError {
+    #[error("a key is 64 hex digits, not {0} characters")]
+    WrongLength(usize),
+    #[error("{0:?} is not a hex digit")]
+    NotHex(char),
+    #[error("not a valid Ed25519 public key")]
+    NotAPoint,
+}
+
+/// Bytes as lowercase hex, for naming a key or a hash without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hex<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Display for Hex<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A 32-byte key written as 64 hex digits, as it is kept in a key file or compiled into a
+/// device. Whitespace around it, such as the newline a file ends with, is ignored.
+pub fn key_bytes(hex: &str) -> Result<[u8; 32], KeyError> {
+    let hex = hex.trim();
+    let len = hex.chars().count();
+    if len != 64 {
+        return Err(KeyError::WrongLength(len));
+    }
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(KeyError::NotHex(c));
+    }
+    let digit = |b: u8| char::from(b).to_digit(16).unwrap_or_default() as u8;
+    let mut key = [0u8; 32];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = (digit(pair[0]) << 4) | digit(pair[1]);
+    }
+    Ok(key)
+}
+
+/// The secret key a packer signs with, from its 64 hex digits.
+pub fn signing_key(hex: &str) -> Result<SigningKey, KeyError> {
+    Ok(SigningKey::from_bytes(&key_bytes(hex)?))
+}
+
+/// The public key a verifier trusts, from its 64 hex digits.
+pub fn public_key(hex: &str) -> Result<VerifyingKey, KeyError> {
+    VerifyingKey::from_bytes(&key_bytes(hex)?).map_err(|_| KeyError::NotAPoint)
+}
+
+/// Where the manifest for the artefact at `artefact` is written.
+#[cfg(feature = "std")]
+pub fn sidecar(artefact: &Path) -> PathBuf {
+    let name = artefact.file_name().unwrap_or_default().to_string_lossy();
+    artefact.with_file_name(format!("{name}{SUFFIX}"))
+}
+
+/// The manifest for `artefact`, signed by `key` if there is one.
+#[cfg(feature = "std")]
+pub fn write(artefact: &[u8], key: Option<&SigningKey>) -> Vec<u8> {
+    let mut manifest = Vec::with_capacity(SIGNED_LEN);
+    manifest.extend_from_slice(&MAGIC);
+    manifest.extend_from_slice(&VERSION.to_le_bytes());
+    manifest.extend_from_slice(&(artefact.len() as u64).to_le_bytes());
+    manifest.extend_from_slice(&Sha256::digest(artefact));
+    if let Some(key) = key {
+        let signature = key.sign(&manifest);
+        manifest.extend_from_slice(key.verifying_key().as_bytes());
+        manifest.extend_from_slice(&signature.to_bytes());
+    }
+    manifest
+}
+
+/// A manifest, borrowed from the bytes it is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Manifest<'a> {
+    header: &'a [u8; HEADER_LEN],
+    signed: Option<(&'a [u8; 32], &'a [u8; 64])>,
+}
+
+impl<'a> Manifest<'a> {
+    /// Borrows a manifest from its bytes, checking that it is one.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, ManifestError> {
+        let header: &[u8; HEADER_LEN] = bytes
+            .get(..HEADER_LEN)
+            .and_then(|header| header.try_into().ok())
+            .ok_or(ManifestError::NoHeader(bytes.len()))?;
+
+        if header[..4] != MAGIC {
+            return Err(ManifestError::NotAManifest);
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version != VERSION {
+            return Err(ManifestError::UnsupportedVersion { found: version });
+        }
+
+        let signed = match bytes.len() {
+            HEADER_LEN => None,
+            SIGNED_LEN => {
+                let signer = bytes[HEADER_LEN..][..32].try_into();
+                let signature = bytes[HEADER_LEN + 32..].try_into();
+                match (signer, signature) {
+                    (Ok(signer), Ok(signature)) => Some((signer, signature)),
+                    _ => return Err(ManifestError::Malformed(bytes.len())),
+                }
+            }
+            len => return Err(ManifestError::Malformed(len)),
+        };
+        Ok(Self { header, signed })
+    }
+
+    /// How many bytes the artefact is.
+    pub fn length(&self) -> u64 {
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&self.header[8..16]);
+        u64::from_le_bytes(length)
+    }
+
+    /// The SHA-256 of the artefact.
+    pub fn sha256(&self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&self.header[16..48]);
+        hash
+    }
+
+    /// The public key of whoever signed, if anyone did.
+    pub fn signer(&self) -> Option<[u8; 32]> {
+        self.signed.map(|(signer, _)| *signer)
+    }
+
+    /// Whether `artefact` is the bytes this manifest describes — the right length, and the
+    /// right hash. Says nothing about who described them.
+    pub fn check(&self, artefact: &[u8]) -> Result<(), ManifestError> {
+        if self.length() != artefact.len() as u64 {
+            return Err(ManifestError::WrongLength {
+                claimed: self.length(),
+                got: artefact.len(),
+            });
+        }
+        match Sha256::digest(artefact).as_slice() == self.sha256() {
+            true => Ok(()),
+            false => Err(ManifestError::WrongHash),
+        }
+    }
+
+    /// Whether `artefact` is the bytes this manifest describes, and `trusted` said so.
+    pub fn verify(&self, artefact: &[u8], trusted: &VerifyingKey) -> Result<(), ManifestError> {
+        let (signer, signature) = self.signed.ok_or(ManifestError::Unsigned)?;
+        if signer != trusted.as_bytes() {
+            return Err(ManifestError::UntrustedSigner {
+                signer: Hex(*signer),
+            });
+        }
+        // Strict, so that a signature has exactly one encoding and a manifest cannot be
+        // altered into another that still verifies.
+        trusted
+            .verify_strict(self.header, &Signature::from_bytes(signature))
+            .map_err(|_| ManifestError::BadSignature)?;
+        self.check(artefact)
+    }
+}
+
+/// Whether `artefact` is what `manifest` describes and `trusted` signed — what to ask before
+/// accepting a buffer.
+pub fn verify(
+    manifest: &[u8],
+    artefact: &[u8],
+    trusted: &VerifyingKey,
+) -> Result<(), ManifestError> {
+    Manifest::new(manifest)?.verify(artefact, trusted)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Test keys only: a seed anyone can read is a key anyone can sign with.
+    const SEED: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const OTHER_SEED: &str = "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb";
+
+    fn key() -> SigningKey {
+        signing_key(SEED).unwrap()
+    }
+
+    fn artefact() -> Vec<u8> {
+        b"XING\x01\x00\x00\x00\x00\x00\x00\x00".to_vec()
+    }
+
+    #[test]
+    fn a_signed_manifest_verifies_its_artefact() {
+        let manifest = write(&artefact(), Some(&key()));
+
+        assert_eq!(manifest.len(), SIGNED_LEN);
+        assert_eq!(
+            verify(&manifest, &artefact(), &key().verifying_key()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn a_manifest_starts_with_the_magic_the_version_and_the_length() {
+        let manifest = write(&artefact(), None);
+
+        assert_eq!(manifest.len(), HEADER_LEN);
+        assert_eq!(&manifest[..4], &MAGIC);
+        assert_eq!(&manifest[4..8], &VERSION.to_le_bytes());
+        assert_eq!(&manifest[8..16], &12u64.to_le_bytes());
+    }
+
+    /// The hash is SHA-256 as anyone else computes it, so a manifest can be checked with
+    /// `sha256sum` as well as with this.
+    #[test]
+    fn the_hash_is_the_sha256_of_the_bytes() {
+        let manifest = write(b"abc", None);
+
+        assert_eq!(
+            Hex(Manifest::new(&manifest).unwrap().sha256()).to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    /// The case the buffer's own header cannot catch: cut at a point boundary, with the count
+    /// rewritten to match.
+    #[test]
+    fn a_truncated_artefact_is_refused() {
+        let manifest = write(&artefact(), Some(&key()));
+        let cut = &artefact()[..8];
+
+        assert_eq!(
+            verify(&manifest, cut, &key().verifying_key()),
+            Err(ManifestError::WrongLength {
+                claimed: 12,
+                got: 8
+            })
+        );
+    }
+
+    #[test]
+    fn an_altered_artefact_is_refused() {
+        let manifest = write(&artefact(), Some(&key()));
+        let mut altered = artefact();
+        altered[8] = 1;
+
+        assert_eq!(
+            verify(&manifest, &altered, &key().verifying_key()),
+            Err(ManifestError::WrongHash)
+        );
+    }
+
+    /// Rewriting the hash to match a different artefact breaks the signature over it.
+    #[test]
+    fn an_altered_manifest_is_refused() {
+        let mut manifest = write(&artefact(), Some(&key()));
+        let mut altered = artefact();
+        altered[8] = 1;
+        manifest[16..48].copy_from_slice(&Sha256::digest(&altered));
+
+        assert_eq!(
+            verify(&manifest, &altered, &key().verifying_key()),
+            Err(ManifestError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn a_manifest_signed_by_another_key_is_refused_and_names_it() {
+        let other = signing_key(OTHER_SEED).unwrap();
+        let manifest = write(&artefact(), Some(&other));
+
+        assert_eq!(
+            verify(&manifest, &artefact(), &key().verifying_key()),
+            Err(ManifestError::UntrustedSigner {
+                signer: Hex(other.verifying_key().to_bytes())
+            })
+        );
+    }
+
+    /// An unsigned manifest is still a checksum, but not one a device holding a key accepts.
+    #[test]
+    fn an_unsigned_manifest_checks_but_does_not_verify() {
+        let manifest = write(&artefact(), None);
+        let read = Manifest::new(&manifest).unwrap();
+
+        assert_eq!(read.signer(), None);
+        assert_eq!(read.check(&artefact()), Ok(()));
+        assert_eq!(
+            read.verify(&artefact(), &key().verifying_key()),
+            Err(ManifestError::Unsigned)
+        );
+    }
+
+    #[test]
+    fn something_that_is_not_a_manifest_is_rejected() {
+        assert_eq!(Manifest::new(&[]), Err(ManifestError::NoHeader(0)));
+        assert_eq!(Manifest::new(&artefact()), Err(ManifestError::NoHeader(12)));
+
+        let mut manifest = write(&artefact(), None);
+        manifest[..4].copy_from_slice(b"XING");
+        assert_eq!(Manifest::new(&manifest), Err(ManifestError::NotAManifest));
+    }
+
+    #[test]
+    fn a_later_version_is_rejected_rather_than_misread() {
+        let mut manifest = write(&artefact(), None);
+        manifest[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert_eq!(
+            Manifest::new(&manifest),
+            Err(ManifestError::UnsupportedVersion { found: VERSION + 1 })
+        );
+    }
+
+    /// A signature cut short is neither a signed manifest nor an unsigned one.
+    #[test]
+    fn a_manifest_of_neither_length_is_rejected() {
+        let manifest = write(&artefact(), Some(&key()));
+
+        assert_eq!(
+            Manifest::new(&manifest[..SIGNED_LEN - 1]),
+            Err(ManifestError::Malformed(SIGNED_LEN - 1))
+        );
+    }
+
+    #[test]
+    fn a_key_is_read_from_hex_with_the_newline_its_file_ends_with() {
+        let key = signing_key(&format!("{SEED}\n")).unwrap();
+
+        assert_eq!(Hex(key.to_bytes()).to_string(), SEED);
+        assert_eq!(
+            public_key(&Hex(key.verifying_key().to_bytes()).to_string()).unwrap(),
+            key.verifying_key()
+        );
+    }
+
+    #[test]
+    fn a_key_that_is_not_64_hex_digits_is_refused() {
+        assert_eq!(key_bytes("abcd"), Err(KeyError::WrongLength(4)));
+        assert_eq!(
+            key_bytes(&SEED.replace('9', "g")),
+            Err(KeyError::NotHex('g'))
+        );
+    }
+}
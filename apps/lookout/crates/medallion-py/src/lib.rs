@@ -1,4 +1,4 @@
-//! Writing the medallion store from python.
+//! Writing and reading the medallion store from python.
 //!
 //! A derivation prototyped as a notebook still has to produce silver in exactly the form
 //! every engine reads: WKB geometry, CRS as PROJJSON, the dataset's own columns, its
@@ -19,11 +19,28 @@
 //! python objects. Nothing about the store's layout is stated here: the dataset's definition
 //! says which columns it holds and how it is partitioned, and a table that does not match is
 //! refused.
+//!
+//! Reading goes through the same definitions. A dataset is read by name, or queried as a
+//! table named for its layer and itself, the way `medallion sql` reads it:
+//!
+//! ```python
+//! sessions = lookout_medallion.read("session", where="country = 'DE'")
+//! counts = lookout_medallion.sql("SELECT country, COUNT(*) FROM silver.session GROUP BY 1")
+//! ```
+//!
+//! Both return a pyarrow table, with the geometry columns as WKB carrying their CRS, so a
+//! notebook neither walks the store's directories itself nor has to know which files in them
+//! a reader is meant to read.
 
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
-use medallion::{Country, Root, TableError, UnknownCountry};
+use datafusion::error::DataFusionError;
+use medallion::verify::Declared;
+use medallion::{
+    Country, GEOMETRY, Geometry, PROJECTED_GEOMETRY, Query, QueryError, Root, TableError,
+    UnknownCountry,
+};
 use model::TargetError;
 use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
@@ -51,6 +68,144 @@ impl Written {
     }
 }
 
+/// One dataset the store defines, as a reader needs to know it.
+#[pyclass(frozen, get_all, skip_from_py_object)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    /// Its name, which is what [`read`] takes.
+    name: &'static str,
+    /// The layer it lives in, which is also the schema [`sql`] names it under.
+    layer: &'static str,
+    /// The Hive key its partitions are keyed on, or `None` for a dataset read whole.
+    partition_key: Option<&'static str>,
+    /// The columns no two of its rows share a value in.
+    unique: Vec<&'static str>,
+    /// The sets of columns no two of its rows share values in all of.
+    unique_together: Vec<Vec<&'static str>>,
+    /// The columns holding another dataset's key, as `(column, dataset, key)`: what to join
+    /// on, and to which dataset's column.
+    references: Vec<(&'static str, &'static str, &'static str)>,
+    /// Its geometry columns, lat/lon first and then the projected twin, or none.
+    geometry: Vec<&'static str>,
+}
+
+#[pymethods]
+impl Dataset {
+    fn __repr__(&self) -> String {
+        format!(
+            "Dataset(name={:?}, layer={:?}, partition_key={}, unique={:?}, \
+             unique_together={:?}, references={:?}, geometry={:?})",
+            self.name,
+            self.layer,
+            self.partition_key
+                .map_or_else(|| "None".to_string(), |key| format!("{key:?}")),
+            self.unique,
+            self.unique_together,
+            self.references,
+            self.geometry,
+        )
+    }
+}
+
+impl From<&Declared> for Dataset {
+    fn from(declared: &Declared) -> Self {
+        Self {
+            name: declared.dataset.name,
+            layer: declared.dataset.layer.as_str(),
+            partition_key: declared.dataset.partition_key,
+            unique: declared.unique.to_vec(),
+            unique_together: declared
+                .unique_together
+                .iter()
+                .map(|columns| columns.to_vec())
+                .collect(),
+            references: declared
+                .references
+                .iter()
+                .map(|reference| (reference.column, reference.dataset.name, reference.key))
+                .collect(),
+            geometry: match declared.geometry {
+                Geometry::Absent => Vec::new(),
+                Geometry::LatLonAndProjected => vec![GEOMETRY, PROJECTED_GEOMETRY],
+            },
+        }
+    }
+}
+
+/// Every dataset the store defines, in every layer.
+///
+/// Listed from the same definitions the writers and [`read`] use, so a notebook learns what
+/// there is to read, and how each is keyed, from the store rather than from its directories.
+#[pyfunction]
+fn datasets() -> Vec<Dataset> {
+    model::DECLARED.iter().map(Dataset::from).collect()
+}
+
+/// Read the dataset `dataset`, from whichever layer it lives in, as a pyarrow table.
+///
+/// `where` is a SQL condition on its columns, partition keys included, so a filter on a
+/// partition key reads only the partitions it matches. `root` names the store, defaulting to
+/// the one in the repo the caller is working in.
+///
+/// What is read is what any reader of the store reads: a compacted partition's merged files
+/// alone, and a rebuilt dataset's published run. A dataset that has never been written has
+/// nothing to read, and is an error rather than an empty table of columns it never held.
+#[pyfunction]
+#[pyo3(signature = (dataset, *, r#where=None, root=None))]
+fn read<'py>(
+    py: Python<'py>,
+    dataset: &str,
+    r#where: Option<&str>,
+    root: Option<PathBuf>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let declared = model::DECLARED
+        .iter()
+        .find(|declared| declared.dataset.name == dataset)
+        .ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "no dataset named `{dataset}`; known: {}",
+                model::ALL
+                    .iter()
+                    .map(|dataset| dataset.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+    let table = format!(
+        "{}.{}",
+        declared.dataset.layer.as_str(),
+        declared.dataset.name
+    );
+    let query = match r#where {
+        Some(condition) => format!("SELECT * FROM {table} WHERE {condition}"),
+        None => format!("SELECT * FROM {table}"),
+    };
+    sql(py, &query, root)
+}
+
+/// Run `query` over the store and return the result as a pyarrow table.
+///
+/// Every dataset is a table named for its layer and itself — `bronze.gps_reading`,
+/// `silver.session` — as in `medallion sql`, and is only read once the query names it.
+#[pyfunction]
+#[pyo3(signature = (query, *, root=None))]
+fn sql<'py>(py: Python<'py>, query: &str, root: Option<PathBuf>) -> PyResult<Bound<'py, PyAny>> {
+    let root = root_at(root)?;
+
+    // Like a write, the query runs on the store's own runtime and needs nothing of python's.
+    let (schema, batches) = py
+        .detach(|| {
+            runtime().block_on(async {
+                let session = Query::new(root);
+                session.register_catalog(&model::ALL)?;
+                session.table(query).await
+            })
+        })
+        .map_err(query_error)?;
+
+    Ok(PyTable::try_new(batches, schema)?.into_pyarrow(py)?)
+}
+
 /// Write `table` as the whole of the silver dataset `dataset`, replacing what is there.
 ///
 /// `root` names the store, defaulting to the one in the repo the caller is working in.
@@ -66,12 +221,7 @@ fn write_silver(
     root: Option<PathBuf>,
 ) -> PyResult<Written> {
     let target = model::silver_target(dataset).map_err(target_error)?;
-    let root = match root {
-        Some(path) => Root::new(path),
-        None => {
-            Root::new(Root::default_path().map_err(|err| PyRuntimeError::new_err(err.to_string()))?)
-        }
-    };
+    let root = root_at(root)?;
     let (batches, _) = table.into_inner();
 
     // The write is filesystem work that calls back into nothing python owns, so the
@@ -111,9 +261,19 @@ fn default_root() -> PyResult<PathBuf> {
     Root::default_path().map_err(|err| PyRuntimeError::new_err(err.to_string()))
 }
 
-/// The runtime the store's async writers run on: one per process, since a call arrives on
-/// whichever thread python is on and building a runtime per call would cost more than the
-/// write.
+/// The store at `root`, or the one in the repo the caller is working in.
+fn root_at(root: Option<PathBuf>) -> PyResult<Root> {
+    match root {
+        Some(path) => Ok(Root::new(path)),
+        None => Ok(Root::new(
+            Root::default_path().map_err(|err| PyRuntimeError::new_err(err.to_string()))?,
+        )),
+    }
+}
+
+/// The runtime the store's async writers and queries run on: one per process, since a call
+/// arrives on whichever thread python is on and building a runtime per call would cost more
+/// than the call.
 fn runtime() -> &'static tokio::runtime::Runtime {
     static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
     RUNTIME.get_or_init(|| {
@@ -145,11 +305,28 @@ fn table_error(err: TableError) -> PyErr {
     }
 }
 
+/// A query that names nothing the store holds, or does not parse, is the caller's mistake;
+/// a store that cannot be read is not.
+fn query_error(err: QueryError) -> PyErr {
+    match err {
+        QueryError::NoSuchDataset { .. }
+        | QueryError::DataFusion(DataFusionError::Plan(_))
+        | QueryError::DataFusion(DataFusionError::SQL(..)) => {
+            PyValueError::new_err(err.to_string())
+        }
+        _ => PyRuntimeError::new_err(err.to_string()),
+    }
+}
+
 #[pymodule]
 fn lookout_medallion(module: &Bound<'_, PyModule>) -> PyResult<()> {
     module.add_function(wrap_pyfunction!(write_silver, module)?)?;
     module.add_function(wrap_pyfunction!(projected_crs, module)?)?;
     module.add_function(wrap_pyfunction!(default_root, module)?)?;
+    module.add_function(wrap_pyfunction!(read, module)?)?;
+    module.add_function(wrap_pyfunction!(sql, module)?)?;
+    module.add_function(wrap_pyfunction!(datasets, module)?)?;
     module.add_class::<Written>()?;
+    module.add_class::<Dataset>()?;
     Ok(())
 }
@@ -0,0 +1,153 @@
+"""What a notebook gets when it reads the store back.
+
+A read is checked against what was written through the same module, since that is the
+round trip a notebook makes: the rows, their partition values, and their geometry come back
+as pyarrow, by dataset name rather than by a glob over the store's directories.
+"""
+
+import datetime
+
+import pyarrow as pa
+import pytest
+import shapely
+
+import lookout_medallion
+from test_write_silver import BERLIN, BERLIN_UTM32N, leg_table
+
+
+@pytest.fixture
+def store(tmp_path):
+    return tmp_path
+
+
+@pytest.fixture
+def legs(store):
+    """Two legs on two days, in the silver `train_segment` dataset."""
+    lookout_medallion.write_silver(
+        "train_segment",
+        leg_table(["a", "b"], ["2026-07-21", "2026-07-22"], ["DE", "DE"]),
+        root=str(store),
+    )
+    return store
+
+
+class TestReadingADataset:
+    def test_a_dataset_is_read_by_name_as_a_pyarrow_table(self, legs):
+        table = lookout_medallion.read("train_segment", root=str(legs))
+
+        assert isinstance(table, pa.Table)
+        assert sorted(table.column("trip_id").to_pylist()) == ["a", "b"]
+
+    def test_the_partition_key_comes_back_as_a_column(self, legs):
+        table = lookout_medallion.read("train_segment", root=str(legs))
+
+        assert "departure_date" in table.column_names
+
+    def test_the_geometry_comes_back_as_wkb(self, legs):
+        table = lookout_medallion.read("train_segment", root=str(legs))
+
+        geometry = table.column("geometry").to_pylist()[0]
+        projected = table.column("geometry_projected").to_pylist()[0]
+        assert shapely.from_wkb(bytes(geometry)).coords[0] == BERLIN
+        assert shapely.from_wkb(bytes(projected)).coords[0] == BERLIN_UTM32N
+
+    def test_a_condition_restricts_the_rows(self, legs):
+        table = lookout_medallion.read(
+            "train_segment", where="departure_date = '2026-07-22'", root=str(legs)
+        )
+
+        assert table.column("trip_id").to_pylist() == ["b"]
+        assert table.column("departure").to_pylist() == [
+            datetime.datetime(2026, 7, 22, 9, tzinfo=datetime.timezone.utc)
+        ]
+
+    def test_a_condition_nothing_matches_is_an_empty_table_of_the_columns(self, legs):
+        table = lookout_medallion.read(
+            "train_segment", where="trip_id = 'z'", root=str(legs)
+        )
+
+        assert table.num_rows == 0
+        assert "trip_id" in table.column_names
+
+
+class TestQuerying:
+    def test_a_dataset_is_named_by_its_layer_and_itself(self, legs):
+        table = lookout_medallion.sql(
+            "SELECT COUNT(*) AS legs FROM silver.train_segment", root=str(legs)
+        )
+
+        assert table.column("legs").to_pylist() == [2]
+
+
+class TestWhatIsRefused:
+    def test_a_dataset_the_store_does_not_define(self, store):
+        with pytest.raises(ValueError, match="crossing_candidates"):
+            lookout_medallion.read("crossing_candidates", root=str(store))
+
+    def test_a_dataset_that_has_never_been_written(self, store):
+        with pytest.raises(ValueError, match="session"):
+            lookout_medallion.read("session", root=str(store))
+
+    def test_a_query_that_does_not_parse(self, legs):
+        with pytest.raises(ValueError):
+            lookout_medallion.sql("SELEC 1", root=str(legs))
+
+
+class TestTheDatasets:
+    def by_name(self):
+        return {dataset.name: dataset for dataset in lookout_medallion.datasets()}
+
+    def test_every_layer_the_store_holds_is_listed(self):
+        layers = {dataset.layer for dataset in lookout_medallion.datasets()}
+
+        assert layers == {"bronze", "silver"}
+
+    def test_a_dataset_states_its_partition_key(self):
+        datasets = self.by_name()
+
+        assert datasets["train_segment"].partition_key == "departure_date"
+        assert datasets["water_crossing"].partition_key == "country"
+        assert datasets["extract_manifest"].partition_key is None
+
+    def test_a_dataset_states_its_unique_columns(self):
+        datasets = self.by_name()
+
+        assert datasets["water_crossing"].unique == [
+            "crossing_id",
+            "crossing_short_id",
+        ]
+        assert datasets["gps_reading"].unique == []
+
+    def test_a_dataset_states_the_columns_unique_together(self):
+        datasets = self.by_name()
+
+        assert datasets["train_segment"].unique_together == [
+            ["trip_id", "from_stop_id", "departure"]
+        ]
+        assert datasets["session"].unique_together == []
+
+    def test_a_dataset_states_what_its_columns_refer_to(self):
+        datasets = self.by_name()
+
+        assert datasets["session_crossing"].references == [
+            ("session_id", "session", "session_id"),
+            ("crossing_id", "water_crossing", "crossing_id"),
+        ]
+        assert datasets["session"].references == []
+
+    def test_a_dataset_states_its_geometry_columns(self):
+        datasets = self.by_name()
+
+        assert datasets["train_segment"].geometry == [
+            "geometry",
+            "geometry_projected",
+        ]
+        assert datasets["session_crossing"].geometry == []
+
+    def test_every_listed_dataset_can_be_read_by_name(self, legs):
+        """`read` finds a dataset the listing names, and not one it does not."""
+        for dataset in lookout_medallion.datasets():
+            try:
+                lookout_medallion.read(dataset.name, root=str(legs))
+            except ValueError as err:
+                assert "no dataset named" not in str(err), dataset.name
@@ -0,0 +1,11 @@
+fn main() {
+    // BUILD_GIT_HASH is the commit every derived file's lineage names, passed in by
+    // the Justfile as the server's is rather than read from `.git`. Fall back to
+    // "unknown" so a bare `cargo build` still compiles.
+    let hash = std::env::var("BUILD_GIT_HASH")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_GIT_HASH={hash}");
+    println!("cargo:rerun-if-env-changed=BUILD_GIT_HASH");
+}
@@ -1,20 +1,33 @@
-//! The `--medallion-root` argument every CLI touching the store shares.
+//! The arguments every CLI touching the store shares: `--medallion-root`, and the
+//! `--from`/`--to` range a derivation is restricted to.
 
 use std::path::PathBuf;
 
-use crate::path::{Root, StoreNotFound};
+use chrono::NaiveDate;
+
+use crate::path::{OpenError, Root};
+use crate::range::{DateRange, EmptyRange};
 
 /// Flattened into each CLI's own args struct, so the flag name and default are identical
 /// everywhere the store is read or written.
 #[derive(Debug, Clone, clap::Args)]
 pub struct MedallionArgs {
-    /// Root of the medallion data store. Defaults to `data/medallion` in the repo this was
-    /// run from.
+    /// Root of the medallion data store: a directory, or an object store's URL such as
+    /// `s3://lookout/medallion`. Defaults to `data/medallion` in the repo this was run from.
     ///
     /// Global, so it is accepted wherever it reads naturally on the command line —
     /// before a subcommand or after it.
     #[arg(long = "medallion-root", global = true)]
     pub medallion_root: Option<PathBuf>,
+    /// The first date to derive, as `YYYY-MM-DD`. Defaults to the earliest there is.
+    ///
+    /// A derivation restricted to a range reads only the partitions it needs for it, and
+    /// replaces only the dates in it. A CLI that does not derive by date ignores it.
+    #[arg(long, global = true)]
+    pub from: Option<NaiveDate>,
+    /// The last date to derive, included. Defaults to the latest there is.
+    #[arg(long, global = true)]
+    pub to: Option<NaiveDate>,
 }
 
 impl MedallionArgs {
@@ -22,13 +35,19 @@ impl MedallionArgs {
     ///
     /// Working out the default can fail — a binary run from outside the repo has no store to
     /// find — which is why this is fallible rather than defaulting to a path that may be the
-    /// wrong one. The flag then says where the store is.
-    pub fn root(&self) -> Result<Root, StoreNotFound> {
+    /// wrong one. The flag then says where the store is. So can opening an object store,
+    /// whose configuration is read from the environment: see [`Root::open`].
+    pub fn root(&self) -> Result<Root, OpenError> {
         match &self.medallion_root {
-            Some(path) => Ok(Root::new(path.clone())),
+            Some(location) => Root::open(&location.to_string_lossy()),
             None => Ok(Root::new(Root::default_path()?)),
         }
     }
+
+    /// The dates to derive: every one, unless either end was given.
+    pub fn range(&self) -> Result<DateRange, EmptyRange> {
+        DateRange::new(self.from, self.to)
+    }
 }
 
 #[cfg(test)]
@@ -75,6 +94,44 @@ mod tests {
         );
     }
 
+    /// A URL names a bucket rather than a directory, and the store is kept below its path.
+    #[test]
+    fn the_root_can_be_an_object_store() {
+        let cli = Cli::parse_from(["a-cli", "--medallion-root", "memory:///medallion"]);
+
+        let root = cli.medallion.root().expect("the named store");
+        assert_eq!(root.path(), PathBuf::from("/medallion"));
+        assert_eq!(root.to_string(), "memory:///medallion");
+    }
+
+    #[test]
+    fn with_no_dates_given_every_date_is_derived() {
+        let cli = Cli::parse_from(["a-cli"]);
+
+        assert_eq!(cli.medallion.range().unwrap(), DateRange::ALL);
+    }
+
+    #[test]
+    fn a_range_is_given_as_the_dates_it_starts_and_ends_on() {
+        let cli = Cli::parse_from(["a-cli", "run", "--from", "2026-07-01", "--to", "2026-07-31"]);
+
+        let range = cli.medallion.range().unwrap();
+        assert_eq!(range.from(), NaiveDate::from_ymd_opt(2026, 7, 1));
+        assert_eq!(range.to(), NaiveDate::from_ymd_opt(2026, 7, 31));
+    }
+
+    #[test]
+    fn a_range_ending_before_it_starts_is_refused() {
+        let cli = Cli::parse_from(["a-cli", "--from", "2026-07-31", "--to", "2026-07-01"]);
+
+        assert!(cli.medallion.range().is_err());
+    }
+
+    #[test]
+    fn a_date_that_is_not_one_is_refused_while_parsing() {
+        assert!(Cli::try_parse_from(["a-cli", "--from", "last-tuesday"]).is_err());
+    }
+
     /// A CLI with subcommands takes the flag on either side of the subcommand, so the
     /// order it is typed in never has to be remembered.
     #[test]
@@ -0,0 +1,246 @@
+//! Every dataset as a table named for where it lives, with nothing registered by hand.
+//!
+//! A reader poking at the store wants to write `SELECT … FROM silver.session` and have it
+//! read, not to look up which spec defines `session` and register it first. So the datasets
+//! are offered to the engine as one schema per layer, built over their definitions: a schema
+//! knows the names of its datasets, and a dataset is listed and read only when a query first
+//! names it. Opening the catalog over a large store therefore costs nothing, and a query
+//! touching one dataset lists that dataset alone.
+//!
+//! A dataset resolves to the table [`Query::register`](crate::Query::register) would have
+//! registered — the partition columns its directories carry, the live files of a compacted
+//! partition, the geometry columns with the CRS their GeoParquet metadata states — since both
+//! read it the same way.
+//!
+//! The definitions stay plain [`DatasetInfo`] values, and this is one engine's view of them
+//! built on request: the engine's catalog traits move between its releases, and the
+//! definitions have no reason to move with them.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use async_trait::async_trait;
+use datafusion::catalog::{SchemaProvider, TableProvider};
+use datafusion::error::DataFusionError;
+use sedona::context::SedonaContext;
+
+use crate::dataset::DatasetInfo;
+use crate::layer::Layer;
+use crate::path::Root;
+use crate::query::read_table;
+
+/// The datasets of one layer, as the tables of one schema.
+pub(crate) struct LayerSchema {
+    root: Root,
+    layer: Layer,
+    datasets: Vec<DatasetInfo>,
+    /// What the datasets are read with. A context of its own rather than the one the queries
+    /// run in: that one holds this schema, and a schema holding it back would keep both alive
+    /// for good.
+    reader: Arc<SedonaContext>,
+    /// The tables resolved so far, so a query naming a dataset twice lists it once.
+    resolved: Mutex<HashMap<&'static str, Arc<dyn TableProvider>>>,
+}
+
+impl LayerSchema {
+    /// One schema per layer `datasets` hold any dataset of, all reading `root`.
+    pub(crate) fn over(root: &Root, datasets: &[DatasetInfo]) -> Vec<Self> {
+        let reader = SedonaContext::new();
+        root.backend().register(&reader.ctx);
+        let reader = Arc::new(reader);
+        let mut schemas: Vec<Self> = Vec::new();
+        for dataset in datasets {
+            match schemas
+                .iter_mut()
+                .find(|schema| schema.layer == dataset.layer)
+            {
+                Some(schema) => schema.datasets.push(*dataset),
+                None => schemas.push(Self {
+                    root: root.clone(),
+                    layer: dataset.layer,
+                    datasets: vec![*dataset],
+                    reader: reader.clone(),
+                    resolved: Mutex::new(HashMap::new()),
+                }),
+            }
+        }
+        schemas
+    }
+
+    /// The layer whose datasets this holds, which is also the schema's name.
+    pub(crate) fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    fn defined(&self, name: &str) -> Option<&DatasetInfo> {
+        self.datasets.iter().find(|dataset| dataset.name == name)
+    }
+}
+
+impl fmt::Debug for LayerSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayerSchema")
+            .field("root", &self.root)
+            .field("layer", &self.layer)
+            .field("datasets", &self.datasets)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl SchemaProvider for LayerSchema {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.datasets
+            .iter()
+            .map(|dataset| dataset.name.to_string())
+            .collect()
+    }
+
+    /// The dataset called `name`, read the first time it is asked for. A dataset holding no
+    /// files is no table, so a query naming it fails to plan rather than reading as empty.
+    async fn table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>, DataFusionError> {
+        let Some(dataset) = self.defined(name) else {
+            return Ok(None);
+        };
+        // The cache is only ever inserted into whole, so one a panic poisoned is still sound.
+        let resolved = self
+            .resolved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(dataset.name)
+            .cloned();
+        if resolved.is_some() {
+            return Ok(resolved);
+        }
+
+        let dir = self
+            .root
+            .path()
+            .join(self.layer.as_str())
+            .join(dataset.name);
+        let Some(df) = read_table(&self.reader, self.root.backend(), &dir, &dir, None)
+            .await
+            .map_err(|err| DataFusionError::External(Box::new(err)))?
+        else {
+            return Ok(None);
+        };
+        let table = df.into_view();
+        self.resolved
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(dataset.name, table.clone());
+        Ok(Some(table))
+    }
+
+    fn table_exist(&self, name: &str) -> bool {
+        self.defined(name).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Int64Array, RecordBatch};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use chrono::{TimeZone, Utc};
+
+    use crate::dataset::DatasetSpec;
+    use crate::layer::layers;
+    use crate::query::{Query, QueryError};
+
+    use super::*;
+
+    const THING: DatasetSpec<layers::Bronze> = DatasetSpec::partitioned("thing", "kind");
+    const DERIVED: DatasetSpec<layers::Silver> = DatasetSpec::unpartitioned("derived");
+    const NOTHING: DatasetSpec<layers::Silver> = DatasetSpec::partitioned("nothing", "kind");
+
+    fn ids(ids: Vec<i64>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+    }
+
+    async fn write_thing(root: &Root, kind: &str, rows: Vec<i64>) {
+        root.dataset(THING)
+            .partition("kind", kind)
+            .unwrap()
+            .append(
+                Utc.with_ymd_and_hms(2026, 7, 26, 9, 0, 0).unwrap(),
+                &[ids(rows)],
+            )
+            .await
+            .unwrap();
+    }
+
+    fn catalog(root: &Root) -> Query {
+        let query = Query::new(root.clone());
+        query
+            .register_catalog(&[THING.info(), DERIVED.info(), NOTHING.info()])
+            .unwrap();
+        query
+    }
+
+    #[tokio::test]
+    async fn a_dataset_is_named_by_its_layer_and_its_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_thing(&root, "a", vec![1, 2]).await;
+        write_thing(&root, "b", vec![3]).await;
+        root.dataset(DERIVED)
+            .replace_with(&[ids(vec![7])])
+            .await
+            .unwrap();
+        let query = catalog(&root);
+
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM bronze.thing WHERE kind = 'a'")
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM silver.derived")
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    /// Nothing is read when the catalog is opened, so what a query sees is the store as it
+    /// is when the query names the dataset.
+    #[tokio::test]
+    async fn a_dataset_is_read_when_it_is_first_named() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let query = catalog(&root);
+
+        write_thing(&root, "a", vec![1]).await;
+
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM bronze.thing")
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    /// As with registering one by hand, a dataset nothing has written is no table rather
+    /// than an empty one.
+    #[tokio::test]
+    async fn a_dataset_holding_nothing_cannot_be_queried() {
+        let tmp = tempfile::tempdir().unwrap();
+        let query = catalog(&Root::new(tmp.path()));
+
+        assert!(matches!(
+            query.sql("SELECT * FROM silver.nothing").await,
+            Err(QueryError::DataFusion(_))
+        ));
+    }
+}
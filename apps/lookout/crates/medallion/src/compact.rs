@@ -0,0 +1,626 @@
+//! Compacting an append-only dataset's batch files into fewer, without rewriting or deleting
+//! any of them.
+//!
+//! Bronze writes one file per ingestion, which is right when it is written and wrong a year
+//! later: a dataset polled on an interval holds a file per poll, and reading it costs a seek
+//! per file. Compacting merges a partition's files into one larger file, but the layer's rule
+//! is that a file once written never changes and is never deleted, so the merged file is
+//! **added beside** the files it merges rather than written over them:
+//!
+//!   - the merged file is a **generation**, named for the instant of the compaction, so two
+//!     compactions of one partition are two generations and never one file written twice;
+//!   - a **manifest** beside it names the files it supersedes. It is written last, and a
+//!     generation with no manifest is an interrupted compaction, read as though it were not
+//!     there — so a reader sees the originals or the generation, never both and never
+//!     neither;
+//!   - a later compaction merges what is live, which may include an earlier generation, and
+//!     supersedes that too.
+//!
+//! [`crate::Query`] and [`crate::summary`] read what is live: the files no completed
+//! compaction supersedes. The originals stay in the store, so compaction trades bytes for
+//! seeks, and an engine reading a partition's files directly sees a row twice — which bronze
+//! already tolerates, since its readers collapse on each row's identity before anything else.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::layer::layers;
+use crate::path::{BATCH_STEM_FORMAT, Dataset};
+use crate::store::{Backend, Stored};
+use crate::summary::{Contents, SummaryError, file_contents};
+use crate::write::{WriteError, writer_at};
+
+/// What a generation's file and its manifest are named with, ahead of the instant of the
+/// compaction. A batch file is named for its instant alone, so the two cannot collide.
+const GENERATION_PREFIX: &str = "compacted-";
+
+const PARQUET: &str = "parquet";
+const MANIFEST: &str = "json";
+
+/// The fewest live files a partition has to hold to be worth compacting: one file merged is
+/// the same file again.
+const FEWEST_TO_MERGE: usize = 2;
+
+/// A failure listing what a partition holds.
+#[derive(Debug, thiserror::Error)]
+pub enum ListingError {
+    #[error("listing {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("reading the compaction manifest {path}: {source}")]
+    Manifest {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("reading the files {path} publishes: {source}")]
+    Pointer {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// A failure compacting a dataset.
+#[derive(Debug, thiserror::Error)]
+pub enum CompactError {
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error("reading {path}: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: parquet::errors::ParquetError,
+    },
+    #[error("reading {path}: {source}")]
+    Arrow {
+        path: String,
+        #[source]
+        source: arrow::error::ArrowError,
+    },
+    #[error("{path} holds files of more than one schema, which one file cannot hold")]
+    Schemas { path: String },
+    #[error(transparent)]
+    Write(#[from] WriteError),
+    #[error("recording the compaction in {path}: {source}")]
+    Record {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Measure(#[from] SummaryError),
+}
+
+/// What compacting a dataset did. The measurements are of what a reader reads — the live
+/// files — so `after` is smaller than `before` although the store holds more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Compaction {
+    /// Partitions merged into a new generation.
+    pub partitions: usize,
+    pub before: Contents,
+    pub after: Contents,
+}
+
+/// One compaction, as recorded beside the generation it produced.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    compacted_at: DateTime<Utc>,
+    /// The generation's file name, within the partition.
+    generation: String,
+    /// The file names, within the partition, the generation holds the rows of.
+    supersedes: Vec<String>,
+}
+
+/// One partition of a dataset holding something to read: the `key=value` directories
+/// between it and the dataset, and its live files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LivePartition {
+    pub(crate) keys: Vec<(String, String)>,
+    pub(crate) files: Vec<PathBuf>,
+}
+
+impl Dataset<layers::Bronze> {
+    /// Merge each partition holding at least `min_files` live files into a new generation,
+    /// identified by `at`.
+    ///
+    /// Nothing is rewritten or deleted: see the [module docs](self). A partition is every
+    /// directory that holds files, at any depth, so a dataset partitioned below its own key
+    /// is compacted at the level its files sit at. A dataset restricted
+    /// [`within`](Dataset::within) a range compacts only the dates in it.
+    pub async fn compact(
+        &self,
+        at: DateTime<Utc>,
+        min_files: usize,
+    ) -> Result<Compaction, CompactError> {
+        let backend = self.backend();
+        let admit = |key: &str, value: &str| self.admits(key, value);
+        let mut compaction = Compaction::default();
+        for partition in partitions_below(backend, &self.dir(), &admit).await? {
+            let live = live_in(backend, &partition).await?;
+            compaction.before.add(contents(backend, &live).await?);
+            if live.len() < min_files.max(FEWEST_TO_MERGE) {
+                compaction.after.add(contents(backend, &live).await?);
+                continue;
+            }
+
+            let generation = merge(backend, &partition, &live, at).await?;
+            compaction.partitions += 1;
+            compaction
+                .after
+                .add(contents(backend, &[generation]).await?);
+        }
+        Ok(compaction)
+    }
+}
+
+/// Every partition below `dir` with the files a reader reads in it, or `None` if no
+/// compaction has superseded anything there — in which case the directory reads as it is.
+pub(crate) async fn live_partitions(
+    backend: &Backend,
+    dir: &Path,
+) -> Result<Option<Vec<LivePartition>>, ListingError> {
+    let (compacted, live) = listed(backend, dir, &|_, _| true).await?;
+    Ok(compacted.then_some(live))
+}
+
+/// Every partition below `dir` that `admit` lets the listing into, with the files a reader
+/// reads in it. `admit` is asked of each `key=value` directory before it is opened, so a
+/// directory it refuses is never listed.
+pub(crate) async fn live_partitions_where(
+    backend: &Backend,
+    dir: &Path,
+    admit: &Admit<'_>,
+) -> Result<Vec<LivePartition>, ListingError> {
+    Ok(listed(backend, dir, admit).await?.1)
+}
+
+/// The first partition below `dir` holding a live file, with its live files, or `None` if
+/// none does: for a reader that needs only the dataset's columns, which lists as far as the
+/// first rather than the whole dataset.
+pub(crate) async fn first_live_partition(
+    backend: &Backend,
+    dir: &Path,
+) -> Result<Option<LivePartition>, ListingError> {
+    let mut unvisited = vec![dir.to_path_buf()];
+    while let Some(partition) = unvisited.pop() {
+        let listed = backend
+            .list(&partition)
+            .await
+            .map_err(|source| ListingError::Io {
+                path: partition.display().to_string(),
+                source,
+            })?;
+        if !listed.files.is_empty() {
+            let listing = listing(backend, &partition).await?;
+            if !listing.live.is_empty() {
+                return Ok(Some(LivePartition {
+                    keys: partition_keys(dir, &partition),
+                    files: listing.live.into_iter().map(|file| file.path).collect(),
+                }));
+            }
+        }
+        unvisited.extend(listed.dirs.into_iter().rev());
+    }
+    Ok(None)
+}
+
+/// What decides whether a listing enters a `key=value` directory. `Sync`, so a listing
+/// holding one across its awaits can still be sent between threads.
+pub(crate) type Admit<'a> = dyn Fn(&str, &str) -> bool + Sync + 'a;
+
+/// The live partitions below `dir` that `admit` allows, and whether any compaction has
+/// superseded a file among them.
+async fn listed(
+    backend: &Backend,
+    dir: &Path,
+    admit: &Admit<'_>,
+) -> Result<(bool, Vec<LivePartition>), ListingError> {
+    let mut compacted = false;
+    let mut live = Vec::new();
+    for partition in partitions_below(backend, dir, admit).await? {
+        let listing = listing(backend, &partition).await?;
+        compacted |= listing.hidden.iter().any(|file| is_parquet(file));
+        if !listing.live.is_empty() {
+            live.push(LivePartition {
+                keys: partition_keys(dir, &partition),
+                files: listing.live.into_iter().map(|file| file.path).collect(),
+            });
+        }
+    }
+    Ok((compacted, live))
+}
+
+/// The `key=value` directories between `dir` and `partition`, outermost first.
+pub(crate) fn partition_keys(dir: &Path, partition: &Path) -> Vec<(String, String)> {
+    partition
+        .strip_prefix(dir)
+        .unwrap_or(partition)
+        .components()
+        .filter_map(|part| {
+            let (key, value) = part.as_os_str().to_str()?.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The files in `dir` that a reader does not read: the ones a completed compaction
+/// superseded, the generations no compaction completed, and the manifests themselves.
+pub(crate) async fn hidden_in(
+    backend: &Backend,
+    dir: &Path,
+) -> Result<HashSet<PathBuf>, ListingError> {
+    Ok(listing(backend, dir).await?.hidden)
+}
+
+/// What one directory holds, split into what is read and what is not.
+struct Listing {
+    live: Vec<Stored>,
+    hidden: HashSet<PathBuf>,
+}
+
+async fn listing(backend: &Backend, dir: &Path) -> Result<Listing, ListingError> {
+    let mut parquet = Vec::new();
+    let mut hidden = HashSet::new();
+    let mut superseded = HashSet::new();
+    let mut completed = HashSet::new();
+    for file in files_in(backend, dir).await? {
+        if is_manifest(&file.path) {
+            let manifest = read_manifest(backend, &file.path).await?;
+            completed.insert(dir.join(manifest.generation));
+            superseded.extend(manifest.supersedes.iter().map(|name| dir.join(name)));
+            hidden.insert(file.path);
+        } else if is_parquet(&file.path) {
+            parquet.push(file);
+        }
+    }
+
+    let (live, unread): (Vec<Stored>, Vec<Stored>) = parquet.into_iter().partition(|file| {
+        !superseded.contains(&file.path)
+            && (!is_generation(&file.path) || completed.contains(&file.path))
+    });
+    hidden.extend(unread.into_iter().map(|file| file.path));
+    Ok(Listing { live, hidden })
+}
+
+/// Write the rows of `live` as one generation of `partition`, then record what it
+/// supersedes. Returns the generation.
+///
+/// The rows are copied a batch at a time, each file's row groups read as they are written
+/// on, so a partition is never held whole; every file's schema is checked from its footer
+/// before anything is written. The generation is written as an append is, so a second
+/// compaction at the same instant is refused rather than written over the first.
+async fn merge(
+    backend: &Backend,
+    partition: &Path,
+    live: &[Stored],
+    at: DateTime<Utc>,
+) -> Result<Stored, CompactError> {
+    let stem = format!("{GENERATION_PREFIX}{}", at.format(BATCH_STEM_FORMAT));
+    let generation = partition.join(format!("{stem}.{PARQUET}"));
+
+    let mut streams = Vec::new();
+    let mut schema = None;
+    for file in live {
+        let (file_schema, stream) =
+            backend
+                .batches(file)
+                .await
+                .map_err(|source| CompactError::Read {
+                    path: file.path.display().to_string(),
+                    source,
+                })?;
+        match &schema {
+            None => schema = Some(file_schema.clone()),
+            Some(first) if first.fields() != file_schema.fields() => {
+                return Err(CompactError::Schemas {
+                    path: partition.display().to_string(),
+                });
+            }
+            Some(_) => {}
+        }
+        streams.push((file, stream));
+    }
+    let Some(schema) = schema else {
+        return Err(WriteError::Empty.into());
+    };
+
+    // The schema is the first file's own, metadata and all, so a GeoParquet file's `geo`
+    // metadata is carried into the generation; files of no rows merge into a file of no
+    // rows, which still states it.
+    let mut writer = writer_at(backend, &generation, schema.clone())?;
+    for (file, mut stream) in streams {
+        let read_error = |source| CompactError::Read {
+            path: file.path.display().to_string(),
+            source,
+        };
+        while let Some(batch) = stream.try_next().await.map_err(read_error)? {
+            let batch =
+                batch
+                    .with_schema(schema.clone())
+                    .map_err(|source| CompactError::Arrow {
+                        path: file.path.display().to_string(),
+                        source,
+                    })?;
+            writer.write(&batch).await?;
+        }
+    }
+    writer.close().await?;
+
+    let manifest = Manifest {
+        compacted_at: at,
+        generation: file_name(&generation),
+        supersedes: live.iter().map(|file| file_name(&file.path)).collect(),
+    };
+    record(
+        backend,
+        &partition.join(format!("{stem}.{MANIFEST}")),
+        &manifest,
+    )
+    .await?;
+    backend
+        .stored(&generation)
+        .await
+        .map_err(|source| ListingError::Io {
+            path: generation.display().to_string(),
+            source,
+        })
+        .map_err(CompactError::from)
+}
+
+/// Write `manifest` to `path` whole or not at all, so a reader never parses half a manifest
+/// and a crash leaves the compaction incomplete.
+async fn record(backend: &Backend, path: &Path, manifest: &Manifest) -> Result<(), CompactError> {
+    let record_error = |source: std::io::Error| CompactError::Record {
+        path: path.display().to_string(),
+        source,
+    };
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(std::io::Error::from)
+        .map_err(record_error)?;
+    backend.put(path, json).await.map_err(record_error)
+}
+
+async fn read_manifest(backend: &Backend, path: &Path) -> Result<Manifest, ListingError> {
+    let json = backend
+        .read(path)
+        .await
+        .map_err(|source| ListingError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+    serde_json::from_slice(&json).map_err(|source| ListingError::Manifest {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+/// The live files of one partition.
+async fn live_in(backend: &Backend, partition: &Path) -> Result<Vec<Stored>, ListingError> {
+    Ok(listing(backend, partition).await?.live)
+}
+
+/// What `files` hold, counted as a summary counts them.
+async fn contents(backend: &Backend, files: &[Stored]) -> Result<Contents, SummaryError> {
+    let mut total = Contents::default();
+    for file in files {
+        total.add(file_contents(backend, file).await?);
+    }
+    Ok(total)
+}
+
+/// `dir` and every directory below it that directly holds a file, in name order, entering
+/// only the `key=value` directories `admit` allows.
+///
+/// Walked with a stack rather than by recursing, so the listing is one future of a known
+/// size; each directory's own go on in reverse, so the first is the next entered, as a
+/// recursive walk would have entered it.
+async fn partitions_below(
+    backend: &Backend,
+    dir: &Path,
+    admit: &Admit<'_>,
+) -> Result<Vec<PathBuf>, ListingError> {
+    let mut partitions = Vec::new();
+    let mut unvisited = vec![dir.to_path_buf()];
+    while let Some(dir) = unvisited.pop() {
+        let listed = backend
+            .list(&dir)
+            .await
+            .map_err(|source| ListingError::Io {
+                path: dir.display().to_string(),
+                source,
+            })?;
+        if !listed.files.is_empty() {
+            partitions.push(dir);
+        }
+        unvisited.extend(
+            listed
+                .dirs
+                .into_iter()
+                .filter(|below| admitted(below, admit))
+                .rev(),
+        );
+    }
+    Ok(partitions)
+}
+
+/// Whether `admit` allows the directory `dir`. A directory not named `key=value` is no
+/// partition, and nothing to refuse.
+fn admitted(dir: &Path, admit: &Admit<'_>) -> bool {
+    let name = file_name(dir);
+    name.split_once('=')
+        .is_none_or(|(key, value)| admit(key, value))
+}
+
+/// The files directly in `dir`, in name order, which is the order they were written in.
+async fn files_in(backend: &Backend, dir: &Path) -> Result<Vec<Stored>, ListingError> {
+    Ok(backend
+        .list(dir)
+        .await
+        .map_err(|source| ListingError::Io {
+            path: dir.display().to_string(),
+            source,
+        })?
+        .files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn is_parquet(path: &Path) -> bool {
+    path.extension().is_some_and(|kind| kind == PARQUET)
+}
+
+fn is_generation(path: &Path) -> bool {
+    file_name(path).starts_with(GENERATION_PREFIX)
+}
+
+pub(crate) fn is_manifest(path: &Path) -> bool {
+    is_generation(path) && path.extension().is_some_and(|kind| kind == MANIFEST)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Int64Array, RecordBatch};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::dataset::DatasetSpec;
+    use crate::path::Root;
+    use crate::query::Query;
+
+    const POLL: DatasetSpec<layers::Bronze> = DatasetSpec::partitioned("poll", "polled_date");
+
+    fn at(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 7, 26, 9, 0, second).unwrap()
+    }
+
+    fn ids(ids: Vec<i64>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+    }
+
+    /// A day of `polls` polls, one file each.
+    async fn polled(root: &Root, polls: u32) -> Dataset<layers::Bronze> {
+        let day = root.dataset(POLL).on_date(at(0).date_naive()).unwrap();
+        for poll in 0..polls {
+            day.append(at(poll), &[ids(vec![poll as i64])])
+                .await
+                .unwrap();
+        }
+        day
+    }
+
+    async fn ids_read(root: &Root) -> i64 {
+        let query = Query::new(root.clone());
+        query.register_by_name(POLL).await.unwrap();
+        query
+            .count("SELECT COUNT(*) AS count FROM poll WHERE polled_date = '2026-07-26'")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_partitions_files_merge_into_one_generation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        polled(&root, 3).await;
+
+        let compaction = root.dataset(POLL).compact(at(30), 2).await.unwrap();
+
+        assert_eq!(compaction.partitions, 1);
+        assert_eq!(compaction.before.files, 3);
+        assert_eq!(compaction.after.files, 1);
+        assert_eq!(compaction.before.rows, compaction.after.rows);
+        assert_eq!(ids_read(&root).await, 3);
+    }
+
+    /// The layer's rule holds through a compaction: every file written before it is still
+    /// there, byte for byte.
+    #[tokio::test]
+    async fn compacting_neither_rewrites_nor_deletes_a_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let day = polled(&root, 2).await;
+        let before: Vec<(PathBuf, Vec<u8>)> = files_in(day.backend(), &day.dir())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|file| (file.path.clone(), std::fs::read(&file.path).unwrap()))
+            .collect();
+
+        root.dataset(POLL).compact(at(30), 2).await.unwrap();
+
+        for (file, bytes) in before {
+            assert_eq!(std::fs::read(&file).unwrap(), bytes, "{}", file.display());
+        }
+    }
+
+    /// What arrives after a compaction is read beside its generation, and the next
+    /// compaction merges the two.
+    #[tokio::test]
+    async fn a_later_compaction_supersedes_the_generation_before_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let day = polled(&root, 2).await;
+        root.dataset(POLL).compact(at(30), 2).await.unwrap();
+        day.append(at(40), &[ids(vec![9])]).await.unwrap();
+        assert_eq!(ids_read(&root).await, 3);
+
+        let compaction = root.dataset(POLL).compact(at(50), 2).await.unwrap();
+
+        assert_eq!(compaction.before.files, 2);
+        assert_eq!(compaction.after.files, 1);
+        assert_eq!(ids_read(&root).await, 3);
+    }
+
+    /// A generation whose manifest was never written is a compaction that did not finish,
+    /// so the originals are what is read — not both, which would count every row twice.
+    #[tokio::test]
+    async fn a_generation_with_no_manifest_is_not_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let day = polled(&root, 2).await;
+        root.dataset(POLL).compact(at(30), 2).await.unwrap();
+        for file in files_in(day.backend(), &day.dir()).await.unwrap() {
+            if is_manifest(&file.path) {
+                std::fs::remove_file(file.path).unwrap();
+            }
+        }
+
+        assert_eq!(ids_read(&root).await, 2);
+        assert_eq!(live_in(day.backend(), &day.dir()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_partition_with_too_few_files_is_left_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        polled(&root, 2).await;
+
+        let compaction = root.dataset(POLL).compact(at(30), 3).await.unwrap();
+
+        assert_eq!(compaction.partitions, 0);
+        assert_eq!(compaction.before, compaction.after);
+        assert!(
+            live_partitions(root.backend(), &root.dataset(POLL).dir())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -5,8 +5,11 @@
 //! may cover a country, but a single zone keeps every geometry within it directly
 //! comparable. This is where that choice is made, so a dataset states which country's
 //! geometry it holds and never picks a zone of its own.
+//!
+//! The countries are a table rather than code: see `REGISTRY` for what adding one takes.
 
 use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 use geo_types::Point;
@@ -25,12 +28,114 @@ pub const COUNTRY: &str = "country";
 pub trait Countries {
     /// The country containing `point`, or `None` where that is no country the store knows.
     fn containing(&self, point: Point<f64>) -> Option<Country>;
+
+    /// The country containing each of `points`, in order: what a writer placing a day's
+    /// worth of rows asks, in one call rather than one per row.
+    fn containing_all(&self, points: &[Point<f64>]) -> Vec<Option<Country>> {
+        points.iter().map(|point| self.containing(*point)).collect()
+    }
+}
+
+/// One row of the registry: a country, and the projected CRS its geometry is held in.
+#[derive(Debug)]
+struct Definition {
+    /// ISO 3166-1 alpha-2, as Overture names countries and a `country=` partition is named.
+    code: &'static str,
+    /// Other codes a person might type for it, accepted when parsing but never written.
+    aliases: &'static [&'static str],
+    name: &'static str,
+    projected_epsg: u16,
+    /// Generated from PROJ by `just crs-definitions`.
+    projected_projjson: &'static str,
+}
+
+/// Every country the store can hold, in code order.
+///
+/// Adding a country is a row here and a line in `just crs-definitions` for its CRS, if no
+/// country already uses it; everything that takes a [`Country`] — projecting, the `country=`
+/// layout, the country areas, `extract --country` — goes by this table. Each country's CRS
+/// is the grid its own mapping agency publishes in, drawn to keep distortion small across
+/// that one country, which a UTM zone stretched over a country as wide as France would not.
+static REGISTRY: [Definition; 7] = [
+    Definition {
+        code: "AT",
+        aliases: &[],
+        name: "Austria",
+        projected_epsg: 3416,
+        projected_projjson: include_str!("etrs89_austria_lambert.projjson.json"),
+    },
+    Definition {
+        code: "CH",
+        aliases: &[],
+        name: "Switzerland",
+        projected_epsg: 2056,
+        projected_projjson: include_str!("ch1903plus_lv95.projjson.json"),
+    },
+    Definition {
+        code: "DE",
+        aliases: &[],
+        name: "Germany",
+        projected_epsg: 25832,
+        projected_projjson: include_str!("etrs89_utm32n.projjson.json"),
+    },
+    // Denmark's national grid is the same UTM zone Germany's is.
+    Definition {
+        code: "DK",
+        aliases: &[],
+        name: "Denmark",
+        projected_epsg: 25832,
+        projected_projjson: include_str!("etrs89_utm32n.projjson.json"),
+    },
+    Definition {
+        code: "FR",
+        aliases: &[],
+        name: "France",
+        projected_epsg: 2154,
+        projected_projjson: include_str!("rgf93_lambert93.projjson.json"),
+    },
+    // The British National Grid covers Great Britain; Northern Ireland has a grid of its
+    // own, which nothing here needs yet.
+    Definition {
+        code: "GB",
+        aliases: &["UK"],
+        name: "United Kingdom",
+        projected_epsg: 27700,
+        projected_projjson: include_str!("osgb36_british_national_grid.projjson.json"),
+    },
+    Definition {
+        code: "NL",
+        aliases: &[],
+        name: "Netherlands",
+        projected_epsg: 28992,
+        projected_projjson: include_str!("amersfoort_rd_new.projjson.json"),
+    },
+];
+
+/// A country whose geometry the store can hold: one row of the registry.
+///
+/// Cheap to copy and compare, as the enum it replaced was. Two countries are the same
+/// country when they have the same code.
+#[derive(Clone, Copy)]
+pub struct Country(&'static Definition);
+
+impl PartialEq for Country {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code()
+    }
+}
+
+impl Eq for Country {}
+
+impl Hash for Country {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code().hash(state);
+    }
 }
 
-/// A country whose geometry the store can hold.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Country {
-    Germany,
+impl fmt::Debug for Country {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Country").field(&self.code()).finish()
+    }
 }
 
 /// A code naming no country the store knows.
@@ -43,11 +148,14 @@ pub struct UnknownCountry {
 impl FromStr for Country {
     type Err = UnknownCountry;
 
-    /// Parses an ISO 3166-1 alpha-2 code, in either case.
+    /// Parses an ISO 3166-1 alpha-2 code, or an alias of one, in either case.
     fn from_str(code: &str) -> Result<Self, Self::Err> {
-        Country::ALL
-            .into_iter()
-            .find(|country| country.code().eq_ignore_ascii_case(code))
+        Country::all()
+            .find(|country| {
+                std::iter::once(country.code())
+                    .chain(country.0.aliases.iter().copied())
+                    .any(|known| known.eq_ignore_ascii_case(code))
+            })
             .ok_or_else(|| UnknownCountry {
                 code: code.to_string(),
             })
@@ -61,13 +169,22 @@ impl Display for Country {
 }
 
 impl Country {
-    /// Every country the store knows, for checks that must cover all of them.
-    pub const ALL: [Country; 1] = [Country::Germany];
+    pub const AUSTRIA: Country = Country(&REGISTRY[0]);
+    pub const SWITZERLAND: Country = Country(&REGISTRY[1]);
+    pub const GERMANY: Country = Country(&REGISTRY[2]);
+    pub const DENMARK: Country = Country(&REGISTRY[3]);
+    pub const FRANCE: Country = Country(&REGISTRY[4]);
+    pub const UNITED_KINGDOM: Country = Country(&REGISTRY[5]);
+    pub const NETHERLANDS: Country = Country(&REGISTRY[6]);
+
+    /// Every country the store knows, in code order, for checks that must cover all of them.
+    pub fn all() -> impl Iterator<Item = Country> {
+        REGISTRY.iter().map(Country)
+    }
 
     /// The codes of every known country, for an error that lists the choices.
     pub fn codes() -> String {
-        Country::ALL
-            .iter()
+        Country::all()
             .map(|country| country.code())
             .collect::<Vec<_>>()
             .join(", ")
@@ -75,24 +192,22 @@ impl Country {
 
     /// The ISO 3166-1 alpha-2 code, as used in a `country=` partition.
     pub fn code(self) -> &'static str {
-        match self {
-            Country::Germany => "DE",
-        }
+        self.0.code
+    }
+
+    /// The country's name in English, for a person reading a listing.
+    pub fn name(self) -> &'static str {
+        self.0.name
     }
 
     /// EPSG code of the country's projected CRS.
     pub fn projected_epsg(self) -> u16 {
-        match self {
-            Country::Germany => 25832,
-        }
+        self.0.projected_epsg
     }
 
     /// The country's projected CRS as PROJJSON, the encoding GeoParquet requires.
-    /// Generated from PROJ by `just crs-definitions`.
     pub fn projected_projjson(self) -> &'static str {
-        match self {
-            Country::Germany => include_str!("etrs89_utm32n.projjson.json"),
-        }
+        self.0.projected_projjson
     }
 }
 
@@ -105,7 +220,7 @@ mod tests {
     /// reverse.
     #[test]
     fn each_country_bundles_the_projjson_of_the_epsg_it_names() {
-        for country in Country::ALL {
+        for country in Country::all() {
             let projjson: serde_json::Value =
                 serde_json::from_str(country.projected_projjson()).unwrap();
 
@@ -123,7 +238,7 @@ mod tests {
     /// partition is named for.
     #[test]
     fn a_country_parses_from_its_own_code_in_either_case() {
-        for country in Country::ALL {
+        for country in Country::all() {
             assert_eq!(country.code().parse(), Ok(country));
             assert_eq!(country.code().to_lowercase().parse(), Ok(country));
             assert_eq!(country.to_string(), country.code());
@@ -138,9 +253,47 @@ mod tests {
         assert!(err.to_string().contains("DE"), "{err}");
     }
 
+    /// A code is what names a partition, so two rows sharing one would put two countries'
+    /// geometry, in two CRSs, in one directory.
+    #[test]
+    fn no_two_countries_share_a_code() {
+        let codes: Vec<&str> = Country::all()
+            .flat_map(|country| {
+                std::iter::once(country.code()).chain(country.0.aliases.iter().copied())
+            })
+            .collect();
+        let distinct: std::collections::BTreeSet<&str> = codes.iter().copied().collect();
+
+        assert_eq!(codes.len(), distinct.len(), "{codes:?}");
+    }
+
+    /// The named countries are positions in the table, so a row added in the middle of it
+    /// has to move them.
+    #[test]
+    fn each_named_country_is_the_row_its_name_says() {
+        for (country, code, name) in [
+            (Country::AUSTRIA, "AT", "Austria"),
+            (Country::SWITZERLAND, "CH", "Switzerland"),
+            (Country::GERMANY, "DE", "Germany"),
+            (Country::DENMARK, "DK", "Denmark"),
+            (Country::FRANCE, "FR", "France"),
+            (Country::UNITED_KINGDOM, "GB", "United Kingdom"),
+            (Country::NETHERLANDS, "NL", "Netherlands"),
+        ] {
+            assert_eq!((country.code(), country.name()), (code, name));
+        }
+    }
+
+    /// `UK` is not an ISO code, but it is what most people would type.
+    #[test]
+    fn an_alias_parses_to_its_country_but_is_never_written() {
+        assert_eq!("uk".parse(), Ok(Country::UNITED_KINGDOM));
+        assert_eq!(Country::UNITED_KINGDOM.to_string(), "GB");
+    }
+
     #[test]
     fn a_country_code_is_iso_3166_1_alpha_2() {
-        for country in Country::ALL {
+        for country in Country::all() {
             let code = country.code();
 
             assert_eq!(code.len(), 2, "{country:?}");
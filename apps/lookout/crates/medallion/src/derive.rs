@@ -15,10 +15,14 @@
 //!   the zone a country's metres are in is the store's choice, and projecting into one while
 //!   declaring another is the mistake this removes the opportunity for.
 //! * **Partitions** are replaced, and the ones the rows no longer cover — dates within a
-//!   country, and the countries themselves — are deleted.
+//!   country, and the countries themselves — are deleted. Reference-derived geometry, which
+//!   describes a place rather than a day, is laid out by country alone and goes through
+//!   [`write_country_rows`]. A call is one [`Rebuild`](crate::Rebuild), so none of this is
+//!   seen by a reader until all of it is, and a call that fails changes nothing.
 //!
 //! A run therefore has to derive the whole dataset, which is the rule silver rebuilds already
-//! follow.
+//! follow — or the whole of a [`DateRange`] of it, through the `_within` writers, which treat
+//! the dates in range as the whole dataset and leave every other date alone.
 //!
 //! **Silver only**, though the layer below permits a gold dataset to be replaced too. What is
 //! written here is the silver format — WKB geometry with its metric twin, the CRS declared per
@@ -26,8 +30,10 @@
 //! produces is a claim withdrawn. Gold states neither: its format is the consumer's, and its
 //! outputs are versioned per run rather than replaced, precisely so an earlier one survives.
 
-use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::marker::PhantomData;
 
 use arrow::array::RecordBatch;
 use chrono::NaiveDate;
@@ -37,8 +43,10 @@ use crate::geo::{
     GEOMETRY, PROJECTED_GEOMETRY, Projector, geo_batch, projected_wkb_field, wkb_field,
 };
 use crate::layer::layers;
-use crate::path::{Replaced, Root};
-use crate::rows::{Dated, Row, batch};
+use crate::path::{Dataset, ReplaceError, Replaced, Root};
+use crate::range::DateRange;
+use crate::rebuild::Rebuild;
+use crate::rows::{Dated, Geometry, Row, batch};
 use crate::table::{
     Layout, SilverTarget, TableError, TableWritten, check_unique, group, replace_dates,
 };
@@ -59,8 +67,8 @@ pub struct GeoRow<R, G> {
 
 /// Write `rows` as the whole of the dataset they belong to, replacing what is there.
 ///
-/// Each partition holds the rows given for it in the order they were given, and a partition
-/// the rows no longer cover is deleted.
+/// Each partition holds the rows given for it, sorted by where they are as every silver
+/// GeoParquet file is, and a partition the rows no longer cover is deleted.
 pub async fn write_geo_rows<R, G>(
     root: &Root,
     rows: &[GeoRow<R, G>],
@@ -68,54 +76,231 @@ pub async fn write_geo_rows<R, G>(
 where
     R: Dated<Layer = layers::Silver> + Clone,
     G: geo_traits::GeometryTrait<T = f64> + geo::MapCoords<f64, f64, Output = G> + Clone,
+{
+    write_geo_rows_within(root, rows, DateRange::ALL).await
+}
+
+/// Write `rows` as the whole of the dates in `range`, replacing what is there and leaving
+/// every date outside it alone.
+///
+/// A row dated outside the range is not written: a run reading a margin around its range —
+/// to see a session that began the evening before, say — derives rows it has no business
+/// replacing, and those belong to whichever run covers their date. [`TableWritten::rows`]
+/// counts the rows that were written. A date in range the rows do not cover is deleted, in
+/// every country, including a country no row is in.
+pub async fn write_geo_rows_within<R, G>(
+    root: &Root,
+    rows: &[GeoRow<R, G>],
+    range: DateRange,
+) -> Result<TableWritten, TableError>
+where
+    R: Dated<Layer = layers::Silver> + Clone,
+    G: geo_traits::GeometryTrait<T = f64> + geo::MapCoords<f64, f64, Output = G> + Clone,
+{
+    let mut writer = GeoRowWriter::open(root, range).await?;
+    let written = writer.write(rows).await;
+    writer.conclude(written).await
+}
+
+/// Rows of a dataset carrying geometry, written as the whole of the dates in a range a piece
+/// at a time, so a run deriving them never holds more than the piece in hand.
+///
+/// Each [`write`](Self::write) stages the rows it is given as one file per country and date
+/// they fall on, beside whatever the pieces before it staged there; the partitions no piece
+/// wrote are swept as the writer [`finish`](Self::finish)es. It is one
+/// [`Rebuild`](crate::Rebuild) throughout, so a reader sees none of the pieces until it has
+/// all of them, and a writer that is [abandoned](Self::abandon) changes nothing.
+///
+/// A partition holds a file per piece that reached it, rather than one: a run writing a
+/// device at a time writes a file per device and day. Uniqueness is checked within a piece,
+/// so the pieces have to be ones no name spans — a session and its samples belong to one
+/// device, for example.
+#[must_use = "the rows are seen by no reader until the writer is finished"]
+pub struct GeoRowWriter<R, G> {
+    target: SilverTarget,
+    dataset: Dataset<layers::Silver>,
+    range: DateRange,
+    rebuild: Rebuild<layers::Silver>,
+    /// One projector per country, built once: constructing it is the expensive part, and a
+    /// country's zone is the same in every partition below it.
+    projectors: HashMap<Country, Projector>,
+    /// The dates written so far in each country.
+    written: HashMap<Country, HashSet<NaiveDate>>,
+    rows: usize,
+    piece: PhantomData<fn(&GeoRow<R, G>)>,
+}
+
+impl<R, G> GeoRowWriter<R, G>
+where
+    R: Dated<Layer = layers::Silver> + Clone,
+    G: geo_traits::GeometryTrait<T = f64> + geo::MapCoords<f64, f64, Output = G> + Clone,
+{
+    /// Start writing the dates in `range` of the dataset `R` belongs to.
+    pub async fn open(root: &Root, range: DateRange) -> Result<Self, TableError> {
+        let target = SilverTarget::of::<R>()?;
+        let Layout::CountryAndDate(_) = target.layout()? else {
+            return Err(TableError::GeometryUnexpected {
+                dataset: target.name(),
+            });
+        };
+        let dataset = root.dataset(target.spec()).within(range)?;
+        let rebuild = dataset.rebuild().await?;
+        Ok(Self {
+            target,
+            dataset,
+            range,
+            rebuild,
+            projectors: HashMap::new(),
+            written: HashMap::new(),
+            rows: 0,
+            piece: PhantomData,
+        })
+    }
+
+    /// Stage `rows` beside what the pieces before them staged, leaving out any dated
+    /// outside the range, as [`write_geo_rows_within`] does.
+    pub async fn write(&mut self, rows: &[GeoRow<R, G>]) -> Result<(), TableError> {
+        let range = self.range;
+        let rows: Vec<&GeoRow<R, G>> = rows
+            .iter()
+            .filter(|placed| range.contains(placed.row.partition_date()))
+            .collect();
+        check_named(&self.target, rows.iter().map(|placed| &placed.row))?;
+
+        // One batch per country and date, since that pair names a partition. Grouped rather
+        // than chunked, so the rows need not arrive in any particular order to land in one
+        // file each.
+        let keys: Vec<(Country, NaiveDate)> = rows
+            .iter()
+            .map(|placed| (placed.country, placed.row.partition_date()))
+            .collect();
+        for ((country, date), indices) in group(&keys) {
+            let projector = match self.projectors.entry(country) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(Projector::for_country(country)?),
+            };
+            let day: Vec<&GeoRow<R, G>> = indices.iter().map(|row| rows[*row as usize]).collect();
+            let batch = geo_day(&day, projector, country)?;
+            let partition = self
+                .dataset
+                .clone()
+                .partition(COUNTRY, country)?
+                .on_date(date)?;
+            self.rebuild.add_geo(&partition, &[batch]).await?;
+            self.written.entry(country).or_default().insert(date);
+        }
+        self.rows += rows.len();
+        Ok(())
+    }
+
+    /// Sweep the partitions no piece wrote, and publish the lot.
+    pub async fn finish(mut self) -> Result<TableWritten, TableError> {
+        let partitions = self.sweep();
+        let partitions = self.rebuild.conclude(partitions).await?;
+        Ok(TableWritten {
+            rows: self.rows,
+            partitions,
+        })
+    }
+
+    /// Give up, deleting what the pieces staged and leaving readers where they were.
+    pub async fn abandon(self) -> Result<(), TableError> {
+        Ok(self.rebuild.abandon().await?)
+    }
+
+    /// Finish if `result` says the run writing the pieces succeeded, and abandon if not,
+    /// passing its failure on — with the abandon's beside it if that fails too, as
+    /// [`Rebuild::conclude`](crate::Rebuild::conclude) does.
+    pub async fn conclude<E>(self, result: Result<(), E>) -> Result<TableWritten, E>
+    where
+        E: From<TableError> + Display,
+    {
+        match result {
+            Ok(()) => Ok(self.finish().await?),
+            Err(err) => match self.rebuild.abandon().await {
+                Ok(()) => Err(err),
+                Err(abandon) => {
+                    Err(TableError::from(ReplaceError::abandoned(&err, abandon)).into())
+                }
+            },
+        }
+    }
+
+    /// Withdraw the dates no piece wrote in each country written, and the countries none
+    /// was.
+    fn sweep(&mut self) -> Result<Replaced, TableError> {
+        let mut partitions = Replaced::default();
+        for (country, dates) in &self.written {
+            let within = self.dataset.clone().partition(COUNTRY, *country)?;
+            let dates: Vec<NaiveDate> = dates.iter().copied().collect();
+            partitions.written += dates.len();
+            partitions.removed +=
+                self.rebuild
+                    .retain_partitions(&within, within.own_key()?, &dates)?;
+        }
+
+        // The dated partitions of a country are swept within it; the countries themselves
+        // can only be swept here, where every one the pieces covered is known.
+        let derived: Vec<Country> = self.written.keys().copied().collect();
+        partitions.removed += self
+            .rebuild
+            .retain_partitions(&self.dataset, COUNTRY, &derived)?;
+        Ok(partitions)
+    }
+}
+
+/// Write `rows` as the whole of the dataset they belong to, for reference-derived geometry laid
+/// out by country alone — one file per country, and no date below it, since such a row
+/// describes a place rather than something that happened on a day.
+///
+/// Projects, replaces and sweeps as [`write_geo_rows`] does: a country the rows no longer cover
+/// is deleted.
+pub async fn write_country_rows<R, G>(
+    root: &Root,
+    rows: &[GeoRow<R, G>],
+) -> Result<TableWritten, TableError>
+where
+    R: Row<Layer = layers::Silver> + Clone,
+    G: geo_traits::GeometryTrait<T = f64> + geo::MapCoords<f64, f64, Output = G> + Clone,
 {
     let target = SilverTarget::of::<R>()?;
-    let Layout::CountryAndDate(_) = target.layout()? else {
+    match target.layout()? {
+        Layout::Country => {}
+        Layout::Date(key) | Layout::CountryAndDate(key) => {
+            return Err(TableError::UnsupportedLayout {
+                dataset: target.name(),
+                key: key.to_string(),
+            });
+        }
+    }
+    if target.geometry == Geometry::Absent {
         return Err(TableError::GeometryUnexpected {
             dataset: target.name(),
         });
-    };
+    }
     check_named(&target, rows.iter().map(|placed| &placed.row))?;
 
-    // One batch per country and date, since that pair names a partition. Grouped rather than
-    // chunked, so the rows need not arrive in any particular order to land in one file each.
-    let keys: Vec<(Country, NaiveDate)> = rows
-        .iter()
-        .map(|placed| (placed.country, placed.row.partition_date()))
-        .collect();
-    let mut projectors: HashMap<Country, Projector> = HashMap::new();
-    let mut by_country: Vec<(Country, Vec<(NaiveDate, RecordBatch)>)> = Vec::new();
-
-    for ((country, date), indices) in group(&keys) {
-        // One projector per country, built once: constructing it is the expensive part, and
-        // a country's zone is the same in every partition below it.
-        let projector = match projectors.entry(country) {
-            Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(entry) => entry.insert(Projector::for_country(country)?),
-        };
-        let day: Vec<&GeoRow<R, G>> = indices.iter().map(|row| &rows[*row as usize]).collect();
-        let batch = geo_day(&day, projector, country)?;
-
-        match by_country.iter_mut().find(|(each, _)| *each == country) {
-            Some((_, days)) => days.push((date, batch)),
-            None => by_country.push((country, vec![(date, batch)])),
+    let countries: Vec<Country> = rows.iter().map(|placed| placed.country).collect();
+    let by_country = group(&countries);
+    let dataset = root.dataset(target.spec());
+    let mut rebuild = dataset.rebuild().await?;
+    let partitions = async {
+        let mut partitions = Replaced::default();
+        for (country, indices) in &by_country {
+            let placed: Vec<&GeoRow<R, G>> =
+                indices.iter().map(|row| &rows[*row as usize]).collect();
+            let batch = geo_day(&placed, &Projector::for_country(*country)?, *country)?;
+            let partition = dataset.clone().partition(COUNTRY, *country)?;
+            rebuild.replace_with_geo(&partition, &[batch]).await?;
+            partitions.written += 1;
         }
-    }
 
-    let mut partitions = Replaced::default();
-    for (country, days) in &by_country {
-        let dataset = root.dataset(target.spec()).partition(COUNTRY, *country)?;
-        partitions += replace_dates(&dataset, &target, days).await?;
+        let derived: Vec<Country> = by_country.iter().map(|(country, _)| *country).collect();
+        partitions.removed += rebuild.retain_partitions(&dataset, COUNTRY, &derived)?;
+        Ok::<_, TableError>(partitions)
     }
-
-    // The dated partitions of a country are swept as that country is written, which is the
-    // level `replace_dates` is given; the countries themselves can only be swept here, where
-    // every one the rows cover is known.
-    let derived: Vec<Country> = by_country.iter().map(|(country, _)| *country).collect();
-    partitions.removed += root
-        .dataset(target.spec())
-        .retain_partitions(COUNTRY, &derived)
-        .await?;
+    .await;
+    let partitions = rebuild.conclude(partitions).await?;
 
     Ok(TableWritten {
         rows: rows.len(),
@@ -127,6 +312,20 @@ where
 /// geometry — dated partitions and nothing above them. Replaces and sweeps as
 /// [`write_geo_rows`] does.
 pub async fn write_rows<R>(root: &Root, rows: &[R]) -> Result<TableWritten, TableError>
+where
+    R: Dated<Layer = layers::Silver> + Clone,
+{
+    write_rows_within(root, rows, DateRange::ALL).await
+}
+
+/// Write `rows` as the whole of the dates in `range`, for a dataset carrying no geometry.
+/// Leaves out rows dated outside the range and sweeps only within it, as
+/// [`write_geo_rows_within`] does.
+pub async fn write_rows_within<R>(
+    root: &Root,
+    rows: &[R],
+    range: DateRange,
+) -> Result<TableWritten, TableError>
 where
     R: Dated<Layer = layers::Silver> + Clone,
 {
@@ -136,6 +335,11 @@ where
             dataset: target.name(),
         });
     };
+    let rows: Vec<R> = rows
+        .iter()
+        .filter(|row| range.contains(row.partition_date()))
+        .cloned()
+        .collect();
     check_named(&target, rows.iter())?;
 
     let dates: Vec<NaiveDate> = rows.iter().map(Dated::partition_date).collect();
@@ -150,7 +354,10 @@ where
         })
         .collect::<Result<Vec<_>, TableError>>()?;
 
-    let partitions = replace_dates(&root.dataset(target.spec()), &target, &days).await?;
+    let dataset = root.dataset(target.spec()).within(range)?;
+    let mut rebuild = dataset.rebuild().await?;
+    let partitions = replace_dates(&mut rebuild, &dataset, &target, &days).await;
+    let partitions = rebuild.conclude(partitions).await?;
     Ok(TableWritten {
         rows: rows.len(),
         partitions,
@@ -194,7 +401,7 @@ fn check_named<'a, R: Row + Clone + 'a>(
     target: &SilverTarget,
     rows: impl Iterator<Item = &'a R>,
 ) -> Result<(), TableError> {
-    if R::UNIQUE.is_empty() {
+    if R::UNIQUE.is_empty() && R::UNIQUE_TOGETHER.is_empty() {
         return Ok(());
     }
     let rows: Vec<R> = rows.cloned().collect();
@@ -210,7 +417,6 @@ mod tests {
     use super::*;
     use crate::dataset::DatasetSpec;
     use crate::query::Query;
-    use crate::rows::Geometry;
 
     /// Dated geometry: a country partition above the date, since the file states one CRS.
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -255,6 +461,41 @@ mod tests {
         }
     }
 
+    /// Dated rows identified by a pair of columns, neither of which names a row on its own.
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    struct CallRow {
+        trip_id: String,
+        stop_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        called_at: DateTime<Utc>,
+    }
+
+    impl Row for CallRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("call", "called_date");
+        const INSTANTS: &'static [&'static str] = &["called_at"];
+        const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[&["trip_id", "stop_id"]];
+    }
+
+    impl Dated for CallRow {
+        fn partition_date(&self) -> NaiveDate {
+            self.called_at.date_naive()
+        }
+    }
+
+    /// Reference-derived geometry: a place, one file per country and no date below it.
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    struct PlaceRow {
+        place_id: String,
+    }
+
+    impl Row for PlaceRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("place", COUNTRY);
+        const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
+        const UNIQUE: &'static [&'static str] = &["place_id"];
+    }
+
     fn at(day: u32) -> DateTime<Utc> {
         Utc.with_ymd_and_hms(2026, 7, day, 9, 0, 0).unwrap()
     }
@@ -276,6 +517,21 @@ mod tests {
         }
     }
 
+    fn place(id: &str, country: Country) -> GeoRow<PlaceRow, Point<f64>> {
+        GeoRow {
+            row: PlaceRow {
+                place_id: id.to_string(),
+            },
+            geometry: berlin(),
+            country,
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Named {
+        track_id: String,
+    }
+
     fn pass(id: &str, day: u32) -> PassRow {
         PassRow {
             track_id: id.to_string(),
@@ -291,8 +547,8 @@ mod tests {
         let written = write_geo_rows(
             &root,
             &[
-                track("a", 21, Country::Germany),
-                track("b", 22, Country::Germany),
+                track("a", 21, Country::GERMANY),
+                track("b", 22, Country::GERMANY),
             ],
         )
         .await
@@ -300,10 +556,15 @@ mod tests {
 
         assert_eq!(written.rows, 2);
         assert_eq!(written.partitions.written, 2);
-        assert!(
-            tmp.path()
-                .join("silver/track/country=DE/seen_date=2026-07-21/part-0.parquet")
-                .exists()
+        assert_eq!(
+            std::fs::read_dir(
+                tmp.path()
+                    .join("silver/track/country=DE/seen_date=2026-07-21")
+            )
+            .unwrap()
+            .count(),
+            1,
+            "one file per partition"
         );
     }
 
@@ -318,9 +579,9 @@ mod tests {
         let written = write_geo_rows(
             &root,
             &[
-                track("a", 21, Country::Germany),
-                track("b", 22, Country::Germany),
-                track("c", 21, Country::Germany),
+                track("a", 21, Country::GERMANY),
+                track("b", 22, Country::GERMANY),
+                track("c", 21, Country::GERMANY),
             ],
         )
         .await
@@ -338,13 +599,66 @@ mod tests {
         );
     }
 
+    /// A run written a piece at a time is one run: the pieces sharing a partition sit beside
+    /// each other in it, a date no piece reached is swept, and none of it is read until the
+    /// writer is finished.
+    #[tokio::test]
+    async fn pieces_written_apart_are_published_together() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, &[track("old", 23, Country::GERMANY)])
+            .await
+            .unwrap();
+
+        let mut writer = GeoRowWriter::open(&root, DateRange::ALL).await.unwrap();
+        writer
+            .write(&[track("a", 21, Country::GERMANY)])
+            .await
+            .unwrap();
+        writer
+            .write(&[
+                track("b", 21, Country::GERMANY),
+                track("c", 22, Country::GERMANY),
+            ])
+            .await
+            .unwrap();
+        let query = Query::new(root.clone());
+        query.register(TrackRow::DATASET, "track").await.unwrap();
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM track")
+                .await
+                .unwrap(),
+            1
+        );
+
+        let written = writer.finish().await.unwrap();
+
+        assert_eq!(written.rows, 3);
+        assert_eq!(written.partitions.written, 2);
+        assert_eq!(written.partitions.removed, 1);
+        let query = Query::new(root);
+        query.register(TrackRow::DATASET, "track").await.unwrap();
+        let named: Vec<Named> = query
+            .rows("SELECT track_id FROM track WHERE seen_date = '2026-07-21' ORDER BY track_id")
+            .await
+            .unwrap();
+        let named: Vec<&str> = named.iter().map(|row| row.track_id.as_str()).collect();
+        assert_eq!(named, ["a", "b"]);
+        assert!(
+            !tmp.path()
+                .join("silver/track/country=DE/seen_date=2026-07-23")
+                .exists()
+        );
+    }
+
     /// The metric column is projected here rather than supplied, so it is in the zone the
     /// file declares for the country the row states.
     #[tokio::test]
     async fn the_projected_column_holds_the_countrys_metres() {
         let tmp = tempfile::tempdir().unwrap();
         let root = Root::new(tmp.path());
-        write_geo_rows(&root, &[track("a", 21, Country::Germany)])
+        write_geo_rows(&root, &[track("a", 21, Country::GERMANY)])
             .await
             .unwrap();
 
@@ -379,8 +693,8 @@ mod tests {
         let err = write_geo_rows(
             &root,
             &[
-                track("a", 21, Country::Germany),
-                track("a", 22, Country::Germany),
+                track("a", 21, Country::GERMANY),
+                track("a", 22, Country::GERMANY),
             ],
         )
         .await
@@ -405,6 +719,33 @@ mod tests {
         assert!(matches!(err, TableError::Duplicate { .. }), "{err}");
     }
 
+    /// A key of several columns is repeated only by a row sharing every one of them, so rows
+    /// sharing a trip but not a stop are written, and a second call at the same stop is not.
+    #[tokio::test]
+    async fn rows_sharing_every_column_of_a_key_are_refused() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let call = |trip: &str, stop: &str, day: u32| CallRow {
+            trip_id: trip.to_string(),
+            stop_id: stop.to_string(),
+            called_at: at(day),
+        };
+
+        let written = write_rows(&root, &[call("t", "a", 21), call("t", "b", 21)])
+            .await
+            .unwrap();
+        assert_eq!(written.rows, 2);
+
+        let err = write_rows(&root, &[call("t", "a", 21), call("t", "a", 22)])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, TableError::Duplicate { column, value, first: 0, second: 1, .. }
+                if column == "(trip_id, stop_id)" && value == "(t, a)"),
+            "{err:?}"
+        );
+    }
+
     #[tokio::test]
     async fn a_date_the_rows_no_longer_cover_is_swept() {
         let tmp = tempfile::tempdir().unwrap();
@@ -456,7 +797,7 @@ mod tests {
             &[GeoRow {
                 row: pass("a", 21),
                 geometry: berlin(),
-                country: Country::Germany,
+                country: Country::GERMANY,
             }],
         )
         .await
@@ -474,7 +815,7 @@ mod tests {
     async fn no_rows_sweep_what_is_there() {
         let tmp = tempfile::tempdir().unwrap();
         let root = Root::new(tmp.path());
-        write_geo_rows(&root, &[track("a", 21, Country::Germany)])
+        write_geo_rows(&root, &[track("a", 21, Country::GERMANY)])
             .await
             .unwrap();
 
@@ -486,4 +827,112 @@ mod tests {
         assert_eq!(written.partitions.removed, 1);
         assert!(!tmp.path().join("silver/track/country=DE").exists());
     }
+
+    #[tokio::test]
+    async fn places_land_in_one_file_per_country() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+
+        let written = write_country_rows(
+            &root,
+            &[place("a", Country::GERMANY), place("b", Country::GERMANY)],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(written.rows, 2);
+        assert_eq!(written.partitions.written, 1);
+        assert_eq!(
+            std::fs::read_dir(tmp.path().join("silver/place/country=DE"))
+                .unwrap()
+                .count(),
+            1,
+            "one file per partition"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_country_the_places_no_longer_cover_is_swept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_country_rows(&root, &[place("a", Country::GERMANY)])
+            .await
+            .unwrap();
+
+        let written = write_country_rows::<PlaceRow, Point<f64>>(&root, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(written.partitions.removed, 1);
+        assert!(!tmp.path().join("silver/place/country=DE").exists());
+    }
+
+    fn days(from: u32, to: u32) -> DateRange {
+        DateRange::new(Some(at(from).date_naive()), Some(at(to).date_naive())).unwrap()
+    }
+
+    /// A run over a range is the whole of that range: it replaces and sweeps the dates in
+    /// it, and neither writes nor removes anything dated outside it.
+    #[tokio::test]
+    async fn a_range_is_replaced_and_swept_as_though_it_were_the_dataset() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_rows(&root, &[pass("a", 21), pass("b", 22), pass("c", 23)])
+            .await
+            .unwrap();
+
+        let written = write_rows_within(&root, &[pass("d", 22), pass("e", 21)], days(22, 23))
+            .await
+            .unwrap();
+
+        assert_eq!(written.rows, 1);
+        assert_eq!(written.partitions.written, 1);
+        assert_eq!(written.partitions.removed, 1);
+        let query = Query::new(root.clone());
+        query.register(PassRow::DATASET, "pass").await.unwrap();
+        let ids: Vec<Named> = query
+            .rows("SELECT track_id FROM pass ORDER BY track_id")
+            .await
+            .unwrap();
+        let ids: Vec<&str> = ids.iter().map(|named| named.track_id.as_str()).collect();
+        assert_eq!(ids, ["a", "d"]);
+    }
+
+    /// A country nothing in range is placed in keeps its dates outside the range; only the
+    /// ones inside it are the run's to withdraw.
+    #[tokio::test]
+    async fn a_range_sweeps_its_dates_from_a_country_no_row_is_in() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(
+            &root,
+            &[
+                track("a", 21, Country::GERMANY),
+                track("b", 22, Country::GERMANY),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let written = write_geo_rows_within::<TrackRow, LineString<f64>>(&root, &[], days(22, 22))
+            .await
+            .unwrap();
+
+        assert_eq!(written.partitions.removed, 1);
+        let country = tmp.path().join("silver/track/country=DE");
+        assert!(country.join("seen_date=2026-07-21").exists());
+        assert!(!country.join("seen_date=2026-07-22").exists());
+    }
+
+    /// Dated rows written as places would lose the date they are partitioned on.
+    #[tokio::test]
+    async fn dated_geometry_is_not_written_as_places() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let err = write_country_rows(&Root::new(tmp.path()), &[track("a", 21, Country::GERMANY)])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TableError::UnsupportedLayout { .. }), "{err}");
+    }
 }
@@ -0,0 +1,956 @@
+//! Comparing what two stores hold of the same dataset, partition by partition.
+//!
+//! A derivation rerun after a code change replaces silver wholesale, and what it changed is
+//! then a question about two stores rather than about one: the store as it was, copied aside,
+//! and the store the rerun left. This answers it the way a reader would see it — the files a
+//! reader reads of each, a partition at a time — and says which rows one holds that the other
+//! does not, and of the rows both hold, which columns differ.
+//!
+//! A row is matched across the two by the first key its dataset declares — a unique column,
+//! or failing one a set of columns unique together — which identifies it; a row whose key is
+//! in both and whose values are not is a change rather than a removal and an addition. A
+//! dataset declaring no key is compared as a multiset of whole rows, so it can only gain and
+//! lose them.
+//!
+//! A geometry column is reported by how far its geometries moved rather than by its bytes:
+//! two encodings of one path can differ in bytes, and a path that moved by a millimetre is a
+//! different answer to a question from one that moved by a kilometre. The distance is the
+//! Hausdorff distance between the two, in the column's own units — metres for the projected
+//! column, degrees for the lat/lon one.
+//!
+//! Rerunning a derivation over inputs that have not changed has to leave nothing to report,
+//! which makes an empty [`Diff`] the check that a derivation is deterministic.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use arrow::array::{Array, ArrayRef, RecordBatch};
+use arrow::datatypes::DataType;
+use arrow::row::{RowConverter, SortField};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use geo::HausdorffDistance;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use crate::compact::{ListingError, is_parquet, live_partitions_where};
+use crate::geo::{GEOMETRY, PROJECTED_GEOMETRY, geometries};
+use crate::layer::Layer;
+use crate::path::Root;
+use crate::rebuild::Published;
+use crate::rows::{Geometry, key_name, keys};
+use crate::verify::Declared;
+
+/// A failure reading either store, as distinct from a difference between them.
+#[derive(Debug, thiserror::Error)]
+pub enum DiffError {
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error("reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{path}: unreadable: {reason}")]
+    Unreadable { path: String, reason: String },
+    #[error("comparing {column}: {reason}")]
+    Compare { column: String, reason: String },
+}
+
+/// Which of the two stores compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Before,
+    After,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Before => write!(f, "before"),
+            Side::After => write!(f, "after"),
+        }
+    }
+}
+
+/// What comparing one dataset across two stores found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub layer: Layer,
+    pub name: &'static str,
+    /// The columns rows were matched on, or `None` where the dataset declares no key.
+    pub key: Option<&'static [&'static str]>,
+    /// Every partition that differs, in path order.
+    pub partitions: Vec<PartitionDiff>,
+    /// Partitions both stores hold the same rows in.
+    pub unchanged: usize,
+}
+
+impl Diff {
+    /// Whether the two stores hold the same rows of the dataset.
+    pub fn is_empty(&self) -> bool {
+        self.partitions.is_empty()
+    }
+}
+
+/// How one partition differs between the two stores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionDiff {
+    /// Its directory, relative to the dataset's; empty for a dataset with no partitions.
+    pub partition: PathBuf,
+    /// The one store that holds the partition at all, or `None` where both do.
+    pub only: Option<Side>,
+    /// Rows only the store after holds.
+    pub added: usize,
+    /// Rows only the store before holds.
+    pub removed: usize,
+    /// Rows both hold under one key, with values that differ. Always none unkeyed.
+    pub changed: usize,
+    /// How the changed rows differ, a column at a time, with the columns only one holds.
+    pub columns: Vec<ColumnDiff>,
+}
+
+impl PartitionDiff {
+    /// Whether the partition holds the same rows in both stores.
+    pub fn is_empty(&self) -> bool {
+        self.only.is_none()
+            && self.added == 0
+            && self.removed == 0
+            && self.changed == 0
+            && self.columns.is_empty()
+    }
+}
+
+/// How one column of a partition differs between the two stores.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnDiff {
+    /// A column one store's files hold and the other's do not.
+    Only { column: String, side: Side },
+    /// A column held as a different type in each, and so not compared.
+    Retyped {
+        column: String,
+        before: String,
+        after: String,
+    },
+    /// Values that differ between rows of one key: how many rows, and the first found.
+    Values {
+        column: String,
+        rows: usize,
+        key: String,
+        before: String,
+        after: String,
+    },
+    /// Geometries that moved: how many rows, and the furthest any of them moved.
+    Moved {
+        column: String,
+        rows: usize,
+        furthest: f64,
+    },
+}
+
+impl Display for ColumnDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnDiff::Only { column, side } => {
+                write!(f, "column `{column}` is only held {side}")
+            }
+            ColumnDiff::Retyped {
+                column,
+                before,
+                after,
+            } => write!(f, "column `{column}` was {before}, is {after}"),
+            ColumnDiff::Values {
+                column,
+                rows,
+                key,
+                before,
+                after,
+            } => write!(
+                f,
+                "column `{column}` differs in {} — {key}: {before} → {after}",
+                rows_of(*rows)
+            ),
+            ColumnDiff::Moved {
+                column,
+                rows,
+                furthest,
+            } => write!(
+                f,
+                "column `{column}` moved in {}, furthest {furthest:.3}{}",
+                rows_of(*rows),
+                unit_of(column)
+            ),
+        }
+    }
+}
+
+/// Compare what `before` and `after` hold of `declared`'s dataset.
+pub async fn dataset(before: &Root, after: &Root, declared: &Declared) -> Result<Diff, DiffError> {
+    let info = declared.dataset;
+    let key = keys(declared.unique, declared.unique_together).next();
+    let geometry: &[&str] = match declared.geometry {
+        Geometry::Absent => &[],
+        Geometry::LatLonAndProjected => &[GEOMETRY, PROJECTED_GEOMETRY],
+    };
+
+    let held_before = live_files(before, declared).await?;
+    let held_after = live_files(after, declared).await?;
+    let partitions: BTreeSet<&PathBuf> = held_before.keys().chain(held_after.keys()).collect();
+
+    let mut diff = Diff {
+        layer: info.layer,
+        name: info.name,
+        key,
+        partitions: Vec::new(),
+        unchanged: 0,
+    };
+    for partition in partitions {
+        let rows_before = read(before, held_before.get(partition)).await?;
+        let rows_after = read(after, held_after.get(partition)).await?;
+        let mut compared = compare(&rows_before, &rows_after, key, geometry)?;
+        compared.partition = partition.clone();
+        compared.only = match (
+            held_before.contains_key(partition),
+            held_after.contains_key(partition),
+        ) {
+            (true, false) => Some(Side::Before),
+            (false, true) => Some(Side::After),
+            _ => None,
+        };
+        match compared.is_empty() {
+            true => diff.unchanged += 1,
+            false => diff.partitions.push(compared),
+        }
+    }
+    Ok(diff)
+}
+
+/// The files a reader reads of the dataset in `root`, by the partition directory they sit
+/// in, relative to the dataset's.
+async fn live_files(
+    root: &Root,
+    declared: &Declared,
+) -> Result<BTreeMap<PathBuf, Vec<PathBuf>>, DiffError> {
+    let backend = root.backend();
+    let dir = root
+        .path()
+        .join(declared.dataset.layer.as_str())
+        .join(declared.dataset.name);
+    let files: Vec<PathBuf> = match Published::read(backend, &dir).await? {
+        Some(published) => published.files().iter().cloned().collect(),
+        None => live_partitions_where(backend, &dir, &|_, _| true)
+            .await?
+            .into_iter()
+            .flat_map(|partition| partition.files)
+            .collect(),
+    };
+
+    let mut by_partition: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for file in files.into_iter().filter(|file| is_parquet(file)) {
+        let partition = file
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&dir).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        by_partition.entry(partition).or_default().push(file);
+    }
+    Ok(by_partition)
+}
+
+/// Every row of `files`, or none where the store does not hold the partition.
+async fn read(root: &Root, files: Option<&Vec<PathBuf>>) -> Result<Vec<RecordBatch>, DiffError> {
+    let mut batches = Vec::new();
+    for path in files.into_iter().flatten() {
+        let unreadable = |reason: String| DiffError::Unreadable {
+            path: path.display().to_string(),
+            reason,
+        };
+        let bytes = root
+            .backend()
+            .read(path)
+            .await
+            .map_err(|source| DiffError::Io {
+                path: path.display().to_string(),
+                source,
+            })?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)
+            .and_then(|builder| builder.build())
+            .map_err(|err| unreadable(err.to_string()))?;
+        for batch in reader {
+            batches.push(batch.map_err(|err| unreadable(err.to_string()))?);
+        }
+    }
+    Ok(batches)
+}
+
+/// The columns every batch of `batches` holds, with their types, in the order the first
+/// holds them.
+fn columns_of(batches: &[RecordBatch]) -> Vec<(String, DataType)> {
+    let Some(first) = batches.first() else {
+        return Vec::new();
+    };
+    first
+        .schema()
+        .fields()
+        .iter()
+        .filter(|field| {
+            batches.iter().all(|batch| {
+                batch
+                    .schema()
+                    .field_with_name(field.name())
+                    .is_ok_and(|other| other.data_type() == field.data_type())
+            })
+        })
+        .map(|field| (field.name().clone(), field.data_type().clone()))
+        .collect()
+}
+
+/// How the rows of one partition differ, before and after.
+fn compare(
+    before: &[RecordBatch],
+    after: &[RecordBatch],
+    key: Option<&[&str]>,
+    geometry: &[&str],
+) -> Result<PartitionDiff, DiffError> {
+    let mut diff = PartitionDiff {
+        partition: PathBuf::new(),
+        only: None,
+        added: 0,
+        removed: 0,
+        changed: 0,
+        columns: Vec::new(),
+    };
+
+    // A side holding no rows says nothing of its columns, so only rows can differ.
+    let columns_before = columns_of(before);
+    let columns_after = columns_of(after);
+    let mut shared: Vec<(String, DataType)> = Vec::new();
+    if !before.is_empty() && !after.is_empty() {
+        for (column, before_type) in &columns_before {
+            match columns_after.iter().find(|(other, _)| other == column) {
+                None => diff.columns.push(ColumnDiff::Only {
+                    column: column.clone(),
+                    side: Side::Before,
+                }),
+                Some((_, after_type)) if after_type != before_type => {
+                    diff.columns.push(ColumnDiff::Retyped {
+                        column: column.clone(),
+                        before: before_type.to_string(),
+                        after: after_type.to_string(),
+                    })
+                }
+                Some(_) => shared.push((column.clone(), before_type.clone())),
+            }
+        }
+        for (column, _) in &columns_after {
+            if !columns_before.iter().any(|(other, _)| other == column) {
+                diff.columns.push(ColumnDiff::Only {
+                    column: column.clone(),
+                    side: Side::After,
+                });
+            }
+        }
+    }
+
+    let rows_before: usize = before.iter().map(RecordBatch::num_rows).sum();
+    let rows_after: usize = after.iter().map(RecordBatch::num_rows).sum();
+    if before.is_empty() || after.is_empty() {
+        diff.added = rows_after;
+        diff.removed = rows_before;
+        return Ok(diff);
+    }
+
+    let table = Table::new(&shared)?;
+    let held = |key: &&[&str]| {
+        key.iter()
+            .all(|key| shared.iter().any(|(column, _)| column == key))
+    };
+    match key.filter(held) {
+        Some(key) => compare_keyed(&table, before, after, key, geometry, &mut diff)?,
+        None => {
+            // Unkeyed, a row is only ever there or not: count each whole row up for the store
+            // before and down for the one after, and what is left over is what differs.
+            let mut held: HashMap<Vec<u8>, isize> = HashMap::new();
+            for (batches, step) in [(before, 1), (after, -1)] {
+                for batch in batches {
+                    let rows = table.rows(batch)?;
+                    for row in rows.iter() {
+                        *held.entry(row.as_ref().to_vec()).or_default() += step;
+                    }
+                }
+            }
+            for count in held.values() {
+                match count.signum() {
+                    1 => diff.removed += count.unsigned_abs(),
+                    -1 => diff.added += count.unsigned_abs(),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(diff)
+}
+
+/// Compare rows matched on `key`, counting what each store holds alone into `diff` and
+/// describing the changed rows a column at a time.
+fn compare_keyed(
+    table: &Table,
+    before: &[RecordBatch],
+    after: &[RecordBatch],
+    key: &[&str],
+    geometry: &[&str],
+    diff: &mut PartitionDiff,
+) -> Result<(), DiffError> {
+    let keyed_before = table.keyed(before, key)?;
+    let keyed_after = table.keyed(after, key)?;
+    let mut columns: Vec<ColumnDiff> = Vec::new();
+
+    for (id, (whole, at)) in &keyed_before {
+        let Some((whole_after, at_after)) = keyed_after.get(id) else {
+            diff.removed += 1;
+            continue;
+        };
+        if whole == whole_after {
+            continue;
+        }
+        diff.changed += 1;
+
+        let row_before = before[at.0].slice(at.1, 1);
+        let row_after = after[at_after.0].slice(at_after.1, 1);
+        for (column, _) in &table.columns {
+            let value_before = column_of(&row_before, column)?;
+            let value_after = column_of(&row_after, column)?;
+            let (encoded_before, encoded_after) = (
+                table.value(column, &value_before)?,
+                table.value(column, &value_after)?,
+            );
+            if encoded_before.row(0) == encoded_after.row(0) {
+                continue;
+            }
+            match geometry.contains(&column.as_str()) {
+                true => {
+                    let moved = moved(&row_before, &row_after, column)?;
+                    match find(&mut columns, column) {
+                        Some(ColumnDiff::Moved { rows, furthest, .. }) => {
+                            *rows += 1;
+                            *furthest = furthest.max(moved);
+                        }
+                        _ => columns.push(ColumnDiff::Moved {
+                            column: column.clone(),
+                            rows: 1,
+                            furthest: moved,
+                        }),
+                    }
+                }
+                false => match find(&mut columns, column) {
+                    Some(ColumnDiff::Values { rows, .. }) => *rows += 1,
+                    _ => columns.push(ColumnDiff::Values {
+                        column: column.clone(),
+                        rows: 1,
+                        key: shown_key(&row_before, key)?,
+                        before: shown(&value_before, column)?,
+                        after: shown(&value_after, column)?,
+                    }),
+                },
+            }
+        }
+    }
+    diff.added += keyed_after
+        .keys()
+        .filter(|id| !keyed_before.contains_key(*id))
+        .count();
+
+    // In the order the dataset holds its columns, rather than the order rows turned them up.
+    columns.sort_by_key(|changed| {
+        table
+            .columns
+            .iter()
+            .position(|(column, _)| column == column_named(changed))
+    });
+    diff.columns.extend(columns);
+    Ok(())
+}
+
+/// The columns two stores' rows are compared on, and the encoders that turn their values into
+/// bytes that are equal exactly when the values are.
+struct Table {
+    columns: Vec<(String, DataType)>,
+    whole: RowConverter,
+    each: HashMap<String, RowConverter>,
+}
+
+/// Each row of a side under its key: the bytes of the whole row, and the batch and row it is.
+type Keyed = HashMap<Vec<u8>, (Vec<u8>, (usize, usize))>;
+
+impl Table {
+    fn new(columns: &[(String, DataType)]) -> Result<Self, DiffError> {
+        let converter = |types: Vec<SortField>, column: &str| {
+            RowConverter::new(types).map_err(|err| DiffError::Compare {
+                column: column.to_string(),
+                reason: err.to_string(),
+            })
+        };
+        let mut each = HashMap::new();
+        for (column, data_type) in columns {
+            each.insert(
+                column.clone(),
+                converter(vec![SortField::new(data_type.clone())], column)?,
+            );
+        }
+        Ok(Self {
+            columns: columns.to_vec(),
+            whole: converter(
+                columns
+                    .iter()
+                    .map(|(_, data_type)| SortField::new(data_type.clone()))
+                    .collect(),
+                "every column",
+            )?,
+            each,
+        })
+    }
+
+    /// Every row of `batch`, encoded whole.
+    fn rows(&self, batch: &RecordBatch) -> Result<arrow::row::Rows, DiffError> {
+        let arrays = self
+            .columns
+            .iter()
+            .map(|(column, _)| column_of(batch, column))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.whole
+            .convert_columns(&arrays)
+            .map_err(|err| DiffError::Compare {
+                column: "every column".to_string(),
+                reason: err.to_string(),
+            })
+    }
+
+    /// Every row of `batches` under its values of `key`'s columns. A key held twice keeps
+    /// its first row: the repeat is what `medallion verify` reports, not a change between
+    /// stores.
+    fn keyed(&self, batches: &[RecordBatch], key: &[&str]) -> Result<Keyed, DiffError> {
+        let mut keyed = Keyed::new();
+        for (index, batch) in batches.iter().enumerate() {
+            let whole = self.rows(batch)?;
+            let ids = key
+                .iter()
+                .map(|column| self.value(column, &column_of(batch, column)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            for row in 0..batch.num_rows() {
+                // Each column's encoding ends where the next begins, so joined they are one
+                // value for the key as a whole.
+                let id: Vec<u8> = ids
+                    .iter()
+                    .flat_map(|id| id.row(row).as_ref().to_vec())
+                    .collect();
+                keyed
+                    .entry(id)
+                    .or_insert_with(|| (whole.row(row).as_ref().to_vec(), (index, row)));
+            }
+        }
+        Ok(keyed)
+    }
+
+    /// The values of `array`, held in `column`, encoded.
+    fn value(&self, column: &str, array: &ArrayRef) -> Result<arrow::row::Rows, DiffError> {
+        let compare = |reason: String| DiffError::Compare {
+            column: column.to_string(),
+            reason,
+        };
+        self.each
+            .get(column)
+            .ok_or_else(|| compare("not a column both stores hold".to_string()))?
+            .convert_columns(std::slice::from_ref(array))
+            .map_err(|err| compare(err.to_string()))
+    }
+}
+
+/// `column` of `batch`.
+fn column_of(batch: &RecordBatch, column: &str) -> Result<ArrayRef, DiffError> {
+    batch
+        .column_by_name(column)
+        .cloned()
+        .ok_or_else(|| DiffError::Compare {
+            column: column.to_string(),
+            reason: "missing from a file".to_string(),
+        })
+}
+
+/// The key of the one row of `row`, as a change shows it: one value alone, several as a
+/// tuple.
+fn shown_key(row: &RecordBatch, key: &[&str]) -> Result<String, DiffError> {
+    let values = key
+        .iter()
+        .map(|column| shown(&column_of(row, column)?, column))
+        .collect::<Result<Vec<String>, DiffError>>()?;
+    Ok(key_name(
+        &values.iter().map(String::as_str).collect::<Vec<_>>(),
+    ))
+}
+
+/// The first value of `array`, as a report shows it.
+fn shown(array: &ArrayRef, column: &str) -> Result<String, DiffError> {
+    if array.is_null(0) {
+        return Ok("null".to_string());
+    }
+    let formatter =
+        ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default()).map_err(|err| {
+            DiffError::Compare {
+                column: column.to_string(),
+                reason: err.to_string(),
+            }
+        })?;
+    Ok(formatter.value(0).to_string())
+}
+
+/// How far the geometry in `column` moved between two one-row batches.
+fn moved(before: &RecordBatch, after: &RecordBatch, column: &str) -> Result<f64, DiffError> {
+    let geometry = |batch: &RecordBatch| {
+        geometries(batch, column)
+            .map_err(|err| DiffError::Compare {
+                column: column.to_string(),
+                reason: err.to_string(),
+            })?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DiffError::Compare {
+                column: column.to_string(),
+                reason: "no geometry".to_string(),
+            })
+    };
+    Ok(geometry(before)?.hausdorff_distance(&geometry(after)?))
+}
+
+/// The difference already found in `column`, if one has been.
+fn find<'a>(columns: &'a mut [ColumnDiff], column: &str) -> Option<&'a mut ColumnDiff> {
+    columns
+        .iter_mut()
+        .find(|changed| column_named(changed) == column)
+}
+
+/// The column a difference is in.
+fn column_named(changed: &ColumnDiff) -> &str {
+    match changed {
+        ColumnDiff::Only { column, .. }
+        | ColumnDiff::Retyped { column, .. }
+        | ColumnDiff::Values { column, .. }
+        | ColumnDiff::Moved { column, .. } => column,
+    }
+}
+
+/// How many rows, as a report says it.
+fn rows_of(rows: usize) -> String {
+    match rows {
+        1 => "1 row".to_string(),
+        rows => format!("{rows} rows"),
+    }
+}
+
+/// The units a geometry column's distances are in.
+fn unit_of(column: &str) -> &'static str {
+    match column {
+        PROJECTED_GEOMETRY => " m",
+        _ => "°",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+    use geo_types::Point;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::country::Country;
+    use crate::dataset::DatasetSpec;
+    use crate::derive::{GeoRow, write_geo_rows, write_rows};
+    use crate::layer::layers;
+    use crate::rows::{Dated, Row};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct StopRow {
+        stop_id: String,
+        name: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        seen_at: DateTime<Utc>,
+    }
+
+    impl Row for StopRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("stop", "seen_date");
+        const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
+        const INSTANTS: &'static [&'static str] = &["seen_at"];
+        const UNIQUE: &'static [&'static str] = &["stop_id"];
+    }
+
+    impl Dated for StopRow {
+        fn partition_date(&self) -> NaiveDate {
+            self.seen_at.date_naive()
+        }
+    }
+
+    /// A dataset declaring nothing unique, which can only gain and lose rows.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct VisitRow {
+        stop_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        visited_at: DateTime<Utc>,
+    }
+
+    impl Row for VisitRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("visit", "visit_date");
+        const INSTANTS: &'static [&'static str] = &["visited_at"];
+    }
+
+    impl Dated for VisitRow {
+        fn partition_date(&self) -> NaiveDate {
+            self.visited_at.date_naive()
+        }
+    }
+
+    /// A dataset whose rows are identified by two columns together.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct CallRow {
+        trip_id: String,
+        seq: u32,
+        stop_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        called_at: DateTime<Utc>,
+    }
+
+    impl Row for CallRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("call", "called_date");
+        const INSTANTS: &'static [&'static str] = &["called_at"];
+        const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[&["trip_id", "seq"]];
+    }
+
+    impl Dated for CallRow {
+        fn partition_date(&self) -> NaiveDate {
+            self.called_at.date_naive()
+        }
+    }
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 7, day, 9, 0, 0).unwrap()
+    }
+
+    fn stop(id: &str, name: &str, day: u32, lon: f64) -> GeoRow<StopRow, Point<f64>> {
+        GeoRow {
+            row: StopRow {
+                stop_id: id.to_string(),
+                name: name.to_string(),
+                seen_at: at(day),
+            },
+            geometry: Point::new(lon, 52.52),
+            country: Country::GERMANY,
+        }
+    }
+
+    fn visit(id: &str, day: u32) -> VisitRow {
+        VisitRow {
+            stop_id: id.to_string(),
+            visited_at: at(day),
+        }
+    }
+
+    async fn stops(rows: &[GeoRow<StopRow, Point<f64>>]) -> (tempfile::TempDir, Root) {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, rows).await.unwrap();
+        (tmp, root)
+    }
+
+    async fn visits(rows: &[VisitRow]) -> (tempfile::TempDir, Root) {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_rows(&root, rows).await.unwrap();
+        (tmp, root)
+    }
+
+    /// The determinism check: the same rows written twice are no difference at all.
+    #[tokio::test]
+    async fn the_same_rows_written_twice_differ_in_nothing() {
+        let rows = [stop("a", "Hbf", 21, 13.4), stop("b", "Ost", 22, 13.43)];
+        let (_before, before) = stops(&rows).await;
+        let (_after, after) = stops(&rows).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<StopRow>())
+            .await
+            .unwrap();
+
+        assert!(diff.is_empty(), "{diff:?}");
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.key, Some(&["stop_id"][..]));
+    }
+
+    #[tokio::test]
+    async fn a_row_under_a_key_only_one_store_holds_is_added_or_removed() {
+        let (_before, before) =
+            stops(&[stop("a", "Hbf", 21, 13.4), stop("b", "Ost", 21, 13.43)]).await;
+        let (_after, after) =
+            stops(&[stop("a", "Hbf", 21, 13.4), stop("c", "Zoo", 21, 13.33)]).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<StopRow>())
+            .await
+            .unwrap();
+
+        let [partition] = diff.partitions.as_slice() else {
+            panic!("expected one partition to differ: {diff:?}");
+        };
+        assert_eq!(
+            (partition.added, partition.removed, partition.changed),
+            (1, 1, 0)
+        );
+        assert!(partition.columns.is_empty());
+    }
+
+    /// A row both hold under one key is a change, described by the columns that differ.
+    #[tokio::test]
+    async fn a_changed_value_is_reported_with_its_key_and_both_values() {
+        let (_before, before) = stops(&[stop("a", "Hbf", 21, 13.4)]).await;
+        let (_after, after) = stops(&[stop("a", "Hauptbahnhof", 21, 13.4)]).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<StopRow>())
+            .await
+            .unwrap();
+
+        let partition = &diff.partitions[0];
+        assert_eq!(
+            (partition.added, partition.removed, partition.changed),
+            (0, 0, 1)
+        );
+        assert_eq!(
+            partition.columns,
+            [ColumnDiff::Values {
+                column: "name".to_string(),
+                rows: 1,
+                key: "a".to_string(),
+                before: "Hbf".to_string(),
+                after: "Hauptbahnhof".to_string(),
+            }]
+        );
+    }
+
+    /// A key of several columns matches rows on all of them, and shows its value as a tuple.
+    #[tokio::test]
+    async fn rows_are_matched_on_a_key_of_several_columns() {
+        let call = |seq: u32, stop_id: &str| CallRow {
+            trip_id: "t".to_string(),
+            seq,
+            stop_id: stop_id.to_string(),
+            called_at: at(21),
+        };
+        let tmp = tempfile::tempdir().unwrap();
+        let before = Root::new(tmp.path().join("before"));
+        let after = Root::new(tmp.path().join("after"));
+        write_rows(&before, &[call(0, "a"), call(1, "b")])
+            .await
+            .unwrap();
+        write_rows(&after, &[call(0, "a"), call(1, "c")])
+            .await
+            .unwrap();
+
+        let diff = dataset(&before, &after, &Declared::of::<CallRow>())
+            .await
+            .unwrap();
+
+        assert_eq!(diff.key, Some(&["trip_id", "seq"][..]));
+        let partition = &diff.partitions[0];
+        assert_eq!(
+            (partition.added, partition.removed, partition.changed),
+            (0, 0, 1)
+        );
+        assert_eq!(
+            partition.columns,
+            [ColumnDiff::Values {
+                column: "stop_id".to_string(),
+                rows: 1,
+                key: "(t, 1)".to_string(),
+                before: "b".to_string(),
+                after: "c".to_string(),
+            }]
+        );
+    }
+
+    /// A geometry is reported by how far it moved, in metres for the projected column.
+    #[tokio::test]
+    async fn a_moved_geometry_is_reported_by_how_far_it_moved() {
+        let (_before, before) = stops(&[stop("a", "Hbf", 21, 13.4)]).await;
+        let (_after, after) = stops(&[stop("a", "Hbf", 21, 13.401)]).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<StopRow>())
+            .await
+            .unwrap();
+
+        let columns = &diff.partitions[0].columns;
+        let Some(ColumnDiff::Moved { rows, furthest, .. }) = columns
+            .iter()
+            .find(|changed| column_named(changed) == PROJECTED_GEOMETRY)
+        else {
+            panic!("expected the projected geometry to have moved: {columns:?}");
+        };
+        assert_eq!(*rows, 1);
+        // A thousandth of a degree of longitude at Berlin's latitude is about 68 m.
+        assert!((60.0..75.0).contains(furthest), "{furthest}");
+        assert!(
+            columns
+                .iter()
+                .any(|changed| column_named(changed) == GEOMETRY)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_partition_only_one_store_holds_is_said_to_be() {
+        let (_before, before) = stops(&[stop("a", "Hbf", 21, 13.4)]).await;
+        let (_after, after) =
+            stops(&[stop("a", "Hbf", 21, 13.4), stop("b", "Ost", 22, 13.43)]).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<StopRow>())
+            .await
+            .unwrap();
+
+        let [partition] = diff.partitions.as_slice() else {
+            panic!("expected one partition to differ: {diff:?}");
+        };
+        assert_eq!(partition.only, Some(Side::After));
+        assert_eq!(partition.added, 1);
+        assert_eq!(diff.unchanged, 1);
+    }
+
+    /// With no key, a row is only there or not: a repeat of a row is one more of it.
+    #[tokio::test]
+    async fn unkeyed_rows_are_compared_whole() {
+        let (_before, before) = visits(&[visit("a", 21), visit("b", 21)]).await;
+        let (_after, after) = visits(&[visit("a", 21), visit("a", 21), visit("c", 21)]).await;
+
+        let diff = dataset(&before, &after, &Declared::of::<VisitRow>())
+            .await
+            .unwrap();
+
+        assert_eq!(diff.key, None);
+        let partition = &diff.partitions[0];
+        assert_eq!(
+            (partition.added, partition.removed, partition.changed),
+            (2, 1, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_store_holding_nothing_of_the_dataset_differs_by_all_of_it() {
+        let (_before, before) = visits(&[visit("a", 21)]).await;
+        let empty = tempfile::tempdir().unwrap();
+
+        let diff = dataset(
+            &before,
+            &Root::new(empty.path()),
+            &Declared::of::<VisitRow>(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(diff.partitions[0].only, Some(Side::Before));
+        assert_eq!(diff.partitions[0].removed, 1);
+    }
+}
@@ -3,6 +3,14 @@
 //!
 //! The metadata is produced by the `geoparquet` encoder rather than assembled here, so the
 //! files conform to the spec version that crate implements.
+//!
+//! A silver file is laid out for being read by place. Its rows are sorted along a Hilbert
+//! curve over the projected geometry, so rows near one another on the ground sit near one
+//! another in the file, and every geometry column is given a GeoParquet 1.1 `bbox` covering:
+//! a struct column of each row's envelope, declared in the `geo` metadata. The row groups are
+//! kept small enough that each covers a patch of the country rather than all of it, so the
+//! statistics parquet keeps on the covering's fields tell a reader filtering on an envelope
+//! which row groups it can skip without opening them.
 
 use std::path::Path;
 use std::sync::Arc;
@@ -12,15 +20,24 @@ use futures::StreamExt;
 
 use crate::country::Country;
 use crate::rows::{Row, RowError, fields};
-use crate::write::WriteError;
-use arrow::array::{Array, ArrayRef, BinaryArray, RecordBatch};
-use arrow::datatypes::{DataType, Field, FieldRef, Schema};
+use crate::store::Backend;
+use crate::write::{WriteError, writer_at};
+use arrow::array::{
+    Array, ArrayRef, BinaryArray, Float64Array, RecordBatch, StructArray, UInt32Array,
+};
+use arrow::buffer::NullBuffer;
+use arrow::datatypes::{DataType, Field, FieldRef, Fields, Schema};
+use geo::BoundingRect;
+use geo_types::Rect;
 use geoarrow_schema::{Crs, Metadata, WkbType};
 use geoparquet::writer::{GeoParquetRecordBatchEncoder, GeoParquetWriterOptions};
+use parquet::arrow::ArrowWriter;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
 
 /// The global CRS every silver geometry is stored in, as PROJJSON — the encoding
 /// GeoParquet requires. Generated from PROJ by `just crs-definitions`.
-const CRS84_PROJJSON: &str = include_str!("crs84.projjson.json");
+pub(crate) const CRS84_PROJJSON: &str = include_str!("crs84.projjson.json");
 
 /// EPSG code of CRS 84's underlying geographic system.
 const CRS84_EPSG: u16 = 4326;
@@ -32,6 +49,29 @@ pub const GEOMETRY: &str = "geometry";
 /// The column holding the same geometry in metres, for distance and length work.
 pub const PROJECTED_GEOMETRY: &str = "geometry_projected";
 
+/// The suffix naming the column that holds a geometry column's `bbox` covering:
+/// `geometry_bbox` covers `geometry`.
+///
+/// GeoParquet suggests a plain `bbox`, which names one covering at most, and a silver dataset
+/// has two geometry columns — and `session` already holds a column of that name.
+const BBOX_SUFFIX: &str = "_bbox";
+
+/// The fields of a `bbox` covering, in the order GeoParquet lists them.
+const BBOX_FIELDS: [&str; 4] = ["xmin", "ymin", "xmax", "ymax"];
+
+/// The most rows a silver GeoParquet row group holds.
+///
+/// A row group is the smallest part of a file a reader can skip, so this is the grain at which
+/// an envelope filter pays off. A silver partition is one country, or one country's day, and
+/// parquet's default of a million rows would make nearly every one a single group covering
+/// the whole country; at this size a country's crossings come to a few dozen groups, each a
+/// patch of the curve, for the cost of a little more footer.
+const ROWS_PER_GROUP: usize = 2048;
+
+/// How many cells a side of the grid the Hilbert curve is drawn over has. Rows whose centres
+/// share a cell share a position on the curve, and keep the order they arrived in.
+const HILBERT_SIDE: u32 = 1 << 16;
+
 /// Failure describing a geometry column.
 #[derive(Debug, thiserror::Error)]
 pub enum GeoError {
@@ -57,6 +97,8 @@ pub enum GeoError {
     Row(#[from] RowError),
     #[error("building the columns: {0}")]
     Encode(#[from] serde_arrow::Error),
+    #[error("the geo metadata the encoder wrote is not valid json: {0}")]
+    GeoMetadata(serde_json::Error),
 }
 
 /// A WKB geometry field in [CRS 84](https://www.opengis.net/def/crs/OGC/1.3/CRS84), for
@@ -187,29 +229,243 @@ pub fn geometries(
         .collect()
 }
 
-/// Write `batches` to `path` as a single GeoParquet file.
+/// `batches` as the bytes of one GeoParquet file, as [`crate::write::encode_batches`] encodes
+/// a plain one.
 ///
 /// The batches' schema must carry GeoArrow metadata on its geometry columns — see
-/// [`wkb_field`]. Like [`crate::write_batches`], the file appears at `path` only once
-/// fully written.
-pub(crate) async fn write_geo_batches(
-    path: &Path,
+/// [`wkb_field`]. `metadata` is added to the footer beside the `geo` key.
+pub(crate) fn encode_geo_batches(
     batches: &[RecordBatch],
-) -> Result<(), GeoError> {
+    metadata: &[KeyValue],
+) -> Result<Vec<u8>, GeoError> {
     let Some(first) = batches.first() else {
         return Err(WriteError::Empty.into());
     };
 
+    let table = arrow::compute::concat_batches(&first.schema(), batches)?;
+    let (table, covered) = laid_out(&table)?;
+
     let options = GeoParquetWriterOptions::default();
-    let mut encoder = GeoParquetRecordBatchEncoder::try_new(first.schema().as_ref(), &options)?;
-    let mut writer = crate::write::writer_at(path, encoder.target_schema())?;
+    let mut encoder = GeoParquetRecordBatchEncoder::try_new(table.schema().as_ref(), &options)?;
+    let properties = WriterProperties::builder()
+        .set_max_row_group_size(ROWS_PER_GROUP)
+        .build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), encoder.target_schema(), Some(properties))?;
+
+    writer.write(&encoder.encode_record_batch(&table)?)?;
+    writer.append_key_value_metadata(covering(canonical(encoder.into_keyvalue()?)?, &covered)?);
+    for key_value in metadata {
+        writer.append_key_value_metadata(key_value.clone());
+    }
+    Ok(writer.into_inner()?)
+}
 
-    for batch in batches {
-        writer.write(&encoder.encode_record_batch(batch)?).await?;
+/// `table` sorted along the Hilbert curve, with a `bbox` covering after each geometry column
+/// it holds, and the names of the columns covered.
+///
+/// The curve is drawn over the projected geometry where there is one — metres are the same
+/// size in both directions, as degrees of longitude and latitude are not — and over the
+/// lat/lon geometry where there is not. Each row is placed by the centre of its envelope. The
+/// sort is stable and depends on nothing but the rows, so the same rows still come out as the
+/// same bytes, whatever order they arrived in short of sharing a cell.
+fn laid_out(table: &RecordBatch) -> Result<(RecordBatch, Vec<String>), GeoError> {
+    let covered: Vec<String> = table
+        .schema()
+        .fields()
+        .iter()
+        .filter(|field| {
+            field
+                .metadata()
+                .get("ARROW:extension:name")
+                .map(String::as_str)
+                == Some("geoarrow.wkb")
+        })
+        .map(|field| field.name().clone())
+        .collect();
+
+    let mut fields: Vec<FieldRef> = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+    let mut sorted_by = None;
+    for (field, column) in table.schema().fields().iter().zip(table.columns()) {
+        // A covering already present is one this writes, and is written again below.
+        if covered
+            .iter()
+            .any(|name| *field.name() == covering_name(name))
+        {
+            continue;
+        }
+        fields.push(field.clone());
+        columns.push(column.clone());
+        if covered.contains(field.name()) {
+            let envelopes = envelopes(table, field.name())?;
+            let bbox = bbox_column(&envelopes)?;
+            fields.push(Arc::new(Field::new(
+                covering_name(field.name()),
+                bbox.data_type().clone(),
+                true,
+            )));
+            columns.push(Arc::new(bbox));
+            match (field.name().as_str(), &sorted_by) {
+                (PROJECTED_GEOMETRY, _) | (_, None) => sorted_by = Some(envelopes),
+                _ => {}
+            }
+        }
     }
-    writer.append_key_value_metadata(encoder.into_keyvalue()?);
-    writer.close().await?;
-    Ok(())
+    let with_coverings = RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(
+            fields,
+            table.schema().metadata().clone(),
+        )),
+        columns,
+    )?;
+
+    let Some(envelopes) = sorted_by else {
+        return Ok((with_coverings, covered));
+    };
+    let order = hilbert_order(&envelopes);
+    Ok((
+        arrow::compute::take_record_batch(&with_coverings, &order)?,
+        covered,
+    ))
+}
+
+/// The name of the column holding `column`'s `bbox` covering.
+pub fn covering_name(column: &str) -> String {
+    format!("{column}{BBOX_SUFFIX}")
+}
+
+/// The envelope of each row's geometry in `column`, or `None` where a row holds none.
+fn envelopes(batch: &RecordBatch, column: &str) -> Result<Vec<Option<Rect<f64>>>, GeoError> {
+    let array = batch
+        .column_by_name(column)
+        .ok_or_else(|| GeoError::NoSuchColumn(column.to_string()))?;
+    let binary = arrow::compute::cast(array, &DataType::Binary)?;
+    let binary = binary
+        .as_any()
+        .downcast_ref::<BinaryArray>()
+        .ok_or_else(|| GeoError::NoSuchColumn(column.to_string()))?;
+
+    (0..binary.len())
+        .map(|i| match binary.is_null(i) {
+            true => Ok(None),
+            false => {
+                let geometry = wkb::reader::read_wkb(binary.value(i))?;
+                Ok(geo_traits::to_geo::ToGeoGeometry::to_geometry(&geometry).bounding_rect())
+            }
+        })
+        .collect()
+}
+
+/// `envelopes` as a GeoParquet `bbox` covering: a struct of `xmin`, `ymin`, `xmax` and
+/// `ymax`, null where there is no envelope.
+fn bbox_column(envelopes: &[Option<Rect<f64>>]) -> Result<StructArray, GeoError> {
+    let corner = |of: fn(&Rect<f64>) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter(
+            envelopes.iter().map(|envelope| envelope.as_ref().map(of)),
+        ))
+    };
+    let fields: Fields = BBOX_FIELDS
+        .iter()
+        .map(|name| Field::new(*name, DataType::Float64, true))
+        .collect();
+    Ok(StructArray::try_new(
+        fields,
+        vec![
+            corner(|rect| rect.min().x),
+            corner(|rect| rect.min().y),
+            corner(|rect| rect.max().x),
+            corner(|rect| rect.max().y),
+        ],
+        Some(NullBuffer::from_iter(envelopes.iter().map(Option::is_some))),
+    )?)
+}
+
+/// The order that sorts rows along the Hilbert curve by the centres of their `envelopes`,
+/// drawn over the extent of all of them. A row with no envelope goes last.
+fn hilbert_order(envelopes: &[Option<Rect<f64>>]) -> UInt32Array {
+    let extent = envelopes.iter().flatten().copied().reduce(|a, b| {
+        Rect::new(
+            (a.min().x.min(b.min().x), a.min().y.min(b.min().y)),
+            (a.max().x.max(b.max().x), a.max().y.max(b.max().y)),
+        )
+    });
+    let cell = |at: f64, from: f64, across: f64| match across > 0.0 {
+        // `as` saturates, so rounding at the far edge stays on the grid.
+        true => ((at - from) / across * f64::from(HILBERT_SIDE - 1)) as u32,
+        false => 0,
+    };
+    let keys: Vec<u64> = envelopes
+        .iter()
+        .map(|envelope| match (envelope, extent) {
+            (Some(envelope), Some(extent)) => {
+                let centre = envelope.center();
+                hilbert(
+                    cell(centre.x, extent.min().x, extent.width()),
+                    cell(centre.y, extent.min().y, extent.height()),
+                )
+            }
+            _ => u64::MAX,
+        })
+        .collect();
+
+    let mut order: Vec<u32> = (0..keys.len() as u32).collect();
+    order.sort_by_key(|row| keys[*row as usize]);
+    UInt32Array::from(order)
+}
+
+/// The distance along the Hilbert curve filling a [`HILBERT_SIDE`]-square grid of cell
+/// `(x, y)`: cells close on the curve are close on the grid.
+fn hilbert(mut x: u32, mut y: u32) -> u64 {
+    let mut distance = 0;
+    let mut side = HILBERT_SIDE / 2;
+    while side > 0 {
+        let right = u32::from(x & side != 0);
+        let up = u32::from(y & side != 0);
+        distance += u64::from(side) * u64::from(side) * u64::from((3 * right) ^ up);
+        // Turn the quadrant so the curve within it runs the way the next level expects.
+        if up == 0 {
+            if right == 1 {
+                x = HILBERT_SIDE - 1 - x;
+                y = HILBERT_SIDE - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        side /= 2;
+    }
+    distance
+}
+
+/// `keyvalue`, the `geo` file metadata, declaring each of `covered`'s `bbox` covering.
+fn covering(mut keyvalue: KeyValue, covered: &[String]) -> Result<KeyValue, GeoError> {
+    if let Some(value) = &keyvalue.value {
+        let mut parsed: serde_json::Value =
+            serde_json::from_str(value).map_err(GeoError::GeoMetadata)?;
+        for column in covered {
+            let name = covering_name(column);
+            let paths: serde_json::Map<String, serde_json::Value> = BBOX_FIELDS
+                .iter()
+                .map(|field| (field.to_string(), serde_json::json!([name, field])))
+                .collect();
+            parsed["columns"][column.as_str()]["covering"] = serde_json::json!({ "bbox": paths });
+        }
+        keyvalue.value = Some(parsed.to_string());
+    }
+    Ok(keyvalue)
+}
+
+/// The `geo` file metadata with its keys sorted.
+///
+/// The encoder serialises the geometry columns from a hash map, so two writes of the same
+/// rows can list them in different orders. A file named for its contents has to come out
+/// byte for byte the same each time, so the json is read back and written out again with
+/// the ordered map `serde_json` keeps by default.
+fn canonical(mut keyvalue: KeyValue) -> Result<KeyValue, GeoError> {
+    if let Some(value) = &keyvalue.value {
+        let parsed: serde_json::Value =
+            serde_json::from_str(value).map_err(GeoError::GeoMetadata)?;
+        keyvalue.value = Some(parsed.to_string());
+    }
+    Ok(keyvalue)
 }
 
 /// Write a query's results to `path` as a single GeoParquet file, as they arrive,
@@ -219,12 +475,13 @@ pub(crate) async fn write_geo_batches(
 /// nothing still writes a readable, correctly typed file instead of failing — a partition
 /// that legitimately holds no rows is a result, not an error.
 pub(crate) async fn write_geo_stream(
+    backend: &Backend,
     path: &Path,
     mut batches: SendableRecordBatchStream,
 ) -> Result<usize, GeoError> {
     let options = GeoParquetWriterOptions::default();
     let mut encoder = GeoParquetRecordBatchEncoder::try_new(batches.schema().as_ref(), &options)?;
-    let mut writer = crate::write::writer_at(path, encoder.target_schema())?;
+    let mut writer = writer_at(backend, path, encoder.target_schema())?;
 
     let mut rows = 0;
     while let Some(batch) = batches.next().await {
@@ -232,7 +489,7 @@ pub(crate) async fn write_geo_stream(
         rows += batch.num_rows();
         writer.write(&encoder.encode_record_batch(&batch)?).await?;
     }
-    writer.append_key_value_metadata(encoder.into_keyvalue()?);
+    writer.append_key_value_metadata(canonical(encoder.into_keyvalue()?)?);
     writer.close().await?;
     Ok(rows)
 }
@@ -240,6 +497,210 @@ pub(crate) async fn write_geo_stream(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arrow::array::{AsArray, StringArray};
+    use arrow::datatypes::Float64Type;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::file::statistics::Statistics;
+
+    /// A batch of points named by `ids`, in lat/lon and in the German zone, in the order given.
+    fn points(ids: &[&str], at: &[(f64, f64)]) -> RecordBatch {
+        let projector = Projector::for_country(Country::GERMANY).unwrap();
+        let lat_lon: Vec<geo_types::Point<f64>> = at
+            .iter()
+            .map(|(lon, lat)| geo_types::Point::new(*lon, *lat))
+            .collect();
+        let projected: Vec<geo_types::Point<f64>> = lat_lon
+            .iter()
+            .map(|point| projector.project(point).unwrap())
+            .collect();
+        let (geometry, lat_lon) = wkb_column(wkb_field(GEOMETRY).unwrap(), &lat_lon).unwrap();
+        let (projected_geometry, projected) = wkb_column(
+            projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).unwrap(),
+            &projected,
+        )
+        .unwrap();
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Arc::new(Field::new("id", DataType::Utf8, false)),
+                geometry,
+                projected_geometry,
+            ])),
+            vec![
+                Arc::new(StringArray::from(ids.to_vec())),
+                lat_lon,
+                projected,
+            ],
+        )
+        .unwrap()
+    }
+
+    fn read_back(encoded: Vec<u8>) -> ParquetRecordBatchReaderBuilder<bytes::Bytes> {
+        ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(encoded)).unwrap()
+    }
+
+    fn ids(encoded: Vec<u8>) -> Vec<String> {
+        read_back(encoded)
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let ids = batch
+                    .column_by_name("id")
+                    .unwrap()
+                    .as_string::<i32>()
+                    .clone();
+                ids.iter()
+                    .map(|id| id.unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Corner to corner, the curve visits the bottom left, the top left, the top right and
+    /// the bottom right in turn.
+    #[test]
+    fn the_hilbert_curve_visits_the_quadrants_in_turn() {
+        let far = HILBERT_SIDE - 1;
+
+        let corners = [
+            hilbert(0, 0),
+            hilbert(0, far),
+            hilbert(far, far),
+            hilbert(far, 0),
+        ];
+
+        assert!(corners.is_sorted(), "corners out of order: {corners:?}");
+        assert_eq!(corners[0], 0);
+        assert_eq!(
+            corners[3],
+            u64::from(HILBERT_SIDE) * u64::from(HILBERT_SIDE) - 1
+        );
+    }
+
+    /// Two cities' rows arriving interleaved are written as one city's and then the other's.
+    #[test]
+    fn rows_near_one_another_are_written_together() {
+        let berlin = (13.40, 52.52);
+        let munich = (11.58, 48.14);
+        let nearby = |(lon, lat): (f64, f64), step: f64| (lon + step, lat + step);
+        let batch = points(
+            &["b1", "m1", "b2", "m2", "b3", "m3"],
+            &[
+                nearby(berlin, 0.0),
+                nearby(munich, 0.0),
+                nearby(berlin, 0.01),
+                nearby(munich, 0.01),
+                nearby(berlin, 0.02),
+                nearby(munich, 0.02),
+            ],
+        );
+
+        let written = ids(encode_geo_batches(&[batch], &[]).unwrap());
+
+        let cities: Vec<char> = written
+            .iter()
+            .map(|id| id.chars().next().unwrap())
+            .collect();
+        assert!(
+            cities == ['b', 'b', 'b', 'm', 'm', 'm'] || cities == ['m', 'm', 'm', 'b', 'b', 'b'],
+            "cities interleaved: {written:?}"
+        );
+    }
+
+    /// What is written depends on the rows and not on the order they arrived in, so a file
+    /// named for its bytes keeps its name.
+    #[test]
+    fn the_same_rows_in_another_order_encode_the_same() {
+        let ids = ["a", "b", "c"];
+        let at = [(13.40, 52.52), (11.58, 48.14), (8.68, 50.11)];
+        let forward = points(&ids, &at);
+        let backward = points(
+            &ids.iter().rev().copied().collect::<Vec<_>>(),
+            &at.iter().rev().copied().collect::<Vec<_>>(),
+        );
+
+        assert_eq!(
+            encode_geo_batches(&[forward], &[]).unwrap(),
+            encode_geo_batches(&[backward], &[]).unwrap()
+        );
+    }
+
+    /// Each geometry column is followed by its envelope, and the `geo` metadata names the
+    /// envelope's fields as the column's `bbox` covering.
+    #[test]
+    fn each_geometry_column_is_covered_and_declared() {
+        let batch = points(&["a"], &[(13.40, 52.52)]);
+
+        let file = read_back(encode_geo_batches(&[batch], &[]).unwrap());
+
+        let geo: serde_json::Value = serde_json::from_str(
+            file.metadata()
+                .file_metadata()
+                .key_value_metadata()
+                .unwrap()
+                .iter()
+                .find(|kv| kv.key == "geo")
+                .and_then(|kv| kv.value.as_deref())
+                .unwrap(),
+        )
+        .unwrap();
+        for column in [GEOMETRY, PROJECTED_GEOMETRY] {
+            let covering = &geo["columns"][column]["covering"]["bbox"];
+            assert_eq!(
+                covering["xmin"],
+                serde_json::json!([covering_name(column), "xmin"])
+            );
+            assert_eq!(
+                covering["ymax"],
+                serde_json::json!([covering_name(column), "ymax"])
+            );
+        }
+
+        let batch = file.build().unwrap().next().unwrap().unwrap();
+        let bbox = batch
+            .column_by_name("geometry_bbox")
+            .unwrap()
+            .as_struct()
+            .clone();
+        for field in BBOX_FIELDS {
+            let corner = bbox.column_by_name(field).unwrap();
+            let corner = corner.as_primitive::<Float64Type>();
+            let expected = match field.starts_with('x') {
+                true => 13.40,
+                false => 52.52,
+            };
+            assert_eq!(corner.value(0), expected, "{field}");
+        }
+    }
+
+    /// A file of more rows than a row group holds is split into several, each with the
+    /// statistics on its covering a reader skips it by.
+    #[test]
+    fn row_groups_are_small_enough_to_skip() {
+        let rows = ROWS_PER_GROUP * 2 + 1;
+        let ids: Vec<String> = (0..rows).map(|row| row.to_string()).collect();
+        let at: Vec<(f64, f64)> = (0..rows)
+            .map(|row| (6.0 + row as f64 * 0.001, 48.0 + row as f64 * 0.001))
+            .collect();
+        let batch = points(&ids.iter().map(String::as_str).collect::<Vec<_>>(), &at);
+
+        let file = read_back(encode_geo_batches(&[batch], &[]).unwrap());
+
+        let metadata = file.metadata();
+        assert_eq!(metadata.num_row_groups(), 3);
+        let xmin = file
+            .parquet_schema()
+            .columns()
+            .iter()
+            .position(|column| column.path().string() == "geometry_bbox.xmin")
+            .unwrap();
+        for group in metadata.row_groups() {
+            match group.column(xmin).statistics() {
+                Some(Statistics::Double(statistics)) => assert!(statistics.min_opt().is_some()),
+                found => panic!("no statistics on the covering: {found:?}"),
+            }
+        }
+    }
 
     #[test]
     fn the_bundled_crs_is_projjson_for_crs84() {
@@ -252,7 +713,7 @@ mod tests {
 
     #[test]
     fn a_projected_field_carries_the_projected_crs() {
-        let field = projected_wkb_field("geometry_utm", Country::Germany).unwrap();
+        let field = projected_wkb_field("geometry_utm", Country::GERMANY).unwrap();
 
         let extension: serde_json::Value =
             serde_json::from_str(field.metadata().get("ARROW:extension:metadata").unwrap())
@@ -263,7 +724,7 @@ mod tests {
     /// Against `cs2cs EPSG:4326 EPSG:25832`, to a millimetre.
     #[test]
     fn projecting_lat_lon_yields_metres_in_the_german_zone() {
-        let projector = Projector::for_country(Country::Germany).unwrap();
+        let projector = Projector::for_country(Country::GERMANY).unwrap();
 
         let projected = projector
             .project(&geo_types::Point::new(13.404954, 52.520008))
@@ -276,10 +737,56 @@ mod tests {
         );
     }
 
+    /// Each country's capital lands where that country's own grid puts it — which a wrong
+    /// EPSG code, a swapped axis or a missing datum shift would each miss by far more than
+    /// the tolerance. Against `cs2cs EPSG:4326 EPSG:<code>`, to within the few metres the
+    /// datum shifts PROJ and proj4rs apply can differ by.
+    #[test]
+    fn every_country_projects_its_capital_into_its_own_grid() {
+        for (country, (lon, lat), (x, y)) in [
+            (Country::AUSTRIA, (16.3738, 48.2082), (625_863.5, 483_137.5)),
+            (
+                Country::SWITZERLAND,
+                (7.4474, 46.948),
+                (2_600_667.5, 1_199_657.3),
+            ),
+            (
+                Country::GERMANY,
+                (13.404954, 52.520008),
+                (798_809.6, 5_828_000.6),
+            ),
+            (
+                Country::DENMARK,
+                (12.5683, 55.6761),
+                (724_351.9, 6_175_804.0),
+            ),
+            (Country::FRANCE, (2.3522, 48.8566), (652_469.0, 6_862_035.3)),
+            (
+                Country::UNITED_KINGDOM,
+                (-0.127758, 51.507351),
+                (530_031.8, 180_374.7),
+            ),
+            (
+                Country::NETHERLANDS,
+                (4.891, 52.373),
+                (121_208.7, 487_351.7),
+            ),
+        ] {
+            let projector = Projector::for_country(country).unwrap();
+
+            let projected = projector.project(&geo_types::Point::new(lon, lat)).unwrap();
+
+            assert!(
+                (projected.x() - x).abs() < 10.0 && (projected.y() - y).abs() < 10.0,
+                "{country}: unexpected projection {projected:?}"
+            );
+        }
+    }
+
     /// Every coordinate is projected, not just the first.
     #[test]
     fn projecting_a_line_string_projects_every_coordinate() {
-        let projector = Projector::for_country(Country::Germany).unwrap();
+        let projector = Projector::for_country(Country::GERMANY).unwrap();
         let line = geo_types::LineString::from(vec![(13.404954, 52.520008), (8.682127, 50.110924)]);
 
         let projected = projector.project(&line).unwrap();
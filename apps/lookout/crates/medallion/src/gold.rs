@@ -0,0 +1,806 @@
+//! Which version of a gold artefact is the current one, and which versions are kept.
+//!
+//! A packer writes each run's output beside the last — see [`Root::gold_artefact`] — so the
+//! directory of an artefact only ever grows, and says nothing about which of its versions is
+//! meant to be used. That is what a device fetching its crossings, or a website serving its
+//! tiles, has to ask. So each artefact keeps an **index** beside its versions, at
+//! `gold/artifact=<name>/_index.json`:
+//!
+//!   - every version a run **recorded**, with each file it holds, its size, and the SHA-256
+//!     of its bytes, so what was fetched can be checked against what was written;
+//!   - the version **promoted** to live, if any. Promoting is a separate step from writing,
+//!     because a run that succeeded is not yet one anybody has looked at: a device follows
+//!     the promoted version and nothing else.
+//!
+//! The index sits at a path that never changes, which is the stable answer to "the current
+//! crossings.pointset": read it, and fetch the live version's files it names. Its leading
+//! `_` is one no artefact version has, and it is a file where versions are directories, so
+//! it is never taken for one.
+//!
+//! Versions are pruned by a [`Retention`], which never removes the live version, nor one
+//! newer than it — a run's output waiting to be looked at — nor one written within the last
+//! [`GRACE`], which may be a run still writing. A version on disk the index does not record
+//! — one written before the index existed, or by a run killed before it recorded — is still
+//! a version, and is kept or collected like any other. Promoting without naming a version
+//! promotes the newest the index records, never an unrecorded one, which may be half
+//! written.
+//!
+//! Writing the index is one put, so a reader sees the old index or the new one. It is also
+//! a conditional one: the index is written only if it is still the version that was read,
+//! and otherwise read again and the change made anew, so two runs recording into one
+//! artefact at the same moment both keep their records.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::layer::Layer;
+use crate::partition::{Partition, PathError};
+use crate::path::{ARTIFACT, BATCH_STEM_FORMAT, Root, VERSION};
+use crate::store::Version;
+
+/// What an artefact's index is called, beside its versions.
+pub const INDEX: &str = "_index.json";
+
+/// How long after its last file was written a version is spared collection, whatever the
+/// retention: long enough that a packer still writing one is not swept out from under.
+pub const GRACE: TimeDelta = TimeDelta::hours(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum GoldError {
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("the index at {path} is not one this reads: {source}")]
+    Index {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("{artifact} has no version {version}")]
+    NoSuchVersion { artifact: String, version: String },
+    #[error("{artifact} has no recorded versions to promote")]
+    NoVersions { artifact: String },
+    #[error("{artifact} has no promoted version, so keeping only that would keep nothing")]
+    NothingPromoted { artifact: String },
+}
+
+/// One file of one version, as it was when the version was recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtefactFile {
+    /// The file's path within its version's directory.
+    pub name: String,
+    pub bytes: u64,
+    /// The SHA-256 of the file's bytes, in lowercase hex.
+    pub sha256: String,
+}
+
+/// One run's output, as the index records it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtefactVersion {
+    pub version: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub recorded_at: DateTime<Utc>,
+    /// In path order.
+    pub files: Vec<ArtefactFile>,
+}
+
+/// What one artefact's index holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtefactIndex {
+    pub artifact: String,
+    /// The live version, which is one of `versions`.
+    pub promoted: Option<String>,
+    /// Oldest first — versions sort chronologically.
+    pub versions: Vec<ArtefactVersion>,
+}
+
+impl ArtefactIndex {
+    fn empty(artifact: &str) -> Self {
+        Self {
+            artifact: artifact.to_string(),
+            promoted: None,
+            versions: Vec::new(),
+        }
+    }
+
+    /// The version a consumer should use: the one promoted, if one is.
+    pub fn live(&self) -> Option<&ArtefactVersion> {
+        let promoted = self.promoted.as_deref()?;
+        self.versions
+            .iter()
+            .find(|version| version.version == promoted)
+    }
+
+    /// Record `version`, in place of any earlier record of the same one.
+    fn insert(&mut self, version: ArtefactVersion) {
+        self.versions
+            .retain(|recorded| recorded.version != version.version);
+        self.versions.push(version);
+        self.versions.sort_by(|a, b| a.version.cmp(&b.version));
+    }
+}
+
+/// Which versions of an artefact a collection keeps. The live version is kept by both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    /// The newest `n` versions, and the live one and every version after it if they are
+    /// not among them.
+    Newest(usize),
+    /// The live version, and any newer one not yet promoted.
+    Promoted,
+}
+
+/// What collecting an artefact removed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Collected {
+    /// The versions removed, oldest first.
+    pub versions: Vec<String>,
+    pub bytes: u64,
+}
+
+/// One gold artefact: its versions, and the index saying which is live.
+#[derive(Debug, Clone)]
+pub struct Artefact {
+    root: Root,
+    artifact: String,
+    dir: PathBuf,
+}
+
+impl Root {
+    /// The gold artefact `artifact`, refused if its name could not name a partition.
+    pub fn artefact(&self, artifact: &str) -> Result<Artefact, PathError> {
+        let dir = self
+            .path()
+            .join(Layer::Gold.as_str())
+            .join(Partition::new(ARTIFACT, artifact)?.to_string());
+        Ok(Artefact {
+            root: self.clone(),
+            artifact: artifact.to_string(),
+            dir,
+        })
+    }
+}
+
+impl Artefact {
+    pub fn name(&self) -> &str {
+        &self.artifact
+    }
+
+    /// Where the file `name` of the version a run at `run` produces belongs — the same path
+    /// as [`Root::gold_artefact`].
+    pub fn file(&self, run: DateTime<Utc>, name: &str) -> Result<PathBuf, PathError> {
+        Ok(self.version_dir(&version_of(run))?.join(name))
+    }
+
+    /// Write `bytes` as the file `name` of the version a run at `run` produces, through the
+    /// store the artefact is in — on disk, or in a bucket — and say where it went. The
+    /// version is not recorded until [`Artefact::record`] is called, once every file is in.
+    pub async fn put(
+        &self,
+        run: DateTime<Utc>,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> Result<PathBuf, GoldError> {
+        let path = self.file(run, name)?;
+        self.root
+            .backend()
+            .put(&path, bytes)
+            .await
+            .map_err(|source| io_error(&path, source))?;
+        Ok(path)
+    }
+
+    /// Where this artefact's index is kept.
+    pub fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX)
+    }
+
+    fn version_dir(&self, version: &str) -> Result<PathBuf, PathError> {
+        Ok(self.dir.join(Partition::new(VERSION, version)?.to_string()))
+    }
+
+    /// What the index holds, or an empty one if nothing has been recorded yet.
+    pub async fn index(&self) -> Result<ArtefactIndex, GoldError> {
+        Ok(self.read_index().await?.0)
+    }
+
+    /// What the index holds, and the version of it that was read — `None` if there is no
+    /// index yet.
+    async fn read_index(&self) -> Result<(ArtefactIndex, Option<Version>), GoldError> {
+        let path = self.index_path();
+        let (json, version) = match self.root.backend().read_versioned(&path).await {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok((ArtefactIndex::empty(&self.artifact), None));
+            }
+            Err(source) => return Err(io_error(&path, source)),
+        };
+        let index = serde_json::from_slice(&json).map_err(|source| GoldError::Index {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Ok((index, Some(version)))
+    }
+
+    /// Make `change` to the index, writing it only if it is still the index `change` was
+    /// made to. One another run wrote meanwhile is read again and `change` made to that, so
+    /// neither run's change is lost.
+    async fn update<T>(
+        &self,
+        mut change: impl FnMut(&mut ArtefactIndex) -> Result<T, GoldError>,
+    ) -> Result<T, GoldError> {
+        let path = self.index_path();
+        loop {
+            let (mut index, read) = self.read_index().await?;
+            let unchanged = index.clone();
+            let changed = change(&mut index)?;
+            if index == unchanged {
+                return Ok(changed);
+            }
+            let json = serde_json::to_vec_pretty(&index).map_err(|source| GoldError::Index {
+                path: path.display().to_string(),
+                source,
+            })?;
+            let written = self
+                .root
+                .backend()
+                .put_if(&path, json, read.as_ref())
+                .await
+                .map_err(|source| io_error(&path, source))?;
+            if written {
+                return Ok(changed);
+            }
+        }
+    }
+
+    /// Record what the run at `run` wrote, once it has written all of it.
+    pub async fn record(&self, run: DateTime<Utc>) -> Result<ArtefactVersion, GoldError> {
+        let version = self.describe(&version_of(run)).await?;
+        self.update(|index| {
+            index.insert(version.clone());
+            Ok(())
+        })
+        .await?;
+        Ok(version)
+    }
+
+    /// Each file of `version` as it is on disk now, hashed.
+    async fn describe(&self, version: &str) -> Result<ArtefactVersion, GoldError> {
+        let dir = self.version_dir(version)?;
+        let backend = self.root.backend();
+        let stored = backend
+            .files_below(&dir)
+            .await
+            .map_err(|source| io_error(&dir, source))?;
+        if stored.is_empty() {
+            return Err(GoldError::NoSuchVersion {
+                artifact: self.artifact.clone(),
+                version: version.to_string(),
+            });
+        }
+
+        let mut files = Vec::with_capacity(stored.len());
+        for file in stored {
+            let bytes = backend
+                .read(&file.path)
+                .await
+                .map_err(|source| io_error(&file.path, source))?;
+            files.push(ArtefactFile {
+                name: relative(&dir, &file.path),
+                bytes: file.bytes,
+                sha256: format!("{:x}", Sha256::digest(&bytes)),
+            });
+        }
+        Ok(ArtefactVersion {
+            version: version.to_string(),
+            recorded_at: Utc::now(),
+            files,
+        })
+    }
+
+    /// The versions on disk, oldest first, whether or not the index records them.
+    pub async fn versions(&self) -> Result<Vec<String>, GoldError> {
+        let listed = self
+            .root
+            .backend()
+            .list(&self.dir)
+            .await
+            .map_err(|source| io_error(&self.dir, source))?;
+        let prefix = format!("{VERSION}=");
+        Ok(listed
+            .dirs
+            .iter()
+            .filter_map(|dir| dir.file_name()?.to_str()?.strip_prefix(&prefix))
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Make `version` the live one, or the newest recorded version if none is named. A
+    /// version on disk the index does not record is recorded as it is promoted, but only
+    /// when named: an unrecorded version may be one a run is still writing.
+    pub async fn promote(&self, version: Option<&str>) -> Result<ArtefactVersion, GoldError> {
+        let index = self.index().await?;
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => index
+                .versions
+                .last()
+                .map(|recorded| recorded.version.clone())
+                .ok_or_else(|| GoldError::NoVersions {
+                    artifact: self.artifact.clone(),
+                })?,
+        };
+        let described = if index.versions.iter().any(|v| v.version == version) {
+            None
+        } else {
+            Some(self.describe(&version).await?)
+        };
+
+        self.update(|index| {
+            let recorded = match index.versions.iter().find(|v| v.version == version) {
+                Some(recorded) => recorded.clone(),
+                // Recorded when first read, and collected since.
+                None => described.clone().ok_or_else(|| GoldError::NoSuchVersion {
+                    artifact: self.artifact.clone(),
+                    version: version.clone(),
+                })?,
+            };
+            index.insert(recorded.clone());
+            index.promoted = Some(version.clone());
+            Ok(recorded)
+        })
+        .await
+    }
+
+    /// The live version and where each of its files is, or `None` if nothing is promoted.
+    pub async fn live(&self) -> Result<Option<(ArtefactVersion, Vec<PathBuf>)>, GoldError> {
+        let index = self.index().await?;
+        let Some(live) = index.live() else {
+            return Ok(None);
+        };
+        let dir = self.version_dir(&live.version)?;
+        let paths = live.files.iter().map(|file| dir.join(&file.name)).collect();
+        Ok(Some((live.clone(), paths)))
+    }
+
+    /// Remove every version `retention` does not keep, and its record, as of `now`: a
+    /// version written within [`GRACE`] of it is kept whatever `retention` says.
+    pub async fn collect(
+        &self,
+        retention: Retention,
+        now: DateTime<Utc>,
+    ) -> Result<Collected, GoldError> {
+        let index = self.index().await?;
+        let versions = self.versions().await?;
+        let promoted = index.promoted.clone();
+
+        // Versions sort chronologically, so those after the live one are the runs since it,
+        // waiting to be looked at, which no retention removes.
+        let mut kept: BTreeSet<&str> = promoted.iter().map(String::as_str).collect();
+        if let Some(promoted) = promoted.as_deref() {
+            kept.extend(
+                versions
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|version| *version > promoted),
+            );
+        }
+        match (retention, promoted.as_deref()) {
+            (Retention::Newest(n), _) => {
+                kept.extend(versions.iter().rev().take(n).map(String::as_str));
+            }
+            (Retention::Promoted, None) => {
+                return Err(GoldError::NothingPromoted {
+                    artifact: self.artifact.clone(),
+                });
+            }
+            (Retention::Promoted, Some(_)) => {}
+        }
+
+        let cutoff = (now - GRACE)
+            .timestamp_nanos_opt()
+            .map_or(i128::MIN, i128::from);
+        let backend = self.root.backend();
+        let mut collected = Collected::default();
+        for version in versions.iter().filter(|v| !kept.contains(v.as_str())) {
+            let dir = self.version_dir(version)?;
+            let files = backend
+                .files_below(&dir)
+                .await
+                .map_err(|source| io_error(&dir, source))?;
+            let mut written = i128::MIN;
+            for file in &files {
+                let file_version = backend
+                    .version(&file.path)
+                    .await
+                    .map_err(|source| io_error(&file.path, source))?;
+                written = written.max(file_version.modified);
+            }
+            // Written since the cutoff, it may be a run's output still being written.
+            if written > cutoff {
+                continue;
+            }
+            backend
+                .remove_dir(&dir)
+                .await
+                .map_err(|source| io_error(&dir, source))?;
+            collected.bytes += files.iter().map(|file| file.bytes).sum::<u64>();
+            collected.versions.push(version.clone());
+        }
+
+        self.update(|index| {
+            index
+                .versions
+                .retain(|recorded| !collected.versions.contains(&recorded.version));
+            Ok(())
+        })
+        .await?;
+        Ok(collected)
+    }
+}
+
+/// Every gold artefact in `root`, by name.
+pub async fn artefacts(root: &Root) -> Result<Vec<Artefact>, GoldError> {
+    let gold = root.path().join(Layer::Gold.as_str());
+    let listed = root
+        .backend()
+        .list(&gold)
+        .await
+        .map_err(|source| io_error(&gold, source))?;
+    let prefix = format!("{ARTIFACT}=");
+    let mut artefacts = Vec::new();
+    for name in listed
+        .dirs
+        .iter()
+        .filter_map(|dir| dir.file_name()?.to_str()?.strip_prefix(&prefix))
+    {
+        artefacts.push(root.artefact(name)?);
+    }
+    Ok(artefacts)
+}
+
+/// The version a run at `run` writes.
+fn version_of(run: DateTime<Utc>) -> String {
+    run.format(BATCH_STEM_FORMAT).to_string()
+}
+
+fn relative(dir: &Path, file: &Path) -> String {
+    file.strip_prefix(dir)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn io_error(path: &Path, source: io::Error) -> GoldError {
+    GoldError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn run(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 8, day, 19, 48, 57).unwrap()
+    }
+
+    /// A moment by which everything a test just wrote is out of its grace.
+    fn after_grace() -> DateTime<Utc> {
+        Utc::now() + GRACE * 2
+    }
+
+    /// Write a version of `crossings` as a packer would, without recording it.
+    fn written(root: &Root, day: u32, bytes: &[u8]) {
+        let path = root
+            .gold_artefact("crossings", run(day), "crossings.pointset")
+            .unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+    }
+
+    /// Write a version of `crossings` as a packer would, and record it.
+    async fn packed(root: &Root, day: u32, bytes: &[u8]) -> ArtefactVersion {
+        written(root, day, bytes);
+        root.artefact("crossings")
+            .unwrap()
+            .record(run(day))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn an_artefacts_file_is_where_the_store_puts_gold_artefacts() {
+        let root = Root::new("/store");
+        assert_eq!(
+            root.artefact("crossings")
+                .unwrap()
+                .file(run(1), "crossings.pointset")
+                .unwrap(),
+            root.gold_artefact("crossings", run(1), "crossings.pointset")
+                .unwrap()
+        );
+        assert_eq!(
+            root.artefact("crossings").unwrap().index_path(),
+            PathBuf::from("/store/gold/artifact=crossings/_index.json")
+        );
+    }
+
+    /// What a consumer fetched can be checked against what the run wrote.
+    #[tokio::test]
+    async fn a_recorded_version_lists_its_files_with_their_sizes_and_hashes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+
+        let version = packed(&root, 1, b"abc").await;
+
+        assert_eq!(version.version, "20260801T194857000Z");
+        assert_eq!(
+            version.files,
+            vec![ArtefactFile {
+                name: "crossings.pointset".into(),
+                bytes: 3,
+                sha256: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into(),
+            }]
+        );
+        let index = root.artefact("crossings").unwrap().index().await.unwrap();
+        assert_eq!(index.versions, vec![version]);
+        assert_eq!(index.promoted, None);
+    }
+
+    /// A version put into a bucket is found there when it is recorded, not looked for on
+    /// the local disk under the bucket's path.
+    #[tokio::test]
+    async fn a_version_put_into_an_object_store_is_recorded_from_it() {
+        let root = Root::in_object_store(
+            std::sync::Arc::new(object_store::memory::InMemory::new()),
+            &url::Url::parse("memory:///medallion").unwrap(),
+        );
+        let artefact = root.artefact("crossings").unwrap();
+
+        let path = artefact
+            .put(run(1), "crossings.pointset", b"abc".to_vec())
+            .await
+            .unwrap();
+        let version = artefact.record(run(1)).await.unwrap();
+
+        assert_eq!(
+            path,
+            root.gold_artefact("crossings", run(1), "crossings.pointset")
+                .unwrap()
+        );
+        assert!(!path.exists());
+        assert_eq!(version.files[0].name, "crossings.pointset");
+        assert_eq!(version.files[0].bytes, 3);
+    }
+
+    /// A run that succeeded is not live until it is promoted, and then it is what a consumer
+    /// is pointed at, however many runs follow it.
+    #[tokio::test]
+    async fn the_live_version_is_the_promoted_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        packed(&root, 1, b"first").await;
+        assert_eq!(artefact.live().await.unwrap(), None);
+
+        let promoted = artefact.promote(None).await.unwrap();
+        packed(&root, 2, b"second").await;
+
+        let (live, paths) = artefact.live().await.unwrap().unwrap();
+        assert_eq!(live, promoted);
+        assert_eq!(live.version, "20260801T194857000Z");
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"first");
+    }
+
+    /// A version written before the index existed is recorded as it is promoted.
+    #[tokio::test]
+    async fn an_unrecorded_version_is_recorded_when_promoted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        written(&root, 3, b"legacy");
+        let artefact = root.artefact("crossings").unwrap();
+
+        artefact.promote(Some("20260803T194857000Z")).await.unwrap();
+
+        let index = artefact.index().await.unwrap();
+        assert_eq!(index.live().unwrap().files[0].bytes, 6);
+        assert!(matches!(
+            artefact.promote(Some("20260809T000000000Z")).await,
+            Err(GoldError::NoSuchVersion { .. })
+        ));
+    }
+
+    /// Collecting keeps the newest versions and the live one, however old it is.
+    #[tokio::test]
+    async fn collecting_keeps_the_newest_and_the_live_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        for day in 1..=5 {
+            packed(&root, day, b"points").await;
+        }
+        artefact.promote(Some("20260803T194857000Z")).await.unwrap();
+
+        let collected = artefact
+            .collect(Retention::Newest(4), after_grace())
+            .await
+            .unwrap();
+
+        assert_eq!(collected.versions, vec!["20260801T194857000Z"]);
+        assert_eq!(collected.bytes, 6);
+        let remaining = vec![
+            "20260802T194857000Z",
+            "20260803T194857000Z",
+            "20260804T194857000Z",
+            "20260805T194857000Z",
+        ];
+        assert_eq!(artefact.versions().await.unwrap(), remaining);
+        let recorded: Vec<_> = artefact
+            .index()
+            .await
+            .unwrap()
+            .versions
+            .into_iter()
+            .map(|version| version.version)
+            .collect();
+        assert_eq!(recorded, remaining);
+    }
+
+    /// Runs since the live one are waiting to be looked at, so none of them is collected,
+    /// however many more of them there are than the retention counts.
+    #[tokio::test]
+    async fn collecting_the_newest_keeps_every_version_after_the_live_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        for day in 1..=5 {
+            packed(&root, day, b"points").await;
+        }
+        artefact.promote(Some("20260801T194857000Z")).await.unwrap();
+
+        let collected = artefact
+            .collect(Retention::Newest(2), after_grace())
+            .await
+            .unwrap();
+
+        assert_eq!(collected, Collected::default());
+        assert_eq!(artefact.versions().await.unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn keeping_only_the_promoted_version_needs_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        packed(&root, 1, b"points").await;
+        packed(&root, 2, b"points").await;
+
+        assert!(matches!(
+            artefact.collect(Retention::Promoted, after_grace()).await,
+            Err(GoldError::NothingPromoted { .. })
+        ));
+        assert_eq!(artefact.versions().await.unwrap().len(), 2);
+
+        artefact.promote(None).await.unwrap();
+        let collected = artefact
+            .collect(Retention::Promoted, after_grace())
+            .await
+            .unwrap();
+        assert_eq!(collected.versions, vec!["20260801T194857000Z"]);
+    }
+
+    /// A version the index does not record may be one a run is still writing, so it is
+    /// promoted only by name.
+    #[tokio::test]
+    async fn promoting_the_newest_version_promotes_the_newest_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        assert!(matches!(
+            artefact.promote(None).await,
+            Err(GoldError::NoVersions { .. })
+        ));
+        packed(&root, 1, b"points").await;
+        written(&root, 2, b"half");
+
+        let promoted = artefact.promote(None).await.unwrap();
+
+        assert_eq!(promoted.version, "20260801T194857000Z");
+        assert_eq!(artefact.index().await.unwrap().versions.len(), 1);
+    }
+
+    /// Keeping only the live version keeps the runs since it, which nobody has looked at
+    /// yet, and any version written within the grace, which may be a run still writing.
+    #[tokio::test]
+    async fn collecting_spares_newer_and_recent_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        for day in 1..=3 {
+            packed(&root, day, b"points").await;
+        }
+        artefact.promote(Some("20260802T194857000Z")).await.unwrap();
+
+        let within_grace = artefact
+            .collect(Retention::Promoted, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(within_grace, Collected::default());
+
+        let collected = artefact
+            .collect(Retention::Promoted, after_grace())
+            .await
+            .unwrap();
+        assert_eq!(collected.versions, vec!["20260801T194857000Z"]);
+        assert_eq!(
+            artefact.versions().await.unwrap(),
+            vec!["20260802T194857000Z", "20260803T194857000Z"]
+        );
+    }
+
+    /// Another run writing the index between this one reading it and writing it back does
+    /// not lose its change: the index is read again and this one's change made to that.
+    #[tokio::test]
+    async fn a_change_to_the_index_made_meanwhile_is_kept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let artefact = root.artefact("crossings").unwrap();
+        packed(&root, 1, b"points").await;
+        written(&root, 2, b"points");
+        let other = artefact.describe("20260802T194857000Z").await.unwrap();
+
+        let mut attempts = 0;
+        artefact
+            .update(|index| {
+                attempts += 1;
+                if attempts == 1 {
+                    let mut theirs = index.clone();
+                    theirs.insert(other.clone());
+                    std::fs::write(
+                        artefact.index_path(),
+                        serde_json::to_vec_pretty(&theirs).unwrap(),
+                    )
+                    .unwrap();
+                }
+                index.promoted = Some("20260801T194857000Z".into());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        let index = artefact.index().await.unwrap();
+        assert_eq!(index.promoted.as_deref(), Some("20260801T194857000Z"));
+        assert_eq!(index.versions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn every_artefact_in_gold_is_found_and_the_pointers_directory_is_not_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        packed(&root, 1, b"points").await;
+        std::fs::create_dir_all(tmp.path().join("gold/_current")).unwrap();
+
+        let names: Vec<_> = artefacts(&root)
+            .await
+            .unwrap()
+            .iter()
+            .map(|artefact| artefact.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["crossings"]);
+    }
+}
@@ -3,7 +3,8 @@
 //! Every CLI that reads or writes the store goes through here rather than joining strings
 //! itself, so the layer names, Hive partition layout and the naming rules partitions must
 //! meet live in one place. [`MedallionArgs`] gives each binary the same `--medallion-root`
-//! flag and default.
+//! flag and default, and the same `--from`/`--to` [`DateRange`] for a run over less than
+//! everything.
 //!
 //! A dataset is passed around as a [`DatasetSpec`], which carries its layer and partition
 //! key, and its columns are the [`Row`] type declared alongside it; the datasets
@@ -40,31 +41,49 @@
 //! ```
 
 mod args;
+mod catalog;
+mod compact;
 mod country;
 mod dataset;
 mod derive;
+pub mod diff;
 mod geo;
+pub mod gold;
 mod layer;
+pub mod lineage;
+mod live;
 mod partition;
 mod path;
 mod query;
+mod range;
+mod rebuild;
 mod rows;
+mod store;
 pub mod summary;
 mod table;
+pub mod verify;
 mod write;
 
 pub use args::MedallionArgs;
+pub use compact::{CompactError, Compaction, ListingError};
 pub use country::{COUNTRY, Countries, Country, UnknownCountry};
 pub use dataset::{DatasetInfo, DatasetSpec};
-pub use derive::{GeoRow, write_geo_rows, write_rows};
+pub use derive::{
+    GeoRow, GeoRowWriter, write_country_rows, write_geo_rows, write_geo_rows_within, write_rows,
+    write_rows_within,
+};
 pub use geo::{
-    GEOMETRY, GeoError, PROJECTED_GEOMETRY, Projector, geo_batch, geometries, projected_wkb_field,
-    wkb_column, wkb_field,
+    GEOMETRY, GeoError, PROJECTED_GEOMETRY, Projector, covering_name, geo_batch, geometries,
+    projected_wkb_field, wkb_column, wkb_field,
 };
 pub use layer::{Layer, LayerKind, Replaceable, layers};
 pub use partition::{Partition, PartitionKey, PartitionValue, PathError};
-pub use path::{AppendError, Dataset, ReplaceError, Replaced, Root, Written};
-pub use query::{Query, QueryError};
-pub use rows::{Dated, Geometry, Row, RowError, batch, fields};
+pub use path::{
+    AppendError, Dataset, OpenError, ReplaceError, Replaced, Root, StoreNotFound, Written,
+};
+pub use query::{MEMORY_LIMIT, Query, QueryError, RowStream};
+pub use range::{DateRange, EmptyRange};
+pub use rebuild::{Cleaned, Rebuild};
+pub use rows::{Dated, Geometry, Reference, Row, RowError, batch, fields};
 pub use table::{SilverTarget, TableError, TableWritten, write_table};
 pub use write::WriteError;
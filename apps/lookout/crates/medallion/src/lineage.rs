@@ -0,0 +1,968 @@
+//! Recording on every derived file what it was derived from, and reading it back.
+//!
+//! A silver or gold file otherwise says nothing of the run that wrote it, so a dataset whose
+//! inputs have since moved on reads exactly like one that is current. A producer that opens
+//! its root [`recording`](Root::recording) a [`Producer`] has each file it writes carry a
+//! [`Lineage`]:
+//!
+//!   - the binary that wrote it and the commit it was built from, [`GIT_HASH`];
+//!   - the tuning it ran under, as the producer names it;
+//!   - every partition it read through a [`Query`](crate::Query), with a fingerprint of the
+//!     files a reader reads there, taken from what the store says of them rather than from
+//!     their bytes: which files, and their sizes.
+//!
+//! A file's name and size stand for its content because nothing this store writes is ever
+//! written again under the same name: a bronze capture is created once, at a name no other
+//! capture takes; a compaction writes a generation of new files; and a rebuild names each
+//! part for the md5 of its bytes. Nothing else — not when a file was last written, which a
+//! `git clone`, `checkout` or `pull` of the store resets, nor a store's tag for it, which
+//! differs between two stores holding the same bytes — is part of the fingerprint, so a
+//! store moved or copied still traces as the run that derived it left it.
+//!
+//! A parquet file carries it in its footer under [`LINEAGE_KEY`]; a gold artefact, in a
+//! format of its own, carries it in a json file beside it — see [`sidecar`]. [`trace`] reads a
+//! dataset's back and compares each input with what the store holds of it now.
+//!
+//! Nothing in a lineage changes unless the inputs, the tuning or the build do, so a rerun
+//! over unchanged inputs still writes the same bytes and a rebuild still publishes nothing.
+//! That is also why a run's reads of the dataset it is writing are not among its inputs:
+//! each run would change them, and no two runs would write the same file.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use chrono::NaiveDate;
+use parquet::file::metadata::KeyValue;
+use serde::{Deserialize, Serialize};
+
+use crate::compact::{Admit, ListingError, LivePartition, live_partitions_where};
+use crate::dataset::DatasetInfo;
+use crate::partition::{DATE_FORMAT, DATE_KEY_SUFFIX};
+use crate::path::Root;
+use crate::range::DateRange;
+use crate::rebuild::Published;
+use crate::store::Backend;
+
+/// The commit the store's writers were built from, as the build was told it; `unknown` for a
+/// build that was not.
+pub const GIT_HASH: &str = env!("BUILD_GIT_HASH");
+
+/// What [`GIT_HASH`] is for a build not told its commit, and what it ends with for one built
+/// from a tree with changes not committed — as the build and the Justfile write them.
+const UNKNOWN_BUILD: &str = "unknown";
+const DIRTY_BUILD: &str = "-dirty";
+
+/// The file metadata key a parquet file's lineage is kept under.
+pub const LINEAGE_KEY: &str = "lineage";
+
+/// What a gold artefact's lineage file is named for, after the artefact's own name.
+const SIDECAR_SUFFIX: &str = ".lineage.json";
+
+/// A failure recording or reading back a lineage.
+#[derive(Debug, thiserror::Error)]
+pub enum LineageError {
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error("reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("reading the footer of {path}: {source}")]
+    Footer {
+        path: String,
+        #[source]
+        source: parquet::errors::ParquetError,
+    },
+    #[error("reading the lineage {path} records: {source}")]
+    Json {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// What one derived file was produced by and from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lineage {
+    /// The binary that wrote it.
+    pub producer: String,
+    /// The commit that binary was built from.
+    pub git_hash: String,
+    /// The tuning it ran under, by name.
+    pub parameters: BTreeMap<String, String>,
+    /// Every partition it read, in path order.
+    pub inputs: Vec<Input>,
+}
+
+impl Lineage {
+    /// The lineage as the json it is recorded as.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a lineage is plain strings and numbers")
+    }
+}
+
+/// One partition a derivation read, as it was when it read it.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Input {
+    /// The dataset, as `<layer>/<name>`.
+    pub dataset: String,
+    /// The partition's `key=value` directories below the dataset, joined by `/`; empty for
+    /// the files of an unpartitioned dataset.
+    pub partition: String,
+    /// The files a reader read there.
+    pub files: usize,
+    /// The md5 of those files' names and sizes, in path order — which stand for their bytes,
+    /// as the [module docs](self) say. Hashing them rather than the bytes keeps asking
+    /// whether anything changed to a listing, whatever the inputs weigh.
+    pub md5: String,
+}
+
+impl Input {
+    /// Which partition of which dataset this is, whatever it held.
+    fn place(&self) -> (&str, &str) {
+        (&self.dataset, &self.partition)
+    }
+
+    /// The date of the partition, where one of its directories is a dated key's.
+    fn date(&self) -> Option<NaiveDate> {
+        self.partition.split('/').find_map(|directory| {
+            let (key, value) = directory.split_once('=')?;
+            match key.ends_with(DATE_KEY_SUFFIX) {
+                true => NaiveDate::parse_from_str(value, DATE_FORMAT).ok(),
+                false => None,
+            }
+        })
+    }
+}
+
+/// How much of its outputs a derivation has to derive again. See [`pending`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pending {
+    /// Nothing it read has changed since its outputs were derived.
+    Nothing,
+    /// What differs is dated, within these dates.
+    Dates(DateRange),
+    Everything,
+}
+
+/// Whether an input is still what a derivation read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Since {
+    Unchanged,
+    /// Its files are not the ones read: new rows arrived, a compaction merged them, or a
+    /// rebuild replaced them.
+    Changed,
+    /// It holds nothing now.
+    Gone,
+}
+
+/// The live files of a dataset recording one lineage, and how each of its inputs stands now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    /// How many live files record it.
+    pub files: usize,
+    /// `None` for files written without one: by a writer that was not recording, or before
+    /// lineage was recorded at all.
+    pub lineage: Option<Lineage>,
+    /// How each of the lineage's inputs stands, in the lineage's order.
+    pub since: Vec<Since>,
+}
+
+impl Derivation {
+    /// Whether anything it was derived from has changed since.
+    pub fn is_stale(&self) -> bool {
+        self.since.iter().any(|since| *since != Since::Unchanged)
+    }
+}
+
+/// A binary that derives datasets, and the tuning it is running under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Producer {
+    name: String,
+    parameters: BTreeMap<String, String>,
+}
+
+impl Producer {
+    /// The producer `name`, conventionally the binary's own: `env!("CARGO_BIN_NAME")`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            parameters: BTreeMap::new(),
+        }
+    }
+
+    /// Record the tuning parameter `name` as `value`, as the producer's own flag would give it.
+    pub fn parameter(mut self, name: &str, value: impl Display) -> Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// What a root recording lineage has read so far, shared by every clone of it.
+#[derive(Debug)]
+pub(crate) struct Recording {
+    producer: Producer,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    reads: Vec<Read>,
+    /// The inputs `reads` resolve to, once asked for, until another read is noted.
+    inputs: Option<Vec<Input>>,
+}
+
+/// One registration of a dataset, as [`crate::query::read_table`] was given it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Read {
+    dataset: PathBuf,
+    dir: PathBuf,
+    restriction: Option<(&'static str, DateRange)>,
+}
+
+impl Recording {
+    pub(crate) fn new(producer: Producer) -> Self {
+        Self {
+            producer,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Note that the files below `dir` of the dataset in `dataset` were read, within
+    /// `restriction`.
+    pub(crate) fn note(
+        &self,
+        dataset: PathBuf,
+        dir: PathBuf,
+        restriction: Option<(&'static str, DateRange)>,
+    ) {
+        let read = Read {
+            dataset,
+            dir,
+            restriction,
+        };
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state.reads.contains(&read) {
+            state.reads.push(read);
+            state.inputs = None;
+        }
+    }
+
+    /// The partitions every read so far covers, hashed once for as long as nothing more is
+    /// read, since a run writing many files reads its inputs once.
+    async fn inputs(&self, root: &Root) -> Result<Vec<Input>, LineageError> {
+        let reads = {
+            let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(inputs) = &state.inputs {
+                return Ok(inputs.clone());
+            }
+            state.reads.clone()
+        };
+
+        let mut inputs = BTreeSet::new();
+        for read in &reads {
+            let admit = |key: &str, value: &str| {
+                read.restriction
+                    .is_none_or(|(dated, range)| key != dated || range.admits(value))
+            };
+            for partition in live_below(root.backend(), &read.dataset, &read.dir, &admit).await? {
+                inputs.insert(input_of(root, &read.dataset, &partition).await?);
+            }
+        }
+        let inputs: Vec<Input> = inputs.into_iter().collect();
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.reads == reads {
+            state.inputs = Some(inputs.clone());
+        }
+        Ok(inputs)
+    }
+}
+
+/// The lineage of what `root`'s producer has derived so far, or `None` where it is not
+/// recording one.
+pub async fn recorded(root: &Root) -> Result<Option<Lineage>, LineageError> {
+    written_to(root, None).await
+}
+
+/// The lineage of a file written into the dataset in `dataset`, as footer metadata: none
+/// where `root` is not recording.
+pub(crate) async fn footer(root: &Root, dataset: &Path) -> Result<Vec<KeyValue>, LineageError> {
+    Ok(written_to(root, Some(dataset))
+        .await?
+        .map(|lineage| KeyValue::new(LINEAGE_KEY.to_string(), lineage.to_json()))
+        .into_iter()
+        .collect())
+}
+
+/// Where a gold artefact's lineage is kept: `crossings.pointset.lineage.json` beside
+/// `crossings.pointset`.
+pub fn sidecar(artefact: &Path) -> PathBuf {
+    let name = artefact.file_name().unwrap_or_default().to_string_lossy();
+    artefact.with_file_name(format!("{name}{SIDECAR_SUFFIX}"))
+}
+
+/// The lineage of a file written into the dataset in `dataset`, leaving out the reads of
+/// that dataset itself.
+async fn written_to(root: &Root, dataset: Option<&Path>) -> Result<Option<Lineage>, LineageError> {
+    let Some(recording) = root.lineage() else {
+        return Ok(None);
+    };
+    let inputs = recording
+        .inputs(root)
+        .await?
+        .into_iter()
+        .filter(|input| dataset != Some(root.path().join(&input.dataset).as_path()))
+        .collect();
+    Ok(Some(Lineage {
+        producer: recording.producer.name.clone(),
+        git_hash: GIT_HASH.to_string(),
+        parameters: recording.producer.parameters.clone(),
+        inputs,
+    }))
+}
+
+/// The lineages the live files of `dataset` record, each with how its inputs stand now, in
+/// the order they are first met in path order.
+pub async fn trace(root: &Root, dataset: DatasetInfo) -> Result<Vec<Derivation>, LineageError> {
+    let recorded = recorded_in(root, dataset).await?;
+
+    let mut now: HashMap<Input, Since> = HashMap::new();
+    let mut derivations = Vec::new();
+    for (lineage, files) in recorded {
+        let mut since = Vec::new();
+        for input in lineage.iter().flat_map(|lineage| &lineage.inputs) {
+            let stands = match now.get(input) {
+                Some(stands) => *stands,
+                None => {
+                    let stands = standing(root, input).await?;
+                    now.insert(input.clone(), stands);
+                    stands
+                }
+            };
+            since.push(stands);
+        }
+        derivations.push(Derivation {
+            files,
+            lineage,
+            since,
+        });
+    }
+    Ok(derivations)
+}
+
+/// How much of its outputs a derivation has to derive again, given everything it has read
+/// through `root` so far: nothing, if it has read exactly what they record they were derived
+/// from, under the producer, build and tuning they record.
+///
+/// A producer registers what a run over everything would read, asks this, and then derives
+/// only what it says — the fingerprint of a run being its lineage, which its outputs already
+/// carry. Where every partition that differs is dated, the answer is the span of their dates,
+/// and which of its own dates that reaches is the producer's to say; any undated one, an
+/// output holding nothing or a file recording no lineage, and it is everything.
+///
+/// So is a build that cannot vouch for its code: one not told its commit, or built from a
+/// tree with changes not committed, could be running code that no output records. A root
+/// not recording is always everything.
+pub async fn pending(root: &Root, outputs: &[DatasetInfo]) -> Result<Pending, LineageError> {
+    match vouches_for_its_code(GIT_HASH) {
+        true => differing(root, outputs).await,
+        false => Ok(Pending::Everything),
+    }
+}
+
+/// Whether the build `hash` names is one whose code a commit fixes.
+fn vouches_for_its_code(hash: &str) -> bool {
+    hash != UNKNOWN_BUILD && !hash.ends_with(DIRTY_BUILD)
+}
+
+/// What [`pending`] answers of a build that vouches for its code.
+async fn differing(root: &Root, outputs: &[DatasetInfo]) -> Result<Pending, LineageError> {
+    let Some(recording) = root.lineage() else {
+        return Ok(Pending::Everything);
+    };
+    let dirs: Vec<PathBuf> = outputs
+        .iter()
+        .map(|output| root.path().join(output.layer.as_str()).join(output.name))
+        .collect();
+    let read_elsewhere = |input: &Input| !dirs.contains(&root.path().join(&input.dataset));
+
+    let read: BTreeSet<Input> = recording
+        .inputs(root)
+        .await?
+        .into_iter()
+        .filter(read_elsewhere)
+        .collect();
+    let mut derived_from = BTreeSet::new();
+    for output in outputs {
+        let recorded = recorded_in(root, *output).await?;
+        if recorded.is_empty() {
+            return Ok(Pending::Everything);
+        }
+        for (lineage, _) in recorded {
+            let Some(lineage) = lineage else {
+                return Ok(Pending::Everything);
+            };
+            if lineage.producer != recording.producer.name
+                || lineage.git_hash != GIT_HASH
+                || lineage.parameters != recording.producer.parameters
+            {
+                return Ok(Pending::Everything);
+            }
+            derived_from.extend(lineage.inputs.into_iter().filter(read_elsewhere));
+        }
+    }
+
+    // What was read and no output was derived from, and what an output was derived from that
+    // is no longer there to read. An input changed since is both, once as each version.
+    let places: BTreeSet<(&str, &str)> = read.iter().map(Input::place).collect();
+    let differing = read
+        .iter()
+        .filter(|input| !derived_from.contains(*input))
+        .chain(
+            derived_from
+                .iter()
+                .filter(|input| !places.contains(&input.place())),
+        );
+    let mut dates = BTreeSet::new();
+    for input in differing {
+        match input.date() {
+            Some(date) => dates.insert(date),
+            None => return Ok(Pending::Everything),
+        };
+    }
+    match (dates.first(), dates.last()) {
+        (Some(from), Some(to)) => Ok(Pending::Dates(
+            DateRange::new(Some(*from), Some(*to)).expect("the first date is not after the last"),
+        )),
+        _ => Ok(Pending::Nothing),
+    }
+}
+
+/// The lineages the live files of `dataset` record, each with how many record it, in the
+/// order they are first met in path order.
+async fn recorded_in(
+    root: &Root,
+    dataset: DatasetInfo,
+) -> Result<Vec<(Option<Lineage>, usize)>, LineageError> {
+    let backend = root.backend();
+    let dir = root.path().join(dataset.layer.as_str()).join(dataset.name);
+
+    let mut recorded: Vec<(Option<Lineage>, usize)> = Vec::new();
+    for partition in live_below(backend, &dir, &dir, &|_, _| true).await? {
+        for file in &partition.files {
+            let lineage = lineage_in(backend, file).await?;
+            match recorded.iter_mut().find(|(seen, _)| *seen == lineage) {
+                Some((_, files)) => *files += 1,
+                None => recorded.push((lineage, 1)),
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// How `input` stands in the store now.
+async fn standing(root: &Root, input: &Input) -> Result<Since, LineageError> {
+    let dataset = root.path().join(&input.dataset);
+    let dir = match input.partition.is_empty() {
+        true => dataset.clone(),
+        false => dataset.join(&input.partition),
+    };
+    let partitions = live_below(root.backend(), &dataset, &dir, &|_, _| true).await?;
+    let Some(partition) = partitions
+        .iter()
+        .find(|partition| partition_dir(&dataset, partition) == dir)
+    else {
+        return Ok(Since::Gone);
+    };
+    match input_of(root, &dataset, partition).await? == *input {
+        true => Ok(Since::Unchanged),
+        false => Ok(Since::Changed),
+    }
+}
+
+/// The lineage `file`'s footer records, if it records one.
+async fn lineage_in(backend: &Backend, file: &Path) -> Result<Option<Lineage>, LineageError> {
+    let shown = || file.display().to_string();
+    let stored = backend
+        .stored(file)
+        .await
+        .map_err(|source| LineageError::Io {
+            path: shown(),
+            source,
+        })?;
+    let footer = backend
+        .footer(&stored)
+        .await
+        .map_err(|source| LineageError::Footer {
+            path: shown(),
+            source,
+        })?;
+    let Some(json) = footer
+        .file_metadata()
+        .key_value_metadata()
+        .into_iter()
+        .flatten()
+        .find(|key_value| key_value.key == LINEAGE_KEY)
+        .and_then(|key_value| key_value.value.as_deref())
+    else {
+        return Ok(None);
+    };
+    serde_json::from_str(json)
+        .map(Some)
+        .map_err(|source| LineageError::Json {
+            path: shown(),
+            source,
+        })
+}
+
+/// The partitions below `dir` a reader reads, as [`crate::query::read_table`] lists them:
+/// the ones the pointer of the dataset in `dataset` publishes, or where nothing has published
+/// it, the live files of each directory.
+async fn live_below(
+    backend: &Backend,
+    dataset: &Path,
+    dir: &Path,
+    admit: &Admit<'_>,
+) -> Result<Vec<LivePartition>, ListingError> {
+    match Published::read(backend, dataset).await? {
+        Some(published) => Ok(published.partitions_below(dir, admit)),
+        None => live_partitions_where(backend, dir, admit).await,
+    }
+}
+
+/// `partition` of the dataset in `dataset` as an input, hashing the names and sizes of its
+/// files without reading one of them.
+async fn input_of(
+    root: &Root,
+    dataset: &Path,
+    partition: &LivePartition,
+) -> Result<Input, LineageError> {
+    let mut md5 = md5::Context::new();
+    for file in &partition.files {
+        let stored = root
+            .backend()
+            .stored(file)
+            .await
+            .map_err(|source| LineageError::Io {
+                path: file.display().to_string(),
+                source,
+            })?;
+        md5.consume(file.file_name().unwrap_or_default().as_encoded_bytes());
+        md5.consume(stored.bytes.to_le_bytes());
+    }
+    Ok(Input {
+        dataset: joined(root.path(), dataset),
+        partition: joined(dataset, &partition_dir(dataset, partition)),
+        files: partition.files.len(),
+        md5: format!("{:x}", md5.compute()),
+    })
+}
+
+/// The directory `partition`'s files sit in.
+fn partition_dir(dataset: &Path, partition: &LivePartition) -> PathBuf {
+    partition
+        .files
+        .first()
+        .and_then(|file| file.parent())
+        .unwrap_or(dataset)
+        .to_path_buf()
+}
+
+/// `path` below `base`, its components joined by `/` whatever the platform.
+fn joined(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use arrow::array::{Int64Array, RecordBatch};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use futures::stream::BoxStream;
+    use object_store::memory::InMemory;
+    use object_store::path::Path as ObjectPath;
+    use object_store::{
+        GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, ObjectStore,
+        PutMultipartOpts, PutOptions, PutPayload, PutResult,
+    };
+    use url::Url;
+
+    use super::*;
+    use crate::dataset::DatasetSpec;
+    use crate::layer::layers;
+    use crate::query::Query;
+
+    const READING: DatasetSpec<layers::Bronze> =
+        DatasetSpec::partitioned("reading", "ingested_date");
+    const DAILY: DatasetSpec<layers::Silver> = DatasetSpec::partitioned("daily", "day");
+
+    fn ids(ids: Vec<i64>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 7, day).unwrap()
+    }
+
+    fn recording(path: &Path) -> Root {
+        Root::new(path).recording(Producer::new("derive").parameter("gap_mins", 30))
+    }
+
+    /// Append a capture of `values` to `READING` on `day`, captured at `minute` past noon.
+    async fn capture(root: &Root, day: u32, minute: u32, values: Vec<i64>) {
+        let at = Utc.with_ymd_and_hms(2026, 7, day, 12, minute, 0).unwrap();
+        root.dataset(READING)
+            .on_date(date(day))
+            .unwrap()
+            .append(at, &[ids(values)])
+            .await
+            .unwrap();
+    }
+
+    /// Derive a day of `DAILY` as a producer does: read bronze within `range`, and the
+    /// dataset's own last run, then write.
+    async fn derive(root: &Root, range: DateRange) -> PathBuf {
+        let query = Query::new(root.clone());
+        query
+            .register_if_present_within(READING, "reading", range)
+            .await
+            .unwrap();
+        query.register_if_present(DAILY, "previous").await.unwrap();
+        root.dataset(DAILY)
+            .on_date(date(27))
+            .unwrap()
+            .replace_with(&[ids(vec![1])])
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_derived_file_records_its_producer_its_tuning_and_what_it_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = recording(tmp.path());
+        capture(&root, 26, 0, vec![1]).await;
+        capture(&root, 27, 0, vec![2, 3]).await;
+
+        derive(&root, DateRange::ALL).await;
+
+        let traced = trace(&root, DAILY.info()).await.unwrap();
+        assert_eq!(traced.len(), 1, "{traced:?}");
+        let lineage = traced[0].lineage.as_ref().unwrap();
+        assert_eq!(lineage.producer, "derive");
+        assert_eq!(lineage.git_hash, GIT_HASH);
+        assert_eq!(lineage.parameters["gap_mins"], "30");
+        let read: Vec<(&str, &str, usize)> = lineage
+            .inputs
+            .iter()
+            .map(|input| {
+                (
+                    input.dataset.as_str(),
+                    input.partition.as_str(),
+                    input.files,
+                )
+            })
+            .collect();
+        assert_eq!(
+            read,
+            [
+                ("bronze/reading", "ingested_date=2026-07-26", 1),
+                ("bronze/reading", "ingested_date=2026-07-27", 1),
+            ]
+        );
+        assert_eq!(traced[0].since, [Since::Unchanged, Since::Unchanged]);
+        assert!(!traced[0].is_stale());
+    }
+
+    /// A run over a range read only the dates in it, so only those are what it was derived
+    /// from.
+    #[tokio::test]
+    async fn a_run_over_a_range_is_derived_from_the_dates_in_it_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = recording(tmp.path());
+        capture(&root, 26, 0, vec![1]).await;
+        capture(&root, 27, 0, vec![2]).await;
+
+        derive(&root, DateRange::on(date(27))).await;
+
+        let traced = trace(&root, DAILY.info()).await.unwrap();
+        let inputs = &traced[0].lineage.as_ref().unwrap().inputs;
+        assert_eq!(inputs.len(), 1, "{inputs:?}");
+        assert_eq!(inputs[0].partition, "ingested_date=2026-07-27");
+    }
+
+    #[tokio::test]
+    async fn an_input_that_has_moved_on_is_reported_as_changed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = recording(tmp.path());
+        capture(&root, 26, 0, vec![1]).await;
+        capture(&root, 27, 0, vec![2]).await;
+        derive(&root, DateRange::ALL).await;
+
+        capture(&root, 27, 5, vec![4]).await;
+
+        let traced = trace(&root, DAILY.info()).await.unwrap();
+        assert_eq!(traced[0].since, [Since::Unchanged, Since::Changed]);
+        assert!(traced[0].is_stale());
+    }
+
+    /// A clone, checkout or pull of a store kept in git writes every file afresh, leaving its
+    /// bytes as they were and its modification time now.
+    #[tokio::test]
+    async fn an_input_written_afresh_with_the_same_bytes_is_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = recording(tmp.path());
+        capture(&root, 27, 0, vec![2]).await;
+        let first = derive(&root, DateRange::ALL).await;
+        let captured = tmp.path().join("bronze/reading/ingested_date=2026-07-27");
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(3600);
+        for entry in std::fs::read_dir(&captured).unwrap() {
+            std::fs::File::options()
+                .write(true)
+                .open(entry.unwrap().path())
+                .unwrap()
+                .set_modified(later)
+                .unwrap();
+        }
+
+        let traced = trace(&root, DAILY.info()).await.unwrap();
+
+        assert_eq!(traced[0].since, [Since::Unchanged]);
+        assert_eq!(pending_for(&root).await, Pending::Nothing);
+        assert_eq!(derive(&root, DateRange::ALL).await, first);
+    }
+
+    /// The lineage is made of nothing that differs between runs over the same inputs — not
+    /// the time, and not the dataset's own last run, which a producer may well read — so a
+    /// rerun writes the very file the last run did, and a rebuild publishes nothing.
+    #[tokio::test]
+    async fn a_rerun_over_unchanged_inputs_writes_the_same_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        capture(&Root::new(tmp.path()), 27, 0, vec![2]).await;
+
+        let first = derive(&recording(tmp.path()), DateRange::ALL).await;
+        let second = derive(&recording(tmp.path()), DateRange::ALL).await;
+
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn a_root_that_is_not_recording_writes_no_lineage() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        capture(&root, 27, 0, vec![2]).await;
+
+        derive(&root, DateRange::ALL).await;
+
+        assert_eq!(
+            trace(&root, DAILY.info()).await.unwrap(),
+            [Derivation {
+                files: 1,
+                lineage: None,
+                since: Vec::new(),
+            }]
+        );
+        assert_eq!(recorded(&root).await.unwrap(), None);
+    }
+
+    /// What a run over everything would have to derive again, asked as a producer asks it:
+    /// once it has read what it reads.
+    async fn pending_for(root: &Root) -> Pending {
+        let query = Query::new(root.clone());
+        query.register_if_present(READING, "reading").await.unwrap();
+        differing(root, &[DAILY.info()]).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn nothing_is_pending_where_nothing_read_has_changed() {
+        let tmp = tempfile::tempdir().unwrap();
+        capture(&Root::new(tmp.path()), 26, 0, vec![1]).await;
+        capture(&Root::new(tmp.path()), 27, 0, vec![2]).await;
+        derive(&recording(tmp.path()), DateRange::ALL).await;
+
+        assert_eq!(pending_for(&recording(tmp.path())).await, Pending::Nothing);
+    }
+
+    /// A date captured into since, or captured again, is pending over that date alone; the
+    /// producer knows which of its own dates that reaches.
+    #[tokio::test]
+    async fn a_dated_input_that_moved_on_is_pending_over_its_date() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        capture(&root, 25, 0, vec![1]).await;
+        capture(&root, 26, 0, vec![2]).await;
+        derive(&recording(tmp.path()), DateRange::ALL).await;
+
+        capture(&root, 26, 5, vec![3]).await;
+        capture(&root, 27, 0, vec![4]).await;
+
+        assert_eq!(
+            pending_for(&recording(tmp.path())).await,
+            Pending::Dates(DateRange::new(Some(date(26)), Some(date(27))).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn other_tuning_or_outputs_with_no_lineage_are_everything() {
+        let tmp = tempfile::tempdir().unwrap();
+        capture(&Root::new(tmp.path()), 27, 0, vec![2]).await;
+        derive(&recording(tmp.path()), DateRange::ALL).await;
+
+        let retuned =
+            Root::new(tmp.path()).recording(Producer::new("derive").parameter("gap_mins", 45));
+        assert_eq!(pending_for(&retuned).await, Pending::Everything);
+
+        derive(&Root::new(tmp.path()), DateRange::ALL).await;
+        assert_eq!(
+            pending_for(&recording(tmp.path())).await,
+            Pending::Everything
+        );
+    }
+
+    /// An in-memory store counting the parquet files read whole: what a range read of a
+    /// footer or a look at a file's metadata is not. A pointer is still read whole, and
+    /// weighs nothing beside what it names.
+    #[derive(Debug)]
+    struct Counting {
+        inner: InMemory,
+        whole: AtomicUsize,
+    }
+
+    impl Counting {
+        fn new() -> Self {
+            Self {
+                inner: InMemory::new(),
+                whole: AtomicUsize::new(0),
+            }
+        }
+
+        fn read_whole(&self) -> usize {
+            self.whole.load(Ordering::SeqCst)
+        }
+    }
+
+    impl fmt::Display for Counting {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Counting({})", self.inner)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStore for Counting {
+        async fn put_opts(
+            &self,
+            location: &ObjectPath,
+            payload: PutPayload,
+            opts: PutOptions,
+        ) -> object_store::Result<PutResult> {
+            self.inner.put_opts(location, payload, opts).await
+        }
+
+        async fn put_multipart_opts(
+            &self,
+            location: &ObjectPath,
+            opts: PutMultipartOpts,
+        ) -> object_store::Result<Box<dyn MultipartUpload>> {
+            self.inner.put_multipart_opts(location, opts).await
+        }
+
+        async fn get_opts(
+            &self,
+            location: &ObjectPath,
+            options: GetOptions,
+        ) -> object_store::Result<GetResult> {
+            if location.extension() == Some("parquet") && options.range.is_none() && !options.head {
+                self.whole.fetch_add(1, Ordering::SeqCst);
+            }
+            self.inner.get_opts(location, options).await
+        }
+
+        async fn delete(&self, location: &ObjectPath) -> object_store::Result<()> {
+            self.inner.delete(location).await
+        }
+
+        fn list(
+            &self,
+            prefix: Option<&ObjectPath>,
+        ) -> BoxStream<'static, object_store::Result<ObjectMeta>> {
+            self.inner.list(prefix)
+        }
+
+        async fn list_with_delimiter(
+            &self,
+            prefix: Option<&ObjectPath>,
+        ) -> object_store::Result<ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &ObjectPath, to: &ObjectPath) -> object_store::Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(
+            &self,
+            from: &ObjectPath,
+            to: &ObjectPath,
+        ) -> object_store::Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    /// Whether anything is pending is asked of what the store says of the inputs, never of
+    /// their bytes, so asking costs a listing however much bronze holds.
+    #[tokio::test]
+    async fn asking_what_is_pending_reads_no_input() {
+        let store = Arc::new(Counting::new());
+        let root =
+            Root::in_object_store(store.clone(), &Url::parse("memory:///medallion").unwrap());
+        let recording = || {
+            root.clone()
+                .recording(Producer::new("derive").parameter("gap_mins", 30))
+        };
+        capture(&root, 26, 0, vec![1]).await;
+        capture(&root, 27, 0, vec![2]).await;
+        derive(&recording(), DateRange::ALL).await;
+
+        let asking = recording();
+        let query = Query::new(asking.clone());
+        query.register_if_present(READING, "reading").await.unwrap();
+        let before = store.read_whole();
+        let pending = differing(&asking, &[DAILY.info()]).await.unwrap();
+
+        assert_eq!(pending, Pending::Nothing);
+        assert_eq!(store.read_whole(), before);
+    }
+
+    /// A build that cannot say which commit its code is could be running code no output
+    /// records, so it derives everything.
+    #[test]
+    fn only_a_build_of_a_clean_commit_vouches_for_its_code() {
+        assert!(vouches_for_its_code("a7d61b9"));
+        assert!(!vouches_for_its_code("a7d61b9-dirty"));
+        assert!(!vouches_for_its_code("unknown"));
+    }
+
+    #[test]
+    fn a_gold_artefacts_lineage_sits_beside_it() {
+        assert_eq!(
+            sidecar(Path::new(
+                "/store/gold/artifact=crossings/version=1/crossings.pointset"
+            )),
+            Path::new("/store/gold/artifact=crossings/version=1/crossings.pointset.lineage.json")
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! The live files of a dataset, as a store the engine can list.
+//!
+//! An engine discovers a table's partition keys from the `key=value` directories between
+//! the table's path and each file it lists, and reads everything it lists. A compacted or
+//! published dataset holds files a reader must not read beside the ones it must — see
+//! [`crate::Dataset::compact`] and [`crate::Rebuild`] — so it cannot be handed the directory,
+//! and a list of files names no directory to find the keys below.
+//!
+//! [`LiveFiles`] squares the two: it answers a listing of the dataset's directory with the
+//! live files alone, already known from the listing that found them, and reads every file
+//! from the store it wraps. Registered under a URL of its own, the dataset is one listing
+//! table over its directory, its keys found as the engine finds any partition's.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use datafusion::prelude::SessionContext;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path as ObjectPath;
+use object_store::{
+    GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, ObjectStore,
+    PutMultipartOptions, PutOptions, PutPayload, PutResult, Result,
+};
+use url::Url;
+
+use crate::store::Backend;
+
+/// The scheme a set of live files is registered under, each set at a host of its own.
+const SCHEME: &str = "medallion-live";
+
+/// How many files' metadata a listing asks the store for at once.
+const CONCURRENT_HEADS: usize = 16;
+
+/// Tells one registered set of live files from every other in the process.
+static REGISTERED: AtomicU64 = AtomicU64::new(0);
+
+/// A read-only view of a store holding only the files it was made with.
+#[derive(Debug)]
+pub(crate) struct LiveFiles {
+    inner: Arc<dyn ObjectStore>,
+    files: BTreeSet<ObjectPath>,
+}
+
+impl LiveFiles {
+    /// Register `files` of `backend` with `ctx`, and return the URL `dir` is listed at
+    /// there, with the trailing `/` that has the engine list it as a directory.
+    pub(crate) fn register(
+        ctx: &SessionContext,
+        backend: &Backend,
+        dir: &Path,
+        files: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<Url> {
+        let files = files
+            .into_iter()
+            .map(|file| backend.location(file.as_ref()))
+            .collect::<Result<_, _>>()?;
+        let live = Self {
+            inner: backend.object_store(),
+            files,
+        };
+        let dir = backend.location(dir)?;
+        let host = REGISTERED.fetch_add(1, Ordering::Relaxed);
+        let table = Url::parse(&format!("{SCHEME}://{host}/{dir}/")).map_err(|err| {
+            object_store::Error::Generic {
+                store: SCHEME,
+                source: Box::new(err),
+            }
+        })?;
+        ctx.register_object_store(&table, Arc::new(live));
+        Ok(table)
+    }
+
+    /// The live files at or below `prefix`, in key order.
+    fn below(&self, prefix: Option<&ObjectPath>) -> impl Iterator<Item = &ObjectPath> {
+        self.files
+            .iter()
+            .filter(move |file| prefix.is_none_or(|prefix| file.prefix_matches(prefix)))
+    }
+}
+
+impl Display for LiveFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} live files of {}", self.files.len(), self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LiveFiles {
+    async fn put_opts(&self, _: &ObjectPath, _: PutPayload, _: PutOptions) -> Result<PutResult> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        _: &ObjectPath,
+        _: PutMultipartOptions,
+    ) -> Result<Box<dyn MultipartUpload>> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    async fn get_opts(&self, location: &ObjectPath, options: GetOptions) -> Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn get_range(&self, location: &ObjectPath, range: Range<u64>) -> Result<Bytes> {
+        self.inner.get_range(location, range).await
+    }
+
+    async fn get_ranges(&self, location: &ObjectPath, ranges: &[Range<u64>]) -> Result<Vec<Bytes>> {
+        self.inner.get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &ObjectPath) -> Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, _: &ObjectPath) -> Result<()> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    /// The live files below `prefix`, each described by the store it is kept in.
+    fn list(&self, prefix: Option<&ObjectPath>) -> BoxStream<'static, Result<ObjectMeta>> {
+        let inner = self.inner.clone();
+        let below: Vec<ObjectPath> = self.below(prefix).cloned().collect();
+        futures::stream::iter(below)
+            .map(move |location| {
+                let inner = inner.clone();
+                async move { inner.head(&location).await }
+            })
+            .buffered(CONCURRENT_HEADS)
+            .boxed()
+    }
+
+    /// The live files directly in `prefix`, and the directories below it holding any.
+    async fn list_with_delimiter(&self, prefix: Option<&ObjectPath>) -> Result<ListResult> {
+        let prefix = prefix.cloned().unwrap_or_default();
+        let mut common_prefixes = BTreeSet::new();
+        let mut direct = Vec::new();
+        for file in self.below(Some(&prefix)) {
+            let mut parts = file.prefix_match(&prefix).into_iter().flatten();
+            match (parts.next(), parts.next()) {
+                (Some(dir), Some(_)) => {
+                    common_prefixes.insert(prefix.child(dir));
+                }
+                (Some(_), None) => direct.push(file.clone()),
+                (None, _) => {}
+            }
+        }
+        let objects = futures::stream::iter(direct)
+            .map(|location| async move { self.inner.head(&location).await })
+            .buffered(CONCURRENT_HEADS)
+            .try_collect()
+            .await?;
+        Ok(ListResult {
+            common_prefixes: common_prefixes.into_iter().collect(),
+            objects,
+        })
+    }
+
+    async fn copy(&self, _: &ObjectPath, _: &ObjectPath) -> Result<()> {
+        Err(object_store::Error::NotImplemented)
+    }
+
+    async fn copy_if_not_exists(&self, _: &ObjectPath, _: &ObjectPath) -> Result<()> {
+        Err(object_store::Error::NotImplemented)
+    }
+}
@@ -6,6 +6,9 @@ use std::str::FromStr;
 /// Date partition values, per `docs/medallion.md`.
 pub(crate) const DATE_FORMAT: &str = "%Y-%m-%d";
 
+/// The suffix a date-valued partition key ends with, as `docs/medallion.md` requires.
+pub(crate) const DATE_KEY_SUFFIX: &str = "_date";
+
 /// A key or value that does not meet the store's naming rules.
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum PathError {
@@ -15,6 +18,10 @@ pub enum PathError {
     Value(String),
     #[error("dataset `{0}` declares no partition key")]
     Unpartitioned(String),
+    #[error(
+        "dataset `{dataset}` is partitioned on `{key}`, which is not a date, so it has no range of dates to restrict it to"
+    )]
+    Undated { dataset: String, key: String },
 }
 
 /// The left-hand side of a `key=value` partition directory: snake_case, so it is also a
@@ -1,18 +1,25 @@
 //! Building paths into the store: `<root>/<layer>/<dataset>/<key=value>…/<file>.parquet`.
 
-use std::collections::HashSet;
-use std::fmt::Display;
+use std::fmt::{self, Display};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use arrow::array::RecordBatch;
 use chrono::{DateTime, NaiveDate, Utc};
 use datafusion::execution::SendableRecordBatchStream;
+use object_store::ObjectStore;
+use url::Url;
 
+use crate::compact::ListingError;
 use crate::dataset::DatasetSpec;
-use crate::geo::{GeoError, write_geo_batches, write_geo_stream};
+use crate::geo::{GeoError, write_geo_stream};
 use crate::layer::{Layer, LayerKind, Replaceable};
-use crate::partition::{DATE_FORMAT, Partition, PathError};
+use crate::lineage::{LineageError, Producer, Recording};
+use crate::partition::{DATE_FORMAT, DATE_KEY_SUFFIX, Partition, PathError};
+use crate::range::DateRange;
+use crate::rebuild::Published;
 use crate::rows::{Row, RowError, batch};
+use crate::store::Backend;
 use crate::write::{WriteError, write_batches};
 
 /// Failure appending rows to a dataset.
@@ -29,11 +36,7 @@ pub enum AppendError {
 /// Millisecond precision, because the name is what keeps one write from landing on another:
 /// a writer that batches — a drain, a backfill — issues several writes in quick succession,
 /// and at second resolution they would collide.
-const BATCH_STEM_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
-
-/// The file a wholly derived partition holds. Such a partition is one file, so its name
-/// carries no information and never varies.
-const PARTITION_STEM: &str = "part-0";
+pub(crate) const BATCH_STEM_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
 
 /// Failure replacing a dataset's partitions.
 #[derive(Debug, thiserror::Error)]
@@ -56,15 +59,58 @@ pub enum ReplaceError {
         #[source]
         source: std::io::Error,
     },
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error(transparent)]
+    Lineage(#[from] LineageError),
+    #[error("staging {path}: {source}")]
+    Stage {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("publishing {path}: {source}")]
+    Publish {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("deleting {path}, which nothing publishes any more: {source}")]
+    Delete {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{dataset} is not the dataset being rebuilt, which is {rebuilding}")]
+    NotRebuilding { dataset: String, rebuilding: String },
+    #[error("{dataset} is being replaced over {range}, which {date} is outside")]
+    OutsideRange {
+        dataset: &'static str,
+        date: NaiveDate,
+        range: DateRange,
+    },
+    /// A run that failed, and then failed to be abandoned: its own failure, shown, and what
+    /// went wrong deleting what it staged — which is left an orphan `clean` finds.
+    #[error("{failure}; abandoning the run failed too: {abandon}")]
+    Abandoned {
+        failure: String,
+        #[source]
+        abandon: Box<dyn std::error::Error + Send + Sync>,
+    },
 }
 
-/// Whether a partition's file holds geometry, and so which encoder writes it. A dataset
-/// with no geometry column cannot be written as GeoParquet, which describes the geometry
-/// columns of the file it is writing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Encoding {
-    Geo,
-    Plain,
+impl ReplaceError {
+    /// The run failure `failure`, with the failure `abandon` to abandon the run beside it
+    /// rather than lost.
+    pub fn abandoned(
+        failure: &impl Display,
+        abandon: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Abandoned {
+            failure: failure.to_string(),
+            abandon: abandon.into(),
+        }
+    }
 }
 
 /// What replacing a dataset's partitions did.
@@ -92,13 +138,101 @@ pub struct Written {
     pub rows: usize,
 }
 
-/// The root of a medallion store.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Root(PathBuf);
+/// The root of a medallion store: a directory on this machine, or a prefix in an object
+/// store.
+///
+/// The layout below it is the same either way, and so is every promise the layers make — see
+/// `docs/medallion.md`. A store in a bucket is how the layers nothing can re-derive are kept
+/// somewhere other than the one repo that wrote them.
+#[derive(Debug, Clone)]
+pub struct Root {
+    /// The store's directory; in an object store, its prefix as an absolute path, `/` for a
+    /// store at the top of its bucket.
+    path: PathBuf,
+    backend: Backend,
+    /// What a producer has read through this root, shared by its clones, where it is
+    /// recording the lineage of what it writes.
+    lineage: Option<Arc<Recording>>,
+}
+
+impl PartialEq for Root {
+    /// Two roots onto one store are the same root, whatever either is recording.
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.backend == other.backend
+    }
+}
+
+impl Eq for Root {}
+
+/// A failure opening the store a location names.
+#[derive(Debug, thiserror::Error)]
+pub enum OpenError {
+    #[error(transparent)]
+    NotFound(#[from] StoreNotFound),
+    #[error("opening the object store at {url}: {source}")]
+    ObjectStore {
+        url: String,
+        #[source]
+        source: object_store::Error,
+    },
+}
 
 impl Root {
+    /// The store in the directory `path`.
     pub fn new(path: impl Into<PathBuf>) -> Self {
-        Self(path.into())
+        Self {
+            path: path.into(),
+            backend: Backend::Local,
+            lineage: None,
+        }
+    }
+
+    /// The store at `url` in `store`: `s3://lookout/medallion` keeps it under the prefix
+    /// `medallion` of the bucket `lookout`, and `memory:///` at the top of an in-memory store.
+    ///
+    /// The store is given rather than built from the URL, so whoever holds it decides how it
+    /// is reached — its endpoint, its credentials, or that it is one a test holds in memory.
+    pub fn in_object_store(store: Arc<dyn ObjectStore>, url: &Url) -> Self {
+        let mut base = url.clone();
+        base.set_path("");
+        base.set_query(None);
+        base.set_fragment(None);
+        Self {
+            path: Path::new("/").join(url.path().trim_matches('/')),
+            backend: Backend::Objects { store, url: base },
+            lineage: None,
+        }
+    }
+
+    /// The store `location` names: a URL with a scheme an object store is reached by, such
+    /// as `s3://lookout/medallion`, or otherwise a directory.
+    ///
+    /// An object store is configured from the environment as its own tools would be — for
+    /// S3, `AWS_ACCESS_KEY_ID`, `AWS_REGION`, and `AWS_ENDPOINT` (with `AWS_ALLOW_HTTP` for
+    /// one without TLS) for anything S3-compatible that is not AWS — so no credential is ever
+    /// an argument.
+    pub fn open(location: &str) -> Result<Self, OpenError> {
+        let url = match Url::parse(location) {
+            // A one-letter scheme is a Windows drive, and `file:` names a directory anyway.
+            Ok(url) if url.scheme().len() > 1 && url.scheme() != "file" => url,
+            Ok(url) => {
+                return Ok(Self::new(
+                    url.to_file_path()
+                        .unwrap_or_else(|()| PathBuf::from(location)),
+                ));
+            }
+            Err(_) => return Ok(Self::new(location)),
+        };
+        let options = std::env::vars()
+            .map(|(key, value)| (key.to_ascii_lowercase(), value))
+            .filter(|(key, _)| key.starts_with(STORE_ENV_PREFIX));
+        let (store, _) = object_store::parse_url_opts(&url, options).map_err(|source| {
+            OpenError::ObjectStore {
+                url: location.to_string(),
+                source,
+            }
+        })?;
+        Ok(Self::in_object_store(Arc::from(store), &url))
     }
 
     /// The store in the repo this was run from: `data/medallion` under the workspace root.
@@ -112,16 +246,53 @@ impl Root {
         Ok(workspace_root()?.join(STORE_IN_REPO))
     }
 
+    /// The store's directory, or its prefix in an object store as an absolute path.
     pub fn path(&self) -> &Path {
-        &self.0
+        &self.path
+    }
+
+    pub(crate) fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// This root, recording on each file `producer` derives through it what it was derived
+    /// from: every dataset read through a [`Query`](crate::Query) over it, hashed as it was
+    /// read. See [`crate::lineage`].
+    ///
+    /// The recording is shared by every clone of the root, so a producer opens its root once,
+    /// recording, and passes that around as it would any other.
+    pub fn recording(self, producer: Producer) -> Self {
+        Self {
+            lineage: Some(Arc::new(Recording::new(producer))),
+            ..self
+        }
+    }
+
+    /// What this root has read, where it is recording.
+    pub(crate) fn lineage(&self) -> Option<&Recording> {
+        self.lineage.as_deref()
+    }
+
+    /// Note that the files below `dir` of the dataset in `dataset` were read, if this root is
+    /// recording what it reads.
+    pub(crate) fn note_read(
+        &self,
+        dataset: PathBuf,
+        dir: PathBuf,
+        restriction: Option<(&'static str, DateRange)>,
+    ) {
+        if let Some(recording) = &self.lineage {
+            recording.note(dataset, dir, restriction);
+        }
     }
 
     /// Start building a path into `dataset`.
     pub fn dataset<L: LayerKind>(&self, dataset: DatasetSpec<L>) -> Dataset<L> {
         Dataset {
-            root: self.0.clone(),
+            root: self.clone(),
             spec: dataset,
             partitions: Vec::new(),
+            range: DateRange::ALL,
         }
     }
 
@@ -139,6 +310,7 @@ impl Root {
     /// datasets an engine reads, and they still sit inside the store rather than beside it.
     /// The run is what versions them: a device holding one of these has no way to say which
     /// run produced it, so a rerun adds a version beside the last rather than replacing it.
+    /// Which version is live is the artefact's index's to say — see [`crate::gold`].
     pub fn gold_artefact(
         &self,
         artifact: &str,
@@ -147,7 +319,7 @@ impl Root {
     ) -> Result<PathBuf, PathError> {
         let version = run.format(BATCH_STEM_FORMAT).to_string();
         Ok(self
-            .0
+            .path
             .join(Layer::Gold.as_str())
             .join(Partition::new(ARTIFACT, artifact)?.to_string())
             .join(Partition::new(VERSION, version)?.to_string())
@@ -155,9 +327,24 @@ impl Root {
     }
 }
 
+impl Display for Root {
+    /// As it would be given to `--medallion-root`: a directory, or the object store's URL.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.backend {
+            Backend::Local => write!(f, "{}", self.path.display()),
+            Backend::Objects { .. } => write!(f, "{}", self.backend.uri(&self.path, false)),
+        }
+    }
+}
+
+/// What the environment variables an object store is configured from start with, lowercased:
+/// the ones S3's own tools read, and nothing that merely shares a name with a setting. S3 is
+/// the one object store this is built to reach; any other scheme fails to open.
+const STORE_ENV_PREFIX: &str = "aws_";
+
 /// How a gold artefact is laid out: what it is, and which run produced it.
-const ARTIFACT: &str = "artifact";
-const VERSION: &str = "version";
+pub(crate) const ARTIFACT: &str = "artifact";
+pub(crate) const VERSION: &str = "version";
 
 /// Where the store sits within the repo.
 const STORE_IN_REPO: &str = "data/medallion";
@@ -192,25 +379,18 @@ fn workspace_root() -> Result<PathBuf, StoreNotFound> {
         })
 }
 
-/// Whether `dir` holds any file, at any depth below it.
-fn holds_files(dir: &Path) -> bool {
-    std::fs::read_dir(dir).is_ok_and(|entries| {
-        entries.flatten().any(|entry| {
-            entry.path().is_dir() && holds_files(&entry.path()) || entry.path().is_file()
-        })
-    })
-}
-
-/// A location within one dataset: which dataset, and the partitions chosen so far.
+/// A location within one dataset: which dataset, the partitions chosen so far, and the
+/// dates it is restricted to.
 ///
 /// `L` is the dataset's layer, so what can be done to it follows from where it lives: the
 /// operations that rewrite or delete are implemented for [`Replaceable`] layers only, and a
 /// bronze dataset simply does not have them.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Dataset<L> {
-    root: PathBuf,
+    root: Root,
     spec: DatasetSpec<L>,
     partitions: Vec<Partition>,
+    range: DateRange,
 }
 
 impl<L: LayerKind> Dataset<L> {
@@ -233,10 +413,53 @@ impl<L: LayerKind> Dataset<L> {
         self.partition(key, id)
     }
 
+    /// Restrict this dataset to the partitions dated within `range`, wherever its dated
+    /// directories sit below the partitions chosen.
+    ///
+    /// Reading it then lists only those directories, and replacing it sweeps only among
+    /// them: a run over a range leaves every date outside it as the last run left it. A
+    /// dataset whose own key is not a date has no dates to restrict, which is a mismatch
+    /// with its definition rather than an empty range — unless the range is every date.
+    pub fn within(mut self, range: DateRange) -> Result<Self, PathError> {
+        if !range.is_all() {
+            let key = self.own_key()?;
+            if !key.ends_with(DATE_KEY_SUFFIX) {
+                return Err(PathError::Undated {
+                    dataset: self.spec.name.to_string(),
+                    key: key.to_string(),
+                });
+            }
+        }
+        self.range = range;
+        Ok(self)
+    }
+
+    /// The dates this dataset is restricted to; every date unless [`Self::within`] said
+    /// otherwise.
+    pub fn range(&self) -> DateRange {
+        self.range
+    }
+
+    /// Whether the `key=value` directory is one this dataset's range reaches: any directory
+    /// of another key, and a directory of the dated key only if its date is in range.
+    pub(crate) fn admits(&self, key: &str, value: &str) -> bool {
+        self.restricted()
+            .is_none_or(|(dated, range)| dated != key || range.admits(value))
+    }
+
+    /// The dated key a restriction applies to and the range it allows, or `None` where the
+    /// dataset is not restricted and every directory counts.
+    pub(crate) fn restricted(&self) -> Option<(&'static str, DateRange)> {
+        match (self.range.is_all(), self.spec.partition_key) {
+            (false, Some(key)) => Some((key, self.range)),
+            _ => None,
+        }
+    }
+
     /// The dataset's declared partition key, or a failure naming the dataset that has
     /// none — partitioning an unpartitioned dataset is a definition mismatch, not a path
     /// the caller can fix by escaping something.
-    fn own_key(&self) -> Result<&'static str, PathError> {
+    pub(crate) fn own_key(&self) -> Result<&'static str, PathError> {
         self.spec
             .partition_key
             .ok_or_else(|| PathError::Unpartitioned(self.spec.name.to_string()))
@@ -254,17 +477,47 @@ impl<L: LayerKind> Dataset<L> {
 
     /// The directory the partitions resolve to.
     pub fn dir(&self) -> PathBuf {
-        let mut dir = self.root.join(L::LAYER.as_str()).join(self.spec.name);
+        let mut dir = self
+            .root
+            .path()
+            .join(L::LAYER.as_str())
+            .join(self.spec.name);
         dir.extend(self.partitions.iter().map(Partition::to_string));
         dir
     }
 
+    /// The whole of the dataset this is a location in: no partitions chosen, and every date.
+    pub(crate) fn whole(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            spec: self.spec,
+            partitions: Vec::new(),
+            range: DateRange::ALL,
+        }
+    }
+
     /// Whether anything has been written here, at any depth below the partitions chosen.
     ///
     /// A directory of empty directories is what a swept dataset leaves, and counts as
-    /// nothing written: it reads the same as one that was never written at all.
-    pub fn holds_files(&self) -> bool {
-        holds_files(&self.dir())
+    /// nothing written: it reads the same as one that was never written at all. A dataset a
+    /// rebuild has published holds only the files it publishes, so one a run has staged
+    /// files into but not yet published holds what it held before.
+    pub async fn holds_files(&self) -> bool {
+        let dir = self.dir();
+        match Published::read(self.backend(), &self.whole().dir()).await {
+            Ok(Some(published)) => published.holds_below(&dir),
+            _ => self.backend().holds_files(&dir).await,
+        }
+    }
+
+    /// Where this dataset's files are kept.
+    pub(crate) fn backend(&self) -> &Backend {
+        self.root.backend()
+    }
+
+    /// The store this dataset is in.
+    pub(crate) fn root(&self) -> &Root {
+        &self.root
     }
 
     /// The file one batch captured at `at` lands in: a new file per write, named for the
@@ -273,28 +526,19 @@ impl<L: LayerKind> Dataset<L> {
         self.file(&at.format(BATCH_STEM_FORMAT).to_string())
     }
 
-    /// The file this partition's contents live in, replaced whenever they are derived again.
-    pub fn partition_file(&self) -> PathBuf {
-        self.file(PARTITION_STEM)
-    }
-
     /// Append `batches` as the capture made at `at`, leaving earlier captures untouched.
     ///
     /// A capture already written at `at` is not replaced: an append that would land on an
     /// existing file fails instead, since these layers are immutable and the rows already
-    /// there are not this caller's to discard.
+    /// there are not this caller's to discard. In an object store the store itself refuses
+    /// it, so two writers racing for one instant cannot both land.
     pub async fn append(
         &self,
         at: DateTime<Utc>,
         batches: &[RecordBatch],
     ) -> Result<PathBuf, WriteError> {
         let path = self.batch_file(at);
-        if path.exists() {
-            return Err(WriteError::Exists {
-                path: path.display().to_string(),
-            });
-        }
-        write_batches(&path, batches).await?;
+        write_batches(self.backend(), &path, batches).await?;
         Ok(path)
     }
 
@@ -326,13 +570,7 @@ impl<L: LayerKind> Dataset<L> {
         batches: SendableRecordBatchStream,
     ) -> Result<Written, GeoError> {
         let path = self.batch_file(at);
-        if path.exists() {
-            return Err(WriteError::Exists {
-                path: path.display().to_string(),
-            }
-            .into());
-        }
-        let rows = write_geo_stream(&path, batches).await?;
+        let rows = write_geo_stream(self.backend(), &path, batches).await?;
         Ok(Written { path, rows })
     }
 
@@ -348,19 +586,24 @@ impl<L: LayerKind> Dataset<L> {
 /// partitions a run no longer produces. They exist for silver and gold, so
 /// `root.rows_of::<RawSampleRow>().replace_dates_geo(…)` is not a call that can be written —
 /// bronze and landing hold what was observed, and nothing can derive that back.
+///
+/// Each is a [`Rebuild`](crate::Rebuild) of its own, published as it returns: a reader sees
+/// the dataset before the call or after it, never part of the way through. A run making
+/// several of these calls over one dataset makes them on one rebuild instead, so the whole
+/// run is what is published.
 impl<L: Replaceable> Dataset<L> {
-    /// Replace this partition's contents with `batches`.
-    pub async fn replace_with(&self, batches: &[RecordBatch]) -> Result<PathBuf, WriteError> {
-        let path = self.partition_file();
-        write_batches(&path, batches).await?;
-        Ok(path)
+    /// Replace this partition's contents with `batches`, returning the file they now live in.
+    pub async fn replace_with(&self, batches: &[RecordBatch]) -> Result<PathBuf, ReplaceError> {
+        let mut rebuild = self.rebuild().await?;
+        let result = rebuild.replace_with(self, batches).await;
+        rebuild.conclude(result).await
     }
 
     /// Replace this partition's contents with `batches`, as GeoParquet.
-    pub async fn replace_with_geo(&self, batches: &[RecordBatch]) -> Result<PathBuf, GeoError> {
-        let path = self.partition_file();
-        write_geo_batches(&path, batches).await?;
-        Ok(path)
+    pub async fn replace_with_geo(&self, batches: &[RecordBatch]) -> Result<PathBuf, ReplaceError> {
+        let mut rebuild = self.rebuild().await?;
+        let result = rebuild.replace_with_geo(self, batches).await;
+        rebuild.conclude(result).await
     }
 
     /// Replace this dataset's partitions with one file per dated batch, as GeoParquet.
@@ -370,11 +613,17 @@ impl<L: Replaceable> Dataset<L> {
     /// longer makes, and a reader has no way to tell it apart from a current one. Only
     /// directories under this dataset's own partition key are considered, so nothing
     /// outside what this dataset writes is ever removed.
+    ///
+    /// A dataset restricted [`within`](Self::within) a range is derived over that range
+    /// alone, so only the dates in it are swept, and a batch dated outside it is refused
+    /// rather than written somewhere the run was not asked to replace.
     pub async fn replace_dates_geo(
         &self,
         days: &[(NaiveDate, RecordBatch)],
     ) -> Result<Replaced, ReplaceError> {
-        self.replace_dates_as(days, Encoding::Geo).await
+        let mut rebuild = self.rebuild().await?;
+        let result = rebuild.replace_dates_geo(self, days).await;
+        rebuild.conclude(result).await
     }
 
     /// Replace this dataset's partitions with one file per dated batch, as plain parquet,
@@ -383,30 +632,9 @@ impl<L: Replaceable> Dataset<L> {
         &self,
         days: &[(NaiveDate, RecordBatch)],
     ) -> Result<Replaced, ReplaceError> {
-        self.replace_dates_as(days, Encoding::Plain).await
-    }
-
-    async fn replace_dates_as(
-        &self,
-        days: &[(NaiveDate, RecordBatch)],
-        encoding: Encoding,
-    ) -> Result<Replaced, ReplaceError> {
-        let mut written = HashSet::new();
-        for (date, batch) in days {
-            let partition = self.clone().on_date(*date)?;
-            let batch = std::slice::from_ref(batch);
-            match encoding {
-                Encoding::Geo => partition.replace_with_geo(batch).await.map(|_| ())?,
-                Encoding::Plain => partition.replace_with(batch).await.map(|_| ())?,
-            }
-            written.insert(partition.dir());
-        }
-
-        let removed = self.sweep(self.own_key()?, &written).await?;
-        Ok(Replaced {
-            written: written.len(),
-            removed,
-        })
+        let mut rebuild = self.rebuild().await?;
+        let result = rebuild.replace_dates(self, days).await;
+        rebuild.conclude(result).await
     }
 
     /// Delete every partition under `key` whose value is not among `values`, reporting how
@@ -419,64 +647,18 @@ impl<L: Replaceable> Dataset<L> {
     /// answer the last run gave rather than the union of every run so far.
     ///
     /// It follows that the caller has to have derived the whole dataset: a run over some of
-    /// the values would read as a run that produced nothing for the rest.
+    /// the values would read as a run that produced nothing for the rest. A dataset
+    /// restricted [`within`](Self::within) a range is the exception that proves it: there the
+    /// run has derived the whole of the range, so a value it produced nothing for loses its
+    /// dates in range and keeps the rest.
     pub async fn retain_partitions<V: Display>(
         &self,
         key: &str,
         values: &[V],
     ) -> Result<usize, ReplaceError> {
-        let keep = values
-            .iter()
-            .map(|value| Ok(self.dir().join(Partition::new(key, value)?.to_string())))
-            .collect::<Result<HashSet<PathBuf>, PathError>>()?;
-
-        self.sweep(key, &keep).await
-    }
-
-    /// Delete every directory of this dataset named for `key` except `keep`, reporting how
-    /// many went.
-    async fn sweep(&self, key: &str, keep: &HashSet<PathBuf>) -> Result<usize, ReplaceError> {
-        let dir = self.dir();
-        let mut entries = match tokio::fs::read_dir(&dir).await {
-            Ok(entries) => entries,
-            // A dataset nothing has been written to yet has no partitions to sweep.
-            Err(source) if source.kind() == std::io::ErrorKind::NotFound => return Ok(0),
-            Err(source) => {
-                return Err(ReplaceError::List {
-                    path: dir.display().to_string(),
-                    source,
-                });
-            }
-        };
-
-        let mut removed = 0;
-        while let Some(entry) = entries
-            .next_entry()
-            .await
-            .map_err(|source| ReplaceError::List {
-                path: dir.display().to_string(),
-                source,
-            })?
-        {
-            let path = entry.path();
-            let is_stale = path.is_dir()
-                && !keep.contains(&path)
-                && entry
-                    .file_name()
-                    .to_string_lossy()
-                    .starts_with(&format!("{key}="));
-            if is_stale {
-                tokio::fs::remove_dir_all(&path)
-                    .await
-                    .map_err(|source| ReplaceError::Remove {
-                        path: path.display().to_string(),
-                        source,
-                    })?;
-                removed += 1;
-            }
-        }
-
-        Ok(removed)
+        let mut rebuild = self.rebuild().await?;
+        let result = rebuild.retain_partitions(self, key, values);
+        rebuild.conclude(result).await
     }
 }
 
@@ -588,20 +770,6 @@ mod tests {
         assert!(root.gold_artefact("", run, "buffer").is_err());
     }
 
-    #[test]
-    fn a_partition_file_sits_under_the_partition_directory_with_a_parquet_extension() {
-        let path = root()
-            .dataset(SESSION)
-            .on_date(NaiveDate::from_ymd_opt(2026, 1, 2).unwrap())
-            .unwrap()
-            .partition_file();
-
-        assert_eq!(
-            path.to_str().unwrap(),
-            "/store/silver/session/start_date=2026-01-02/part-0.parquet"
-        );
-    }
-
     #[test]
     fn a_batch_file_is_named_for_the_instant_of_the_write() {
         let at = Utc.with_ymd_and_hms(2026, 7, 26, 14, 5, 30).unwrap();
@@ -848,6 +1016,106 @@ mod tests {
         assert_eq!(partitions_of(&dataset), ["start_date=2026-07-26"]);
     }
 
+    fn days(from: u32, to: u32) -> DateRange {
+        DateRange::new(Some(date(from)), Some(date(to))).unwrap()
+    }
+
+    /// A run over a range has derived the whole of the range and nothing else, so the dates
+    /// outside it stand whatever it produced.
+    #[tokio::test]
+    async fn a_range_sweeps_only_the_dates_in_it() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dataset = Root::new(tmp.path()).dataset(SESSION);
+        dataset
+            .replace_dates_geo(&[
+                (date(25), geo_batch()),
+                (date(26), geo_batch()),
+                (date(27), geo_batch()),
+            ])
+            .await
+            .expect("first run");
+
+        let replaced = dataset
+            .clone()
+            .within(days(26, 27))
+            .expect("a dated dataset")
+            .replace_dates_geo(&[(date(26), geo_batch())])
+            .await
+            .expect("run over the range");
+
+        assert_eq!(
+            replaced,
+            Replaced {
+                written: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(
+            partitions_of(&dataset),
+            ["start_date=2026-07-25", "start_date=2026-07-26"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_range_refuses_a_date_outside_it() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dataset = Root::new(tmp.path())
+            .dataset(SESSION)
+            .within(DateRange::on(date(26)))
+            .expect("a dated dataset");
+
+        let err = dataset
+            .replace_dates_geo(&[(date(27), geo_batch())])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ReplaceError::OutsideRange { .. }), "{err}");
+        assert!(!dataset.holds_files().await);
+    }
+
+    /// Above the dates, a value the run names nothing under loses only its dates in range:
+    /// the run has no view of the rest of it.
+    #[tokio::test]
+    async fn a_range_sweeps_within_a_value_it_no_longer_names_rather_than_removing_it() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let dataset = Root::new(tmp.path()).dataset(SESSION);
+        dataset
+            .clone()
+            .partition("country", "FR")
+            .expect("country")
+            .replace_dates_geo(&[(date(25), geo_batch()), (date(26), geo_batch())])
+            .await
+            .expect("write");
+
+        let removed = dataset
+            .clone()
+            .within(DateRange::on(date(26)))
+            .expect("a dated dataset")
+            .retain_partitions::<&str>("country", &[])
+            .await
+            .expect("retain");
+
+        assert_eq!(removed, 1);
+        let france = dataset.partition("country", "FR").expect("country");
+        assert_eq!(partitions_of(&france), ["start_date=2026-07-25"]);
+    }
+
+    #[test]
+    fn a_dataset_keyed_on_no_date_cannot_be_restricted_to_a_range() {
+        let err = root()
+            .dataset(OVERTURE_EXTRACT)
+            .within(DateRange::on(date(26)))
+            .unwrap_err();
+
+        assert!(matches!(err, PathError::Undated { .. }), "{err}");
+        assert!(
+            root()
+                .dataset(OVERTURE_EXTRACT)
+                .within(DateRange::ALL)
+                .is_ok()
+        );
+    }
+
     /// An append-only layer still takes a streamed write, which is how a query's results
     /// reach bronze: what it may not do is land on a capture already written.
     #[tokio::test]
@@ -3,14 +3,48 @@
 //! A dataset is registered as a table by name, which handles walking its partition
 //! directories and reading the geometry columns back with their CRS, so callers express
 //! what they want of a dataset as a query rather than as file traversal.
+//!
+//! That includes knowing which files to read. A compacted bronze partition holds the files a
+//! compaction merged beside the generation it merged them into, and a table reads the
+//! generation alone — see [`crate::Dataset::compact`]. A dataset restricted to a range of
+//! dates is listed only within it, so a run over a day reads that day's files rather than
+//! filtering every file's rows down to it. A dataset a rebuild has published is read as the
+//! files its pointer names, so a query sees one run's files and never a run still staging.
+//!
+//! A query holds at most [`MEMORY_LIMIT`] at once. The sorts, windows and aggregations that
+//! would hold more spill to disk instead, which is what lets a derivation order the whole of
+//! bronze without needing a machine the size of bronze to do it.
+
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
 
 use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::{DataType, SchemaRef};
+use datafusion::dataframe::DataFrame;
+use datafusion::datasource::file_format::options::ReadOptions;
+use datafusion::datasource::listing::{ListingTable, ListingTableConfig, ListingTableUrl};
+use datafusion::error::DataFusionError;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::execution::memory_pool::FairSpillPool;
+use datafusion::execution::runtime_env::RuntimeEnvBuilder;
+use datafusion::prelude::{SessionConfig, SessionContext};
+use futures::StreamExt;
 use sedona::context::SedonaContext;
 use sedona_geoparquet::provider::GeoParquetReadOptions;
 
-use crate::dataset::DatasetSpec;
+use crate::catalog::LayerSchema;
+use crate::compact::{
+    ListingError, LivePartition, first_live_partition, live_partitions, live_partitions_where,
+};
+use crate::dataset::{DatasetInfo, DatasetSpec};
 use crate::layer::LayerKind;
+use crate::live::LiveFiles;
+use crate::partition::PathError;
 use crate::path::{Dataset, Root};
+use crate::range::DateRange;
+use crate::rebuild::Published;
+use crate::store::Backend;
 
 /// A failure querying the store.
 #[derive(Debug, thiserror::Error)]
@@ -24,6 +58,26 @@ pub enum QueryError {
     DataFusion(#[from] datafusion::error::DataFusionError),
     #[error("reading rows: {0}")]
     Rows(#[from] serde_arrow::Error),
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error(transparent)]
+    Path(#[from] PathError),
+}
+
+/// A sedona session whose memory is a pool of `bytes` its operators spill to disk beyond.
+///
+/// Built as [`SedonaContext::new`] builds its own, on a runtime carrying the pool: the
+/// default one has no limit at all, so nothing in it ever spills.
+pub(crate) fn context(bytes: usize) -> SedonaContext {
+    let runtime = RuntimeEnvBuilder::new()
+        .with_memory_pool(Arc::new(FairSpillPool::new(bytes)))
+        .build_arc()
+        .expect("a runtime of the default disk manager and a fixed pool builds");
+    SedonaContext::new_from_context(SessionContext::new_with_config_rt(
+        SessionConfig::new(),
+        runtime,
+    ))
+    .expect("a sedona session builds over any runtime")
 }
 
 /// The single column a counting query returns. Its name is fixed, so callers alias their
@@ -33,6 +87,12 @@ struct Counted {
     count: i64,
 }
 
+/// How many bytes a session's queries may hold between them before what can spill does.
+///
+/// Shared fairly between the operators that spill, so one large sort does not starve the
+/// rest of the plan; what cannot spill fails the query rather than growing past it.
+pub const MEMORY_LIMIT: usize = 2 << 30;
+
 /// A SQL session over one medallion store.
 pub struct Query {
     root: Root,
@@ -40,11 +100,16 @@ pub struct Query {
 }
 
 impl Query {
+    /// A session over `root`, which reads it wherever it is kept.
     pub fn new(root: Root) -> Self {
-        Self {
-            root,
-            ctx: SedonaContext::new(),
-        }
+        Self::with_memory_limit(root, MEMORY_LIMIT)
+    }
+
+    /// A session over `root` whose queries hold no more than `bytes` before spilling.
+    pub fn with_memory_limit(root: Root, bytes: usize) -> Self {
+        let ctx = context(bytes);
+        root.backend().register(&ctx.ctx);
+        Self { root, ctx }
     }
 
     /// Register `dataset` from `layer` under `table`, so queries can name it.
@@ -60,6 +125,22 @@ impl Query {
         self.register_at(&self.root.dataset(dataset), table).await
     }
 
+    /// Register the partitions of `dataset` dated within `range` under `table`.
+    ///
+    /// Only the directories in range are listed, so a query over them costs what they hold
+    /// rather than what the dataset holds. A range the dataset holds nothing in is an empty
+    /// table of the dataset's columns, not an absent one: a run over a quiet day has nothing
+    /// to derive, which is not a failure.
+    pub async fn register_within<L: LayerKind>(
+        &self,
+        dataset: DatasetSpec<L>,
+        table: &str,
+        range: DateRange,
+    ) -> Result<(), QueryError> {
+        self.register_at(&self.root.dataset(dataset).within(range)?, table)
+            .await
+    }
+
     /// Register `dataset` as a table of its own name, for a query that reads it as what it
     /// is rather than under a name chosen for the query.
     pub async fn register_by_name<L: LayerKind>(
@@ -70,28 +151,60 @@ impl Query {
     }
 
     /// Register one partition of a dataset under `table`, for a dataset whose partitions
-    /// hold different schemas and so cannot be read as a single table.
+    /// hold different schemas and so cannot be read as a single table — or a dataset
+    /// restricted [`within`](Dataset::within) a range of dates, read as
+    /// [`Self::register_within`] reads one.
     ///
-    /// A dataset holding no files is absent, whether it was never written or a rebuild has
-    /// since swept every partition away: both leave a reader with nothing to read, and the
-    /// directory a sweep leaves behind is not something a caller should have to know about.
+    /// A dataset holding no files is [`QueryError::NoSuchDataset`], whether it was never
+    /// written or a rebuild has since swept every partition away. One that registers is an
+    /// input of whatever a [recording](Root::recording) root is used to write next.
     pub async fn register_at<L: LayerKind>(
         &self,
         dataset: &Dataset<L>,
         table: &str,
     ) -> Result<(), QueryError> {
-        if !dataset.holds_files() {
+        let Some(df) = read_table(
+            &self.ctx,
+            dataset.backend(),
+            &dataset.whole().dir(),
+            &dataset.dir(),
+            dataset.restricted(),
+        )
+        .await?
+        else {
             return Err(QueryError::NoSuchDataset {
                 layer: dataset.layer(),
                 dataset: dataset.name().to_string(),
             });
-        }
-        let dir = dataset.dir();
-        let df = self
-            .ctx
-            .read_parquet(dir.display().to_string(), GeoParquetReadOptions::default())
-            .await?;
+        };
         self.ctx.ctx.register_table(table, df.into_view())?;
+        dataset
+            .root()
+            .note_read(dataset.whole().dir(), dataset.dir(), dataset.restricted());
+        Ok(())
+    }
+
+    /// Expose every dataset of `datasets` as a table named for its layer and itself —
+    /// `bronze.gps_reading`, `silver.session` — with nothing registered by hand.
+    ///
+    /// A dataset is listed and read only once a query names it, so opening a catalog over
+    /// the whole store costs nothing up front, and is then read as [`Self::register`] reads
+    /// it. One holding no files is not there to name.
+    pub fn register_catalog(&self, datasets: &[DatasetInfo]) -> Result<(), QueryError> {
+        let name = self
+            .ctx
+            .ctx
+            .state()
+            .config_options()
+            .catalog
+            .default_catalog
+            .clone();
+        let catalog = self.ctx.ctx.catalog(&name).ok_or_else(|| {
+            DataFusionError::Plan(format!("the session has no catalog named {name}"))
+        })?;
+        for schema in LayerSchema::over(&self.root, datasets) {
+            catalog.register_schema(schema.layer().as_str(), Arc::new(schema))?;
+        }
         Ok(())
     }
 
@@ -103,7 +216,20 @@ impl Query {
         dataset: DatasetSpec<L>,
         table: &str,
     ) -> Result<bool, QueryError> {
-        match self.register(dataset, table).await {
+        self.register_if_present_within(dataset, table, DateRange::ALL)
+            .await
+    }
+
+    /// Register the partitions of `dataset` within `range` if the dataset exists, reporting
+    /// whether it did. Whether it exists is a question about the dataset, not the range: one
+    /// holding nothing in range is registered, empty.
+    pub async fn register_if_present_within<L: LayerKind>(
+        &self,
+        dataset: DatasetSpec<L>,
+        table: &str,
+        range: DateRange,
+    ) -> Result<bool, QueryError> {
+        match self.register_within(dataset, table, range).await {
             Ok(()) => Ok(true),
             Err(QueryError::NoSuchDataset { .. }) => Ok(false),
             Err(err) => Err(err),
@@ -115,6 +241,15 @@ impl Query {
         Ok(self.ctx.sql(sql).await?.collect().await?)
     }
 
+    /// Run `sql` and collect the result with its schema, for a caller handing the result on
+    /// as a table: a query that matches nothing returns no batches, and so nothing else to
+    /// read its columns from.
+    pub async fn table(&self, sql: &str) -> Result<(SchemaRef, Vec<RecordBatch>), QueryError> {
+        let frame = self.ctx.sql(sql).await?;
+        let schema = frame.schema().inner().clone();
+        Ok((schema, frame.collect().await?))
+    }
+
     /// Run a `SELECT COUNT(*) …` and return the count. The query must select exactly one
     /// row of one column.
     pub async fn count(&self, sql: &str) -> Result<i64, QueryError> {
@@ -131,12 +266,169 @@ impl Query {
     where
         T: for<'de> serde::Deserialize<'de>,
     {
+        let mut stream = self.stream_rows::<T>(sql).await?;
         let mut rows = Vec::new();
-        for batch in self.sql(sql).await? {
-            rows.extend(serde_arrow::from_record_batch::<Vec<T>>(&batch)?);
+        while let Some(batch) = stream.next().await? {
+            rows.extend(batch);
         }
         Ok(rows)
     }
+
+    /// Run `sql` and deserialise the result into `T` a batch at a time, for a result too
+    /// large to hold whole: only the batch being read is in memory as arrow, and what the
+    /// caller keeps of the rows is up to it.
+    ///
+    /// The batches arrive in the order the query states. An `ORDER BY` is still the engine's
+    /// to satisfy, and it sorts the whole result before the first batch arrives, so a caller
+    /// grouping by a key orders by that key and reads one group's rows at a time.
+    pub async fn stream_rows<T>(&self, sql: &str) -> Result<RowStream<T>, QueryError>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        Ok(RowStream {
+            batches: self.ctx.sql(sql).await?.execute_stream().await?,
+            rows: PhantomData,
+        })
+    }
+}
+
+/// The rows of one query, deserialised a batch at a time as they are read — see
+/// [`Query::stream_rows`].
+///
+/// It holds the running query rather than the session it was run in, so it can outlive the
+/// [`Query`] that started it.
+pub struct RowStream<T> {
+    batches: SendableRecordBatchStream,
+    rows: PhantomData<fn() -> T>,
+}
+
+impl<T> RowStream<T>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    /// The rows of the next batch, or `None` once the query has returned everything. A
+    /// batch can hold no rows, which is not the end.
+    pub async fn next(&mut self) -> Result<Option<Vec<T>>, QueryError> {
+        match self.batches.next().await {
+            None => Ok(None),
+            Some(batch) => Ok(Some(serde_arrow::from_record_batch(&batch?)?)),
+        }
+    }
+}
+
+/// The table the live files below `dir` make up, or `None` where it holds no files at all.
+///
+/// A dataset holding no files is absent, whether it was never written or a rebuild has since
+/// swept every partition away: both leave a reader with nothing to read, and the directory a
+/// sweep leaves behind is not something a caller should have to know about. Restricted to a
+/// range of one dated key, only the directories of that key in range are listed.
+///
+/// `dataset` is the directory of the whole dataset `dir` is in. Once a rebuild has published
+/// it, the files its pointer names are the ones read, and nothing else below it — see
+/// [`crate::Rebuild`].
+pub(crate) async fn read_table(
+    ctx: &SedonaContext,
+    backend: &Backend,
+    dataset: &Path,
+    dir: &Path,
+    restriction: Option<(&str, DateRange)>,
+) -> Result<Option<DataFrame>, QueryError> {
+    let admit = |key: &str, value: &str| {
+        restriction.is_none_or(|(dated, range)| key != dated || range.admits(value))
+    };
+    if let Some(published) = Published::read(backend, dataset).await? {
+        if let Some(df) =
+            read_live(ctx, backend, dir, &published.partitions_below(dir, &admit)).await?
+        {
+            return Ok(Some(df));
+        }
+        // As below, the columns of a range holding nothing are read from a partition that
+        // holds something, and a dataset holding nothing at all is absent.
+        let mut first = published.partitions_below(dir, &|_, _| true);
+        first.truncate(1);
+        return match read_live(ctx, backend, dir, &first).await? {
+            Some(df) => Ok(Some(df.limit(0, Some(0))?)),
+            None => Ok(None),
+        };
+    }
+
+    if !backend.holds_files(dir).await {
+        return Ok(None);
+    }
+    let df = match restriction {
+        None => match live_partitions(backend, dir).await? {
+            None => read_dir(ctx, backend, dir).await?,
+            Some(partitions) => match read_live(ctx, backend, dir, &partitions).await? {
+                Some(df) => df,
+                None => return Ok(None),
+            },
+        },
+        Some(_) => {
+            let partitions = live_partitions_where(backend, dir, &admit).await?;
+            match read_live(ctx, backend, dir, &partitions).await? {
+                Some(df) => df,
+                // The columns are the dataset's, read from the first partition holding
+                // anything rather than from every footer in the dataset.
+                None => match first_live_partition(backend, dir).await? {
+                    Some(first) => read_live(ctx, backend, dir, &[first])
+                        .await?
+                        .expect("a partition holding files reads as a table")
+                        .limit(0, Some(0))?,
+                    None => read_dir(ctx, backend, dir).await?.limit(0, Some(0))?,
+                },
+            }
+        }
+    };
+    Ok(Some(df))
+}
+
+/// Every file below `dir`, left to the engine to discover.
+async fn read_dir(
+    ctx: &SedonaContext,
+    backend: &Backend,
+    dir: &Path,
+) -> Result<DataFrame, QueryError> {
+    Ok(ctx
+        .read_parquet(backend.uri(dir, true), GeoParquetReadOptions::default())
+        .await?)
+}
+
+/// One table over the live files of a compacted, restricted or published dataset, below
+/// `dir`, or `None` for no partitions.
+///
+/// A single listing table over `dir`, listed through [`LiveFiles`] so that what it lists is
+/// the live files alone, and partitioned on the keys of the first partition: the engine
+/// reads each file's keys from its path, as string columns, just as it would discovering
+/// them itself. One table however many partitions it covers, so the plan a query makes of
+/// it does not grow with them.
+pub(crate) async fn read_live(
+    ctx: &SedonaContext,
+    backend: &Backend,
+    dir: &Path,
+    partitions: &[LivePartition],
+) -> Result<Option<DataFrame>, QueryError> {
+    let Some(first) = partitions.first() else {
+        return Ok(None);
+    };
+    let files = partitions.iter().flat_map(|partition| &partition.files);
+    let url = LiveFiles::register(&ctx.ctx, backend, dir, files).map_err(DataFusionError::from)?;
+
+    let keys = first
+        .keys
+        .iter()
+        .map(|(key, _)| (key.clone(), DataType::Utf8))
+        .collect();
+    let options = GeoParquetReadOptions::default()
+        .to_listing_options(&ctx.ctx.copied_config(), ctx.ctx.copied_table_options())
+        .with_table_partition_cols(keys);
+    let config = ListingTableConfig::new(ListingTableUrl::parse(url)?)
+        .with_listing_options(options)
+        .infer_schema(&ctx.ctx.state())
+        .await?;
+    Ok(Some(
+        ctx.ctx
+            .read_table(Arc::new(ListingTable::try_new(config)?))?,
+    ))
 }
 
 #[cfg(test)]
@@ -268,6 +560,76 @@ mod tests {
         );
     }
 
+    /// Read a batch at a time, the rows are the ones a collected read returns, in order.
+    #[tokio::test]
+    async fn streamed_rows_are_the_rows_a_collected_read_returns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_rows(tmp.path(), vec![1, 2, 3], vec!["a", "b", "c"]).await;
+        let query = Query::new(root);
+        query.register(THING, "thing").await.unwrap();
+
+        let mut stream = query
+            .stream_rows::<Row>("SELECT id, name FROM thing ORDER BY id")
+            .await
+            .unwrap();
+        let mut streamed = Vec::new();
+        while let Some(batch) = stream.next().await.unwrap() {
+            streamed.extend(batch);
+        }
+
+        assert_eq!(
+            streamed,
+            query
+                .rows::<Row>("SELECT id, name FROM thing ORDER BY id")
+                .await
+                .unwrap()
+        );
+        assert_eq!(streamed.len(), 3);
+    }
+
+    /// The stream holds the running query, so the session it was started from can go.
+    #[tokio::test]
+    async fn a_stream_outlives_the_query_that_started_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_rows(tmp.path(), vec![1, 2], vec!["a", "b"]).await;
+        let mut stream = {
+            let query = Query::new(root);
+            query.register(THING, "thing").await.unwrap();
+            query
+                .stream_rows::<Row>("SELECT id, name FROM thing")
+                .await
+                .unwrap()
+        };
+
+        let mut rows = 0;
+        while let Some(batch) = stream.next().await.unwrap() {
+            rows += batch.len();
+        }
+        assert_eq!(rows, 2);
+    }
+
+    /// An empty result is still a table of the query's columns, not an absence of one.
+    #[tokio::test]
+    async fn a_table_that_matches_nothing_keeps_its_columns() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_rows(tmp.path(), vec![1], vec!["a"]).await;
+        let query = Query::new(root);
+        query.register(THING, "thing").await.unwrap();
+
+        let (schema, batches) = query
+            .table("SELECT id, name FROM thing WHERE id > 1")
+            .await
+            .unwrap();
+
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 0);
+        let names: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(names, ["id", "name"]);
+    }
+
     #[tokio::test]
     async fn a_count_comes_back_as_a_number() {
         let tmp = tempfile::tempdir().unwrap();
@@ -296,6 +658,155 @@ mod tests {
         assert!(!query.register_if_present(NOTHING, "nothing").await.unwrap());
     }
 
+    const READING: DatasetSpec<layers::Bronze> =
+        DatasetSpec::partitioned("reading", "ingested_date");
+
+    /// A store holding one reading per day, each with its day as its id.
+    async fn store_with_days(dir: &std::path::Path, days: &[u32]) -> Root {
+        let root = Root::new(dir);
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        for day in days {
+            let at = Utc.with_ymd_and_hms(2026, 7, *day, 9, 0, 0).unwrap();
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Int64Array::from(vec![i64::from(*day)]))],
+            )
+            .unwrap();
+            root.dataset(READING)
+                .on_date(at.date_naive())
+                .unwrap()
+                .append(at, &[batch])
+                .await
+                .unwrap();
+        }
+        root
+    }
+
+    fn days(from: u32, to: u32) -> DateRange {
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2026, 7, day).unwrap();
+        DateRange::new(Some(date(from)), Some(date(to))).unwrap()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Read {
+        id: i64,
+        ingested_date: String,
+    }
+
+    /// Registered within a range, a dataset is the partitions in it — still carrying the
+    /// partition column discovery would have given it.
+    #[tokio::test]
+    async fn a_range_registers_only_the_partitions_in_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_days(tmp.path(), &[20, 21, 22, 23]).await;
+        let query = Query::new(root);
+        query
+            .register_within(READING, "reading", days(21, 22))
+            .await
+            .unwrap();
+
+        let read: Vec<Read> = query
+            .rows("SELECT id, ingested_date FROM reading ORDER BY id")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            read,
+            vec![
+                Read {
+                    id: 21,
+                    ingested_date: "2026-07-21".into()
+                },
+                Read {
+                    id: 22,
+                    ingested_date: "2026-07-22".into()
+                },
+            ]
+        );
+    }
+
+    /// However many partitions a range covers, it is read as one table rather than as a
+    /// union of a table per partition, which would grow with them.
+    #[tokio::test]
+    async fn a_range_of_many_partitions_is_one_scan() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_days(tmp.path(), &(1..=20).collect::<Vec<_>>()).await;
+        let query = Query::new(root);
+        query
+            .register_within(READING, "reading", days(2, 19))
+            .await
+            .unwrap();
+
+        let plan = query
+            .ctx
+            .ctx
+            .table("reading")
+            .await
+            .unwrap()
+            .create_physical_plan()
+            .await
+            .unwrap();
+        let plan = datafusion::physical_plan::displayable(plan.as_ref())
+            .indent(true)
+            .to_string();
+
+        assert!(!plan.contains("UnionExec"), "{plan}");
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM reading WHERE ingested_date = '2026-07-05'")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            query
+                .count("SELECT COUNT(*) AS count FROM reading")
+                .await
+                .unwrap(),
+            18
+        );
+    }
+
+    /// A quiet stretch is nothing to derive rather than a dataset that is not there, and has
+    /// the columns of one that is, its partition column among them.
+    #[tokio::test]
+    async fn a_range_holding_nothing_is_an_empty_table() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_days(tmp.path(), &[20]).await;
+        let query = Query::new(root);
+
+        assert!(
+            query
+                .register_if_present_within(READING, "reading", days(25, 26))
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            query
+                .count("SELECT COUNT(ingested_date) AS count FROM reading")
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    /// A dataset keyed on something other than a date has no dates to restrict it to.
+    #[tokio::test]
+    async fn a_dataset_not_keyed_on_a_date_cannot_be_registered_within_a_range() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = store_with_rows(tmp.path(), vec![1], vec!["a"]).await;
+
+        let err = Query::new(root)
+            .register_within(THING, "thing", days(21, 22))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, QueryError::Path(PathError::Undated { .. })),
+            "{err}"
+        );
+    }
+
     /// A rebuild that produces nothing sweeps every partition and leaves the dataset's own
     /// directory standing. That is not a dataset a reader can read, so it reads as absent
     /// rather than as a schema the engine cannot infer.
@@ -0,0 +1,231 @@
+//! A span of partition dates, for a run that reads or rewrites less than a whole dataset.
+//!
+//! A derivation is expressed over dates because that is what the layout partitions on: a
+//! range names the `<event>_date=` directories a run may read and the ones it may replace,
+//! and leaves every other directory exactly as it was. Both ends are inclusive and either
+//! may be open, so the unbounded range is the whole dataset — which is what every run meant
+//! before ranges existed.
+
+use std::fmt::{self, Display};
+
+use chrono::{Days, NaiveDate};
+
+use crate::partition::DATE_FORMAT;
+
+/// The dates from `from` to `to`, both included; an open end reaches as far as the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DateRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+/// A range whose start falls after its end, and so holds no date at all.
+///
+/// Refused rather than read as empty: a run over no dates would replace nothing and sweep
+/// nothing, which looks like success and is almost certainly the ends typed the wrong way
+/// round.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("the range starts on {from} but ends on {to}, before it starts")]
+pub struct EmptyRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    /// Every date: the whole dataset.
+    pub const ALL: Self = Self {
+        from: None,
+        to: None,
+    };
+
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Self, EmptyRange> {
+        match (from, to) {
+            (Some(from), Some(to)) if from > to => Err(EmptyRange { from, to }),
+            _ => Ok(Self { from, to }),
+        }
+    }
+
+    /// The one date `date`.
+    pub fn on(date: NaiveDate) -> Self {
+        Self {
+            from: Some(date),
+            to: Some(date),
+        }
+    }
+
+    pub fn from(&self) -> Option<NaiveDate> {
+        self.from
+    }
+
+    pub fn to(&self) -> Option<NaiveDate> {
+        self.to
+    }
+
+    /// Whether this is the whole dataset, in which case nothing is pruned.
+    pub fn is_all(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| from <= date) && self.to.is_none_or(|to| date <= to)
+    }
+
+    /// The same range starting `days` earlier, for reading what the dates in range were
+    /// derived from when that can have been recorded before them.
+    pub fn starting_earlier(self, days: u64) -> Self {
+        Self {
+            from: self.from.map(|from| from - Days::new(days)),
+            ..self
+        }
+    }
+
+    /// The same range ending `days` later.
+    pub fn ending_later(self, days: u64) -> Self {
+        Self {
+            to: self.to.map(|to| to + Days::new(days)),
+            ..self
+        }
+    }
+
+    /// The same range with no end, for a source partitioned on when something arrived
+    /// rather than when it happened: what happened in range can arrive any time after it.
+    pub fn open_ended(self) -> Self {
+        Self { to: None, ..self }
+    }
+
+    /// The same range with no start, for what something in range can have begun any time
+    /// before.
+    pub fn open_started(self) -> Self {
+        Self { from: None, ..self }
+    }
+
+    /// The dates in both this range and `other`, or `None` if they share none.
+    pub fn intersection(self, other: Self) -> Option<Self> {
+        let from = self.from.max(other.from);
+        let to = match (self.to, other.to) {
+            (Some(to), Some(other)) => Some(to.min(other)),
+            (to, other) => to.or(other),
+        };
+        Self::new(from, to).ok()
+    }
+
+    /// Whether the partition value `value` is a date in range. A value that is no date is
+    /// in no bounded range, so a directory a range cannot place is neither read nor swept.
+    pub(crate) fn admits(&self, value: &str) -> bool {
+        self.is_all()
+            || NaiveDate::parse_from_str(value, DATE_FORMAT).is_ok_and(|date| self.contains(date))
+    }
+}
+
+impl Display for DateRange {
+    /// As a Rust range is written, so an open end reads as one: `2026-07-01..=2026-07-31`,
+    /// `2026-07-01..`, `..`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(from) = self.from {
+            write!(f, "{}", from.format(DATE_FORMAT))?;
+        }
+        match self.to {
+            Some(to) => write!(f, "..={}", to.format(DATE_FORMAT)),
+            None => write!(f, ".."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 7, day).unwrap()
+    }
+
+    #[test]
+    fn both_ends_are_included() {
+        let range = DateRange::new(Some(date(10)), Some(date(12))).unwrap();
+
+        assert!(!range.contains(date(9)));
+        assert!(range.contains(date(10)));
+        assert!(range.contains(date(12)));
+        assert!(!range.contains(date(13)));
+    }
+
+    #[test]
+    fn an_open_end_reaches_as_far_as_the_data() {
+        let from = DateRange::new(Some(date(10)), None).unwrap();
+        let to = DateRange::new(None, Some(date(10))).unwrap();
+
+        assert!(from.contains(date(31)));
+        assert!(!from.contains(date(9)));
+        assert!(to.contains(date(1)));
+        assert!(!to.contains(date(11)));
+        assert!(DateRange::ALL.contains(date(1)));
+    }
+
+    #[test]
+    fn a_range_ending_before_it_starts_is_refused() {
+        assert_eq!(
+            DateRange::new(Some(date(12)), Some(date(10))),
+            Err(EmptyRange {
+                from: date(12),
+                to: date(10)
+            })
+        );
+    }
+
+    #[test]
+    fn widening_moves_only_the_ends_there_are() {
+        let range = DateRange::new(Some(date(10)), Some(date(12))).unwrap();
+
+        assert_eq!(
+            range.starting_earlier(2).ending_later(1),
+            DateRange::new(Some(date(8)), Some(date(13))).unwrap()
+        );
+        assert_eq!(DateRange::ALL.starting_earlier(2), DateRange::ALL);
+        assert_eq!(range.open_ended().to(), None);
+        assert_eq!(range.open_started().from(), None);
+        assert_eq!(range.open_started().to(), Some(date(12)));
+    }
+
+    #[test]
+    fn an_intersection_keeps_the_dates_both_ranges_hold() {
+        let range = |from, to| DateRange::new(from, to).unwrap();
+
+        assert_eq!(
+            range(Some(date(10)), Some(date(20))).intersection(range(Some(date(15)), None)),
+            Some(range(Some(date(15)), Some(date(20))))
+        );
+        assert_eq!(
+            DateRange::ALL.intersection(DateRange::on(date(10))),
+            Some(DateRange::on(date(10)))
+        );
+        assert_eq!(
+            DateRange::on(date(10)).intersection(DateRange::on(date(11))),
+            None
+        );
+    }
+
+    /// A directory under a date key that holds no date cannot be placed in a range, so a
+    /// bounded one leaves it alone rather than guessing.
+    #[test]
+    fn a_partition_value_that_is_no_date_is_in_no_bounded_range() {
+        let range = DateRange::on(date(10));
+
+        assert!(range.admits("2026-07-10"));
+        assert!(!range.admits("2026-07-11"));
+        assert!(!range.admits("latest"));
+        assert!(DateRange::ALL.admits("latest"));
+    }
+
+    #[test]
+    fn a_range_is_written_as_a_rust_range_is() {
+        let range = |from, to| DateRange::new(from, to).unwrap().to_string();
+
+        assert_eq!(
+            range(Some(date(1)), Some(date(31))),
+            "2026-07-01..=2026-07-31"
+        );
+        assert_eq!(range(Some(date(1)), None), "2026-07-01..");
+        assert_eq!(range(None, Some(date(31))), "..=2026-07-31");
+        assert_eq!(range(None, None), "..");
+    }
+}
@@ -0,0 +1,906 @@
+//! Rebuilding a derived dataset so a reader sees the whole of one run or the whole of the run
+//! before it, and never a mix of the two.
+//!
+//! A rebuild replaces many partitions, and replacing them one at a time leaves a window in
+//! which some are the new run's and the rest the old one's — a window that becomes permanent
+//! if the run fails half way. So a rebuild never writes over a file a reader might be reading:
+//!
+//!   - a partition's new file is **staged** beside the old, named for the md5 of its bytes,
+//!     `part-<md5>.parquet`. Rows a rerun derives the same are the same name, which the
+//!     dataset already holds, so they are not written again;
+//!   - the files a reader reads are the ones the dataset's **pointer** names, a small json
+//!     file at `<layer>/_current/<dataset>.json`. Publishing a rebuild is writing the pointer,
+//!     one put that is the whole switch from the old run's files to the new run's;
+//!   - only then is anything deleted: the files the pointer no longer names, and the
+//!     partition directories the run withdrew.
+//!
+//! A run that fails, or is never published, leaves the pointer naming the old files, and its
+//! staged files are deleted as it is abandoned. What an abandon cannot delete — a process
+//! killed mid-run has no chance to — is an orphan nothing reads, and [`Dataset::clean`]
+//! removes it.
+//!
+//! A dataset no rebuild has published yet has no pointer, and is read as every parquet file
+//! below it, as it always was. Its first rebuild records those files as the pointer before it
+//! stages anything, so the staged files are never read as the dataset either.
+//!
+//! Three things this does not do. A query planned before a publish reads the files it
+//! planned, and fails rather than mixing runs if they are deleted before it reads them. A
+//! [`clean`](Dataset::clean) run while a rebuild is staging deletes what it has staged, since
+//! it cannot tell staged from orphaned. And an engine reading the directories itself, rather
+//! than through [`crate::Query`], sees every file in them, staged or published.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Display;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use arrow::array::RecordBatch;
+use chrono::{DateTime, NaiveDate, Utc};
+use parquet::file::metadata::KeyValue;
+use serde::{Deserialize, Serialize};
+
+use crate::compact::{Admit, ListingError, LivePartition, is_parquet, partition_keys};
+use crate::geo::encode_geo_batches;
+use crate::layer::Replaceable;
+use crate::lineage;
+use crate::partition::{Partition, PathError};
+use crate::path::{Dataset, ReplaceError, Replaced};
+use crate::store::Backend;
+use crate::write::encode_batches;
+
+/// The directory of a layer the pointers of its datasets are kept in. Its leading `_` is one
+/// no snake_case dataset name has, so it cannot be taken for a dataset.
+pub(crate) const CURRENT: &str = "_current";
+
+/// What a staged file's name starts with, ahead of the md5 of its bytes.
+const PART_PREFIX: &str = "part-";
+
+/// The files one rebuild published, as recorded in the dataset's pointer.
+#[derive(Debug, Serialize, Deserialize)]
+struct Pointer {
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    published_at: DateTime<Utc>,
+    /// The files, relative to the dataset's directory, in path order.
+    files: Vec<String>,
+}
+
+/// Whether a partition's file holds geometry, and so which encoder writes it. A dataset
+/// with no geometry column cannot be written as GeoParquet, which describes the geometry
+/// columns of the file it is writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Geo,
+    Plain,
+}
+
+/// Whether a staged file replaces everything in its partition or only what other runs put
+/// there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placing {
+    Replace,
+    Add,
+}
+
+/// What cleaning a dataset deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cleaned {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// The files a dataset's pointer publishes, read for the readers that read only those.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Published {
+    files: BTreeSet<PathBuf>,
+}
+
+impl Published {
+    /// What the dataset in `dir` publishes, or `None` if no rebuild has published it yet —
+    /// in which case every parquet file below it is read.
+    pub(crate) async fn read(backend: &Backend, dir: &Path) -> Result<Option<Self>, ListingError> {
+        let path = pointer_of(dir);
+        let json = match backend.read(&path).await {
+            Ok(json) => json,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(ListingError::Io {
+                    path: path.display().to_string(),
+                    source,
+                });
+            }
+        };
+        let pointer: Pointer =
+            serde_json::from_slice(&json).map_err(|source| ListingError::Pointer {
+                path: path.display().to_string(),
+                source,
+            })?;
+        Ok(Some(Self {
+            files: pointer.files.iter().map(|file| dir.join(file)).collect(),
+        }))
+    }
+
+    /// Every file a reader reads.
+    pub(crate) fn files(&self) -> &BTreeSet<PathBuf> {
+        &self.files
+    }
+
+    /// Whether `file` is one a reader reads.
+    pub(crate) fn contains(&self, file: &Path) -> bool {
+        self.files.contains(file)
+    }
+
+    /// Whether anything published sits below `dir`.
+    pub(crate) fn holds_below(&self, dir: &Path) -> bool {
+        self.files.iter().any(|file| file.starts_with(dir))
+    }
+
+    /// The partitions below `dir` holding a published file, each with its published files,
+    /// leaving out any below a `key=value` directory `admit` refuses.
+    pub(crate) fn partitions_below(&self, dir: &Path, admit: &Admit<'_>) -> Vec<LivePartition> {
+        let mut partitions: BTreeMap<&Path, Vec<PathBuf>> = BTreeMap::new();
+        for file in self.files.iter().filter(|file| file.starts_with(dir)) {
+            if let Some(partition) = file.parent() {
+                partitions.entry(partition).or_default().push(file.clone());
+            }
+        }
+        partitions
+            .into_iter()
+            .map(|(partition, files)| LivePartition {
+                keys: partition_keys(dir, partition),
+                files,
+            })
+            .filter(|partition| partition.keys.iter().all(|(key, value)| admit(key, value)))
+            .collect()
+    }
+}
+
+/// Where the pointer of the dataset in `dir` is kept: beside the dataset rather than in it,
+/// so the dataset's directory holds its partitions and nothing else.
+fn pointer_of(dir: &Path) -> PathBuf {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    dir.with_file_name(CURRENT).join(format!("{name}.json"))
+}
+
+/// One run replacing partitions of a dataset, seen by no reader until it is published.
+///
+/// Each operation mirrors the [`Dataset`] method of the same name, and takes the partition
+/// or range it acts on as a [`Dataset`] of the one being rebuilt. A run replacing a dataset
+/// in several calls makes them all on one rebuild, and then [`publish`](Self::publish)es it
+/// — or [`conclude`](Self::conclude)s it with whatever the run returned, which publishes a
+/// run that succeeded and abandons one that failed. See the [module docs](self).
+#[must_use = "a rebuild changes nothing a reader sees until it is published"]
+pub struct Rebuild<L> {
+    /// The whole of the dataset being rebuilt.
+    dataset: Dataset<L>,
+    /// Whether the dataset has a pointer, so that what a reader reads is `base`.
+    recorded: bool,
+    /// The files a reader reads now.
+    base: BTreeSet<PathBuf>,
+    /// The files a reader will read once this is published.
+    live: BTreeSet<PathBuf>,
+    /// The files this run has written.
+    staged: BTreeSet<PathBuf>,
+    /// The files this run has placed in a partition, written or already there, which a file
+    /// [added](Self::add_geo) beside them leaves in place.
+    placed: BTreeSet<PathBuf>,
+    /// The partition directories this run withdrew, to be removed once nothing below them is
+    /// published.
+    withdrawn: BTreeSet<PathBuf>,
+}
+
+impl<L: Replaceable> Dataset<L> {
+    /// Start rebuilding the whole of this dataset, from what a reader reads of it now.
+    pub async fn rebuild(&self) -> Result<Rebuild<L>, ReplaceError> {
+        let dataset = self.whole();
+        let dir = dataset.dir();
+        let (recorded, base) = match Published::read(dataset.backend(), &dir).await? {
+            Some(published) => (true, published.files),
+            None => (false, unpublished(dataset.backend(), &dir).await?),
+        };
+        Ok(Rebuild {
+            dataset,
+            recorded,
+            live: base.clone(),
+            base,
+            staged: BTreeSet::new(),
+            placed: BTreeSet::new(),
+            withdrawn: BTreeSet::new(),
+        })
+    }
+
+    /// Delete every parquet file below this location that the dataset's pointer does not
+    /// publish: what a rebuild staged and was killed before it could publish or abandon.
+    ///
+    /// A dataset no rebuild has published has nothing unpublished, and is left as it is. Not
+    /// to be run while a rebuild of the dataset is staging, whose files it would delete.
+    pub async fn clean(&self) -> Result<Cleaned, ReplaceError> {
+        let backend = self.backend();
+        let Some(published) = Published::read(backend, &self.whole().dir()).await? else {
+            return Ok(Cleaned::default());
+        };
+
+        let mut cleaned = Cleaned::default();
+        for file in files_below(backend, &self.dir()).await? {
+            if !is_parquet(&file.path) || published.contains(&file.path) {
+                continue;
+            }
+            remove(backend, &file.path).await?;
+            cleaned.files += 1;
+            cleaned.bytes += file.bytes;
+        }
+        Ok(cleaned)
+    }
+}
+
+impl<L: Replaceable> Rebuild<L> {
+    /// Replace the contents of `partition` with `batches`, returning the file they will live
+    /// in.
+    pub async fn replace_with(
+        &mut self,
+        partition: &Dataset<L>,
+        batches: &[RecordBatch],
+    ) -> Result<PathBuf, ReplaceError> {
+        self.check(partition)?;
+        let lineage = self.lineage().await?;
+        self.stage(
+            partition,
+            encode_batches(batches, &lineage)?,
+            Placing::Replace,
+        )
+        .await
+    }
+
+    /// Replace the contents of `partition` with `batches`, as GeoParquet.
+    pub async fn replace_with_geo(
+        &mut self,
+        partition: &Dataset<L>,
+        batches: &[RecordBatch],
+    ) -> Result<PathBuf, ReplaceError> {
+        self.check(partition)?;
+        let lineage = self.lineage().await?;
+        self.stage(
+            partition,
+            encode_geo_batches(batches, &lineage)?,
+            Placing::Replace,
+        )
+        .await
+    }
+
+    /// Add `batches` to what this run has placed in `partition`, as GeoParquet, returning the
+    /// file they will live in.
+    ///
+    /// The first file this run places in a partition still replaces what a reader reads of
+    /// it; the ones after sit beside it. A run writing a partition in several pieces — a
+    /// device's rows at a time, say — writes each as it goes rather than holding the rest
+    /// until the last arrives. Unlike the others, this has no [`Dataset`] counterpart: a
+    /// partition written in pieces is only ever one run's.
+    pub async fn add_geo(
+        &mut self,
+        partition: &Dataset<L>,
+        batches: &[RecordBatch],
+    ) -> Result<PathBuf, ReplaceError> {
+        self.check(partition)?;
+        let lineage = self.lineage().await?;
+        self.stage(
+            partition,
+            encode_geo_batches(batches, &lineage)?,
+            Placing::Add,
+        )
+        .await
+    }
+
+    /// Replace the partitions of `dataset` with one file per dated batch, as GeoParquet, and
+    /// withdraw the dates no batch is for. See [`Dataset::replace_dates_geo`].
+    pub async fn replace_dates_geo(
+        &mut self,
+        dataset: &Dataset<L>,
+        days: &[(NaiveDate, RecordBatch)],
+    ) -> Result<Replaced, ReplaceError> {
+        self.replace_dates_as(dataset, days, Encoding::Geo).await
+    }
+
+    /// Replace the partitions of `dataset` with one file per dated batch, as plain parquet.
+    /// See [`Dataset::replace_dates`].
+    pub async fn replace_dates(
+        &mut self,
+        dataset: &Dataset<L>,
+        days: &[(NaiveDate, RecordBatch)],
+    ) -> Result<Replaced, ReplaceError> {
+        self.replace_dates_as(dataset, days, Encoding::Plain).await
+    }
+
+    async fn replace_dates_as(
+        &mut self,
+        dataset: &Dataset<L>,
+        days: &[(NaiveDate, RecordBatch)],
+        encoding: Encoding,
+    ) -> Result<Replaced, ReplaceError> {
+        self.check(dataset)?;
+        let range = dataset.range();
+        let lineage = self.lineage().await?;
+        let mut written = HashSet::new();
+        for (date, batch) in days {
+            if !range.contains(*date) {
+                return Err(ReplaceError::OutsideRange {
+                    dataset: dataset.name(),
+                    date: *date,
+                    range,
+                });
+            }
+            let partition = dataset.clone().on_date(*date)?;
+            let batch = std::slice::from_ref(batch);
+            let bytes = match encoding {
+                Encoding::Geo => encode_geo_batches(batch, &lineage)?,
+                Encoding::Plain => encode_batches(batch, &lineage)?,
+            };
+            self.stage(&partition, bytes, Placing::Replace).await?;
+            written.insert(partition.dir());
+        }
+
+        let removed = self.withdraw(dataset, dataset.own_key()?, &written);
+        Ok(Replaced {
+            written: written.len(),
+            removed,
+        })
+    }
+
+    /// Withdraw every partition of `dataset` under `key` whose value is not among `values`,
+    /// reporting how many go. See [`Dataset::retain_partitions`].
+    pub fn retain_partitions<V: Display>(
+        &mut self,
+        dataset: &Dataset<L>,
+        key: &str,
+        values: &[V],
+    ) -> Result<usize, ReplaceError> {
+        self.check(dataset)?;
+        let keep = values
+            .iter()
+            .map(|value| Ok(dataset.dir().join(Partition::new(key, value)?.to_string())))
+            .collect::<Result<HashSet<PathBuf>, PathError>>()?;
+
+        Ok(self.withdraw(dataset, key, &keep))
+    }
+
+    /// Switch readers to what this run produced, then delete what they no longer read.
+    ///
+    /// A run that changed nothing publishes nothing, so rerunning a derivation over the same
+    /// input leaves the store exactly as it was.
+    pub async fn publish(self) -> Result<(), ReplaceError> {
+        if self.live == self.base {
+            return self.abandon().await;
+        }
+        self.record(&self.live).await?;
+
+        let backend = self.dataset.backend();
+        for file in self.base.union(&self.staged) {
+            if !self.live.contains(file) {
+                remove(backend, file).await?;
+            }
+        }
+        for dir in &self.withdrawn {
+            if self.live.iter().any(|file| file.starts_with(dir)) {
+                continue;
+            }
+            match backend.remove_dir(dir).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(ReplaceError::Remove {
+                        path: dir.display().to_string(),
+                        source: err,
+                    });
+                }
+                _ => {}
+            }
+        }
+        // A pointer naming nothing is a dataset holding nothing, as no pointer and no files
+        // is; it goes only now the files are gone, so no moment reads the old ones again.
+        if self.live.is_empty() {
+            remove(backend, &pointer_of(&self.dataset.dir())).await?;
+        }
+        Ok(())
+    }
+
+    /// Give up on this run, deleting what it staged and leaving readers where they were.
+    pub async fn abandon(self) -> Result<(), ReplaceError> {
+        let backend = self.dataset.backend();
+        for file in self.staged.difference(&self.base) {
+            remove(backend, file).await?;
+        }
+        if self.recorded && self.base.is_empty() {
+            remove(backend, &pointer_of(&self.dataset.dir())).await?;
+        }
+        Ok(())
+    }
+
+    /// Publish this run if `result` says it succeeded, and abandon it if not, passing
+    /// `result` on — the end of a run written as one expression over the run's outcome.
+    ///
+    /// A failed run that then fails to be abandoned is [`ReplaceError::Abandoned`]: the run's
+    /// own failure comes first, as the one worth reporting, and the abandon's is beside it.
+    pub async fn conclude<T, E>(self, result: Result<T, E>) -> Result<T, E>
+    where
+        E: From<ReplaceError> + Display,
+    {
+        match result {
+            Ok(value) => {
+                self.publish().await?;
+                Ok(value)
+            }
+            Err(err) => match self.abandon().await {
+                Ok(()) => Err(err),
+                Err(abandon) => Err(ReplaceError::abandoned(&err, abandon).into()),
+            },
+        }
+    }
+
+    /// Refuse a location in any dataset other than the one being rebuilt, whose files this
+    /// rebuild knows nothing of.
+    fn check(&self, dataset: &Dataset<L>) -> Result<(), ReplaceError> {
+        match dataset.whole() == self.dataset {
+            true => Ok(()),
+            false => Err(ReplaceError::NotRebuilding {
+                dataset: dataset.dir().display().to_string(),
+                rebuilding: self.dataset.dir().display().to_string(),
+            }),
+        }
+    }
+
+    /// What the files this run writes record of what they were derived from: nothing, unless
+    /// the dataset's root is recording. See [`crate::lineage`].
+    async fn lineage(&self) -> Result<Vec<KeyValue>, ReplaceError> {
+        Ok(lineage::footer(self.dataset.root(), &self.dataset.dir()).await?)
+    }
+
+    /// Write `bytes` as a file `partition` will hold, unless it holds them already: the one
+    /// file, or one beside those this run has placed there already.
+    async fn stage(
+        &mut self,
+        partition: &Dataset<L>,
+        bytes: Vec<u8>,
+        placing: Placing,
+    ) -> Result<PathBuf, ReplaceError> {
+        self.adopt().await?;
+        let dir = partition.dir();
+        let path = dir.join(format!("{PART_PREFIX}{:x}.parquet", md5::compute(&bytes)));
+        if !self.live.contains(&path) {
+            self.dataset
+                .backend()
+                .put(&path, bytes)
+                .await
+                .map_err(|source| ReplaceError::Stage {
+                    path: path.display().to_string(),
+                    source,
+                })?;
+            self.staged.insert(path.clone());
+        }
+        let elsewhere = |file: &PathBuf| file.parent() != Some(dir.as_path());
+        if placing == Placing::Replace {
+            self.placed.retain(elsewhere);
+        }
+        let placed = &self.placed;
+        self.live
+            .retain(|file| elsewhere(file) || placed.contains(file));
+        self.live.insert(path.clone());
+        self.placed.insert(path.clone());
+        Ok(path)
+    }
+
+    /// Record what a reader reads now as the pointer, if nothing has, so that a reader of a
+    /// dataset no rebuild has published does not read what this one stages.
+    async fn adopt(&mut self) -> Result<(), ReplaceError> {
+        if !self.recorded {
+            self.record(&self.base).await?;
+            self.recorded = true;
+        }
+        Ok(())
+    }
+
+    /// Withdraw each directory of `dataset` named for `key` that is not among `keep` and
+    /// holds something a reader reads, reporting how many.
+    ///
+    /// Restricted to a range, a directory of the dated key is withdrawn only if its date is
+    /// in range, and a directory above the dated key is never withdrawn whole: its dates in
+    /// range are, as a run that produced none of them.
+    fn withdraw(&mut self, dataset: &Dataset<L>, key: &str, keep: &HashSet<PathBuf>) -> usize {
+        let dir = dataset.dir();
+        let named = format!("{key}=");
+        let mut withdrawn = BTreeSet::new();
+        for file in &self.live {
+            let Ok(below) = file.strip_prefix(&dir) else {
+                continue;
+            };
+            let mut parts = below
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy());
+            let Some(first) = parts.next() else {
+                continue;
+            };
+            let Some(value) = first.strip_prefix(&named) else {
+                continue;
+            };
+            let unit = dir.join(&*first);
+            if keep.contains(&unit) {
+                continue;
+            }
+            let unit = match dataset.restricted() {
+                None => unit,
+                Some((dated, range)) if dated == key => match range.admits(value) {
+                    true => unit,
+                    false => continue,
+                },
+                Some((dated, range)) => match parts.next() {
+                    Some(next)
+                        if next
+                            .strip_prefix(&format!("{dated}="))
+                            .is_some_and(|date| range.admits(date)) =>
+                    {
+                        unit.join(&*next)
+                    }
+                    _ => continue,
+                },
+            };
+            withdrawn.insert(unit);
+        }
+
+        let kept = |file: &PathBuf| !withdrawn.iter().any(|dir| file.starts_with(dir));
+        self.live.retain(kept);
+        self.placed.retain(kept);
+        let removed = withdrawn.len();
+        self.withdrawn.extend(withdrawn);
+        removed
+    }
+
+    /// Write `files` as the dataset's pointer, which is the moment a reader reads them.
+    async fn record(&self, files: &BTreeSet<PathBuf>) -> Result<(), ReplaceError> {
+        let dir = self.dataset.dir();
+        let path = pointer_of(&dir);
+        let pointer = Pointer {
+            published_at: Utc::now(),
+            files: files
+                .iter()
+                .map(|file| {
+                    file.strip_prefix(&dir)
+                        .unwrap_or(file)
+                        .display()
+                        .to_string()
+                })
+                .collect(),
+        };
+        let publish_error = |source: io::Error| ReplaceError::Publish {
+            path: path.display().to_string(),
+            source,
+        };
+        let json = serde_json::to_vec_pretty(&pointer)
+            .map_err(io::Error::from)
+            .map_err(publish_error)?;
+        self.dataset
+            .backend()
+            .put(&path, json)
+            .await
+            .map_err(publish_error)
+    }
+}
+
+/// What a reader of the dataset in `dir` reads while it has no pointer: every parquet file
+/// below it, less the hidden ones a write in progress on disk stages to.
+async fn unpublished(backend: &Backend, dir: &Path) -> Result<BTreeSet<PathBuf>, ReplaceError> {
+    Ok(files_below(backend, dir)
+        .await?
+        .into_iter()
+        .map(|file| file.path)
+        .filter(|file| is_parquet(file))
+        .filter(|file| {
+            !file
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect())
+}
+
+async fn files_below(
+    backend: &Backend,
+    dir: &Path,
+) -> Result<Vec<crate::store::Stored>, ReplaceError> {
+    backend
+        .files_below(dir)
+        .await
+        .map_err(|source| ReplaceError::List {
+            path: dir.display().to_string(),
+            source,
+        })
+}
+
+async fn remove(backend: &Backend, file: &Path) -> Result<(), ReplaceError> {
+    backend
+        .remove_file(file)
+        .await
+        .map_err(|source| ReplaceError::Delete {
+            path: file.display().to_string(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    use super::*;
+    use crate::dataset::DatasetSpec;
+    use crate::layer::layers;
+    use crate::path::Root;
+    use crate::query::Query;
+
+    const DAILY: DatasetSpec<layers::Silver> = DatasetSpec::partitioned("daily", "day");
+    const OTHER: DatasetSpec<layers::Silver> = DatasetSpec::partitioned("other", "day");
+
+    fn ids(ids: Vec<i64>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 7, day).unwrap()
+    }
+
+    /// The rows a reader reads of `DAILY`.
+    async fn rows_read(root: &Root) -> i64 {
+        let query = Query::new(root.clone());
+        query.register_by_name(DAILY).await.unwrap();
+        query
+            .count("SELECT COUNT(*) AS count FROM daily")
+            .await
+            .unwrap()
+    }
+
+    fn files_on_disk(root: &Root) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut unvisited = vec![root.dataset(DAILY).dir()];
+        while let Some(dir) = unvisited.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                match path.is_dir() {
+                    true => unvisited.push(path),
+                    false => files.push(path),
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    /// A partition's file is named for what it holds, so the same rows are the same file.
+    #[tokio::test]
+    async fn a_partition_file_is_named_for_its_contents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let partition = Root::new(tmp.path())
+            .dataset(DAILY)
+            .on_date(date(1))
+            .unwrap();
+
+        let first = partition.replace_with(&[ids(vec![1])]).await.unwrap();
+        let again = partition.replace_with(&[ids(vec![1])]).await.unwrap();
+        let other = partition.replace_with(&[ids(vec![2])]).await.unwrap();
+
+        assert_eq!(first.parent(), Some(partition.dir().as_path()));
+        let name = first.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(
+            name.starts_with(PART_PREFIX) && name.ends_with(".parquet") && name.len() == 45,
+            "unexpected name {name}"
+        );
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+    }
+
+    /// Nothing staged is read until the run is published, and then all of it is.
+    #[tokio::test]
+    async fn a_rebuild_is_read_whole_and_only_once_published() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        daily
+            .replace_dates(&[(date(1), ids(vec![1])), (date(2), ids(vec![2]))])
+            .await
+            .unwrap();
+
+        let mut rebuild = daily.rebuild().await.unwrap();
+        rebuild
+            .replace_dates(&daily, &[(date(2), ids(vec![3, 4, 5]))])
+            .await
+            .unwrap();
+        assert_eq!(rows_read(&root).await, 2, "staged rows were read");
+
+        rebuild.publish().await.unwrap();
+        assert_eq!(rows_read(&root).await, 3);
+    }
+
+    /// A run that fails leaves the dataset as the last run published it, with nothing of its
+    /// own left behind.
+    #[tokio::test]
+    async fn a_failed_run_leaves_the_last_published_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        daily
+            .replace_dates(&[(date(1), ids(vec![1])), (date(2), ids(vec![2]))])
+            .await
+            .unwrap();
+        let before = files_on_disk(&root);
+
+        let mut rebuild = daily.rebuild().await.unwrap();
+        let result = async {
+            rebuild
+                .replace_dates(&daily, &[(date(3), ids(vec![3]))])
+                .await?;
+            Err::<(), _>(ReplaceError::OutsideRange {
+                dataset: DAILY.name,
+                date: date(4),
+                range: crate::range::DateRange::on(date(3)),
+            })
+        }
+        .await;
+        let concluded = rebuild.conclude(result).await;
+
+        assert!(concluded.is_err());
+        assert_eq!(rows_read(&root).await, 2);
+        assert_eq!(files_on_disk(&root), before);
+    }
+
+    /// A directory where the run staged a file is something the abandon cannot delete.
+    #[tokio::test]
+    async fn a_failed_run_that_cannot_be_abandoned_reports_both_failures() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        daily
+            .replace_dates(&[(date(1), ids(vec![1]))])
+            .await
+            .unwrap();
+        let before = files_on_disk(&root);
+
+        let mut rebuild = daily.rebuild().await.unwrap();
+        rebuild
+            .replace_dates(&daily, &[(date(3), ids(vec![3]))])
+            .await
+            .unwrap();
+        let staged = files_on_disk(&root)
+            .into_iter()
+            .find(|file| !before.contains(file) && is_parquet(file))
+            .unwrap();
+        std::fs::remove_file(&staged).unwrap();
+        std::fs::create_dir(&staged).unwrap();
+        std::fs::write(staged.join("held"), b"").unwrap();
+        let failure = ReplaceError::OutsideRange {
+            dataset: DAILY.name,
+            date: date(4),
+            range: crate::range::DateRange::on(date(3)),
+        };
+        let shown = failure.to_string();
+
+        let err = rebuild.conclude(Err::<(), _>(failure)).await.unwrap_err();
+
+        assert!(matches!(err, ReplaceError::Abandoned { .. }), "{err}");
+        let err = err.to_string();
+        assert!(err.starts_with(&shown), "{err}");
+        assert!(
+            err.contains("abandoning the run failed too: deleting"),
+            "{err}"
+        );
+    }
+
+    /// Rows a rerun derives the same are neither written nor published again.
+    #[tokio::test]
+    async fn rerunning_with_the_same_rows_changes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        let days = [(date(1), ids(vec![1])), (date(2), ids(vec![2]))];
+        daily.replace_dates(&days).await.unwrap();
+        let pointer = pointer_of(&daily.dir());
+        let published = std::fs::read(&pointer).unwrap();
+        let modified = std::fs::metadata(&pointer).unwrap().modified().unwrap();
+
+        let replaced = daily.replace_dates(&days).await.unwrap();
+
+        assert_eq!(replaced.written, 2);
+        assert_eq!(std::fs::read(&pointer).unwrap(), published);
+        assert_eq!(
+            std::fs::metadata(&pointer).unwrap().modified().unwrap(),
+            modified
+        );
+    }
+
+    /// A run killed before it could publish or abandon leaves files nothing reads, which a
+    /// clean deletes and nothing else.
+    #[tokio::test]
+    async fn cleaning_deletes_only_what_nothing_publishes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        daily
+            .replace_dates(&[(date(1), ids(vec![1]))])
+            .await
+            .unwrap();
+        let published = files_on_disk(&root);
+        let mut killed = daily.rebuild().await.unwrap();
+        killed
+            .replace_dates(&daily, &[(date(2), ids(vec![2, 3]))])
+            .await
+            .unwrap();
+        drop(killed);
+
+        let cleaned = daily.clean().await.unwrap();
+
+        assert_eq!(cleaned.files, 1);
+        assert!(cleaned.bytes > 0);
+        assert_eq!(files_on_disk(&root), published);
+        assert_eq!(rows_read(&root).await, 1);
+    }
+
+    /// A dataset written before any rebuild published it is read as it always was, and the
+    /// first rebuild of it takes what it holds as its starting point.
+    #[tokio::test]
+    async fn a_dataset_with_no_pointer_is_adopted_as_it_stands() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let daily = root.dataset(DAILY);
+        let legacy = daily
+            .clone()
+            .on_date(date(1))
+            .unwrap()
+            .dir()
+            .join("part-0.parquet");
+        std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        std::fs::write(&legacy, encode_batches(&[ids(vec![1, 2])], &[]).unwrap()).unwrap();
+        assert_eq!(rows_read(&root).await, 2);
+
+        let mut rebuild = daily.rebuild().await.unwrap();
+        rebuild
+            .replace_with(&daily.clone().on_date(date(2)).unwrap(), &[ids(vec![3])])
+            .await
+            .unwrap();
+        assert_eq!(rows_read(&root).await, 2, "staged rows were read");
+        rebuild.publish().await.unwrap();
+
+        assert_eq!(rows_read(&root).await, 3);
+        assert!(legacy.exists(), "a partition the run left alone stands");
+    }
+
+    /// A rebuild emptied of every partition publishes nothing, and leaves no pointer behind
+    /// to say so.
+    #[tokio::test]
+    async fn a_rebuild_withdrawing_everything_leaves_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let daily = Root::new(tmp.path()).dataset(DAILY);
+        daily
+            .replace_dates(&[(date(1), ids(vec![1]))])
+            .await
+            .unwrap();
+
+        let replaced = daily.replace_dates(&[]).await.unwrap();
+
+        assert_eq!(replaced.removed, 1);
+        assert!(!daily.holds_files().await);
+        assert!(!pointer_of(&daily.dir()).exists());
+    }
+
+    /// A rebuild knows the files of one dataset, so it is refused a partition of another.
+    #[tokio::test]
+    async fn a_rebuild_is_refused_another_datasets_partition() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let mut rebuild = root.dataset(DAILY).rebuild().await.unwrap();
+
+        let err = rebuild
+            .replace_with(
+                &root.dataset(OTHER).on_date(date(1)).unwrap(),
+                &[ids(vec![1])],
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ReplaceError::NotRebuilding { .. }), "{err}");
+        rebuild.abandon().await.unwrap();
+    }
+}
@@ -10,6 +10,11 @@
 //! its instant columns and they are declared as timestamps here. This is the one place
 //! that rule is expressed, so datasets cannot drift apart on the representation of time.
 //!
+//! **A dataset states its own integrity.** The columns identifying a row, alone or together,
+//! and the columns holding another dataset's key are declared on the row type, so a writer
+//! refuses a repeated key as it writes and a verifier can check a store's references between
+//! datasets, with neither restating which columns those are.
+//!
 //! **A variant is stored as its name.** A column whose Rust type is an enum of dataless
 //! variants is a string column rather than a union, since engines vary in what they make
 //! of a union and a stored dataset must not depend on which one reads it.
@@ -23,7 +28,7 @@ use serde::{Deserialize, Serialize};
 use serde_arrow::schema::{SchemaLike, TracingOptions};
 use serde_json::json;
 
-use crate::dataset::DatasetSpec;
+use crate::dataset::{DatasetInfo, DatasetSpec};
 use crate::layer::LayerKind;
 
 /// How an instant column is stored, whatever integer the row type carries it as.
@@ -76,6 +81,65 @@ pub trait Row: Serialize + for<'de> Deserialize<'de> {
     /// where the dataset is defined rather than by each writer, and a reader can take an id
     /// to mean one row without checking.
     const UNIQUE: &'static [&'static str] = &[];
+
+    /// The sets of columns no two rows of the dataset may share values in all of — a row
+    /// identified by a tuple, such as a leg by its trip, the stop it leaves and when, where
+    /// no one of those columns is a name on its own.
+    ///
+    /// Two rows sharing some of a set's columns are distinct; only sharing every one is a
+    /// repeat. A null is a value like any other here, so two rows null in the same column
+    /// and equal in the rest are a repeat too.
+    const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[];
+
+    /// The columns holding another dataset's key, each a value every row's must be found
+    /// among — a sample's session, a match's crossing.
+    ///
+    /// Not checked as rows are written: the dataset referred to is often written by another
+    /// run, or by the same one afterwards, so a reference can only be checked of a store as
+    /// a whole. [`crate::verify::dataset`] does.
+    const REFERENCES: &'static [Reference] = &[];
+}
+
+/// A column holding a key of another dataset: which column, and where its values are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    /// The column of the dataset declaring it.
+    pub column: &'static str,
+    /// The dataset the values are keys of.
+    pub dataset: DatasetInfo,
+    /// The column of that dataset they are found in, which it declares unique.
+    pub key: &'static str,
+}
+
+impl Reference {
+    /// `column`, holding values of `R`'s `key` column.
+    pub const fn to<R: Row>(column: &'static str, key: &'static str) -> Self {
+        Self {
+            column,
+            dataset: R::DATASET.info(),
+            key,
+        }
+    }
+}
+
+/// Every set of columns identifying a row of a dataset declaring `unique` and `together`: a
+/// unique column as a set of one, then the sets declared as such.
+pub(crate) fn keys(
+    unique: &'static [&'static str],
+    together: &'static [&'static [&'static str]],
+) -> impl Iterator<Item = &'static [&'static str]> {
+    unique
+        .iter()
+        .map(std::slice::from_ref)
+        .chain(together.iter().copied())
+}
+
+/// A key as an error or a problem names it: its one column, or its columns as a tuple.
+pub(crate) fn key_name(key: &[&str]) -> String {
+    match key {
+        [column] => column.to_string(),
+        columns => format!("({})", columns.join(", ")),
+    }
 }
 
 /// A dataset partitioned by a date its own rows carry.
@@ -0,0 +1,514 @@
+//! Where a store's files are kept: in a directory on this machine, or in an object store.
+//!
+//! Everything that lists, reads, writes or deletes the store's files goes through a
+//! [`Backend`], so a [`Root`](crate::Root) in a bucket holds the same layout and keeps the same
+//! promises as one on disk. The two differ only in how they keep them:
+//!
+//!   - a file is never seen half-written. On disk it is staged beside its path and renamed
+//!     into place; an object appears only once its upload completes;
+//!   - an append never replaces a capture. On disk the path is checked before the write; in
+//!     an object store the write is a conditional put the store itself refuses when the
+//!     object exists, so there is no moment between checking and writing for another writer
+//!     to land in;
+//!   - a directory is a key prefix in an object store, and exists only while an object sits
+//!     below it. Removing a partition deletes every object under its prefix, and a dataset
+//!     swept of every partition is one no listing finds — the same answer an empty directory
+//!     on disk gives.
+//!
+//! Paths stay [`Path`]s either way. An object store's are absolute paths whose components are
+//! its key's segments, `/medallion/bronze/gps_reading/…` for the key
+//! `medallion/bronze/gps_reading/…`, so the layout is built, compared and logged once for
+//! both.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::datatypes::SchemaRef;
+use bytes::Bytes;
+use datafusion::prelude::SessionContext;
+use futures::{StreamExt, TryStreamExt};
+use object_store::local::LocalFileSystem;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutMode, UpdateVersion};
+use parquet::arrow::ParquetRecordBatchStreamBuilder;
+use parquet::arrow::async_reader::{ParquetObjectReader, ParquetRecordBatchStream};
+use parquet::errors::ParquetError;
+use parquet::file::metadata::ParquetMetaData;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use url::Url;
+
+/// Where a store's files are kept.
+#[derive(Debug, Clone)]
+pub(crate) enum Backend {
+    /// A directory on this machine; a path is the file's own.
+    Local,
+    /// An object store; a path is a key within it.
+    Objects {
+        store: Arc<dyn ObjectStore>,
+        /// The store's scheme and bucket alone, `s3://lookout`, which is what an engine
+        /// reading a path is told to find the store by.
+        url: Url,
+    },
+}
+
+impl PartialEq for Backend {
+    /// Two handles onto one store are the same backend; two stores at one URL — two
+    /// in-memory stores, say — are not.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Local, Self::Local) => true,
+            (
+                Self::Objects { store, url },
+                Self::Objects {
+                    store: other_store,
+                    url: other_url,
+                },
+            ) => url == other_url && Arc::ptr_eq(store, other_store),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Backend {}
+
+/// What one directory directly holds: its directories and its files, each in name order.
+#[derive(Debug, Default)]
+pub(crate) struct Listed {
+    pub(crate) dirs: Vec<PathBuf>,
+    pub(crate) files: Vec<Stored>,
+}
+
+/// A file in the store, and its size — which a listing gives for free, so nothing asks for
+/// it again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Stored {
+    pub(crate) path: PathBuf,
+    pub(crate) bytes: u64,
+}
+
+/// Which version of a file the store holds, as its metadata says without the file being
+/// read: its size, when it was last written, and the tag the store gives that version where
+/// it keeps one. A file nothing has written to since is described the same way twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Version {
+    pub(crate) bytes: u64,
+    /// Nanoseconds since the epoch, as precisely as the store keeps them.
+    pub(crate) modified: i128,
+    pub(crate) e_tag: Option<String>,
+}
+
+impl Backend {
+    /// The key `path` is kept under. A path into either backend is absolute — an object
+    /// store's start at its own `/` — so a relative one names nothing in the store.
+    pub(crate) fn location(&self, path: &Path) -> Result<ObjectPath, object_store::path::Error> {
+        match self {
+            Self::Local => ObjectPath::from_absolute_path(path),
+            Self::Objects { .. } if !path.is_absolute() => {
+                Err(object_store::path::Error::InvalidPath {
+                    path: path.to_path_buf(),
+                })
+            }
+            Self::Objects { .. } => {
+                ObjectPath::parse(path.to_string_lossy().trim_start_matches('/'))
+            }
+        }
+    }
+
+    /// The object store this backend's keys are kept in: the local filesystem's own, for a
+    /// directory on this machine.
+    pub(crate) fn object_store(&self) -> Arc<dyn ObjectStore> {
+        match self {
+            Self::Local => Arc::new(LocalFileSystem::new()),
+            Self::Objects { store, .. } => store.clone(),
+        }
+    }
+
+    /// The path an object store's key `location` is known by.
+    fn path_of(location: &ObjectPath) -> PathBuf {
+        Path::new("/").join(location.as_ref())
+    }
+
+    /// Where an engine finds `path`. A directory is written with a trailing `/` in an object
+    /// store, which is what tells the engine to list the prefix rather than open one object.
+    pub(crate) fn uri(&self, path: &Path, dir: bool) -> String {
+        match self {
+            Self::Local => path.display().to_string(),
+            Self::Objects { url, .. } => format!(
+                "{}://{}{}{}",
+                url.scheme(),
+                url.authority(),
+                path.display(),
+                if dir { "/" } else { "" }
+            ),
+        }
+    }
+
+    /// Let `ctx` read this backend's paths. The local filesystem is always registered, so
+    /// only an object store needs to be.
+    pub(crate) fn register(&self, ctx: &SessionContext) {
+        if let Self::Objects { store, url } = self {
+            ctx.register_object_store(url, store.clone());
+        }
+    }
+
+    /// What `dir` directly holds. A directory that does not exist holds nothing, which is
+    /// how a dataset nothing has written to lists.
+    pub(crate) async fn list(&self, dir: &Path) -> io::Result<Listed> {
+        let mut listed = Listed::default();
+        match self {
+            Self::Local => {
+                let entries = match std::fs::read_dir(dir) {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(listed),
+                    Err(err) => return Err(err),
+                };
+                for entry in entries {
+                    let path = entry?.path();
+                    let metadata = std::fs::metadata(&path)?;
+                    match metadata.is_dir() {
+                        true => listed.dirs.push(path),
+                        false => listed.files.push(Stored {
+                            path,
+                            bytes: metadata.len(),
+                        }),
+                    }
+                }
+            }
+            Self::Objects { store, .. } => {
+                let prefix = self.location(dir).map_err(io::Error::other)?;
+                let result = store.list_with_delimiter(Some(&prefix)).await?;
+                listed.dirs = result.common_prefixes.iter().map(Self::path_of).collect();
+                listed.files = result
+                    .objects
+                    .iter()
+                    .map(|object| Stored {
+                        path: Self::path_of(&object.location),
+                        bytes: object.size,
+                    })
+                    .collect();
+            }
+        }
+        listed.dirs.sort();
+        listed.files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(listed)
+    }
+
+    /// Whether `dir` holds any file, at any depth below it.
+    pub(crate) async fn holds_files(&self, dir: &Path) -> bool {
+        match self {
+            Self::Local => holds_files(dir),
+            Self::Objects { store, .. } => match self.location(dir) {
+                Ok(prefix) => store
+                    .list(Some(&prefix))
+                    .next()
+                    .await
+                    .is_some_and(|object| object.is_ok()),
+                Err(_) => false,
+            },
+        }
+    }
+
+    /// The file at `path`, with its size.
+    pub(crate) async fn stored(&self, path: &Path) -> io::Result<Stored> {
+        let bytes = match self {
+            Self::Local => tokio::fs::metadata(path).await?.len(),
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                store.head(&location).await?.size
+            }
+        };
+        Ok(Stored {
+            path: path.to_path_buf(),
+            bytes,
+        })
+    }
+
+    /// The version of the file at `path` the store holds, asked of its metadata alone.
+    pub(crate) async fn version(&self, path: &Path) -> io::Result<Version> {
+        match self {
+            Self::Local => {
+                let metadata = tokio::fs::metadata(path).await?;
+                let modified = metadata
+                    .modified()?
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_err(io::Error::other)?;
+                Ok(Version {
+                    bytes: metadata.len(),
+                    modified: modified.as_nanos().try_into().unwrap_or(i128::MAX),
+                    e_tag: None,
+                })
+            }
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                let meta = store.head(&location).await?;
+                Ok(Version {
+                    bytes: meta.size,
+                    modified: meta
+                        .last_modified
+                        .timestamp_nanos_opt()
+                        .map_or(i128::MAX, i128::from),
+                    e_tag: meta.e_tag,
+                })
+            }
+        }
+    }
+
+    /// The whole of the file at `path`.
+    pub(crate) async fn read(&self, path: &Path) -> io::Result<Bytes> {
+        match self {
+            Self::Local => Ok(Bytes::from(tokio::fs::read(path).await?)),
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                Ok(store.get(&location).await?.bytes().await?)
+            }
+        }
+    }
+
+    /// The whole of the file at `path`, and the version of it that was read.
+    pub(crate) async fn read_versioned(&self, path: &Path) -> io::Result<(Bytes, Version)> {
+        match self {
+            // The version is taken before the bytes, so a write landing between the two
+            // leaves a version older than what was read — which a later conditional put
+            // takes for a change, never for no change.
+            Self::Local => {
+                let version = self.version(path).await?;
+                Ok((self.read(path).await?, version))
+            }
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                let got = store.get(&location).await?;
+                let version = Version {
+                    bytes: got.meta.size,
+                    modified: got
+                        .meta
+                        .last_modified
+                        .timestamp_nanos_opt()
+                        .map_or(i128::MAX, i128::from),
+                    e_tag: got.meta.e_tag.clone(),
+                };
+                Ok((got.bytes().await?, version))
+            }
+        }
+    }
+
+    /// Write `bytes` to `path` as [`put`](Self::put) does, but only if the file there is
+    /// still `expected` — the version that was read to work them out — or, for `None`, only
+    /// if there is no file there. `false` is a file that changed meanwhile, and then nothing
+    /// was written.
+    ///
+    /// In an object store this is a conditional put the store itself refuses. On disk,
+    /// creating is a hard link that fails where a file exists; replacing compares the
+    /// file's version just before the rename, which leaves a moment between the two for
+    /// another writer on this machine to land in.
+    pub(crate) async fn put_if(
+        &self,
+        path: &Path,
+        bytes: Vec<u8>,
+        expected: Option<&Version>,
+    ) -> io::Result<bool> {
+        match self {
+            Self::Local => {
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let staged = path.with_file_name(format!(".{name}"));
+                tokio::fs::write(&staged, bytes).await?;
+                let placed = match expected {
+                    None => match tokio::fs::hard_link(&staged, path).await {
+                        Ok(()) => true,
+                        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => false,
+                        Err(err) => return Err(err),
+                    },
+                    Some(expected) => match self.version(path).await {
+                        Ok(current) if current == *expected => {
+                            tokio::fs::rename(&staged, path).await?;
+                            return Ok(true);
+                        }
+                        Ok(_) => false,
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => false,
+                        Err(err) => return Err(err),
+                    },
+                };
+                tokio::fs::remove_file(&staged).await?;
+                Ok(placed)
+            }
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                let mode = match expected {
+                    None => PutMode::Create,
+                    Some(expected) => PutMode::Update(UpdateVersion {
+                        e_tag: expected.e_tag.clone(),
+                        version: None,
+                    }),
+                };
+                match store.put_opts(&location, bytes.into(), mode.into()).await {
+                    Ok(_) => Ok(true),
+                    Err(
+                        object_store::Error::AlreadyExists { .. }
+                        | object_store::Error::Precondition { .. },
+                    ) => Ok(false),
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+
+    /// Write `bytes` to `path` whole or not at all, replacing anything there: on disk to a
+    /// hidden sibling first and then renamed, so a reader never sees half of it.
+    pub(crate) async fn put(&self, path: &Path, bytes: Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Local => {
+                if let Some(dir) = path.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let staged = path.with_file_name(format!(".{name}"));
+                tokio::fs::write(&staged, bytes).await?;
+                tokio::fs::rename(&staged, path).await
+            }
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                store.put(&location, bytes.into()).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Every file below `dir`, at any depth, in path order. A directory that does not exist
+    /// holds none.
+    pub(crate) async fn files_below(&self, dir: &Path) -> io::Result<Vec<Stored>> {
+        let mut files = Vec::new();
+        match self {
+            Self::Local => {
+                let mut unvisited = vec![dir.to_path_buf()];
+                while let Some(dir) = unvisited.pop() {
+                    let listed = self.list(&dir).await?;
+                    files.extend(listed.files);
+                    unvisited.extend(listed.dirs);
+                }
+            }
+            Self::Objects { store, .. } => {
+                let prefix = self.location(dir).map_err(io::Error::other)?;
+                let mut objects = store.list(Some(&prefix));
+                while let Some(object) = objects.next().await {
+                    let object = object?;
+                    files.push(Stored {
+                        path: Self::path_of(&object.location),
+                        bytes: object.size,
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Delete the file at `path`. One already gone is what deleting it would have left, so
+    /// is no failure.
+    pub(crate) async fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let removed = match self {
+            Self::Local => tokio::fs::remove_file(path).await,
+            Self::Objects { store, .. } => {
+                let location = self.location(path).map_err(io::Error::other)?;
+                store.delete(&location).await.map_err(io::Error::from)
+            }
+        };
+        match removed {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Delete `dir` and everything below it.
+    pub(crate) async fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+        match self {
+            Self::Local => tokio::fs::remove_dir_all(dir).await,
+            Self::Objects { store, .. } => {
+                let prefix = self.location(dir).map_err(io::Error::other)?;
+                let objects = store
+                    .list(Some(&prefix))
+                    .map_ok(|object| object.location)
+                    .boxed();
+                store.delete_stream(objects).try_collect::<Vec<_>>().await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// The rows the parquet file `file` declares in its footer, read without reading the
+    /// file. See [`Self::footer`].
+    pub(crate) async fn rows_in(&self, file: &Stored) -> Result<u64, ParquetError> {
+        let rows = self.footer(file).await?.file_metadata().num_rows();
+        Ok(rows.max(0) as u64)
+    }
+
+    /// The footer of the parquet file `file`, read without reading the file: the footer alone
+    /// is fetched from an object store, by a range request from the end of an object whose
+    /// size the listing already gave.
+    pub(crate) async fn footer(&self, file: &Stored) -> Result<Arc<ParquetMetaData>, ParquetError> {
+        match self {
+            Self::Local => {
+                let opened = std::fs::File::open(&file.path).map_err(ParquetError::from)?;
+                Ok(Arc::new(
+                    SerializedFileReader::new(opened)?.metadata().clone(),
+                ))
+            }
+            Self::Objects { store, .. } => {
+                let location = self
+                    .location(&file.path)
+                    .map_err(|err| ParquetError::External(Box::new(err)))?;
+                let reader =
+                    ParquetObjectReader::new(store.clone(), location).with_file_size(file.bytes);
+                Ok(ParquetRecordBatchStreamBuilder::new(reader)
+                    .await?
+                    .metadata()
+                    .clone())
+            }
+        }
+    }
+
+    /// A reader of the parquet file at `path`, which fetches its footer and then only the
+    /// row groups and columns it is asked for, rather than the whole file. `bytes` is the
+    /// file's size where a listing already gave it, which saves asking the store for it.
+    pub(crate) async fn parquet(
+        &self,
+        path: &Path,
+        bytes: Option<u64>,
+    ) -> Result<ParquetRecordBatchStreamBuilder<ParquetObjectReader>, ParquetError> {
+        let location = self
+            .location(path)
+            .map_err(|err| ParquetError::External(Box::new(err)))?;
+        let reader = ParquetObjectReader::new(self.object_store(), location);
+        let reader = match bytes {
+            Some(bytes) => reader.with_file_size(bytes),
+            None => reader,
+        };
+        ParquetRecordBatchStreamBuilder::new(reader).await
+    }
+
+    /// The rows of the parquet file `file` as a stream of batches, read a row group at a
+    /// time rather than whole, with the schema its footer declares — the file's own metadata
+    /// included, which the stream's batches do not carry.
+    pub(crate) async fn batches(
+        &self,
+        file: &Stored,
+    ) -> Result<(SchemaRef, ParquetRecordBatchStream<ParquetObjectReader>), ParquetError> {
+        let builder = self.parquet(&file.path, Some(file.bytes)).await?;
+        let schema = builder.schema().clone();
+        Ok((schema, builder.build()?))
+    }
+}
+
+/// Whether the directory `dir` holds any file, at any depth below it.
+///
+/// A directory of empty directories is what a swept dataset leaves on disk, and holds
+/// nothing: it reads the same as one that was never written.
+fn holds_files(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry.path().is_dir() && holds_files(&entry.path()) || entry.path().is_file()
+        })
+    })
+}
@@ -6,17 +6,29 @@
 //! summary cover every dataset — including the ones whose partitions hold different schemas
 //! and so cannot be read as a single table.
 //!
+//! A store in an object store is summarised the same way: its objects are listed by prefix,
+//! and each footer is fetched on its own by a range request, so a summary of a bucket costs a
+//! request per file rather than a download of it.
+//!
 //! Absence is a result, not an error: a dataset nothing has written yet is summarised as
 //! holding nothing, so a reader sees the gaps as well as the contents.
+//!
+//! What is counted is what a reader reads. A compacted partition holds its original files as
+//! well as the generation merging them, and only the generation is counted — see
+//! [`crate::Dataset::compact`]. A rebuilt dataset counts the files its pointer publishes, and
+//! not what a run is staging beside them — see [`crate::Rebuild`].
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use parquet::errors::ParquetError;
-use parquet::file::reader::{FileReader, SerializedFileReader};
 
+use crate::compact::{ListingError, hidden_in};
 use crate::dataset::DatasetInfo;
+use crate::gold::GoldError;
 use crate::layer::Layer;
 use crate::path::Root;
+use crate::rebuild::{CURRENT, Published};
+use crate::store::{Backend, Listed, Stored};
 
 /// The extension of the files whose rows can be counted; anything else contributes its
 /// bytes but no rows.
@@ -37,6 +49,10 @@ pub enum SummaryError {
         #[source]
         source: ParquetError,
     },
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+    #[error(transparent)]
+    Gold(#[from] GoldError),
 }
 
 /// How much data some part of the store holds.
@@ -89,6 +105,9 @@ pub struct ArtefactSummary {
     pub artifact: String,
     /// One entry per run, oldest first — the versions sort chronologically.
     pub versions: Vec<VersionSummary>,
+    /// The version promoted to live in the artefact's index, if one is — see
+    /// [`crate::gold`].
+    pub live: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -98,7 +117,8 @@ pub struct VersionSummary {
 }
 
 /// What `dataset` holds in `root`.
-pub fn dataset(root: &Root, dataset: DatasetInfo) -> Result<DatasetSummary, SummaryError> {
+pub async fn dataset(root: &Root, dataset: DatasetInfo) -> Result<DatasetSummary, SummaryError> {
+    let backend = root.backend();
     let dir = root.path().join(dataset.layer.as_str()).join(dataset.name);
     let mut summary = DatasetSummary {
         layer: dataset.layer,
@@ -107,15 +127,16 @@ pub fn dataset(root: &Root, dataset: DatasetInfo) -> Result<DatasetSummary, Summ
         contents: Contents::default(),
     };
 
+    let published = Published::read(backend, &dir).await?;
     if dataset.partition_key.is_none() {
-        summary.contents = contents_of(&dir)?;
+        summary.contents = contents_of(backend, &dir, published.as_ref()).await?;
         return Ok(summary);
     }
-    for partition in sorted_dirs(&dir)? {
-        let contents = contents_of(&partition.path())?;
+    for partition in sorted_dirs(backend, &dir).await? {
+        let contents = contents_of(backend, &partition, published.as_ref()).await?;
         summary.contents.add(contents);
         summary.partitions.push(PartitionSummary {
-            value: partition_value(&partition.file_name().to_string_lossy()),
+            value: partition_value(&partition),
             contents,
         });
     }
@@ -127,111 +148,115 @@ pub fn dataset(root: &Root, dataset: DatasetInfo) -> Result<DatasetSummary, Summ
 
 /// What gold artefacts `root` holds, by artefact and then by the run that produced each
 /// version.
-pub fn artefacts(root: &Root) -> Result<Vec<ArtefactSummary>, SummaryError> {
+pub async fn artefacts(root: &Root) -> Result<Vec<ArtefactSummary>, SummaryError> {
+    let backend = root.backend();
     let mut artefacts = Vec::new();
-    for artifact in sorted_dirs(&root.path().join(Layer::Gold.as_str()))? {
+    for artifact in sorted_dirs(backend, &root.path().join(Layer::Gold.as_str())).await? {
+        // Where gold datasets' pointers are kept, which is no artefact.
+        if artifact.ends_with(CURRENT) {
+            continue;
+        }
         let mut versions = Vec::new();
-        for version in sorted_dirs(&artifact.path())? {
+        for version in sorted_dirs(backend, &artifact).await? {
             versions.push(VersionSummary {
-                version: partition_value(&version.file_name().to_string_lossy()),
-                contents: contents_of(&version.path())?,
+                version: partition_value(&version),
+                contents: contents_of(backend, &version, None).await?,
             });
         }
         versions.retain(|version| !version.contents.is_empty());
         if !versions.is_empty() {
+            let name = partition_value(&artifact);
+            let live = root
+                .artefact(&name)
+                .map_err(GoldError::from)?
+                .index()
+                .await?
+                .promoted;
             artefacts.push(ArtefactSummary {
-                artifact: partition_value(&artifact.file_name().to_string_lossy()),
+                artifact: name,
                 versions,
+                live,
             });
         }
     }
     Ok(artefacts)
 }
 
-/// The value half of a `key=value` directory name, or the whole name if it is not one —
-/// a summary reports what is on disk rather than refusing to describe it.
-fn partition_value(dir_name: &str) -> String {
-    dir_name
-        .split_once('=')
-        .map_or(dir_name, |(_, value)| value)
+/// The value half of the `key=value` directory `dir`, or its whole name if it is not one —
+/// a summary reports what is in the store rather than refusing to describe it.
+fn partition_value(dir: &Path) -> String {
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    name.split_once('=')
+        .map_or(&*name, |(_, value)| value)
         .to_string()
 }
 
 /// The directories directly below `dir`, in name order; none at all if `dir` does not
 /// exist, which is how an absent dataset summarises as holding nothing.
-fn sorted_dirs(dir: &Path) -> Result<Vec<std::fs::DirEntry>, SummaryError> {
-    let Ok(entries) = std::fs::read_dir(dir) else {
-        return Ok(Vec::new());
-    };
-    let mut dirs = Vec::new();
-    for entry in entries {
-        let entry = entry.map_err(|source| SummaryError::Io {
-            path: dir.display().to_string(),
-            source,
-        })?;
-        if entry.path().is_dir() {
-            dirs.push(entry);
-        }
-    }
-    dirs.sort_by_key(std::fs::DirEntry::file_name);
-    Ok(dirs)
+async fn sorted_dirs(backend: &Backend, dir: &Path) -> Result<Vec<PathBuf>, SummaryError> {
+    Ok(listed(backend, dir).await?.dirs)
 }
 
-/// Everything below `dir`, at any depth.
-fn contents_of(dir: &Path) -> Result<Contents, SummaryError> {
-    let Ok(entries) = std::fs::read_dir(dir) else {
-        return Ok(Contents::default());
-    };
+/// What `dir` directly holds.
+async fn listed(backend: &Backend, dir: &Path) -> Result<Listed, SummaryError> {
+    backend.list(dir).await.map_err(|source| SummaryError::Io {
+        path: dir.display().to_string(),
+        source,
+    })
+}
+
+/// Everything below `dir`, at any depth, that a reader reads: of a dataset a rebuild has
+/// `published`, only the files it publishes.
+async fn contents_of(
+    backend: &Backend,
+    dir: &Path,
+    published: Option<&Published>,
+) -> Result<Contents, SummaryError> {
     let mut contents = Contents::default();
-    for entry in entries {
-        let path = entry
-            .map_err(|source| SummaryError::Io {
-                path: dir.display().to_string(),
-                source,
-            })?
-            .path();
-        if path.is_dir() {
-            contents.add(contents_of(&path)?);
-        } else {
-            contents.add(file_contents(&path)?);
+    let mut unvisited = vec![dir.to_path_buf()];
+    while let Some(dir) = unvisited.pop() {
+        let listed = listed(backend, &dir).await?;
+        if !listed.files.is_empty() {
+            let hidden = hidden_in(backend, &dir).await?;
+            for file in listed.files {
+                let unpublished =
+                    published.is_some_and(|published| !published.contains(&file.path));
+                if !hidden.contains(&file.path) && !unpublished {
+                    contents.add(file_contents(backend, &file).await?);
+                }
+            }
         }
+        unvisited.extend(listed.dirs);
     }
     Ok(contents)
 }
 
 /// One file's size, and its rows if it is one the store counts rows in.
-fn file_contents(path: &Path) -> Result<Contents, SummaryError> {
-    let file = std::fs::File::open(path).map_err(|source| SummaryError::Io {
-        path: path.display().to_string(),
-        source,
-    })?;
-    let bytes = file
-        .metadata()
-        .map_err(|source| SummaryError::Io {
-            path: path.display().to_string(),
-            source,
-        })?
-        .len();
-    let rows = match path.extension().is_some_and(|kind| kind == PARQUET) {
-        true => rows_in(file, path)?,
+pub(crate) async fn file_contents(
+    backend: &Backend,
+    file: &Stored,
+) -> Result<Contents, SummaryError> {
+    let rows = match file.path.extension().is_some_and(|kind| kind == PARQUET) {
+        // The rows a parquet file declares in its own footer, so counting them reads no data.
+        true => backend
+            .rows_in(file)
+            .await
+            .map_err(|source| SummaryError::Parquet {
+                path: file.path.display().to_string(),
+                source,
+            })?,
         false => 0,
     };
     Ok(Contents {
         files: 1,
         rows,
-        bytes,
+        bytes: file.bytes,
     })
 }
 
-/// The rows a parquet file declares in its own footer, so counting them reads no data.
-fn rows_in(file: std::fs::File, path: &Path) -> Result<u64, SummaryError> {
-    let reader = SerializedFileReader::new(file).map_err(|source| SummaryError::Parquet {
-        path: path.display().to_string(),
-        source,
-    })?;
-    Ok(reader.metadata().file_metadata().num_rows().max(0) as u64)
-}
-
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -270,7 +295,7 @@ mod tests {
         write(root.dataset(THING).partition("kind", "a").unwrap(), 2).await;
         write(root.dataset(THING).partition("kind", "a").unwrap(), 4).await;
 
-        let summary = dataset(&root, THING.info()).unwrap();
+        let summary = dataset(&root, THING.info()).await.unwrap();
 
         assert_eq!(summary.contents.rows, 9);
         assert_eq!(summary.contents.files, 3);
@@ -297,7 +322,7 @@ mod tests {
         let root = Root::new(tmp.path());
         write(root.dataset(WHOLE), 5).await;
 
-        let summary = dataset(&root, WHOLE.info()).unwrap();
+        let summary = dataset(&root, WHOLE.info()).await.unwrap();
 
         assert!(summary.partitions.is_empty());
         assert_eq!(summary.contents.rows, 5);
@@ -305,11 +330,11 @@ mod tests {
 
     /// A dataset nothing has written is reported as holding nothing rather than as a
     /// failure: what a store is missing is the point of asking.
-    #[test]
-    fn a_dataset_that_was_never_written_holds_nothing() {
+    #[tokio::test]
+    async fn a_dataset_that_was_never_written_holds_nothing() {
         let tmp = tempfile::tempdir().unwrap();
 
-        let summary = dataset(&Root::new(tmp.path()), THING.info()).unwrap();
+        let summary = dataset(&Root::new(tmp.path()), THING.info()).await.unwrap();
 
         assert!(summary.contents.is_empty());
         assert_eq!(summary.contents, Contents::default());
@@ -318,13 +343,13 @@ mod tests {
 
     /// A rebuild that produces nothing sweeps a partition and leaves its directory
     /// standing; an empty directory is not a partition the store holds.
-    #[test]
-    fn a_partition_swept_empty_is_not_reported() {
+    #[tokio::test]
+    async fn a_partition_swept_empty_is_not_reported() {
         let tmp = tempfile::tempdir().unwrap();
         let root = Root::new(tmp.path());
         std::fs::create_dir_all(root.dataset(THING).partition("kind", "a").unwrap().dir()).unwrap();
 
-        let summary = dataset(&root, THING.info()).unwrap();
+        let summary = dataset(&root, THING.info()).await.unwrap();
 
         assert!(summary.partitions.is_empty());
         assert!(summary.contents.is_empty());
@@ -332,8 +357,8 @@ mod tests {
 
     /// Gold artefacts are not parquet, so they are summarised by what they weigh and which
     /// runs produced them, with no rows to count.
-    #[test]
-    fn gold_artefacts_are_summarised_by_artefact_and_run() {
+    #[tokio::test]
+    async fn gold_artefacts_are_summarised_by_artefact_and_run() {
         let tmp = tempfile::tempdir().unwrap();
         let root = Root::new(tmp.path());
         for run in [
@@ -347,7 +372,7 @@ mod tests {
             std::fs::write(path, b"packed points").unwrap();
         }
 
-        let artefacts = artefacts(&root).unwrap();
+        let artefacts = artefacts(&root).await.unwrap();
 
         assert_eq!(artefacts.len(), 1);
         assert_eq!(artefacts[0].artifact, "crossings");
@@ -364,12 +389,39 @@ mod tests {
                 bytes: 13,
             }
         );
+        assert_eq!(artefacts[0].live, None);
+    }
+
+    /// The version a consumer is pointed at is the one the artefact's index promoted, and
+    /// the index itself, beside the versions, is not taken for one.
+    #[tokio::test]
+    async fn an_artefacts_live_version_is_the_promoted_one() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        for day in [26, 27] {
+            let run = Utc.with_ymd_and_hms(2026, 7, day, 9, 0, 0).unwrap();
+            let path = root
+                .gold_artefact("crossings", run, "crossings.pointset")
+                .unwrap();
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"packed points").unwrap();
+        }
+        root.artefact("crossings")
+            .unwrap()
+            .promote(Some("20260726T090000000Z"))
+            .await
+            .unwrap();
+
+        let artefacts = artefacts(&root).await.unwrap();
+
+        assert_eq!(artefacts[0].versions.len(), 2);
+        assert_eq!(artefacts[0].live.as_deref(), Some("20260726T090000000Z"));
     }
 
-    #[test]
-    fn a_store_with_no_gold_layer_holds_no_artefacts() {
+    #[tokio::test]
+    async fn a_store_with_no_gold_layer_holds_no_artefacts() {
         let tmp = tempfile::tempdir().unwrap();
 
-        assert!(artefacts(&Root::new(tmp.path())).unwrap().is_empty());
+        assert!(artefacts(&Root::new(tmp.path())).await.unwrap().is_empty());
     }
 }
@@ -19,7 +19,8 @@
 //!
 //! The write replaces the whole dataset, so the table must hold every row of it — the rule
 //! silver rebuilds already follow, and the reason a partition the table has no rows for is
-//! swept.
+//! swept. It is one [`Rebuild`], so a reader sees the dataset the table replaced until the
+//! whole table is written.
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -34,11 +35,13 @@ use geoarrow_array::cast::to_wkb;
 use geoarrow_schema::error::GeoArrowError;
 
 use crate::country::{COUNTRY, Country, UnknownCountry};
-use crate::dataset::DatasetSpec;
+use crate::dataset::{DatasetInfo, DatasetSpec};
 use crate::geo::{GEOMETRY, GeoError, PROJECTED_GEOMETRY, projected_wkb_field, wkb_field};
 use crate::layer::layers;
-use crate::path::{ReplaceError, Replaced, Root};
-use crate::rows::{Geometry, Row, RowError, fields};
+use crate::partition::DATE_KEY_SUFFIX;
+use crate::path::{Dataset, ReplaceError, Replaced, Root};
+use crate::rebuild::Rebuild;
+use crate::rows::{Geometry, Row, RowError, fields, key_name, keys};
 
 /// A silver dataset as something a table can be written to: where it lives, the columns it
 /// holds, and whether it carries geometry.
@@ -51,6 +54,7 @@ pub struct SilverTarget {
     columns: Vec<FieldRef>,
     pub(crate) geometry: Geometry,
     unique: &'static [&'static str],
+    together: &'static [&'static [&'static str]],
 }
 
 /// How a dataset's partition directories are laid out, and so which columns a table must
@@ -69,9 +73,6 @@ pub(crate) enum Layout {
     CountryAndDate(&'static str),
 }
 
-/// The suffix a date-valued partition key ends with, as `docs/medallion.md` requires.
-const DATE_KEY_SUFFIX: &str = "_date";
-
 /// A failure writing a table into a dataset.
 #[derive(Debug, thiserror::Error)]
 pub enum TableError {
@@ -85,6 +86,8 @@ pub enum TableError {
         dataset: &'static str,
         columns: Vec<String>,
     },
+    /// Two rows sharing a key. A key of several columns is named, and its value shown, as a
+    /// tuple.
     #[error("{dataset}.{column} identifies a row, but rows {first} and {second} both hold {value}")]
     Duplicate {
         dataset: &'static str,
@@ -157,6 +160,7 @@ impl SilverTarget {
             columns: fields::<R>()?,
             geometry: R::GEOMETRY,
             unique: R::UNIQUE,
+            together: R::UNIQUE_TOGETHER,
         })
     }
 
@@ -188,31 +192,42 @@ impl SilverTarget {
 
     /// The columns the partition values are read from, outermost first.
     fn partition_columns(&self) -> Result<Vec<&'static str>, TableError> {
-        Ok(match self.layout()? {
-            Layout::Country => vec![COUNTRY],
-            Layout::Date(key) => vec![key],
-            Layout::CountryAndDate(key) => vec![COUNTRY, key],
-        })
+        Ok(self.layout()?.keys())
     }
 
     pub(crate) fn layout(&self) -> Result<Layout, TableError> {
-        let key = self
-            .spec
+        Layout::of(self.spec.info(), self.geometry)
+    }
+}
+
+impl Layout {
+    /// The layout a silver dataset's definition implies.
+    pub(crate) fn of(dataset: DatasetInfo, geometry: Geometry) -> Result<Self, TableError> {
+        let key = dataset
             .partition_key
-            .ok_or(TableError::Unpartitioned(self.spec.name))?;
+            .ok_or(TableError::Unpartitioned(dataset.name))?;
 
-        match (key, self.geometry) {
+        match (key, geometry) {
             (COUNTRY, _) => Ok(Layout::Country),
             (key, Geometry::LatLonAndProjected) if key.ends_with(DATE_KEY_SUFFIX) => {
                 Ok(Layout::CountryAndDate(key))
             }
             (key, Geometry::Absent) if key.ends_with(DATE_KEY_SUFFIX) => Ok(Layout::Date(key)),
             (key, _) => Err(TableError::UnsupportedLayout {
-                dataset: self.spec.name,
+                dataset: dataset.name,
                 key: key.to_string(),
             }),
         }
     }
+
+    /// The keys of its partition directories, outermost first.
+    pub(crate) fn keys(self) -> Vec<&'static str> {
+        match self {
+            Layout::Country => vec![COUNTRY],
+            Layout::Date(key) => vec![key],
+            Layout::CountryAndDate(key) => vec![COUNTRY, key],
+        }
+    }
 }
 
 /// Write `table` as the whole of `target`'s dataset, replacing what is there.
@@ -236,16 +251,25 @@ pub async fn write_table(
     check_unique(target, &table)?;
 
     let columns = translate(target, &table)?;
-    let written = match target.layout()? {
-        Layout::Country => write_by_country(root, target, &table, &columns, None).await?,
-        Layout::Date(key) => {
-            let days = days(target, &dates_of(target, &table, key)?, &columns, None)?;
-            replace_dates(&root.dataset(target.spec), target, &days).await?
-        }
-        Layout::CountryAndDate(key) => {
-            write_by_country(root, target, &table, &columns, Some(key)).await?
+    let layout = target.layout()?;
+    let dataset = root.dataset(target.spec);
+    let mut rebuild = dataset.rebuild().await?;
+    let written = async {
+        match layout {
+            Layout::Country => {
+                write_by_country(&mut rebuild, &dataset, target, &table, &columns, None).await
+            }
+            Layout::Date(key) => {
+                let days = days(target, &dates_of(target, &table, key)?, &columns, None)?;
+                replace_dates(&mut rebuild, &dataset, target, &days).await
+            }
+            Layout::CountryAndDate(key) => {
+                write_by_country(&mut rebuild, &dataset, target, &table, &columns, Some(key)).await
+            }
         }
-    };
+    }
+    .await;
+    let written = rebuild.conclude(written).await?;
 
     Ok(TableWritten {
         rows: table.num_rows(),
@@ -256,7 +280,8 @@ pub async fn write_table(
 /// One country's rows, written as a partition of their own or as dates below it, with the
 /// countries the run did not produce swept away.
 async fn write_by_country(
-    root: &Root,
+    rebuild: &mut Rebuild<layers::Silver>,
+    whole: &Dataset<layers::Silver>,
     target: &SilverTarget,
     table: &RecordBatch,
     columns: &Columns,
@@ -268,17 +293,17 @@ async fn write_by_country(
     let mut written = Replaced::default();
 
     for (country, rows) in by_country {
-        let dataset = root.dataset(target.spec).partition(COUNTRY, country)?;
+        let dataset = whole.clone().partition(COUNTRY, country)?;
         let columns = columns.take(&rows)?;
         written += match &dates {
             None => {
                 let batch = [columns.batch(target, Some(country))?];
                 match target.geometry {
-                    Geometry::Absent => dataset.replace_with(&batch).await.map(|_| ())?,
+                    Geometry::Absent => rebuild.replace_with(&dataset, &batch).await?,
                     Geometry::LatLonAndProjected => {
-                        dataset.replace_with_geo(&batch).await.map(|_| ())?
+                        rebuild.replace_with_geo(&dataset, &batch).await?
                     }
-                }
+                };
                 Replaced {
                     written: 1,
                     removed: 0,
@@ -287,27 +312,26 @@ async fn write_by_country(
             Some(all) => {
                 let mine: Vec<NaiveDate> = rows.iter().map(|row| all[*row as usize]).collect();
                 let days = days(target, &mine, &columns, Some(country))?;
-                replace_dates(&dataset, target, &days).await?
+                replace_dates(rebuild, &dataset, target, &days).await?
             }
         };
     }
 
-    written.removed += root
-        .dataset(target.spec)
-        .retain_partitions(COUNTRY, &derived)
-        .await?;
+    written.removed += rebuild.retain_partitions(whole, COUNTRY, &derived)?;
     Ok(written)
 }
 
-/// One partition per date, written with the encoder the dataset's geometry calls for.
+/// One partition per date, written onto `rebuild` with the encoder the dataset's geometry
+/// calls for.
 pub(crate) async fn replace_dates(
-    dataset: &crate::path::Dataset<layers::Silver>,
+    rebuild: &mut Rebuild<layers::Silver>,
+    dataset: &Dataset<layers::Silver>,
     target: &SilverTarget,
     days: &[(NaiveDate, RecordBatch)],
 ) -> Result<Replaced, TableError> {
     Ok(match target.geometry {
-        Geometry::Absent => dataset.replace_dates(days).await?,
-        Geometry::LatLonAndProjected => dataset.replace_dates_geo(days).await?,
+        Geometry::Absent => rebuild.replace_dates(dataset, days).await?,
+        Geometry::LatLonAndProjected => rebuild.replace_dates_geo(dataset, days).await?,
     })
 }
 
@@ -537,30 +561,48 @@ fn dates_of(
         .collect()
 }
 
-/// Refuse a table two of whose rows share a value in a column the dataset declares unique.
+/// Refuse a table two of whose rows share a value in a column the dataset declares unique,
+/// or values in every column of a set it declares unique together.
 ///
 /// Checked over the whole table rather than per partition, because a name that identifies a
 /// row has to do so across the dataset — the partition a row lands in is a fact about how it
 /// is stored, not about what it is called.
 pub(crate) fn check_unique(target: &SilverTarget, table: &RecordBatch) -> Result<(), TableError> {
-    for column in target.unique {
-        let array = table
-            .column_by_name(column)
-            .ok_or_else(|| TableError::Missing {
-                dataset: target.name(),
-                column: column.to_string(),
-            })?;
-        let converter = RowConverter::new(vec![SortField::new(array.data_type().clone())])?;
-        let encoded = converter.convert_columns(std::slice::from_ref(array))?;
+    for key in keys(target.unique, target.together) {
+        let arrays = key
+            .iter()
+            .map(|column| {
+                table
+                    .column_by_name(column)
+                    .cloned()
+                    .ok_or_else(|| TableError::Missing {
+                        dataset: target.name(),
+                        column: column.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<ArrayRef>, TableError>>()?;
+        let converter = RowConverter::new(
+            arrays
+                .iter()
+                .map(|array| SortField::new(array.data_type().clone()))
+                .collect(),
+        )?;
+        let encoded = converter.convert_columns(&arrays)?;
 
         let mut seen: HashMap<Vec<u8>, usize> = HashMap::new();
-        for row in 0..array.len() {
+        for row in 0..table.num_rows() {
             if let Some(first) = seen.insert(encoded.row(row).as_ref().to_vec(), row) {
-                let shown = ArrayFormatter::try_new(array, &FormatOptions::default())?;
+                let values = arrays
+                    .iter()
+                    .map(|array| -> Result<String, TableError> {
+                        let shown = ArrayFormatter::try_new(array, &FormatOptions::default())?;
+                        Ok(shown.value(row).to_string())
+                    })
+                    .collect::<Result<Vec<String>, TableError>>()?;
                 return Err(TableError::Duplicate {
                     dataset: target.name(),
-                    column: column.to_string(),
-                    value: shown.value(row).to_string(),
+                    column: key_name(key),
+                    value: key_name(&values.iter().map(String::as_str).collect::<Vec<_>>()),
                     first,
                     second: row,
                 });
@@ -661,14 +703,14 @@ mod tests {
 
     /// A table shaped like `crossing`: the row columns, both geometries, and the country.
     fn crossing_table(ids: &[&str], points: &[Point<f64>], countries: &[&str]) -> RecordBatch {
-        let projector = Projector::for_country(Country::Germany).unwrap();
+        let projector = Projector::for_country(Country::GERMANY).unwrap();
         let projected: Vec<Point<f64>> = points
             .iter()
             .map(|point| projector.project(point).unwrap())
             .collect();
         let (geometry_field, geometry) = wkb_column(wkb_field(GEOMETRY).unwrap(), points).unwrap();
         let (projected_field, projected) = wkb_column(
-            projected_wkb_field(PROJECTED_GEOMETRY, Country::Germany).unwrap(),
+            projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).unwrap(),
             &projected,
         )
         .unwrap();
@@ -724,10 +766,12 @@ mod tests {
 
         assert_eq!(written.rows, 2);
         assert_eq!(written.partitions.written, 1);
-        assert!(
-            tmp.path()
-                .join("silver/crossing/country=DE/part-0.parquet")
-                .exists()
+        assert_eq!(
+            std::fs::read_dir(tmp.path().join("silver/crossing/country=DE"))
+                .unwrap()
+                .count(),
+            1,
+            "one file per partition"
         );
     }
 
@@ -754,14 +798,17 @@ mod tests {
             .map(|field| field.name().as_str())
             .collect();
         // `country` comes back, but as the discovered partition key rather than as a
-        // column of the file: it is the last one, after everything the file holds.
+        // column of the file: it is the last one, after everything the file holds. Each
+        // geometry is followed by the covering the writer adds to it.
         assert_eq!(
             columns,
             vec![
                 "crossing_id",
                 "overlap_m",
                 GEOMETRY,
+                "geometry_bbox",
                 PROJECTED_GEOMETRY,
+                "geometry_projected_bbox",
                 COUNTRY
             ]
         );
@@ -1017,12 +1064,12 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         let root = Root::new(tmp.path());
         let target = SilverTarget::of::<TrackRow>().unwrap();
-        let projector = Projector::for_country(Country::Germany).unwrap();
+        let projector = Projector::for_country(Country::GERMANY).unwrap();
         let line = LineString::from(vec![berlin().0, frankfurt().0]);
         let projected = projector.project(&line).unwrap();
         let (geometry_field, geometry) = wkb_column(wkb_field(GEOMETRY).unwrap(), &[line]).unwrap();
         let (projected_field, projected_array) = wkb_column(
-            projected_wkb_field(PROJECTED_GEOMETRY, Country::Germany).unwrap(),
+            projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).unwrap(),
             &[projected],
         )
         .unwrap();
@@ -1046,10 +1093,15 @@ mod tests {
 
         write_table(&root, &target, &[table]).await.unwrap();
 
-        assert!(
-            tmp.path()
-                .join("silver/track/country=DE/seen_date=2026-07-21/part-0.parquet")
-                .exists()
+        assert_eq!(
+            std::fs::read_dir(
+                tmp.path()
+                    .join("silver/track/country=DE/seen_date=2026-07-21")
+            )
+            .unwrap()
+            .count(),
+            1,
+            "one file per partition"
         );
     }
 
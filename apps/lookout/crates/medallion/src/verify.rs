@@ -0,0 +1,1427 @@
+//! Checking that what a store holds is what its datasets' definitions say it should be.
+//!
+//! Writers apply the rules as they write, so a store only they have written to holds to them.
+//! A store also arrives by other routes — a `git pull` of someone else's, a copy made by hand,
+//! a writer from before a rule existed — and this reads it back and says where it departs:
+//!
+//!   - every parquet file is one a reader can decode, to the last row;
+//!   - every directory is a `key=value` partition the dataset's layout has room for, its key
+//!     the one the layout puts there and its value one the key admits — a country the store
+//!     knows, a date;
+//!   - a silver file carrying geometry declares it as GeoParquet 1.1, its lat/lon column in
+//!     CRS 84 and its projected column in the zone of the country it is filed under;
+//!   - no two rows a reader reads share a value in a column the dataset declares unique, or
+//!     values in every column of a set it declares unique together;
+//!   - every value in a column referring to another dataset is a key that dataset holds;
+//!   - every instant column is a UTC millisecond timestamp;
+//!   - every file a dataset's pointer publishes is there to read.
+//!
+//! Departures are [`Problem`]s, reported rather than raised, so one verification describes
+//! everything wrong with a dataset instead of the first thing. A [`VerifyError`] is a store
+//! that could not be listed at all.
+//!
+//! Readability is checked of every file, published or not, since a file nothing reads now is
+//! still one a compaction or a rebuild could publish. Uniqueness is checked of what a reader
+//! reads: a compacted partition holds its rows twice by design, and a rebuild's orphans are
+//! the rows it replaced. References are checked of what a reader reads on both sides, since
+//! a key only an orphan holds is one no reader would find.
+//!
+//! Files are read a row group at a time, never whole. Repeated keys are found by a query,
+//! grouping the rows a reader reads by key and keeping the groups of more than one, which the
+//! engine spills to disk past [`MEMORY_LIMIT`] — so a bronze dataset of any size is checked
+//! without its keys being held here. The distinct values of a referring column are held, to
+//! be looked for among the keys of the dataset they refer to.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::datatypes::{DataType, Int64Type, Schema, TimeUnit};
+use arrow::row::{RowConverter, SortField};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use chrono::NaiveDate;
+use futures::TryStreamExt;
+use parquet::arrow::ProjectionMask;
+use parquet::file::metadata::KeyValue;
+
+use crate::compact::{ListingError, LivePartition, is_manifest, is_parquet, live_partitions_where};
+use crate::country::{COUNTRY, Country};
+use crate::dataset::DatasetInfo;
+use crate::geo::{CRS84_PROJJSON, GEOMETRY, PROJECTED_GEOMETRY};
+use crate::layer::Layer;
+use crate::partition::{DATE_FORMAT, DATE_KEY_SUFFIX, PartitionKey, PartitionValue};
+use crate::path::Root;
+use crate::query::{MEMORY_LIMIT, context, read_live};
+use crate::rebuild::Published;
+use crate::rows::{Geometry, Reference, Row, key_name, keys};
+use crate::store::{Backend, Stored};
+use crate::table::Layout;
+
+/// The file metadata key GeoParquet keeps its description of the geometry columns under.
+const GEO_KEY: &str = "geo";
+
+/// The GeoParquet version silver is written in, per `docs/medallion.md`.
+const GEOPARQUET_VERSION: &str = "1.1.0";
+
+/// The only geometry encoding silver writes.
+const WKB: &str = "WKB";
+
+/// The timezone an instant column is declared in.
+const UTC: &str = "UTC";
+
+/// The table the live files of a dataset are queried as, for its repeated keys.
+const LIVE_TABLE: &str = "live";
+
+/// What a dataset's definition says its files hold, as a value, so datasets of every layer
+/// can be verified together.
+///
+/// Built from the dataset's [`Row`] type where it has one, so what is checked is what the
+/// writers write. A dataset kept in an upstream's own shape has none, and is checked only as
+/// far as this store decides: its files are parquet, and its own partition key is the
+/// outermost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Declared {
+    pub dataset: DatasetInfo,
+    pub geometry: Geometry,
+    pub instants: &'static [&'static str],
+    pub unique: &'static [&'static str],
+    pub unique_together: &'static [&'static [&'static str]],
+    pub references: &'static [Reference],
+    /// Whether a row type declares the dataset's columns and layout, rather than an
+    /// upstream's own release.
+    pub typed: bool,
+}
+
+impl Declared {
+    /// What `R`'s definition declares.
+    pub const fn of<R: Row>() -> Self {
+        Self {
+            dataset: R::DATASET.info(),
+            geometry: R::GEOMETRY,
+            instants: R::INSTANTS,
+            unique: R::UNIQUE,
+            unique_together: R::UNIQUE_TOGETHER,
+            references: R::REFERENCES,
+            typed: true,
+        }
+    }
+
+    /// A dataset with no row type, held in the shape and layout of wherever it came from.
+    pub const fn untyped(dataset: DatasetInfo) -> Self {
+        Self {
+            dataset,
+            geometry: Geometry::Absent,
+            instants: &[],
+            unique: &[],
+            unique_together: &[],
+            references: &[],
+            typed: false,
+        }
+    }
+}
+
+/// A failure listing what a dataset holds, as distinct from a problem with what it holds.
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error("listing {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Listing(#[from] ListingError),
+}
+
+/// What verifying one dataset found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub layer: Layer,
+    pub name: &'static str,
+    /// The parquet files read.
+    pub files: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl Verification {
+    /// Whether the dataset holds to its definition.
+    pub fn is_sound(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// One way a dataset departs from its definition. Paths are relative to the dataset's own
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The definition itself has no layout a store could hold.
+    Definition(String),
+    /// A file a reader cannot decode, or one that is no parquet at all.
+    Unreadable { file: PathBuf, reason: String },
+    /// A file the dataset's pointer publishes and the store does not hold.
+    Missing { file: PathBuf },
+    /// A directory the dataset's layout has no place for.
+    Partition { dir: PathBuf, reason: String },
+    /// A geometry file whose GeoParquet metadata is not what silver writes.
+    Geo { file: PathBuf, reason: String },
+    /// A declared column a file lacks, or holds as the wrong type.
+    Column {
+        file: PathBuf,
+        column: String,
+        reason: String,
+    },
+    /// A key that more than one row a reader reads holds: the first found, and how many rows
+    /// repeat a key an earlier row holds, in all. A key of several columns names each, and
+    /// shows its value as a tuple.
+    Duplicate {
+        columns: Vec<String>,
+        value: String,
+        first: PathBuf,
+        second: PathBuf,
+        repeated: usize,
+    },
+    /// Values of a column referring to another dataset that are not among that dataset's
+    /// keys: the first found, the file it is in, and how many distinct values are missing.
+    Dangling {
+        column: String,
+        dataset: DatasetInfo,
+        key: &'static str,
+        value: String,
+        file: PathBuf,
+        missing: usize,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Definition(reason) => write!(f, "definition: {reason}"),
+            Problem::Unreadable { file, reason } => {
+                write!(f, "{}: unreadable: {reason}", file.display())
+            }
+            Problem::Missing { file } => {
+                write!(f, "{}: published but not in the store", file.display())
+            }
+            Problem::Partition { dir, reason } => write!(f, "{}/: {reason}", dir.display()),
+            Problem::Geo { file, reason } => {
+                write!(f, "{}: geo metadata: {reason}", file.display())
+            }
+            Problem::Column {
+                file,
+                column,
+                reason,
+            } => write!(f, "{}: column `{column}` {reason}", file.display()),
+            Problem::Duplicate {
+                columns,
+                value,
+                first,
+                second,
+                repeated,
+            } => {
+                match columns.as_slice() {
+                    [column] => write!(f, "column `{column}` is unique")?,
+                    columns => write!(
+                        f,
+                        "columns {} are unique together",
+                        columns
+                            .iter()
+                            .map(|column| format!("`{column}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )?,
+                }
+                match first == second {
+                    true => write!(f, ", but {value} is in {} twice", first.display())?,
+                    false => write!(
+                        f,
+                        ", but {value} is in both {} and {}",
+                        first.display(),
+                        second.display()
+                    )?,
+                }
+                match repeated {
+                    1 => Ok(()),
+                    repeated => write!(f, " ({repeated} repeats in all)"),
+                }
+            }
+            Problem::Dangling {
+                column,
+                dataset,
+                key,
+                value,
+                file,
+                missing,
+            } => {
+                write!(
+                    f,
+                    "column `{column}` refers to {}.{}.{key}, which holds no {value} (in {})",
+                    dataset.layer.as_str(),
+                    dataset.name,
+                    file.display()
+                )?;
+                match missing {
+                    1 => Ok(()),
+                    missing => write!(f, " ({missing} values missing in all)"),
+                }
+            }
+        }
+    }
+}
+
+/// Check what `root` holds of `declared`'s dataset against its definition.
+pub async fn dataset(root: &Root, declared: &Declared) -> Result<Verification, VerifyError> {
+    let backend = root.backend();
+    let info = declared.dataset;
+    let dir = root.path().join(info.layer.as_str()).join(info.name);
+    let mut problems = Vec::new();
+
+    let partitioned_on = match partition_keys(declared) {
+        Ok(keys) => Some(keys),
+        Err(reason) => {
+            problems.push(Problem::Definition(reason));
+            None
+        }
+    };
+
+    let stored: Vec<Stored> = backend
+        .files_below(&dir)
+        .await
+        .map_err(|source| VerifyError::Io {
+            path: dir.display().to_string(),
+            source,
+        })?
+        .into_iter()
+        .filter(|file| !is_hidden(&file.path))
+        .collect();
+
+    if let Some(keys) = &partitioned_on {
+        let dirs: BTreeSet<&Path> = stored
+            .iter()
+            .filter_map(|file| file.path.parent())
+            .collect();
+        for partition in dirs {
+            if let Some(reason) = misplaced(&dir, partition, keys, declared.typed) {
+                problems.push(Problem::Partition {
+                    dir: relative(&dir, partition),
+                    reason,
+                });
+            }
+        }
+    }
+
+    let live = match Published::read(backend, &dir).await? {
+        Some(published) => {
+            let on_disk: BTreeSet<&Path> = stored.iter().map(|file| file.path.as_path()).collect();
+            for file in published.files() {
+                if !on_disk.contains(file.as_path()) {
+                    problems.push(Problem::Missing {
+                        file: relative(&dir, file),
+                    });
+                }
+            }
+            published.files().clone()
+        }
+        None => live_partitions_where(backend, &dir, &|_, _| true)
+            .await?
+            .into_iter()
+            .flat_map(|partition| partition.files)
+            .collect(),
+    };
+
+    let mut referring = Referring::of(declared);
+    let mut readable = Vec::new();
+    let mut files = 0;
+    for file in &stored {
+        let path = &file.path;
+        if !is_parquet(path) {
+            if !is_manifest(path) {
+                problems.push(Problem::Unreadable {
+                    file: relative(&dir, path),
+                    reason: "not a parquet file".to_string(),
+                });
+            }
+            continue;
+        }
+        files += 1;
+        let shown = relative(&dir, path);
+        let is_live = live.contains(path);
+        let noting = is_live.then_some(referring.as_mut_slice());
+        match check_file(backend, declared, &dir, file, &shown, noting, &mut problems).await {
+            Ok(()) if is_live => readable.push(path.clone()),
+            Ok(()) => {}
+            Err(reason) => problems.push(Problem::Unreadable {
+                file: shown,
+                reason,
+            }),
+        }
+    }
+    for columns in keys(declared.unique, declared.unique_together) {
+        // A key column a file lacks is reported where its schema is checked, and leaves the
+        // key nothing to group by.
+        let lacking = problems.iter().any(|problem| {
+            matches!(problem, Problem::Column { column, .. } if columns.contains(&column.as_str()))
+        });
+        if lacking {
+            continue;
+        }
+        match duplicate(backend, &dir, &readable, columns).await {
+            Ok(found) => problems.extend(found),
+            Err(reason) => problems.push(Problem::Column {
+                file: PathBuf::from("."),
+                column: key_name(columns),
+                reason: format!("could not be checked for repeats: {reason}"),
+            }),
+        }
+    }
+    for referring in referring {
+        problems.extend(referring.dangling(root).await?);
+    }
+
+    Ok(Verification {
+        layer: info.layer,
+        name: info.name,
+        files,
+        problems,
+    })
+}
+
+/// The keys a dataset's partition directories are named for, outermost first: for silver the
+/// ones its layout implies, and otherwise its own key alone.
+fn partition_keys(declared: &Declared) -> Result<Vec<&'static str>, String> {
+    match (declared.dataset.layer, declared.typed) {
+        (Layer::Silver, true) => Layout::of(declared.dataset, declared.geometry)
+            .map(Layout::keys)
+            .map_err(|err| err.to_string()),
+        _ => Ok(declared.dataset.partition_key.into_iter().collect()),
+    }
+}
+
+/// Why files may not sit in `partition`, if they may not: it is not `keys` in order, each
+/// with a value the key admits. Below the last key, only a dataset in an upstream's own
+/// layout, one not `typed`, may go deeper.
+fn misplaced(dir: &Path, partition: &Path, keys: &[&str], typed: bool) -> Option<String> {
+    let levels: Vec<String> = partition
+        .strip_prefix(dir)
+        .unwrap_or(partition)
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    for (depth, level) in levels.iter().enumerate() {
+        let Some((key, value)) = level.split_once('=') else {
+            return Some(format!("`{level}` is not a `key=value` directory"));
+        };
+        if let Err(err) = PartitionKey::from_str(key) {
+            return Some(err.to_string());
+        }
+        if let Err(err) = PartitionValue::from_str(value) {
+            return Some(err.to_string());
+        }
+        match keys.get(depth) {
+            Some(expected) if key != *expected => {
+                return Some(format!("keyed on `{key}` where `{expected}` belongs"));
+            }
+            None if typed => {
+                return Some(format!("the dataset's layout has no `{key}=` level"));
+            }
+            _ => {}
+        }
+        if key == COUNTRY {
+            if let Err(err) = Country::from_str(value) {
+                return Some(err.to_string());
+            }
+        } else if key.ends_with(DATE_KEY_SUFFIX)
+            && NaiveDate::parse_from_str(value, DATE_FORMAT).is_err()
+        {
+            return Some(format!("`{value}` is not a {DATE_FORMAT} date"));
+        }
+    }
+    match keys.get(levels.len()) {
+        Some(key) => Some(format!("holds files above its `{key}=` partitions")),
+        None => None,
+    }
+}
+
+/// Read one parquet file a row group at a time, checking its columns and geometry against
+/// its footer, and noting the values it refers to other datasets by into `referring` if a
+/// reader reads it. An `Err` is a file that cannot be decoded, to the last row.
+async fn check_file(
+    backend: &Backend,
+    declared: &Declared,
+    dir: &Path,
+    file: &Stored,
+    shown: &Path,
+    mut referring: Option<&mut [Referring]>,
+    problems: &mut Vec<Problem>,
+) -> Result<(), String> {
+    let builder = backend
+        .parquet(&file.path, Some(file.bytes))
+        .await
+        .map_err(|err| err.to_string())?;
+    let schema = builder.schema().clone();
+    let geo = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|metadata| metadata.iter().find(|kv| kv.key == GEO_KEY).cloned());
+
+    if declared.typed {
+        problems.extend(column_problems(declared, &schema, shown));
+    }
+    if declared.dataset.layer == Layer::Silver && declared.geometry == Geometry::LatLonAndProjected
+    {
+        let country = country_of(dir, &file.path);
+        if let Err(reason) = check_geo(geo.as_ref(), country) {
+            problems.push(Problem::Geo {
+                file: shown.to_path_buf(),
+                reason,
+            });
+        }
+    }
+
+    let mut batches = builder.build().map_err(|err| err.to_string())?;
+    while let Some(batch) = batches.try_next().await.map_err(|err| err.to_string())? {
+        let Some(referring) = referring.as_deref_mut() else {
+            continue;
+        };
+        // A column the file lacks is reported where its schema is checked.
+        for referring in referring.iter_mut() {
+            let Some(array) = batch.column_by_name(referring.reference.column) else {
+                continue;
+            };
+            if let Err(reason) = referring.note(array, shown) {
+                problems.push(Problem::Column {
+                    file: shown.to_path_buf(),
+                    column: referring.reference.column.to_string(),
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The declared columns `schema` lacks, and the instants it holds as anything but a UTC
+/// millisecond timestamp.
+fn column_problems(declared: &Declared, schema: &Schema, file: &Path) -> Vec<Problem> {
+    let problem = |column: &str, reason: String| Problem::Column {
+        file: file.to_path_buf(),
+        column: column.to_string(),
+        reason,
+    };
+    let mut problems = Vec::new();
+    for column in declared.instants {
+        match schema.field_with_name(column) {
+            Err(_) => problems.push(problem(column, "is missing".to_string())),
+            Ok(field) => match field.data_type() {
+                DataType::Timestamp(TimeUnit::Millisecond, Some(zone)) if &**zone == UTC => {}
+                found => problems.push(problem(
+                    column,
+                    format!("is {found}, not a UTC millisecond timestamp"),
+                )),
+            },
+        }
+    }
+    let keyed = keys(declared.unique, declared.unique_together).flatten();
+    let referring = declared
+        .references
+        .iter()
+        .map(|reference| &reference.column);
+    for column in keyed.chain(referring).collect::<BTreeSet<_>>() {
+        if schema.field_with_name(column).is_err() {
+            problems.push(problem(column, "is missing".to_string()));
+        }
+    }
+    problems
+}
+
+/// The country `path` is filed under, if its `country=` directory names one.
+fn country_of(dir: &Path, path: &Path) -> Option<Country> {
+    path.strip_prefix(dir).ok()?.components().find_map(|part| {
+        let (key, value) = part.as_os_str().to_str()?.split_once('=')?;
+        (key == COUNTRY).then(|| Country::from_str(value).ok())?
+    })
+}
+
+/// Why `geo` is not the GeoParquet metadata silver writes for a file in `country`, if it is
+/// not. With no country to hold the projected column to, its CRS goes unchecked: the
+/// directory is reported on its own.
+fn check_geo(geo: Option<&KeyValue>, country: Option<Country>) -> Result<(), String> {
+    let json = geo
+        .and_then(|kv| kv.value.as_deref())
+        .ok_or_else(|| format!("there is no `{GEO_KEY}` key"))?;
+    let geo: serde_json::Value =
+        serde_json::from_str(json).map_err(|err| format!("not valid json: {err}"))?;
+
+    let version = &geo["version"];
+    if version != GEOPARQUET_VERSION {
+        return Err(format!("version {version}, not {GEOPARQUET_VERSION}"));
+    }
+    // CRS 84 is what a column with no `crs` is read as, so leaving it out says the same.
+    let crs84 = parse(CRS84_PROJJSON)?;
+    check_column(&geo, GEOMETRY, Some(&crs84), true)?;
+    let projected = country
+        .map(|country| parse(country.projected_projjson()))
+        .transpose()?;
+    check_column(&geo, PROJECTED_GEOMETRY, projected.as_ref(), false)
+}
+
+fn check_column(
+    geo: &serde_json::Value,
+    column: &str,
+    crs: Option<&serde_json::Value>,
+    crs_optional: bool,
+) -> Result<(), String> {
+    let declared = &geo["columns"][column];
+    if declared.is_null() {
+        return Err(format!("`{column}` is not declared"));
+    }
+    let encoding = &declared["encoding"];
+    if encoding != WKB {
+        return Err(format!("`{column}` is encoded as {encoding}, not {WKB}"));
+    }
+    match (crs, &declared["crs"]) {
+        (None, _) => Ok(()),
+        (Some(_), serde_json::Value::Null) if crs_optional => Ok(()),
+        (Some(expected), found) if found == expected => Ok(()),
+        (Some(expected), found) => Err(format!(
+            "`{column}` is in {}, not {}",
+            crs_name(found),
+            crs_name(expected)
+        )),
+    }
+}
+
+/// A CRS as its `authority:code`, which is how one is recognised at a glance.
+fn crs_name(crs: &serde_json::Value) -> String {
+    match (&crs["id"]["authority"], &crs["id"]["code"]) {
+        (serde_json::Value::String(authority), code) if !code.is_null() => {
+            format!("{authority}:{}", code.to_string().trim_matches('"'))
+        }
+        _ if crs.is_null() => "no declared CRS".to_string(),
+        _ => "an unidentified CRS".to_string(),
+    }
+}
+
+fn parse(projjson: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(projjson).map_err(|err| format!("the bundled PROJJSON: {err}"))
+}
+
+/// The distinct values of one referring column, each with how it is shown and the file it
+/// was first seen in, to be looked for among the keys of the dataset referred to.
+struct Referring {
+    reference: Reference,
+    /// The converter the values are encoded by, and the type it converts.
+    converter: Option<(RowConverter, DataType)>,
+    values: HashMap<Vec<u8>, (String, PathBuf)>,
+}
+
+impl Referring {
+    /// One for each column `declared` refers to another dataset by, nothing noted yet.
+    fn of(declared: &Declared) -> Vec<Self> {
+        declared
+            .references
+            .iter()
+            .map(|&reference| Referring {
+                reference,
+                converter: None,
+                values: HashMap::new(),
+            })
+            .collect()
+    }
+
+    /// Note the distinct values `array` holds, leaving out nulls: a row referring to nothing
+    /// is not a reference to check.
+    fn note(&mut self, array: &ArrayRef, file: &Path) -> Result<(), String> {
+        let arrays = std::slice::from_ref(array);
+        let (converter, _) = match &mut self.converter {
+            Some(converter) => converter,
+            None => self
+                .converter
+                .insert((converter_of(arrays)?, array.data_type().clone())),
+        };
+        let encoded = converter.convert_columns(arrays).map_err(|err| {
+            format!(
+                "is {}, unlike in the files before it: {err}",
+                array.data_type()
+            )
+        })?;
+
+        for row in (0..array.len()).filter(|&row| array.is_valid(row)) {
+            let value = encoded.row(row).as_ref().to_vec();
+            if !self.values.contains_key(&value) {
+                self.values
+                    .insert(value, (shown(arrays, row)?, file.to_path_buf()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the keys of the dataset referred to, as a reader would, and report the values
+    /// noted that none of them is. A dataset the store does not hold has no keys, so every
+    /// value refers to nothing.
+    async fn dangling(mut self, root: &Root) -> Result<Option<Problem>, VerifyError> {
+        let Some((converter, data_type)) = &self.converter else {
+            return Ok(None);
+        };
+        let Reference {
+            column,
+            dataset,
+            key,
+        } = self.reference;
+        let backend = root.backend();
+        let dir = root.path().join(dataset.layer.as_str()).join(dataset.name);
+        // A file of another dataset, so named from the root rather than from this one's own
+        // directory.
+        let unreadable = |path: &Path, reason: String| Problem::Unreadable {
+            file: relative(root.path(), path),
+            reason: format!("reading the keys `{column}` refers to: {reason}"),
+        };
+
+        for path in live_files(root, &dir).await? {
+            if self.values.is_empty() {
+                break;
+            }
+            let found =
+                strike_keys(backend, &path, key, converter, data_type, &mut self.values).await;
+            if let Err(reason) = found {
+                return Ok(Some(unreadable(&path, reason)));
+            }
+        }
+
+        let missing = self.values.len();
+        let Some((value, file)) = self
+            .values
+            .into_values()
+            .min_by(|a, b| (&a.1, &a.0).cmp(&(&b.1, &b.0)))
+        else {
+            return Ok(None);
+        };
+        Ok(Some(Problem::Dangling {
+            column: column.to_string(),
+            dataset,
+            key,
+            value,
+            file,
+            missing,
+        }))
+    }
+}
+
+/// A converter for rows of `arrays`' types.
+fn converter_of(arrays: &[ArrayRef]) -> Result<RowConverter, String> {
+    RowConverter::new(
+        arrays
+            .iter()
+            .map(|array| SortField::new(array.data_type().clone()))
+            .collect(),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Row `row` of `arrays`, as a problem shows it: one value alone, several as a tuple.
+fn shown(arrays: &[ArrayRef], row: usize) -> Result<String, String> {
+    let values = arrays
+        .iter()
+        .map(|array| {
+            ArrayFormatter::try_new(array, &FormatOptions::default())
+                .map(|shown| shown.value(row).to_string())
+                .map_err(|err| err.to_string())
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    Ok(key_name(
+        &values.iter().map(String::as_str).collect::<Vec<_>>(),
+    ))
+}
+
+/// The files of the dataset in `dir` a reader reads.
+async fn live_files(root: &Root, dir: &Path) -> Result<BTreeSet<PathBuf>, VerifyError> {
+    let backend = root.backend();
+    Ok(match Published::read(backend, dir).await? {
+        Some(published) => published.files().clone(),
+        None => live_partitions_where(backend, dir, &|_, _| true)
+            .await?
+            .into_iter()
+            .flat_map(|partition| partition.files)
+            .collect(),
+    }
+    .into_iter()
+    .filter(|file| is_parquet(file))
+    .collect())
+}
+
+/// Remove from `values` each value of `column` in one parquet file, encoded by `converter` —
+/// and so cast first to `data_type`, the type it converts, since a key and a reference to it
+/// need only agree in value. The column is read alone, a row group at a time.
+async fn strike_keys<V>(
+    backend: &Backend,
+    path: &Path,
+    column: &str,
+    converter: &RowConverter,
+    data_type: &DataType,
+    values: &mut HashMap<Vec<u8>, V>,
+) -> Result<(), String> {
+    let builder = backend
+        .parquet(path, None)
+        .await
+        .map_err(|err| err.to_string())?;
+    if builder.schema().field_with_name(column).is_err() {
+        return Err(format!("it has no column `{column}`"));
+    }
+    let mask = ProjectionMask::columns(builder.parquet_schema(), [column]);
+    let mut batches = builder
+        .with_projection(mask)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    while let Some(batch) = batches.try_next().await.map_err(|err| err.to_string())? {
+        let Some(array) = batch.column_by_name(column) else {
+            continue;
+        };
+        let array = arrow::compute::cast(array, data_type).map_err(|err| err.to_string())?;
+        let encoded = converter
+            .convert_columns(&[array])
+            .map_err(|err| err.to_string())?;
+        for row in encoded.iter() {
+            values.remove(row.as_ref());
+        }
+    }
+    Ok(())
+}
+
+/// The first key of `columns` that more than one row of `files` holds, if any, with the two
+/// files it is first found in and how many rows repeat a key in all.
+///
+/// The engine finds it, grouping the rows by key and keeping the groups of more than one, so
+/// no key is held here; the files are queried as one table with no partition columns, as
+/// only the key is asked of them. The example alone is then looked for file by file.
+async fn duplicate(
+    backend: &Backend,
+    dir: &Path,
+    files: &[PathBuf],
+    columns: &'static [&'static str],
+) -> Result<Option<Problem>, String> {
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let ctx = context(MEMORY_LIMIT);
+    let partition = LivePartition {
+        keys: Vec::new(),
+        files: files.to_vec(),
+    };
+    let Some(table) = read_live(&ctx, backend, dir, &[partition])
+        .await
+        .map_err(|err| err.to_string())?
+    else {
+        return Ok(None);
+    };
+    ctx.ctx
+        .register_table(LIVE_TABLE, table.into_view())
+        .map_err(|err| err.to_string())?;
+
+    let listed = columns
+        .iter()
+        .map(|column| format!("\"{column}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT {listed}, CAST(SUM(\"#rows\" - 1) OVER () AS BIGINT) AS \"#repeated\" \
+         FROM (SELECT {listed}, COUNT(*) AS \"#rows\" FROM {LIVE_TABLE} \
+               GROUP BY {listed} HAVING COUNT(*) > 1) AS repeated_keys \
+         ORDER BY {listed} LIMIT 1"
+    );
+    let batches = ctx
+        .sql(&sql)
+        .await
+        .map_err(|err| err.to_string())?
+        .collect()
+        .await
+        .map_err(|err| err.to_string())?;
+    let Some(batch) = batches.into_iter().find(|batch| batch.num_rows() > 0) else {
+        return Ok(None);
+    };
+
+    let key = &batch.columns()[..columns.len()];
+    let repeated = batch
+        .column(columns.len())
+        .as_primitive::<Int64Type>()
+        .value(0);
+    let (first, second) = holding(backend, files, columns, key).await?;
+    Ok(Some(Problem::Duplicate {
+        columns: columns.iter().map(|column| column.to_string()).collect(),
+        value: shown(key, 0)?,
+        first: relative(dir, &first),
+        second: relative(dir, &second),
+        repeated: usize::try_from(repeated).map_err(|err| err.to_string())?,
+    }))
+}
+
+/// The first two of `files`, in the order a reader reads them, holding the one-row `key` of
+/// `columns` — the same file twice if it repeats the key itself. Only the key's columns are
+/// read, a row group at a time.
+async fn holding(
+    backend: &Backend,
+    files: &[PathBuf],
+    columns: &[&str],
+    key: &[ArrayRef],
+) -> Result<(PathBuf, PathBuf), String> {
+    let converter = converter_of(key)?;
+    let wanted = converter
+        .convert_columns(key)
+        .map_err(|err| err.to_string())?
+        .row(0)
+        .owned();
+
+    let mut found = Vec::new();
+    for path in files {
+        let builder = backend
+            .parquet(path, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        let mask = ProjectionMask::columns(builder.parquet_schema(), columns.iter().copied());
+        let mut batches = builder
+            .with_projection(mask)
+            .build()
+            .map_err(|err| err.to_string())?;
+        while let Some(batch) = batches.try_next().await.map_err(|err| err.to_string())? {
+            let arrays = columns
+                .iter()
+                .zip(key)
+                .map(|(column, value)| {
+                    let array = batch
+                        .column_by_name(column)
+                        .ok_or_else(|| format!("{} has no column `{column}`", path.display()))?;
+                    arrow::compute::cast(array, value.data_type()).map_err(|err| err.to_string())
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let rows = converter
+                .convert_columns(&arrays)
+                .map_err(|err| err.to_string())?;
+            for _ in rows.iter().filter(|row| *row == wanted.row()) {
+                found.push(path.clone());
+                if let [first, second] = found.as_slice() {
+                    return Ok((first.clone(), second.clone()));
+                }
+            }
+        }
+    }
+    Err("the repeated key was not found twice reading the files one by one".to_string())
+}
+
+/// `path` as it is shown in a problem: below the dataset's directory, which is itself `.`.
+fn relative(dir: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(dir) {
+        Ok(below) if below.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(below) => below.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// A file a write in progress stages to on disk, which no reader reads and nothing should
+/// verify.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Int64Array, RecordBatch, StringArray};
+    use arrow::datatypes::Field;
+    use chrono::{DateTime, TimeZone, Utc};
+    use geo_types::{LineString, Point};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::dataset::DatasetSpec;
+    use crate::derive::{GeoRow, write_geo_rows};
+    use crate::layer::layers;
+    use crate::rows::Dated;
+    use crate::write::encode_batches;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct TrackRow {
+        track_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        seen_at: DateTime<Utc>,
+    }
+
+    impl Row for TrackRow {
+        type Layer = layers::Silver;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("track", "seen_date");
+        const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
+        const INSTANTS: &'static [&'static str] = &["seen_at"];
+        const UNIQUE: &'static [&'static str] = &["track_id"];
+    }
+
+    impl Dated for TrackRow {
+        fn partition_date(&self) -> NaiveDate {
+            self.seen_at.date_naive()
+        }
+    }
+
+    /// Bronze, which nothing holds to its unique columns as it is appended to.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct PingRow {
+        ping_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        t: DateTime<Utc>,
+    }
+
+    impl Row for PingRow {
+        type Layer = layers::Bronze;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("ping", "ingested_date");
+        const INSTANTS: &'static [&'static str] = &["t"];
+        const UNIQUE: &'static [&'static str] = &["ping_id"];
+    }
+
+    /// Bronze identified by a device and an instant together, neither of which is a name.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct FixRow {
+        device_id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        t: DateTime<Utc>,
+    }
+
+    impl Row for FixRow {
+        type Layer = layers::Bronze;
+        const DATASET: DatasetSpec<Self::Layer> = DatasetSpec::partitioned("fix", "ingested_date");
+        const INSTANTS: &'static [&'static str] = &["t"];
+        const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[&["device_id", "t"]];
+    }
+
+    /// Bronze referring to the pings, a sighting being of at most one of them.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct SightingRow {
+        ping_id: Option<String>,
+    }
+
+    impl Row for SightingRow {
+        type Layer = layers::Bronze;
+        const DATASET: DatasetSpec<Self::Layer> =
+            DatasetSpec::partitioned("sighting", "ingested_date");
+        const REFERENCES: &'static [Reference] = &[Reference::to::<PingRow>("ping_id", "ping_id")];
+    }
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 7, day, 9, 0, 0).unwrap()
+    }
+
+    fn track(id: &str, day: u32) -> GeoRow<TrackRow, LineString<f64>> {
+        let berlin = Point::new(13.404954, 52.520008);
+        GeoRow {
+            row: TrackRow {
+                track_id: id.to_string(),
+                seen_at: at(day),
+            },
+            geometry: LineString::from(vec![berlin, berlin]),
+            country: Country::GERMANY,
+        }
+    }
+
+    async fn ping(root: &Root, id: &str, day: u32) {
+        root.rows_of::<PingRow>()
+            .on_date(at(day).date_naive())
+            .unwrap()
+            .append_rows(
+                at(day),
+                &[PingRow {
+                    ping_id: id.to_string(),
+                    t: at(day),
+                }],
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn fix(root: &Root, device_id: &str, day: u32) {
+        root.rows_of::<FixRow>()
+            .on_date(at(day).date_naive())
+            .unwrap()
+            .append_rows(
+                at(day),
+                &[FixRow {
+                    device_id: device_id.to_string(),
+                    t: at(21),
+                }],
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn sightings(root: &Root, ping_ids: &[Option<&str>]) {
+        let rows: Vec<SightingRow> = ping_ids
+            .iter()
+            .map(|ping_id| SightingRow {
+                ping_id: ping_id.map(str::to_string),
+            })
+            .collect();
+        root.rows_of::<SightingRow>()
+            .on_date(at(21).date_naive())
+            .unwrap()
+            .append_rows(at(21), &rows)
+            .await
+            .unwrap();
+    }
+
+    /// The one file in `dir`.
+    fn only_file(dir: &Path) -> PathBuf {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1, "{files:?}");
+        files.remove(0)
+    }
+
+    /// A copy of the one file in `from`, as `name` in `to`.
+    fn copy(from: &Path, to: &Path, name: &str) {
+        std::fs::create_dir_all(to).unwrap();
+        std::fs::copy(only_file(from), to.join(name)).unwrap();
+    }
+
+    async fn problems<R: Row>(root: &Root) -> Vec<String> {
+        dataset(root, &Declared::of::<R>())
+            .await
+            .unwrap()
+            .problems
+            .iter()
+            .map(Problem::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn a_store_its_writers_wrote_is_sound() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, &[track("a", 21), track("b", 22)])
+            .await
+            .unwrap();
+        ping(&root, "a", 21).await;
+        ping(&root, "b", 21).await;
+
+        let tracks = dataset(&root, &Declared::of::<TrackRow>()).await.unwrap();
+        let pings = dataset(&root, &Declared::of::<PingRow>()).await.unwrap();
+
+        assert!(tracks.is_sound(), "{:?}", tracks.problems);
+        assert_eq!(tracks.files, 2);
+        assert!(pings.is_sound(), "{:?}", pings.problems);
+        assert_eq!(pings.files, 2);
+    }
+
+    /// A dataset nothing has written holds nothing wrong.
+    #[tokio::test]
+    async fn an_absent_dataset_is_sound() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let tracks = dataset(&Root::new(tmp.path()), &Declared::of::<TrackRow>())
+            .await
+            .unwrap();
+
+        assert!(tracks.is_sound());
+        assert_eq!(tracks.files, 0);
+    }
+
+    #[tokio::test]
+    async fn a_file_that_is_not_parquet_is_unreadable() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        ping(&root, "a", 21).await;
+        let partition = tmp.path().join("bronze/ping/ingested_date=2026-07-21");
+        std::fs::write(partition.join("torn.parquet"), b"PAR1 and then nothing").unwrap();
+
+        let problems = problems::<PingRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with("ingested_date=2026-07-21/torn.parquet: unreadable"),
+            "{problems:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_directory_the_layout_has_no_place_for_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, &[track("a", 21)]).await.unwrap();
+        let dataset = tmp.path().join("silver/track");
+        let written = dataset.join("country=DE/seen_date=2026-07-21");
+        copy(&written, &dataset.join("seen_date=2026-07-21"), "a.parquet");
+        copy(
+            &written,
+            &dataset.join("country=ZZ/seen_date=2026-07-21"),
+            "a.parquet",
+        );
+        copy(
+            &written,
+            &dataset.join("country=DE/seen_date=today"),
+            "a.parquet",
+        );
+
+        let problems = problems::<TrackRow>(&root).await;
+
+        assert!(
+            problems.contains(
+                &"seen_date=2026-07-21/: keyed on `seen_date` where `country` belongs".to_string()
+            ),
+            "{problems:?}"
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem
+                    .starts_with("country=ZZ/seen_date=2026-07-21/: unknown country")),
+            "{problems:?}"
+        );
+        assert!(
+            problems.contains(
+                &"country=DE/seen_date=today/: `today` is not a %Y-%m-%d date".to_string()
+            ),
+            "{problems:?}"
+        );
+    }
+
+    /// Bronze is appended to without its unique columns being checked, so a repeat is only
+    /// found by reading it back.
+    #[tokio::test]
+    async fn a_unique_value_held_twice_is_reported_with_both_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        ping(&root, "a", 21).await;
+        ping(&root, "a", 22).await;
+
+        let problems = problems::<PingRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with(
+                "column `ping_id` is unique, but a is in both ingested_date=2026-07-21/"
+            ),
+            "{problems:?}"
+        );
+        assert!(
+            problems[0].contains(" and ingested_date=2026-07-22/"),
+            "{problems:?}"
+        );
+    }
+
+    /// Repeats are counted by row, so a key held three times is repeated twice.
+    #[tokio::test]
+    async fn a_key_repeated_within_one_file_is_reported_as_that_file_twice() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let row = |ping_id: &str| PingRow {
+            ping_id: ping_id.to_string(),
+            t: at(21),
+        };
+        root.rows_of::<PingRow>()
+            .on_date(at(21).date_naive())
+            .unwrap()
+            .append_rows(at(21), &[row("b"), row("a"), row("b"), row("b")])
+            .await
+            .unwrap();
+
+        let problems = problems::<PingRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0]
+                .starts_with("column `ping_id` is unique, but b is in ingested_date=2026-07-21/"),
+            "{problems:?}"
+        );
+        assert!(
+            problems[0].ends_with(" twice (2 repeats in all)"),
+            "{problems:?}"
+        );
+    }
+
+    /// Rows sharing one column of a key are distinct; rows sharing all of them are a repeat.
+    #[tokio::test]
+    async fn a_key_of_several_columns_held_twice_is_reported_as_a_tuple() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        fix(&root, "d", 21).await;
+        fix(&root, "e", 21).await;
+        assert_eq!(problems::<FixRow>(&root).await, Vec::<String>::new());
+
+        fix(&root, "d", 22).await;
+        let problems = problems::<FixRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with(
+                "columns `device_id`, `t` are unique together, but (d, 2026-07-21T09:00:00"
+            ),
+            "{problems:?}"
+        );
+        assert!(
+            problems[0].contains(" and ingested_date=2026-07-22/"),
+            "{problems:?}"
+        );
+    }
+
+    /// A null refers to nothing, so it is not looked for.
+    #[tokio::test]
+    async fn references_to_keys_the_store_holds_are_sound() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        ping(&root, "a", 21).await;
+        ping(&root, "b", 22).await;
+        sightings(&root, &[Some("a"), Some("b"), Some("a"), None]).await;
+
+        assert_eq!(problems::<SightingRow>(&root).await, Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn a_reference_to_a_key_the_store_lacks_is_reported_once_with_a_count() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        ping(&root, "a", 21).await;
+        sightings(&root, &[Some("a"), Some("c"), Some("b"), Some("c")]).await;
+
+        let problems = problems::<SightingRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with(
+                "column `ping_id` refers to bronze.ping.ping_id, which holds no b \
+                 (in ingested_date=2026-07-21/"
+            ),
+            "{problems:?}"
+        );
+        assert!(
+            problems[0].ends_with("(2 values missing in all)"),
+            "{problems:?}"
+        );
+    }
+
+    /// A dataset never written holds no keys, so everything referring to it dangles.
+    #[tokio::test]
+    async fn a_reference_to_a_dataset_the_store_lacks_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        sightings(&root, &[Some("a")]).await;
+
+        let problems = problems::<SightingRow>(&root).await;
+
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].contains("refers to bronze.ping.ping_id, which holds no a"),
+            "{problems:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn an_instant_held_as_a_plain_integer_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("ping_id", DataType::Utf8, false),
+            Field::new("t", DataType::Int64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["a"])),
+                Arc::new(Int64Array::from(vec![at(21).timestamp_millis()])),
+            ],
+        )
+        .unwrap();
+        let partition = tmp.path().join("bronze/ping/ingested_date=2026-07-21");
+        std::fs::create_dir_all(&partition).unwrap();
+        std::fs::write(
+            partition.join("a.parquet"),
+            encode_batches(&[batch], &[]).unwrap(),
+        )
+        .unwrap();
+
+        let problems = problems::<PingRow>(&root).await;
+
+        assert_eq!(
+            problems,
+            [
+                "ingested_date=2026-07-21/a.parquet: column `t` is Int64, not a UTC millisecond timestamp"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_geometry_file_without_geoparquet_metadata_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, &[track("a", 21)]).await.unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "track_id",
+            DataType::Utf8,
+            false,
+        )]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(StringArray::from(vec!["b"]))]).unwrap();
+        let partition = tmp
+            .path()
+            .join("silver/track/country=DE/seen_date=2026-07-21");
+        std::fs::write(
+            partition.join("plain.parquet"),
+            encode_batches(&[batch], &[]).unwrap(),
+        )
+        .unwrap();
+
+        let problems = problems::<TrackRow>(&root).await;
+
+        assert!(
+            problems.contains(
+                &"country=DE/seen_date=2026-07-21/plain.parquet: geo metadata: there is no `geo` key"
+                    .to_string()
+            ),
+            "{problems:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_published_file_the_store_lacks_is_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = Root::new(tmp.path());
+        write_geo_rows(&root, &[track("a", 21)]).await.unwrap();
+        let partition = tmp
+            .path()
+            .join("silver/track/country=DE/seen_date=2026-07-21");
+        let file = only_file(&partition);
+        std::fs::remove_file(&file).unwrap();
+
+        let problems = problems::<TrackRow>(&root).await;
+
+        assert_eq!(
+            problems,
+            [format!(
+                "country=DE/seen_date=2026-07-21/{}: published but not in the store",
+                file.file_name().unwrap().to_string_lossy()
+            )]
+        );
+    }
+
+    /// The projected column's CRS is the zone of the country a file is filed under, so a
+    /// file moved to a country whose zone it was not written in would be found. With only
+    /// one country known, this holds the check to the CRS of the one there is.
+    #[test]
+    fn a_projected_column_in_another_crs_is_reported() {
+        let crs84: serde_json::Value = serde_json::from_str(CRS84_PROJJSON).unwrap();
+        let geo = serde_json::json!({
+            "version": GEOPARQUET_VERSION,
+            "columns": {
+                GEOMETRY: { "encoding": WKB, "crs": crs84 },
+                PROJECTED_GEOMETRY: { "encoding": WKB, "crs": crs84 },
+            },
+        });
+        let kv = KeyValue::new(GEO_KEY.to_string(), geo.to_string());
+
+        assert_eq!(
+            check_geo(Some(&kv), Some(Country::GERMANY)),
+            Err("`geometry_projected` is in OGC:CRS84, not EPSG:25832".to_string())
+        );
+        assert_eq!(check_geo(Some(&kv), None), Ok(()));
+    }
+}
@@ -1,12 +1,18 @@
 //! Writing a batch of rows into the store as one parquet file.
 
 use std::path::Path;
+use std::sync::Arc;
 
 use arrow::array::RecordBatch;
+use arrow::datatypes::SchemaRef;
 use object_store::local::LocalFileSystem;
 use object_store::path::Path as ObjectPath;
-use parquet::arrow::AsyncArrowWriter;
+use object_store::{ObjectStore, PutMode};
 use parquet::arrow::async_writer::ParquetObjectWriter;
+use parquet::arrow::{ArrowWriter, AsyncArrowWriter};
+use parquet::file::metadata::KeyValue;
+
+use crate::store::Backend;
 
 /// Failure writing a parquet file into the store.
 #[derive(Debug, thiserror::Error)]
@@ -29,15 +35,18 @@ pub enum WriteError {
 
 /// Write `batches` to `path` as a single parquet file, taking the schema from the first.
 ///
-/// The file appears at `path` only once fully written: the write goes through
-/// [`LocalFileSystem`], which stages to a temporary sibling and renames on completion, so
-/// an interrupted write leaves nothing a reader can list or open.
-pub(crate) async fn write_batches(path: &Path, batches: &[RecordBatch]) -> Result<(), WriteError> {
+/// The file appears at `path` only once fully written — see [`writer_at`] — so an
+/// interrupted write leaves nothing a reader can list or open, and only where nothing is yet.
+pub(crate) async fn write_batches(
+    backend: &Backend,
+    path: &Path,
+    batches: &[RecordBatch],
+) -> Result<(), WriteError> {
     let Some(first) = batches.first() else {
         return Err(WriteError::Empty);
     };
 
-    let mut writer = writer_at(path, first.schema())?;
+    let mut writer = writer_at(backend, path, first.schema())?;
     for batch in batches {
         writer.write(batch).await?;
     }
@@ -45,20 +54,138 @@ pub(crate) async fn write_batches(path: &Path, batches: &[RecordBatch]) -> Resul
     Ok(())
 }
 
-/// A parquet writer onto `path`, through the store's write path: [`LocalFileSystem`]
-/// stages to a temporary sibling and renames on completion, so an interrupted write leaves
-/// nothing a reader can list or open.
+/// A parquet writer onto `path`, whose file appears only once the writer is closed, and
+/// which fails rather than replace a file already there.
+///
+/// On disk the write goes through [`LocalFileSystem`], which stages to a temporary sibling
+/// and renames on completion. In an object store the file is held until it is closed and
+/// then put on the condition that nothing is at its key, so two appends racing for one key
+/// cannot both succeed. Holding the file is the price of that: a conditional put is one
+/// request, so what an append writes to an object store has to fit in memory.
 pub(crate) fn writer_at(
+    backend: &Backend,
     path: &Path,
-    schema: arrow::datatypes::SchemaRef,
-) -> Result<AsyncArrowWriter<ParquetObjectWriter>, WriteError> {
-    let location = ObjectPath::from_absolute_path(path).map_err(|source| WriteError::Path {
+    schema: SchemaRef,
+) -> Result<FileWriter, WriteError> {
+    let location = backend.location(path).map_err(|source| WriteError::Path {
         path: path.display().to_string(),
         source,
     })?;
-    let object_writer =
-        ParquetObjectWriter::new(std::sync::Arc::new(LocalFileSystem::new()), location);
-    Ok(AsyncArrowWriter::try_new(object_writer, schema, None)?)
+    let sink = match backend {
+        Backend::Local if path.exists() => {
+            return Err(WriteError::Exists {
+                path: path.display().to_string(),
+            });
+        }
+        Backend::Local => streamed(Arc::new(LocalFileSystem::new()), location, schema)?,
+        Backend::Objects { store, .. } => Sink::Held {
+            writer: ArrowWriter::try_new(Vec::new(), schema, None)?,
+            store: store.clone(),
+            location,
+        },
+    };
+    Ok(FileWriter {
+        path: path.display().to_string(),
+        sink,
+    })
+}
+
+/// `batches` as the bytes of one parquet file, taking the schema from the first — for a file
+/// whose name is read off what it holds, and so cannot be chosen before it is written.
+///
+/// `metadata` is added to the file's footer, for the lineage of a derived file.
+pub(crate) fn encode_batches(
+    batches: &[RecordBatch],
+    metadata: &[KeyValue],
+) -> Result<Vec<u8>, WriteError> {
+    let Some(first) = batches.first() else {
+        return Err(WriteError::Empty);
+    };
+
+    let mut writer = ArrowWriter::try_new(Vec::new(), first.schema(), None)?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    for key_value in metadata {
+        writer.append_key_value_metadata(key_value.clone());
+    }
+    Ok(writer.into_inner()?)
+}
+
+fn streamed(
+    store: Arc<dyn ObjectStore>,
+    location: ObjectPath,
+    schema: SchemaRef,
+) -> Result<Sink, WriteError> {
+    let object_writer = ParquetObjectWriter::new(store, location);
+    Ok(Sink::Streamed(AsyncArrowWriter::try_new(
+        object_writer,
+        schema,
+        None,
+    )?))
+}
+
+/// One parquet file being written into the store. See [`writer_at`].
+pub(crate) struct FileWriter {
+    path: String,
+    sink: Sink,
+}
+
+enum Sink {
+    /// Written out as it goes, and made visible whole when closed.
+    Streamed(AsyncArrowWriter<ParquetObjectWriter>),
+    /// Built in memory, and put only where nothing is yet.
+    Held {
+        writer: ArrowWriter<Vec<u8>>,
+        store: Arc<dyn ObjectStore>,
+        location: ObjectPath,
+    },
+}
+
+impl FileWriter {
+    pub(crate) async fn write(&mut self, batch: &RecordBatch) -> Result<(), WriteError> {
+        match &mut self.sink {
+            Sink::Streamed(writer) => writer.write(batch).await?,
+            Sink::Held { writer, .. } => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    /// Add `metadata` to the file's footer, for the GeoParquet `geo` key.
+    pub(crate) fn append_key_value_metadata(&mut self, metadata: KeyValue) {
+        match &mut self.sink {
+            Sink::Streamed(writer) => writer.append_key_value_metadata(metadata),
+            Sink::Held { writer, .. } => writer.append_key_value_metadata(metadata),
+        }
+    }
+
+    /// Finish the file, which is when it appears. A held file whose key something else took
+    /// meanwhile is [`WriteError::Exists`], and what that something wrote stands.
+    pub(crate) async fn close(self) -> Result<(), WriteError> {
+        match self.sink {
+            Sink::Streamed(writer) => {
+                writer.close().await?;
+            }
+            Sink::Held {
+                writer,
+                store,
+                location,
+            } => {
+                let bytes = writer.into_inner()?;
+                match store
+                    .put_opts(&location, bytes.into(), PutMode::Create.into())
+                    .await
+                {
+                    Ok(_) => {}
+                    Err(object_store::Error::AlreadyExists { .. }) => {
+                        return Err(WriteError::Exists { path: self.path });
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -112,7 +239,7 @@ mod tests {
             .unwrap()
             .batch_file(Utc.with_ymd_and_hms(2026, 7, 26, 14, 5, 30).unwrap());
 
-        write_batches(&path, &[batch(vec![1, 2], vec!["a", "b"])])
+        write_batches(&Backend::Local, &path, &[batch(vec![1, 2], vec!["a", "b"])])
             .await
             .unwrap();
 
@@ -125,9 +252,10 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         let path = Root::new(tmp.path())
             .dataset(SENSOR_READING)
-            .partition_file();
+            .batch_file(Utc.with_ymd_and_hms(2026, 7, 26, 14, 5, 30).unwrap());
 
         write_batches(
+            &Backend::Local,
             &path,
             &[batch(vec![1], vec!["a"]), batch(vec![2, 3], vec!["b", "c"])],
         )
@@ -142,9 +270,11 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         let path = Root::new(tmp.path())
             .dataset(SENSOR_READING)
-            .partition_file();
+            .batch_file(Utc.with_ymd_and_hms(2026, 7, 26, 14, 5, 30).unwrap());
 
-        let err = write_batches(&path, &[]).await.unwrap_err();
+        let err = write_batches(&Backend::Local, &path, &[])
+            .await
+            .unwrap_err();
 
         assert!(matches!(err, WriteError::Empty), "unexpected error: {err}");
         assert!(!path.exists(), "no file should have been created");
@@ -155,7 +285,7 @@ mod tests {
         let tmp = tempfile::tempdir().unwrap();
         let path = Root::new(tmp.path())
             .dataset(SENSOR_READING)
-            .partition_file();
+            .batch_file(Utc.with_ymd_and_hms(2026, 7, 26, 14, 5, 30).unwrap());
         // Batches with differing schemas: the first is accepted, the second fails mid-write.
         let mismatched = RecordBatch::try_new(
             Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)])),
@@ -163,9 +293,13 @@ mod tests {
         )
         .unwrap();
 
-        let err = write_batches(&path, &[batch(vec![1], vec!["a"]), mismatched])
-            .await
-            .unwrap_err();
+        let err = write_batches(
+            &Backend::Local,
+            &path,
+            &[batch(vec![1], vec!["a"]), mismatched],
+        )
+        .await
+        .unwrap_err();
 
         assert!(matches!(err, WriteError::Parquet(_)), "unexpected: {err}");
         assert!(
@@ -206,6 +340,7 @@ mod tests {
     #[tokio::test]
     async fn a_relative_destination_is_rejected() {
         let err = write_batches(
+            &Backend::Local,
             std::path::Path::new("relative/part-0.parquet"),
             &[batch(vec![1], vec!["a"])],
         )
@@ -0,0 +1,76 @@
+//! What a store in an object store has to keep, written once and checked against whichever
+//! store a test hands it: an in-memory one in the default profile, and MinIO behind docker.
+//! A bucket is held to exactly the promises memory is, so the two cannot drift apart.
+
+use std::sync::Arc;
+
+use arrow::array::{Int64Array, RecordBatch};
+use arrow::datatypes::{DataType, Field, Schema};
+use chrono::{NaiveDate, TimeZone, Utc};
+use medallion::{DatasetSpec, Query, Root, WriteError, layers};
+
+pub const THING: DatasetSpec<layers::Bronze> = DatasetSpec::partitioned("thing", "kind");
+pub const DAILY: DatasetSpec<layers::Silver> = DatasetSpec::partitioned("daily", "day");
+
+pub fn ids(ids: Vec<i64>) -> RecordBatch {
+    let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+    RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+}
+
+pub fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 7, day).unwrap()
+}
+
+/// A second capture at the same instant is refused by the store, and the first is left as
+/// it was written.
+pub async fn an_append_never_replaces_a_capture(root: &Root) {
+    let at = Utc.with_ymd_and_hms(2026, 7, 26, 9, 0, 0).unwrap();
+    let partition = root.dataset(THING).partition("kind", "a").unwrap();
+
+    partition.append(at, &[ids(vec![1, 2])]).await.unwrap();
+    let again = partition.append(at, &[ids(vec![3])]).await;
+
+    assert!(matches!(again, Err(WriteError::Exists { .. })), "{again:?}");
+    let query = Query::new(root.clone());
+    query.register_by_name(THING).await.unwrap();
+    assert_eq!(
+        query
+            .count("SELECT COUNT(*) AS count FROM thing")
+            .await
+            .unwrap(),
+        2
+    );
+}
+
+/// A rebuild producing fewer dates deletes the rest, so a reader sees what the last run
+/// produced and nothing older.
+pub async fn a_replacement_sweeps_what_the_run_no_longer_produces(root: &Root) {
+    let daily = root.dataset(DAILY);
+    daily
+        .replace_dates(&[(date(1), ids(vec![1])), (date(2), ids(vec![2, 3]))])
+        .await
+        .unwrap();
+
+    let replaced = daily
+        .replace_dates(&[(date(2), ids(vec![4]))])
+        .await
+        .unwrap();
+
+    assert_eq!((replaced.written, replaced.removed), (1, 1));
+    let query = Query::new(root.clone());
+    query.register_by_name(DAILY).await.unwrap();
+    assert_eq!(
+        query
+            .count("SELECT COUNT(*) AS count FROM daily WHERE day = '2026-07-02' AND id = 4")
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        query
+            .count("SELECT COUNT(*) AS count FROM daily")
+            .await
+            .unwrap(),
+        1
+    );
+}
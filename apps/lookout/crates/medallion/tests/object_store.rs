@@ -0,0 +1,108 @@
+//! A store kept in an object store rather than on disk: the same layout, the same promises,
+//! reached through the same [`Root`].
+//!
+//! An in-memory store stands in for a bucket here, so these run in the default profile with
+//! nothing to start. `object_store_docker.rs` runs the shared checks against MinIO, which is
+//! what proves a real S3 API refuses an append the way memory does.
+
+use std::sync::Arc;
+
+use chrono::{Duration, TimeZone, Utc};
+use medallion::{Query, Root};
+use object_store::memory::InMemory;
+use url::Url;
+
+mod common;
+
+use common::{DAILY, THING, date, ids};
+
+fn in_memory() -> Root {
+    Root::in_object_store(
+        Arc::new(InMemory::new()),
+        &Url::parse("memory:///medallion").unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn an_append_never_replaces_a_capture() {
+    common::an_append_never_replaces_a_capture(&in_memory()).await;
+}
+
+#[tokio::test]
+async fn a_replacement_sweeps_what_the_run_no_longer_produces() {
+    common::a_replacement_sweeps_what_the_run_no_longer_produces(&in_memory()).await;
+}
+
+/// A compacted partition is read as its generation alone, with the files it merged left in
+/// the bucket beside it.
+#[tokio::test]
+async fn a_compacted_partition_reads_its_rows_once() {
+    let root = in_memory();
+    let partition = root.dataset(THING).partition("kind", "a").unwrap();
+    let start = Utc.with_ymd_and_hms(2026, 7, 26, 9, 0, 0).unwrap();
+    for minute in 0..3 {
+        partition
+            .append(start + Duration::minutes(minute), &[ids(vec![minute])])
+            .await
+            .unwrap();
+    }
+
+    let compaction = root
+        .dataset(THING)
+        .compact(start + Duration::hours(1), 2)
+        .await
+        .unwrap();
+
+    assert_eq!(compaction.partitions, 1);
+    assert_eq!((compaction.before.files, compaction.after.files), (3, 1));
+    let query = Query::new(root.clone());
+    query.register_by_name(THING).await.unwrap();
+    assert_eq!(
+        query
+            .count("SELECT COUNT(*) AS count FROM thing")
+            .await
+            .unwrap(),
+        3
+    );
+}
+
+/// The summary counts rows from each object's footer, and sizes from the listing.
+#[tokio::test]
+async fn a_summary_counts_what_the_bucket_holds() {
+    let root = in_memory();
+    root.dataset(DAILY)
+        .replace_dates(&[(date(1), ids(vec![1, 2])), (date(2), ids(vec![3]))])
+        .await
+        .unwrap();
+
+    let summary = medallion::summary::dataset(&root, DAILY.info())
+        .await
+        .unwrap();
+
+    assert_eq!(summary.partitions.len(), 2);
+    assert_eq!((summary.contents.files, summary.contents.rows), (2, 3));
+    assert!(summary.contents.bytes > 0);
+}
+
+/// A prefix exists only while an object sits below it, so a dataset swept of every
+/// partition is one nothing finds — and no table, as on disk.
+#[tokio::test]
+async fn a_dataset_swept_of_everything_holds_nothing() {
+    let root = in_memory();
+    let daily = root.dataset(DAILY);
+    daily
+        .replace_dates(&[(date(1), ids(vec![1]))])
+        .await
+        .unwrap();
+
+    let removed = daily.retain_partitions::<&str>("day", &[]).await.unwrap();
+
+    assert_eq!(removed, 1);
+    assert!(!daily.holds_files().await);
+    assert!(
+        Query::new(root.clone())
+            .register_by_name(DAILY)
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,73 @@
+//! The shared object-store checks against MinIO, a real S3 API, so that an append is seen
+//! refused by the conditional put a bucket actually implements rather than by memory's
+//! imitation of one. Needs docker, so it is left out of the `no-docker` profile.
+
+use std::sync::Arc;
+
+use medallion::Root;
+use object_store::aws::AmazonS3Builder;
+use testcontainers::core::{CmdWaitFor, ExecCommand};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, ImageExt};
+use testcontainers_modules::minio::MinIO;
+use url::Url;
+
+mod common;
+
+/// The bucket the store is kept in.
+const BUCKET: &str = "lookout";
+/// The credentials the MinIO image starts with.
+const CREDENTIALS: &str = "minioadmin";
+
+/// Start a throwaway MinIO holding [`BUCKET`], returning it (drop = stop) and its endpoint.
+///
+/// Pinned to a release that honours `If-None-Match` on a put, which is what an append
+/// relies on; the module's default predates it.
+async fn start_minio() -> (ContainerAsync<MinIO>, String) {
+    let container = MinIO::default()
+        .with_tag("RELEASE.2025-04-22T22-12-26Z")
+        .start()
+        .await
+        .expect("start minio");
+    let make_bucket = format!(
+        "mc alias set local http://127.0.0.1:9000 {CREDENTIALS} {CREDENTIALS} \
+         && mc mb local/{BUCKET}"
+    );
+    container
+        .exec(
+            ExecCommand::new(["sh", "-c", &make_bucket])
+                .with_cmd_ready_condition(CmdWaitFor::exit_code(0)),
+        )
+        .await
+        .expect("create the bucket");
+    let port = container
+        .get_host_port_ipv4(9000)
+        .await
+        .expect("minio port");
+    (container, format!("http://127.0.0.1:{port}"))
+}
+
+/// The store under `prefix` of the bucket, so each check keeps to a store of its own.
+fn root(endpoint: &str, prefix: &str) -> Root {
+    let store = AmazonS3Builder::new()
+        .with_endpoint(endpoint)
+        .with_allow_http(true)
+        .with_region("us-east-1")
+        .with_bucket_name(BUCKET)
+        .with_access_key_id(CREDENTIALS)
+        .with_secret_access_key(CREDENTIALS)
+        .build()
+        .expect("s3 store");
+    Root::in_object_store(
+        Arc::new(store),
+        &Url::parse(&format!("s3://{BUCKET}/{prefix}")).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn a_bucket_keeps_the_stores_promises_docker() {
+    let (_minio, endpoint) = start_minio().await;
+
+    common::an_append_never_replaces_a_capture(&root(&endpoint, "append")).await;
+    common::a_replacement_sweeps_what_the_run_no_longer_produces(&root(&endpoint, "replace")).await;
+}
@@ -19,11 +19,13 @@ use std::fmt::{self, Display};
 use std::str::FromStr;
 
 use chrono::{DateTime, NaiveDate, Utc};
-use medallion::{COUNTRY, DatasetSpec, Dated, Geometry, PartitionValue, PathError, Row, layers};
+use medallion::{
+    COUNTRY, DatasetSpec, Dated, Geometry, PartitionValue, PathError, Reference, Row, layers,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::device::DeviceId;
-use crate::session::SessionId;
+use crate::session::{SessionId, SessionRow};
 
 /// One place a stretch of track meets one body of water.
 pub const WATER_CROSSING: DatasetSpec<layers::Silver> =
@@ -165,6 +167,12 @@ impl Row for SessionCrossingRow {
     type Layer = layers::Silver;
     const DATASET: DatasetSpec<Self::Layer> = SESSION_CROSSING;
     const INSTANTS: &'static [&'static str] = &["crossed_at"];
+    /// A session passes a crossing once, at its nearest sample.
+    const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[&["session_id", "crossing_id"]];
+    const REFERENCES: &'static [Reference] = &[
+        Reference::to::<SessionRow>("session_id", "session_id"),
+        Reference::to::<WaterCrossingRow>("crossing_id", "crossing_id"),
+    ];
 }
 
 impl Dated for SessionCrossingRow {
@@ -19,7 +19,8 @@ mod session;
 mod silver;
 mod telemetry;
 
-use medallion::DatasetInfo;
+use medallion::verify::Declared;
+use medallion::{DatasetInfo, DatasetSpec, layers};
 
 pub use crossing::{
     CrossingId, OverlapKind, SESSION_CROSSING, SessionCrossingRow, WATER_CROSSING, WaterCrossingRow,
@@ -33,15 +34,18 @@ pub use session::{
 pub use silver::{TargetError, silver_target};
 pub use telemetry::{
     ACCEL_READING, AccelReadingRow, DEVICE_SESSION, DeviceSessionRow, GPS_READING, GpsReadingRow,
-    RAW_SAMPLE, RawSampleRow,
+    RAW_SAMPLE, REJECTED_SAMPLE, REPARSED_SAMPLE, RawSampleRow, RejectedSampleRow,
+    ReparsedSampleRow,
 };
 
 /// Every dataset defined here, for checks that must cover all of them.
 ///
 /// Held as [`DatasetInfo`] rather than as the specs themselves: a spec carries its layer in
 /// its type, so datasets of different layers cannot sit in one array.
-pub const ALL: [DatasetInfo; 12] = [
+pub const ALL: [DatasetInfo; 14] = [
     RAW_SAMPLE.info(),
+    REJECTED_SAMPLE.info(),
+    REPARSED_SAMPLE.info(),
     GPS_READING.info(),
     ACCEL_READING.info(),
     DEVICE_SESSION.info(),
@@ -55,6 +59,56 @@ pub const ALL: [DatasetInfo; 12] = [
     EXTRACT_MANIFEST.info(),
 ];
 
+/// What each dataset in [`ALL`] declares of its files, in the same order, for checking a
+/// store against the definitions rather than trusting whoever wrote it.
+pub const DECLARED: [Declared; 14] = [
+    Declared::of::<RawSampleRow>(),
+    Declared::of::<RejectedSampleRow>(),
+    Declared::of::<ReparsedSampleRow>(),
+    Declared::of::<GpsReadingRow>(),
+    Declared::of::<AccelReadingRow>(),
+    Declared::of::<DeviceSessionRow>(),
+    Declared::of::<MotisSegmentRow>(),
+    Declared::of::<TrainSegmentRow>(),
+    Declared::of::<SessionRow>(),
+    Declared::of::<SessionSampleRow>(),
+    Declared::of::<WaterCrossingRow>(),
+    Declared::of::<SessionCrossingRow>(),
+    Declared::untyped(OVERTURE_EXTRACT.info()),
+    Declared::of::<ExtractManifestRow>(),
+];
+
+/// The bronze datasets, as specs, for the operations only an append-only dataset has —
+/// compacting one is not something a derived dataset can be asked to do.
+pub const BRONZE: [DatasetSpec<layers::Bronze>; 9] = [
+    RAW_SAMPLE,
+    REJECTED_SAMPLE,
+    REPARSED_SAMPLE,
+    GPS_READING,
+    ACCEL_READING,
+    DEVICE_SESSION,
+    MOTIS_SEGMENT,
+    OVERTURE_EXTRACT,
+    EXTRACT_MANIFEST,
+];
+
+/// The bronze datasets compaction leaves alone.
+///
+/// An extraction writes each of its partitions once, as one stream, and nothing is added to
+/// it afterwards, so an extract never accumulates the file per write a polled dataset does.
+/// It is also the largest thing in the store, which a merge would copy for nothing.
+pub const UNCOMPACTED: [DatasetSpec<layers::Bronze>; 1] = [OVERTURE_EXTRACT];
+
+/// The silver datasets, as specs, for the operations only a rebuilt dataset has — cleaning
+/// up after a rebuild that never finished is nothing an append-only dataset needs.
+pub const SILVER: [DatasetSpec<layers::Silver>; 5] = [
+    TRAIN_SEGMENT,
+    SESSION,
+    SESSION_SAMPLE,
+    WATER_CROSSING,
+    SESSION_CROSSING,
+];
+
 #[cfg(test)]
 mod tests {
     use arrow::datatypes::DataType;
@@ -100,6 +154,41 @@ mod tests {
         );
     }
 
+    /// A bronze dataset left out of [`BRONZE`] would never be compacted, and would go on
+    /// growing a file per write.
+    #[test]
+    fn every_bronze_dataset_is_listed_as_one() {
+        let bronze: Vec<DatasetInfo> = BRONZE.iter().map(DatasetSpec::info).collect();
+        let defined: Vec<DatasetInfo> = ALL
+            .into_iter()
+            .filter(|dataset| dataset.layer == medallion::Layer::Bronze)
+            .collect();
+
+        assert_eq!(bronze, defined);
+    }
+
+    /// A silver dataset left out of [`SILVER`] would keep whatever a killed rebuild staged
+    /// in it for good.
+    #[test]
+    fn every_silver_dataset_is_listed_as_one() {
+        let silver: Vec<DatasetInfo> = SILVER.iter().map(DatasetSpec::info).collect();
+        let defined: Vec<DatasetInfo> = ALL
+            .into_iter()
+            .filter(|dataset| dataset.layer == medallion::Layer::Silver)
+            .collect();
+
+        assert_eq!(silver, defined);
+    }
+
+    /// A dataset left out of [`DECLARED`] would never be verified, and one declared under
+    /// another's row type would be verified against the wrong columns.
+    #[test]
+    fn every_dataset_declares_its_files() {
+        let declared: Vec<DatasetInfo> = DECLARED.iter().map(|declared| declared.dataset).collect();
+
+        assert_eq!(declared, ALL);
+    }
+
     /// The datasets that declare a partition key, as `(dataset name, key)`.
     fn partition_keys() -> Vec<(&'static str, &'static str)> {
         ALL.iter()
@@ -166,6 +255,8 @@ mod tests {
     #[test]
     fn every_row_type_describes_a_dataset_and_names_its_own_instant_columns() {
         check_rows_of::<RawSampleRow>();
+        check_rows_of::<RejectedSampleRow>();
+        check_rows_of::<ReparsedSampleRow>();
         check_rows_of::<GpsReadingRow>();
         check_rows_of::<AccelReadingRow>();
         check_rows_of::<DeviceSessionRow>();
@@ -90,6 +90,9 @@ impl Row for TrainSegmentRow {
     const DATASET: DatasetSpec<Self::Layer> = TRAIN_SEGMENT;
     const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
     const INSTANTS: &'static [&'static str] = &["departure", "arrival"];
+    /// A leg's identity, as the type's own description gives it.
+    const UNIQUE_TOGETHER: &'static [&'static [&'static str]] =
+        &[&["trip_id", "from_stop_id", "departure"]];
 }
 
 impl Dated for TrainSegmentRow {
@@ -17,7 +17,7 @@ use std::fmt::{self, Display};
 use std::str::FromStr;
 
 use chrono::{DateTime, NaiveDate, Utc};
-use medallion::{DatasetSpec, Dated, Geometry, PartitionValue, PathError, Row, layers};
+use medallion::{DatasetSpec, Dated, Geometry, PartitionValue, PathError, Reference, Row, layers};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -134,6 +134,8 @@ impl Row for SessionRow {
     const DATASET: DatasetSpec<Self::Layer> = SESSION;
     const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
     const INSTANTS: &'static [&'static str] = &["started_at", "ended_at"];
+    /// Named for its device and its start, which no two sessions share.
+    const UNIQUE: &'static [&'static str] = &["session_id"];
 }
 
 impl Dated for SessionRow {
@@ -175,6 +177,9 @@ impl Row for SessionSampleRow {
     const DATASET: DatasetSpec<Self::Layer> = SESSION_SAMPLE;
     const GEOMETRY: Geometry = Geometry::LatLonAndProjected;
     const INSTANTS: &'static [&'static str] = &["t"];
+    const UNIQUE_TOGETHER: &'static [&'static [&'static str]] = &[&["device_id", "t"]];
+    const REFERENCES: &'static [Reference] =
+        &[Reference::to::<SessionRow>("session_id", "session_id")];
 }
 
 impl Dated for SessionSampleRow {
@@ -3,6 +3,12 @@
 //! Every payload lands verbatim in [`RAW_SAMPLE`], and the readings interpreted from it in
 //! one dataset per sensor. Sensors are split rather than sharing one under a `sensor=`
 //! partition because they carry different columns, and a dataset is one schema.
+//!
+//! A payload that could not be interpreted lands in [`REJECTED_SAMPLE`] as well, with why,
+//! so what was lost to a parse error is found without reparsing all of raw. A rejected
+//! payload interpreted later, by a parser since fixed, is recorded in [`REPARSED_SAMPLE`]:
+//! bronze is appended to, never edited, so recovering one is a row of its own rather than
+//! the removal of the rejection.
 
 use medallion::{DatasetSpec, Row, layers};
 use serde::{Deserialize, Serialize};
@@ -14,6 +20,14 @@ use crate::device::DeviceId;
 pub const RAW_SAMPLE: DatasetSpec<layers::Bronze> =
     DatasetSpec::partitioned("raw_sample", "ingested_date");
 
+/// The payloads that could not be interpreted, with the error that stopped them.
+pub const REJECTED_SAMPLE: DatasetSpec<layers::Bronze> =
+    DatasetSpec::partitioned("rejected_sample", "ingested_date");
+
+/// The rejected payloads that have since been interpreted, and when.
+pub const REPARSED_SAMPLE: DatasetSpec<layers::Bronze> =
+    DatasetSpec::partitioned("reparsed_sample", "ingested_date");
+
 /// GPS samples interpreted from the payloads.
 pub const GPS_READING: DatasetSpec<layers::Bronze> =
     DatasetSpec::partitioned("gps_reading", "ingested_date");
@@ -45,6 +59,42 @@ impl Row for RawSampleRow {
     const INSTANTS: &'static [&'static str] = &["received_at"];
 }
 
+/// One payload no version of the protocol could interpret, as [`RawSampleRow`] holds it,
+/// with the parser's reason.
+///
+/// `error` is what the parser said at ingestion; a fixed parser may say otherwise, which is
+/// the point of keeping `json` alongside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedSampleRow {
+    /// The md5 its [`RawSampleRow`] is keyed on.
+    pub md5: String,
+    pub received_at: Option<i64>,
+    pub json: String,
+    pub error: String,
+}
+
+impl Row for RejectedSampleRow {
+    type Layer = layers::Bronze;
+    const DATASET: DatasetSpec<Self::Layer> = REJECTED_SAMPLE;
+    const INSTANTS: &'static [&'static str] = &["received_at"];
+}
+
+/// One rejected payload whose readings have since been interpreted into the reading
+/// datasets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReparsedSampleRow {
+    /// The md5 of the [`RejectedSampleRow`] recovered.
+    pub md5: String,
+    /// When its readings were written, which is the ingestion they are filed under.
+    pub reparsed_at: i64,
+}
+
+impl Row for ReparsedSampleRow {
+    type Layer = layers::Bronze;
+    const DATASET: DatasetSpec<Self::Layer> = REPARSED_SAMPLE;
+    const INSTANTS: &'static [&'static str] = &["reparsed_at"];
+}
+
 /// One GPS sample as the device reported it.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GpsReadingRow {
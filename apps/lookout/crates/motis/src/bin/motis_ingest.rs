@@ -7,13 +7,18 @@
 //! extract cannot be ingested.
 //!
 //! Only the partitions the capture log covers are rewritten, so a rerun over unchanged
-//! bronze leaves the same dataset.
+//! bronze leaves the same dataset. `--from`/`--to` restrict the run to a range of departure
+//! dates, leaving the rest of the dataset as the last run left it.
+//!
+//! Given no range, a run derives only what has changed since the last: the departures the
+//! polls captured since could have seen, or nothing at all if no poll, extract or tuning has.
 
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
 use medallion::MedallionArgs;
-use motis::ingest::ingest;
+use medallion::lineage::Producer;
+use motis::ingest::{ingest, pending};
 use transport::countries::CountryAreas;
 
 #[derive(Parser)]
@@ -32,12 +37,33 @@ async fn main() {
         .init();
 
     let args = Args::parse();
-    let root = args.medallion.root().expect("locate the medallion store");
+    let root = args
+        .medallion
+        .root()
+        .expect("locate the medallion store")
+        .recording(Producer::new(env!("CARGO_BIN_NAME")));
+    let range = args.medallion.range().expect("read the range to derive");
 
     let countries = CountryAreas::newest(&root)
         .await
         .expect("read the country areas of the newest extract");
-    let outcome = ingest(&root, &countries)
+    let range = match range.is_all() {
+        true => match pending(&root)
+            .await
+            .expect("read what the dataset was derived from")
+        {
+            Some(range) => range,
+            None => {
+                tracing::info!(
+                    medallion_root = %root.path().display(),
+                    "nothing read has changed since the train segments were derived"
+                );
+                return;
+            }
+        },
+        false => range,
+    };
+    let outcome = ingest(&root, &countries, range)
         .await
         .expect("derive train segments");
 
@@ -46,6 +72,7 @@ async fn main() {
         deduped = outcome.deduped,
         partitions = outcome.partitions,
         unplaceable = outcome.unplaceable,
+        range = %range,
         medallion_root = %root.path().display(),
         "derived train segments"
     );
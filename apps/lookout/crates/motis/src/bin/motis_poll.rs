@@ -2,6 +2,10 @@
 //! local Motis server for train trips within a buffered bounding box around them, and
 //! writes the returned segments to the bronze capture log, one parquet file per poll.
 //!
+//! With the queue kept as a stream (`LOOKOUT_QUEUE=stream`) it reads as a consumer group of
+//! its own, `motis`, seeing each sample once beside the recorder rather than peeking at the
+//! latest on a list the recorder drains.
+//!
 //! Runs a continuous loop until interrupted; Ctrl-C stops it cleanly, between polls.
 
 use std::time::Duration;
@@ -13,8 +17,9 @@ use tracing_subscriber::EnvFilter;
 use medallion::MedallionArgs;
 use motis::bronze::SegmentLog;
 use motis::client::{DEFAULT_BASE_URL, MotisClient};
-use motis::poll::{PollConfig, PollOutcome, poll_once};
+use motis::poll::{PollConfig, PollOutcome, Source, poll_once};
 use motis::window::PositionWindow;
+use telemetry::QueueKind;
 
 const DEFAULT_POLL_INTERVAL_SECS: u64 = 30;
 const DEFAULT_WINDOW_AGE_MINS: u64 = 30;
@@ -24,6 +29,9 @@ const DEFAULT_ZOOM: f64 = 8.0;
 /// How many of the most-recent queued samples to scan for GPS each tick.
 const SAMPLE_LIMIT: usize = 1000;
 
+/// The consumer group the poller reads the queue as, when it is kept as a stream.
+const GROUP: &str = "motis";
+
 /// Half-width (minutes) of the `map/trips` time window queried around now.
 const QUERY_WINDOW_HALF_MINS: u64 = 5;
 
@@ -80,6 +88,10 @@ async fn main() {
     let mut conn = telemetry::connect(&url)
         .await
         .expect("connect to telemetry redis");
+    let kind = QueueKind::from_env().expect("read how the queue is kept");
+    let mut source = Source::new(kind, GROUP, &mut conn)
+        .await
+        .expect("join the telemetry queue");
 
     tracing::info!(
         motis_url = %args.motis_url,
@@ -88,6 +100,7 @@ async fn main() {
         window_age_mins = args.window_age_mins,
         recent_lookback_mins = args.recent_lookback_mins,
         zoom = args.zoom,
+        queue = %kind,
         "starting motis poll loop (Ctrl-C to stop)"
     );
 
@@ -99,7 +112,7 @@ async fn main() {
                 break;
             }
             _ = ticker.tick() => {
-                match poll_once(Utc::now(), &mut conn, &client, &log, &mut window, &config).await {
+                match poll_once(Utc::now(), &mut conn, &mut source, &client, &log, &mut window, &config).await {
                     Ok(PollOutcome::NoRecentGps { ingested }) => {
                         tracing::info!(ingested, "no recent gps positions; skipping motis query");
                     }
@@ -4,11 +4,15 @@
 //! store it as WKB alongside the same line projected into metres.
 //!
 //! Silver holds one current row per leg, so a run rewrites each `departure_date` partition
-//! it touches: re-running over unchanged bronze produces an identical dataset.
+//! it touches: re-running over unchanged bronze produces an identical dataset. A run over a
+//! range of departure dates reads only the polls that could have seen a leg departing in it,
+//! and rewrites only those dates. [`pending`] is the range a run need cover: the departures
+//! the polls captured since the last run could have seen, or none at all.
 
 use chrono::{DateTime, Utc};
 use geo_types::{LineString, Point};
-use medallion::{Countries, GeoRow, Query, Root};
+use medallion::lineage::{self, LineageError, Pending};
+use medallion::{Countries, DateRange, GeoRow, Query, Root};
 use model::TrainSegmentRow;
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +22,11 @@ const POLYLINE_PRECISION: u32 = 5;
 /// The capture log under its query name.
 const CAPTURED: &str = "captured";
 
+/// How many days apart a leg's departure and a poll that saw it can be. A poll asks for the
+/// legs running within minutes of it, and a leg runs for hours, so a day either side of a
+/// departure date covers every poll that could have seen it depart then.
+const POLLED_WITHIN_DAYS: u64 = 1;
+
 /// One row per scheduled leg, newest capture kept.
 ///
 /// A leg's identity is `(trip_id, from_stop_id, departure)`: `departure` alone is not
@@ -39,7 +48,8 @@ const DEDUPED: &str = "
 pub struct IngestOutcome {
     /// Rows read from the capture log.
     pub read: usize,
-    /// Distinct legs after dedup.
+    /// Distinct legs after dedup, including those the polls read saw departing outside the
+    /// range, which are not written.
     pub deduped: usize,
     /// Partitions rewritten.
     pub partitions: usize,
@@ -56,6 +66,8 @@ pub enum IngestError {
     Polyline(String),
     #[error("writing the dataset: {0}")]
     Write(#[from] medallion::TableError),
+    #[error("reading what the dataset was derived from: {0}")]
+    Lineage(#[from] LineageError),
 }
 
 /// One deduped leg as the query returns it: the columns the silver dataset holds, plus the
@@ -96,7 +108,8 @@ impl From<&Leg> for TrainSegmentRow {
     }
 }
 
-/// Derive the silver dataset from the bronze capture log in the same store.
+/// Derive the legs departing within `range` from the bronze capture log in the same store,
+/// replacing those dates of the silver dataset.
 ///
 /// The country each leg runs in is looked up from where it starts, since that fixes the CRS
 /// of its projected geometry — a property of the leg rather than of the run that ingested it.
@@ -104,12 +117,13 @@ impl From<&Leg> for TrainSegmentRow {
 ///
 /// Dedup and partitioning are one SQL query each against the capture log; the polyline
 /// decoding and projection either query does not express happen per partition in Rust.
-pub async fn ingest(root: &Root, countries: &impl Countries) -> Result<IngestOutcome, IngestError> {
+pub async fn ingest(
+    root: &Root,
+    countries: &impl Countries,
+    range: DateRange,
+) -> Result<IngestOutcome, IngestError> {
     let query = Query::new(root.clone());
-    if !query
-        .register_if_present(model::MOTIS_SEGMENT, CAPTURED)
-        .await?
-    {
+    if !register_captured(&query, range).await? {
         return Ok(IngestOutcome::default());
     }
 
@@ -132,9 +146,14 @@ pub async fn ingest(root: &Root, countries: &impl Countries) -> Result<IngestOut
         ..IngestOutcome::default()
     };
     let mut placed: Vec<GeoRow<TrainSegmentRow, LineString<f64>>> = Vec::new();
-    for leg in &legs {
-        let line = decode_polyline(&leg.polyline)?;
-        match countries.containing(starts_from(&line)) {
+    let lines = legs
+        .iter()
+        .map(|leg| decode_polyline(&leg.polyline))
+        .collect::<Result<Vec<_>, _>>()?;
+    let starts: Vec<Point<f64>> = lines.iter().map(starts_from).collect();
+    let placed_in = countries.containing_all(&starts);
+    for ((leg, line), country) in legs.iter().zip(lines).zip(placed_in) {
+        match country {
             Some(country) => placed.push(GeoRow {
                 row: TrainSegmentRow::from(leg),
                 geometry: line,
@@ -144,13 +163,45 @@ pub async fn ingest(root: &Root, countries: &impl Countries) -> Result<IngestOut
         }
     }
 
-    outcome.partitions = medallion::write_geo_rows(root, &placed)
+    outcome.partitions = medallion::write_geo_rows_within(root, &placed, range)
         .await?
         .partitions
         .written;
     Ok(outcome)
 }
 
+/// The departure dates a run over every date has to derive again, or `None` where no poll
+/// has changed since the last run, nor anything else it reads — see
+/// [`medallion::lineage::pending`].
+///
+/// A poll captured on one date saw the legs departing a day either side of it, so the
+/// departures to derive again are the polls' dates widened by as much.
+pub async fn pending(root: &Root) -> Result<Option<DateRange>, IngestError> {
+    register_captured(&Query::new(root.clone()), DateRange::ALL).await?;
+    Ok(
+        match lineage::pending(root, &[model::TRAIN_SEGMENT.info()]).await? {
+            Pending::Nothing => None,
+            Pending::Dates(polled) => Some(
+                polled
+                    .starting_earlier(POLLED_WITHIN_DAYS)
+                    .ending_later(POLLED_WITHIN_DAYS),
+            ),
+            Pending::Everything => Some(DateRange::ALL),
+        },
+    )
+}
+
+/// Register the polls a run over `range` of departures reads, reporting whether there are
+/// any.
+async fn register_captured(query: &Query, range: DateRange) -> Result<bool, IngestError> {
+    let polled = range
+        .starting_earlier(POLLED_WITHIN_DAYS)
+        .ending_later(POLLED_WITHIN_DAYS);
+    Ok(query
+        .register_if_present_within(model::MOTIS_SEGMENT, CAPTURED, polled)
+        .await?)
+}
+
 /// Where a leg starts, which decides the zone its projected geometry is written in.
 fn starts_from(line: &LineString<f64>) -> Point<f64> {
     line.points()
@@ -197,7 +248,7 @@ mod tests {
     }
 
     fn germany() -> Everywhere {
-        Everywhere(Country::Germany)
+        Everywhere(Country::GERMANY)
     }
 
     fn fixture() -> Vec<TripSegment> {
@@ -213,7 +264,9 @@ mod tests {
                 .await
                 .expect("append poll");
         }
-        ingest(root, &germany()).await.expect("ingest")
+        ingest(root, &germany(), DateRange::ALL)
+            .await
+            .expect("ingest")
     }
 
     /// The derived dataset, registered the way any other reader would register it.
@@ -287,7 +340,9 @@ mod tests {
         )
         .await
         .expect("second poll");
-        ingest(&root, &germany()).await.expect("ingest");
+        ingest(&root, &germany(), DateRange::ALL)
+            .await
+            .expect("ingest");
 
         let kept = derived(&root)
             .await
@@ -367,7 +422,9 @@ mod tests {
             .count("SELECT COUNT(*) AS count FROM derived")
             .await
             .expect("count");
-        let second = ingest(&root, &germany()).await.expect("re-ingest");
+        let second = ingest(&root, &germany(), DateRange::ALL)
+            .await
+            .expect("re-ingest");
 
         assert_eq!(first, second, "the same run, run twice");
         assert_eq!(
@@ -422,7 +479,9 @@ mod tests {
         .await
         .expect("append poll");
 
-        let outcome = ingest(&root, &Nowhere).await.expect("ingest");
+        let outcome = ingest(&root, &Nowhere, DateRange::ALL)
+            .await
+            .expect("ingest");
 
         assert_eq!(outcome.unplaceable, fixture().len());
         assert_eq!(outcome.partitions, 0);
@@ -433,10 +492,48 @@ mod tests {
     async fn an_empty_capture_log_derives_nothing() {
         let tmp = tempfile::tempdir().expect("tempdir");
 
-        let outcome = ingest(&Root::new(tmp.path()), &germany())
+        let outcome = ingest(&Root::new(tmp.path()), &germany(), DateRange::ALL)
             .await
             .expect("ingest");
 
         assert_eq!(outcome, IngestOutcome::default());
     }
+
+    /// The fixture's legs all depart on the 19th, and a poll taken that day is what saw them.
+    fn departure_day() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 7, 19, 9, 0, 0).unwrap()
+    }
+
+    /// A run over a range reads the polls around it and replaces its dates alone: the legs
+    /// departing on a date outside it are neither rewritten nor swept.
+    #[tokio::test]
+    async fn a_range_replaces_only_the_departures_within_it() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = Root::new(tmp.path());
+        ingest_polls(&root, &[departure_day()]).await;
+        let day_after = DateRange::on(departure_day().date_naive() + chrono::Days::new(1));
+
+        let elsewhere = ingest(&root, &germany(), day_after)
+            .await
+            .expect("ingest the day after");
+        let within = ingest(
+            &root,
+            &germany(),
+            DateRange::on(departure_day().date_naive()),
+        )
+        .await
+        .expect("ingest the day");
+
+        assert_eq!(elsewhere.deduped, fixture().len(), "the poll is read");
+        assert_eq!(elsewhere.partitions, 0, "but none of its legs depart then");
+        assert_eq!(within.partitions, 1);
+        assert_eq!(
+            derived(&root)
+                .await
+                .count("SELECT COUNT(*) AS count FROM derived")
+                .await
+                .expect("count"),
+            fixture().len() as i64
+        );
+    }
 }
@@ -2,6 +2,11 @@
 //! the latest telemetry samples, then query Motis for trips in its buffered bbox and
 //! append them to the bronze capture log. The `motis_poll` binary is a thin loop around
 //! [`poll_once`]; tests drive it directly against a real redis and a mock Motis server.
+//!
+//! Where the samples come from depends on how the queue is kept — see [`Source`]. A list is
+//! the recorder's to drain, so a tick peeks at its latest samples, and sees again what the
+//! last tick saw; a stream gives the poller a consumer group of its own, so a tick reads
+//! all that has arrived since the last, and keeps each device's latest fix of it.
 
 use std::collections::HashMap;
 use std::time::Duration;
@@ -9,8 +14,9 @@ use std::time::Duration;
 use chrono::{DateTime, Utc};
 use motis_openapi_progenitor::types::{Mode, TripSegment};
 use redis::aio::MultiplexedConnection;
-use shared::{Message, V0Message, V1Message};
-use telemetry::RawSample;
+use shared::{GpsReading, Message, V0Message, V1Message};
+use telemetry::stream::{Group, Start};
+use telemetry::{QueueError, QueueKind, RawSample};
 
 use crate::bronze::{BronzeError, SegmentLog};
 use crate::client::{MotisClient, MotisError, TimeWindow, TripDetails};
@@ -25,10 +31,82 @@ pub struct PollConfig {
     pub query_window_half: Duration,
     /// Motis zoom level (higher adds subway/tram/bus on top of long-distance rail).
     pub zoom: f64,
-    /// How many of the most-recent queued samples to scan for GPS.
+    /// How many of the most-recent queued samples to scan for GPS — or, reading a stream,
+    /// the most new samples read at a time while catching up with it.
     pub sample_limit: usize,
 }
 
+/// Where a tick reads the recent samples from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The latest on the list, read without taking them.
+    Latest,
+    /// What the poller's consumer group has not yet read of the stream, all of it, reduced to
+    /// each device's latest fix. Each is acknowledged as soon as it is read: a sample missed
+    /// by a poller that dies between reading and querying is only a position, and a later one
+    /// will do as well.
+    Group(Group),
+}
+
+impl Source {
+    /// Where to read the queue kept as `kind`. A stream is read as the group `name`, which,
+    /// made now, starts at the newest entry — the poller wants what is recent, not the
+    /// stream's history.
+    pub async fn new(
+        kind: QueueKind,
+        name: &str,
+        conn: &mut MultiplexedConnection,
+    ) -> Result<Self, QueueError> {
+        match kind {
+            QueueKind::List => Ok(Self::Latest),
+            QueueKind::Stream => {
+                let group = Group::new(name, name, Start::Newest);
+                group.join(conn).await?;
+                Ok(Self::Group(group))
+            }
+        }
+    }
+
+    /// Up to `limit` recent samples off the list, or each device's latest fix off the stream,
+    /// read `limit` at a time.
+    ///
+    /// The group is read until it has caught up, so that its backlog cannot grow between
+    /// ticks however fast samples arrive; at accelerometer rates a tick's worth is mostly not
+    /// positions, and what the window wants of the fixes is where each device is now.
+    async fn recent(
+        &mut self,
+        conn: &mut MultiplexedConnection,
+        limit: usize,
+    ) -> Result<Vec<RawSample>, QueueError> {
+        match self {
+            Self::Latest => telemetry::latest_samples(conn, limit).await,
+            Self::Group(group) => {
+                let mut latest = HashMap::new();
+                loop {
+                    let taken = group.take_new(conn, limit.max(1)).await?;
+                    if taken.is_empty() {
+                        break;
+                    }
+                    group.acknowledge(conn, &taken).await?;
+                    // A malformed entry carries no position, so is as well dropped as skipped.
+                    for sample in taken.iter().filter_map(|taken| taken.sample().ok()) {
+                        let Some(reading) = gps_reading(&sample) else {
+                            continue;
+                        };
+                        match latest.get(&reading.id) {
+                            Some((t, _)) if *t >= reading.t => {}
+                            _ => {
+                                latest.insert(reading.id, (reading.t, sample));
+                            }
+                        }
+                    }
+                }
+                Ok(latest.into_values().map(|(_, sample)| sample).collect())
+            }
+        }
+    }
+}
+
 /// The result of one [`poll_once`] tick.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PollOutcome {
@@ -53,11 +131,12 @@ pub enum PollError {
     Log(#[from] BronzeError),
 }
 
-/// One poll: ingest the latest recent GPS into `window`, then (if any) log the Motis
-/// trips in its buffered bbox over a short window around `now`.
+/// One poll: ingest the latest recent GPS from `source` into `window`, then (if any) log the
+/// Motis trips in its buffered bbox over a short window around `now`.
 pub async fn poll_once(
     now: DateTime<Utc>,
     conn: &mut MultiplexedConnection,
+    source: &mut Source,
     client: &MotisClient,
     log: &SegmentLog,
     window: &mut PositionWindow,
@@ -66,7 +145,7 @@ pub async fn poll_once(
     let now_ms = now.timestamp_millis();
     let cutoff = now_ms - config.recent_lookback.as_millis() as i64;
 
-    let samples = telemetry::latest_samples(conn, config.sample_limit).await?;
+    let samples = source.recent(conn, config.sample_limit).await?;
     let mut ingested = 0;
     for (t, lat, lon) in samples
         .iter()
@@ -145,10 +224,13 @@ async fn resolve_details(
 /// The `(t, lat, lon)` of a sample if it is a GPS fix, else `None` (accel/session or an
 /// unparseable payload are skipped).
 fn sample_gps(raw: &RawSample) -> Option<(i64, f64, f64)> {
+    gps_reading(raw).map(|r| (r.t, r.gps.lat, r.gps.lon))
+}
+
+/// The GPS reading a sample holds, if it is a fix.
+fn gps_reading(raw: &RawSample) -> Option<GpsReading> {
     match raw.parse().ok()? {
-        Message::Version0(V0Message::Gps(r)) | Message::Version1(V1Message::Gps(r)) => {
-            Some((r.t, r.gps.lat, r.gps.lon))
-        }
+        Message::Version0(V0Message::Gps(r)) | Message::Version1(V1Message::Gps(r)) => Some(r),
         _ => None,
     }
 }
@@ -1,7 +1,8 @@
 //! Integration test for the poll core [`motis::poll::poll_once`]: GPS samples on a real
 //! redis (testcontainers) drive a real [`MotisClient`] against a mock Motis server
 //! (wiremock) and land in a real bronze capture log. Exercises the whole tick — recent-GPS
-//! filtering, the buffered-bbox query, and writing the poll's segments — end to end.
+//! filtering, the buffered-bbox query, and writing the poll's segments — end to end. The
+//! same tick reading a stream through its own consumer group is exercised too.
 //!
 //! Requires Docker (redis); the `_docker`-suffixed name is skipped by the no-docker
 //! profile. The Motis server is mocked, so no live Motis is needed.
@@ -15,9 +16,12 @@ use common::{RAIL_MODES, captured_segments, gps, lpush, start_redis, wait_ready}
 use medallion::Root;
 use motis::bronze::SegmentLog;
 use motis::client::MotisClient;
-use motis::poll::{PollConfig, PollOutcome, poll_once};
+use motis::poll::{PollConfig, PollOutcome, Source, poll_once};
 use motis::window::PositionWindow;
+use redis::aio::MultiplexedConnection;
 use shared::{Accel, AccelReading, Message, V1Message};
+use telemetry::stream::{Group, SAMPLE_FIELD, STREAM_KEY, Start};
+use telemetry::{QueueKind, RawSample};
 use uuid::Uuid;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -102,9 +106,17 @@ async fn poll_once_ingests_recent_gps_and_logs_motis_segments_docker() {
         sample_limit: 1000,
     };
 
-    let outcome = poll_once(now, &mut conn, &client, &log, &mut window, &config)
-        .await
-        .expect("poll once");
+    let outcome = poll_once(
+        now,
+        &mut conn,
+        &mut Source::Latest,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("poll once");
 
     // The accel sample and the 20-min-old GPS are excluded; the three recent GPS are
     // ingested and their bbox queried, returning four segments of which only the one rail
@@ -140,3 +152,186 @@ async fn poll_once_ingests_recent_gps_and_logs_motis_segments_docker() {
         "the rail segment's trip resolved its agency and train number"
     );
 }
+
+/// XADD a message the way the server's `StreamSink` does.
+async fn xadd(conn: &mut MultiplexedConnection, message: &Message) {
+    let payload = serde_json::to_string(message).expect("serialize message");
+    let item = serde_json::to_string(&RawSample::new(1_700_000_050_000, payload))
+        .expect("serialize envelope");
+    let _: String = redis::cmd("XADD")
+        .arg(STREAM_KEY)
+        .arg("*")
+        .arg(SAMPLE_FIELD)
+        .arg(item)
+        .query_async(conn)
+        .await
+        .expect("xadd");
+}
+
+#[tokio::test]
+async fn poll_once_reads_each_stream_sample_once_beside_the_recorder_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+
+    // Both groups exist before anything is added, as they would once running: the poller's
+    // from the newest entry, the recorder's from the oldest.
+    let mut source = Source::new(QueueKind::Stream, "motis", &mut conn)
+        .await
+        .expect("join as the poller");
+    let mut recorder = Group::new("recorder", "recorder", Start::Oldest);
+    recorder
+        .recover(&mut conn)
+        .await
+        .expect("join as the recorder");
+
+    let now = Utc::now();
+    let now_ms = now.timestamp_millis();
+    xadd(&mut conn, &accel(1, now_ms)).await;
+    xadd(&mut conn, &gps(2, now_ms - 2 * 60_000, 50.106, 8.662)).await;
+    xadd(&mut conn, &gps(3, now_ms - 60_000, 50.133, 8.741)).await;
+
+    let motis = mock_motis(TRIPS_FIXTURE).await;
+    let store = tempfile::tempdir().expect("temp store");
+    let log = SegmentLog::new(Root::new(store.path()));
+    let client = MotisClient::new(&motis.uri());
+    let mut window = PositionWindow::new(Duration::from_secs(30 * 60));
+    let config = PollConfig {
+        recent_lookback: Duration::from_secs(5 * 60),
+        query_window_half: Duration::from_secs(5 * 60),
+        zoom: 8.0,
+        sample_limit: 1000,
+    };
+
+    let first = poll_once(
+        now,
+        &mut conn,
+        &mut source,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("first poll");
+    assert!(
+        matches!(
+            first,
+            PollOutcome::Queried {
+                ingested: 2,
+                positions: 2,
+                ..
+            }
+        ),
+        "{first:?}"
+    );
+    // Read and acknowledged, so the next tick ingests nothing new and keeps the window.
+    let second = poll_once(
+        now,
+        &mut conn,
+        &mut source,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("second poll");
+    assert!(
+        matches!(
+            second,
+            PollOutcome::Queried {
+                ingested: 0,
+                positions: 2,
+                ..
+            }
+        ),
+        "{second:?}"
+    );
+
+    // The poller's reads took nothing from the recorder's group.
+    let mut archived = Vec::new();
+    while let Some(taken) = recorder
+        .take(&mut conn, Duration::from_millis(200))
+        .await
+        .expect("take as the recorder")
+    {
+        archived.push(taken);
+    }
+    assert_eq!(archived.len(), 3);
+}
+
+/// A backlog larger than one read is caught up with in one tick, and only each device's
+/// latest fix of it reaches the window.
+#[tokio::test]
+async fn poll_once_catches_up_keeping_each_devices_latest_fix_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    let mut source = Source::new(QueueKind::Stream, "motis", &mut conn)
+        .await
+        .expect("join as the poller");
+
+    let now = Utc::now();
+    let now_ms = now.timestamp_millis();
+    for n in 0..10 {
+        xadd(&mut conn, &accel(1, now_ms - 200_000 + n)).await;
+    }
+    xadd(&mut conn, &gps(1, now_ms - 3 * 60_000, 50.106, 8.662)).await;
+    xadd(&mut conn, &gps(1, now_ms - 60_000, 50.110, 8.670)).await;
+    // Out of order: an older fix read after a newer one is not where the device is.
+    xadd(&mut conn, &gps(1, now_ms - 2 * 60_000, 50.120, 8.680)).await;
+    xadd(&mut conn, &gps(2, now_ms - 60_000, 50.133, 8.741)).await;
+
+    let motis = mock_motis(TRIPS_FIXTURE).await;
+    let store = tempfile::tempdir().expect("temp store");
+    let log = SegmentLog::new(Root::new(store.path()));
+    let client = MotisClient::new(&motis.uri());
+    let mut window = PositionWindow::new(Duration::from_secs(30 * 60));
+    let config = PollConfig {
+        recent_lookback: Duration::from_secs(5 * 60),
+        query_window_half: Duration::from_secs(5 * 60),
+        zoom: 8.0,
+        sample_limit: 3,
+    };
+
+    let outcome = poll_once(
+        now,
+        &mut conn,
+        &mut source,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("poll");
+    assert!(
+        matches!(
+            outcome,
+            PollOutcome::Queried {
+                ingested: 2,
+                positions: 2,
+                ..
+            }
+        ),
+        "{outcome:?}"
+    );
+    let bbox = window.bbox().expect("positions");
+    assert_eq!((bbox.min().y, bbox.max().y), (50.110, 50.133));
+
+    // Nothing is left behind for the next tick.
+    let second = poll_once(
+        now,
+        &mut conn,
+        &mut source,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("second poll");
+    assert!(
+        matches!(second, PollOutcome::Queried { ingested: 0, .. }),
+        "{second:?}"
+    );
+}
@@ -17,7 +17,7 @@ use common::{RAIL_MODES, captured_segments, gps, lpush, start_redis, wait_ready}
 use medallion::Root;
 use motis::bronze::SegmentLog;
 use motis::client::MotisClient;
-use motis::poll::{PollConfig, PollOutcome, poll_once};
+use motis::poll::{PollConfig, PollOutcome, Source, poll_once};
 use motis::window::PositionWindow;
 
 #[tokio::test]
@@ -43,9 +43,17 @@ async fn poll_once_captures_rail_from_local_motis_end_to_end() {
         sample_limit: 1000,
     };
 
-    let outcome = poll_once(now, &mut conn, &client, &log, &mut window, &config)
-        .await
-        .expect("poll once against local motis");
+    let outcome = poll_once(
+        now,
+        &mut conn,
+        &mut Source::Latest,
+        &client,
+        &log,
+        &mut window,
+        &config,
+    )
+    .await
+    .expect("poll once against local motis");
 
     let PollOutcome::Queried { segments, .. } = outcome else {
         panic!("expected a Motis query, got {outcome:?}");
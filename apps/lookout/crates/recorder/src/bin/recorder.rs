@@ -2,33 +2,98 @@
 //! `LOOKOUT_REDIS_URL` and writes the samples it reads — the verbatim payloads plus the
 //! readings interpreted from them.
 //!
-//! Two modes (default `view-latest`, to avoid accidental data loss while iterating):
+//! Five modes (default `view-latest`, to avoid accidental data loss while iterating):
 //!   - `view-latest`: non-destructively read the latest N samples and archive them.
-//!   - `drain`: `BRPOP` every sample off the queue (destructive) until empty or Ctrl-C.
+//!   - `drain`: take every sample off the queue (destructive) until empty or Ctrl-C.
+//!   - `follow`: take samples as they arrive (destructive) until SIGTERM or Ctrl-C.
+//!   - `reparse`: interpret the rejected payloads again, once the parser is fixed; reads
+//!     and writes bronze alone, so needs no redis.
+//!   - `drain-landing`: archive what a server with no redis landed as files in the store
+//!     (destructive), as `drain` archives the queue; needs no redis either.
 //!
-//! A drain writes in batches of at most [`BATCH_SIZE`] rather than once at the end. `BRPOP`
-//! has already removed what it holds, so a batch that fails to write is put back on the
-//! queue — and if that requeue also fails the samples are gone. Writing in bounded batches
-//! caps how many samples a single failure can put at risk, however long the drain runs.
+//! A drain takes samples through a [`telemetry::Reader`], which keeps hold of each in redis
+//! — on its own processing list, or pending against its consumer group when the queue is a
+//! stream (`LOOKOUT_QUEUE=stream`) — and acknowledges a batch, letting go of it, only once
+//! the batch's bronze files are written. A batch that fails to write is left where it is and
+//! the drain stops; so is whatever a drain killed part way through held. The next drain under
+//! the same `--consumer` name starts by recovering all of it, so nothing taken is lost,
+//! though a batch written but not yet acknowledged when a drain dies is written again.
+//! Writing in batches of at most [`BATCH_SIZE`] bounds how much that repeats.
+//!
+//! A follow is a drain that does not stop when the queue runs dry, meant to run as a service
+//! so the queue never holds the only copy of a sample for long. It writes a batch once it is
+//! full or once its oldest sample has waited `--batch-secs`, whichever is first — see
+//! [`recorder::follow::Batch`] — and with `--sessionise` derives the silver sessions every
+//! `--sessionise-secs`, as `sessionise` would with the same tuning over the dates written
+//! since the last time. A redis that stops answering is reconnected to with a growing wait
+//! rather than ending the follow, and so is a batch that cannot be written: it is left
+//! unacknowledged, as a drain leaves it, and taken again by the recovery reconnecting makes.
+//!
+//! Both also archive the frames the server could not parse, which it keeps on a dead-letter
+//! list of their own (see [`telemetry::DEAD_LETTER_KEY`]): a drain before the queue, a
+//! follow whenever it connects and after each batch. They land in `raw_sample` like any
+//! payload, and in `rejected_sample` with the parse error, where `reparse` finds them once
+//! the parser reads them — see [`recorder::bronze`]. An item on either list that is not even
+//! the server's envelope is archived the same way, verbatim and with its receipt unknown,
+//! and acknowledged with its batch, so no recovery takes it again.
+//!
+//! A server run with no redis to reach lands samples under the store's `landing/telemetry`
+//! instead, a file a minute (see [`telemetry::landing`]). `drain-landing` takes those files
+//! as a drain takes the queue: each finished one is moved aside under `--consumer`, written
+//! as one ingestion, and deleted once written, so one a drain dies holding is taken again
+//! by the next under the same name. Whatever in them the parser cannot read is rejected as
+//! a dead letter is.
 
-use std::time::Duration;
+use std::fmt::Display;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::{Pin, pin};
+use std::time::{Duration, Instant};
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
-use medallion::MedallionArgs;
-use recorder::bronze::{Archive, Payload, Written};
-use telemetry::RawSample;
+use medallion::{DateRange, MedallionArgs, Root};
+use recorder::bronze::{self, Archive, Payload, Written};
+use recorder::follow::{Backoff, Batch, Derivation};
+use recorder::sessions::{Gap, Lead};
+use recorder::silver;
+use redis::aio::MultiplexedConnection;
+use telemetry::landing::{Landing, LandingConsumer};
+use telemetry::{Consumer, QueueError, QueueKind, RawSample, Reader, Taken};
+use tokio::signal::unix::{SignalKind, signal};
 
-/// How long to block on `BRPOP` before treating the queue as drained.
+/// How long to block on taking a sample before treating the queue as drained — and, when
+/// following, the longest a take blocks before the batch is looked at again.
 const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// How long to block on taking a dead letter before treating the list as archived. Short,
+/// since a follow archives dead letters between batches, and the server has added them all
+/// before they are looked for.
+const DEAD_LETTER_WAIT: Duration = Duration::from_millis(100);
+
 /// Default number of most-recent samples read in `view-latest`.
 const DEFAULT_LIMIT: usize = 1000;
 
-/// Most samples held before being written. This bounds what a failed write followed by a
-/// failed requeue can lose.
+/// Most samples held before being written. This bounds what a drain that dies between
+/// writing and acknowledging a batch archives twice.
 const BATCH_SIZE: usize = 100;
 
+/// The reader a drain reads as when no `--consumer` is given: the list consumer, or the
+/// stream's consumer group, of that name.
+const DEFAULT_CONSUMER: &str = "recorder";
+
+/// Default longest a sample waits in a follow's batch before the batch is written.
+const DEFAULT_BATCH_SECS: u64 = 60;
+
+/// Default shortest time between a follow's derivations of the sessions. Each reads every
+/// sample its sessions can be made of, so it runs far less often than a batch is written.
+const DEFAULT_SESSIONISE_SECS: u64 = 15 * 60;
+
+/// The first and the longest wait before a follow tries again a redis that stopped answering,
+/// or a write that failed.
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const LONGEST_RETRY: Duration = Duration::from_secs(60);
+
 #[derive(Parser)]
 #[command(about = "Read the lookout telemetry queue into the bronze telemetry datasets")]
 struct Args {
@@ -47,7 +112,63 @@ enum Command {
         limit: usize,
     },
     /// Remove every sample from the queue (destructive) and archive them.
-    Drain,
+    Drain {
+        /// Who to take samples as: the list consumer, or the stream group, of this name. A
+        /// drain recovers what an earlier one under the same name left unacknowledged; drains
+        /// running at once on a list need their own.
+        #[arg(long, default_value = DEFAULT_CONSUMER)]
+        consumer: String,
+    },
+    /// Archive samples as they arrive until stopped (destructive; runs as a service).
+    Follow(FollowArgs),
+    /// Interpret the rejected payloads no reparse has recovered with the current parser, and
+    /// write the readings of those it now reads.
+    Reparse,
+    /// Archive the samples a server with no redis landed as files, deleting them once written
+    /// (destructive).
+    DrainLanding {
+        /// Who to take the files as, as for `drain`.
+        #[arg(long, default_value = DEFAULT_CONSUMER)]
+        consumer: String,
+        /// The directory the server landed samples in, when it is not the store's own — as
+        /// for a store kept in an object store, which the server cannot append to.
+        #[arg(long)]
+        landing: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args)]
+struct FollowArgs {
+    /// Who to take samples as, as for `drain`.
+    #[arg(long, default_value = DEFAULT_CONSUMER)]
+    consumer: String,
+    /// Most samples in a batch: a batch this full is written at once.
+    #[arg(long, default_value_t = BATCH_SIZE)]
+    batch_size: usize,
+    /// Longest a sample waits in a batch, in seconds, before the batch is written.
+    #[arg(long, default_value_t = DEFAULT_BATCH_SECS)]
+    batch_secs: u64,
+    /// Derive the silver sessions over the dates written, every `--sessionise-secs`.
+    #[arg(long)]
+    sessionise: bool,
+    /// Shortest time between derivations of the sessions, in seconds.
+    #[arg(long, default_value_t = DEFAULT_SESSIONISE_SECS)]
+    sessionise_secs: u64,
+    /// The `sessionise --gap-mins` to derive the sessions with.
+    #[arg(long, default_value_t = Gap::default().as_seconds() / 60)]
+    gap_mins: u32,
+    /// The `sessionise --lead-secs` to derive the sessions with.
+    #[arg(long, default_value_t = Lead::default().as_seconds())]
+    lead_secs: u32,
+}
+
+/// What a follow deriving the sessions derives them with, `range` bounding the dates it
+/// derives.
+struct Sessionising {
+    root: Root,
+    gap: Gap,
+    lead: Lead,
+    range: DateRange,
 }
 
 #[tokio::main]
@@ -72,16 +193,59 @@ async fn main() {
         .expect("install rustls crypto provider");
 
     let archive = Archive::new(root.clone());
-
-    let url = std::env::var("LOOKOUT_REDIS_URL")
-        .expect("LOOKOUT_REDIS_URL must be set — run via `just bronze-record`");
-    let mut conn = telemetry::connect(&url)
-        .await
-        .expect("connect to telemetry redis");
-
     let written = match &command {
-        Command::ViewLatest { limit } => view_latest(&archive, &mut conn, *limit).await,
-        Command::Drain => drain(&archive, &mut conn).await,
+        Command::ViewLatest { limit } => {
+            view_latest(&archive, &mut connect(&redis_url()).await, *limit).await
+        }
+        Command::Drain { consumer } => {
+            // Checked before connecting, as the server checks it.
+            let kind = queue_kind();
+            let mut conn = connect(&redis_url()).await;
+            let rejected =
+                dead_letters(&archive, &mut conn, &Consumer::dead_letters(consumer)).await;
+            let mut reader = Reader::new(kind, consumer);
+            rejected + drain(&archive, &mut conn, &mut reader).await
+        }
+        Command::Follow(follow_args) => {
+            let sessionising = match follow_args.sessionise {
+                false => None,
+                true => {
+                    let gap = Gap::new(chrono::Duration::minutes(i64::from(follow_args.gap_mins)));
+                    let lead =
+                        Lead::new(chrono::Duration::seconds(i64::from(follow_args.lead_secs)));
+                    Some(Sessionising {
+                        root: root.clone().recording(silver::producer(gap, lead)),
+                        gap,
+                        lead,
+                        range: args.medallion.range().expect("read the range to derive"),
+                    })
+                }
+            };
+            let reader = Reader::new(queue_kind(), &follow_args.consumer);
+            follow(
+                &archive,
+                &redis_url(),
+                reader,
+                follow_args,
+                sessionising.as_ref(),
+            )
+            .await
+        }
+        Command::Reparse => {
+            reparse(&archive, &root).await;
+            return;
+        }
+        Command::DrainLanding { consumer, landing } => {
+            let landing = match landing {
+                Some(dir) => Landing::new(dir),
+                None => Landing::in_store(root.path()),
+            };
+            tracing::info!(
+                dir = %landing.dir().display(),
+                "draining landed telemetry (destructive)"
+            );
+            drain_landing(&archive, &landing.consumer(consumer)).await
+        }
     };
 
     tracing::info!(
@@ -95,12 +259,26 @@ async fn main() {
     );
 }
 
+/// How the queue is kept, for a mode that reads it.
+fn queue_kind() -> QueueKind {
+    QueueKind::from_env().expect("read how the queue is kept")
+}
+
+/// Where the telemetry redis is, for a mode that reads it.
+fn redis_url() -> String {
+    std::env::var("LOOKOUT_REDIS_URL")
+        .expect("LOOKOUT_REDIS_URL must be set — run via `just bronze-record`")
+}
+
+/// Connect to the telemetry redis, for a mode that has no use for carrying on without it.
+async fn connect(url: &str) -> MultiplexedConnection {
+    telemetry::connect(url)
+        .await
+        .expect("connect to telemetry redis")
+}
+
 /// Non-destructively archive the latest `limit` samples, in batches.
-async fn view_latest(
-    archive: &Archive,
-    conn: &mut redis::aio::MultiplexedConnection,
-    limit: usize,
-) -> Written {
+async fn view_latest(archive: &Archive, conn: &mut MultiplexedConnection, limit: usize) -> Written {
     tracing::info!(limit, "reading latest samples (non-destructive)");
     let samples = telemetry::latest_samples(conn, limit)
         .await
@@ -118,13 +296,35 @@ async fn view_latest(
     total
 }
 
-/// Destructively `BRPOP` samples until the queue drains empty or Ctrl-C, writing each
-/// batch as it fills. A batch that fails to write is put back on the queue and the drain
-/// stops, so the failure can't repeat across the rest of the queue.
-async fn drain(archive: &Archive, conn: &mut redis::aio::MultiplexedConnection) -> Written {
-    tracing::info!("draining telemetry queue (destructive; Ctrl-C to stop)");
+/// Destructively take samples until the queue drains empty or Ctrl-C, writing each batch
+/// as it fills and acknowledging it once written. Whatever an earlier drain as `reader`
+/// left unacknowledged is recovered first. A batch that fails to write, or to be
+/// acknowledged, stops the drain, so the failure can't repeat across the rest of the queue;
+/// its samples stay unacknowledged for the next drain to recover.
+async fn drain(
+    archive: &Archive,
+    conn: &mut MultiplexedConnection,
+    reader: &mut Reader,
+) -> Written {
     let mut total = Written::default();
-    let mut batch: Vec<RawSample> = Vec::with_capacity(BATCH_SIZE);
+    match reader.recover(conn).await {
+        Ok(0) => {}
+        Ok(recovered) => tracing::info!(
+            recovered,
+            reader = %reader,
+            "recovered unacknowledged samples"
+        ),
+        Err(err) => {
+            tracing::error!(%err, "failed to recover unacknowledged samples; not draining");
+            return total;
+        }
+    }
+
+    tracing::info!(
+        reader = %reader,
+        "draining telemetry queue (destructive; Ctrl-C to stop)"
+    );
+    let mut batch: Vec<Held> = Vec::with_capacity(BATCH_SIZE);
 
     loop {
         let stop = tokio::select! {
@@ -132,9 +332,9 @@ async fn drain(archive: &Archive, conn: &mut redis::aio::MultiplexedConnection)
                 tracing::info!("interrupted; stopping");
                 true
             }
-            result = telemetry::brpop_sample(conn, IDLE_TIMEOUT) => match result {
-                Ok(Some(raw)) => {
-                    batch.push(raw);
+            result = reader.take(conn, IDLE_TIMEOUT) => match result {
+                Ok(Some(item)) => {
+                    batch.push(read(item, &*reader));
                     false
                 }
                 Ok(None) => {
@@ -150,48 +350,507 @@ async fn drain(archive: &Archive, conn: &mut redis::aio::MultiplexedConnection)
 
         if stop || batch.len() >= BATCH_SIZE {
             if !batch.is_empty() {
-                match write(archive, &batch).await {
+                match write_held(archive, Utc::now(), &batch).await {
                     Some(written) => total = total + written,
-                    None => {
-                        requeue(conn, &batch).await;
-                        break;
-                    }
+                    None => break,
                 }
-                batch.clear();
+                let taken: Vec<Taken> = batch.drain(..).map(|(item, _)| item).collect();
+                if let Err(err) = reader.acknowledge(conn, &taken).await {
+                    tracing::error!(
+                        %err,
+                        count = taken.len(),
+                        "failed to acknowledge written batch; it will be written again"
+                    );
+                    break;
+                }
+                trim(conn, reader).await;
             }
             if stop {
                 break;
             }
         }
     }
+    report_lost(reader);
     total
 }
 
-/// Write one batch, reporting what landed, or `None` if it could not be written.
-async fn write(archive: &Archive, samples: &[RawSample]) -> Option<Written> {
-    let payloads: Vec<Payload> = samples.iter().map(Payload::from).collect();
-    match archive.write(Utc::now(), &payloads).await {
-        Ok(written) => Some(written),
+/// Archive what the dead-letter list holds as `consumer`, a batch at a time, without waiting
+/// for more than [`DEAD_LETTER_WAIT`]: what an earlier run left unacknowledged first, as a
+/// drain recovers the queue. A failure is reported and ends the pass with what failed left
+/// unacknowledged, for the next pass to recover; the samples are the caller's to carry on
+/// with either way.
+async fn dead_letters(
+    archive: &Archive,
+    conn: &mut MultiplexedConnection,
+    consumer: &Consumer,
+) -> Written {
+    let mut total = Written::default();
+    if let Err(err) = consumer.recover(conn).await {
+        tracing::error!(%err, "failed to recover unacknowledged dead letters");
+        return total;
+    }
+    let mut more = true;
+    while more {
+        let mut batch: Vec<Held> = Vec::with_capacity(BATCH_SIZE);
+        while more && batch.len() < BATCH_SIZE {
+            match consumer.take(conn, DEAD_LETTER_WAIT).await {
+                Ok(Some(item)) => batch.push(read(item, consumer.processing_key())),
+                Ok(None) => more = false,
+                Err(err) => {
+                    tracing::error!(%err, "error taking dead letters");
+                    more = false;
+                }
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+        let Some(written) = write_held(archive, Utc::now(), &batch).await else {
+            break;
+        };
+        tracing::info!(
+            raw = written.raw,
+            unparseable = written.unparseable,
+            "archived dead letters"
+        );
+        total = total + written;
+        let taken: Vec<Taken> = batch.into_iter().map(|(item, _)| item).collect();
+        if let Err(err) = consumer.acknowledge(conn, &taken).await {
+            tracing::error!(
+                %err,
+                count = taken.len(),
+                "failed to acknowledge archived dead letters; they will be archived again"
+            );
+            break;
+        }
+    }
+    total
+}
+
+/// Archive every finished file landed in the store as `consumer`, oldest first, deleting each
+/// once it is written: whatever an earlier drain under the same name took and did not delete
+/// first. Each file is one ingestion. A file that fails to be read, written or deleted stops
+/// the drain, and stays taken for the next to write again.
+async fn drain_landing(archive: &Archive, consumer: &LandingConsumer) -> Written {
+    let mut total = Written::default();
+    match consumer.recover() {
+        Ok(0) => {}
+        Ok(recovered) => tracing::info!(
+            recovered,
+            dir = %consumer.processing_dir().display(),
+            "recovered unacknowledged landed files"
+        ),
         Err(err) => {
-            tracing::error!(%err, count = samples.len(), "failed to write batch");
-            None
+            tracing::error!(%err, "failed to recover unacknowledged landed files; not draining");
+            return total;
+        }
+    }
+
+    loop {
+        let segment = match consumer.take(Utc::now()) {
+            Ok(Some(segment)) => segment,
+            Ok(None) => {
+                tracing::info!("landing drained; stopping");
+                break;
+            }
+            Err(err) => {
+                tracing::error!(%err, "error taking landed telemetry; stopping");
+                break;
+            }
+        };
+        // A torn line is a payload whose receipt is unknown, kept as any unreadable one is.
+        let torn = segment.torn().iter().map(|json| Payload {
+            received_at: None,
+            json,
+        });
+        let payloads: Vec<Payload> = segment
+            .samples()
+            .iter()
+            .map(Payload::from)
+            .chain(torn)
+            .collect();
+        let Some(written) = write_payloads(archive, Utc::now(), &payloads).await else {
+            break;
+        };
+        total = total + written;
+        if let Err(err) = consumer.acknowledge(segment) {
+            tracing::error!(
+                %err,
+                "failed to delete written landed file; it will be written again"
+            );
+            break;
         }
     }
+    total
+}
+
+/// Interpret every rejected payload no reparse has yet recovered with the parser as it is
+/// now, and write the readings of those it reads. Rejected payloads are the exception, so
+/// they are read whole.
+async fn reparse(archive: &Archive, root: &Root) {
+    let rejected = bronze::unrecovered(root)
+        .await
+        .expect("read the rejected payloads");
+    tracing::info!(rejected = rejected.len(), "reparsing rejected payloads");
+    let reparsed = archive
+        .reparse(Utc::now(), &rejected)
+        .await
+        .expect("write the reparsed readings");
+    tracing::info!(
+        reparsed = reparsed.reparsed,
+        gps = reparsed.gps,
+        accel = reparsed.accel,
+        devices = reparsed.devices,
+        still_rejected = reparsed.still_rejected,
+        medallion_root = %root.path().display(),
+        "reparsed rejected payloads"
+    );
 }
 
-/// Put a batch back on the queue after a failed write, so a drain that could not be
-/// archived loses nothing.
-async fn requeue(conn: &mut redis::aio::MultiplexedConnection, samples: &[RawSample]) {
-    let mut requeued = 0;
-    for sample in samples {
-        match telemetry::requeue_sample(conn, sample).await {
-            Ok(()) => requeued += 1,
-            Err(err) => tracing::error!(%err, "failed to requeue sample — sample lost"),
+/// Take samples as they arrive until SIGTERM or Ctrl-C, writing each batch once it falls due
+/// and acknowledging it once written, and deriving the sessions with `sessionising` every
+/// `--sessionise-secs` over the dates written since — see [`Derivation`].
+///
+/// When redis stops answering, the batch in hand is written anyway — writing needs no redis —
+/// and acknowledged once a new connection is made, before what the lost one left
+/// unacknowledged is recovered; recovering first would take it to be written again. A batch
+/// that cannot be written is let go of unacknowledged, and the follow waits, longer after
+/// each failure in a row, before reconnecting to take it again by recovering it. On being
+/// stopped, the batch in hand is written, and the sessions derived over what is still to be,
+/// before returning. A take cut short by the stop may already have taken its sample in redis,
+/// where the next run recovers it.
+async fn follow(
+    archive: &Archive,
+    url: &str,
+    mut reader: Reader,
+    args: &FollowArgs,
+    sessionising: Option<&Sessionising>,
+) -> Written {
+    let dead = Consumer::dead_letters(&args.consumer);
+    let mut batch = Batch::new(args.batch_size, Duration::from_secs(args.batch_secs));
+    let mut backoff = Backoff::new(FIRST_RETRY, LONGEST_RETRY);
+    let mut writes = Backoff::new(FIRST_RETRY, LONGEST_RETRY);
+    let mut derivation = Derivation::new(Duration::from_secs(args.sessionise_secs));
+    // Batches written while redis was away, which it has still to be told of.
+    let mut unacknowledged: Vec<Taken> = Vec::new();
+    let mut stop = pin!(stopped());
+    let mut total = Written::default();
+
+    tracing::info!(
+        reader = %reader,
+        batch_size = args.batch_size,
+        batch_secs = args.batch_secs,
+        sessionise = args.sessionise,
+        sessionise_secs = args.sessionise_secs,
+        "following telemetry queue (destructive; SIGTERM or Ctrl-C to stop)"
+    );
+    'connection: loop {
+        let Some(mut conn) = reconnect(
+            url,
+            &mut reader,
+            &mut unacknowledged,
+            &mut backoff,
+            stop.as_mut(),
+        )
+        .await
+        else {
+            break;
+        };
+        total = total + dead_letters(archive, &mut conn, &dead).await;
+        loop {
+            if batch.is_due(Instant::now()) {
+                match flush(
+                    archive,
+                    &mut conn,
+                    &reader,
+                    &mut batch,
+                    &mut unacknowledged,
+                    &mut derivation,
+                )
+                .await
+                {
+                    Some(written) => {
+                        writes.succeeded();
+                        total = total + written;
+                    }
+                    None => {
+                        if !pause(writes.failed(), "writing the batch", stop.as_mut()).await {
+                            break 'connection;
+                        }
+                        continue 'connection;
+                    }
+                }
+                total = total + dead_letters(archive, &mut conn, &dead).await;
+            }
+            if let Some(sessionising) = sessionising
+                && derivation.is_due(Instant::now())
+            {
+                sessionise(sessionising, &mut derivation).await;
+            }
+            let wait = batch.wait(Instant::now(), IDLE_TIMEOUT);
+            tokio::select! {
+                _ = &mut stop => {
+                    tracing::info!("stopped; writing the batch in hand");
+                    if let Some(written) = flush(
+                        archive, &mut conn, &reader, &mut batch, &mut unacknowledged, &mut derivation,
+                    ).await {
+                        total = total + written;
+                    }
+                    if let Some(sessionising) = sessionising {
+                        sessionise(sessionising, &mut derivation).await;
+                    }
+                    break 'connection;
+                }
+                result = reader.take(&mut conn, wait) => match result {
+                    Ok(taken) => {
+                        backoff.succeeded();
+                        if let Some(item) = taken {
+                            batch.push(Instant::now(), read(item, &reader));
+                        }
+                    }
+                    Err(err) => {
+                        tracing::error!(%err, held = batch.len(), "lost the telemetry redis");
+                        // A batch that cannot be written either is recovered on reconnecting.
+                        if let Some(written) = flush(
+                            archive, &mut conn, &reader, &mut batch, &mut unacknowledged, &mut derivation,
+                        ).await {
+                            total = total + written;
+                        }
+                        if !pause(backoff.failed(), "the telemetry redis", stop.as_mut()).await {
+                            break 'connection;
+                        }
+                        continue 'connection;
+                    }
+                },
+            }
         }
     }
+    if !unacknowledged.is_empty() {
+        tracing::warn!(
+            count = unacknowledged.len(),
+            "stopping with written samples unacknowledged; the next run will write them again"
+        );
+    }
+    report_lost(&reader);
+    total
+}
+
+/// Resolves once the process is asked to stop: by Ctrl-C, or by the SIGTERM a service
+/// manager sends.
+async fn stopped() {
+    let mut terminate = signal(SignalKind::terminate()).expect("listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+/// Wait `wait` before `retrying` something that failed, or until `stop`: `false` if stopped
+/// first.
+async fn pause(wait: Duration, retrying: &str, stop: Pin<&mut impl Future<Output = ()>>) -> bool {
+    tracing::info!(wait_secs = wait.as_secs_f64(), retrying, "waiting to retry");
+    tokio::select! {
+        _ = stop => false,
+        _ = tokio::time::sleep(wait) => true,
+    }
+}
+
+/// Connect to the telemetry redis, acknowledge what was written while it was away, and
+/// recover what an earlier connection left unacknowledged — retrying, after a longer
+/// wait each time, until all three succeed. `None` if stopped first.
+async fn reconnect(
+    url: &str,
+    reader: &mut Reader,
+    unacknowledged: &mut Vec<Taken>,
+    backoff: &mut Backoff,
+    mut stop: Pin<&mut impl Future<Output = ()>>,
+) -> Option<MultiplexedConnection> {
+    loop {
+        match resume(url, reader, unacknowledged).await {
+            Ok(conn) => return Some(conn),
+            Err(err) => {
+                tracing::error!(%err, "failed to reach the telemetry redis");
+                if !pause(backoff.failed(), "the telemetry redis", stop.as_mut()).await {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// One attempt at what [`reconnect`] retries.
+async fn resume(
+    url: &str,
+    reader: &mut Reader,
+    unacknowledged: &mut Vec<Taken>,
+) -> Result<MultiplexedConnection, QueueError> {
+    let mut conn = telemetry::connect(url).await?;
+    reader.acknowledge(&mut conn, unacknowledged).await?;
+    unacknowledged.clear();
+    trim(&mut conn, reader).await;
+    let recovered = reader.recover(&mut conn).await?;
+    if recovered > 0 {
+        tracing::info!(
+            recovered,
+            reader = %reader,
+            "recovered unacknowledged samples"
+        );
+    }
+    Ok(conn)
+}
+
+/// Write what `batch` holds, acknowledge it, and note its ingestion date in `derivation`.
+/// `None` if the batch could not be written, which lets go of it with its samples left
+/// unacknowledged, for a recovery to take again. A batch written but not acknowledged, redis
+/// having gone, is added to `unacknowledged` for when it is back.
+async fn flush(
+    archive: &Archive,
+    conn: &mut MultiplexedConnection,
+    reader: &Reader,
+    batch: &mut Batch<Held>,
+    unacknowledged: &mut Vec<Taken>,
+    derivation: &mut Derivation,
+) -> Option<Written> {
+    if batch.is_empty() {
+        return Some(Written::default());
+    }
+    let held = batch.take();
+    let ingested_at = Utc::now();
+    let written = write_held(archive, ingested_at, &held).await?;
+    derivation.wrote(ingested_at.date_naive());
     tracing::info!(
-        requeued,
-        of = samples.len(),
-        "requeued batch after failed write"
+        raw = written.raw,
+        gps = written.gps,
+        accel = written.accel,
+        "wrote batch"
     );
+    let taken: Vec<Taken> = held.into_iter().map(|(item, _)| item).collect();
+    if let Err(err) = reader.acknowledge(conn, &taken).await {
+        tracing::warn!(
+            %err,
+            count = taken.len(),
+            "failed to acknowledge written batch; will once redis answers"
+        );
+        unacknowledged.extend(taken);
+    } else {
+        trim(conn, reader).await;
+    }
+    Some(written)
+}
+
+/// Let go of what `reader` has archived and acknowledged, where the queue keeps it until told
+/// to. A failure is reported and the next batch's trim tries again: nothing is lost by
+/// keeping an entry longer.
+async fn trim(conn: &mut MultiplexedConnection, reader: &Reader) {
+    match reader.trim(conn).await {
+        Ok(0) => {}
+        Ok(trimmed) => tracing::debug!(trimmed, reader = %reader, "trimmed archived entries"),
+        Err(err) => tracing::warn!(%err, reader = %reader, "failed to trim archived entries"),
+    }
+}
+
+/// Warn of the items `reader` found gone while it held them, once it stops.
+fn report_lost(reader: &Reader) {
+    if reader.lost() > 0 {
+        tracing::warn!(
+            lost = reader.lost(),
+            reader = %reader,
+            "items went missing from the queue before they were archived"
+        );
+    }
+}
+
+/// Derive the sessions reaching into the ingestion dates written since `derivation` last
+/// derived them, those in the follow's range. A failure is reported and the follow carries
+/// on: the samples are in bronze either way, and the dates are left for the next derivation.
+///
+/// A sample is ingested on or after the date it was recorded, so the sessions a batch can
+/// change reach into the dates it was written on, unless a sample arrived a day or more late;
+/// a `sessionise` over the dates it was recorded settles that.
+async fn sessionise(sessionising: &Sessionising, derivation: &mut Derivation) {
+    let Some(written) = derivation.take(Instant::now()) else {
+        return;
+    };
+    let Some(range) = written.intersection(sessionising.range) else {
+        tracing::debug!(%written, "nothing written in range; not deriving sessions");
+        return;
+    };
+    let derived = silver::sessionise(
+        &sessionising.root,
+        sessionising.gap,
+        sessionising.lead,
+        range,
+    )
+    .await;
+    match derived {
+        Ok(Some(outcome)) => tracing::info!(
+            sessions = outcome.sessions,
+            samples = outcome.samples,
+            unplaceable = outcome.unplaceable,
+            "derived sessions"
+        ),
+        Ok(None) => tracing::info!("nothing read has changed since the sessions were derived"),
+        Err(err) => {
+            tracing::error!(%err, %range, "failed to derive sessions");
+            derivation.failed(written);
+        }
+    }
+}
+
+/// An item taken off a queue, with the sample it holds if it holds one.
+type Held = (Taken, Option<RawSample>);
+
+/// Read the sample `item` holds, warning of one that holds none, taken by `from`: it is
+/// archived all the same, as [`write_held`] says, rather than left unacknowledged for every
+/// recovery to take again.
+fn read(item: Taken, from: impl Display) -> Held {
+    match item.sample() {
+        Ok(sample) => (item, Some(sample)),
+        Err(err) => {
+            tracing::warn!(%err, from = %from, "malformed queue item; archiving it verbatim");
+            (item, None)
+        }
+    }
+}
+
+/// Write one batch, reporting what landed, or `None` if it could not be written.
+async fn write(archive: &Archive, samples: &[RawSample]) -> Option<Written> {
+    let payloads: Vec<Payload> = samples.iter().map(Payload::from).collect();
+    write_payloads(archive, Utc::now(), &payloads).await
+}
+
+/// [`write`], for items taken off a queue: each as the sample it holds, or, for one that
+/// holds none, verbatim with its receipt unknown, as a torn landed line is. Either way the
+/// item is archived, so acknowledging it once written loses nothing.
+async fn write_held(
+    archive: &Archive,
+    ingested_at: DateTime<Utc>,
+    held: &[Held],
+) -> Option<Written> {
+    let payloads: Vec<Payload> = held
+        .iter()
+        .map(|(item, sample)| match sample {
+            Some(sample) => Payload::from(sample),
+            None => Payload {
+                received_at: None,
+                json: item.item(),
+            },
+        })
+        .collect();
+    write_payloads(archive, ingested_at, &payloads).await
+}
+
+/// [`write`], for payloads that are not all whole samples, as an ingestion at `ingested_at`.
+async fn write_payloads(
+    archive: &Archive,
+    ingested_at: DateTime<Utc>,
+    payloads: &[Payload<'_>],
+) -> Option<Written> {
+    match archive.write(ingested_at, payloads).await {
+        Ok(written) => Some(written),
+        Err(err) => {
+            tracing::error!(%err, count = payloads.len(), "failed to write batch");
+            None
+        }
+    }
 }
@@ -7,15 +7,23 @@
 //! without an extract cannot be sessionised.
 //!
 //! Every session is re-derived from all of bronze, so a rerun replaces what the last one
-//! wrote rather than adding to it.
+//! wrote rather than adding to it. `--from`/`--to` narrow a run to the sessions starting, and
+//! the samples recorded, on those dates; the readings are read from a day before the range,
+//! so a session begun the evening before is still cut where a whole run would cut it.
+//!
+//! Given no range, a run derives nothing at all if no reading, session start, extract or
+//! tuning has changed since the last; otherwise it derives everything, since a reading
+//! ingested today can belong to a session from any day before.
+//!
+//! The readings are split a device at a time, each device's sessions placed before the next
+//! device's readings are read, so a run holds one device's readings rather than all of them.
 
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
 use medallion::MedallionArgs;
-use recorder::sessions::{Gap, Lead, sessions};
+use recorder::sessions::{Gap, Lead};
 use recorder::silver;
-use transport::countries::CountryAreas;
 
 #[derive(Parser)]
 #[command(about = "Derive the silver session datasets from the bronze telemetry")]
@@ -40,17 +48,25 @@ async fn main() {
         .init();
 
     let args = Args::parse();
-    let root = args.medallion.root().expect("locate the medallion store");
     let gap = Gap::new(chrono::Duration::minutes(i64::from(args.gap_mins)));
     let lead = Lead::new(chrono::Duration::seconds(i64::from(args.lead_secs)));
+    let root = args
+        .medallion
+        .root()
+        .expect("locate the medallion store")
+        .recording(silver::producer(gap, lead));
 
-    let countries = CountryAreas::newest(&root)
-        .await
-        .expect("read the country areas of the newest extract");
-    let derived = sessions(&root, gap, lead).await.expect("derive sessions");
-    let outcome = silver::write(&root, &derived, &countries)
+    let range = args.medallion.range().expect("read the range to derive");
+    let Some(outcome) = silver::sessionise(&root, gap, lead, range)
         .await
-        .expect("write sessions");
+        .expect("derive sessions")
+    else {
+        tracing::info!(
+            medallion_root = %root.path().display(),
+            "nothing read has changed since the sessions were derived"
+        );
+        return;
+    };
 
     tracing::info!(
         sessions = outcome.sessions,
@@ -62,6 +78,7 @@ async fn main() {
         unplaceable = outcome.unplaceable,
         gap_mins = args.gap_mins,
         lead_secs = args.lead_secs,
+        range = %range,
         medallion_root = %root.path().display(),
         "derived sessions"
     );
@@ -1,11 +1,13 @@
 //! The bronze telemetry datasets, written one file per ingestion.
 //!
-//! An ingestion writes four datasets, each partitioned by the UTC date it was ingested on
+//! An ingestion writes five datasets, each partitioned by the UTC date it was ingested on
 //! and named for the instant of the write:
 //!
 //!   - `raw_sample` — every payload verbatim, keyed on its md5. This is the lossless
 //!     record everything else is derived from, so a payload that fails to parse still
 //!     lands here.
+//!   - `rejected_sample` — each payload that failed to parse, verbatim again, with the
+//!     parser's error.
 //!   - `gps_reading` / `accel_reading` — one row per reading, interpreted from the
 //!     payloads. Both protocol versions produce the same rows.
 //!   - `device_session` — the metadata a device announces when it starts a session.
@@ -13,10 +15,18 @@
 //! Readings are split by sensor into their own datasets rather than sharing one under a
 //! `sensor=` partition, because the two carry different columns and a dataset is one
 //! schema.
+//!
+//! A [reparse](Archive::reparse) is an ingestion of its own, of rejected payloads rather
+//! than queued ones, once the parser has been fixed: it writes the readings it can now
+//! interpret, and a `reparsed_sample` row for each payload they came from, so that the next
+//! reparse leaves it alone. It writes no raw rows — the payloads are in raw already.
 
 use chrono::{DateTime, Utc};
-use medallion::{Dataset, DatasetSpec, Root, Row};
-use model::{AccelReadingRow, DeviceSessionRow, GpsReadingRow, RawSampleRow};
+use medallion::{Dataset, DatasetSpec, Query, QueryError, Root, Row};
+use model::{
+    AccelReadingRow, DeviceSessionRow, GpsReadingRow, RawSampleRow, RejectedSampleRow,
+    ReparsedSampleRow,
+};
 use shared::{AccelReading, GpsReading, Message, SessionStart, V0Message, V1Message};
 use telemetry::RawSample;
 
@@ -56,10 +66,23 @@ pub struct Written {
     pub gps: usize,
     pub accel: usize,
     pub devices: usize,
-    /// Payloads archived verbatim that no version of the protocol could interpret.
+    /// Payloads archived verbatim that no version of the protocol could interpret, each
+    /// written to `rejected_sample` too.
     pub unparseable: usize,
 }
 
+/// What one reparse wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Reparsed {
+    /// Rejected payloads interpreted this time, and recorded as reparsed.
+    pub reparsed: usize,
+    pub gps: usize,
+    pub accel: usize,
+    pub devices: usize,
+    /// Rejected payloads the parser still cannot interpret, left for a later reparse.
+    pub still_rejected: usize,
+}
+
 impl std::ops::Add for Written {
     type Output = Self;
 
@@ -78,10 +101,10 @@ impl std::ops::Add for Written {
 #[derive(Debug, Default)]
 struct Rows {
     raw: Vec<RawSampleRow>,
+    rejected: Vec<RejectedSampleRow>,
     gps: Vec<GpsReadingRow>,
     accel: Vec<AccelReadingRow>,
     devices: Vec<DeviceSessionRow>,
-    unparseable: usize,
 }
 
 /// A handle on the bronze telemetry datasets within a medallion store.
@@ -131,6 +154,7 @@ impl Archive {
         let rows = Rows::interpret(payloads);
 
         self.write_dataset(ingested_at, &rows.raw).await?;
+        self.write_dataset(ingested_at, &rows.rejected).await?;
         self.write_dataset(ingested_at, &rows.gps).await?;
         self.write_dataset(ingested_at, &rows.accel).await?;
         self.write_dataset(ingested_at, &rows.devices).await?;
@@ -140,7 +164,44 @@ impl Archive {
             gps: rows.gps.len(),
             accel: rows.accel.len(),
             devices: rows.devices.len(),
-            unparseable: rows.unparseable,
+            unparseable: rows.rejected.len(),
+        })
+    }
+
+    /// Interpret `rejected` payloads again, with the parser as it is now, and write the
+    /// readings of those it can as an ingestion at `reparsed_at`, returning what landed.
+    ///
+    /// The `reparsed_sample` rows are written last, so a reparse that fails part way leaves
+    /// its payloads to be tried again rather than recorded as recovered; the readings it did
+    /// write are then written twice, the same repeat a drain that dies before acknowledging
+    /// leaves — see [`telemetry`].
+    pub async fn reparse(
+        &self,
+        reparsed_at: DateTime<Utc>,
+        rejected: &[RejectedSampleRow],
+    ) -> Result<Reparsed, ArchiveError> {
+        let mut rows = Rows::default();
+        let mut reparsed: Vec<ReparsedSampleRow> = Vec::new();
+        for payload in rejected {
+            if rows.read(&payload.json).is_ok() {
+                reparsed.push(ReparsedSampleRow {
+                    md5: payload.md5.clone(),
+                    reparsed_at: reparsed_at.timestamp_millis(),
+                });
+            }
+        }
+
+        self.write_dataset(reparsed_at, &rows.gps).await?;
+        self.write_dataset(reparsed_at, &rows.accel).await?;
+        self.write_dataset(reparsed_at, &rows.devices).await?;
+        self.write_dataset(reparsed_at, &reparsed).await?;
+
+        Ok(Reparsed {
+            reparsed: reparsed.len(),
+            gps: rows.gps.len(),
+            accel: rows.accel.len(),
+            devices: rows.devices.len(),
+            still_rejected: rejected.len() - reparsed.len(),
         })
     }
 
@@ -158,27 +219,79 @@ impl Archive {
 
 impl Rows {
     /// Split `payloads` into the rows each dataset holds. Every payload lands in `raw`,
-    /// whether or not it can be interpreted.
+    /// whether or not it can be interpreted, and one that cannot in `rejected` as well.
     fn interpret(payloads: &[Payload<'_>]) -> Self {
         let mut rows = Self::default();
         for payload in payloads {
-            rows.raw.push(raw_row(payload));
-            match serde_json::from_str::<Message>(payload.json) {
-                Ok(Message::Version0(V0Message::Gps(r)) | Message::Version1(V1Message::Gps(r))) => {
-                    rows.gps.push(gps_row(&r))
-                }
-                Ok(
-                    Message::Version0(V0Message::Acceleration(r))
-                    | Message::Version1(V1Message::Acceleration(r)),
-                ) => rows.accel.push(accel_row(&r)),
-                Ok(Message::Version1(V1Message::StartSession(s))) => {
-                    rows.devices.push(device_session_row(&s))
-                }
-                Err(_) => rows.unparseable += 1,
+            let raw = raw_row(payload);
+            if let Err(err) = rows.read(payload.json) {
+                rows.rejected.push(RejectedSampleRow {
+                    md5: raw.md5.clone(),
+                    received_at: raw.received_at,
+                    json: raw.json.clone(),
+                    error: err.to_string(),
+                });
             }
+            rows.raw.push(raw);
         }
         rows
     }
+
+    /// Add the reading `json` holds to the dataset for its kind, or say why it holds none.
+    fn read(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        match serde_json::from_str::<Message>(json)? {
+            Message::Version0(V0Message::Gps(r)) | Message::Version1(V1Message::Gps(r)) => {
+                self.gps.push(gps_row(&r))
+            }
+            Message::Version0(V0Message::Acceleration(r))
+            | Message::Version1(V1Message::Acceleration(r)) => self.accel.push(accel_row(&r)),
+            Message::Version1(V1Message::StartSession(s)) => {
+                self.devices.push(device_session_row(&s))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The rejected payloads under their query name.
+const REJECTED: &str = "rejected";
+
+/// The reparsed payloads under their query name.
+const REPARSED: &str = "reparsed";
+
+/// Every rejected payload no reparse has yet recovered, once each however often it was
+/// rejected: a payload taken twice off the queue is archived, and rejected, twice — and
+/// perhaps with different errors, by two versions of the parser, of which the same one is
+/// kept every time.
+pub async fn unrecovered(root: &Root) -> Result<Vec<RejectedSampleRow>, QueryError> {
+    let query = Query::new(root.clone());
+    if !query
+        .register_if_present(model::REJECTED_SAMPLE, REJECTED)
+        .await?
+    {
+        return Ok(Vec::new());
+    }
+    let recovered = match query
+        .register_if_present(model::REPARSED_SAMPLE, REPARSED)
+        .await?
+    {
+        true => format!("WHERE md5 NOT IN (SELECT md5 FROM {REPARSED})"),
+        false => String::new(),
+    };
+    query
+        .rows(&format!(
+            "
+            SELECT md5, received_at, json, error
+            FROM (
+              SELECT *, ROW_NUMBER() OVER (PARTITION BY md5 ORDER BY error) AS rank
+              FROM {REJECTED}
+              {recovered}
+            )
+            WHERE rank = 1
+            ORDER BY received_at, md5
+            "
+        ))
+        .await
 }
 
 /// The archived form of a payload: its json verbatim, keyed on the md5 of that json.
@@ -231,7 +344,6 @@ fn device_session_row(start: &SessionStart) -> DeviceSessionRow {
 #[cfg(test)]
 mod tests {
     use chrono::TimeZone;
-    use medallion::Query;
     use shared::{Accel, AccelReading, DeviceInfo, DeviceType, Gps, GpsReading, SessionStart};
     use uuid::Uuid;
 
@@ -370,7 +482,8 @@ mod tests {
     }
 
     /// A payload no protocol version can interpret is still archived verbatim, since raw
-    /// is what everything else is rederived from.
+    /// is what everything else is rederived from — and rejected, with why, so it can be
+    /// found without reparsing raw.
     #[tokio::test]
     async fn an_uninterpretable_payload_is_still_archived() {
         let tmp = tempfile::tempdir().expect("tempdir");
@@ -388,6 +501,94 @@ mod tests {
         assert_eq!(written.raw, 1);
         assert_eq!(written.unparseable, 1);
         assert_eq!(rows_in(&root, model::RAW_SAMPLE).await, 1);
+
+        let rejected = unrecovered(&root).await.expect("read rejected");
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].json, samples[0].json());
+        assert_eq!(rejected[0].received_at, Some(1_700_000_050_000));
+        assert!(!rejected[0].error.is_empty(), "the parse error is kept");
+    }
+
+    /// A payload an older parser rejected is interpreted by the current one on a reparse,
+    /// and only once however often the reparse runs; what still fails is left rejected.
+    #[tokio::test]
+    async fn a_reparse_recovers_what_the_parser_now_reads_once() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = Root::new(tmp.path());
+        let archive = Archive::new(root.clone());
+        let json = serde_json::to_string(&gps(1_700_000_000_001, 55.95)).expect("serialize");
+        // Written as an older parser would have: the payload it wrongly refused.
+        let refused = RejectedSampleRow {
+            md5: format!("{:x}", md5::compute(&json)),
+            received_at: Some(1_700_000_050_000),
+            json: json.clone(),
+            error: "unknown variant `gps`".to_string(),
+        };
+        archive
+            .write_dataset(ingested_at(), &[refused.clone(), refused])
+            .await
+            .expect("write rejected");
+        archive
+            .write(
+                ingested_at() + chrono::Duration::milliseconds(1),
+                &[Payload {
+                    received_at: Some(1_700_000_050_001),
+                    json: "not-a-sample",
+                }],
+            )
+            .await
+            .expect("write");
+
+        let rejected = unrecovered(&root).await.expect("read rejected");
+        assert_eq!(rejected.len(), 2, "each payload once: {rejected:?}");
+
+        let reparsed_at = ingested_at() + chrono::Duration::days(1);
+        let reparsed = archive
+            .reparse(reparsed_at, &rejected)
+            .await
+            .expect("reparse");
+        assert_eq!(
+            reparsed,
+            Reparsed {
+                reparsed: 1,
+                gps: 1,
+                accel: 0,
+                devices: 0,
+                still_rejected: 1
+            }
+        );
+        assert_eq!(rows_in(&root, model::GPS_READING).await, 1);
+
+        let left = unrecovered(&root).await.expect("read rejected");
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].json, "not-a-sample");
+        let again = archive
+            .reparse(reparsed_at + chrono::Duration::seconds(1), &left)
+            .await
+            .expect("reparse again");
+        assert_eq!(again.reparsed, 0);
+        assert_eq!(rows_in(&root, model::GPS_READING).await, 1);
+    }
+
+    #[tokio::test]
+    async fn a_store_with_nothing_rejected_has_nothing_to_reparse() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = Root::new(tmp.path());
+        Archive::new(root.clone())
+            .write(
+                ingested_at(),
+                &archived(&[queued(&gps(1_700_000_000_001, 55.95))]),
+            )
+            .await
+            .expect("write");
+
+        assert!(unrecovered(&root).await.expect("read rejected").is_empty());
+        assert!(
+            !Archive::new(root)
+                .ingestion_file(model::REJECTED_SAMPLE, ingested_at())
+                .expect("path")
+                .exists()
+        );
     }
 
     /// A payload whose receipt was never timed is archived with that unknown, rather than
@@ -0,0 +1,243 @@
+//! What `recorder follow` decides between one sample and the next: when the batch it is
+//! filling is due to be written, when the sessions are due to be derived again and over which
+//! dates, and how long to wait before trying again something that has stopped working.
+//!
+//! A batch is written when it is full or when its oldest sample has waited long enough,
+//! whichever comes first: a busy queue is written in batches of a useful size, and a quiet
+//! one still reaches bronze within a bounded time of a sample arriving. The sessions are
+//! derived far less often than batches are written, and only over the dates written since
+//! they last were. Nothing here touches redis or the store, so the decisions are testable
+//! without either.
+
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use medallion::DateRange;
+
+/// The shortest a take from the queue is allowed to block. A blocking take given no time at
+/// all would wait forever, which is the last thing a batch about to fall due wants.
+const SHORTEST_WAIT: Duration = Duration::from_millis(10);
+
+/// The samples taken since the last write, and when the first of them was.
+#[derive(Debug)]
+pub struct Batch<T> {
+    items: Vec<T>,
+    opened: Option<Instant>,
+    size: usize,
+    age: Duration,
+}
+
+impl<T> Batch<T> {
+    /// An empty batch, due once it holds `size` items or its first is `age` old.
+    pub fn new(size: usize, age: Duration) -> Self {
+        Self {
+            items: Vec::with_capacity(size),
+            opened: None,
+            size,
+            age,
+        }
+    }
+
+    /// Add `item`, taken at `now`.
+    pub fn push(&mut self, now: Instant, item: T) {
+        self.opened.get_or_insert(now);
+        self.items.push(item);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the batch should be written at `now`: it is full, or its first item has
+    /// waited its age. An empty batch is never due.
+    pub fn is_due(&self, now: Instant) -> bool {
+        match self.opened {
+            None => false,
+            Some(opened) => self.items.len() >= self.size || now >= opened + self.age,
+        }
+    }
+
+    /// How long a take may block at `now` without holding the batch past when it falls due:
+    /// `idle` while the batch is empty, and otherwise no longer than the batch has left.
+    pub fn wait(&self, now: Instant, idle: Duration) -> Duration {
+        let left = match self.opened {
+            None => idle,
+            Some(opened) => (opened + self.age).saturating_duration_since(now),
+        };
+        left.min(idle).max(SHORTEST_WAIT)
+    }
+
+    /// Empty the batch, returning what it held in the order it was taken.
+    pub fn take(&mut self) -> Vec<T> {
+        self.opened = None;
+        std::mem::replace(&mut self.items, Vec::with_capacity(self.size))
+    }
+}
+
+/// The ingestion dates written since the sessions were last derived, and when that was.
+///
+/// Deriving reads every sample a session in its range can be made of, which takes far longer
+/// than writing a batch, so a follow derives at most once every `every`, over the dates
+/// written in between, rather than after each batch.
+#[derive(Debug)]
+pub struct Derivation {
+    every: Duration,
+    last: Option<Instant>,
+    written: Option<(NaiveDate, NaiveDate)>,
+}
+
+impl Derivation {
+    /// Due as soon as anything is written, and then no sooner than `every` after the last.
+    pub fn new(every: Duration) -> Self {
+        Self {
+            every,
+            last: None,
+            written: None,
+        }
+    }
+
+    /// Note that an ingestion was written on `date`.
+    pub fn wrote(&mut self, date: NaiveDate) {
+        self.written = Some(match self.written {
+            None => (date, date),
+            Some((first, last)) => (first.min(date), last.max(date)),
+        });
+    }
+
+    /// Whether the sessions should be derived at `now`: something has been written since
+    /// they last were, at least `every` ago.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.written.is_some() && self.last.is_none_or(|last| now >= last + self.every)
+    }
+
+    /// Start a derivation at `now`, returning the dates written since the last, if any.
+    pub fn take(&mut self, now: Instant) -> Option<DateRange> {
+        let (first, last) = self.written.take()?;
+        self.last = Some(now);
+        DateRange::new(Some(first), Some(last)).ok()
+    }
+
+    /// Put back the dates of a derivation that failed, for the next to cover too.
+    pub fn failed(&mut self, range: DateRange) {
+        for date in [range.from(), range.to()].into_iter().flatten() {
+            self.wrote(date);
+        }
+    }
+}
+
+/// How long to wait before each attempt at something that keeps failing: doubling from
+/// `first` on each failure, up to `most`, and back to `first` once it works again.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    first: Duration,
+    most: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(first: Duration, most: Duration) -> Self {
+        Self {
+            first,
+            most,
+            next: first,
+        }
+    }
+
+    /// How long to wait after this failure, lengthening the wait after the next.
+    pub fn failed(&mut self) -> Duration {
+        let wait = self.next;
+        self.next = (self.next * 2).min(self.most);
+        wait
+    }
+
+    /// Forget the failures: the next waits `first` again.
+    pub fn succeeded(&mut self) {
+        self.next = self.first;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_batch_falls_due_when_full_or_when_its_first_item_is_old_enough() {
+        let start = Instant::now();
+        let mut batch = Batch::new(3, Duration::from_secs(60));
+        assert!(!batch.is_due(start + Duration::from_secs(600)));
+
+        batch.push(start, 1);
+        batch.push(start + Duration::from_secs(10), 2);
+        assert!(!batch.is_due(start + Duration::from_secs(59)));
+        assert!(batch.is_due(start + Duration::from_secs(60)));
+
+        batch.push(start + Duration::from_secs(20), 3);
+        assert!(batch.is_due(start + Duration::from_secs(20)));
+
+        assert_eq!(batch.take(), vec![1, 2, 3]);
+        assert!(batch.is_empty());
+        assert!(!batch.is_due(start + Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn a_take_blocks_no_longer_than_the_batch_has_left() {
+        let start = Instant::now();
+        let idle = Duration::from_secs(5);
+        let mut batch = Batch::new(100, Duration::from_secs(30));
+        assert_eq!(batch.wait(start, idle), idle);
+
+        batch.push(start, ());
+        assert_eq!(batch.wait(start, idle), idle);
+        assert_eq!(
+            batch.wait(start + Duration::from_secs(28), idle),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            batch.wait(start + Duration::from_secs(31), idle),
+            SHORTEST_WAIT
+        );
+    }
+
+    #[test]
+    fn sessions_are_derived_at_most_once_an_interval_over_the_dates_written_since() {
+        let start = Instant::now();
+        let date = |day| NaiveDate::from_ymd_opt(2026, 7, day).unwrap();
+        let mut derivation = Derivation::new(Duration::from_secs(600));
+        assert!(!derivation.is_due(start), "nothing is written yet");
+
+        derivation.wrote(date(10));
+        assert!(derivation.is_due(start));
+        assert_eq!(derivation.take(start), Some(DateRange::on(date(10))));
+
+        derivation.wrote(date(11));
+        derivation.wrote(date(10));
+        assert!(!derivation.is_due(start + Duration::from_secs(599)));
+        assert!(derivation.is_due(start + Duration::from_secs(600)));
+        assert_eq!(
+            derivation.take(start + Duration::from_secs(600)),
+            Some(DateRange::new(Some(date(10)), Some(date(11))).unwrap())
+        );
+        assert!(!derivation.is_due(start + Duration::from_secs(6000)));
+
+        derivation.failed(DateRange::on(date(11)));
+        assert!(derivation.is_due(start + Duration::from_secs(1200)));
+        assert_eq!(
+            derivation.take(start + Duration::from_secs(1200)),
+            Some(DateRange::on(date(11)))
+        );
+    }
+
+    #[test]
+    fn the_wait_doubles_to_a_ceiling_and_starts_over_once_it_works() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        let waits: Vec<u64> = (0..5).map(|_| backoff.failed().as_secs()).collect();
+        assert_eq!(waits, vec![1, 2, 4, 5, 5]);
+
+        backoff.succeeded();
+        assert_eq!(backoff.failed(), Duration::from_secs(1));
+    }
+}
@@ -1,5 +1,6 @@
 //! The recorder's datasets: the bronze telemetry the cli writes drained samples into.
 
 pub mod bronze;
+pub mod follow;
 pub mod sessions;
 pub mod silver;
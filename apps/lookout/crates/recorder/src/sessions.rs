@@ -6,16 +6,27 @@
 //! the next drain — so a run has to be able to re-derive a session it has already written
 //! and reach the same answer.
 //!
+//! A run over a range of dates reads less, but not only the range: bronze telemetry is
+//! partitioned on when it was ingested, which can be any time after it was recorded, and a
+//! session in range can have begun before it — days before, for a device left recording.
+//!
 //! Bronze tolerates the same observation arriving twice, so the samples are deduped on
 //! `(device_id, t)` before anything looks at the intervals between them: a repeated sample
 //! left in place is a zero-length interval, which is not a silence and must not be read as
 //! one.
+//!
+//! The samples are read a device at a time: sessions never span two devices, so once the
+//! samples have been ordered by device nothing of one device is needed to split the next, and
+//! a run holds one device's samples as read rather than the whole of bronze. The ordering
+//! itself is the query's, which spills to disk past [`medallion::MEMORY_LIMIT`] rather than
+//! holding bronze in memory to sort it.
 
 use std::collections::HashMap;
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Days, Duration, NaiveDate, NaiveTime, Utc};
 use geo_types::Point;
-use medallion::{Query, Root};
+use medallion::lineage::{self, LineageError, Pending};
+use medallion::{DateRange, Query, Root, RowStream};
 use model::{DeviceId, SessionId, StartedBy};
 use serde::{Deserialize, Serialize};
 
@@ -44,6 +55,48 @@ const DISTINCT_SAMPLES: &str = "
     ORDER BY device_id, t
 ";
 
+/// How many days before a range the samples are first read from, so a session that began
+/// before the range and ran into it is seen from its start. A session that began earlier
+/// still has the read reach back a day at a time until it is seen from its start — see
+/// [`lookback`].
+const LOOKBACK_DAYS: u64 = 1;
+
+/// How many devices' first session reaching past `from` may have begun before `read`, the
+/// instant every sample recorded since has been read from: a session first seen within
+/// `margin` of it can have had a sample before it that was not. All are epoch millis.
+///
+/// Sessions are stood in for by the runs of samples no silence of more than `gap` splits. A
+/// reported start only ever splits a run, so a run begun in time is a session begun in time;
+/// the margin is the gap the run's first sample can reach back across, and the lead a
+/// session announced within it can absorb.
+fn unseen_starts(read: i64, from: i64, gap: i64, margin: i64) -> String {
+    format!(
+        "
+        WITH millis AS (
+          SELECT device_id, CAST(t AS BIGINT) AS t FROM samples
+        ),
+        recorded AS (
+          SELECT device_id, t, LAG(t) OVER (PARTITION BY device_id ORDER BY t) AS previous
+          FROM millis
+          WHERE t >= {read}
+        ),
+        runs AS (
+          SELECT device_id, t FROM recorded WHERE previous IS NULL OR t - previous > {gap}
+        ),
+        reaching AS (
+          SELECT device_id, MIN(t) AS t FROM recorded WHERE t >= {from} GROUP BY device_id
+        )
+        SELECT COUNT(*) AS count FROM (
+          SELECT reaching.device_id, MAX(runs.t) AS began
+          FROM reaching JOIN runs
+            ON runs.device_id = reaching.device_id AND runs.t <= reaching.t
+          GROUP BY reaching.device_id
+        )
+        WHERE began < {read} + {margin}
+        "
+    )
+}
+
 /// One row per distinct session start, in the same order.
 const DISTINCT_SESSION_STARTS: &str = "
     SELECT DISTINCT device_id, t FROM session_starts ORDER BY device_id, t
@@ -54,6 +107,8 @@ const DISTINCT_SESSION_STARTS: &str = "
 pub enum SessionError {
     #[error("reading the bronze telemetry: {0}")]
     Query(#[from] medallion::QueryError),
+    #[error("comparing with what the last run read: {0}")]
+    Lineage(#[from] LineageError),
 }
 
 /// How long a device has to go unheard before the silence separates two sessions.
@@ -186,22 +241,82 @@ impl Session {
     }
 }
 
-/// Derive every session in the store, oldest first within each device.
+/// Whether a run over every date has anything to derive: `false` where no reading, session
+/// start, extract or tuning has changed since the last — see
+/// [`medallion::lineage::pending`]. The root's other reads, the country areas among them,
+/// have to have been made already.
+///
+/// A reading is filed under the date it was ingested, which can be any time after it was
+/// recorded, so a date that changed says nothing of which sessions the change reaches:
+/// anything pending is everything.
+pub async fn pending(root: &Root) -> Result<bool, SessionError> {
+    let query = Query::new(root.clone());
+    query
+        .register_if_present(model::GPS_READING, SAMPLES)
+        .await?;
+    query
+        .register_if_present(model::DEVICE_SESSION, SESSION_STARTS)
+        .await?;
+    Ok(
+        lineage::pending(root, &[model::SESSION.info(), model::SESSION_SAMPLE.info()]).await?
+            != Pending::Nothing,
+    )
+}
+
+/// Derive every session reaching into `range`, oldest first within each device.
+///
+/// The sessions returned include some that begin outside the range, since the samples read
+/// around it make them up too; which of them a run may replace is the writer's question.
+/// This collects what [`sessions_by_device`] yields, for a caller that wants them all at
+/// once; a run over a store of any size takes them a device at a time instead.
+pub async fn sessions(
+    root: &Root,
+    gap: Gap,
+    lead: Lead,
+    range: DateRange,
+) -> Result<Vec<Session>, SessionError> {
+    let mut devices = sessions_by_device(root, gap, lead, range).await?;
+    let mut sessions = Vec::new();
+    while let Some(device) = devices.next().await? {
+        sessions.extend(device);
+    }
+    Ok(sessions)
+}
+
+/// Derive every session reaching into `range`, a device at a time — see [`Devices`].
+///
+/// Samples are read from as far before the range as its sessions reach — see [`lookback`] —
+/// onwards, with no end: a sample cannot have been ingested before it was recorded, but can
+/// have been any time after.
 ///
 /// A store holding no samples yet derives no sessions rather than failing: the datasets
 /// are written by a separate drain, which may not have run.
-pub async fn sessions(root: &Root, gap: Gap, lead: Lead) -> Result<Vec<Session>, SessionError> {
+pub async fn sessions_by_device(
+    root: &Root,
+    gap: Gap,
+    lead: Lead,
+    range: DateRange,
+) -> Result<Devices, SessionError> {
+    let ingested = lookback(root, gap, lead, range).await?;
     let query = Query::new(root.clone());
     if !query
-        .register_if_present(model::GPS_READING, SAMPLES)
+        .register_if_present_within(model::GPS_READING, SAMPLES, ingested)
         .await?
     {
-        return Ok(Vec::new());
-    }
-    let samples: Vec<Sample> = query.rows(DISTINCT_SAMPLES).await?;
-
+        return Ok(Devices {
+            samples: None,
+            unsplit: Vec::new(),
+            started: HashMap::new(),
+            gap,
+            lead,
+        });
+    }
+    let samples = query.stream_rows(DISTINCT_SAMPLES).await?;
+
+    // A device's session starts are few beside its samples — one a journey — so they are
+    // read whole, to be at hand whichever device's samples come next.
     let started = if query
-        .register_if_present(model::DEVICE_SESSION, SESSION_STARTS)
+        .register_if_present_within(model::DEVICE_SESSION, SESSION_STARTS, ingested)
         .await?
     {
         started_by_device(query.rows(DISTINCT_SESSION_STARTS).await?)
@@ -209,16 +324,109 @@ pub async fn sessions(root: &Root, gap: Gap, lead: Lead) -> Result<Vec<Session>,
         HashMap::new()
     };
 
-    Ok(samples
-        .chunk_by(|a, b| a.device_id == b.device_id)
-        .flat_map(|device| {
-            let started = started
-                .get(&device[0].device_id)
-                .map(Vec::as_slice)
-                .unwrap_or_default();
-            split(device, started, gap, lead)
-        })
-        .collect())
+    Ok(Devices {
+        samples: Some(samples),
+        unsplit: Vec::new(),
+        started,
+        gap,
+        lead,
+    })
+}
+
+/// The ingestion dates a run over `range` reads the samples of: from [`LOOKBACK_DAYS`] before
+/// the range, and a day earlier each time a session reaching into the range may have begun
+/// before what was read — see [`unseen_starts`] — until none may have.
+///
+/// Every sample recorded since the start of the dates read is among them, so a session seen
+/// to begin more than its gap after that start began there. A device's samples run out, so
+/// the reach back ends at the first of them at the latest; each day further is one more read
+/// of what the run reads anyway, which only a session running through midnight costs.
+async fn lookback(
+    root: &Root,
+    gap: Gap,
+    lead: Lead,
+    range: DateRange,
+) -> Result<DateRange, SessionError> {
+    let Some(from) = range.from() else {
+        return Ok(range.open_ended());
+    };
+    let millis = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc().timestamp_millis();
+    let margin = (gap.0 + lead.0).num_milliseconds();
+    let mut days = LOOKBACK_DAYS;
+    loop {
+        let ingested = range.starting_earlier(days).open_ended();
+        let query = Query::new(root.clone());
+        if !query
+            .register_if_present_within(model::GPS_READING, SAMPLES, ingested)
+            .await?
+        {
+            return Ok(ingested);
+        }
+        let unseen = unseen_starts(
+            millis(from - Days::new(days)),
+            millis(from),
+            gap.0.num_milliseconds(),
+            margin,
+        );
+        if query.count(&unseen).await? == 0 {
+            return Ok(ingested);
+        }
+        days += 1;
+    }
+}
+
+/// The sessions of one device after another, split as its samples are read.
+///
+/// The samples arrive ordered by device and then by time, so a device's run of them is
+/// complete once a sample of another device follows it, and is split and let go before the
+/// next is read. What is held at once is one device's samples, plus the rest of the batch
+/// they arrived in.
+pub struct Devices {
+    /// The deduped samples still to read, or `None` once every one has been.
+    samples: Option<RowStream<Sample>>,
+    /// Samples read but not yet split: the start of one device's run, and perhaps of the
+    /// runs after it.
+    unsplit: Vec<Sample>,
+    started: HashMap<DeviceId, Vec<DateTime<Utc>>>,
+    gap: Gap,
+    lead: Lead,
+}
+
+impl Devices {
+    /// The sessions of the next device, oldest first, or `None` once every device has been
+    /// split. A device yields at least one session, since it is only seen through a sample.
+    pub async fn next(&mut self) -> Result<Option<Vec<Session>>, SessionError> {
+        loop {
+            // How many of the samples held make up a device's whole run: those before the
+            // first of another device, or every one once nothing more is to be read.
+            let complete = match self.samples {
+                None => self.unsplit.len(),
+                Some(_) => self
+                    .unsplit
+                    .iter()
+                    .position(|sample| sample.device_id != self.unsplit[0].device_id)
+                    .unwrap_or(0),
+            };
+            if complete > 0 {
+                let rest = self.unsplit.split_off(complete);
+                let device = std::mem::replace(&mut self.unsplit, rest);
+                let started = self
+                    .started
+                    .get(&device[0].device_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                return Ok(Some(split(&device, started, self.gap, self.lead)));
+            }
+
+            match &mut self.samples {
+                None => return Ok(None),
+                Some(samples) => match samples.next().await? {
+                    Some(batch) => self.unsplit.extend(batch),
+                    None => self.samples = None,
+                },
+            }
+        }
+    }
 }
 
 /// The instants each device reported a session start at, in time order.
@@ -363,6 +571,12 @@ mod tests {
     /// with, so the sessions are derived from bronze in the shape bronze really has.
     async fn store(tmp: &tempfile::TempDir, messages: &[Message]) -> Root {
         let root = Root::new(tmp.path());
+        ingest(&root, start(), messages).await;
+        root
+    }
+
+    /// Archive `messages` into `root` as one ingestion at `at`.
+    async fn ingest(root: &Root, at: DateTime<Utc>, messages: &[Message]) {
         let json: Vec<String> = messages
             .iter()
             .map(|message| serde_json::to_string(message).expect("serialize"))
@@ -370,16 +584,15 @@ mod tests {
         let payloads: Vec<Payload> = json
             .iter()
             .map(|json| Payload {
-                received_at: Some(start().timestamp_millis()),
+                received_at: Some(at.timestamp_millis()),
                 json,
             })
             .collect();
 
         Archive::new(root.clone())
-            .write(start(), &payloads)
+            .write(at, &payloads)
             .await
             .expect("archive");
-        root
     }
 
     /// The sessions derived from a store holding `messages`, at the default threshold.
@@ -390,7 +603,7 @@ mod tests {
     /// The same, at the threshold a test names rather than the default one.
     async fn derived_at(tmp: &tempfile::TempDir, messages: &[Message], gap: Gap) -> Vec<Session> {
         let root = store(tmp, messages).await;
-        sessions(&root, gap, Lead::default())
+        sessions(&root, gap, Lead::default(), DateRange::ALL)
             .await
             .expect("derive sessions")
     }
@@ -673,15 +886,89 @@ mod tests {
         );
     }
 
+    /// Each device's sessions arrive together, and apart from every other device's, however
+    /// the devices' samples interleave in time.
+    #[tokio::test]
+    async fn sessions_arrive_a_device_at_a_time() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let (first, second) = (device(1), device(2));
+        let root = store(
+            &tmp,
+            &[
+                gps(second, start(), 48.1),
+                gps(first, start() + minutes(1), 52.5),
+                gps(second, start() + minutes(30), 48.2),
+                gps(first, start() + minutes(2), 52.6),
+            ],
+        )
+        .await;
+
+        let mut devices =
+            sessions_by_device(&root, Gap::default(), Lead::default(), DateRange::ALL)
+                .await
+                .expect("read the samples");
+        let mut seen = Vec::new();
+        while let Some(sessions) = devices.next().await.expect("derive sessions") {
+            assert!(
+                sessions
+                    .iter()
+                    .all(|session| session.device_id == sessions[0].device_id),
+                "one device's sessions at a time"
+            );
+            seen.push((sessions[0].device_id.clone(), sessions.len()));
+        }
+
+        assert_eq!(
+            seen,
+            [(DeviceId::from(first), 1), (DeviceId::from(second), 2)]
+        );
+    }
+
+    /// A run over one day reads back as far as the session reaching into it began, however
+    /// many midnights before: here two, with each day's samples ingested the day they were
+    /// recorded, so the day's lookback alone would begin the session a day late.
+    #[tokio::test]
+    async fn a_session_running_through_two_midnights_is_seen_from_its_start() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = Root::new(tmp.path());
+        let id = device(1);
+        let began = Utc.with_ymd_and_hms(2026, 7, 24, 22, 0, 0).unwrap();
+        // Every ten minutes, the longest silence a session survives, to 01:00 two days on.
+        let fixes: Vec<DateTime<Utc>> = (0..=162).map(|n| began + minutes(10 * n)).collect();
+        for day in fixes.chunk_by(|a, b| a.date_naive() == b.date_naive()) {
+            let messages: Vec<Message> = day.iter().map(|at| gps(id, *at, 52.5)).collect();
+            ingest(&root, day[0], &messages).await;
+        }
+        let last = *fixes.last().expect("fixes");
+
+        let sessions = sessions(
+            &root,
+            Gap::new(minutes(10)),
+            Lead::default(),
+            DateRange::on(last.date_naive()),
+        )
+        .await
+        .expect("derive sessions");
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].started_at(), began);
+        assert_eq!(sessions[0].samples.len(), fixes.len());
+    }
+
     /// The drain may not have run yet, which is a store with nothing in it rather than a
     /// failure.
     #[tokio::test]
     async fn a_store_with_no_samples_derives_no_sessions() {
         let tmp = tempfile::tempdir().expect("tempdir");
 
-        let derived = sessions(&Root::new(tmp.path()), Gap::default(), Lead::default())
-            .await
-            .expect("derive sessions");
+        let derived = sessions(
+            &Root::new(tmp.path()),
+            Gap::default(),
+            Lead::default(),
+            DateRange::ALL,
+        )
+        .await
+        .expect("derive sessions");
 
         assert!(derived.is_empty());
     }
@@ -8,27 +8,44 @@
 //! The two datasets are partitioned by different dates — a sample by its own instant, a
 //! session by the instant it began — so a session crossing midnight has its samples split
 //! over two partitions while itself living in one. A run rebuilds every partition it
-//! produces rows for, since it re-derives every session from all of bronze.
+//! produces rows for, since it re-derives every session from all of bronze — or every
+//! partition dated within the range it was given, each dataset by its own date, so a session
+//! crossing the end of the range keeps the samples beyond it as the last run wrote them.
 //!
 //! Both sit under a `country=` partition, because the projected column's CRS is declared
 //! per file and the zone is chosen per country: rows of two countries cannot share a file
 //! and state one CRS truthfully. Which country a session is in follows from where it
 //! started, so a session whose start is in no country the store knows is left unwritten —
 //! there is no zone to project it into — and counted.
+//!
+//! [`sessionise`] is a whole derivation, from reading bronze to writing both datasets: what
+//! the `sessionise` binary runs, and what `recorder follow` runs after each batch it writes.
+
+use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
 use geo::{BoundingRect, Distance, Euclidean};
 use geo_types::{LineString, Point};
-use medallion::{Countries, GeoRow, Projector, Replaced, Root};
+use medallion::lineage::Producer;
+use medallion::{
+    Countries, DateRange, GeoRow, GeoRowWriter, Projector, ReplaceError, Replaced, Root, TableError,
+};
 use model::{Bbox, SessionRow, SessionSampleRow};
+use transport::countries::{CountryAreas, CountryError};
 
-use crate::sessions::Session;
+use crate::sessions::{Gap, Lead, Session, SessionError, pending, sessions_by_device};
+
+/// What a derivation of the sessions is recorded as having been produced by, whichever
+/// binary ran it.
+const PRODUCER: &str = "sessionise";
 
 /// What one write did, per dataset.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct WriteOutcome {
+    /// Sessions written: those starting within the range.
     pub sessions: usize,
     pub session_partitions: Replaced,
+    /// Samples written: those recorded within the range.
     pub samples: usize,
     pub sample_partitions: Replaced,
     /// Sessions starting outside every country the store knows, and so not written.
@@ -44,6 +61,18 @@ pub enum SilverError {
     Write(#[from] medallion::TableError),
 }
 
+/// A failure of a whole derivation: reading what it places sessions with, deriving them, or
+/// writing them.
+#[derive(Debug, thiserror::Error)]
+pub enum SessioniseError {
+    #[error("reading the country areas of the newest extract: {0}")]
+    Countries(#[from] CountryError),
+    #[error("deriving the sessions: {0}")]
+    Derive(#[from] SessionError),
+    #[error("writing the sessions: {0}")]
+    Write(#[from] SilverError),
+}
+
 /// One session placed on the map: its row and path, and its samples' rows and points.
 struct Placed {
     row: SessionRow,
@@ -57,50 +86,162 @@ struct Located {
     point: Point<f64>,
 }
 
-/// Write `sessions` and their samples to the silver datasets under `root`.
-///
-/// Each session's country is looked up from where it started, since that fixes the CRS of
-/// its projected geometry — for its samples as much as for itself, so that a session and the
-/// samples it is made of are measured in the same metres wherever they later went. A session
-/// starting where `countries` knows no country is reported as unplaceable rather than
-/// written.
+/// Write `sessions` and their samples to the silver datasets under `root`, replacing the
+/// dates in `range` — see [`Writer`], which this writes them through in one piece.
 pub async fn write(
     root: &Root,
     sessions: &[Session],
     countries: &impl Countries,
+    range: DateRange,
 ) -> Result<WriteOutcome, SilverError> {
-    let mut outcome = WriteOutcome::default();
-    let mut session_rows: Vec<GeoRow<SessionRow, LineString<f64>>> = Vec::new();
-    let mut sample_rows: Vec<GeoRow<SessionSampleRow, Point<f64>>> = Vec::new();
-
-    for session in sessions {
-        match countries.containing(session.started_from()) {
-            None => outcome.unplaceable += 1,
-            Some(country) => {
-                let placed = place(session, &Projector::for_country(country)?)?;
-                sample_rows.extend(placed.samples.into_iter().map(|sample| GeoRow {
-                    row: sample.row,
-                    geometry: sample.point,
-                    country,
-                }));
-                session_rows.push(GeoRow {
-                    row: placed.row,
-                    geometry: placed.path,
-                    country,
-                });
+    let mut writer = Writer::open(root, range).await?;
+    let added = writer.add(sessions, countries).await;
+    writer.conclude(added).await
+}
+
+/// The producer a derivation with this tuning records: one name and set of parameters however
+/// it was run, so that a store cannot tell, and need not re-derive because of, which binary
+/// last derived its sessions.
+pub fn producer(gap: Gap, lead: Lead) -> Producer {
+    Producer::new(PRODUCER)
+        .parameter("gap_mins", gap.as_seconds() / 60)
+        .parameter("lead_secs", lead.as_seconds())
+}
+
+/// Derive the sessions reaching into `range` from the bronze telemetry and write them,
+/// placing each with the country areas of the newest extract. `root` is expected to be
+/// recording [`producer`] for the same tuning.
+///
+/// Returns `None` when asked for every date and nothing read has changed since the last
+/// derivation, which then writes nothing; otherwise everything is derived, since a reading
+/// ingested today can belong to a session from any day before. The sessions are split a
+/// device at a time, each device's placed and written before the next device's readings are
+/// read.
+pub async fn sessionise(
+    root: &Root,
+    gap: Gap,
+    lead: Lead,
+    range: DateRange,
+) -> Result<Option<WriteOutcome>, SessioniseError> {
+    // Read before asking what is pending, which compares against every read the root has made.
+    let countries = CountryAreas::newest(root).await?;
+    if range.is_all() && !pending(root).await? {
+        return Ok(None);
+    }
+    let mut devices = sessions_by_device(root, gap, lead, range).await?;
+    let mut writer = Writer::open(root, range).await?;
+    let derived = async {
+        while let Some(derived) = devices.next().await? {
+            writer.add(&derived, &countries).await?;
+        }
+        Ok::<_, SessioniseError>(())
+    }
+    .await;
+    Ok(Some(writer.conclude(derived).await?))
+}
+
+/// The two datasets a run replaces, written a few sessions at a time.
+///
+/// A run derives sessions a device at a time, and writes each device's rows here before the
+/// next is derived, so what it holds at once is one device's sessions and rows, whatever the
+/// size of the store. Neither dataset is seen by a reader until the writer is finished; each
+/// is then published whole, sessions first.
+pub struct Writer {
+    sessions: GeoRowWriter<SessionRow, LineString<f64>>,
+    samples: GeoRowWriter<SessionSampleRow, Point<f64>>,
+    unplaceable: usize,
+}
+
+impl Writer {
+    /// Start replacing the dates in `range` of both datasets under `root`.
+    pub async fn open(root: &Root, range: DateRange) -> Result<Self, SilverError> {
+        Ok(Self {
+            sessions: GeoRowWriter::open(root, range).await?,
+            samples: GeoRowWriter::open(root, range).await?,
+            unplaceable: 0,
+        })
+    }
+
+    /// Place `sessions` and their samples, and write them.
+    ///
+    /// Each session's country is looked up from where it started, since that fixes the CRS
+    /// of its projected geometry — for its samples as much as for itself, so that a session
+    /// and the samples it is made of are measured in the same metres wherever they later
+    /// went. A session starting where `countries` knows no country is counted as unplaceable
+    /// rather than written.
+    pub async fn add(
+        &mut self,
+        sessions: &[Session],
+        countries: &impl Countries,
+    ) -> Result<(), SilverError> {
+        let mut placed_sessions = Vec::with_capacity(sessions.len());
+        let mut placed_samples = Vec::new();
+        let starts: Vec<Point<f64>> = sessions.iter().map(Session::started_from).collect();
+        for (session, country) in sessions.iter().zip(countries.containing_all(&starts)) {
+            match country {
+                None => self.unplaceable += 1,
+                Some(country) => {
+                    let placed = place(session, &Projector::for_country(country)?)?;
+                    placed_samples.extend(placed.samples.into_iter().map(|sample| GeoRow {
+                        row: sample.row,
+                        geometry: sample.point,
+                        country,
+                    }));
+                    placed_sessions.push(GeoRow {
+                        row: placed.row,
+                        geometry: placed.path,
+                        country,
+                    });
+                }
+            }
+        }
+        self.sessions.write(&placed_sessions).await?;
+        self.samples.write(&placed_samples).await?;
+        Ok(())
+    }
+
+    /// Publish both datasets if `result` says the run adding to them succeeded, and
+    /// abandon both if not, passing its failure on — with what either abandon failed at
+    /// beside it, as [`medallion::Rebuild::conclude`] reports it.
+    pub async fn conclude<E>(self, result: Result<(), E>) -> Result<WriteOutcome, E>
+    where
+        E: From<SilverError> + Display,
+    {
+        match result {
+            Ok(()) => Ok(self.finish().await?),
+            Err(err) => {
+                let err = abandoned(err, self.sessions.abandon().await);
+                Err(abandoned(err, self.samples.abandon().await))
             }
         }
     }
 
-    outcome.sessions = session_rows.len();
-    outcome.samples = sample_rows.len();
-    outcome.session_partitions = medallion::write_geo_rows(root, &session_rows)
-        .await?
-        .partitions;
-    outcome.sample_partitions = medallion::write_geo_rows(root, &sample_rows)
-        .await?
-        .partitions;
-    Ok(outcome)
+    /// Sweep and publish both datasets.
+    async fn finish(self) -> Result<WriteOutcome, SilverError> {
+        let sessions = match self.sessions.finish().await {
+            Ok(written) => written,
+            Err(err) => return Err(abandoned(err.into(), self.samples.abandon().await)),
+        };
+        let samples = self.samples.finish().await?;
+        Ok(WriteOutcome {
+            sessions: sessions.rows,
+            session_partitions: sessions.partitions,
+            samples: samples.rows,
+            sample_partitions: samples.partitions,
+            unplaceable: self.unplaceable,
+        })
+    }
+}
+
+/// `failure`, with what abandoning one of the datasets then failed at beside it rather than
+/// lost, if it did.
+fn abandoned<E: From<SilverError> + Display>(failure: E, abandon: Result<(), TableError>) -> E {
+    match abandon {
+        Ok(()) => failure,
+        Err(abandon) => {
+            SilverError::from(TableError::from(ReplaceError::abandoned(&failure, abandon))).into()
+        }
+    }
 }
 
 /// One session placed on the map: its row and path, and its samples' rows and points.
@@ -209,7 +350,7 @@ mod tests {
     use uuid::Uuid;
 
     use crate::bronze::{Archive, Payload};
-    use crate::sessions::{Gap, Lead, sessions};
+    use crate::sessions::sessions;
 
     use super::*;
 
@@ -232,7 +373,7 @@ mod tests {
     }
 
     fn germany() -> Everywhere {
-        Everywhere(Country::Germany)
+        Everywhere(Country::GERMANY)
     }
 
     /// A degree of latitude is about this many metres, near enough to check that a speed
@@ -292,10 +433,10 @@ mod tests {
         let root = Root::new(tmp.path());
         drain(&root, messages).await;
 
-        let derived = sessions(&root, Gap::default(), Lead::default())
+        let derived = sessions(&root, Gap::default(), Lead::default(), DateRange::ALL)
             .await
             .expect("derive sessions");
-        let outcome = write(&root, &derived, &germany())
+        let outcome = write(&root, &derived, &germany(), DateRange::ALL)
             .await
             .expect("write sessions");
         (root, outcome)
@@ -440,6 +581,40 @@ mod tests {
         );
     }
 
+    /// A run over one date writes each dataset's rows of that date: the session that
+    /// started the day before keeps the row the last run wrote, while its samples after
+    /// midnight are replaced.
+    #[tokio::test]
+    async fn a_range_writes_only_the_rows_dated_within_it() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let id = Uuid::from_u128(1);
+        let midnight = Utc.with_ymd_and_hms(2026, 7, 27, 0, 0, 0).unwrap();
+        let (root, _) = written(
+            &tmp,
+            &[
+                gps(id, midnight - Duration::minutes(1), 52.5, 13.4),
+                gps(id, midnight + Duration::minutes(1), 52.6, 13.4),
+            ],
+        )
+        .await;
+
+        let range = DateRange::on(midnight.date_naive());
+        let derived = sessions(&root, Gap::default(), Lead::default(), range)
+            .await
+            .expect("derive sessions");
+        let outcome = write(&root, &derived, &germany(), range)
+            .await
+            .expect("write the range");
+
+        assert_eq!(outcome.sessions, 0);
+        assert_eq!(outcome.session_partitions, Replaced::default());
+        assert_eq!(outcome.samples, 1);
+        assert_eq!(outcome.sample_partitions.written, 1);
+        assert_eq!(outcome.sample_partitions.removed, 0);
+        assert_eq!(session_rows(&root).await.len(), 1);
+        assert_eq!(rows(&root).await.len(), 2);
+    }
+
     #[tokio::test]
     async fn the_geometry_columns_hold_the_position_in_degrees_and_in_metres() {
         let tmp = tempfile::tempdir().expect("tempdir");
@@ -477,10 +652,10 @@ mod tests {
         ];
 
         let (root, first) = written(&tmp, &messages).await;
-        let derived = sessions(&root, Gap::default(), Lead::default())
+        let derived = sessions(&root, Gap::default(), Lead::default(), DateRange::ALL)
             .await
             .expect("derive sessions");
-        let second = write(&root, &derived, &germany())
+        let second = write(&root, &derived, &germany(), DateRange::ALL)
             .await
             .expect("write again");
 
@@ -695,10 +870,15 @@ mod tests {
 
         // At a threshold longer than the silence they are one session, starting on the
         // first date only.
-        let derived = sessions(&root, Gap::new(Duration::hours(2)), Lead::default())
-            .await
-            .expect("derive sessions");
-        let second = write(&root, &derived, &germany())
+        let derived = sessions(
+            &root,
+            Gap::new(Duration::hours(2)),
+            Lead::default(),
+            DateRange::ALL,
+        )
+        .await
+        .expect("derive sessions");
+        let second = write(&root, &derived, &germany(), DateRange::ALL)
             .await
             .expect("write again");
 
@@ -722,11 +902,13 @@ mod tests {
         let id = Uuid::from_u128(1);
         let root = Root::new(tmp.path());
         drain(&root, &[gps(id, at(9, 0, 0), 52.5, 13.4)]).await;
-        let derived = sessions(&root, Gap::default(), Lead::default())
+        let derived = sessions(&root, Gap::default(), Lead::default(), DateRange::ALL)
             .await
             .expect("derive sessions");
 
-        let outcome = write(&root, &derived, &Nowhere).await.expect("write");
+        let outcome = write(&root, &derived, &Nowhere, DateRange::ALL)
+            .await
+            .expect("write");
 
         assert_eq!(
             outcome,
@@ -769,10 +951,12 @@ mod tests {
         assert_eq!(first.sessions, 1);
         assert!(root.path().join("silver/session/country=DE").exists());
 
-        let derived = sessions(&root, Gap::default(), Lead::default())
+        let derived = sessions(&root, Gap::default(), Lead::default(), DateRange::ALL)
             .await
             .expect("derive sessions");
-        let outcome = write(&root, &derived, &Nowhere).await.expect("write again");
+        let outcome = write(&root, &derived, &Nowhere, DateRange::ALL)
+            .await
+            .expect("write again");
 
         assert_eq!(outcome.unplaceable, 1);
         assert_eq!(outcome.session_partitions.removed, 1);
@@ -790,7 +974,7 @@ mod tests {
     async fn no_sessions_write_nothing() {
         let tmp = tempfile::tempdir().expect("tempdir");
 
-        let outcome = write(&Root::new(tmp.path()), &[], &germany())
+        let outcome = write(&Root::new(tmp.path()), &[], &germany(), DateRange::ALL)
             .await
             .expect("write nothing");
 
@@ -0,0 +1,149 @@
+//! End-to-end integration test for the recorder's offline path: land samples in a store the
+//! way a server with no redis does (a [`Landing`] file a minute), run the actual `recorder`
+//! binary to drain them into bronze, then query the store and assert it holds what a queue
+//! drain would have written — and that what the server is still landing is left alone.
+//!
+//! Needs neither redis nor Docker.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+use chrono::Utc;
+use medallion::{Query, Root};
+use serde::Deserialize;
+use shared::{Accel, AccelReading, Gps, GpsReading, Message, V1Message};
+use telemetry::RawSample;
+use telemetry::landing::Landing;
+use uuid::Uuid;
+
+fn land(landing: &mut Landing, received_at: i64, message: &Message) {
+    let sample = RawSample::new(
+        received_at,
+        serde_json::to_string(message).expect("serialize"),
+    );
+    landing.append(&sample).expect("land sample");
+}
+
+fn gps_sample(id: Uuid, t: i64, lat: f64) -> Message {
+    Message::Version1(V1Message::Gps(GpsReading {
+        id,
+        t,
+        gps: Gps {
+            lat,
+            lon: -3.19,
+            alt: Some(80.0),
+            acc: 5.0,
+            speed: Some(31.4),
+            heading: Some(275.0),
+        },
+    }))
+}
+
+fn accel_sample(id: Uuid, t: i64) -> Message {
+    Message::Version1(V1Message::Acceleration(AccelReading {
+        id,
+        t,
+        accel: Accel {
+            rms: 0.42,
+            peak: 1.7,
+            n: 600,
+            x: None,
+            y: None,
+            z: None,
+        },
+    }))
+}
+
+/// One row of the rejected dataset, as the assertions need it.
+#[derive(Debug, Deserialize)]
+struct Rejected {
+    json: String,
+}
+
+#[tokio::test]
+async fn drain_landing_archives_finished_files_and_deletes_them() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut landing = Landing::in_store(dir.path());
+    let device = Uuid::from_u128(1);
+
+    // Two minutes long over, the first torn at the end by a server killed mid-write, and one
+    // the server is still landing into.
+    land(
+        &mut landing,
+        1_700_000_040_000,
+        &gps_sample(device, 1_700_000_040_000, 55.95),
+    );
+    land(
+        &mut landing,
+        1_700_000_041_000,
+        &gps_sample(device, 1_700_000_041_000, 55.96),
+    );
+    OpenOptions::new()
+        .append(true)
+        .open(landing.dir().join("20231114T2214Z.jsonl"))
+        .and_then(|mut segment| segment.write_all(b"{\"received_at\":17"))
+        .expect("tear the first segment");
+    land(
+        &mut landing,
+        1_700_000_100_000,
+        &accel_sample(device, 1_700_000_100_000),
+    );
+    let now = Utc::now().timestamp_millis();
+    land(&mut landing, now, &accel_sample(device, now));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_recorder"))
+        .args(["drain-landing", "--medallion-root"])
+        .arg(dir.path())
+        .env_remove("LOOKOUT_REDIS_URL")
+        .status()
+        .expect("run recorder drain-landing");
+    assert!(status.success(), "recorder exited with {status}");
+
+    // The finished files are written and gone, nothing is left taken, and the current
+    // minute's stays where the server is appending to it.
+    let consumer = landing.consumer("recorder");
+    assert_eq!(consumer.recover().expect("recover"), 0);
+    let left = std::fs::read_dir(landing.dir())
+        .expect("read landing")
+        .map(|entry| entry.expect("entry").path())
+        .filter(|path| path.is_file())
+        .count();
+    assert_eq!(left, 1, "only the segment still being landed into is left");
+
+    let query = Query::new(Root::new(dir.path()));
+    for dataset in [
+        model::RAW_SAMPLE,
+        model::GPS_READING,
+        model::ACCEL_READING,
+        model::REJECTED_SAMPLE,
+    ] {
+        query
+            .register(dataset, dataset.name)
+            .await
+            .expect("register dataset");
+    }
+    let mut counts = Vec::new();
+    for dataset in [model::RAW_SAMPLE, model::GPS_READING, model::ACCEL_READING] {
+        counts.push(
+            query
+                .count(&format!("SELECT COUNT(*) AS count FROM {}", dataset.name))
+                .await
+                .expect("count"),
+        );
+    }
+    assert_eq!(
+        counts,
+        vec![4, 2, 1],
+        "one lossless row per landed line, and the readings interpreted from them"
+    );
+    let rejected: Vec<Rejected> = query
+        .rows(&format!("SELECT json FROM {}", model::REJECTED_SAMPLE.name))
+        .await
+        .expect("rejected");
+    assert_eq!(
+        rejected.iter().map(|r| r.json.as_str()).collect::<Vec<_>>(),
+        vec!["{\"received_at\":17"],
+        "the torn line is kept as a payload that did not parse"
+    );
+}
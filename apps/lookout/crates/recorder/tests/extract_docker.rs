@@ -1,7 +1,10 @@
 //! End-to-end integration test for the recorder's extract path: prefill a real redis
 //! (a testcontainer) the way the server does (`LPUSH` of sample JSON), run the actual
 //! `recorder` binary to drain it into a medallion store, then query the store and assert
-//! it holds the lossless raw rows plus the readings interpreted from them.
+//! it holds the lossless raw rows plus the readings interpreted from them. A `follow` run
+//! is stopped the way a service manager stops it, and must write the batch it holds first.
+//! A frame the server dead-lettered is archived by a drain as rejected, where a `reparse`
+//! — which needs no redis — finds it.
 //!
 //! Requires Docker; the `_docker`-suffixed name is skipped by the no-docker profile.
 
@@ -12,14 +15,15 @@ use medallion::{Query, Root};
 use redis::aio::MultiplexedConnection;
 use serde::Deserialize;
 use shared::{Accel, AccelReading, Gps, GpsReading, Message, V1Message};
-use telemetry::{QUEUE_KEY, RawSample};
+use telemetry::{Consumer, DEAD_LETTER_KEY, QUEUE_KEY, RawSample};
 use testcontainers::runners::AsyncRunner;
 use testcontainers::{ContainerAsync, ImageExt};
 use testcontainers_modules::redis::{REDIS_PORT, Redis};
 use uuid::Uuid;
 
 async fn start_redis() -> (ContainerAsync<Redis>, String) {
-    // Match the queue test's pin: a fractional BRPOP timeout needs Redis >= 6.0.
+    // Match the queue test's pin: the drain takes samples with BLMOVE, which needs
+    // Redis >= 6.2.
     let container = Redis::default()
         .with_tag("7-alpine")
         .start()
@@ -54,14 +58,28 @@ async fn wait_ready(url: &str) -> MultiplexedConnection {
     }
 }
 
+async fn len(conn: &mut MultiplexedConnection, key: &str) -> i64 {
+    redis::cmd("LLEN")
+        .arg(key)
+        .query_async(conn)
+        .await
+        .expect("llen")
+}
+
 /// LPUSH a message the way the server's `RedisSink` does: as a RawSample envelope
 /// (payload + received_at).
 async fn lpush(conn: &mut MultiplexedConnection, message: &Message) {
     let payload = serde_json::to_string(message).expect("serialize");
+    lpush_payload(conn, QUEUE_KEY, &payload).await;
+}
+
+/// LPUSH `payload` onto `key` in its RawSample envelope, whether or not it parses — as the
+/// server's sinks dead-letter a frame.
+async fn lpush_payload(conn: &mut MultiplexedConnection, key: &str, payload: &str) {
     let item = serde_json::to_string(&RawSample::new(1_700_000_050_000, payload))
         .expect("serialize envelope");
     let _: i64 = redis::cmd("LPUSH")
-        .arg(QUEUE_KEY)
+        .arg(key)
         .arg(item)
         .query_async(conn)
         .await
@@ -128,6 +146,16 @@ async fn extract_queue_to_store_docker() {
         .expect("run recorder");
     assert!(status.success(), "recorder exited with {status}");
 
+    // Everything was taken off the queue, and acknowledged once written, so neither the
+    // queue nor the drain's processing list holds anything.
+    for key in [QUEUE_KEY, Consumer::new("recorder").processing_key()] {
+        assert_eq!(
+            len(&mut conn, key).await,
+            0,
+            "{key} should be empty after a drain"
+        );
+    }
+
     // Query the store and assert it holds the lossless payloads plus the readings
     // interpreted from them.
     let query = Query::new(Root::new(dir.path()));
@@ -164,3 +192,205 @@ async fn extract_queue_to_store_docker() {
         vec![55.95, 55.96]
     );
 }
+
+/// One row of the rejected dataset, as the assertions need it.
+#[derive(Debug, Deserialize)]
+struct Rejected {
+    json: String,
+}
+
+#[tokio::test]
+async fn drain_archives_dead_letters_as_rejected_for_reparse_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    lpush(
+        &mut conn,
+        &gps_sample(Uuid::from_u128(1), 1_700_000_000_003, 55.95),
+    )
+    .await;
+    lpush_payload(&mut conn, DEAD_LETTER_KEY, "not-a-sample").await;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let status = Command::new(env!("CARGO_BIN_EXE_recorder"))
+        .args(["drain", "--medallion-root"])
+        .arg(dir.path())
+        .env("LOOKOUT_REDIS_URL", &url)
+        .status()
+        .expect("run recorder");
+    assert!(status.success(), "recorder exited with {status}");
+
+    for key in [
+        QUEUE_KEY,
+        DEAD_LETTER_KEY,
+        Consumer::dead_letters("recorder").processing_key(),
+    ] {
+        assert_eq!(
+            len(&mut conn, key).await,
+            0,
+            "{key} should be empty after a drain"
+        );
+    }
+
+    // Nothing the parser rejects today reads tomorrow without a fix, so a reparse finds the
+    // dead letter and leaves it rejected — and runs with no redis to hand.
+    let status = Command::new(env!("CARGO_BIN_EXE_recorder"))
+        .args(["reparse", "--medallion-root"])
+        .arg(dir.path())
+        .env_remove("LOOKOUT_REDIS_URL")
+        .status()
+        .expect("run recorder reparse");
+    assert!(status.success(), "recorder reparse exited with {status}");
+
+    let query = Query::new(Root::new(dir.path()));
+    for dataset in [model::RAW_SAMPLE, model::REJECTED_SAMPLE] {
+        query
+            .register(dataset, dataset.name)
+            .await
+            .expect("register dataset");
+    }
+    assert_eq!(
+        query
+            .count(&format!(
+                "SELECT COUNT(*) AS count FROM {}",
+                model::RAW_SAMPLE.name
+            ))
+            .await
+            .expect("count"),
+        2,
+        "the dead letter is archived raw like any payload"
+    );
+    let rejected: Vec<Rejected> = query
+        .rows(&format!("SELECT json FROM {}", model::REJECTED_SAMPLE.name))
+        .await
+        .expect("rejected");
+    assert_eq!(
+        rejected.iter().map(|r| r.json.as_str()).collect::<Vec<_>>(),
+        vec!["not-a-sample"]
+    );
+    assert!(
+        !dir.path()
+            .join("bronze")
+            .join(model::REPARSED_SAMPLE.name)
+            .exists(),
+        "nothing was recovered"
+    );
+}
+
+/// An item that is not even the server's envelope is archived verbatim and acknowledged,
+/// rather than left on a processing list for every later drain to recover again.
+#[tokio::test]
+async fn a_drain_archives_and_acknowledges_items_it_cannot_unwrap_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    for (key, garbage) in [
+        (QUEUE_KEY, "queued garbage"),
+        (DEAD_LETTER_KEY, "dead garbage"),
+    ] {
+        let _: i64 = redis::cmd("LPUSH")
+            .arg(key)
+            .arg(garbage)
+            .query_async(&mut conn)
+            .await
+            .expect("lpush");
+    }
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let status = Command::new(env!("CARGO_BIN_EXE_recorder"))
+        .args(["drain", "--medallion-root"])
+        .arg(dir.path())
+        .env("LOOKOUT_REDIS_URL", &url)
+        .status()
+        .expect("run recorder");
+    assert!(status.success(), "recorder exited with {status}");
+
+    for key in [
+        QUEUE_KEY,
+        Consumer::new("recorder").processing_key(),
+        DEAD_LETTER_KEY,
+        Consumer::dead_letters("recorder").processing_key(),
+    ] {
+        assert_eq!(
+            len(&mut conn, key).await,
+            0,
+            "{key} should be empty after one drain"
+        );
+    }
+
+    let query = Query::new(Root::new(dir.path()));
+    query
+        .register(model::REJECTED_SAMPLE, model::REJECTED_SAMPLE.name)
+        .await
+        .expect("register dataset");
+    let rejected: Vec<Rejected> = query
+        .rows(&format!(
+            "SELECT json FROM {} ORDER BY json",
+            model::REJECTED_SAMPLE.name
+        ))
+        .await
+        .expect("rejected");
+    assert_eq!(
+        rejected.iter().map(|r| r.json.as_str()).collect::<Vec<_>>(),
+        vec!["dead garbage", "queued garbage"]
+    );
+}
+
+#[tokio::test]
+async fn follow_writes_the_batch_in_hand_when_terminated_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    let device = Uuid::from_u128(2);
+    for t in 0..3 {
+        lpush(&mut conn, &accel_sample(device, 1_700_000_000_000 + t)).await;
+    }
+
+    // A batch that is neither full nor old enough to be written before the follow is
+    // stopped, so only the stop writes it.
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut follow = Command::new(env!("CARGO_BIN_EXE_recorder"))
+        .args(["follow", "--batch-size", "100", "--batch-secs", "600"])
+        .arg("--medallion-root")
+        .arg(dir.path())
+        .env("LOOKOUT_REDIS_URL", &url)
+        .spawn()
+        .expect("start recorder");
+
+    // Taken, so on the processing list, but held in the batch unwritten.
+    let processing = Consumer::new("recorder");
+    let deadline = std::time::Instant::now() + Duration::from_secs(30);
+    while len(&mut conn, processing.processing_key()).await < 3 {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "follow took nothing in 30s"
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(len(&mut conn, QUEUE_KEY).await, 0);
+
+    let killed = Command::new("kill")
+        .arg("-TERM")
+        .arg(follow.id().to_string())
+        .status()
+        .expect("send SIGTERM");
+    assert!(killed.success());
+    let status = follow.wait().expect("wait for recorder");
+    assert!(status.success(), "recorder exited with {status}");
+
+    assert_eq!(
+        len(&mut conn, processing.processing_key()).await,
+        0,
+        "the batch written on the way out is acknowledged"
+    );
+    let query = Query::new(Root::new(dir.path()));
+    query
+        .register(model::RAW_SAMPLE, model::RAW_SAMPLE.name)
+        .await
+        .expect("register dataset");
+    let count = query
+        .count(&format!(
+            "SELECT COUNT(*) AS count FROM {}",
+            model::RAW_SAMPLE.name
+        ))
+        .await
+        .expect("count");
+    assert_eq!(count, 3);
+}
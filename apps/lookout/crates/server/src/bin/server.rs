@@ -1,8 +1,11 @@
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 
-use server::queue::RedisSink;
+use server::queue::{FileSink, RedisSink, SampleSink, StreamSink};
 use server::{AppState, build_app};
+use telemetry::QueueKind;
+use telemetry::landing::Landing;
 
 fn static_dir() -> String {
     std::env::var("LOOKOUT_STATIC_DIR")
@@ -26,11 +29,20 @@ async fn main() {
         .install_default()
         .expect("install rustls crypto provider");
 
+    // Checked before connecting: a misnamed queue kind would otherwise push where no
+    // reader looks.
+    let kind = match QueueKind::from_env() {
+        Ok(kind) => kind,
+        Err(err) => {
+            eprintln!("FATAL: {err}");
+            std::process::exit(1);
+        }
+    };
     let sink = match std::env::var("LOOKOUT_REDIS_URL") {
-        Ok(url) => match RedisSink::connect(&url).await {
+        Ok(url) => match connect(kind, &url).await {
             Ok(sink) => {
-                tracing::info!("connected to telemetry redis");
-                Some(Arc::new(sink) as Arc<dyn server::queue::SampleSink>)
+                tracing::info!(%kind, "connected to telemetry redis");
+                Some(sink)
             }
             Err(err) => {
                 // A configured-but-unreachable redis would silently drop all
@@ -41,10 +53,23 @@ async fn main() {
                 std::process::exit(1);
             }
         },
-        Err(_) => {
-            tracing::warn!("LOOKOUT_REDIS_URL unset; received samples will be logged only");
-            None
-        }
+        Err(_) => match std::env::var("LOOKOUT_LANDING_ROOT") {
+            Ok(root) => {
+                // Offline: land samples in the store for the recorder to drain later.
+                let landing = Landing::in_store(Path::new(&root));
+                tracing::info!(
+                    dir = %landing.dir().display(),
+                    "LOOKOUT_REDIS_URL unset; landing samples as files"
+                );
+                Some(Arc::new(FileSink::new(landing)) as Arc<dyn SampleSink>)
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "LOOKOUT_REDIS_URL and LOOKOUT_LANDING_ROOT unset; received samples will be logged only"
+                );
+                None
+            }
+        },
     };
 
     let app = build_app(AppState { sink }, static_dir());
@@ -58,3 +83,11 @@ async fn main() {
     tracing::info!("listening on http://{addr}");
     axum::serve(listener, app).await.unwrap();
 }
+
+/// The sink pushing onto the queue as `kind` keeps it.
+async fn connect(kind: QueueKind, url: &str) -> Result<Arc<dyn SampleSink>, redis::RedisError> {
+    Ok(match kind {
+        QueueKind::List => Arc::new(RedisSink::connect(url).await?),
+        QueueKind::Stream => Arc::new(StreamSink::connect(url).await?),
+    })
+}
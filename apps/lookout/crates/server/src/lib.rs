@@ -83,21 +83,16 @@ enum Ingest {
     Retry,
 }
 
-/// Validate an incoming message and, if a sink is configured, enqueue it. The queue
-/// item carries the verbatim payload plus `received_at`, stamped here at handling time
-/// so queue latency doesn't distort it.
+/// Validate an incoming message and, if a sink is configured, enqueue it — or, if it
+/// fails to parse, [`reject`] it. The queue item carries the verbatim payload plus
+/// `received_at`, stamped here at handling time so queue latency doesn't distort it.
 async fn handle_sample(state: &AppState, text: &str) -> Ingest {
+    let sample = RawSample::new(received_at_millis(), text);
     let message: TelemetryMessage = match serde_json::from_str(text) {
         Ok(message) => message,
-        Err(err) => {
-            // Re-sending won't fix malformed JSON, so accept (drop) it rather than
-            // blocking the client's outbox behind a message that can never succeed.
-            tracing::warn!(%err, %text, "discarding malformed sample");
-            return Ingest::Accepted;
-        }
+        Err(err) => return reject(state, &sample, &err).await,
     };
 
-    let sample = RawSample::new(received_at_millis(), text);
     match &state.sink {
         Some(sink) => match sink.push(&sample).await {
             Ok(depth) => {
@@ -116,6 +111,32 @@ async fn handle_sample(state: &AppState, text: &str) -> Ingest {
     }
 }
 
+/// Keep a message that failed to parse on the sink's dead-letter list.
+///
+/// Re-sending won't fix malformed JSON, so once it is kept it is accepted rather than
+/// blocking the client's outbox behind a message that can never succeed. It is kept
+/// because the parser, rather than the device, may be what is wrong: a protocol bug would
+/// otherwise silently lose every message it touched. A failure to keep it is retried as a
+/// failure to queue is; with no sink configured it is dropped, as every sample is.
+async fn reject(state: &AppState, sample: &RawSample, err: &serde_json::Error) -> Ingest {
+    match &state.sink {
+        Some(sink) => match sink.reject(sample).await {
+            Ok(held) => {
+                tracing::warn!(%err, text = sample.json(), held, "dead-lettered malformed sample");
+                Ingest::Accepted
+            }
+            Err(push_err) => {
+                tracing::error!(%push_err, %err, "failed to dead-letter malformed sample");
+                Ingest::Retry
+            }
+        },
+        None => {
+            tracing::warn!(%err, text = sample.json(), "discarding malformed sample");
+            Ingest::Accepted
+        }
+    }
+}
+
 /// Wall-clock time now, as epoch milliseconds — a server-stamped counterpart to the
 /// device-stamped `t`. A backwards clock (pre-1970) saturates to 0 rather than
 /// panicking on a single sample.
@@ -2,13 +2,27 @@
 //! port the handler pushes to, so tests can swap a recording sink for redis and
 //! drive the real handler without a container. The production sink (`RedisSink`)
 //! `LPUSH`es onto the shared queue; connection + key live in the `telemetry` crate.
+//! Where the queue is kept as a stream instead (`LOOKOUT_QUEUE=stream`), `StreamSink`
+//! `XADD`s onto it, so that every reader's consumer group sees each sample.
+//!
+//! Either sink keeps the frames the handler could not parse on the dead-letter list
+//! instead, trimmed to its length as it is added to, for the recorder to archive apart
+//! from the samples — see [`telemetry::DEAD_LETTER_KEY`].
+//!
+//! With no redis to reach at all, `FileSink` lands samples as files in the store instead —
+//! see [`telemetry::landing`] — for the recorder to drain into bronze later.
+
+use std::sync::{Arc, Mutex, PoisonError};
 
 use async_trait::async_trait;
 use redis::RedisError;
 use redis::aio::MultiplexedConnection;
-use telemetry::RawSample;
+use telemetry::landing::{Landing, LandingError};
+use telemetry::{DEAD_LETTER_LENGTH, RawSample};
 
-pub use telemetry::QUEUE_KEY;
+use telemetry::stream::SAMPLE_FIELD;
+pub use telemetry::stream::STREAM_KEY;
+pub use telemetry::{DEAD_LETTER_KEY, QUEUE_KEY};
 
 /// Failure enqueueing a sample.
 #[derive(Debug, thiserror::Error)]
@@ -17,6 +31,10 @@ pub enum PushError {
     Serialize(#[from] serde_json::Error),
     #[error("redis error: {0}")]
     Redis(#[from] RedisError),
+    #[error("landing error: {0}")]
+    Landing(#[from] LandingError),
+    #[error("landing append did not finish: {0}")]
+    Append(#[from] tokio::task::JoinError),
 }
 
 /// A destination the websocket handler enqueues received samples onto. The sink
@@ -26,6 +44,43 @@ pub enum PushError {
 pub trait SampleSink: Send + Sync {
     /// Enqueue a sample, returning the resulting queue depth.
     async fn push(&self, sample: &RawSample) -> Result<i64, PushError>;
+
+    /// Keep a frame that did not parse as a sample, returning how many the dead-letter list
+    /// holds. Re-sending cannot fix it, but a parser that was wrong about it can be.
+    async fn reject(&self, sample: &RawSample) -> Result<i64, PushError>;
+}
+
+/// `LPUSH` `sample` onto the dead-letter list and trim the list to [`DEAD_LETTER_LENGTH`],
+/// in one round trip, returning its length. The same list whichever way the queue is kept.
+///
+/// What the trim cuts off is the oldest frames, gone before the recorder archived them, so
+/// each cut is logged with how many went rather than made silently.
+async fn push_dead_letter(
+    mut conn: MultiplexedConnection,
+    sample: &RawSample,
+) -> Result<i64, PushError> {
+    let item = serde_json::to_string(sample)?;
+    let (length,): (i64,) = redis::pipe()
+        .cmd("LPUSH")
+        .arg(DEAD_LETTER_KEY)
+        .arg(item)
+        .cmd("LTRIM")
+        .arg(DEAD_LETTER_KEY)
+        .arg(0)
+        .arg(DEAD_LETTER_LENGTH - 1)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    // The length is `LPUSH`'s, from before the trim: past the cap is what the trim cut.
+    let cap = DEAD_LETTER_LENGTH as i64;
+    if length > cap {
+        tracing::warn!(
+            dropped = length - cap,
+            cap,
+            "dead-letter list full: dropped its oldest frames unarchived"
+        );
+    }
+    Ok(length.min(cap))
 }
 
 /// A [`SampleSink`] backed by an upstash redis list.
@@ -57,4 +112,94 @@ impl SampleSink for RedisSink {
             .await?;
         Ok(depth)
     }
+
+    async fn reject(&self, sample: &RawSample) -> Result<i64, PushError> {
+        push_dead_letter(self.conn.clone(), sample).await
+    }
+}
+
+/// A [`SampleSink`] backed by a redis stream, added to without a cap: the recorder trims it to
+/// what it has still to archive — see [`telemetry::stream`].
+pub struct StreamSink {
+    conn: MultiplexedConnection,
+}
+
+impl StreamSink {
+    /// Connect to the telemetry redis, as [`RedisSink::connect`] does.
+    pub async fn connect(url: &str) -> Result<Self, RedisError> {
+        let conn = telemetry::connect(url).await?;
+        Ok(Self { conn })
+    }
+}
+
+#[async_trait]
+impl SampleSink for StreamSink {
+    /// The depth returned is the stream's length, which counts entries every group may
+    /// already have read: a stream is not emptied by reading it.
+    async fn push(&self, sample: &RawSample) -> Result<i64, PushError> {
+        let mut conn = self.conn.clone();
+        let item = serde_json::to_string(sample)?;
+        let (length,): (i64,) = redis::pipe()
+            .cmd("XADD")
+            .arg(STREAM_KEY)
+            .arg("*")
+            .arg(SAMPLE_FIELD)
+            .arg(item)
+            .ignore()
+            .cmd("XLEN")
+            .arg(STREAM_KEY)
+            .query_async(&mut conn)
+            .await?;
+        Ok(length)
+    }
+
+    async fn reject(&self, sample: &RawSample) -> Result<i64, PushError> {
+        push_dead_letter(self.conn.clone(), sample).await
+    }
+}
+
+/// A [`SampleSink`] appending to the landed telemetry in the store, for a server with no
+/// redis to reach.
+///
+/// A frame that did not parse is landed with the samples rather than apart: the recorder is
+/// the only reader of the landing directory, and keeps what it cannot interpret as rejected
+/// whichever way it arrived.
+pub struct FileSink {
+    /// Locked so appends from concurrent sockets land one after another.
+    landing: Arc<Mutex<Landing>>,
+}
+
+impl FileSink {
+    pub fn new(landing: Landing) -> Self {
+        Self {
+            landing: Arc::new(Mutex::new(landing)),
+        }
+    }
+
+    /// Append `sample` off the async runtime, returning how many samples have been landed.
+    async fn append(&self, sample: &RawSample) -> Result<i64, PushError> {
+        let landing = self.landing.clone();
+        let sample = sample.clone();
+        let landed = tokio::task::spawn_blocking(move || {
+            landing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .append(&sample)
+        })
+        .await??;
+        Ok(landed as i64)
+    }
+}
+
+#[async_trait]
+impl SampleSink for FileSink {
+    /// The depth returned is the number of samples landed since the server started, waiting
+    /// or not: finding how many wait would mean listing the directory at every sample.
+    async fn push(&self, sample: &RawSample) -> Result<i64, PushError> {
+        self.append(sample).await
+    }
+
+    async fn reject(&self, sample: &RawSample) -> Result<i64, PushError> {
+        self.append(sample).await
+    }
 }
@@ -0,0 +1,37 @@
+//! Integration test for the file adapter (`FileSink`): samples pushed and frames rejected
+//! are landed in the store as the recorder's landing consumer reads them back — each
+//! envelope verbatim, in the order it arrived, in the segment of the minute it was received.
+
+use chrono::DateTime;
+use server::queue::{FileSink, SampleSink};
+use telemetry::RawSample;
+use telemetry::landing::Landing;
+
+#[tokio::test]
+async fn file_sink_lands_samples_and_rejects_for_the_recorder() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let sink = FileSink::new(Landing::in_store(tmp.path()));
+    let first = RawSample::new(1_700_000_040_000, "{\"v\":1}");
+    let rejected = RawSample::new(1_700_000_040_001, "not-a-sample");
+    let later = RawSample::new(1_700_000_100_000, "{\"v\":1}");
+
+    // push and reject both return how many samples have been landed.
+    assert_eq!(sink.push(&first).await.expect("push first"), 1);
+    assert_eq!(sink.reject(&rejected).await.expect("reject"), 2);
+    assert_eq!(sink.push(&later).await.expect("push later"), 3);
+
+    let consumer = Landing::in_store(tmp.path()).consumer("recorder");
+    let now = DateTime::from_timestamp_millis(1_700_001_000_000).expect("instant");
+    let segment = consumer
+        .take(now)
+        .expect("take")
+        .expect("the first minute's segment");
+    assert_eq!(segment.samples(), [first, rejected]);
+    assert!(segment.torn().is_empty());
+    consumer.acknowledge(segment).expect("acknowledge");
+    let segment = consumer
+        .take(now)
+        .expect("take")
+        .expect("the next minute's segment");
+    assert_eq!(segment.samples(), [later]);
+}
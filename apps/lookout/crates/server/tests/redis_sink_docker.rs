@@ -3,7 +3,9 @@
 //! A testcontainers redis is started and samples are pushed through `RedisSink`,
 //! then read back off the `lookout-telemetry` list the way the `recorder` cli will
 //! (tail-first, FIFO) — covering LPUSH ordering, queue depth, and JSON round-trip
-//! through redis.
+//! through redis. `StreamSink` is covered the same way, read back through a consumer
+//! group as the recorder reads a stream. Both keep rejected frames on the one dead-letter
+//! list, apart from the queue.
 //!
 //! Requires Docker; the `_docker`-suffixed name marks it for exclusion from
 //! no-docker test runs.
@@ -11,9 +13,10 @@
 use std::time::Duration;
 
 use redis::aio::MultiplexedConnection;
-use server::queue::{QUEUE_KEY, RedisSink, SampleSink};
+use server::queue::{DEAD_LETTER_KEY, QUEUE_KEY, RedisSink, STREAM_KEY, SampleSink, StreamSink};
 use shared::{Accel, AccelReading, Gps, GpsReading, Message, V1Message};
 use telemetry::RawSample;
+use telemetry::stream::{Group, Start};
 use testcontainers::ContainerAsync;
 use testcontainers::runners::AsyncRunner;
 use testcontainers_modules::redis::{REDIS_PORT, Redis};
@@ -106,7 +109,7 @@ async fn redis_sink_lpushes_samples_in_fifo_order_docker() {
         .expect("llen");
     assert_eq!(len, 2);
 
-    // The recorder drains with BRPOP (tail), and LPUSH prepends, so the tail is the
+    // The recorder drains from the tail, and LPUSH prepends, so the tail is the
     // oldest sample — draining yields insertion order (FIFO).
     let first = rpop_sample(&mut conn).await;
     let second = rpop_sample(&mut conn).await;
@@ -114,6 +117,99 @@ async fn redis_sink_lpushes_samples_in_fifo_order_docker() {
     assert_eq!(second.parse().expect("parse gps"), gps_sample);
 }
 
+#[tokio::test]
+async fn stream_sink_adds_samples_a_group_reads_in_order_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_for_redis(&url).await;
+
+    let sink = StreamSink::connect(&url).await.expect("connect sink");
+    let first = wrap(&Message::Version1(V1Message::Acceleration(AccelReading {
+        id: Uuid::from_u128(1),
+        t: 1_700_000_000_001,
+        accel: Accel {
+            rms: 0.42,
+            peak: 1.7,
+            n: 600,
+            x: None,
+            y: None,
+            z: None,
+        },
+    })));
+    let second = wrap(&Message::Version1(V1Message::Acceleration(AccelReading {
+        id: Uuid::from_u128(2),
+        t: 1_700_000_000_002,
+        accel: Accel {
+            rms: 0.5,
+            peak: 2.0,
+            n: 600,
+            x: None,
+            y: None,
+            z: None,
+        },
+    })));
+
+    // push returns the stream's length.
+    assert_eq!(sink.push(&first).await.expect("push first"), 1);
+    assert_eq!(sink.push(&second).await.expect("push second"), 2);
+
+    // A group made after the pushes, from the oldest entry, reads both in the order added;
+    // reading leaves the stream as it was.
+    let mut group = Group::new("recorder", "recorder", Start::Oldest);
+    group.join(&mut conn).await.expect("join");
+    let read: Vec<RawSample> = group
+        .take_new(&mut conn, 10)
+        .await
+        .expect("read")
+        .iter()
+        .map(|taken| taken.sample().expect("envelope"))
+        .collect();
+    assert_eq!(read, vec![first, second]);
+    let len: i64 = redis::cmd("XLEN")
+        .arg(STREAM_KEY)
+        .query_async(&mut conn)
+        .await
+        .expect("xlen");
+    assert_eq!(len, 2);
+}
+
+#[tokio::test]
+async fn either_sink_keeps_rejected_frames_on_the_dead_letter_list_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_for_redis(&url).await;
+
+    let list = RedisSink::connect(&url).await.expect("connect list sink");
+    let stream = StreamSink::connect(&url)
+        .await
+        .expect("connect stream sink");
+    let first = RawSample::new(1_700_000_050_000, "not-a-sample");
+    let second = RawSample::new(1_700_000_050_001, "{\"v\":99}");
+
+    // reject returns how many the dead-letter list holds, whichever sink added to it.
+    assert_eq!(list.reject(&first).await.expect("reject first"), 1);
+    assert_eq!(stream.reject(&second).await.expect("reject second"), 2);
+
+    // Held oldest at the tail, where a consumer takes from, and nothing was queued.
+    let items: Vec<String> = redis::cmd("LRANGE")
+        .arg(DEAD_LETTER_KEY)
+        .arg(0)
+        .arg(-1)
+        .query_async(&mut conn)
+        .await
+        .expect("lrange");
+    let held: Vec<RawSample> = items
+        .iter()
+        .map(|item| serde_json::from_str(item).expect("deserialize envelope"))
+        .collect();
+    assert_eq!(held, vec![second, first]);
+    let queued: i64 = redis::cmd("EXISTS")
+        .arg(QUEUE_KEY)
+        .arg(STREAM_KEY)
+        .query_async(&mut conn)
+        .await
+        .expect("exists");
+    assert_eq!(queued, 0);
+}
+
 fn wrap(message: &Message) -> RawSample {
     RawSample::new(
         1_700_000_050_000,
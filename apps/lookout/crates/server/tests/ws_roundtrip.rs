@@ -17,9 +17,11 @@ use tokio::time::{Instant, sleep, timeout};
 use tokio_tungstenite::tungstenite::Message as WsMessage;
 use uuid::Uuid;
 
-/// A [`SampleSink`] that records pushed queue items in memory for assertions.
+/// A [`SampleSink`] that records pushed queue items, and rejected ones apart from them, in
+/// memory for assertions.
 struct RecordingSink {
     samples: Arc<Mutex<Vec<RawSample>>>,
+    rejected: Arc<Mutex<Vec<RawSample>>>,
 }
 
 #[async_trait::async_trait]
@@ -29,6 +31,18 @@ impl SampleSink for RecordingSink {
         samples.push(sample.clone());
         Ok(samples.len() as i64)
     }
+
+    async fn reject(&self, sample: &RawSample) -> Result<i64, PushError> {
+        let mut rejected = self.rejected.lock().expect("lock");
+        rejected.push(sample.clone());
+        Ok(rejected.len() as i64)
+    }
+}
+
+/// What the recording sink was given: samples queued, and samples rejected.
+struct Recorded {
+    samples: Arc<Mutex<Vec<RawSample>>>,
+    rejected: Arc<Mutex<Vec<RawSample>>>,
 }
 
 fn static_dir() -> String {
@@ -36,13 +50,17 @@ fn static_dir() -> String {
 }
 
 /// Spawn the real router with a recording sink; returns its address and the shared
-/// buffer of pushed queue items.
-async fn spawn_app() -> (SocketAddr, Arc<Mutex<Vec<RawSample>>>) {
-    let recorded = Arc::new(Mutex::new(Vec::new()));
+/// buffers of pushed and rejected queue items.
+async fn spawn_app() -> (SocketAddr, Recorded) {
+    let recorded = Recorded {
+        samples: Arc::new(Mutex::new(Vec::new())),
+        rejected: Arc::new(Mutex::new(Vec::new())),
+    };
     let app = build_app(
         AppState {
             sink: Some(Arc::new(RecordingSink {
-                samples: Arc::clone(&recorded),
+                samples: Arc::clone(&recorded.samples),
+                rejected: Arc::clone(&recorded.rejected),
             })),
         },
         static_dir(),
@@ -58,10 +76,10 @@ async fn spawn_app() -> (SocketAddr, Arc<Mutex<Vec<RawSample>>>) {
 
 /// Both protocol versions must pass ingest: a historical v0 payload (no `v`, sensor
 /// key inferred) and a modern v1 message (tagged), while genuinely malformed JSON is
-/// dropped. The three are sent in order and the handler processes them in order, so
-/// the two valid ones land in the sink and the malformed one doesn't.
+/// dead-lettered. The three are sent in order and the handler processes them in order,
+/// so the two valid ones land in the queue and the malformed one is rejected, verbatim.
 #[tokio::test]
-async fn both_versions_enqueue_and_malformed_is_dropped() {
+async fn both_versions_enqueue_and_malformed_is_dead_lettered() {
     let (addr, recorded) = spawn_app().await;
 
     // A historical v0 payload: no `v`, variant inferred from the `gps` key.
@@ -97,7 +115,7 @@ async fn both_versions_enqueue_and_malformed_is_dropped() {
         .await
         .expect("connect");
     // Malformed first so that, once both valid ones are recorded, we know the
-    // malformed one was already processed (and dropped) by the in-order handler.
+    // malformed one was already processed (and rejected) by the in-order handler.
     ws.send(WsMessage::Text("not-a-sample".into()))
         .await
         .expect("send malformed");
@@ -112,9 +130,12 @@ async fn both_versions_enqueue_and_malformed_is_dropped() {
     let deadline = Instant::now() + Duration::from_secs(5);
     loop {
         {
-            let samples = recorded.lock().expect("lock");
+            let samples = recorded.samples.lock().expect("lock");
             if samples.len() >= 2 {
-                assert_eq!(samples.len(), 2, "malformed sample must be dropped");
+                assert_eq!(samples.len(), 2, "malformed sample must not be queued");
+                let rejected = recorded.rejected.lock().expect("lock");
+                assert_eq!(rejected.len(), 1, "malformed sample must be dead-lettered");
+                assert_eq!(rejected[0].json(), "not-a-sample");
                 assert_eq!(samples[0].parse().expect("parse v0"), v0_expected);
                 assert_eq!(samples[1].parse().expect("parse v1"), v1);
                 assert!(
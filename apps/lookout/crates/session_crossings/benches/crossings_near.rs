@@ -0,0 +1,116 @@
+//! How much reading the water crossings near a place saves over reading the whole country,
+//! over the German `water_crossing` dataset in the repo's own store.
+//!
+//! Run with `just bench-crossings-near`, after `just silver-water-crossings`. For each window
+//! it times the read `match_crossings` used to make — every crossing in the country, kept or
+//! not by their position — against the read by the `bbox` covering it makes now, best of a
+//! few runs each, and then shows what the query plan says was pruned for one of them: the
+//! row groups the covering's statistics ruled out, which were never opened.
+//!
+//! A plain binary rather than a harnessed benchmark: what is measured is a handful of queries
+//! against a real store, where a harness's thousands of iterations would measure the cache.
+
+use std::time::{Duration, Instant};
+
+use medallion::{COUNTRY, Country, GEOMETRY, Query, Root, covering_name};
+use serde::Deserialize;
+
+/// Runs per query; the fastest is reported, as the one least disturbed by anything else.
+const RUNS: usize = 5;
+
+/// Windows about ten kilometres across, `(name, (west, south), (east, north))` in lat/lon:
+/// city centres, which hold more water than most of the country, and a patch of the
+/// Lüneburg Heath, which holds little.
+const WINDOWS: [(&str, (f64, f64), (f64, f64)); 4] = [
+    ("berlin", (13.33, 52.48), (13.48, 52.56)),
+    ("hamburg", (9.92, 53.51), (10.07, 53.59)),
+    ("munich", (11.50, 48.10), (11.65, 48.18)),
+    ("heath", (10.00, 53.10), (10.15, 53.18)),
+];
+
+/// One row of `EXPLAIN ANALYZE`.
+#[derive(Debug, Deserialize)]
+struct Plan {
+    plan: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let root = Root::new(Root::default_path().expect("locate the medallion store"));
+    let query = Query::new(root);
+    let derived = query
+        .register_if_present(model::WATER_CROSSING, "water_crossing")
+        .await
+        .expect("register the water crossings");
+    if !derived {
+        println!("no water crossings to read: run `just silver-water-crossings` first");
+        return;
+    }
+
+    let country = Country::GERMANY;
+    let total = query
+        .count(&format!(
+            "SELECT COUNT(*) FROM water_crossing WHERE {COUNTRY} = '{country}'"
+        ))
+        .await
+        .expect("count the crossings");
+    println!("{total} crossings in {country}");
+
+    for (name, south_west, north_east) in WINDOWS {
+        let whole = format!(
+            "SELECT COUNT(*) FROM water_crossing
+             WHERE {COUNTRY} = '{country}'
+               AND ST_X({GEOMETRY}) BETWEEN {} AND {}
+               AND ST_Y({GEOMETRY}) BETWEEN {} AND {}",
+            south_west.0, north_east.0, south_west.1, north_east.1
+        );
+        let near = near(country, south_west, north_east);
+
+        let (found, scanned) = fastest(&query, &whole).await;
+        let (covered, pruned) = fastest(&query, &near).await;
+        assert_eq!(found, covered, "{name}: the two reads disagree");
+        println!(
+            "{name:>8}: {found:>5} crossings, {:>8.2} ms reading the country, {:>8.2} ms by the covering",
+            scanned.as_secs_f64() * 1000.0,
+            pruned.as_secs_f64() * 1000.0,
+        );
+    }
+
+    let (_, south_west, north_east) = WINDOWS[0];
+    let plan: Vec<Plan> = query
+        .rows(&format!(
+            "EXPLAIN ANALYZE {}",
+            near(country, south_west, north_east)
+        ))
+        .await
+        .expect("explain the read");
+    for line in plan.iter().flat_map(|row| row.plan.lines()) {
+        if line.contains("row_groups_pruned") {
+            println!("{}", line.trim());
+        }
+    }
+}
+
+/// Count the crossings in the window by their `bbox` covering, as `match_crossings` reads.
+fn near(country: Country, south_west: (f64, f64), north_east: (f64, f64)) -> String {
+    let bbox = covering_name(GEOMETRY);
+    format!(
+        "SELECT COUNT(*) FROM water_crossing
+         WHERE {COUNTRY} = '{country}'
+           AND {bbox}['xmax'] >= {} AND {bbox}['xmin'] <= {}
+           AND {bbox}['ymax'] >= {} AND {bbox}['ymin'] <= {}",
+        south_west.0, north_east.0, south_west.1, north_east.1
+    )
+}
+
+/// The count `sql` returns, and the fastest of [`RUNS`] runs of it.
+async fn fastest(query: &Query, sql: &str) -> (i64, Duration) {
+    let mut best = Duration::MAX;
+    let mut count = 0;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        count = query.count(sql).await.expect("run the query");
+        best = best.min(started.elapsed());
+    }
+    (count, best)
+}
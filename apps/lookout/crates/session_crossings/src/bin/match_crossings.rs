@@ -2,12 +2,18 @@
 //! recorded session passed, matched by how near the session's samples come to one.
 //!
 //! Reads the silver sessions and water crossings, so both have to have been derived. Every
-//! session is matched again, so a rerun replaces what the last one wrote.
+//! session is matched again, so a rerun replaces what the last one wrote; `--from`/`--to`
+//! narrow that to the passes made on those dates.
+//!
+//! Given no range, a run derives only what has changed since the last: the dates whose
+//! sessions or samples were derived again, or nothing at all if none were and no crossing or
+//! tuning changed either.
 
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
 use medallion::MedallionArgs;
+use medallion::lineage::Producer;
 use session_crossings::matching::Radius;
 use session_crossings::silver;
 
@@ -30,9 +36,32 @@ async fn main() {
         .init();
 
     let args = Args::parse();
-    let root = args.medallion.root().expect("locate the medallion store");
+    let root = args
+        .medallion
+        .root()
+        .expect("locate the medallion store")
+        .recording(
+            Producer::new(env!("CARGO_BIN_NAME")).parameter("match_radius_m", args.match_radius_m),
+        );
 
-    let outcome = silver::derive(&root, Radius::new(args.match_radius_m))
+    let range = args.medallion.range().expect("read the range to derive");
+    let range = match range.is_all() {
+        true => match silver::pending(&root)
+            .await
+            .expect("read what the dataset was derived from")
+        {
+            Some(range) => range,
+            None => {
+                tracing::info!(
+                    medallion_root = %root.path().display(),
+                    "nothing read has changed since the crossings each session passed were derived"
+                );
+                return;
+            }
+        },
+        false => range,
+    };
+    let outcome = silver::derive(&root, Radius::new(args.match_radius_m), range)
         .await
         .expect("derive the crossings each session passed");
 
@@ -44,6 +73,7 @@ async fn main() {
         partitions = outcome.partitions.written,
         partitions_removed = outcome.partitions.removed,
         match_radius_m = args.match_radius_m,
+        range = %range,
         medallion_root = %root.path().display(),
         "derived the crossings each session passed"
     );
@@ -94,6 +94,44 @@ pub fn passes(
     passed
 }
 
+/// The envelopes taking in every crossing any of `sessions` could pass: each session's
+/// envelope grown by the radius, as [`passes`] grows it, with those that overlap merged into
+/// one. No two of them overlap, and there are none where there are no sessions, and so
+/// nothing to reach.
+///
+/// This is what the crossings are read by, so a store skips the parts of a country no
+/// session went near before any distance is measured. They are kept apart rather than taken
+/// together as one envelope: two sessions at either end of a country would otherwise reach
+/// everything between them.
+pub fn reach(sessions: &[Session], radius: Radius) -> Vec<Rect<f64>> {
+    let mut reach: Vec<Rect<f64>> = Vec::new();
+    for session in sessions {
+        let mut envelope = grown(session.envelope, radius);
+        // What an envelope takes in can make it overlap another it did not before, so it
+        // takes in every one it overlaps until it overlaps none.
+        while let Some(overlapped) = reach.iter().position(|other| overlaps(other, &envelope)) {
+            let other = reach.swap_remove(overlapped);
+            envelope = Rect::new(
+                (
+                    envelope.min().x.min(other.min().x),
+                    envelope.min().y.min(other.min().y),
+                ),
+                (
+                    envelope.max().x.max(other.max().x),
+                    envelope.max().y.max(other.max().y),
+                ),
+            );
+        }
+        reach.push(envelope);
+    }
+    reach.sort_by(|a, b| {
+        (a.min().x, a.min().y)
+            .partial_cmp(&(b.min().x, b.min().y))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    reach
+}
+
 /// The crossings one session passed.
 fn passes_of(session: &Session, crossings: &[Crossing], radius: Radius) -> Vec<SessionCrossingRow> {
     let reachable = grown(session.envelope, radius);
@@ -143,6 +181,14 @@ fn grown(envelope: Rect<f64>, radius: Radius) -> Rect<f64> {
     Rect::new(south_west.0, north_east.0)
 }
 
+/// Whether two envelopes share any point, edges included.
+fn overlaps(a: &Rect<f64>, b: &Rect<f64>) -> bool {
+    a.min().x <= b.max().x
+        && b.min().x <= a.max().x
+        && a.min().y <= b.max().y
+        && b.min().y <= a.max().y
+}
+
 /// Whether `point` falls within `envelope`, edges included.
 ///
 /// `Rect`'s own containment excludes its edges, and a crossing exactly on the grown edge is
@@ -292,6 +338,54 @@ mod tests {
         assert_eq!(passed[0].samples_within, 1);
     }
 
+    fn within(reach: &[Rect<f64>], crossing: &Crossing) -> bool {
+        reach
+            .iter()
+            .any(|envelope| contains(envelope, crossing.lat_lon))
+    }
+
+    /// Every crossing a session passes is inside the reach, including one at the radius from
+    /// the session's far end — and nothing between two sessions far apart is.
+    #[test]
+    fn the_reach_takes_in_whatever_any_session_could_pass() {
+        let near = session(vec![sample(0, 0.0), sample(1, 500.0)]);
+        let far = session(vec![sample(0, 20_000.0)]);
+        let edge = crossing("edge", 20_090.0);
+
+        let reach = reach(&[near, far.clone()], Radius::new(100.0));
+
+        assert_eq!(reach.len(), 2);
+        assert_eq!(
+            passes(&[far], std::slice::from_ref(&edge), Radius::new(100.0)).len(),
+            1
+        );
+        assert!(within(&reach, &edge));
+        assert!(within(&reach, &crossing("start", -90.0)));
+        assert!(!within(&reach, &crossing("between", 10_000.0)));
+        assert!(!within(&reach, &crossing("beyond", 21_000.0)));
+        assert!(!within(&reach, &crossing("before", -1_000.0)));
+    }
+
+    /// Sessions whose reaches overlap are read as one envelope, including two that overlap
+    /// only through a third between them.
+    #[test]
+    fn overlapping_reaches_are_merged() {
+        let west = session(vec![sample(0, 0.0), sample(1, 1_000.0)]);
+        let east = session(vec![sample(0, 3_000.0), sample(1, 4_000.0)]);
+        let bridging = session(vec![sample(0, 1_050.0), sample(1, 2_950.0)]);
+
+        let reach = reach(&[west, east, bridging], Radius::new(100.0));
+
+        assert_eq!(reach.len(), 1);
+        assert!(within(&reach, &crossing("middle", 2_000.0)));
+        assert!(within(&reach, &crossing("ends", 4_090.0)));
+    }
+
+    #[test]
+    fn there_is_no_reach_without_sessions() {
+        assert!(reach(&[], Radius::default()).is_empty());
+    }
+
     #[test]
     fn rows_come_back_in_the_order_they_were_crossed() {
         let session = session(vec![sample(0, 0.0), sample(5, 1_000.0)]);
@@ -1,29 +1,39 @@
 //! Deriving the silver `session_crossing` dataset: which crossings each session passed.
 //!
 //! Both inputs are read a country at a time, because a distance is only a distance within one
-//! projected zone and the zone is chosen per country. The output carries no geometry — a match
-//! is a session, a crossing and an instant — so it is partitioned by the date it happened and
-//! by nothing else.
+//! projected zone and the zone is chosen per country. A run holds one country's sessions and
+//! samples at a time, and the passes found so far, and never the whole of silver. The output
+//! carries no geometry — a match is a session, a crossing and an instant — so it is
+//! partitioned by the date it happened and by nothing else.
 //!
 //! A run derives the whole dataset from the whole of silver, and replaces what it produces, so
-//! a partition it no longer produces rows for goes with it.
+//! a partition it no longer produces rows for goes with it. A run over a range of dates
+//! replaces the passes dated in it, and reads the samples of a day either side as well: a pass
+//! is dated by the sample nearest the crossing, which for a pass near midnight can fall on
+//! the next or the last date. [`pending`] is the range a run need cover: the dates whose
+//! sessions or samples have changed since the last run, or none at all.
 
 use std::collections::{HashMap, HashSet};
 
 use chrono::{DateTime, Utc};
 use geo_types::{Point, Rect};
-use medallion::{COUNTRY, Country, Query, Replaced, Root};
+use medallion::lineage::{self, LineageError, Pending};
+use medallion::{COUNTRY, Country, DateRange, GEOMETRY, Query, Replaced, Root, covering_name};
 use model::{Bbox, CrossingId, DeviceId, SessionCrossingRow, SessionId};
 use serde::Deserialize;
 
-use crate::matching::{Crossing, Radius, Sample, Session, passes};
+use crate::matching::{Crossing, Radius, Sample, Session, passes, reach};
+
+/// How many days either side of a range the samples are read from, so a pass near either
+/// end is dated by the same nearest sample a run over everything would find.
+const MARGIN_DAYS: u64 = 1;
 
 /// What one run derived.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct MatchOutcome {
     /// Sessions read, over every country.
     pub sessions: usize,
-    /// Crossings read.
+    /// Crossings read: those within reach of some session, since no other can be passed.
     pub crossings: usize,
     /// Sessions that passed at least one crossing.
     pub sessions_matched: usize,
@@ -41,6 +51,8 @@ pub enum CrossingError {
     Missing { dataset: &'static str },
     #[error("writing the dataset: {0}")]
     Write(#[from] medallion::TableError),
+    #[error("reading what the dataset was derived from: {0}")]
+    Lineage(#[from] LineageError),
 }
 
 /// One session as the store holds it: its identity and the envelope of its path.
@@ -72,29 +84,32 @@ struct StoredCrossing {
     lat: f64,
 }
 
-/// Derive the crossings every session passed, and write them.
+/// Derive the crossings every session passed in `range`, and write them.
+///
+/// Only the samples around the range are read. Every session that started by the end of
+/// them is, since a session passing a crossing in range can have started any time before;
+/// the crossings are read wherever the sessions reach, being placed rather than dated.
 ///
 /// A country the store holds no sessions or no crossings for contributes nothing rather than
 /// failing: a store can legitimately hold sessions in a country no extract has covered yet.
-pub async fn derive(root: &Root, radius: Radius) -> Result<MatchOutcome, CrossingError> {
+pub async fn derive(
+    root: &Root,
+    radius: Radius,
+    range: DateRange,
+) -> Result<MatchOutcome, CrossingError> {
     let query = Query::new(root.clone());
-    for (dataset, table) in [
-        (model::SESSION, "session"),
-        (model::SESSION_SAMPLE, "session_sample"),
-        (model::WATER_CROSSING, "water_crossing"),
-    ] {
-        if !query.register_if_present(dataset, table).await? {
-            return Err(CrossingError::Missing {
-                dataset: dataset.name,
-            });
-        }
-    }
+    register_inputs(&query, range).await?;
 
     let mut outcome = MatchOutcome::default();
     let mut passed: Vec<SessionCrossingRow> = Vec::new();
-    for country in Country::ALL {
+    for country in Country::all() {
         let sessions = sessions_in(&query, country).await?;
-        let crossings = crossings_in(&query, country).await?;
+        let reach = reach(&sessions, radius);
+        let crossings = if reach.is_empty() {
+            Vec::new()
+        } else {
+            crossings_in(&query, country, &reach).await?
+        };
         outcome.sessions += sessions.len();
         outcome.crossings += crossings.len();
 
@@ -108,11 +123,51 @@ pub async fn derive(root: &Root, radius: Radius) -> Result<MatchOutcome, Crossin
     }
 
     passed.sort_by(|a, b| (a.crossed_at, &a.crossing_id).cmp(&(b.crossed_at, &b.crossing_id)));
-    outcome.passes = passed.len();
-    outcome.partitions = medallion::write_rows(root, &passed).await?.partitions;
+    let written = medallion::write_rows_within(root, &passed, range).await?;
+    outcome.passes = written.rows;
+    outcome.partitions = written.partitions;
     Ok(outcome)
 }
 
+/// The dates a run over every date has to derive again, or `None` where no session, sample
+/// or crossing has changed since the last run — see [`medallion::lineage::pending`].
+///
+/// A pass is dated by the sample nearest its crossing, and a session that changed changed
+/// its samples too, so the dates to derive again are the samples' own.
+pub async fn pending(root: &Root) -> Result<Option<DateRange>, CrossingError> {
+    register_inputs(&Query::new(root.clone()), DateRange::ALL).await?;
+    Ok(
+        match lineage::pending(root, &[model::SESSION_CROSSING.info()]).await? {
+            Pending::Nothing => None,
+            Pending::Dates(changed) => Some(changed),
+            Pending::Everything => Some(DateRange::ALL),
+        },
+    )
+}
+
+/// Register what a run over `range` reads: the sessions and samples around it, and every
+/// crossing.
+async fn register_inputs(query: &Query, range: DateRange) -> Result<(), CrossingError> {
+    let around = range
+        .starting_earlier(MARGIN_DAYS)
+        .ending_later(MARGIN_DAYS);
+    for (dataset, table, within) in [
+        (model::SESSION, "session", around.open_started()),
+        (model::SESSION_SAMPLE, "session_sample", around),
+        (model::WATER_CROSSING, "water_crossing", DateRange::ALL),
+    ] {
+        if !query
+            .register_if_present_within(dataset, table, within)
+            .await?
+        {
+            return Err(CrossingError::Missing {
+                dataset: dataset.name,
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Every session of one country, with its samples in metres.
 async fn sessions_in(query: &Query, country: Country) -> Result<Vec<Session>, CrossingError> {
     let stored: Vec<StoredSession> = query
@@ -121,8 +176,8 @@ async fn sessions_in(query: &Query, country: Country) -> Result<Vec<Session>, Cr
              WHERE {COUNTRY} = '{country}'"
         ))
         .await?;
-    let samples: Vec<StoredSample> = query
-        .rows(&format!(
+    let mut samples = query
+        .stream_rows::<StoredSample>(&format!(
             "SELECT session_id, t,
                     ST_X(geometry_projected) AS x, ST_Y(geometry_projected) AS y
              FROM session_sample
@@ -131,15 +186,19 @@ async fn sessions_in(query: &Query, country: Country) -> Result<Vec<Session>, Cr
         ))
         .await?;
 
+    // Each batch is folded into its sessions as it arrives, so the country's samples are
+    // held once, as the instants and points matching needs, and never as rows as well.
     let mut by_session: HashMap<String, Vec<Sample>> = HashMap::new();
-    for sample in samples {
-        by_session
-            .entry(sample.session_id.to_string())
-            .or_default()
-            .push(Sample {
-                t: sample.t,
-                at: Point::new(sample.x, sample.y),
-            });
+    while let Some(batch) = samples.next().await? {
+        for sample in batch {
+            by_session
+                .entry(sample.session_id.to_string())
+                .or_default()
+                .push(Sample {
+                    t: sample.t,
+                    at: Point::new(sample.x, sample.y),
+                });
+        }
     }
 
     Ok(stored
@@ -158,15 +217,41 @@ async fn sessions_in(query: &Query, country: Country) -> Result<Vec<Session>, Cr
         .collect())
 }
 
-/// Every crossing of one country.
-async fn crossings_in(query: &Query, country: Country) -> Result<Vec<Crossing>, CrossingError> {
+/// The crossings of one country within any envelope of `reach`, in lat/lon.
+///
+/// The envelopes are tested against the crossings' `bbox` covering rather than their
+/// geometry: the file is sorted by place and its row groups carry statistics on the covering,
+/// so a group is read only where some envelope overlaps it — the union of what each would
+/// select alone — and the rest are filtered on plain numbers without decoding a geometry.
+/// The envelopes never overlap, so no crossing is read twice.
+async fn crossings_in(
+    query: &Query,
+    country: Country,
+    reach: &[Rect<f64>],
+) -> Result<Vec<Crossing>, CrossingError> {
+    let bbox = covering_name(GEOMETRY);
+    let within = reach
+        .iter()
+        .map(|envelope| {
+            format!(
+                "({bbox}['xmax'] >= {} AND {bbox}['xmin'] <= {}
+                  AND {bbox}['ymax'] >= {} AND {bbox}['ymin'] <= {})",
+                envelope.min().x,
+                envelope.max().x,
+                envelope.min().y,
+                envelope.max().y,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n                  OR ");
     let stored: Vec<StoredCrossing> = query
         .rows(&format!(
             "SELECT crossing_id,
                     ST_X(geometry_projected) AS x, ST_Y(geometry_projected) AS y,
                     ST_X(geometry) AS lon, ST_Y(geometry) AS lat
              FROM water_crossing
-             WHERE {COUNTRY} = '{country}'"
+             WHERE {COUNTRY} = '{country}'
+               AND ({within})"
         ))
         .await?;
 
@@ -9,8 +9,8 @@
 use chrono::{DateTime, TimeZone, Utc};
 use geo_types::Point;
 use medallion::{
-    COUNTRY, Countries, Country, GEOMETRY, PROJECTED_GEOMETRY, Projector, Query, Root, geo_batch,
-    projected_wkb_field, wkb_field,
+    COUNTRY, Countries, Country, DateRange, GEOMETRY, PROJECTED_GEOMETRY, Projector, Query, Root,
+    geo_batch, projected_wkb_field, wkb_field,
 };
 use model::{CrossingId, OverlapKind, WaterCrossingRow};
 use recorder::bronze::{Archive, Payload};
@@ -27,7 +27,7 @@ struct Germany;
 
 impl Countries for Germany {
     fn containing(&self, _point: Point<f64>) -> Option<Country> {
-        Some(Country::Germany)
+        Some(Country::GERMANY)
     }
 }
 
@@ -98,10 +98,10 @@ async fn store_with_a_session(root: &Root, samples: usize) {
         .write(at(0), &payloads)
         .await
         .expect("archive the samples");
-    let derived = sessions(root, Gap::default(), Lead::default())
+    let derived = sessions(root, Gap::default(), Lead::default(), DateRange::ALL)
         .await
         .expect("derive the sessions");
-    silver::write(root, &derived, &Germany)
+    silver::write(root, &derived, &Germany, DateRange::ALL)
         .await
         .expect("write the sessions");
 }
@@ -109,7 +109,7 @@ async fn store_with_a_session(root: &Root, samples: usize) {
 /// Add crossings at the given distances east of Berlin, written the way the crossings
 /// pipeline writes them: one file per country, both geometries, as GeoParquet.
 async fn store_with_crossings(root: &Root, at_metres: &[f64]) {
-    let projector = Projector::for_country(Country::Germany).expect("projector");
+    let projector = Projector::for_country(Country::GERMANY).expect("projector");
     let rows: Vec<WaterCrossingRow> = at_metres
         .iter()
         .enumerate()
@@ -149,7 +149,7 @@ async fn store_with_crossings(root: &Root, at_metres: &[f64]) {
         &[
             (wkb_field(GEOMETRY).expect("field"), points.as_slice()),
             (
-                projected_wkb_field(PROJECTED_GEOMETRY, Country::Germany).expect("field"),
+                projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).expect("field"),
                 projected.as_slice(),
             ),
         ],
@@ -157,7 +157,7 @@ async fn store_with_crossings(root: &Root, at_metres: &[f64]) {
     .expect("build the batch");
 
     root.rows_of::<WaterCrossingRow>()
-        .partition(COUNTRY, Country::Germany)
+        .partition(COUNTRY, Country::GERMANY)
         .expect("partition")
         .replace_with_geo(&[batch])
         .await
@@ -191,7 +191,7 @@ async fn a_crossing_the_session_ran_past_is_recorded_against_it() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[1_060.0]).await;
 
-    let outcome = session_crossings::silver::derive(&root, Radius::default())
+    let outcome = session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
 
@@ -214,12 +214,15 @@ async fn a_crossing_nowhere_near_the_session_is_not() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[50_000.0]).await;
 
-    let outcome = session_crossings::silver::derive(&root, Radius::default())
+    let outcome = session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
 
     assert_eq!(outcome.passes, 0);
-    assert_eq!(outcome.crossings, 1, "the crossing should still be read");
+    assert_eq!(
+        outcome.crossings, 0,
+        "a crossing beyond every session's reach should not be read"
+    );
     assert!(passes_in(&root).await.is_empty());
 }
 
@@ -232,7 +235,7 @@ async fn the_radius_decides_what_counts_as_passed() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[1_060.0]).await;
 
-    let narrow = session_crossings::silver::derive(&root, Radius::new(20.0))
+    let narrow = session_crossings::silver::derive(&root, Radius::new(20.0), DateRange::ALL)
         .await
         .expect("derive");
 
@@ -249,11 +252,11 @@ async fn a_rerun_leaves_the_same_passes() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[1_060.0, 2_020.0]).await;
 
-    session_crossings::silver::derive(&root, Radius::default())
+    session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
     let first = passes_in(&root).await;
-    let second_run = session_crossings::silver::derive(&root, Radius::default())
+    let second_run = session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive again");
 
@@ -269,11 +272,11 @@ async fn a_pass_the_rerun_no_longer_makes_is_swept() {
     let root = Root::new(tmp.path());
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[1_060.0]).await;
-    session_crossings::silver::derive(&root, Radius::default())
+    session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
 
-    let narrowed = session_crossings::silver::derive(&root, Radius::new(20.0))
+    let narrowed = session_crossings::silver::derive(&root, Radius::new(20.0), DateRange::ALL)
         .await
         .expect("derive again");
 
@@ -281,6 +284,36 @@ async fn a_pass_the_rerun_no_longer_makes_is_swept() {
     assert!(passes_in(&root).await.is_empty());
 }
 
+/// A run over a range replaces the passes dated in it and nothing else: one over the next
+/// day leaves a pass the narrower radius would no longer make, and one over its day sweeps
+/// it.
+#[tokio::test]
+async fn a_range_replaces_only_the_passes_dated_within_it() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = Root::new(tmp.path());
+    store_with_a_session(&root, 3).await;
+    store_with_crossings(&root, &[1_060.0]).await;
+    session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
+        .await
+        .expect("derive");
+    let passed = at(0).date_naive();
+
+    let next_day = DateRange::on(passed.succ_opt().unwrap());
+    let elsewhere = session_crossings::silver::derive(&root, Radius::new(20.0), next_day)
+        .await
+        .expect("derive the next day");
+    assert_eq!(elsewhere.passes, 0);
+    assert_eq!(elsewhere.partitions.removed, 0);
+    assert_eq!(passes_in(&root).await.len(), 1);
+
+    let that_day =
+        session_crossings::silver::derive(&root, Radius::new(20.0), DateRange::on(passed))
+            .await
+            .expect("derive the day it was passed");
+    assert_eq!(that_day.partitions.removed, 1);
+    assert!(passes_in(&root).await.is_empty());
+}
+
 /// The dataset is laid out by the date the crossing was passed, so a session running over
 /// midnight has its passes split the way its samples are.
 #[tokio::test]
@@ -290,14 +323,19 @@ async fn passes_are_partitioned_by_the_date_they_happened_on() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[1_060.0]).await;
 
-    session_crossings::silver::derive(&root, Radius::default())
+    session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
 
-    assert!(
-        tmp.path()
-            .join("silver/session_crossing/crossed_date=2026-07-22/part-0.parquet")
-            .exists()
+    assert_eq!(
+        std::fs::read_dir(
+            tmp.path()
+                .join("silver/session_crossing/crossed_date=2026-07-22")
+        )
+        .unwrap()
+        .count(),
+        1,
+        "one file per partition"
     );
 }
 
@@ -310,7 +348,7 @@ async fn a_session_that_never_moved_still_records_what_it_sat_beside() {
     store_with_a_session(&root, 1).await;
     store_with_crossings(&root, &[30.0]).await;
 
-    let outcome = session_crossings::silver::derive(&root, Radius::default())
+    let outcome = session_crossings::silver::derive(&root, Radius::default(), DateRange::ALL)
         .await
         .expect("derive");
 
@@ -328,7 +366,7 @@ async fn the_samples_within_the_radius_are_counted() {
     store_with_a_session(&root, 3).await;
     store_with_crossings(&root, &[500.0]).await;
 
-    let outcome = session_crossings::silver::derive(&root, Radius::new(1_000.0))
+    let outcome = session_crossings::silver::derive(&root, Radius::new(1_000.0), DateRange::ALL)
         .await
         .expect("derive");
 
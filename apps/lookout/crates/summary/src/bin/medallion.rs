@@ -0,0 +1,518 @@
+//! `medallion`: work with a medallion store by dataset name.
+//!
+//! `medallion sql` runs one query against the store, with every dataset defined for this app
+//! already there to name as `<layer>.<dataset>` — `SELECT COUNT(*) FROM silver.session` —
+//! and the spatial functions of the engine the derivations use. Nothing is registered or
+//! read until the query names it, so a query over one dataset costs what that dataset holds.
+//!
+//! `medallion clean` deletes what silver rebuilds staged and never published — the files a
+//! run killed part way through leaves behind, which no reader reads but which still take
+//! up room. It deletes only what a dataset's pointer does not publish, so it is safe at any
+//! time except while a rebuild of the same dataset is running.
+//!
+//! `medallion verify` reads every dataset back and checks it against its definition — files
+//! that decode, partitions the layout has room for, GeoParquet metadata in the CRS the
+//! country prescribes, unique columns that are, instants that are UTC milliseconds — and
+//! prints every problem found. It fails if there is one, so it can stand after a pull of a
+//! store, or in a check that a store is fit to publish.
+//!
+//! `medallion export` writes a silver dataset out for someone working outside the store — as
+//! GeoJSON or FlatGeobuf for QGIS, CSV with WKT for a spreadsheet, or, for sessions, GPX for
+//! a phone's map app, with the crossings each session passed as waypoints. `--from`/`--to`,
+//! `--country` and `--where` narrow it to the rows wanted: one trip is
+//! `medallion export session --format gpx --where "session_id = '…'"`.
+//!
+//! `medallion diff` compares what two stores hold — the same store before and after a rerun
+//! of `just silver`, say — dataset by dataset and partition by partition. A dataset with a
+//! unique column has its rows matched on it, so a row whose values changed is reported as
+//! changed, with the columns that did and how: values before and after for a few of the
+//! rows, and for geometry how far it moved. A dataset without one has its rows compared
+//! whole, as added and removed. It fails if anything differs, which makes it the
+//! determinism check too: rederiving over unchanged bronze must leave nothing to report.
+//!
+//! `medallion gold` manages which version of a gold artefact is the one to use. `promote`
+//! makes a version live in the artefact's index — the newest recorded, unless one is named —
+//! and `latest` prints where the live version's files are, which is what a script fetching
+//! the current `crossings.pointset` asks. `gc` removes the versions a retention policy does
+//! not keep: `--keep 3` the three newest, `--keep-promoted` the live one and any newer. The
+//! live version and any newer one are kept by both, as is any version written within the last
+//! hour.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use medallion::gold::Retention;
+use medallion::{Country, MedallionArgs, Query, Root};
+use summary::export::{self, Selection};
+use summary::sql::{Format, render};
+use summary::{clean_report, collection_report, diff_report, verification_report};
+
+#[derive(Parser)]
+#[command(about = "Query the medallion store by dataset name")]
+struct Args {
+    #[command(flatten)]
+    medallion: MedallionArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a SQL query against the store and print what it returns.
+    Sql {
+        /// The query, naming datasets as `<layer>.<dataset>`, e.g. `bronze.gps_reading`.
+        query: String,
+        /// How the result is printed.
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Check every dataset against its definition, and fail if any departs from it.
+    Verify {
+        /// The dataset to verify, e.g. `session`. Defaults to every one.
+        #[arg(long)]
+        dataset: Option<String>,
+    },
+    /// Delete the files silver rebuilds staged and never published.
+    Clean {
+        /// The silver dataset to clean, e.g. `session`. Defaults to every one.
+        #[arg(long)]
+        dataset: Option<String>,
+    },
+    /// Write a silver dataset out as a file another tool opens. `--from`/`--to` narrow it to
+    /// the dates its partitions are filed under.
+    Export {
+        /// The silver dataset to export, e.g. `session`.
+        dataset: String,
+        /// What to write it as.
+        #[arg(long, value_enum)]
+        format: export::Format,
+        /// Only the rows filed under this country, as its ISO code, e.g. `DE`.
+        #[arg(long)]
+        country: Option<Country>,
+        /// Only the rows meeting this SQL condition over the dataset's columns.
+        #[arg(long = "where")]
+        predicate: Option<String>,
+        /// The file to write. Defaults to standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Compare what two stores hold, and fail if they differ.
+    Diff {
+        /// The store as it was, e.g. a copy taken before a rerun: a directory or a URL.
+        before: String,
+        /// The store as it is now.
+        after: String,
+        /// The dataset to compare, e.g. `session`. Defaults to every one.
+        dataset: Option<String>,
+    },
+    /// Say which version of a gold artefact is live, and remove the ones no longer wanted.
+    Gold {
+        #[command(subcommand)]
+        command: GoldCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum GoldCommand {
+    /// Make a version of an artefact the live one.
+    Promote {
+        /// The artefact, e.g. `crossings`.
+        artifact: String,
+        /// The version to promote. Defaults to the newest recorded.
+        version: Option<String>,
+    },
+    /// Print where each file of an artefact's live version is.
+    Latest {
+        /// The artefact, e.g. `crossings`.
+        artifact: String,
+    },
+    /// Remove the versions of artefacts a retention policy does not keep.
+    Gc {
+        /// The artefact to collect. Defaults to every one.
+        #[arg(long)]
+        artifact: Option<String>,
+        #[command(flatten)]
+        retention: RetentionArgs,
+    },
+}
+
+/// Which versions a collection keeps, one of the two ways; the live version, and any newer
+/// one, is always kept.
+#[derive(ClapArgs, Debug, PartialEq, Eq)]
+#[group(required = true, multiple = false)]
+struct RetentionArgs {
+    /// Keep this many of the newest versions, besides the live one and any newer.
+    #[arg(long)]
+    keep: Option<usize>,
+    /// Keep the live version and any newer one.
+    #[arg(long)]
+    keep_promoted: bool,
+}
+
+impl RetentionArgs {
+    fn retention(&self) -> Retention {
+        match self.keep {
+            Some(n) => Retention::Newest(n),
+            None => Retention::Promoted,
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    // Every command but `diff` works on the one store, which `diff` is given two of instead.
+    let root = || args.medallion.root();
+
+    match args.command {
+        Command::Sql { query, format } => sql(&root()?, &query, format),
+        Command::Verify { dataset } => verify(&root()?, dataset.as_deref()),
+        Command::Clean { dataset } => clean(&root()?, dataset.as_deref()),
+        Command::Export {
+            dataset,
+            format,
+            country,
+            predicate,
+            output,
+        } => {
+            let selection = Selection {
+                range: args.medallion.range()?,
+                country,
+                predicate,
+            };
+            export(&root()?, &dataset, &selection, format, output)
+        }
+        Command::Diff {
+            before,
+            after,
+            dataset,
+        } => diff(
+            &Root::open(&before)?,
+            &Root::open(&after)?,
+            dataset.as_deref(),
+        ),
+        Command::Gold { command } => {
+            tokio::runtime::Runtime::new()?.block_on(gold(&root()?, command))
+        }
+    }
+}
+
+fn sql(root: &Root, sql: &str, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let batches = tokio::runtime::Runtime::new()?.block_on(async {
+        let query = Query::new(root.clone());
+        query.register_catalog(&model::ALL)?;
+        query.sql(sql).await
+    })?;
+
+    print!("{}", render(&batches, format)?);
+    Ok(())
+}
+
+fn verify(root: &Root, dataset: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let declared: Vec<_> = model::DECLARED
+        .into_iter()
+        .filter(|declared| dataset.is_none_or(|name| declared.dataset.name == name))
+        .collect();
+    if let (Some(name), true) = (dataset, declared.is_empty()) {
+        return Err(format!("no dataset is called {name}").into());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut verifications = Vec::new();
+    for declared in &declared {
+        verifications.push(runtime.block_on(medallion::verify::dataset(root, declared))?);
+    }
+
+    println!("{root}");
+    print!("{}", verification_report(&verifications));
+    let unsound = verifications
+        .iter()
+        .filter(|verification| !verification.is_sound())
+        .count();
+    match unsound {
+        0 => Ok(()),
+        1 => Err("1 dataset departs from its definition".into()),
+        unsound => Err(format!("{unsound} datasets depart from their definitions").into()),
+    }
+}
+
+fn clean(root: &Root, dataset: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let specs: Vec<_> = model::SILVER
+        .into_iter()
+        .filter(|spec| dataset.is_none_or(|name| spec.name == name))
+        .collect();
+    if let (Some(name), true) = (dataset, specs.is_empty()) {
+        return Err(format!("no silver dataset is called {name}").into());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut cleaned = Vec::new();
+    for spec in specs {
+        cleaned.push((spec.name, runtime.block_on(root.dataset(spec).clean())?));
+    }
+
+    println!("{root}");
+    print!("{}", clean_report(&cleaned));
+    Ok(())
+}
+
+fn diff(
+    before: &Root,
+    after: &Root,
+    dataset: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let declared: Vec<_> = model::DECLARED
+        .into_iter()
+        .filter(|declared| dataset.is_none_or(|name| declared.dataset.name == name))
+        .collect();
+    if let (Some(name), true) = (dataset, declared.is_empty()) {
+        return Err(format!("no dataset is called {name}").into());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut diffs = Vec::new();
+    for declared in &declared {
+        diffs.push(runtime.block_on(medallion::diff::dataset(before, after, declared))?);
+    }
+
+    println!("{before} → {after}");
+    print!("{}", diff_report(&diffs));
+    match diffs.iter().filter(|diff| !diff.is_empty()).count() {
+        0 => Ok(()),
+        1 => Err("1 dataset differs".into()),
+        differing => Err(format!("{differing} datasets differ").into()),
+    }
+}
+
+fn export(
+    root: &Root,
+    dataset: &str,
+    selection: &Selection,
+    format: export::Format,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = tokio::runtime::Runtime::new()?
+        .block_on(export::export(root, dataset, selection, format))?;
+    match output {
+        Some(path) => std::fs::write(path, bytes)?,
+        None => std::io::stdout().write_all(&bytes)?,
+    }
+    Ok(())
+}
+
+async fn gold(root: &Root, command: GoldCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        GoldCommand::Promote { artifact, version } => {
+            let promoted = root
+                .artefact(&artifact)?
+                .promote(version.as_deref())
+                .await?;
+            println!("{artifact}: {} is live", promoted.version);
+        }
+        GoldCommand::Latest { artifact } => match root.artefact(&artifact)?.live().await? {
+            Some((_, paths)) => {
+                for path in paths {
+                    println!("{}", path.display());
+                }
+            }
+            None => return Err(format!("{artifact} has no live version").into()),
+        },
+        GoldCommand::Gc {
+            artifact,
+            retention,
+        } => {
+            let artefacts = match artifact {
+                Some(name) => vec![root.artefact(&name)?],
+                None => medallion::gold::artefacts(root).await?,
+            };
+            let mut collected = Vec::new();
+            for artefact in &artefacts {
+                collected.push((
+                    artefact.name(),
+                    artefact.collect(retention.retention(), Utc::now()).await?,
+                ));
+            }
+            println!("{root}");
+            print!("{}", collection_report(&collected));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_query_is_printed_as_a_table_unless_told_otherwise() {
+        let args = Args::parse_from(["medallion", "sql", "SELECT 1"]);
+
+        let Command::Sql { query, format } = args.command else {
+            panic!("expected the sql command");
+        };
+        assert_eq!(query, "SELECT 1");
+        assert_eq!(format, Format::Table);
+    }
+
+    #[test]
+    fn a_query_can_be_printed_as_csv_or_json() {
+        for (flag, expected) in [("csv", Format::Csv), ("json", Format::Json)] {
+            let args = Args::parse_from(["medallion", "sql", "SELECT 1", "--format", flag]);
+
+            let Command::Sql { format, .. } = args.command else {
+                panic!("expected the sql command");
+            };
+            assert_eq!(format, expected);
+        }
+    }
+
+    #[test]
+    fn a_verification_covers_every_dataset_unless_one_is_named() {
+        let args = Args::parse_from(["medallion", "verify"]);
+        let Command::Verify { dataset } = args.command else {
+            panic!("expected the verify command");
+        };
+        assert_eq!(dataset, None);
+
+        let args = Args::parse_from(["medallion", "verify", "--dataset", "gps_reading"]);
+        let Command::Verify { dataset } = args.command else {
+            panic!("expected the verify command");
+        };
+        assert_eq!(dataset.as_deref(), Some("gps_reading"));
+    }
+
+    #[test]
+    fn a_clean_covers_every_silver_dataset_unless_one_is_named() {
+        let args = Args::parse_from(["medallion", "clean"]);
+        let Command::Clean { dataset } = args.command else {
+            panic!("expected the clean command");
+        };
+        assert_eq!(dataset, None);
+
+        let args = Args::parse_from(["medallion", "clean", "--dataset", "session"]);
+        let Command::Clean { dataset } = args.command else {
+            panic!("expected the clean command");
+        };
+        assert_eq!(dataset.as_deref(), Some("session"));
+    }
+
+    /// Collecting removes data, so how much to keep is never left to a default.
+    #[test]
+    fn a_gold_collection_says_what_it_keeps() {
+        assert!(Args::try_parse_from(["medallion", "gold", "gc"]).is_err());
+        assert!(
+            Args::try_parse_from(["medallion", "gold", "gc", "--keep", "2", "--keep-promoted"])
+                .is_err()
+        );
+
+        let args = Args::parse_from(["medallion", "gold", "gc", "--keep", "2"]);
+        let Command::Gold {
+            command:
+                GoldCommand::Gc {
+                    artifact,
+                    retention,
+                },
+        } = args.command
+        else {
+            panic!("expected the gold gc command");
+        };
+        assert_eq!(artifact, None);
+        assert_eq!(retention.retention(), Retention::Newest(2));
+
+        let args = Args::parse_from([
+            "medallion",
+            "gold",
+            "gc",
+            "--artifact",
+            "crossings",
+            "--keep-promoted",
+        ]);
+        let Command::Gold {
+            command:
+                GoldCommand::Gc {
+                    artifact,
+                    retention,
+                },
+        } = args.command
+        else {
+            panic!("expected the gold gc command");
+        };
+        assert_eq!(artifact.as_deref(), Some("crossings"));
+        assert_eq!(retention.retention(), Retention::Promoted);
+    }
+
+    #[test]
+    fn a_diff_names_two_stores_and_optionally_a_dataset() {
+        let args = Args::parse_from(["medallion", "diff", "before", "s3://lookout/medallion"]);
+        let Command::Diff {
+            before,
+            after,
+            dataset,
+        } = args.command
+        else {
+            panic!("expected the diff command");
+        };
+        assert_eq!(before, "before");
+        assert_eq!(after, "s3://lookout/medallion");
+        assert_eq!(dataset, None);
+
+        let args = Args::parse_from(["medallion", "diff", "a", "b", "session"]);
+        let Command::Diff { dataset, .. } = args.command else {
+            panic!("expected the diff command");
+        };
+        assert_eq!(dataset.as_deref(), Some("session"));
+    }
+
+    #[test]
+    fn promoting_names_an_artefact_and_optionally_a_version() {
+        let args = Args::parse_from(["medallion", "gold", "promote", "crossings"]);
+        let Command::Gold {
+            command: GoldCommand::Promote { artifact, version },
+        } = args.command
+        else {
+            panic!("expected the gold promote command");
+        };
+        assert_eq!(artifact, "crossings");
+        assert_eq!(version, None);
+    }
+
+    #[test]
+    fn an_export_names_its_dataset_and_format_and_may_narrow_them() {
+        let args = Args::parse_from([
+            "medallion",
+            "export",
+            "session",
+            "--format",
+            "gpx",
+            "--country",
+            "de",
+            "--where",
+            "session_id = 'a'",
+            "--from",
+            "2026-07-22",
+            "-o",
+            "trip.gpx",
+        ]);
+
+        let Command::Export {
+            dataset,
+            format,
+            country,
+            predicate,
+            output,
+        } = args.command
+        else {
+            panic!("expected the export command");
+        };
+        assert_eq!(dataset, "session");
+        assert_eq!(format, export::Format::Gpx);
+        assert_eq!(country, Some(Country::GERMANY));
+        assert_eq!(predicate.as_deref(), Some("session_id = 'a'"));
+        assert_eq!(output, Some(PathBuf::from("trip.gpx")));
+        assert_eq!(
+            args.medallion.from,
+            chrono::NaiveDate::from_ymd_opt(2026, 7, 22)
+        );
+    }
+}
@@ -2,12 +2,29 @@
 //! this app, in every layer, plus the gold artefacts.
 //!
 //! Reads no rows: the counts come from each parquet file's own footer, so this stays cheap
-//! on a store far too large to scan. A dataset nothing has written is reported as absent
-//! rather than left out, since what is missing is half of what the question is asking.
+//! on a store far too large to scan, and on one kept in a bucket, where a footer is fetched
+//! without the file. A dataset nothing has written is reported as absent rather than left
+//! out, since what is missing is half of what the question is asking.
+//!
+//! `summarise --lineage <dataset>` reports instead what the dataset's files were derived
+//! from: the producer, build and tuning each one records, and every partition it read, marked
+//! as it stands now — so a dataset whose bronze has since moved on says so, and says where.
+//!
+//! `summarise compact` acts on what the report shows: it merges the batch files of each
+//! bronze partition holding enough of them into one, and reports the files and bytes a
+//! reader reads before and after. Nothing is rewritten or deleted, so running it is always
+//! safe and running it twice only compacts what arrived in between. The Overture extracts
+//! are left alone: each is written once, so there is nothing in it to merge.
+
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+use medallion::{MedallionArgs, Root};
+use summary::{Detail, compaction_report, lineage_report, report};
 
-use clap::Parser;
-use medallion::MedallionArgs;
-use summary::{Detail, report};
+/// The fewest live files a partition has to hold before it is compacted, unless told
+/// otherwise: few enough that a day's polls are merged the next day, many enough that a
+/// partition already compacted is not merged again for one late file.
+const MIN_FILES: usize = 16;
 
 #[derive(Parser)]
 #[command(about = "Summarise what the medallion store holds")]
@@ -17,23 +34,168 @@ struct Args {
     /// List every partition of every dataset, rather than the span each one covers.
     #[arg(long)]
     partitions: bool,
+    /// Report what this dataset's files were derived from, and whether it has changed since,
+    /// e.g. `session`.
+    #[arg(long, value_name = "DATASET", conflicts_with = "partitions")]
+    lineage: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Merge the batch files of each bronze partition into one, without removing any.
+    Compact {
+        /// The bronze dataset to compact, e.g. `motis_segment`. Defaults to every one but the
+        /// Overture extracts, which are never compacted.
+        #[arg(long)]
+        dataset: Option<String>,
+        /// The fewest live files a partition has to hold to be compacted.
+        #[arg(long, default_value_t = MIN_FILES)]
+        min_files: usize,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
     let root = args.medallion.root()?;
 
-    let datasets = model::ALL
-        .into_iter()
-        .map(|dataset| medallion::summary::dataset(&root, dataset))
-        .collect::<Result<Vec<_>, _>>()?;
-    let artefacts = medallion::summary::artefacts(&root)?;
-    let detail = match args.partitions {
+    match args.command {
+        None => match args.lineage {
+            Some(dataset) => lineage(&root, &dataset),
+            None => summarise(&root, args.partitions),
+        },
+        Some(Command::Compact { dataset, min_files }) => {
+            compact(&root, dataset.as_deref(), min_files)
+        }
+    }
+}
+
+fn summarise(root: &Root, partitions: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (datasets, artefacts) = runtime.block_on(async {
+        let mut datasets = Vec::new();
+        for dataset in model::ALL {
+            datasets.push(medallion::summary::dataset(root, dataset).await?);
+        }
+        let artefacts = medallion::summary::artefacts(root).await?;
+        Ok::<_, medallion::summary::SummaryError>((datasets, artefacts))
+    })?;
+    let detail = match partitions {
         true => Detail::Partitions,
         false => Detail::Datasets,
     };
 
-    println!("{}", root.path().display());
+    println!("{root}");
     print!("{}", report(&datasets, &artefacts, detail));
     Ok(())
 }
+
+fn lineage(root: &Root, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(dataset) = model::ALL.into_iter().find(|dataset| dataset.name == name) else {
+        return Err(format!("no dataset is called {name}").into());
+    };
+    let runtime = tokio::runtime::Runtime::new()?;
+    let derivations = runtime.block_on(medallion::lineage::trace(root, dataset))?;
+
+    println!("{root}");
+    print!("{}", lineage_report(name, &derivations));
+    Ok(())
+}
+
+fn compact(
+    root: &Root,
+    dataset: Option<&str>,
+    min_files: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(spec) = model::UNCOMPACTED
+        .into_iter()
+        .find(|spec| dataset == Some(spec.name))
+    {
+        return Err(format!("{} is written once and never compacted", spec.name).into());
+    }
+    let specs: Vec<_> = model::BRONZE
+        .into_iter()
+        .filter(|spec| !model::UNCOMPACTED.contains(spec))
+        .filter(|spec| dataset.is_none_or(|name| spec.name == name))
+        .collect();
+    if let (Some(name), true) = (dataset, specs.is_empty()) {
+        return Err(format!("no bronze dataset is called {name}").into());
+    }
+
+    // One instant for the run, so every partition's generation is named the same and the
+    // run can be found again from any one of them.
+    let at = Utc::now();
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut compactions = Vec::new();
+    for spec in specs {
+        let compaction = runtime.block_on(root.dataset(spec).compact(at, min_files))?;
+        compactions.push((spec.name, compaction));
+    }
+
+    println!("{root}");
+    print!("{}", compaction_report(&compactions));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_no_command_the_store_is_summarised() {
+        let args = Args::parse_from(["summarise", "--partitions"]);
+
+        assert!(args.command.is_none());
+        assert!(args.partitions);
+    }
+
+    #[test]
+    fn a_lineage_is_asked_of_a_dataset_by_its_name() {
+        let args = Args::parse_from(["summarise", "--lineage", "session"]);
+
+        assert!(args.command.is_none());
+        assert_eq!(args.lineage.as_deref(), Some("session"));
+        assert!(Args::try_parse_from(["summarise", "--lineage"]).is_err());
+    }
+
+    #[test]
+    fn compacting_covers_every_bronze_dataset_unless_one_is_named() {
+        let args = Args::parse_from(["summarise", "compact"]);
+
+        let Some(Command::Compact { dataset, min_files }) = args.command else {
+            panic!("expected the compact command");
+        };
+        assert!(dataset.is_none());
+        assert_eq!(min_files, MIN_FILES);
+    }
+
+    #[test]
+    fn a_dataset_to_compact_is_named_by_its_name() {
+        let args = Args::parse_from([
+            "summarise",
+            "compact",
+            "--dataset",
+            "motis_segment",
+            "--min-files",
+            "2",
+        ]);
+
+        let Some(Command::Compact { dataset, min_files }) = args.command else {
+            panic!("expected the compact command");
+        };
+        assert_eq!(dataset.as_deref(), Some("motis_segment"));
+        assert_eq!(min_files, 2);
+    }
+
+    /// Naming the extracts is refused before the store is touched, rather than reported as a
+    /// compaction that found nothing to do.
+    #[test]
+    fn the_overture_extracts_are_never_compacted() {
+        let root = Root::new("/nowhere");
+
+        let err = compact(&root, Some(model::OVERTURE_EXTRACT.name), 2).unwrap_err();
+
+        assert!(err.to_string().contains("never compacted"), "{err}");
+    }
+}
@@ -0,0 +1,835 @@
+//! Handing a silver dataset to someone outside the store: a file QGIS, a phone map app or a
+//! spreadsheet opens as it is.
+//!
+//! Every format is written from the lat/lon [`GEOMETRY`] alone. The projected twin is the
+//! store's working copy for distances, in a zone a consumer would have to be told about, and
+//! the `bbox` coverings beside each are there for the store's own reads; all three are left
+//! out. A column of several fields, such as a session's envelope, is spread over one column
+//! per field, `bbox_xmin` and so on, since no format here nests.
+//!
+//! * **GeoJSON** — a `FeatureCollection`, one feature per row, the other columns as its
+//!   properties, a null one as `null`.
+//! * **FlatGeobuf** — the same features, packed for a reader that opens a large file without
+//!   parsing all of it. Its columns are declared from the dataset's own, so every column is
+//!   in the file with a type of its own, however many of its values are null.
+//! * **CSV** — a header and a line per row, with the geometry as WKT, for a spreadsheet.
+//! * **GPX** — sessions only: a track per session through its samples, with the crossings it
+//!   passed as waypoints, which is what a phone's map app or a GPS unit opens.
+
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, BooleanArray, Float64Array, Int64Array, RecordBatch,
+    RecordBatchOptions, StringArray, StructArray, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, FieldRef, Float64Type, Int64Type, Schema, UInt64Type};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use chrono::{DateTime, SecondsFormat, Utc};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use medallion::{COUNTRY, Country, DateRange, GEOMETRY, PROJECTED_GEOMETRY, Query, Root};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use wkt::ToWkt;
+
+/// The name GPX files give as the program that wrote them.
+const CREATOR: &str = "lookout";
+
+/// A file an export is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// A GeoJSON `FeatureCollection`.
+    Geojson,
+    /// FlatGeobuf.
+    Fgb,
+    /// CSV, with the geometry as WKT.
+    Csv,
+    /// GPX tracks, with the crossings passed as waypoints. `session` and `session_sample`
+    /// only.
+    Gpx,
+}
+
+/// Which rows of a dataset to export. Every condition given has to hold.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// The dates to read, by the dataset's own partition date.
+    pub range: DateRange,
+    /// The one country to read, for a dataset partitioned by country.
+    pub country: Option<Country>,
+    /// A SQL condition over the dataset's columns, e.g. `session_id = 'a1b2…'`.
+    pub predicate: Option<String>,
+}
+
+impl Selection {
+    /// The selection as a `WHERE` condition over the table the dataset is registered as.
+    fn condition(&self) -> String {
+        let conditions: Vec<String> = self
+            .country
+            .map(|country| format!("{COUNTRY} = '{country}'"))
+            .into_iter()
+            .chain(
+                self.predicate
+                    .iter()
+                    .map(|predicate| format!("({predicate})")),
+            )
+            .collect();
+        match conditions.is_empty() {
+            true => "TRUE".to_string(),
+            false => conditions.join(" AND "),
+        }
+    }
+}
+
+/// A failure exporting a dataset.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("no silver dataset is called {0}")]
+    NoSuchDataset(String),
+    #[error("reading the dataset: {0}")]
+    Query(#[from] medallion::QueryError),
+    #[error("decoding the geometry: {0}")]
+    Geo(#[from] medallion::GeoError),
+    #[error("{0} carries no geometry to export as anything but CSV")]
+    NoGeometry(String),
+    #[error("{0} holds no sessions to write as GPX tracks: only session and session_sample do")]
+    NotSessions(String),
+    #[error("writing the columns: {0}")]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[error("writing the properties: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("writing FlatGeobuf: {0}")]
+    FlatGeobuf(String),
+}
+
+/// The selected rows of silver dataset `name`, as `format`.
+pub async fn export(
+    root: &Root,
+    name: &str,
+    selection: &Selection,
+    format: Format,
+) -> Result<Vec<u8>, ExportError> {
+    let spec = model::SILVER
+        .into_iter()
+        .find(|spec| spec.name == name)
+        .ok_or_else(|| ExportError::NoSuchDataset(name.to_string()))?;
+    let query = Query::new(root.clone());
+    query
+        .register_within(spec, "selected", selection.range)
+        .await?;
+
+    match format {
+        Format::Gpx => Ok(gpx_of(&query, name, selection).await?.into_bytes()),
+        Format::Csv => Ok(csv(&selected(&query, selection).await?)?.into_bytes()),
+        Format::Geojson => {
+            let batches = with_geometry(name, selected(&query, selection).await?)?;
+            Ok(geojson(&batches)?.into_bytes())
+        }
+        Format::Fgb => {
+            let batches = with_geometry(name, selected(&query, selection).await?)?;
+            flatgeobuf(name, &batches)
+        }
+    }
+}
+
+/// The selected rows of the dataset registered as `selected`.
+async fn selected(query: &Query, selection: &Selection) -> Result<Vec<RecordBatch>, ExportError> {
+    Ok(query
+        .sql(&format!(
+            "SELECT * FROM selected WHERE {}",
+            selection.condition()
+        ))
+        .await?)
+}
+
+/// `batches`, unless they are of dataset `name` and it carries no geometry.
+fn with_geometry(name: &str, batches: Vec<RecordBatch>) -> Result<Vec<RecordBatch>, ExportError> {
+    match batches
+        .first()
+        .is_none_or(|batch| batch.schema().index_of(GEOMETRY).is_ok())
+    {
+        true => Ok(batches),
+        false => Err(ExportError::NoGeometry(name.to_string())),
+    }
+}
+
+/// `batches` as a GeoJSON `FeatureCollection`: a feature per row, its geometry from
+/// [`GEOMETRY`] and its properties from every column kept.
+pub fn geojson(batches: &[RecordBatch]) -> Result<String, ExportError> {
+    let mut features = Vec::new();
+    for batch in batches {
+        let geometries = medallion::geometries(batch, GEOMETRY)?;
+        for (geometry, properties) in geometries.iter().zip(properties(batch)?) {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": geojson_geometry(geometry),
+                "properties": properties,
+            }));
+        }
+    }
+    Ok(format!(
+        "{}\n",
+        json!({ "type": "FeatureCollection", "features": features })
+    ))
+}
+
+/// `batches` as CSV: the columns kept, then the geometry as WKT in a column of its own name.
+///
+/// A dataset with no geometry is written as it is, which is the one format that allows it.
+pub fn csv(batches: &[RecordBatch]) -> Result<String, ExportError> {
+    let mut writer = arrow::csv::WriterBuilder::new()
+        .with_header(true)
+        .build(Vec::new());
+    for batch in batches {
+        let mut kept = kept(batch)?;
+        if batch.schema().index_of(GEOMETRY).is_ok() {
+            let wkt: StringArray = medallion::geometries(batch, GEOMETRY)?
+                .iter()
+                .map(|geometry| Some(geometry.wkt_string()))
+                .collect();
+            let mut fields: Vec<FieldRef> = kept.schema().fields().iter().cloned().collect();
+            let mut columns: Vec<ArrayRef> = kept.columns().to_vec();
+            fields.push(Arc::new(Field::new(GEOMETRY, DataType::Utf8, true)));
+            columns.push(Arc::new(wkt));
+            kept = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+        }
+        writer.write(&kept)?;
+    }
+    String::from_utf8(writer.into_inner())
+        .map_err(|err| arrow::error::ArrowError::ExternalError(Box::new(err)).into())
+}
+
+/// `batches` as FlatGeobuf, a layer called `name`.
+///
+/// The layer is declared as the one kind of geometry its rows hold, which is what lets QGIS
+/// style it without asking, or as unknown where they hold more than one. Its columns are the
+/// kept columns of the first batch, each declared with the type its values are written as —
+/// see [`FgbValues`] — and a null is a property the feature does not carry, which is how
+/// FlatGeobuf writes one.
+pub fn flatgeobuf(name: &str, batches: &[RecordBatch]) -> Result<Vec<u8>, ExportError> {
+    use flatgeobuf::{FgbWriter, GeometryType};
+    use geozero::PropertyProcessor;
+
+    let failed = |err: &dyn std::fmt::Display| ExportError::FlatGeobuf(err.to_string());
+    let mut geometries = Vec::with_capacity(batches.len());
+    let mut kinds = Vec::new();
+    for batch in batches {
+        let batch_geometries = medallion::geometries(batch, GEOMETRY)?;
+        for geometry in &batch_geometries {
+            let kind = match geometry {
+                Geometry::Point(_) => GeometryType::Point,
+                Geometry::LineString(_) | Geometry::Line(_) => GeometryType::LineString,
+                Geometry::Polygon(_) | Geometry::Rect(_) | Geometry::Triangle(_) => {
+                    GeometryType::Polygon
+                }
+                Geometry::MultiPoint(_) => GeometryType::MultiPoint,
+                Geometry::MultiLineString(_) => GeometryType::MultiLineString,
+                Geometry::MultiPolygon(_) => GeometryType::MultiPolygon,
+                Geometry::GeometryCollection(_) => GeometryType::GeometryCollection,
+            };
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        geometries.push(batch_geometries);
+    }
+    let kind = match kinds.as_slice() {
+        [kind] => *kind,
+        _ => GeometryType::Unknown,
+    };
+
+    let mut writer = FgbWriter::create(name, kind).map_err(|err| failed(&err))?;
+    if let Some(first) = batches.first() {
+        let kept = kept(first)?;
+        for (field, column) in kept.schema().fields().iter().zip(kept.columns()) {
+            writer.add_column(
+                field.name(),
+                FgbValues::of(column)?.column_type(),
+                |_, _| {},
+            );
+        }
+    }
+    for (batch, batch_geometries) in batches.iter().zip(geometries) {
+        let kept = kept(batch)?;
+        let names: Vec<&str> = kept
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        let values = kept
+            .columns()
+            .iter()
+            .map(FgbValues::of)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (row, geometry) in batch_geometries.into_iter().enumerate() {
+            let mut written = Ok(false);
+            writer
+                .add_feature_geom(geometry, |feature| {
+                    for (index, (name, column)) in names.iter().zip(&values).enumerate() {
+                        if let Some(value) = column.value(row) {
+                            written = written.and(feature.property(index, name, &value));
+                        }
+                    }
+                })
+                .map_err(|err| failed(&err))?;
+            written.map_err(|err| failed(&err))?;
+        }
+    }
+    let mut bytes = Vec::new();
+    writer.write(&mut bytes).map_err(|err| failed(&err))?;
+    Ok(bytes)
+}
+
+/// One kept column's values as FlatGeobuf takes them: each number widened to the 64-bit kind
+/// of its sign, an instant or a date as ISO 8601 text, and anything with no FlatGeobuf type of
+/// its own — a list, say — as the text a table shows it as.
+enum FgbValues {
+    Bool(BooleanArray),
+    Long(Int64Array),
+    ULong(UInt64Array),
+    Double(Float64Array),
+    String(StringArray),
+    DateTime(StringArray),
+}
+
+impl FgbValues {
+    fn of(column: &ArrayRef) -> Result<Self, ExportError> {
+        use arrow::compute::cast;
+
+        Ok(match column.data_type() {
+            DataType::Boolean => Self::Bool(column.as_boolean().clone()),
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => Self::Long(
+                cast(column, &DataType::Int64)?
+                    .as_primitive::<Int64Type>()
+                    .clone(),
+            ),
+            DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+                Self::ULong(
+                    cast(column, &DataType::UInt64)?
+                        .as_primitive::<UInt64Type>()
+                        .clone(),
+                )
+            }
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => Self::Double(
+                cast(column, &DataType::Float64)?
+                    .as_primitive::<Float64Type>()
+                    .clone(),
+            ),
+            DataType::Timestamp(..) | DataType::Date32 | DataType::Date64 => {
+                Self::DateTime(cast(column, &DataType::Utf8)?.as_string::<i32>().clone())
+            }
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+                Self::String(cast(column, &DataType::Utf8)?.as_string::<i32>().clone())
+            }
+            _ => {
+                let shown = ArrayFormatter::try_new(column.as_ref(), &FormatOptions::default())?;
+                Self::String(
+                    (0..column.len())
+                        .map(|row| column.is_valid(row).then(|| shown.value(row).to_string()))
+                        .collect(),
+                )
+            }
+        })
+    }
+
+    fn column_type(&self) -> flatgeobuf::ColumnType {
+        use flatgeobuf::ColumnType;
+
+        match self {
+            Self::Bool(_) => ColumnType::Bool,
+            Self::Long(_) => ColumnType::Long,
+            Self::ULong(_) => ColumnType::ULong,
+            Self::Double(_) => ColumnType::Double,
+            Self::String(_) => ColumnType::String,
+            Self::DateTime(_) => ColumnType::DateTime,
+        }
+    }
+
+    /// The value in `row`, or `None` for a null.
+    fn value(&self, row: usize) -> Option<geozero::ColumnValue<'_>> {
+        use geozero::ColumnValue;
+
+        let array: &dyn Array = match self {
+            Self::Bool(array) => array,
+            Self::Long(array) => array,
+            Self::ULong(array) => array,
+            Self::Double(array) => array,
+            Self::String(array) | Self::DateTime(array) => array,
+        };
+        if array.is_null(row) {
+            return None;
+        }
+        Some(match self {
+            Self::Bool(array) => ColumnValue::Bool(array.value(row)),
+            Self::Long(array) => ColumnValue::Long(array.value(row)),
+            Self::ULong(array) => ColumnValue::ULong(array.value(row)),
+            Self::Double(array) => ColumnValue::Double(array.value(row)),
+            Self::String(array) => ColumnValue::String(array.value(row)),
+            Self::DateTime(array) => ColumnValue::DateTime(array.value(row)),
+        })
+    }
+}
+
+/// One sample of a session, as a point on its track.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TrackPoint {
+    pub session_id: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub t: DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: Option<f64>,
+}
+
+/// One crossing a session passed, where it is and when the session was nearest.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Waypoint {
+    pub session_id: String,
+    pub crossing_id: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub crossed_at: DateTime<Utc>,
+    pub distance_m: f64,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// The GPX for the selected sessions of `name`: their samples, and the crossings they passed
+/// where the store holds both the passes and the crossings.
+///
+/// Exporting `session` selects whole sessions, and reads every sample of each wherever it is
+/// filed; exporting `session_sample` selects the samples themselves, so a track is as much of
+/// a session as the selection takes in.
+async fn gpx_of(query: &Query, name: &str, selection: &Selection) -> Result<String, ExportError> {
+    let condition = selection.condition();
+    let (samples, sessions) = match name {
+        "session" => {
+            query
+                .register(model::SESSION_SAMPLE, "session_sample")
+                .await?;
+            (
+                "session_sample".to_string(),
+                format!("SELECT session_id FROM selected WHERE {condition}"),
+            )
+        }
+        "session_sample" => (
+            format!("(SELECT * FROM selected WHERE {condition})"),
+            format!("SELECT DISTINCT session_id FROM selected WHERE {condition}"),
+        ),
+        other => return Err(ExportError::NotSessions(other.to_string())),
+    };
+
+    let points: Vec<TrackPoint> = query
+        .rows(&format!(
+            "SELECT session_id, t, lat, lon, alt FROM {samples} AS samples
+             WHERE session_id IN ({sessions})
+             ORDER BY session_id, t"
+        ))
+        .await?;
+
+    let passes_known = query
+        .register_if_present(model::SESSION_CROSSING, "session_crossing")
+        .await?
+        && query
+            .register_if_present(model::WATER_CROSSING, "water_crossing")
+            .await?;
+    let waypoints: Vec<Waypoint> = match passes_known {
+        true => {
+            query
+                .rows(&format!(
+                    "SELECT passed.session_id, passed.crossing_id, passed.crossed_at,
+                            passed.distance_m,
+                            ST_Y(crossing.{GEOMETRY}) AS lat, ST_X(crossing.{GEOMETRY}) AS lon
+                     FROM session_crossing AS passed
+                     JOIN water_crossing AS crossing ON passed.crossing_id = crossing.crossing_id
+                     WHERE passed.session_id IN ({sessions})
+                     ORDER BY passed.crossed_at, passed.crossing_id"
+                ))
+                .await?
+        }
+        false => Vec::new(),
+    };
+
+    Ok(gpx(&points, &waypoints))
+}
+
+/// GPX 1.1 of a track per session through `points`, which are ordered within each session,
+/// and `waypoints` for the crossings passed.
+pub fn gpx(points: &[TrackPoint], waypoints: &[Waypoint]) -> String {
+    let mut gpx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str(&format!(
+        "<gpx version=\"1.1\" creator=\"{CREATOR}\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n"
+    ));
+    // GPX puts every waypoint before the first track.
+    for waypoint in waypoints {
+        gpx.push_str(&format!(
+            "  <wpt lat=\"{}\" lon=\"{}\">\n    <time>{}</time>\n    <name>{}</name>\n    \
+             <desc>passed {:.0} m away in session {}</desc>\n  </wpt>\n",
+            waypoint.lat,
+            waypoint.lon,
+            instant(waypoint.crossed_at),
+            escaped(&waypoint.crossing_id),
+            waypoint.distance_m,
+            escaped(&waypoint.session_id),
+        ));
+    }
+    for session in points.chunk_by(|a, b| a.session_id == b.session_id) {
+        gpx.push_str(&format!(
+            "  <trk>\n    <name>{}</name>\n    <trkseg>\n",
+            escaped(&session[0].session_id)
+        ));
+        for point in session {
+            let elevation = point
+                .alt
+                .map(|alt| format!("<ele>{alt}</ele>"))
+                .unwrap_or_default();
+            gpx.push_str(&format!(
+                "      <trkpt lat=\"{}\" lon=\"{}\">{elevation}<time>{}</time></trkpt>\n",
+                point.lat,
+                point.lon,
+                instant(point.t)
+            ));
+        }
+        gpx.push_str("    </trkseg>\n  </trk>\n");
+    }
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+/// An instant as GPX writes one: UTC, to the millisecond where it has one.
+fn instant(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// `text` safe to stand between XML tags or inside an attribute.
+fn escaped(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The columns of `batch` an export carries as they are: every one but the geometry and
+/// what the store keeps beside it, with a column of several fields spread over one per field.
+fn kept(batch: &RecordBatch) -> Result<RecordBatch, ExportError> {
+    let left_out = [
+        GEOMETRY.to_string(),
+        PROJECTED_GEOMETRY.to_string(),
+        medallion::covering_name(GEOMETRY),
+        medallion::covering_name(PROJECTED_GEOMETRY),
+    ];
+    let mut fields: Vec<FieldRef> = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        if left_out.contains(field.name()) {
+            continue;
+        }
+        match column.as_any().downcast_ref::<StructArray>() {
+            Some(parts) => {
+                for (part, values) in parts.fields().iter().zip(parts.columns()) {
+                    fields.push(Arc::new(Field::new(
+                        format!("{}_{}", field.name(), part.name()),
+                        part.data_type().clone(),
+                        true,
+                    )));
+                    columns.push(values.clone());
+                }
+            }
+            None => {
+                fields.push(field.clone());
+                columns.push(column.clone());
+            }
+        }
+    }
+    // Counted rather than read off the columns, since every one may have been left out.
+    let rows = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
+    Ok(RecordBatch::try_new_with_options(
+        Arc::new(Schema::new(fields)),
+        columns,
+        &rows,
+    )?)
+}
+
+/// Each row of `batch`'s kept columns as a JSON object, a null value written as `null`, so
+/// every feature carries every column.
+fn properties(batch: &RecordBatch) -> Result<Vec<Map<String, Value>>, ExportError> {
+    let kept = kept(batch)?;
+    if kept.num_columns() == 0 {
+        return Ok(vec![Map::new(); kept.num_rows()]);
+    }
+    let mut writer = arrow::json::WriterBuilder::new()
+        .with_explicit_nulls(true)
+        .build::<_, arrow::json::writer::JsonArray>(Vec::new());
+    writer.write(&kept)?;
+    writer.finish()?;
+    let bytes = writer.into_inner();
+    match bytes.is_empty() {
+        // Nothing is written for a batch of no rows, not even the brackets.
+        true => Ok(Vec::new()),
+        false => Ok(serde_json::from_slice(&bytes)?),
+    }
+}
+
+/// `geometry` as a GeoJSON geometry object.
+fn geojson_geometry(geometry: &Geometry<f64>) -> Value {
+    let (kind, coordinates) = match geometry {
+        Geometry::Point(point) => ("Point", position(&point.0)),
+        Geometry::Line(line) => (
+            "LineString",
+            json!([position(&line.start), position(&line.end)]),
+        ),
+        Geometry::LineString(line) => ("LineString", positions(line)),
+        Geometry::Polygon(polygon) => ("Polygon", rings(polygon)),
+        Geometry::Rect(rect) => ("Polygon", rings(&rect.to_polygon())),
+        Geometry::Triangle(triangle) => ("Polygon", rings(&triangle.to_polygon())),
+        Geometry::MultiPoint(points) => (
+            "MultiPoint",
+            points.iter().map(|point| position(&point.0)).collect(),
+        ),
+        Geometry::MultiLineString(lines) => {
+            ("MultiLineString", lines.iter().map(positions).collect())
+        }
+        Geometry::MultiPolygon(polygons) => ("MultiPolygon", polygons.iter().map(rings).collect()),
+        Geometry::GeometryCollection(collection) => {
+            return json!({
+                "type": "GeometryCollection",
+                "geometries": collection.iter().map(geojson_geometry).collect::<Vec<_>>(),
+            });
+        }
+    };
+    json!({ "type": kind, "coordinates": coordinates })
+}
+
+fn position(coord: &Coord<f64>) -> Value {
+    json!([coord.x, coord.y])
+}
+
+fn positions(line: &LineString<f64>) -> Value {
+    line.coords().map(position).collect()
+}
+
+fn rings(polygon: &Polygon<f64>) -> Value {
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(positions)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Float64Array, Int64Array};
+    use chrono::TimeZone;
+    use geo_types::Point;
+
+    use super::*;
+
+    /// Two crossings, as silver holds them: an id, a length, the geometry twice, and the
+    /// covering the writer adds.
+    fn crossings() -> RecordBatch {
+        let at = [Point::new(13.4, 52.52), Point::new(11.58, 48.14)];
+        let (geometry, lat_lon) =
+            medallion::wkb_column(medallion::wkb_field(GEOMETRY).unwrap(), &at).unwrap();
+        let (projected_field, projected) = medallion::wkb_column(
+            medallion::projected_wkb_field(PROJECTED_GEOMETRY, Country::GERMANY).unwrap(),
+            &at,
+        )
+        .unwrap();
+        let envelope = StructArray::from(vec![
+            (
+                Arc::new(Field::new("xmin", DataType::Float64, true)),
+                Arc::new(Float64Array::from(vec![13.4, 11.58])) as ArrayRef,
+            ),
+            (
+                Arc::new(Field::new("ymin", DataType::Float64, true)),
+                Arc::new(Float64Array::from(vec![52.52, 48.14])) as ArrayRef,
+            ),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Arc::new(Field::new("crossing_id", DataType::Utf8, false)),
+                Arc::new(Field::new("length", DataType::Int64, false)),
+                geometry,
+                projected_field,
+                Arc::new(Field::new("bbox", envelope.data_type().clone(), false)),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                Arc::new(Int64Array::from(vec![12, 40])),
+                lat_lon,
+                projected,
+                Arc::new(envelope),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn each_row_is_a_feature_with_its_columns_as_properties() {
+        let collection: Value = serde_json::from_str(&geojson(&[crossings()]).unwrap()).unwrap();
+
+        assert_eq!(collection["type"], "FeatureCollection");
+        let first = &collection["features"][0];
+        assert_eq!(first["geometry"]["type"], "Point");
+        assert_eq!(first["geometry"]["coordinates"], json!([13.4, 52.52]));
+        assert_eq!(first["properties"]["crossing_id"], "a");
+        assert_eq!(first["properties"]["length"], 12);
+        assert_eq!(collection["features"][1]["properties"]["crossing_id"], "b");
+    }
+
+    /// Only the lat/lon geometry leaves the store, as the feature's own; a struct column is
+    /// spread over one property per field.
+    #[test]
+    fn the_stores_own_columns_are_left_out_and_structs_spread() {
+        let collection: Value = serde_json::from_str(&geojson(&[crossings()]).unwrap()).unwrap();
+
+        let properties = collection["features"][0]["properties"].as_object().unwrap();
+        let mut names: Vec<&str> = properties.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["bbox_xmin", "bbox_ymin", "crossing_id", "length"]);
+    }
+
+    #[test]
+    fn a_line_is_a_geojson_line_string() {
+        let line = Geometry::LineString(LineString::from(vec![(1.0, 2.0), (3.0, 4.0)]));
+
+        assert_eq!(
+            geojson_geometry(&line),
+            json!({ "type": "LineString", "coordinates": [[1.0, 2.0], [3.0, 4.0]] })
+        );
+    }
+
+    #[test]
+    fn csv_carries_the_geometry_as_wkt() {
+        let csv = csv(&[crossings()]).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "crossing_id,length,bbox_xmin,bbox_ymin,geometry"
+        );
+        assert_eq!(lines.next().unwrap(), "a,12,13.4,52.52,POINT(13.4 52.52)");
+    }
+
+    /// [`crossings`], with a note on the second that the first has none of.
+    fn noted_crossings() -> RecordBatch {
+        let crossings = crossings();
+        let mut fields: Vec<FieldRef> = crossings.schema().fields().iter().cloned().collect();
+        let mut columns = crossings.columns().to_vec();
+        fields.push(Arc::new(Field::new("note", DataType::Utf8, true)));
+        columns.push(Arc::new(StringArray::from(vec![
+            None,
+            Some("by the bridge"),
+        ])));
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+    }
+
+    /// A column null in the first row is still a property of every feature.
+    #[test]
+    fn a_null_is_a_property_written_as_null() {
+        let collection: Value =
+            serde_json::from_str(&geojson(&[noted_crossings()]).unwrap()).unwrap();
+
+        assert_eq!(collection["features"][0]["properties"]["note"], Value::Null);
+        assert!(
+            collection["features"][0]["properties"]
+                .as_object()
+                .unwrap()
+                .contains_key("note")
+        );
+        assert_eq!(
+            collection["features"][1]["properties"]["note"],
+            "by the bridge"
+        );
+    }
+
+    /// The file declares every kept column, whatever its first row holds, and reads back as
+    /// the rows it was written from: a null is a property the feature leaves out.
+    #[test]
+    fn flatgeobuf_reads_back_as_the_rows_written() {
+        use flatgeobuf::{FallibleStreamingIterator, FgbReader};
+        use geozero::FeatureProperties;
+
+        let bytes = flatgeobuf("water_crossing", &[noted_crossings()]).unwrap();
+
+        assert_eq!(&bytes[..3], b"fgb");
+        let reader = FgbReader::open(std::io::Cursor::new(&bytes)).unwrap();
+        let columns: Vec<String> = reader
+            .header()
+            .columns()
+            .unwrap()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        assert_eq!(
+            columns,
+            ["crossing_id", "length", "bbox_xmin", "bbox_ymin", "note"]
+        );
+
+        let mut features = reader.select_all().unwrap();
+        let mut rows = Vec::new();
+        while let Some(feature) = features.next().unwrap() {
+            rows.push(feature.properties().unwrap());
+        }
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["crossing_id"], "a");
+        assert_eq!(rows[0]["length"], "12");
+        assert_eq!(rows[0]["bbox_ymin"], "52.52");
+        assert_eq!(rows[0].get("note"), None);
+        assert_eq!(rows[1]["crossing_id"], "b");
+        assert_eq!(rows[1]["note"], "by the bridge");
+    }
+
+    fn point(session: &str, minute: u32, lon: f64) -> TrackPoint {
+        TrackPoint {
+            session_id: session.to_string(),
+            t: Utc.with_ymd_and_hms(2026, 7, 22, 9, minute, 0).unwrap(),
+            lat: 52.52,
+            lon,
+            alt: None,
+        }
+    }
+
+    #[test]
+    fn each_session_is_a_track_and_each_pass_a_waypoint() {
+        let points = [
+            point("one", 0, 13.40),
+            point("one", 1, 13.41),
+            point("two", 5, 13.50),
+        ];
+        let waypoints = [Waypoint {
+            session_id: "one".to_string(),
+            crossing_id: "spree<1>".to_string(),
+            crossed_at: Utc.with_ymd_and_hms(2026, 7, 22, 9, 1, 0).unwrap(),
+            distance_m: 41.6,
+            lat: 52.52,
+            lon: 13.41,
+        }];
+
+        let gpx = gpx(&points, &waypoints);
+
+        assert_eq!(gpx.matches("<trk>").count(), 2);
+        assert_eq!(gpx.matches("<trkpt ").count(), 3);
+        assert!(gpx.contains("<name>one</name>"));
+        assert!(gpx.contains("<time>2026-07-22T09:01:00Z</time>"));
+        assert!(gpx.contains("<name>spree&lt;1&gt;</name>"));
+        assert!(gpx.contains("passed 42 m away in session one"));
+        assert!(
+            gpx.find("<wpt ").unwrap() < gpx.find("<trk>").unwrap(),
+            "GPX lists waypoints before tracks"
+        );
+    }
+
+    #[test]
+    fn a_selection_is_every_condition_given() {
+        let everything = Selection::default();
+        let narrowed = Selection {
+            country: Some(Country::GERMANY),
+            predicate: Some("session_id = 'a' OR session_id = 'b'".to_string()),
+            ..Selection::default()
+        };
+
+        assert_eq!(everything.condition(), "TRUE");
+        assert_eq!(
+            narrowed.condition(),
+            "country = 'DE' AND (session_id = 'a' OR session_id = 'b')"
+        );
+    }
+}
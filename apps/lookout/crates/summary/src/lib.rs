@@ -5,14 +5,23 @@
 //! one per partition. What each layer is for is not restated here: a summary describes the
 //! store in front of it, not the design.
 
-use medallion::Layer;
+pub mod export;
+pub mod sql;
+
+use medallion::diff::{Diff, PartitionDiff, Side};
+use medallion::gold::Collected;
+use medallion::lineage::{Derivation, Since};
 use medallion::summary::{ArtefactSummary, Contents, DatasetSummary, PartitionSummary};
+use medallion::verify::Verification;
+use medallion::{Cleaned, Compaction, Layer};
 
 /// The layers reported, in the order data flows through them.
 const LAYERS: [Layer; 4] = [Layer::Landing, Layer::Bronze, Layer::Silver, Layer::Gold];
 
 /// What is shown of a dataset holding nothing, in place of its measurements.
 const ABSENT: &str = "absent";
+/// What is shown of an artefact none of whose versions has been promoted to live.
+const NOT_LIVE: &str = "none live";
 
 /// How much of each dataset to show.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,6 +67,288 @@ pub fn report(
     lay_out(&rows)
 }
 
+/// The report for one compaction run: per dataset, what a reader reads before and after.
+///
+/// Rows are not shown: a compaction that changed them would be a broken one, and the test
+/// for that belongs with the compaction rather than in what an operator reads.
+pub fn compaction_report(compactions: &[(&str, Compaction)]) -> String {
+    let lines: Vec<[String; 4]> = compactions
+        .iter()
+        .map(|(name, compaction)| {
+            [
+                name.to_string(),
+                format!(
+                    "{} → {}",
+                    count(compaction.before.files as u64, "file"),
+                    count(compaction.after.files as u64, "file")
+                ),
+                format!(
+                    "{} → {}",
+                    size(compaction.before.bytes),
+                    size(compaction.after.bytes)
+                ),
+                match compaction.partitions {
+                    0 => "nothing to compact".to_string(),
+                    partitions => format!("{} compacted", count(partitions as u64, "partition")),
+                },
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 3];
+    for line in &lines {
+        for (width, column) in widths.iter_mut().zip(line) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for [name, files, bytes, partitions] in &lines {
+        let [name_width, files_width, bytes_width] = widths;
+        out.push_str(&format!(
+            "  {name:name_width$}  {files:>files_width$}  {bytes:>bytes_width$}  {partitions}\n"
+        ));
+    }
+    out
+}
+
+/// The report for one clean: per dataset, the unpublished files it deleted.
+pub fn clean_report(cleaned: &[(&str, Cleaned)]) -> String {
+    let width = cleaned
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (name, cleaned) in cleaned {
+        let outcome = match cleaned.files {
+            0 => "nothing to clean".to_string(),
+            files => format!(
+                "{} deleted, {}",
+                count(files as u64, "unpublished file"),
+                size(cleaned.bytes)
+            ),
+        };
+        out.push_str(&format!("  {name:width$}  {outcome}\n"));
+    }
+    out
+}
+
+/// The report for one collection of gold: per artefact, the versions it removed.
+pub fn collection_report(collected: &[(&str, Collected)]) -> String {
+    let width = collected
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (name, collected) in collected {
+        let outcome = match collected.versions.len() {
+            0 => "nothing to collect".to_string(),
+            versions => format!(
+                "{} removed, {}",
+                count(versions as u64, "version"),
+                size(collected.bytes)
+            ),
+        };
+        out.push_str(&format!("  {name:width$}  {outcome}\n"));
+    }
+    out
+}
+
+/// The report for one verification: per layer, each dataset's line with the files read and
+/// whether they hold to its definition, and below a dataset that does not, every problem.
+///
+/// Every problem is listed rather than a count of them, since the report is what an operator
+/// fixes the store from.
+pub fn verification_report(verifications: &[Verification]) -> String {
+    let width = verifications
+        .iter()
+        .map(|verification| verification.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let files_width = verifications
+        .iter()
+        .map(|verification| count(verification.files as u64, "file").chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for layer in LAYERS {
+        let of_layer: Vec<&Verification> = verifications
+            .iter()
+            .filter(|verification| verification.layer == layer)
+            .collect();
+        if of_layer.is_empty() {
+            continue;
+        }
+
+        out.push_str(&format!("\n{}\n", layer.as_str()));
+        for verification in of_layer {
+            let files = count(verification.files as u64, "file");
+            let verdict = match verification.problems.len() {
+                0 => "sound".to_string(),
+                problems => count(problems as u64, "problem"),
+            };
+            out.push_str(&format!(
+                "  {:width$}  {files:>files_width$}  {verdict}\n",
+                verification.name
+            ));
+            for problem in &verification.problems {
+                out.push_str(&format!("    {problem}\n"));
+            }
+        }
+    }
+    out
+}
+
+/// The report for one comparison of two stores: per layer, each dataset's line with its
+/// partitions and whether any differ, and below a dataset that differs, each partition that
+/// does with the rows it gained, lost and changed and how the changed rows' columns differ.
+///
+/// A partition both stores hold the same rows in is counted rather than listed: a rerun over
+/// unchanged inputs should produce nothing else, and it is the rest that needs reading.
+pub fn diff_report(diffs: &[Diff]) -> String {
+    let width = diffs
+        .iter()
+        .map(|diff| diff.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let partitions_of =
+        |diff: &Diff| count((diff.partitions.len() + diff.unchanged) as u64, "partition");
+    let partitions_width = diffs
+        .iter()
+        .map(|diff| partitions_of(diff).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for layer in LAYERS {
+        let of_layer: Vec<&Diff> = diffs.iter().filter(|diff| diff.layer == layer).collect();
+        if of_layer.is_empty() {
+            continue;
+        }
+
+        out.push_str(&format!("\n{}\n", layer.as_str()));
+        for diff in of_layer {
+            let verdict = match (diff.partitions.len(), diff.key) {
+                (0, _) => "identical".to_string(),
+                (differing, Some(key)) => format!(
+                    "{differing} differ, rows keyed on {}",
+                    key.iter()
+                        .map(|column| format!("`{column}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                (differing, None) => format!("{differing} differ, rows compared whole"),
+            };
+            out.push_str(&format!(
+                "  {:width$}  {:>partitions_width$}  {verdict}\n",
+                diff.name,
+                partitions_of(diff)
+            ));
+            for partition in &diff.partitions {
+                out.push_str(&format!(
+                    "    {}: {}\n",
+                    match partition.partition.as_os_str().is_empty() {
+                        true => "every row".to_string(),
+                        false => partition.partition.display().to_string(),
+                    },
+                    partition_changes(partition)
+                ));
+                for column in &partition.columns {
+                    out.push_str(&format!("      {column}\n"));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// What one partition gained, lost and changed, leaving out what it did not.
+fn partition_changes(partition: &PartitionDiff) -> String {
+    let mut changes = Vec::new();
+    match partition.only {
+        Some(Side::Before) => changes.push("only before".to_string()),
+        Some(Side::After) => changes.push("only after".to_string()),
+        None => {}
+    }
+    for (rows, change) in [
+        (partition.added, "added"),
+        (partition.removed, "removed"),
+        (partition.changed, "changed"),
+    ] {
+        if rows > 0 {
+            changes.push(format!("{} {change}", count(rows as u64, "row")));
+        }
+    }
+    match changes.is_empty() {
+        true => "columns differ".to_string(),
+        false => changes.join(", "),
+    }
+}
+
+/// The report for one dataset's lineage: each lineage its live files record, with the files
+/// recording it, and below it every input it names and how that input stands now.
+///
+/// A lineage is `stale` once any input has changed or gone, which is the dataset asking to
+/// be derived again; files that record no lineage are counted, since nothing can be said of
+/// what they were derived from.
+pub fn lineage_report(name: &str, derivations: &[Derivation]) -> String {
+    let mut out = format!("{name}\n");
+    if derivations.is_empty() {
+        out.push_str("  holds nothing to trace\n");
+    }
+    for derivation in derivations {
+        let files = count(derivation.files as u64, "file");
+        let Some(lineage) = &derivation.lineage else {
+            out.push_str(&format!("  {files}  no lineage recorded\n"));
+            continue;
+        };
+        let verdict = match derivation.is_stale() {
+            true => "stale",
+            false => "current",
+        };
+        let mut heading = format!("  {files}  {} at {}", lineage.producer, lineage.git_hash);
+        for (parameter, value) in &lineage.parameters {
+            heading.push_str(&format!("  {parameter}={value}"));
+        }
+        out.push_str(&format!("{heading}  {verdict}\n"));
+
+        let inputs: Vec<(String, String)> = lineage
+            .inputs
+            .iter()
+            .map(|input| {
+                let place = match input.partition.is_empty() {
+                    true => input.dataset.clone(),
+                    false => format!("{}/{}", input.dataset, input.partition),
+                };
+                (place, count(input.files as u64, "file"))
+            })
+            .collect();
+        let place_width = inputs
+            .iter()
+            .map(|(place, _)| place.chars().count())
+            .max()
+            .unwrap_or(0);
+        let files_width = inputs
+            .iter()
+            .map(|(_, files)| files.chars().count())
+            .max()
+            .unwrap_or(0);
+        for ((place, files), since) in inputs.iter().zip(&derivation.since) {
+            let since = match since {
+                Since::Unchanged => "unchanged",
+                Since::Changed => "changed since",
+                Since::Gone => "gone",
+            };
+            out.push_str(&format!(
+                "    {place:place_width$}  {files:>files_width$}  {since}\n"
+            ));
+        }
+    }
+    out
+}
+
 /// One dataset's line, and its partitions' lines when they were asked for.
 fn dataset_rows(dataset: &DatasetSummary, detail: Detail) -> Vec<Row> {
     let mut rows = vec![Row::of(
@@ -88,21 +379,26 @@ fn artefact_rows(artefact: &ArtefactSummary, detail: Detail) -> Vec<Row> {
         });
     let versions = match artefact.versions.last() {
         Some(latest) => format!(
-            "{} versions, latest {}",
+            "{} versions, latest {}, {}",
             artefact.versions.len(),
-            latest.version
+            latest.version,
+            match &artefact.live {
+                Some(live) => format!("live {live}"),
+                None => NOT_LIVE.to_string(),
+            }
         ),
         None => ABSENT.to_string(),
     };
 
     let mut rows = vec![Row::of(&artefact.artifact, contents, &versions)];
     if detail == Detail::Partitions {
-        rows.extend(
-            artefact
-                .versions
-                .iter()
-                .map(|version| Row::of(&indent(&version.version), version.contents, "")),
-        );
+        rows.extend(artefact.versions.iter().map(|version| {
+            let live = match artefact.live.as_deref() == Some(version.version.as_str()) {
+                true => "live",
+                false => "",
+            };
+            Row::of(&indent(&version.version), version.contents, live)
+        }));
     }
     rows
 }
@@ -240,7 +536,12 @@ fn size(bytes: u64) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
+    use medallion::diff::ColumnDiff;
+    use medallion::lineage::{Input, Lineage};
     use medallion::summary::VersionSummary;
+    use medallion::verify::Problem;
 
     use super::*;
 
@@ -360,18 +661,268 @@ mod tests {
                     contents: contents(1, 0, 4096),
                 },
             ],
+            live: None,
         }];
 
         let report = report(&[], &artefacts, Detail::Datasets);
 
         assert!(report.contains("gold"), "{report}");
         assert!(
-            report.contains("2 versions, latest 20260727T090000Z"),
+            report.contains("2 versions, latest 20260727T090000Z, none live"),
             "{report}"
         );
         assert!(report.contains("6.0 KiB"), "{report}");
     }
 
+    /// Which version a device or the website is given is the question a reader of gold is
+    /// asking, so the live one is named, and marked among the versions.
+    #[test]
+    fn an_artefacts_live_version_is_named_and_marked() {
+        let artefacts = [ArtefactSummary {
+            artifact: "crossings".to_string(),
+            versions: vec![
+                VersionSummary {
+                    version: "20260726T090000Z".to_string(),
+                    contents: contents(1, 0, 2048),
+                },
+                VersionSummary {
+                    version: "20260727T090000Z".to_string(),
+                    contents: contents(1, 0, 4096),
+                },
+            ],
+            live: Some("20260726T090000Z".to_string()),
+        }];
+
+        let report = report(&[], &artefacts, Detail::Partitions);
+
+        assert!(
+            report.contains("latest 20260727T090000Z, live 20260726T090000Z"),
+            "{report}"
+        );
+        let marked: Vec<_> = report
+            .lines()
+            .filter(|line| line.trim_end().ends_with("live"))
+            .collect();
+        assert_eq!(marked.len(), 1, "{report}");
+        assert!(marked[0].contains("20260726T090000Z"), "{report}");
+    }
+
+    #[test]
+    fn a_compaction_is_reported_as_what_it_left_against_what_it_found() {
+        let compacted = Compaction {
+            partitions: 2,
+            before: contents(1_307, 5_000, 3 << 20),
+            after: contents(2, 5_000, 2 << 20),
+        };
+        let untouched = Compaction {
+            partitions: 0,
+            before: contents(1, 10, 512),
+            after: contents(1, 10, 512),
+        };
+
+        let report = compaction_report(&[("motis_segment", compacted), ("gps_reading", untouched)]);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].contains("1,307 files → 2 files"), "{report}");
+        assert!(lines[0].contains("3.0 MiB → 2.0 MiB"), "{report}");
+        assert!(lines[0].ends_with("2 partitions compacted"), "{report}");
+        assert!(lines[1].ends_with("nothing to compact"), "{report}");
+    }
+
+    #[test]
+    fn a_clean_is_reported_as_what_it_deleted() {
+        let report = clean_report(&[
+            (
+                "session",
+                Cleaned {
+                    files: 3,
+                    bytes: 3 << 20,
+                },
+            ),
+            ("water_crossing", Cleaned::default()),
+        ]);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(
+            lines[0].ends_with("3 unpublished files deleted, 3.0 MiB"),
+            "{report}"
+        );
+        assert!(lines[1].ends_with("nothing to clean"), "{report}");
+        assert_eq!(lines[0].find("3 "), lines[1].find("nothing"), "{report}");
+    }
+
+    #[test]
+    fn a_collection_is_reported_as_what_it_removed() {
+        let report = collection_report(&[
+            (
+                "crossings",
+                Collected {
+                    versions: vec!["20260801T194857000Z".into(), "20260802T194857000Z".into()],
+                    bytes: 140_000,
+                },
+            ),
+            ("crossings_tiles", Collected::default()),
+        ]);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(
+            lines[0].ends_with("2 versions removed, 136.7 KiB"),
+            "{report}"
+        );
+        assert!(lines[1].ends_with("nothing to collect"), "{report}");
+    }
+
+    #[test]
+    fn a_verification_lists_every_problem_below_its_dataset() {
+        let verifications = [
+            Verification {
+                layer: Layer::Bronze,
+                name: "gps_reading",
+                files: 1_204,
+                problems: vec![],
+            },
+            Verification {
+                layer: Layer::Silver,
+                name: "session",
+                files: 3,
+                problems: vec![
+                    Problem::Missing {
+                        file: PathBuf::from("country=DE/start_date=2026-07-21/part-a.parquet"),
+                    },
+                    Problem::Unreadable {
+                        file: PathBuf::from("country=DE/start_date=2026-07-22/part-b.parquet"),
+                        reason: "EOF".to_string(),
+                    },
+                ],
+            },
+        ];
+
+        let report = verification_report(&verifications);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[1], "bronze", "{report}");
+        assert!(lines[2].ends_with("1,204 files  sound"), "{report}");
+        assert_eq!(lines[4], "silver", "{report}");
+        assert!(lines[5].ends_with("3 files  2 problems"), "{report}");
+        assert_eq!(
+            lines[6],
+            "    country=DE/start_date=2026-07-21/part-a.parquet: published but not in the store"
+        );
+        assert!(
+            lines[7].ends_with("part-b.parquet: unreadable: EOF"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn a_diff_lists_each_differing_partition_below_its_dataset() {
+        let diffs = [
+            Diff {
+                layer: Layer::Silver,
+                name: "session",
+                key: Some(&["session_id"][..]),
+                partitions: vec![],
+                unchanged: 12,
+            },
+            Diff {
+                layer: Layer::Silver,
+                name: "session_sample",
+                key: None,
+                partitions: vec![
+                    PartitionDiff {
+                        partition: PathBuf::from("country=DE/sample_date=2026-07-21"),
+                        only: None,
+                        added: 2,
+                        removed: 1,
+                        changed: 0,
+                        columns: vec![ColumnDiff::Only {
+                            column: "bearing".to_string(),
+                            side: Side::After,
+                        }],
+                    },
+                    PartitionDiff {
+                        partition: PathBuf::from("country=DE/sample_date=2026-07-22"),
+                        only: Some(Side::Before),
+                        added: 0,
+                        removed: 40,
+                        changed: 0,
+                        columns: vec![],
+                    },
+                ],
+                unchanged: 38,
+            },
+        ];
+
+        let report = diff_report(&diffs);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[1], "silver", "{report}");
+        assert!(lines[2].ends_with("12 partitions  identical"), "{report}");
+        assert!(
+            lines[3].ends_with("40 partitions  2 differ, rows compared whole"),
+            "{report}"
+        );
+        assert_eq!(
+            lines[4],
+            "    country=DE/sample_date=2026-07-21: 2 rows added, 1 row removed"
+        );
+        assert_eq!(lines[5], "      column `bearing` is only held after");
+        assert_eq!(
+            lines[6],
+            "    country=DE/sample_date=2026-07-22: only before, 40 rows removed"
+        );
+    }
+
+    /// Each input is shown with how it stands now, so a stale dataset says which of what it
+    /// was derived from moved on.
+    #[test]
+    fn a_lineage_lists_its_inputs_and_how_each_stands_now() {
+        let input = |partition: &str, files| Input {
+            dataset: "bronze/gps_reading".to_string(),
+            partition: partition.to_string(),
+            files,
+            md5: "0".repeat(32),
+        };
+        let derivations = [
+            Derivation {
+                files: 3,
+                lineage: Some(Lineage {
+                    producer: "sessionise".to_string(),
+                    git_hash: "a7d61b9".to_string(),
+                    parameters: [("gap_mins".to_string(), "30".to_string())].into(),
+                    inputs: vec![
+                        input("ingested_date=2026-07-27", 12),
+                        input("ingested_date=2026-07-28", 1),
+                    ],
+                }),
+                since: vec![Since::Unchanged, Since::Changed],
+            },
+            Derivation {
+                files: 1,
+                lineage: None,
+                since: Vec::new(),
+            },
+        ];
+
+        let report = lineage_report("session", &derivations);
+
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "session");
+        assert_eq!(
+            lines[1],
+            "  3 files  sessionise at a7d61b9  gap_mins=30  stale"
+        );
+        assert_eq!(
+            lines[2],
+            "    bronze/gps_reading/ingested_date=2026-07-27  12 files  unchanged"
+        );
+        assert_eq!(
+            lines[3],
+            "    bronze/gps_reading/ingested_date=2026-07-28    1 file  changed since"
+        );
+        assert_eq!(lines[4], "  1 file  no lineage recorded");
+    }
+
     #[test]
     fn counts_are_grouped_for_reading_and_pluralised() {
         assert_eq!(count(0, "row"), "0 rows");
@@ -0,0 +1,250 @@
+//! Showing what an ad-hoc query returned, in the form whoever asked can use.
+//!
+//! A table for reading in a terminal, CSV for a spreadsheet, JSON for a script. Values are
+//! shown as arrow displays them, so a geometry comes out as the hex of its WKB: a query that
+//! wants it readable asks for `ST_AsText(geometry)` rather than this guessing at it.
+//!
+//! CSV holds no nested values, so there a struct's fields are columns of their own, named
+//! for the path to them — a session's `bbox` is `bbox.xmin` through `bbox.ymax` — and a
+//! list or a map is written as its JSON text.
+
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, AsArray, RecordBatch, RecordBatchOptions, StringArray};
+use arrow::compute::{is_null, nullif};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+
+/// How a query's result is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Aligned columns, for reading.
+    Table,
+    /// A header line, then a line per row.
+    Csv,
+    /// One array of objects, a key per column.
+    Json,
+}
+
+/// `batches` written out as `format`. A result of no rows is still a result: a table or CSV
+/// of its header alone, where the batches say what the columns are, or an empty array.
+pub fn render(batches: &[RecordBatch], format: Format) -> Result<String, ArrowError> {
+    let bytes = match format {
+        Format::Table => {
+            return Ok(format!(
+                "{}\n",
+                arrow::util::pretty::pretty_format_batches(batches)?
+            ));
+        }
+        Format::Csv => {
+            let mut writer = arrow::csv::WriterBuilder::new()
+                .with_header(true)
+                .build(Vec::new());
+            for batch in batches {
+                writer.write(&flattened(batch)?)?;
+            }
+            writer.into_inner()
+        }
+        Format::Json => {
+            let mut writer = arrow::json::ArrayWriter::new(Vec::new());
+            writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+            writer.finish()?;
+            let mut bytes = writer.into_inner();
+            bytes.push(b'\n');
+            bytes
+        }
+    };
+    String::from_utf8(bytes).map_err(|err| ArrowError::ExternalError(Box::new(err)))
+}
+
+/// `batch` as CSV can hold it: each struct's fields in its place, and every other nested
+/// value as JSON text.
+fn flattened(batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        flatten(field.name(), column, &mut fields, &mut columns)?;
+    }
+    let options = RecordBatchOptions::new().with_row_count(Some(batch.num_rows()));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+}
+
+/// Add `column`, named `name`, to `fields` and `columns` as columns CSV can hold. A field of
+/// a struct that is null is null itself, whatever the struct's child holds there.
+fn flatten(
+    name: &str,
+    column: &ArrayRef,
+    fields: &mut Vec<Field>,
+    columns: &mut Vec<ArrayRef>,
+) -> Result<(), ArrowError> {
+    match column.data_type() {
+        DataType::Struct(_) => {
+            let nulls = is_null(column)?;
+            let parent = column.as_struct();
+            for (field, child) in parent.fields().iter().zip(parent.columns()) {
+                let child = match column.null_count() {
+                    0 => child.clone(),
+                    _ => nullif(child, &nulls)?,
+                };
+                flatten(&format!("{name}.{}", field.name()), &child, fields, columns)?;
+            }
+        }
+        nested if nested.is_nested() => {
+            fields.push(Field::new(name, DataType::Utf8, true));
+            columns.push(Arc::new(json_text(column)?));
+        }
+        data_type => {
+            fields.push(Field::new(name, data_type.clone(), true));
+            columns.push(column.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Each value of `column` as JSON text, as the JSON format writes it, and a null as a null.
+fn json_text(column: &ArrayRef) -> Result<StringArray, ArrowError> {
+    let batch = RecordBatch::try_from_iter([("value", column.clone())])?;
+    let mut writer = arrow::json::LineDelimitedWriter::new(Vec::new());
+    writer.write(&batch)?;
+    writer.finish()?;
+    let lines = String::from_utf8(writer.into_inner())
+        .map_err(|err| ArrowError::ExternalError(Box::new(err)))?;
+    lines
+        .lines()
+        .map(|line| {
+            let mut row: serde_json::Map<String, serde_json::Value> = serde_json::from_str(line)
+                .map_err(|err| ArrowError::ExternalError(Box::new(err)))?;
+            Ok(row.remove("value").map(|value| value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Array, Float64Array, Int64Array, ListArray, StructArray};
+    use arrow::buffer::NullBuffer;
+    use arrow::datatypes::{Fields, Int64Type};
+    use medallion::{Query, Root};
+
+    use super::*;
+
+    fn result() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_table_lines_up_its_columns() {
+        let table = render(&[result()], Format::Table).unwrap();
+
+        assert_eq!(
+            table,
+            "+----+------+\n\
+             | id | name |\n\
+             +----+------+\n\
+             | 1  | a    |\n\
+             | 2  |      |\n\
+             +----+------+\n"
+        );
+    }
+
+    #[test]
+    fn csv_has_a_header_then_a_line_per_row() {
+        assert_eq!(
+            render(&[result()], Format::Csv).unwrap(),
+            "id,name\n1,a\n2,\n"
+        );
+    }
+
+    /// A struct made by the query itself, so that no store need hold one.
+    #[tokio::test]
+    async fn a_query_selecting_a_struct_is_written_as_csv() {
+        let query = Query::new(Root::new(std::env::temp_dir()));
+        let batches = query
+            .sql(
+                "SELECT 's1' AS session_id, \
+                 named_struct('xmin', 13.4, 'ymax', 52.5) AS bbox",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            render(&batches, Format::Csv).unwrap(),
+            "session_id,bbox.xmin,bbox.ymax\ns1,13.4,52.5\n"
+        );
+    }
+
+    /// A session's envelope, as a query selecting it returns it: a struct, null for a
+    /// session with no samples to bound.
+    #[test]
+    fn csv_gives_a_null_struct_an_empty_column_per_field() {
+        let bounds = |values: Vec<f64>| Arc::new(Float64Array::from(values)) as ArrayRef;
+        let bbox = StructArray::new(
+            Fields::from(vec![
+                Field::new("xmin", DataType::Float64, false),
+                Field::new("ymax", DataType::Float64, false),
+            ]),
+            vec![bounds(vec![13.4, 0.0]), bounds(vec![52.5, 0.0])],
+            Some(NullBuffer::from(vec![true, false])),
+        );
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("session_id", DataType::Utf8, false),
+            Field::new("bbox", bbox.data_type().clone(), true),
+        ]));
+        let sessions = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["s1", "s2"])),
+                Arc::new(bbox),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            render(&[sessions], Format::Csv).unwrap(),
+            "session_id,bbox.xmin,bbox.ymax\ns1,13.4,52.5\ns2,,\n"
+        );
+    }
+
+    /// A null alone on its line is written as an empty quoted field, so the line is not blank.
+    #[test]
+    fn csv_writes_a_list_as_its_json() {
+        let ids = ListArray::from_iter_primitive::<Int64Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            Some(vec![]),
+            None,
+        ]);
+        let batch = RecordBatch::try_from_iter([("ids", Arc::new(ids) as ArrayRef)]).unwrap();
+
+        assert_eq!(
+            render(&[batch], Format::Csv).unwrap(),
+            "ids\n\"[1,2]\"\n[]\n\"\"\n"
+        );
+    }
+
+    /// A null is left out of its object, as arrow writes one, rather than spelled `null`.
+    #[test]
+    fn json_is_an_array_of_an_object_per_row() {
+        assert_eq!(
+            render(&[result()], Format::Json).unwrap(),
+            "[{\"id\":1,\"name\":\"a\"},{\"id\":2}]\n"
+        );
+    }
+
+    #[test]
+    fn no_rows_are_an_empty_array() {
+        assert_eq!(render(&[], Format::Json).unwrap(), "[]\n");
+    }
+}
@@ -0,0 +1,480 @@
+//! The telemetry queue kept as files in the store's landing layer, for a server with no redis
+//! to reach: a laptop on a train records to disk, and the recorder archives what it landed
+//! once there is time to.
+//!
+//! Samples are appended as [`RawSample`] JSON lines — the items the redis list holds, one per
+//! line — to a segment file per minute of receipt, named for that minute. The server only ever
+//! appends to the segment of the minute it is in, so a segment whose minute is over, and a
+//! few seconds besides for an append already under way, is finished and can be taken.
+//!
+//! A [`LandingConsumer`] takes a finished segment by renaming it into a processing directory
+//! of its own, and deletes it only once it is acknowledged, after it has been archived: the
+//! files' counterpart of a [`Consumer`](crate::Consumer)'s processing list. A drain that dies
+//! leaves its segment there, and the next under the same name takes it again before any
+//! other. A rename is atomic, so two consumers never both take one segment.
+//!
+//! A line that is not a sample envelope — the torn end of a segment the server was killed
+//! while writing — is handed over as the text it is rather than dropped, so the archive keeps
+//! it as it keeps any payload it cannot read. So is a last line with no newline after it,
+//! which the server never finished, and a line cut inside a character, whose bytes are not
+//! text: that is read lossily, and the samples beside it are read as ever. A server restarted within the same minute
+//! starts its first line in that segment on a line of its own, so the torn end spoils no
+//! sample after it.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::RawSample;
+
+/// Where the landed samples are kept, below the store's root.
+pub const LANDING_PATH: &str = "landing/telemetry";
+
+/// Below the landing directory, where each consumer's taken segments wait to be
+/// acknowledged, in a directory named for it.
+const PROCESSING_DIR: &str = "processing";
+
+/// The extension of a segment file.
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// How a segment is named from the minute it collects: sortable, so the oldest is first.
+const SEGMENT_NAME: &str = "%Y%m%dT%H%MZ";
+
+/// How long after its minute is over a segment is left alone: long enough for an append that
+/// began within the minute to have finished.
+const SETTLE: TimeDelta = TimeDelta::seconds(5);
+
+/// A failure landing a sample, or taking landed ones.
+#[derive(Debug, thiserror::Error)]
+pub enum LandingError {
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to serialize sample: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// What an I/O failure on `path` is reported as.
+fn at(path: &Path) -> impl FnOnce(io::Error) -> LandingError + '_ {
+    move |source| LandingError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// The landed telemetry in one directory, and what has been landed there through this.
+#[derive(Debug)]
+pub struct Landing {
+    dir: PathBuf,
+    /// The name of the segment last appended to, once the directory is known to exist and
+    /// the segment to end on a whole line.
+    current: Option<String>,
+    appended: usize,
+}
+
+impl Landing {
+    /// The landed telemetry in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            current: None,
+            appended: 0,
+        }
+    }
+
+    /// The landed telemetry of the store in the directory `root`, at [`LANDING_PATH`].
+    pub fn in_store(root: &Path) -> Self {
+        Self::new(root.join(LANDING_PATH))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append `sample` to the segment of the minute it was received in, and return how many
+    /// samples have been landed through this.
+    ///
+    /// The first append to a segment makes the directory if there is none, and starts on a
+    /// new line if the segment is already there ending part way through one, as a server
+    /// killed mid-write leaves it; later appends to it do neither. The line is written in one
+    /// call to a file opened for appending, so appends to the same segment never interleave
+    /// within a line; a caller appending from several threads at once still serialises them,
+    /// as one writing a line in more than one call would have to.
+    pub fn append(&mut self, sample: &RawSample) -> Result<usize, LandingError> {
+        let mut line = serde_json::to_string(sample)?;
+        line.push('\n');
+        let name = segment_name(sample.received_at());
+        let path = self.dir.join(&name);
+        let first = self.current.as_ref() != Some(&name);
+        if first {
+            fs::create_dir_all(&self.dir).map_err(at(&self.dir))?;
+            if ends_mid_line(&path).map_err(at(&path))? {
+                line.insert(0, '\n');
+            }
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(at(&path))?;
+        if first {
+            self.current = Some(name);
+        }
+        self.appended += 1;
+        Ok(self.appended)
+    }
+
+    /// The consumer called `name` of this landing directory.
+    pub fn consumer(&self, name: &str) -> LandingConsumer {
+        LandingConsumer {
+            landing: self.dir.clone(),
+            processing: self.dir.join(PROCESSING_DIR).join(name),
+        }
+    }
+}
+
+/// The landed telemetry's counterpart of a list [`Consumer`](crate::Consumer), taking a
+/// segment at a time rather than a sample. Named as one is, so a drain restarted under the
+/// same name finds the segment the last one left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LandingConsumer {
+    landing: PathBuf,
+    processing: PathBuf,
+}
+
+/// A segment taken and not yet acknowledged, with what it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    path: PathBuf,
+    samples: Vec<RawSample>,
+    torn: Vec<String>,
+}
+
+impl Segment {
+    /// The samples the segment holds, in the order they were landed.
+    pub fn samples(&self) -> &[RawSample] {
+        &self.samples
+    }
+
+    /// The lines that are not samples, as the text they are — with a character a line was
+    /// cut inside of replaced by U+FFFD.
+    pub fn torn(&self) -> &[String] {
+        &self.torn
+    }
+}
+
+impl LandingConsumer {
+    /// The directory holding what this consumer has taken and not acknowledged.
+    pub fn processing_dir(&self) -> &Path {
+        &self.processing
+    }
+
+    /// Say how many segments an earlier run under this name took and never acknowledged.
+    /// They stay where they are: [`take`](Self::take) takes them first.
+    pub fn recover(&self) -> Result<usize, LandingError> {
+        Ok(segments(&self.processing)?.len())
+    }
+
+    /// Take the oldest segment this consumer holds unacknowledged, or else the oldest landed
+    /// segment finished by `now`. Returns `None` when there is neither.
+    pub fn take(&self, now: DateTime<Utc>) -> Result<Option<Segment>, LandingError> {
+        if let Some(held) = segments(&self.processing)?.into_iter().next() {
+            return read(held).map(Some);
+        }
+        let current = segment_name((now - SETTLE).timestamp_millis());
+        fs::create_dir_all(&self.processing).map_err(at(&self.processing))?;
+        for landed in segments(&self.landing)? {
+            let name = landed
+                .file_name()
+                .expect("a segment is a file in the directory");
+            if name.to_string_lossy().as_ref() >= current.as_str() {
+                break;
+            }
+            let taken = self.processing.join(name);
+            match fs::rename(&landed, &taken) {
+                Ok(()) => return read(taken).map(Some),
+                // Another consumer took it first.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(at(&landed)(err)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Delete `segment`, once what it holds is archived.
+    pub fn acknowledge(&self, segment: Segment) -> Result<(), LandingError> {
+        fs::remove_file(&segment.path).map_err(at(&segment.path))
+    }
+}
+
+/// The segment file the sample received at `received_at` (epoch millis) is landed in.
+fn segment_name(received_at: i64) -> String {
+    let minute = DateTime::from_timestamp_millis(received_at).unwrap_or_default();
+    format!("{}.{SEGMENT_EXTENSION}", minute.format(SEGMENT_NAME))
+}
+
+/// Whether the segment at `path` ends part way through a line; a segment not there yet does
+/// not.
+fn ends_mid_line(path: &Path) -> io::Result<bool> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last = [0; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last != *b"\n")
+}
+
+/// The segment files in `dir`, oldest first; none if it does not exist yet.
+fn segments(dir: &Path) -> Result<Vec<PathBuf>, LandingError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(at(dir)(err)),
+    };
+    let mut segments = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(at(dir))?.path();
+        if path.is_file()
+            && path.extension().and_then(|extension| extension.to_str()) == Some(SEGMENT_EXTENSION)
+        {
+            segments.push(path);
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// What the segment at `path` holds.
+///
+/// Read as bytes and split on newlines rather than read as text: a server killed mid-append
+/// can cut its last line inside a multi-byte character, and that line is torn, not the
+/// segment. Were the segment refused, it would stay taken, and be retaken and refused by
+/// every drain after.
+fn read(path: PathBuf) -> Result<Segment, LandingError> {
+    let bytes = fs::read(&path).map_err(at(&path))?;
+    let mut samples = Vec::new();
+    let mut torn = Vec::new();
+    let mut lines = bytes.split(|byte| *byte == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        // What follows the last newline is nothing after a finished line, and otherwise one
+        // the server never finished.
+        let finished = lines.peek().is_some();
+        if line.is_empty() {
+            continue;
+        }
+        let sample = match (finished, std::str::from_utf8(line)) {
+            (true, Ok(text)) => serde_json::from_str(text).ok(),
+            _ => None,
+        };
+        match sample {
+            Some(sample) => samples.push(sample),
+            None => torn.push(String::from_utf8_lossy(line).into_owned()),
+        }
+    }
+    Ok(Segment {
+        path,
+        samples,
+        torn,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at_time(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 7, 26, hour, minute, second)
+            .unwrap()
+    }
+
+    fn sample(received: DateTime<Utc>, n: u32) -> RawSample {
+        RawSample::new(received.timestamp_millis(), format!("{{\"n\":{n}}}"))
+    }
+
+    #[test]
+    fn samples_land_in_a_segment_per_minute_of_receipt() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut landing = Landing::in_store(tmp.path());
+
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        landing
+            .append(&sample(at_time(14, 5, 59), 2))
+            .expect("append");
+        let appended = landing
+            .append(&sample(at_time(14, 6, 0), 3))
+            .expect("append");
+        assert_eq!(appended, 3);
+
+        assert!(
+            tmp.path()
+                .join("landing/telemetry/20260726T1405Z.jsonl")
+                .exists()
+        );
+    }
+
+    /// A segment is taken only once its minute, and the settling time after it, are over —
+    /// oldest first — and is gone once acknowledged.
+    #[test]
+    fn a_consumer_takes_finished_segments_oldest_first() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut landing = Landing::new(tmp.path());
+        landing
+            .append(&sample(at_time(14, 6, 0), 3))
+            .expect("append");
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        landing
+            .append(&sample(at_time(14, 5, 2), 2))
+            .expect("append");
+        let consumer = landing.consumer("test");
+
+        assert_eq!(consumer.take(at_time(14, 6, 4)).expect("take"), None);
+        let first = consumer
+            .take(at_time(14, 6, 5))
+            .expect("take")
+            .expect("the 14:05 segment is finished");
+        assert_eq!(
+            first.samples(),
+            [sample(at_time(14, 5, 1), 1), sample(at_time(14, 5, 2), 2)]
+        );
+        consumer.acknowledge(first).expect("acknowledge");
+
+        assert_eq!(consumer.take(at_time(14, 6, 5)).expect("take"), None);
+        let second = consumer
+            .take(at_time(14, 7, 5))
+            .expect("take")
+            .expect("the 14:06 segment is finished");
+        assert_eq!(second.samples(), [sample(at_time(14, 6, 0), 3)]);
+        consumer.acknowledge(second).expect("acknowledge");
+        assert_eq!(consumer.take(at_time(15, 0, 0)).expect("take"), None);
+    }
+
+    /// A segment taken and never acknowledged is taken again by the next consumer of the
+    /// same name, and by no other.
+    #[test]
+    fn an_unacknowledged_segment_is_taken_again_under_the_same_name() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut landing = Landing::new(tmp.path());
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        let later = at_time(15, 0, 0);
+
+        let taken = landing
+            .consumer("test")
+            .take(later)
+            .expect("take")
+            .expect("a segment");
+
+        assert_eq!(landing.consumer("other").take(later).expect("take"), None);
+        let restarted = landing.consumer("test");
+        assert_eq!(restarted.recover().expect("recover"), 1);
+        assert_eq!(restarted.take(later).expect("take"), Some(taken));
+    }
+
+    /// The torn end of a segment is handed over as text, beside the samples that are whole.
+    #[test]
+    fn a_line_that_is_not_a_sample_is_kept_as_text() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut landing = Landing::new(tmp.path());
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(tmp.path().join("20260726T1405Z.jsonl"))
+            .expect("open segment");
+        segment
+            .write_all(b"{\"received_at\":17")
+            .expect("write torn line");
+
+        let taken = landing
+            .consumer("test")
+            .take(at_time(15, 0, 0))
+            .expect("take")
+            .expect("a segment");
+        assert_eq!(taken.samples(), [sample(at_time(14, 5, 1), 1)]);
+        assert_eq!(taken.torn(), ["{\"received_at\":17"]);
+    }
+
+    /// A segment cut inside a multi-byte character is still read: the cut line is handed over
+    /// lossily as torn, and the segment can be archived and acknowledged like any other.
+    #[test]
+    fn a_line_cut_inside_a_character_is_torn_and_the_segment_still_read() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut landing = Landing::new(tmp.path());
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(tmp.path().join("20260726T1405Z.jsonl"))
+            .expect("open segment");
+        // The first of the two bytes of "ü", and nothing after it.
+        segment
+            .write_all(b"{\"received_at\":17,\"payload\":\"Gr\xc3")
+            .expect("write torn line");
+
+        let consumer = landing.consumer("test");
+        let taken = consumer
+            .take(at_time(15, 0, 0))
+            .expect("take")
+            .expect("a segment");
+        assert_eq!(taken.samples(), [sample(at_time(14, 5, 1), 1)]);
+        assert_eq!(
+            taken.torn(),
+            ["{\"received_at\":17,\"payload\":\"Gr\u{fffd}"]
+        );
+
+        consumer.acknowledge(taken).expect("acknowledge");
+        assert_eq!(consumer.take(at_time(15, 0, 0)).expect("take"), None);
+    }
+
+    /// A server restarted into a segment its last run tore starts a line of its own there,
+    /// so only the torn line is lost to the reader, not the sample after it.
+    #[test]
+    fn a_landing_reopening_a_torn_segment_starts_a_new_line() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(
+            tmp.path().join("20260726T1405Z.jsonl"),
+            "{\"received_at\":17",
+        )
+        .expect("write torn segment");
+
+        let mut landing = Landing::new(tmp.path());
+        landing
+            .append(&sample(at_time(14, 5, 1), 1))
+            .expect("append");
+        landing
+            .append(&sample(at_time(14, 5, 2), 2))
+            .expect("append");
+
+        let taken = landing
+            .consumer("test")
+            .take(at_time(15, 0, 0))
+            .expect("take")
+            .expect("a segment");
+        assert_eq!(
+            taken.samples(),
+            [sample(at_time(14, 5, 1), 1), sample(at_time(14, 5, 2), 2)]
+        );
+        assert_eq!(taken.torn(), ["{\"received_at\":17"]);
+    }
+}
@@ -2,7 +2,35 @@
 //! the `recorder` cli drains. This crate owns the wire contract shared by both — the
 //! queue key, how to connect over TLS, and how to read a sample back off — so neither
 //! side hard-codes it. The push adapter lives with the server (its only pusher).
+//!
+//! A drain reads through a [`Consumer`], which never holds a sample only in memory: taking
+//! one moves it onto the consumer's own processing list in the same command, and it leaves
+//! that list only once the consumer acknowledges it, after it has been archived. A drain
+//! that dies part way through therefore leaves its samples on the processing list rather
+//! than losing them, and [`Consumer::recover`] returns them to the queue when it restarts.
+//! The cost is that a sample archived but not yet acknowledged when a drain dies is archived
+//! twice — which bronze tolerates, as it tolerates any repeated observation.
+//!
+//! The queue can instead be kept as a redis stream — see [`stream`] — which every reader
+//! reads in full through a consumer group of its own, rather than one reader draining it
+//! and the others peeking. Which it is kept as is configuration, read by the server and by
+//! every reader alike from `LOOKOUT_QUEUE` ([`QueueKind::from_env`]); a [`Reader`] takes
+//! samples from either the same way.
+//!
+//! A frame the server cannot parse is not queued with the samples but kept apart, on the
+//! dead-letter list at [`DEAD_LETTER_KEY`], whichever way the queue is kept: no reader
+//! interpreting samples wants it, and the recorder archives it from there through a
+//! [`Consumer::dead_letters`] so that what a device sent is kept even when the server's
+//! parser was wrong about it.
+//!
+//! A server with no redis to reach can land samples as files in the store instead — see
+//! [`landing`] — which the recorder drains into bronze the way it drains the queue.
 
+pub mod landing;
+pub mod stream;
+
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 
 use redis::aio::MultiplexedConnection;
@@ -10,9 +38,76 @@ use redis::{AsyncConnectionConfig, Client, RedisError};
 use serde::{Deserialize, Serialize};
 use shared::Message;
 
+use crate::stream::{Group, Start};
+
 /// The redis list holding queued telemetry samples.
 pub const QUEUE_KEY: &str = "lookout-telemetry";
 
+/// What a consumer's processing list is keyed under, ahead of the consumer's name.
+pub const PROCESSING_KEY_PREFIX: &str = "lookout-telemetry:processing:";
+
+/// The redis list holding frames the server could not parse, as [`RawSample`]s like any
+/// queued sample.
+pub const DEAD_LETTER_KEY: &str = "lookout-telemetry:dead-letter";
+
+/// What a dead-letter consumer's processing list is keyed under, ahead of its name.
+pub const DEAD_LETTER_PROCESSING_KEY_PREFIX: &str = "lookout-telemetry:dead-letter:processing:";
+
+/// The most frames the dead-letter list holds, trimmed as the server adds to it. A device
+/// stuck sending garbage would otherwise fill redis with it while nothing archives the list;
+/// past this the oldest are let go, and the server logs each it lets go.
+pub const DEAD_LETTER_LENGTH: usize = 100_000;
+
+/// The environment variable naming how the queue is kept: `list` (or unset) or `stream`.
+pub const QUEUE_KIND_VAR: &str = "LOOKOUT_QUEUE";
+
+/// How the queue is kept in redis. The server and every reader have to agree, so each
+/// reads it from the same place — see [`QueueKind::from_env`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueKind {
+    /// The list at [`QUEUE_KEY`]: one reader drains it.
+    #[default]
+    List,
+    /// The stream at [`stream::STREAM_KEY`]: every consumer group reads all of it.
+    Stream,
+}
+
+/// A queue kind that is neither `list` nor `stream`.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown queue kind `{0}`: expected `list` or `stream`")]
+pub struct UnknownQueueKind(String);
+
+impl QueueKind {
+    /// The kind `LOOKOUT_QUEUE` names, or the list if it is unset.
+    pub fn from_env() -> Result<Self, UnknownQueueKind> {
+        match std::env::var(QUEUE_KIND_VAR) {
+            Ok(kind) => kind.parse(),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for QueueKind {
+    type Err = UnknownQueueKind;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "list" => Ok(Self::List),
+            "stream" => Ok(Self::Stream),
+            _ => Err(UnknownQueueKind(kind.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for QueueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::List => f.write_str("list"),
+            Self::Stream => f.write_str("stream"),
+        }
+    }
+}
+
 /// One item on the queue: a verbatim sample payload plus `received_at`, the epoch
 /// millis the **server** stamped when it first received the sample over the websocket.
 ///
@@ -99,20 +194,129 @@ pub async fn brpop_sample(
         .map_err(QueueError::from)
 }
 
-/// Put a sample back on the **tail** of the queue — where `BRPOP` took it — so a
-/// failed archive doesn't destroy it. `RPUSH` targets the tail, restoring its FIFO
-/// position ahead of the head-pushed newer samples.
-pub async fn requeue_sample(
-    conn: &mut MultiplexedConnection,
-    sample: &RawSample,
-) -> Result<(), QueueError> {
-    let item = serde_json::to_string(sample)?;
-    let _: i64 = redis::cmd("RPUSH")
-        .arg(QUEUE_KEY)
-        .arg(item)
-        .query_async(conn)
-        .await?;
-    Ok(())
+/// One reader of the queue that loses nothing if it stops part way: what it takes is moved
+/// onto a processing list of its own rather than removed, and stays there until
+/// acknowledged.
+///
+/// The processing list is named for the consumer, so a drain restarted under the same name
+/// finds what the last one left. Two drains running at once need two names: sharing one
+/// list, each would recover the other's samples from under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consumer {
+    queue: &'static str,
+    processing: String,
+}
+
+/// A queue item taken and not yet acknowledged, held as the exact string queued: a
+/// [`Consumer`] acknowledges it by removing that value from its processing list, and a
+/// [`Group`] by the id of the stream entry it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Taken {
+    item: String,
+    /// The id of the stream entry holding the item; `None` for an item off the list.
+    entry: Option<String>,
+}
+
+impl Taken {
+    /// The sample the item holds. An item that is not one should be archived as it stands,
+    /// from [`Taken::item`], before it is acknowledged: acknowledging removes the only copy.
+    pub fn sample(&self) -> Result<RawSample, serde_json::Error> {
+        serde_json::from_str(&self.item)
+    }
+
+    /// The item exactly as it was queued.
+    pub fn item(&self) -> &str {
+        &self.item
+    }
+}
+
+impl Consumer {
+    /// The consumer called `name` of the queue.
+    pub fn new(name: &str) -> Self {
+        Self {
+            queue: QUEUE_KEY,
+            processing: format!("{PROCESSING_KEY_PREFIX}{name}"),
+        }
+    }
+
+    /// The consumer called `name` of the dead-letter list, which is read as the queue is but
+    /// kept apart from it, with processing lists of its own.
+    pub fn dead_letters(name: &str) -> Self {
+        Self {
+            queue: DEAD_LETTER_KEY,
+            processing: format!("{DEAD_LETTER_PROCESSING_KEY_PREFIX}{name}"),
+        }
+    }
+
+    /// The redis list holding what this consumer has taken and not acknowledged.
+    pub fn processing_key(&self) -> &str {
+        &self.processing
+    }
+
+    /// Return everything an earlier run under this name took and never acknowledged to the
+    /// queue, ahead of what is queued there, and say how many items that was.
+    ///
+    /// The newest is moved first and each goes to the tail, which is where samples are taken
+    /// from, so they are taken again oldest first, as they were the first time. Each move is
+    /// one command, so a recovery that is itself interrupted leaves every item on one list or
+    /// the other.
+    pub async fn recover(&self, conn: &mut MultiplexedConnection) -> Result<usize, QueueError> {
+        let mut recovered = 0;
+        loop {
+            let moved: Option<String> = redis::cmd("LMOVE")
+                .arg(&self.processing)
+                .arg(self.queue)
+                .arg("LEFT")
+                .arg("RIGHT")
+                .query_async(conn)
+                .await?;
+            match moved {
+                Some(_) => recovered += 1,
+                None => return Ok(recovered),
+            }
+        }
+    }
+
+    /// Move the oldest queued item onto the processing list (`BLMOVE`, Redis >= 6.2),
+    /// blocking up to `timeout`. Returns `None` when the timeout elapses with the queue
+    /// still empty.
+    pub async fn take(
+        &self,
+        conn: &mut MultiplexedConnection,
+        timeout: Duration,
+    ) -> Result<Option<Taken>, QueueError> {
+        let item: Option<String> = redis::cmd("BLMOVE")
+            .arg(self.queue)
+            .arg(&self.processing)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .arg(timeout.as_secs_f64())
+            .query_async(conn)
+            .await?;
+        Ok(item.map(|item| Taken { item, entry: None }))
+    }
+
+    /// Remove `taken` from the processing list, once what they hold is archived. An item
+    /// queued twice is taken twice, and each acknowledgement removes one of them.
+    pub async fn acknowledge(
+        &self,
+        conn: &mut MultiplexedConnection,
+        taken: &[Taken],
+    ) -> Result<(), QueueError> {
+        if taken.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        for taken in taken {
+            pipe.cmd("LREM")
+                .arg(&self.processing)
+                .arg(1)
+                .arg(&taken.item)
+                .ignore();
+        }
+        pipe.query_async::<()>(conn).await?;
+        Ok(())
+    }
 }
 
 /// Read the most recent `limit` samples **without removing them** (non-destructive).
@@ -139,6 +343,88 @@ pub async fn latest_samples(
         .map_err(QueueError::from)
 }
 
+/// A reader of the queue however it is kept: a [`Consumer`] of the list, or a consumer of a
+/// stream [`Group`]. Either recovers what it took and never acknowledged, takes one item at
+/// a time, and acknowledges what it has archived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reader {
+    List(Consumer),
+    Stream(Group),
+}
+
+impl Reader {
+    /// The reader called `name` of the queue kept as `kind`. On the stream, `name` is both
+    /// the group and its consumer — one consumer a group, since each group is one reader —
+    /// and a group made for it starts from the oldest entry, as a list reader would.
+    pub fn new(kind: QueueKind, name: &str) -> Self {
+        match kind {
+            QueueKind::List => Self::List(Consumer::new(name)),
+            QueueKind::Stream => Self::Stream(Group::new(name, name, Start::Oldest)),
+        }
+    }
+
+    /// Put whatever an earlier run took and never acknowledged in line to be taken again,
+    /// before anything else, and say how many items that was.
+    pub async fn recover(&mut self, conn: &mut MultiplexedConnection) -> Result<usize, QueueError> {
+        match self {
+            Self::List(consumer) => consumer.recover(conn).await,
+            Self::Stream(group) => group.recover(conn).await,
+        }
+    }
+
+    /// Take the next item, blocking up to `timeout`; `None` if nothing came.
+    pub async fn take(
+        &mut self,
+        conn: &mut MultiplexedConnection,
+        timeout: Duration,
+    ) -> Result<Option<Taken>, QueueError> {
+        match self {
+            Self::List(consumer) => consumer.take(conn, timeout).await,
+            Self::Stream(group) => group.take(conn, timeout).await,
+        }
+    }
+
+    /// Acknowledge `taken`, once what they hold is archived.
+    pub async fn acknowledge(
+        &self,
+        conn: &mut MultiplexedConnection,
+        taken: &[Taken],
+    ) -> Result<(), QueueError> {
+        match self {
+            Self::List(consumer) => consumer.acknowledge(conn, taken).await,
+            Self::Stream(group) => group.acknowledge(conn, taken).await,
+        }
+    }
+
+    /// Let go of what this reader has archived, where taking it did not already: a list item
+    /// is gone once acknowledged, but a stream keeps its entries until trimmed — see
+    /// [`Group::trim`]. Returns how many went. For the reader archiving the queue.
+    pub async fn trim(&self, conn: &mut MultiplexedConnection) -> Result<usize, QueueError> {
+        match self {
+            Self::List(_) => Ok(0),
+            Self::Stream(group) => group.trim(conn).await,
+        }
+    }
+
+    /// How many items this reader found gone while it held them, with nothing to archive.
+    pub fn lost(&self) -> usize {
+        match self {
+            Self::List(_) => 0,
+            Self::Stream(group) => group.lost(),
+        }
+    }
+}
+
+/// Where what the reader has taken waits to be acknowledged, for logs.
+impl fmt::Display for Reader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::List(consumer) => write!(f, "list {}", consumer.processing_key()),
+            Self::Stream(group) => write!(f, "stream group {}", group.name()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
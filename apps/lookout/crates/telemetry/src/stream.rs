@@ -0,0 +1,370 @@
+//! The telemetry queue kept as a redis stream, for when more than one reader wants every
+//! sample.
+//!
+//! A list hands each item to whichever reader takes it first, so only one can drain it and
+//! any other has to peek. A stream keeps its entries, and each consumer group reading it has
+//! its own place in it: the recorder's group and the motis poller's each see every sample,
+//! however far apart they read. Within a group an entry is delivered to one consumer, and is
+//! pending against that consumer until acknowledged — the stream's counterpart of a
+//! [`Consumer`](crate::Consumer)'s processing list, and what [`Group::recover`] reads again
+//! after a restart.
+//!
+//! Acknowledging an entry takes it off a group's pending list, not out of the stream, so the
+//! server adds to it without bound and the archiving reader trims it as it goes — see
+//! [`Group::trim`] — to the oldest entry its group has still to archive, whether pending or
+//! not yet read. Nothing the archive has not got is trimmed, however far behind it is; a group
+//! that only wants what is recent may find older entries gone, and does not mind. An entry
+//! that does go missing while pending, as one deleted by hand would, is counted and warned of
+//! when it is next read, rather than acknowledged without a word.
+
+use std::time::Duration;
+
+use redis::aio::MultiplexedConnection;
+use redis::streams::{
+    StreamAutoClaimReply, StreamId, StreamInfoGroupsReply, StreamPendingReply, StreamReadReply,
+};
+
+use crate::{QueueError, Taken};
+
+/// The redis stream holding queued telemetry samples, when the queue is kept as one. Not
+/// [`QUEUE_KEY`](crate::QUEUE_KEY), which names a list: a key holds one type or the other.
+pub const STREAM_KEY: &str = "lookout-telemetry-stream";
+
+/// The field of a stream entry holding the queued sample, as the JSON a list item is.
+pub const SAMPLE_FIELD: &str = "sample";
+
+/// How long an entry has to have been pending against another consumer of the group before
+/// [`Group::recover`] claims it. Long enough that a consumer still working through a batch
+/// keeps it; a consumer silent this long is taken to be gone.
+const CLAIM_IDLE: Duration = Duration::from_secs(60 * 60);
+
+/// How many entries one claim moves, of however many there are to claim.
+const CLAIM_COUNT: usize = 100;
+
+/// Where a group reads from when it does not exist yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Start {
+    /// Every entry still in the stream: for a reader that archives them.
+    Oldest,
+    /// Only entries added from now on: for a reader that only wants what is recent.
+    Newest,
+}
+
+/// One consumer of a consumer group on the stream, taking entries one at a time.
+///
+/// After [`recover`](Self::recover), entries still pending against this consumer are taken
+/// again, oldest first, before any new one; it is for those alone that the consumer's name
+/// matters, so a consumer restarted under the same name resumes what the last left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    name: String,
+    consumer: String,
+    start: Start,
+    /// While re-reading what is pending, the id of the last entry re-read; `None` once every
+    /// one has been, and what is taken is new.
+    pending_after: Option<String>,
+    /// How many entries were found gone from the stream while pending against this consumer.
+    lost: usize,
+}
+
+impl Group {
+    /// The consumer `consumer` of the group `name`, which, if it has to be made, starts at
+    /// `start`.
+    pub fn new(name: &str, consumer: &str, start: Start) -> Self {
+        Self {
+            name: name.to_string(),
+            consumer: consumer.to_string(),
+            start,
+            pending_after: None,
+            lost: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    /// How many entries this consumer has found gone from the stream while they were pending
+    /// against it, and so had nothing to archive.
+    pub fn lost(&self) -> usize {
+        self.lost
+    }
+
+    /// Make the group if it does not exist, claim what other consumers of it have left
+    /// pending for [`CLAIM_IDLE`], and have the next takes re-read everything pending against
+    /// this consumer before anything new. Returns how many entries are to be re-read.
+    pub async fn recover(&mut self, conn: &mut MultiplexedConnection) -> Result<usize, QueueError> {
+        self.join(conn).await?;
+
+        let mut from = "0-0".to_string();
+        loop {
+            let claimed: StreamAutoClaimReply = redis::cmd("XAUTOCLAIM")
+                .arg(STREAM_KEY)
+                .arg(&self.name)
+                .arg(&self.consumer)
+                .arg(CLAIM_IDLE.as_millis() as u64)
+                .arg(&from)
+                .arg("COUNT")
+                .arg(CLAIM_COUNT)
+                .arg("JUSTID")
+                .query_async(conn)
+                .await?;
+            // A cursor of 0-0 is redis saying the whole pending list has been scanned.
+            match claimed.next_stream_id.as_str() {
+                "0-0" => break,
+                _ => from = claimed.next_stream_id,
+            }
+        }
+
+        let pending: StreamPendingReply = redis::cmd("XPENDING")
+            .arg(STREAM_KEY)
+            .arg(&self.name)
+            .query_async(conn)
+            .await?;
+        let mine = match pending {
+            StreamPendingReply::Empty => 0,
+            StreamPendingReply::Data(data) => data
+                .consumers
+                .iter()
+                .filter(|consumer| consumer.name == self.consumer)
+                .map(|consumer| consumer.pending)
+                .sum(),
+        };
+        self.pending_after = Some("0-0".to_string());
+        Ok(mine)
+    }
+
+    /// Take the next entry — one pending against this consumer while any are left to
+    /// re-read, and otherwise the next new one, blocking up to `timeout` for it. Returns
+    /// `None` when the timeout elapses with nothing new.
+    pub async fn take(
+        &mut self,
+        conn: &mut MultiplexedConnection,
+        timeout: Duration,
+    ) -> Result<Option<Taken>, QueueError> {
+        while let Some(after) = self.pending_after.clone() {
+            match self.read(conn, &after, 1, None).await?.into_iter().next() {
+                Some(entry) => {
+                    self.pending_after = Some(entry.id.clone());
+                    match self.taken(conn, entry).await? {
+                        Some(taken) => return Ok(Some(taken)),
+                        None => continue,
+                    }
+                }
+                None => self.pending_after = None,
+            }
+        }
+        loop {
+            match self
+                .read(conn, ">", 1, Some(timeout))
+                .await?
+                .into_iter()
+                .next()
+            {
+                None => return Ok(None),
+                Some(entry) => {
+                    if let Some(taken) = self.taken(conn, entry).await? {
+                        return Ok(Some(taken));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take up to `count` new entries without waiting for any: what has been added since the
+    /// group last read, for a reader polling for what is recent.
+    pub async fn take_new(
+        &mut self,
+        conn: &mut MultiplexedConnection,
+        count: usize,
+    ) -> Result<Vec<Taken>, QueueError> {
+        let mut taken = Vec::new();
+        for entry in self.read(conn, ">", count, None).await? {
+            if let Some(item) = self.taken(conn, entry).await? {
+                taken.push(item);
+            }
+        }
+        Ok(taken)
+    }
+
+    /// Acknowledge `taken`, once what they hold is archived: they stop being pending
+    /// against this consumer, and are never delivered to the group again.
+    pub async fn acknowledge(
+        &self,
+        conn: &mut MultiplexedConnection,
+        taken: &[Taken],
+    ) -> Result<(), QueueError> {
+        let ids: Vec<&str> = taken
+            .iter()
+            .filter_map(|taken| taken.entry.as_deref())
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _: i64 = redis::cmd("XACK")
+            .arg(STREAM_KEY)
+            .arg(&self.name)
+            .arg(&ids)
+            .query_async(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Trim the stream of every entry this group has read and acknowledged: everything older
+    /// than the oldest entry pending against it, or, with none pending, everything it has been
+    /// delivered. Returns how many entries went. For the group archiving the stream alone,
+    /// since any other group behind it loses what it had not read.
+    ///
+    /// The group only moves forward, so a floor read here is never past what it has still to
+    /// archive by the time the stream is trimmed to it.
+    pub async fn trim(&self, conn: &mut MultiplexedConnection) -> Result<usize, QueueError> {
+        let pending: StreamPendingReply = redis::cmd("XPENDING")
+            .arg(STREAM_KEY)
+            .arg(&self.name)
+            .query_async(conn)
+            .await?;
+        let floor = match pending {
+            StreamPendingReply::Data(data) => data.start_id,
+            StreamPendingReply::Empty => {
+                let info: StreamInfoGroupsReply = redis::cmd("XINFO")
+                    .arg("GROUPS")
+                    .arg(STREAM_KEY)
+                    .query_async(conn)
+                    .await?;
+                let Some(group) = info
+                    .groups
+                    .into_iter()
+                    .find(|group| group.name == self.name)
+                else {
+                    return Ok(0);
+                };
+                match after(&group.last_delivered_id) {
+                    Some(floor) => floor,
+                    None => return Ok(0),
+                }
+            }
+        };
+        let trimmed: usize = redis::cmd("XTRIM")
+            .arg(STREAM_KEY)
+            .arg("MINID")
+            .arg(&floor)
+            .query_async(conn)
+            .await?;
+        Ok(trimmed)
+    }
+
+    /// Make the group, unless it exists already — which redis reports as a `BUSYGROUP`
+    /// error, and is no failure here. Makes the stream too, so a group can be joined before
+    /// the server has added anything. [`recover`](Self::recover) joins first, so only a
+    /// reader that never recovers has to.
+    pub async fn join(&self, conn: &mut MultiplexedConnection) -> Result<(), QueueError> {
+        let start = match self.start {
+            Start::Oldest => "0",
+            Start::Newest => "$",
+        };
+        let made: Result<(), _> = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(STREAM_KEY)
+            .arg(&self.name)
+            .arg(start)
+            .arg("MKSTREAM")
+            .query_async(conn)
+            .await;
+        match made {
+            Err(err) if err.code() != Some("BUSYGROUP") => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Up to `count` entries after `after` for this consumer: `>` for new ones, or an id to
+    /// re-read what is pending against it from there. `block` waits that long for new ones.
+    async fn read(
+        &self,
+        conn: &mut MultiplexedConnection,
+        after: &str,
+        count: usize,
+        block: Option<Duration>,
+    ) -> Result<Vec<StreamId>, QueueError> {
+        let mut read = redis::cmd("XREADGROUP");
+        read.arg("GROUP")
+            .arg(&self.name)
+            .arg(&self.consumer)
+            .arg("COUNT")
+            .arg(count);
+        if let Some(block) = block {
+            // Zero would block forever.
+            read.arg("BLOCK").arg((block.as_millis() as u64).max(1));
+        }
+        let reply: Option<StreamReadReply> = read
+            .arg("STREAMS")
+            .arg(STREAM_KEY)
+            .arg(after)
+            .query_async(conn)
+            .await?;
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .collect())
+    }
+
+    /// The item `entry` holds, or `None` for an entry gone from the stream while it was
+    /// pending: redis still lists its id, but there is nothing left to archive. It is counted
+    /// against [`lost`](Self::lost) and warned of, then acknowledged rather than left pending
+    /// forever.
+    async fn taken(
+        &mut self,
+        conn: &mut MultiplexedConnection,
+        entry: StreamId,
+    ) -> Result<Option<Taken>, QueueError> {
+        let item = entry.get::<String>(SAMPLE_FIELD);
+        let taken = Taken {
+            item: item.clone().unwrap_or_default(),
+            entry: Some(entry.id),
+        };
+        match item {
+            Some(_) => Ok(Some(taken)),
+            None => {
+                self.lost += 1;
+                tracing::warn!(
+                    entry = taken.entry.as_deref(),
+                    group = %self.name,
+                    lost = self.lost,
+                    "pending stream entry is gone; nothing to archive"
+                );
+                self.acknowledge(conn, &[taken]).await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// The smallest stream id after `id`, or `None` for an id that is not one — as `0-0`, which
+/// a group that has been delivered nothing reports, is, and which nothing is before.
+fn after(id: &str) -> Option<String> {
+    let (millis, sequence) = id.split_once('-')?;
+    let millis: u64 = millis.parse().ok()?;
+    let sequence: u64 = sequence.parse().ok()?;
+    match (millis, sequence) {
+        (0, 0) => None,
+        (millis, u64::MAX) => Some(format!("{}-0", millis + 1)),
+        (millis, sequence) => Some(format!("{millis}-{}", sequence + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_id_after_an_entry_is_the_next_sequence_in_its_millisecond() {
+        assert_eq!(after("1700000000000-4").as_deref(), Some("1700000000000-5"));
+        assert_eq!(after(&format!("7-{}", u64::MAX)).as_deref(), Some("8-0"));
+        assert_eq!(after("0-0"), None);
+        assert_eq!(after("garbage"), None);
+    }
+}
@@ -1,7 +1,10 @@
 //! Integration test for the queue read paths against a real redis: the recorder's
-//! two modes rely on `latest_samples` being newest-first + non-destructive, and
-//! `brpop_sample` being FIFO + destructive. Samples are pushed the way the server
-//! does (`LPUSH` of JSON).
+//! two modes rely on `latest_samples` being newest-first + non-destructive, and on a
+//! `Consumer` taking FIFO, holding what it took until acknowledged, and recovering it
+//! in order after a crash, and a dead-letter `Consumer` doing the same on its own list;
+//! `brpop_sample` is FIFO + destructive. Samples are pushed the way the server does
+//! (`LPUSH` of JSON). Kept as a stream, every consumer group reads every sample, and a
+//! consumer restarted re-reads what it left unacknowledged.
 //!
 //! Requires Docker; the `_docker`-suffixed name is skipped by the no-docker profile.
 
@@ -9,7 +12,11 @@ use std::time::Duration;
 
 use redis::aio::MultiplexedConnection;
 use shared::{Accel, AccelReading, Message, V1Message};
-use telemetry::{QUEUE_KEY, RawSample, brpop_sample, latest_samples};
+use telemetry::stream::{Group, SAMPLE_FIELD, STREAM_KEY, Start};
+use telemetry::{
+    Consumer, DEAD_LETTER_KEY, QUEUE_KEY, QueueKind, RawSample, Reader, Taken, brpop_sample,
+    latest_samples,
+};
 use testcontainers::runners::AsyncRunner;
 use testcontainers::{ContainerAsync, ImageExt};
 use testcontainers_modules::redis::{REDIS_PORT, Redis};
@@ -17,8 +24,8 @@ use uuid::Uuid;
 
 async fn start_redis() -> (ContainerAsync<Redis>, String) {
     // Pin a modern redis: `brpop_sample` uses a fractional BRPOP timeout, which
-    // only Redis >= 6.0 accepts (the module's default image is older). upstash,
-    // the real backend, is modern.
+    // only Redis >= 6.0 accepts, and `Consumer::take` uses BLMOVE, which needs 6.2
+    // (the module's default image is older). upstash, the real backend, is modern.
     let container = Redis::default()
         .with_tag("7-alpine")
         .start()
@@ -68,14 +75,57 @@ fn sample(n: u128) -> Message {
     }))
 }
 
+/// XADD a message the way the server's `StreamSink` does, returning the entry's id.
+async fn xadd(conn: &mut MultiplexedConnection, sample: &Message) -> String {
+    let payload = serde_json::to_string(sample).expect("serialize");
+    let item = serde_json::to_string(&RawSample::new(1_700_000_050_000, payload))
+        .expect("serialize envelope");
+    redis::cmd("XADD")
+        .arg(STREAM_KEY)
+        .arg("*")
+        .arg(SAMPLE_FIELD)
+        .arg(item)
+        .query_async(conn)
+        .await
+        .expect("xadd")
+}
+
+async fn xlen(conn: &mut MultiplexedConnection) -> i64 {
+    redis::cmd("XLEN")
+        .arg(STREAM_KEY)
+        .query_async(conn)
+        .await
+        .expect("xlen")
+}
+
+fn parsed(taken: &[Taken]) -> Vec<Message> {
+    taken
+        .iter()
+        .map(|item| item.sample().expect("envelope").parse().expect("parse"))
+        .collect()
+}
+
+async fn len(conn: &mut MultiplexedConnection, key: &str) -> i64 {
+    redis::cmd("LLEN")
+        .arg(key)
+        .query_async(conn)
+        .await
+        .expect("llen")
+}
+
 /// LPUSH a message the way the server's `RedisSink` does: as a RawSample envelope
 /// (payload + received_at).
 async fn lpush(conn: &mut MultiplexedConnection, sample: &Message) {
     let payload = serde_json::to_string(sample).expect("serialize");
+    lpush_payload(conn, QUEUE_KEY, &payload).await;
+}
+
+/// LPUSH `payload` onto `key` in its RawSample envelope, whether or not it parses.
+async fn lpush_payload(conn: &mut MultiplexedConnection, key: &str, payload: &str) {
     let item = serde_json::to_string(&RawSample::new(1_700_000_050_000, payload))
         .expect("serialize envelope");
     let _: i64 = redis::cmd("LPUSH")
-        .arg(QUEUE_KEY)
+        .arg(key)
         .arg(item)
         .query_async(conn)
         .await
@@ -124,3 +174,234 @@ async fn queue_read_paths_docker() {
         .expect("brpop empty");
     assert_eq!(empty, None);
 }
+
+#[tokio::test]
+async fn consumer_recovers_what_it_did_not_acknowledge_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    for n in 1..=4 {
+        lpush(&mut conn, &sample(n)).await;
+    }
+
+    // Take the three oldest, but acknowledge only the first — as a drain that wrote one
+    // batch and died before writing the next would.
+    let consumer = Consumer::new("test");
+    let mut taken = Vec::new();
+    for _ in 0..3 {
+        let item = consumer
+            .take(&mut conn, Duration::from_secs(2))
+            .await
+            .expect("take")
+            .expect("a queued sample");
+        taken.push(item);
+    }
+    let parsed: Vec<Message> = taken
+        .iter()
+        .map(|item| item.sample().expect("envelope").parse().expect("parse"))
+        .collect();
+    assert_eq!(parsed, vec![sample(1), sample(2), sample(3)]);
+    assert_eq!(len(&mut conn, consumer.processing_key()).await, 3);
+    assert_eq!(len(&mut conn, QUEUE_KEY).await, 1);
+
+    consumer
+        .acknowledge(&mut conn, &taken[..1])
+        .await
+        .expect("acknowledge");
+    assert_eq!(len(&mut conn, consumer.processing_key()).await, 2);
+
+    // A restart under the same name puts the unacknowledged two back ahead of what is
+    // still queued, in the order they were first taken.
+    let restarted = Consumer::new("test");
+    assert_eq!(restarted.recover(&mut conn).await.expect("recover"), 2);
+    assert_eq!(len(&mut conn, restarted.processing_key()).await, 0);
+    let mut again = Vec::new();
+    while let Some(item) = restarted
+        .take(&mut conn, Duration::from_millis(200))
+        .await
+        .expect("take")
+    {
+        again.push(item);
+    }
+    let parsed: Vec<Message> = again
+        .iter()
+        .map(|item| item.sample().expect("envelope").parse().expect("parse"))
+        .collect();
+    assert_eq!(parsed, vec![sample(2), sample(3), sample(4)]);
+
+    restarted
+        .acknowledge(&mut conn, &again)
+        .await
+        .expect("acknowledge");
+    assert_eq!(len(&mut conn, restarted.processing_key()).await, 0);
+    assert_eq!(restarted.recover(&mut conn).await.expect("recover"), 0);
+}
+
+/// Dead letters are read as the queue is, but neither consumer takes from the other's list.
+#[tokio::test]
+async fn dead_letters_are_taken_apart_from_the_queue_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    lpush(&mut conn, &sample(1)).await;
+    lpush_payload(&mut conn, DEAD_LETTER_KEY, "not-a-sample").await;
+
+    let dead_letters = Consumer::dead_letters("test");
+    let queue = Consumer::new("test");
+    assert_ne!(dead_letters.processing_key(), queue.processing_key());
+
+    let taken = dead_letters
+        .take(&mut conn, Duration::from_secs(2))
+        .await
+        .expect("take")
+        .expect("a dead letter");
+    assert_eq!(taken.sample().expect("envelope").json(), "not-a-sample");
+    assert!(
+        dead_letters
+            .take(&mut conn, Duration::from_millis(200))
+            .await
+            .expect("take")
+            .is_none(),
+        "the queued sample is not a dead letter"
+    );
+    assert_eq!(len(&mut conn, QUEUE_KEY).await, 1);
+
+    // Unacknowledged, it goes back to the dead-letter list on recovery, not to the queue.
+    assert_eq!(dead_letters.recover(&mut conn).await.expect("recover"), 1);
+    assert_eq!(len(&mut conn, DEAD_LETTER_KEY).await, 1);
+    assert_eq!(len(&mut conn, QUEUE_KEY).await, 1);
+}
+
+#[tokio::test]
+async fn stream_groups_each_read_every_sample_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+
+    // Two readers, as the recorder and the poller would be, both made before anything is
+    // added: the same configuration picks the stream for each.
+    let mut recorder = Reader::new(QueueKind::Stream, "recorder");
+    assert_eq!(recorder.recover(&mut conn).await.expect("recover"), 0);
+    let mut poller = Group::new("motis", "motis", Start::Newest);
+    poller.join(&mut conn).await.expect("join");
+    for n in 1..=3 {
+        xadd(&mut conn, &sample(n)).await;
+    }
+
+    // The poller reads all three without taking any from the recorder.
+    let polled = poller.take_new(&mut conn, 10).await.expect("take new");
+    assert_eq!(parsed(&polled), vec![sample(1), sample(2), sample(3)]);
+    poller
+        .acknowledge(&mut conn, &polled)
+        .await
+        .expect("acknowledge");
+    assert!(
+        poller
+            .take_new(&mut conn, 10)
+            .await
+            .expect("take new")
+            .is_empty()
+    );
+
+    // The recorder takes two and acknowledges the first, then dies.
+    let mut taken = Vec::new();
+    for _ in 0..2 {
+        taken.push(
+            recorder
+                .take(&mut conn, Duration::from_secs(2))
+                .await
+                .expect("take")
+                .expect("an entry"),
+        );
+    }
+    assert_eq!(parsed(&taken), vec![sample(1), sample(2)]);
+    recorder
+        .acknowledge(&mut conn, &taken[..1])
+        .await
+        .expect("acknowledge");
+
+    // Restarted under the same name, it re-reads the one it left pending before the new.
+    let mut restarted = Reader::new(QueueKind::Stream, "recorder");
+    assert_eq!(restarted.recover(&mut conn).await.expect("recover"), 1);
+    let mut again = Vec::new();
+    while let Some(item) = restarted
+        .take(&mut conn, Duration::from_millis(200))
+        .await
+        .expect("take")
+    {
+        again.push(item);
+    }
+    assert_eq!(parsed(&again), vec![sample(2), sample(3)]);
+    restarted
+        .acknowledge(&mut conn, &again)
+        .await
+        .expect("acknowledge");
+    assert_eq!(restarted.recover(&mut conn).await.expect("recover"), 0);
+
+    // Reading took nothing out of the stream.
+    assert_eq!(xlen(&mut conn).await, 3);
+}
+
+/// The archiving reader trims the stream to what it has still to archive, however far behind
+/// another group is, and an entry gone while pending is counted rather than let go quietly.
+#[tokio::test]
+async fn the_archiving_reader_trims_only_what_it_has_archived_docker() {
+    let (_container, url) = start_redis().await;
+    let mut conn = wait_ready(&url).await;
+    let mut recorder = Reader::new(QueueKind::Stream, "recorder");
+    assert_eq!(recorder.recover(&mut conn).await.expect("recover"), 0);
+    let poller = Group::new("motis", "motis", Start::Oldest);
+    poller.join(&mut conn).await.expect("join");
+    let mut ids = Vec::new();
+    for n in 1..=4 {
+        ids.push(xadd(&mut conn, &sample(n)).await);
+    }
+
+    // Nothing read, nothing trimmed.
+    assert_eq!(recorder.trim(&mut conn).await.expect("trim"), 0);
+
+    // Three taken and the first acknowledged: only the first goes, the two pending stay.
+    let mut taken = Vec::new();
+    for _ in 0..3 {
+        taken.push(
+            recorder
+                .take(&mut conn, Duration::from_secs(2))
+                .await
+                .expect("take")
+                .expect("an entry"),
+        );
+    }
+    recorder
+        .acknowledge(&mut conn, &taken[..1])
+        .await
+        .expect("acknowledge");
+    assert_eq!(recorder.trim(&mut conn).await.expect("trim"), 1);
+    assert_eq!(xlen(&mut conn).await, 3);
+
+    // One of the two pending is deleted from under the recorder: a restart re-reads the
+    // other, and counts the one that is gone.
+    let _: i64 = redis::cmd("XDEL")
+        .arg(STREAM_KEY)
+        .arg(&ids[1])
+        .query_async(&mut conn)
+        .await
+        .expect("xdel");
+    let mut restarted = Reader::new(QueueKind::Stream, "recorder");
+    assert_eq!(restarted.recover(&mut conn).await.expect("recover"), 2);
+    let mut again = Vec::new();
+    while let Some(item) = restarted
+        .take(&mut conn, Duration::from_millis(200))
+        .await
+        .expect("take")
+    {
+        again.push(item);
+    }
+    assert_eq!(parsed(&again), vec![sample(3), sample(4)]);
+    assert_eq!(restarted.lost(), 1);
+
+    // All archived: the stream is trimmed of everything the recorder read, though the poller
+    // read none of it.
+    restarted
+        .acknowledge(&mut conn, &again)
+        .await
+        .expect("acknowledge");
+    assert_eq!(restarted.trim(&mut conn).await.expect("trim"), 2);
+    assert_eq!(xlen(&mut conn).await, 0);
+}
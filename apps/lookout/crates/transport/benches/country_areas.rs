@@ -0,0 +1,129 @@
+//! What the indexed country areas save over testing every outline in turn, placing every
+//! sample of one day of sessions, from the repo's own store.
+//!
+//! Run with `just bench-country-areas`, after an extract has been taken and sessions derived.
+//! It loads the newest extract's country areas, reads the position of every sample on the
+//! newest `sample_date` in silver, and places each three ways, best of a few runs each: a
+//! scan of every area with `Contains`, as `CountryAreas` used to answer; the index a point
+//! at a time; and the index given the whole day at once. The three have to agree.
+//!
+//! A plain binary rather than a harnessed benchmark, as `crossings_near` is: what is measured
+//! is one real day against real outlines, which a harness's synthetic inputs would not be.
+
+use std::time::{Duration, Instant};
+
+use geo::{Contains, CoordsIter};
+use geo_types::Point;
+use medallion::{Countries, Country, GEOMETRY, Query, Root};
+use serde::Deserialize;
+use transport::countries::CountryAreas;
+
+/// Runs per way of placing the day; the fastest is reported, as the one least disturbed by
+/// anything else.
+const RUNS: usize = 5;
+
+/// The newest day's samples.
+const NEWEST_DAY: &str = "sample_date = (SELECT MAX(sample_date) FROM session_sample)";
+
+#[derive(Debug, Deserialize)]
+struct Sample {
+    lon: f64,
+    lat: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Day {
+    day: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let root = Root::new(Root::default_path().expect("locate the medallion store"));
+    let areas = match CountryAreas::newest(&root).await {
+        Ok(areas) => areas,
+        Err(err) => {
+            println!(
+                "no country areas to place samples in ({err}): run `just bronze-extract new` first"
+            );
+            return;
+        }
+    };
+    let query = Query::new(root);
+    let derived = query
+        .register_if_present(model::SESSION_SAMPLE, "session_sample")
+        .await
+        .expect("register the session samples");
+    if !derived {
+        println!("no session samples to place: run `just silver-sessionise` first");
+        return;
+    }
+
+    let day: Vec<Day> = query
+        .rows("SELECT CAST(MAX(sample_date) AS VARCHAR) AS day FROM session_sample")
+        .await
+        .expect("find the newest day");
+    let samples: Vec<Sample> = query
+        .rows(&format!(
+            "SELECT ST_X({GEOMETRY}) AS lon, ST_Y({GEOMETRY}) AS lat
+             FROM session_sample WHERE {NEWEST_DAY}"
+        ))
+        .await
+        .expect("read the newest day's samples");
+    let points: Vec<Point<f64>> = samples
+        .iter()
+        .map(|sample| Point::new(sample.lon, sample.lat))
+        .collect();
+
+    let vertices: usize = areas.areas().map(|(_, area)| area.coords_count()).sum();
+    println!(
+        "{} samples on {}, against {} areas of {} vertices",
+        points.len(),
+        day.first().map(|day| day.day.as_str()).unwrap_or("no day"),
+        areas.areas().count(),
+        vertices,
+    );
+
+    let (scanned, scanning) = fastest(|| points.iter().map(|point| scan(&areas, *point)).collect());
+    let (indexed, indexing) = fastest(|| {
+        points
+            .iter()
+            .map(|point| areas.containing(*point))
+            .collect()
+    });
+    let (batched, batching) = fastest(|| areas.containing_all(&points));
+    assert_eq!(scanned, indexed, "the index disagrees with the scan");
+    assert_eq!(scanned, batched, "the batch disagrees with the scan");
+
+    let placed = scanned.iter().filter(|country| country.is_some()).count();
+    println!(
+        "{placed} placed, {} in no known country",
+        points.len() - placed
+    );
+    for (name, took) in [("scan", scanning), ("index", indexing), ("batch", batching)] {
+        println!(
+            "{name:>8}: {:>10.2} ms, {:>8.2} µs a sample",
+            took.as_secs_f64() * 1000.0,
+            took.as_secs_f64() * 1e6 / points.len().max(1) as f64,
+        );
+    }
+}
+
+/// The country containing `point`, testing each area in turn — the lookup the index replaced.
+fn scan(areas: &CountryAreas, point: Point<f64>) -> Option<Country> {
+    areas
+        .areas()
+        .find(|(_, area)| area.contains(&point))
+        .map(|(country, _)| country)
+}
+
+/// What `place` answers, and the fastest of [`RUNS`] runs of it.
+fn fastest(place: impl Fn() -> Vec<Option<Country>>) -> (Vec<Option<Country>>, Duration) {
+    let mut best = Duration::MAX;
+    let mut placed = Vec::new();
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        placed = place();
+        best = best.min(started.elapsed());
+    }
+    (placed, best)
+}
@@ -0,0 +1,109 @@
+//! `crossings_derive`: derive the silver `water_crossing` dataset — every place a stretch of
+//! track meets a body of water in an Overture extract, collapsed to one crossing per place.
+//!
+//! Reads the newest extract unless one is named, so the extract has to have been taken (or
+//! backfilled) first. The whole dataset is derived again, so a rerun replaces what the last
+//! one wrote; the tuning it ran under is stored on every row.
+//!
+//! A run derives nothing at all where neither the extract nor the tuning has changed since
+//! the last.
+
+use clap::Parser;
+use tracing_subscriber::EnvFilter;
+
+use medallion::MedallionArgs;
+use medallion::lineage::Producer;
+use transport::extract::ExtractId;
+use transport::overlap::Tuning;
+use transport::silver;
+
+#[derive(Parser)]
+#[command(about = "Derive where rail crosses water from an Overture extract")]
+struct Args {
+    /// The extract to derive from, e.g. `20260727T193628Z`. Defaults to the newest recorded.
+    extract_id: Option<ExtractId>,
+    /// How close two parts of one track over one water have to be to be one crossing, in
+    /// metres.
+    #[arg(long, default_value_t = Tuning::default().merge_distance_m)]
+    merge_distance_m: f64,
+    /// The shortest stretch of track over water kept as a crossing, in metres.
+    #[arg(long, default_value_t = Tuning::default().min_crossing_m)]
+    min_crossing_m: f64,
+    #[command(flatten)]
+    medallion: MedallionArgs,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "crossings_derive=info,transport=info".into()),
+        )
+        .init();
+
+    let args = Args::parse();
+    let tuning = Tuning {
+        merge_distance_m: args.merge_distance_m,
+        min_crossing_m: args.min_crossing_m,
+    };
+    let root = args
+        .medallion
+        .root()
+        .expect("locate the medallion store")
+        .recording(
+            Producer::new(env!("CARGO_BIN_NAME"))
+                .parameter("merge_distance_m", tuning.merge_distance_m)
+                .parameter("min_crossing_m", tuning.min_crossing_m),
+        );
+
+    if !silver::pending(&root, args.extract_id.as_ref())
+        .await
+        .expect("read what the water crossings were derived from")
+    {
+        tracing::info!(
+            medallion_root = %root.path().display(),
+            "nothing read has changed since the water crossings were derived"
+        );
+        return;
+    }
+    let outcome = silver::derive(&root, args.extract_id.as_ref(), tuning)
+        .await
+        .expect("derive the water crossings");
+
+    tracing::info!(
+        extract_id = %outcome.extract_id,
+        rails = outcome.rails,
+        waters = outcome.waters,
+        crossings = outcome.crossings,
+        partitions = outcome.partitions.written,
+        partitions_removed = outcome.partitions.removed,
+        merge_distance_m = tuning.merge_distance_m,
+        min_crossing_m = tuning.min_crossing_m,
+        medallion_root = %root.path().display(),
+        "derived the water crossings"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Named nothing, this derives from the newest extract under the tuning the notebook
+    /// settled on, so a bare run reproduces what the notebook wrote.
+    #[test]
+    fn the_newest_extract_and_the_notebooks_tuning_are_the_default() {
+        let args = Args::parse_from(["crossings_derive"]);
+
+        assert!(args.extract_id.is_none());
+        assert_eq!(args.merge_distance_m, Tuning::default().merge_distance_m);
+        assert_eq!(args.min_crossing_m, Tuning::default().min_crossing_m);
+    }
+
+    #[test]
+    fn an_extract_to_derive_from_is_named_by_its_id() {
+        let args = Args::parse_from(["crossings_derive", "20260727T193628Z"]);
+
+        assert_eq!(args.extract_id.unwrap().to_string(), "20260727T193628Z");
+    }
+}
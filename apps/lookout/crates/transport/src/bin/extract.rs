@@ -173,6 +173,6 @@ mod tests {
             panic!("expected a new extract");
         };
         assert_eq!(release, DEFAULT_RELEASE);
-        assert_eq!(country, Country::Germany);
+        assert_eq!(country, Country::GERMANY);
     }
 }
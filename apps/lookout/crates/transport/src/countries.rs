@@ -7,10 +7,21 @@
 //! Only the countries the store knows a projected zone for are loaded — an area of any
 //! other is not something a derivation could write geometry for, so it would only be able
 //! to answer with a country nothing can be done with.
+//!
+//! A lookup is made for every session and every train leg, against outlines that run to
+//! hundreds of thousands of vertices each, so the areas are indexed twice over: an R-tree
+//! of their envelopes says which few could contain a point, and each area is **prepared** —
+//! its edges held in an R-tree of their own — so that testing one is a ray cast against the
+//! handful of edges level with the point rather than a walk round every ring. That is the
+//! same preparation GEOS's indexed point-in-area locator makes. geo's own `PreparedGeometry`
+//! prepares for `relate` instead, which builds a whole intersection matrix per point.
 
-use geo::Contains;
-use geo_types::{Geometry, Point};
+use geo::algorithm::kernels::{Kernel, Orientation, RobustKernel};
+use geo::{BoundingRect, LinesIter};
+use geo_types::{Coord, Geometry, Line, Point};
 use medallion::{Countries, Country, GEOMETRY, Query, Root};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{AABB, RTree};
 
 /// The newest extract's country areas.
 const NEWEST_EXTRACT: &str = "
@@ -30,10 +41,85 @@ pub enum CountryError {
     NoExtract,
 }
 
-/// The area of each country the store knows a projected zone for.
+/// The area of each country the store knows a projected zone for, indexed for lookup.
 #[derive(Debug, Clone, Default)]
 pub struct CountryAreas {
-    areas: Vec<(Country, Geometry<f64>)>,
+    /// In registry order, which is the order an overlap is settled in.
+    areas: Vec<Area>,
+    /// Each area's envelope, carrying its index in `areas`.
+    envelopes: RTree<GeomWithData<Rectangle<Point<f64>>, usize>>,
+}
+
+/// One country's area, prepared for testing points against.
+#[derive(Debug, Clone)]
+struct Area {
+    country: Country,
+    geometry: Geometry<f64>,
+    /// Every edge of every ring, exterior and interior alike.
+    edges: RTree<Line<f64>>,
+    /// The easternmost the area reaches, where a ray cast east from a point can stop.
+    east: f64,
+}
+
+impl Area {
+    /// Only a polygon or a multipolygon is an area; anything else an extract holds under
+    /// that name is kept, but contains nothing.
+    fn new(country: Country, geometry: Geometry<f64>) -> Self {
+        let edges: Vec<Line<f64>> = match &geometry {
+            Geometry::Polygon(polygon) => polygon.lines_iter().collect(),
+            Geometry::MultiPolygon(polygons) => polygons.lines_iter().collect(),
+            _ => Vec::new(),
+        };
+        let east = geometry
+            .bounding_rect()
+            .map(|bounds| bounds.max().x)
+            .unwrap_or(f64::NEG_INFINITY);
+        Self {
+            country,
+            geometry,
+            edges: RTree::bulk_load(edges),
+            east,
+        }
+    }
+
+    /// Whether `point` is inside the area, as [`geo::Contains`] says: on its boundary is
+    /// not inside.
+    ///
+    /// Casts a ray east from the point and counts the edges it crosses, over every ring at
+    /// once, so a point in a hole has crossed the hole's ring as well and comes out outside.
+    /// An edge's end is counted as above the ray or not, never both, so a ray through a
+    /// vertex crosses one of the two edges meeting there, not neither or both.
+    fn contains(&self, point: Point<f64>) -> bool {
+        let p = point.0;
+        if p.x > self.east {
+            return false;
+        }
+        let ray = AABB::from_corners(point, Point::new(self.east, p.y));
+
+        let mut inside = false;
+        for edge in self.edges.locate_in_envelope_intersecting(&ray) {
+            let (a, b) = (edge.start, edge.end);
+            let orientation = RobustKernel::orient2d(a, b, p);
+            if orientation == Orientation::Collinear && within(a, b, p) {
+                return false;
+            }
+            if (a.y > p.y) != (b.y > p.y) {
+                // Heading north, the edge passes east of the point when the point is on its
+                // left; heading south, when it is on its right.
+                let east = match a.y < b.y {
+                    true => orientation == Orientation::CounterClockwise,
+                    false => orientation == Orientation::Clockwise,
+                };
+                inside ^= east;
+            }
+        }
+        inside
+    }
+}
+
+/// Whether `p`, known to be on the line through `a` and `b`, is on the segment between them.
+fn within(a: Coord<f64>, b: Coord<f64>, p: Coord<f64>) -> bool {
+    a.x.min(b.x) <= p.x && p.x <= a.x.max(b.x) && a.y.min(b.y) <= p.y && p.y <= a.y.max(b.y)
 }
 
 /// One country area as the extract holds it.
@@ -43,6 +129,32 @@ struct Extracted {
 }
 
 impl CountryAreas {
+    /// Index `areas`. Where two overlap, the earlier one is the answer for a point in both.
+    pub fn new(areas: Vec<(Country, Geometry<f64>)>) -> Self {
+        let areas: Vec<Area> = areas
+            .into_iter()
+            .map(|(country, geometry)| Area::new(country, geometry))
+            .collect();
+        let envelopes = areas
+            .iter()
+            .enumerate()
+            .filter_map(|(index, area)| {
+                let bounds = area.geometry.bounding_rect()?;
+                let envelope = Rectangle::from_corners(bounds.min().into(), bounds.max().into());
+                Some(GeomWithData::new(envelope, index))
+            })
+            .collect();
+        Self {
+            areas,
+            envelopes: RTree::bulk_load(envelopes),
+        }
+    }
+
+    /// Each area and the country it is, in the order overlaps are settled in.
+    pub fn areas(&self) -> impl Iterator<Item = (Country, &Geometry<f64>)> {
+        self.areas.iter().map(|area| (area.country, &area.geometry))
+    }
+
     /// Load the areas from the newest extract in `root`.
     pub async fn newest(root: &Root) -> Result<Self, CountryError> {
         let query = Query::new(root.clone());
@@ -58,7 +170,7 @@ impl CountryAreas {
         query.register_at(&areas, "division_area").await?;
 
         let mut areas = Vec::new();
-        for country in Country::ALL {
+        for country in Country::all() {
             let batches = query
                 .sql(&format!(
                     "SELECT ST_AsBinary({GEOMETRY}) AS {GEOMETRY}
@@ -76,21 +188,24 @@ impl CountryAreas {
             }
         }
 
-        Ok(Self { areas })
+        Ok(Self::new(areas))
     }
 }
 
 impl Countries for CountryAreas {
     fn containing(&self, point: Point<f64>) -> Option<Country> {
-        self.areas
-            .iter()
-            .find(|(_, area)| area.contains(&point))
-            .map(|(country, _)| *country)
+        self.envelopes
+            .locate_in_envelope_intersecting(&AABB::from_point(point))
+            .map(|envelope| envelope.data)
+            .filter(|index| self.areas[*index].contains(point))
+            .min()
+            .map(|index| self.areas[index].country)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use geo::{Contains, MapCoords};
     use geo_types::{Geometry, polygon};
 
     use super::*;
@@ -107,13 +222,11 @@ mod tests {
 
     #[test]
     fn a_point_inside_an_area_is_in_that_country() {
-        let areas = CountryAreas {
-            areas: vec![(Country::Germany, square(10.0))],
-        };
+        let areas = CountryAreas::new(vec![(Country::GERMANY, square(10.0))]);
 
         assert_eq!(
             areas.containing(Point::new(5.0, 5.0)),
-            Some(Country::Germany)
+            Some(Country::GERMANY)
         );
     }
 
@@ -121,13 +234,142 @@ mod tests {
     /// project it into, and guessing the nearest would put geometry in the wrong metres.
     #[test]
     fn a_point_outside_every_area_is_in_no_country() {
-        let areas = CountryAreas {
-            areas: vec![(Country::Germany, square(10.0))],
-        };
+        let areas = CountryAreas::new(vec![(Country::GERMANY, square(10.0))]);
 
         assert_eq!(areas.containing(Point::new(20.0, 20.0)), None);
     }
 
+    /// A square ten degrees across with a square hole four degrees across in its middle,
+    /// and a notch cut into its eastern side, so that it is concave and holed at once.
+    fn holed() -> Geometry<f64> {
+        Geometry::Polygon(polygon![
+            exterior: [
+                (x: 0.0, y: 0.0),
+                (x: 10.0, y: 0.0),
+                (x: 10.0, y: 4.0),
+                (x: 6.0, y: 5.0),
+                (x: 10.0, y: 6.0),
+                (x: 10.0, y: 10.0),
+                (x: 0.0, y: 10.0),
+            ],
+            interiors: [[
+                (x: 1.0, y: 3.0),
+                (x: 5.0, y: 3.0),
+                (x: 5.0, y: 7.0),
+                (x: 1.0, y: 7.0),
+            ]],
+        ])
+    }
+
+    #[test]
+    fn a_point_in_a_hole_is_not_in_the_country() {
+        let areas = CountryAreas::new(vec![(Country::GERMANY, holed())]);
+
+        assert_eq!(areas.containing(Point::new(3.0, 5.0)), None);
+        assert_eq!(areas.containing(Point::new(8.0, 5.0)), None, "in the notch");
+        assert_eq!(
+            areas.containing(Point::new(0.5, 5.0)),
+            Some(Country::GERMANY)
+        );
+    }
+
+    /// Islands, exclaves: a country is often several polygons, and each is the country.
+    #[test]
+    fn every_part_of_a_multipolygon_is_the_country() {
+        let island = polygon![
+            (x: 20.0, y: 20.0),
+            (x: 21.0, y: 20.0),
+            (x: 21.0, y: 21.0),
+            (x: 20.0, y: 21.0),
+        ];
+        let Geometry::Polygon(mainland) = square(10.0) else {
+            unreachable!()
+        };
+        let areas = CountryAreas::new(vec![(
+            Country::DENMARK,
+            Geometry::MultiPolygon(vec![mainland, island].into()),
+        )]);
+
+        assert_eq!(
+            areas.containing(Point::new(5.0, 5.0)),
+            Some(Country::DENMARK)
+        );
+        assert_eq!(
+            areas.containing(Point::new(20.5, 20.5)),
+            Some(Country::DENMARK)
+        );
+        assert_eq!(areas.containing(Point::new(15.0, 15.0)), None);
+    }
+
+    /// The prepared test is a reimplementation of `Contains`, so it is held to it: over a
+    /// grid that puts points inside, outside, in the hole, on edges, on vertices, and level
+    /// with vertices, where a ray cast is easiest to get wrong.
+    #[test]
+    fn the_prepared_test_agrees_with_contains() {
+        let area = holed();
+        let areas = CountryAreas::new(vec![(Country::GERMANY, area.clone())]);
+
+        for x in -2..=24 {
+            for y in -2..=24 {
+                let point = Point::new(f64::from(x) * 0.5, f64::from(y) * 0.5);
+                let expected = area.contains(&point).then_some(Country::GERMANY);
+                assert_eq!(areas.containing(point), expected, "{point:?}");
+            }
+        }
+    }
+
+    /// A point on a border is in neither country, as `Contains` has it.
+    #[test]
+    fn a_point_on_the_border_is_in_neither_country() {
+        let Geometry::Polygon(east) = square(10.0) else {
+            unreachable!()
+        };
+        let areas = CountryAreas::new(vec![
+            (Country::GERMANY, square(10.0)),
+            (
+                Country::AUSTRIA,
+                Geometry::Polygon(east.map_coords(|c| Coord { x: c.x + 10.0, ..c })),
+            ),
+        ]);
+
+        assert_eq!(areas.containing(Point::new(10.0, 5.0)), None);
+        assert_eq!(
+            areas.containing(Point::new(10.5, 5.0)),
+            Some(Country::AUSTRIA)
+        );
+    }
+
+    /// Outlines are the reference data's, and two can overlap; the answer is still one
+    /// country, and the same one every time.
+    #[test]
+    fn where_two_areas_overlap_the_earlier_one_answers() {
+        let areas = CountryAreas::new(vec![
+            (Country::SWITZERLAND, square(10.0)),
+            (Country::AUSTRIA, square(10.0)),
+        ]);
+
+        assert_eq!(
+            areas.containing(Point::new(5.0, 5.0)),
+            Some(Country::SWITZERLAND)
+        );
+    }
+
+    #[test]
+    fn a_batch_is_answered_point_by_point_in_order() {
+        let areas = CountryAreas::new(vec![(Country::GERMANY, holed())]);
+        let points = [
+            Point::new(0.5, 0.5),
+            Point::new(3.0, 5.0),
+            Point::new(20.0, 20.0),
+            Point::new(9.0, 9.0),
+        ];
+
+        assert_eq!(
+            areas.containing_all(&points),
+            vec![Some(Country::GERMANY), None, None, Some(Country::GERMANY)]
+        );
+    }
+
     /// A store nothing has been extracted into cannot place a point, and says so rather
     /// than answering as though everywhere were unknown territory.
     #[tokio::test]
@@ -0,0 +1,216 @@
+//! Naming the things the crossings derivation finds, so two runs agree on what they found.
+//!
+//! A crossing is where a stretch of physical track meets a body of water. Both parts of that
+//! need a name that follows from the data rather than from the run:
+//!
+//!   - a **track** is a connected component of rail segments — segments joined end to end by
+//!     a shared connector. Labelling components in the order they were found names them by
+//!     row order, so the same track would be component 7 in one run and 12 in the next. Here a
+//!     component is named by the lexically smallest segment in it, which follows from its
+//!     members.
+//!   - a **crossing** is then named by the water, the track, and where along the track the two
+//!     meet. The place is part of the name because one track crosses one body of water more
+//!     than once, and those are separate sightings, not one.
+//!
+//! These are the names the water-crossings notebook minted before this derivation replaced it,
+//! computed the same way from the same parts. Which part names a crossing is decided here
+//! rather than by the notebook's row order where two parts tie — see `overlap::collapse` — so
+//! a crossing whose parts tie can carry a different name than the notebook gave it.
+
+use std::collections::HashMap;
+
+use model::CrossingId;
+
+/// Separates the parts of a crossing id, and marks the position within the last one. A
+/// composite rather than a hash: every part is already a column of the row, and a prediction
+/// that fails to match its ground truth is read by eye.
+const SEPARATOR: char = ':';
+const POSITION: char = '@';
+
+/// Decimal places the position is written to: a centimetre on a 10 km segment, far finer than
+/// what separates two crossings of the same water, and fixed width, so ids compare as strings.
+const POSITION_PLACES: usize = 6;
+
+/// Map each rail segment to the canonical id of the track it belongs to.
+///
+/// `segments` pairs each segment's id with the connectors it has; two segments sharing a
+/// connector are the same track. The canonical id of a track is the lexically smallest segment
+/// id in it, so the name follows from the component's members and not from the order they were
+/// read in.
+pub fn track_ids<S, C>(segments: &[(S, Vec<C>)]) -> HashMap<String, String>
+where
+    S: AsRef<str>,
+    C: AsRef<str>,
+{
+    let mut components = Components::new(segments.len());
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (segment, (_, connectors)) in segments.iter().enumerate() {
+        for connector in connectors {
+            match seen.get(connector.as_ref()) {
+                Some(other) => components.join(*other, segment),
+                None => {
+                    seen.insert(connector.as_ref(), segment);
+                }
+            }
+        }
+    }
+
+    let mut canonical: HashMap<usize, &str> = HashMap::new();
+    for (segment, (id, _)) in segments.iter().enumerate() {
+        let name = canonical
+            .entry(components.root(segment))
+            .or_insert(id.as_ref());
+        *name = (*name).min(id.as_ref());
+    }
+    segments
+        .iter()
+        .enumerate()
+        .map(|(segment, (id, _))| {
+            (
+                id.as_ref().to_string(),
+                canonical[&components.root(segment)].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// The id of the crossing where `track_id` meets `water_id` at `frac` along `rail_id`.
+///
+/// `frac` is the position along that one segment, from 0 at its start to 1 at its end. It is
+/// what separates the several crossings of one water body by one track, and `rail_id` is what
+/// gives it a meaning: each segment is parameterised on its own, so the same fraction of two
+/// segments of one track are different places.
+pub fn crossing_id(
+    water_id: &str,
+    track_id: &str,
+    rail_id: &str,
+    frac: f64,
+) -> Result<CrossingId, medallion::PathError> {
+    CrossingId::new(format!(
+        "{water_id}{SEPARATOR}{track_id}{SEPARATOR}{rail_id}{POSITION}{frac:.POSITION_PLACES$}"
+    ))
+}
+
+/// The four-byte name of the crossing `crossing_id` names: the first four bytes of its md5,
+/// read little-endian, which is the order a device casts them in.
+///
+/// A hash of the id rather than a re-derivation from the parts behind it, so the two cannot
+/// come to disagree about what one crossing is. Four bytes is few enough that two crossings can
+/// land on one name by chance; that is the store's to refuse when the dataset is written, not
+/// this function's to avoid.
+pub fn short_id(crossing_id: &CrossingId) -> u32 {
+    let digest = md5::compute(crossing_id.to_string());
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Connected components of a graph of `n` nodes, built up one edge at a time.
+///
+/// The roots it hands back depend on the order edges were joined in, so they are only ever
+/// used to **group** — anything that names a group derives the name from its members.
+pub(crate) struct Components {
+    parent: Vec<usize>,
+}
+
+impl Components {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+        }
+    }
+
+    /// The node standing for the component `node` is in.
+    pub(crate) fn root(&mut self, mut node: usize) -> usize {
+        while self.parent[node] != node {
+            self.parent[node] = self.parent[self.parent[node]];
+            node = self.parent[node];
+        }
+        node
+    }
+
+    /// Put `a` and `b` in the same component.
+    pub(crate) fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(listed: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        listed
+            .iter()
+            .map(|(id, connectors)| {
+                (
+                    id.to_string(),
+                    connectors.iter().map(|c| c.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segments_sharing_a_connector_are_one_track_named_by_its_smallest_member() {
+        let tracks = track_ids(&segments(&[
+            ("c", &["1", "2"]),
+            ("a", &["2", "3"]),
+            ("b", &["3", "4"]),
+            ("z", &["9"]),
+        ]));
+
+        assert_eq!(tracks["a"], "a");
+        assert_eq!(tracks["b"], "a");
+        assert_eq!(tracks["c"], "a");
+        assert_eq!(tracks["z"], "z");
+    }
+
+    /// The name follows from the members, so the order segments are read in — which a query
+    /// does not promise — does not change what a track is called.
+    #[test]
+    fn a_tracks_name_does_not_depend_on_the_order_its_segments_arrive_in() {
+        let listed: &[(&str, &[&str])] = &[("c", &["1", "2"]), ("a", &["2", "3"]), ("b", &["3"])];
+        let mut reversed = segments(listed);
+        reversed.reverse();
+
+        assert_eq!(track_ids(&segments(listed)), track_ids(&reversed));
+    }
+
+    #[test]
+    fn a_segment_with_no_connectors_is_a_track_of_its_own() {
+        let tracks = track_ids(&segments(&[("a", &[]), ("b", &[])]));
+
+        assert_eq!(tracks["a"], "a");
+        assert_eq!(tracks["b"], "b");
+    }
+
+    /// The format the notebook minted, so ids written by either name the same crossing.
+    #[test]
+    fn a_crossing_id_names_the_water_the_track_and_the_place_on_the_segment() {
+        let id = crossing_id("water", "track", "rail", 0.5).unwrap();
+
+        assert_eq!(id.to_string(), "water:track:rail@0.500000");
+    }
+
+    #[test]
+    fn the_position_is_written_to_a_fixed_number_of_places() {
+        let id = crossing_id("w", "t", "r", 1.0 / 3.0).unwrap();
+
+        assert_eq!(id.to_string(), "w:t:r@0.333333");
+    }
+
+    /// The value `hashlib` gives the notebook's `short_id` for the same id, so a device
+    /// flashed from either names a crossing the same way.
+    #[test]
+    fn a_short_id_is_the_low_four_bytes_of_the_ids_md5() {
+        let id = crossing_id("water", "track", "rail", 0.5).unwrap();
+
+        assert_eq!(short_id(&id), 0x3999_78dc);
+        assert_ne!(
+            short_id(&id),
+            short_id(&crossing_id("water", "track", "rail", 0.25).unwrap())
+        );
+    }
+}
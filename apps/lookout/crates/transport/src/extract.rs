@@ -223,6 +223,7 @@ impl<'a> Extractor<'a> {
             .dataset(model::OVERTURE_EXTRACT)
             .for_id(&id)?
             .holds_files()
+            .await
         {
             return Err(ExtractError::AlreadyPresent { id });
         }
@@ -453,7 +454,7 @@ mod tests {
             extract_id: id.to_string(),
             extracted_at: Utc.with_ymd_and_hms(2026, 7, 27, hour, 0, 0).unwrap(),
             release: release.to_string(),
-            country: Country::Germany.code().to_string(),
+            country: Country::GERMANY.code().to_string(),
             min_lon: window().min().x,
             min_lat: window().min().y,
             max_lon: window().max().x,
@@ -1,9 +1,16 @@
-//! Point-in-time extracts of Overture Maps into the bronze layer of the medallion store.
+//! Point-in-time extracts of Overture Maps into the bronze layer of the medallion store, and
+//! the silver water crossings derived from them.
 //!
 //!   - [`overture`] — read one release, from the public bucket or a local mirror of it.
 //!   - [`extract`] — write a country's rail, water and divisions from it into bronze.
 //!   - [`countries`] — which country a place is in, from the areas an extract took.
+//!   - [`silver`] — derive the silver water crossings from an extract, deciding which
+//!     meetings of rail and water are crossings in [`overlap`] and naming them in
+//!     [`crossing_ids`].
 
 pub mod countries;
+pub mod crossing_ids;
 pub mod extract;
+pub mod overlap;
 pub mod overture;
+pub mod silver;
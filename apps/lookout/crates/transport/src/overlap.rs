@@ -0,0 +1,841 @@
+//! Where rail meets water, and which of those meetings are crossings.
+//!
+//! A stretch of rail and a body of water overlap in **parts**: a line where the track runs
+//! across an area of water, a point where it crosses the centreline of a watercourse. Each part
+//! is kept or dropped on its own, and the parts that survive are collapsed into crossings:
+//!
+//!   - a line is kept if it is longer than [`Tuning::min_crossing_m`] — shorter, and a train
+//!     is past it before anyone could see it;
+//!   - a point is kept if the water is of a class wide enough to notice from a train, since a
+//!     centreline has no width of its own to measure;
+//!   - a point inside an area of water is dropped, because the area's own line part already
+//!     says that water was crossed there;
+//!   - a part on a stretch of rail flagged as a tunnel or covered, or as carrying no trains, is
+//!     dropped: nobody sees the water from there.
+//!
+//! Lengths and merge distances are measured in the country's projected metres; where a part
+//! falls along its segment, `frac`, is measured in lat/lon, as the reference data holds the
+//! segment. Parts of one track over one water within [`Tuning::merge_distance_m`] of each
+//! other collapse into one crossing — a bridge is one crossing, not one per span — represented
+//! by its longest part.
+
+use std::collections::HashMap;
+
+use geo::line_intersection::{LineIntersection, line_intersection};
+use geo::{BooleanOps, Centroid, Contains, Euclidean, Intersects, Length, LineLocatePoint};
+use geo_types::{Geometry, LineString, MultiLineString, Point};
+use medallion::{GeoError, PathError, Projector};
+use model::{OverlapKind, WaterCrossingRow};
+use rstar::RTree;
+use rstar::primitives::GeomWithData;
+
+use crate::crossing_ids::{Components, crossing_id, short_id, track_ids};
+
+/// Water classes wide enough that crossing their centreline is worth calling a crossing.
+const SUBSTANTIAL_WATER_CLASSES: &[&str] = &["river", "canal", "fairway", "water"];
+
+/// `rail_flags` values meaning no train passenger would see the water there: the view is
+/// blocked, or no train runs. `is_bridge` is the opposite, and deliberately not here.
+const BLOCKING_RAIL_FLAGS: &[&str] = &[
+    "is_tunnel",
+    "is_covered",
+    "is_abandoned",
+    "is_disused",
+    "is_under_construction",
+];
+
+/// The choices that decide what one crossing is. Stored on every row they produced, since two
+/// runs that collapsed differently do not agree on what a crossing is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    /// How close two parts of one track over one water have to be to be one crossing, in
+    /// metres.
+    pub merge_distance_m: f64,
+    /// The shortest line overlap kept, in metres.
+    pub min_crossing_m: f64,
+}
+
+impl Default for Tuning {
+    /// What the notebook this derivation replaced settled on, by eye, over Germany.
+    fn default() -> Self {
+        Self {
+            merge_distance_m: 100.0,
+            min_crossing_m: 5.0,
+        }
+    }
+}
+
+/// One rail segment, as the extract holds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rail {
+    pub id: String,
+    pub class: Option<String>,
+    pub line: LineString<f64>,
+    /// The connectors joining it to the segments either side, which is what makes a track.
+    pub connectors: Vec<String>,
+    pub flags: Vec<RailFlag>,
+}
+
+/// One of a segment's `rail_flags`: some values, over all of it or over a stretch of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RailFlag {
+    pub values: Vec<String>,
+    /// The stretch the values hold over, as fractions from the segment's start; the whole
+    /// segment where absent.
+    pub between: Option<(f64, f64)>,
+}
+
+impl RailFlag {
+    /// Whether this flag hides water at `frac` along its segment from a passenger.
+    fn blocks(&self, frac: f64) -> bool {
+        self.values
+            .iter()
+            .any(|value| BLOCKING_RAIL_FLAGS.contains(&value.as_str()))
+            && self
+                .between
+                .is_none_or(|(from, to)| (from..=to).contains(&frac))
+    }
+}
+
+/// One body of water, as the extract holds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Water {
+    pub id: String,
+    pub subtype: Option<String>,
+    pub class: Option<String>,
+    pub geometry: Geometry<f64>,
+}
+
+impl Water {
+    fn is_areal(&self) -> bool {
+        matches!(
+            self.geometry,
+            Geometry::Polygon(_) | Geometry::MultiPolygon(_)
+        )
+    }
+
+    fn is_substantial(&self) -> bool {
+        self.class
+            .as_deref()
+            .is_some_and(|class| SUBSTANTIAL_WATER_CLASSES.contains(&class))
+    }
+}
+
+/// A rail segment and every body of water its line touches.
+///
+/// Which waters those are is the expensive question, and the caller's: a spatial join answers
+/// it far faster than anything done pair by pair here.
+#[derive(Debug, Clone)]
+pub struct Meeting<'a> {
+    pub rail: &'a Rail,
+    pub waters: Vec<&'a Water>,
+}
+
+/// A failure deriving the crossings.
+#[derive(Debug, thiserror::Error)]
+pub enum OverlapError {
+    #[error("measuring in metres: {0}")]
+    Geo(#[from] GeoError),
+    #[error("naming a crossing: {0}")]
+    Name(#[from] PathError),
+}
+
+/// One part kept: where it is, how long it is, and what it is a part of.
+#[derive(Debug, Clone)]
+struct Part<'a> {
+    rail: &'a Rail,
+    water: &'a Water,
+    kind: OverlapKind,
+    overlap_m: f64,
+    /// The part's centroid in lat/lon, which is where the crossing is said to be.
+    at: Point<f64>,
+    /// The same in metres, which is what merging measures.
+    at_m: Point<f64>,
+    frac: f64,
+}
+
+/// One piece of the overlap of a rail line and a water geometry, before it is measured.
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Line(LineString<f64>),
+    Point(Point<f64>),
+}
+
+/// Every crossing the meetings hold, as rows of `water_crossing` with the point each is at,
+/// ordered by id.
+///
+/// Tracks are the connected runs of the segments that have a crossing on them, as the notebook
+/// this replaced named them, so a track's name is decided by the crossed segments alone.
+pub fn crossings(
+    meetings: &[Meeting],
+    tuning: Tuning,
+    extract_id: &str,
+    projector: &Projector,
+) -> Result<Vec<(WaterCrossingRow, Point<f64>)>, OverlapError> {
+    let mut parts = Vec::new();
+    for meeting in meetings {
+        parts.extend(parts_of(meeting, tuning, projector)?);
+    }
+
+    let mut crossed: Vec<&Rail> = parts.iter().map(|part| part.rail).collect();
+    crossed.sort_by(|a, b| a.id.cmp(&b.id));
+    crossed.dedup_by(|a, b| a.id == b.id);
+    let tracks = track_ids(
+        &crossed
+            .iter()
+            .map(|rail| (rail.id.as_str(), rail.connectors.clone()))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut rows = collapse(&parts, &tracks, tuning)
+        .into_iter()
+        .map(|collapsed| collapsed.row(&tracks, tuning, extract_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.sort_by(|(a, _), (b, _)| a.crossing_id.cmp(&b.crossing_id));
+    Ok(rows)
+}
+
+/// The parts of one segment's overlap with its waters that survive every rule above.
+fn parts_of<'a>(
+    meeting: &Meeting<'a>,
+    tuning: Tuning,
+    projector: &Projector,
+) -> Result<Vec<Part<'a>>, OverlapError> {
+    let rail = meeting.rail;
+    let mut kept = Vec::new();
+    for &water in &meeting.waters {
+        for piece in pieces(&rail.line, &water.geometry) {
+            let (kind, overlap_m, at) = match &piece {
+                Piece::Line(line) => {
+                    let overlap_m = Euclidean.length(&projector.project(line)?);
+                    if overlap_m <= tuning.min_crossing_m {
+                        continue;
+                    }
+                    let Some(at) = line.centroid() else { continue };
+                    (OverlapKind::Line, overlap_m, at)
+                }
+                Piece::Point(point) => {
+                    if !water.is_substantial() {
+                        continue;
+                    }
+                    (OverlapKind::Point, 0.0, *point)
+                }
+            };
+            let Some(frac) = rail.line.line_locate_point(&at) else {
+                continue;
+            };
+
+            let redundant = kind == OverlapKind::Point
+                && meeting
+                    .waters
+                    .iter()
+                    .any(|other| other.is_areal() && other.geometry.contains(&at));
+            let blocked = rail.flags.iter().any(|flag| flag.blocks(frac));
+            if redundant || blocked {
+                continue;
+            }
+
+            kept.push(Part {
+                rail,
+                water,
+                kind,
+                overlap_m,
+                at,
+                at_m: projector.project(&at)?,
+                frac,
+            });
+        }
+    }
+    Ok(kept)
+}
+
+/// The pieces `rail` and `water` overlap in.
+///
+/// An area of water clips the rail to the stretches inside it. A line of water meets the rail
+/// at the points the two cross, and along any stretch the two share; a point where they cross
+/// at the end of such a stretch is part of it, not a second piece.
+fn pieces(rail: &LineString<f64>, water: &Geometry<f64>) -> Vec<Piece> {
+    let clipped = |inside: MultiLineString<f64>| {
+        inside
+            .into_iter()
+            .filter(|line| line.0.len() > 1)
+            .map(Piece::Line)
+            .collect()
+    };
+    let rail_only = || MultiLineString::new(vec![rail.clone()]);
+
+    match water {
+        Geometry::Polygon(area) => clipped(area.clip(&rail_only(), false)),
+        Geometry::MultiPolygon(areas) => clipped(areas.clip(&rail_only(), false)),
+        Geometry::Rect(area) => clipped(area.to_polygon().clip(&rail_only(), false)),
+        Geometry::Triangle(area) => clipped(area.to_polygon().clip(&rail_only(), false)),
+        Geometry::LineString(line) => crossed(rail, std::slice::from_ref(line)),
+        Geometry::MultiLineString(lines) => crossed(rail, &lines.0),
+        Geometry::Line(line) => crossed(rail, &[LineString::from(*line)]),
+        Geometry::GeometryCollection(collection) => collection
+            .iter()
+            .flat_map(|member| pieces(rail, member))
+            .collect(),
+        Geometry::Point(_) | Geometry::MultiPoint(_) => Vec::new(),
+    }
+}
+
+/// The pieces `rail` meets `lines` in: the stretches the two share, then the points they
+/// cross at that are not on one.
+fn crossed(rail: &LineString<f64>, lines: &[LineString<f64>]) -> Vec<Piece> {
+    let mut shared = Vec::new();
+    let mut points: Vec<Point<f64>> = Vec::new();
+    for water in lines {
+        for along in rail.lines() {
+            for across in water.lines() {
+                match line_intersection(along, across) {
+                    Some(LineIntersection::SinglePoint { intersection, .. }) => {
+                        let point = Point::from(intersection);
+                        if !points.contains(&point) {
+                            points.push(point);
+                        }
+                    }
+                    Some(LineIntersection::Collinear { intersection })
+                        if intersection.start != intersection.end =>
+                    {
+                        shared.push(intersection);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    points.retain(|point| !shared.iter().any(|line| line.intersects(point)));
+    shared
+        .into_iter()
+        .map(|line| Piece::Line(LineString::from(line)))
+        .chain(points.into_iter().map(Piece::Point))
+        .collect()
+}
+
+/// One crossing: the part that represents it, and what it was merged from.
+struct Collapsed<'p, 'a> {
+    representative: &'p Part<'a>,
+    merged_parts: u32,
+    total_overlap_m: f64,
+}
+
+impl Collapsed<'_, '_> {
+    fn row(
+        &self,
+        tracks: &HashMap<String, String>,
+        tuning: Tuning,
+        extract_id: &str,
+    ) -> Result<(WaterCrossingRow, Point<f64>), OverlapError> {
+        let part = self.representative;
+        let track_id = &tracks[&part.rail.id];
+        let crossing_id = crossing_id(&part.water.id, track_id, &part.rail.id, part.frac)?;
+        Ok((
+            WaterCrossingRow {
+                crossing_short_id: short_id(&crossing_id),
+                crossing_id,
+                water_id: part.water.id.clone(),
+                water_subtype: part.water.subtype.clone(),
+                water_class: part.water.class.clone(),
+                track_id: track_id.clone(),
+                rail_id: part.rail.id.clone(),
+                rail_class: part.rail.class.clone(),
+                overlap_kind: part.kind,
+                overlap_m: part.overlap_m,
+                total_overlap_m: self.total_overlap_m,
+                merged_parts: self.merged_parts,
+                frac: part.frac,
+                extract_id: extract_id.to_string(),
+                merge_distance_m: tuning.merge_distance_m,
+                min_crossing_m: tuning.min_crossing_m,
+            },
+            part.at,
+        ))
+    }
+}
+
+/// The parts merged into crossings: parts of one track over one water, each within the merge
+/// distance of another, are one crossing, represented by its longest part.
+///
+/// Where two are as long — every point part is — the one nearer the start of the
+/// lexically smaller segment represents it, so which part names a crossing follows from the
+/// parts and not from the order they were found in. The notebook this replaced took the first
+/// of them in its row order instead, which followed from its join, so a crossing of tied parts
+/// can be named by a different part, and so differently, than the notebook named it.
+///
+/// Only parts of one track over one water can merge, so the parts are grouped by the two
+/// first, and each group's near neighbours found through an index on where its parts are —
+/// a country's parts are never compared pair by pair.
+fn collapse<'p, 'a>(
+    parts: &'p [Part<'a>],
+    tracks: &HashMap<String, String>,
+    tuning: Tuning,
+) -> Vec<Collapsed<'p, 'a>> {
+    let mut groups: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (index, part) in parts.iter().enumerate() {
+        groups
+            .entry((tracks[&part.rail.id].as_str(), part.water.id.as_str()))
+            .or_default()
+            .push(index);
+    }
+    let mut components = Components::new(parts.len());
+    let within = tuning.merge_distance_m * tuning.merge_distance_m;
+    for members in groups.values() {
+        let placed = RTree::bulk_load(
+            members
+                .iter()
+                .map(|&member| GeomWithData::new(parts[member].at_m, member))
+                .collect(),
+        );
+        for &member in members {
+            for near in placed.locate_within_distance(parts[member].at_m, within) {
+                components.join(member, near.data);
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = Vec::new();
+    let mut merged: HashMap<usize, Collapsed> = HashMap::new();
+    for (index, part) in parts.iter().enumerate() {
+        let root = components.root(index);
+        let collapsed = merged.entry(root).or_insert_with(|| {
+            order.push(root);
+            Collapsed {
+                representative: part,
+                merged_parts: 0,
+                total_overlap_m: 0.0,
+            }
+        });
+        if represents(part, collapsed.representative) {
+            collapsed.representative = part;
+        }
+        collapsed.merged_parts += 1;
+        collapsed.total_overlap_m += part.overlap_m;
+    }
+    order
+        .into_iter()
+        .filter_map(|root| merged.remove(&root))
+        .collect()
+}
+
+/// Whether `part` represents a crossing better than `current` does.
+fn represents(part: &Part, current: &Part) -> bool {
+    part.overlap_m
+        .total_cmp(&current.overlap_m)
+        .then_with(|| current.rail.id.cmp(&part.rail.id))
+        .then_with(|| current.frac.total_cmp(&part.frac))
+        .is_gt()
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{line_string, polygon};
+    use medallion::Country;
+
+    use super::*;
+
+    /// Ruhland, where a line crosses the Schwarze Elster: the rail in these tests runs east
+    /// from here.
+    const LON: f64 = 13.548209;
+    const LAT: f64 = 51.617567;
+
+    /// Degrees of longitude per metre at the test latitude — near enough to place things a
+    /// known distance apart; what is measured is measured in the projected zone.
+    fn east(metres: f64) -> f64 {
+        LON + metres / 111_320.0 / LAT.to_radians().cos()
+    }
+
+    fn north(metres: f64) -> f64 {
+        LAT + metres / 111_320.0
+    }
+
+    fn projector() -> Projector {
+        Projector::for_country(Country::GERMANY).expect("projector")
+    }
+
+    /// A segment running `metres` east from Ruhland.
+    fn rail(id: &str, from_m: f64, to_m: f64, connectors: &[&str]) -> Rail {
+        Rail {
+            id: id.to_string(),
+            class: Some("standard_gauge".to_string()),
+            line: line_string![(x: east(from_m), y: LAT), (x: east(to_m), y: LAT)],
+            connectors: connectors.iter().map(|c| c.to_string()).collect(),
+            flags: Vec::new(),
+        }
+    }
+
+    /// A river running north–south across the rail, `at_m` metres east of Ruhland.
+    fn river(id: &str, at_m: f64, class: &str) -> Water {
+        Water {
+            id: id.to_string(),
+            subtype: Some("river".to_string()),
+            class: Some(class.to_string()),
+            geometry: Geometry::LineString(line_string![
+                (x: east(at_m), y: north(-100.0)),
+                (x: east(at_m), y: north(100.0)),
+            ]),
+        }
+    }
+
+    /// A lake spanning `from_m` to `to_m` metres east of Ruhland, across the rail.
+    fn lake(id: &str, from_m: f64, to_m: f64) -> Water {
+        Water {
+            id: id.to_string(),
+            subtype: Some("lake".to_string()),
+            class: Some("lake".to_string()),
+            geometry: Geometry::Polygon(polygon![
+                (x: east(from_m), y: north(-50.0)),
+                (x: east(to_m), y: north(-50.0)),
+                (x: east(to_m), y: north(50.0)),
+                (x: east(from_m), y: north(50.0)),
+            ]),
+        }
+    }
+
+    fn derive(meetings: &[Meeting]) -> Vec<WaterCrossingRow> {
+        crossings(
+            meetings,
+            Tuning::default(),
+            "20260804T152143Z",
+            &projector(),
+        )
+        .expect("derive")
+        .into_iter()
+        .map(|(row, _)| row)
+        .collect()
+    }
+
+    #[test]
+    fn rail_crossing_a_river_is_a_point_crossing_where_they_meet() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let river = river("w", 250.0, "river");
+
+        let found = crossings(
+            &[Meeting {
+                rail: &rail,
+                waters: vec![&river],
+            }],
+            Tuning::default(),
+            "20260804T152143Z",
+            &projector(),
+        )
+        .unwrap();
+
+        assert_eq!(found.len(), 1);
+        let (row, at) = &found[0];
+        assert_eq!(row.overlap_kind, OverlapKind::Point);
+        assert_eq!(row.overlap_m, 0.0);
+        assert!((row.frac - 0.25).abs() < 1e-6, "{}", row.frac);
+        assert!((at.x() - east(250.0)).abs() < 1e-9 && (at.y() - LAT).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rail_across_a_lake_is_a_line_crossing_as_long_as_the_lake_is_wide() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let lake = lake("w", 400.0, 600.0);
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&lake],
+        }]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].overlap_kind, OverlapKind::Line);
+        assert!(
+            (found[0].overlap_m - 200.0).abs() < 2.0,
+            "{}",
+            found[0].overlap_m
+        );
+        assert!((found[0].frac - 0.5).abs() < 1e-6);
+    }
+
+    /// A few metres of water is gone past before anyone could look at it.
+    #[test]
+    fn a_line_overlap_no_longer_than_the_minimum_is_dropped() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let pond = lake("w", 500.0, 504.0);
+
+        assert!(
+            derive(&[Meeting {
+                rail: &rail,
+                waters: vec![&pond],
+            }])
+            .is_empty()
+        );
+    }
+
+    /// A centreline has no width to measure, so its class stands in for one.
+    #[test]
+    fn a_point_crossing_of_a_minor_watercourse_is_dropped() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let ditch = river("w", 250.0, "ditch");
+
+        assert!(
+            derive(&[Meeting {
+                rail: &rail,
+                waters: vec![&ditch],
+            }])
+            .is_empty()
+        );
+    }
+
+    /// The river's centreline inside the lake it flows through would say the same crossing
+    /// again: the lake's own line part already says it.
+    #[test]
+    fn a_point_crossing_inside_an_area_of_water_is_dropped() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let lake = lake("lake", 400.0, 600.0);
+        let river = river("river", 500.0, "river");
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&lake, &river],
+        }]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].water_id, "lake");
+    }
+
+    #[test]
+    fn a_crossing_in_a_tunnel_is_dropped() {
+        let mut rail = rail("r", 0.0, 1_000.0, &[]);
+        rail.flags = vec![RailFlag {
+            values: vec!["is_tunnel".to_string()],
+            between: Some((0.2, 0.3)),
+        }];
+
+        let tunnelled = river("tunnelled", 250.0, "river");
+        let open = river("open", 750.0, "river");
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&tunnelled, &open],
+        }]);
+
+        assert_eq!(
+            found
+                .iter()
+                .map(|row| row.water_id.as_str())
+                .collect::<Vec<_>>(),
+            ["open"]
+        );
+    }
+
+    /// A bridge is the opposite of a tunnel — the view is better, not blocked.
+    #[test]
+    fn a_crossing_on_a_bridge_is_kept() {
+        let mut rail = rail("r", 0.0, 1_000.0, &[]);
+        rail.flags = vec![RailFlag {
+            values: vec!["is_bridge".to_string()],
+            between: None,
+        }];
+        let river = river("w", 250.0, "river");
+
+        assert_eq!(
+            derive(&[Meeting {
+                rail: &rail,
+                waters: vec![&river],
+            }])
+            .len(),
+            1
+        );
+    }
+
+    /// Two spans of one bridge over one river are one crossing, represented by the longer and
+    /// carrying the length of both.
+    #[test]
+    fn parts_of_one_track_over_one_water_within_the_merge_distance_are_one_crossing() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let water = Water {
+            geometry: Geometry::MultiPolygon(geo_types::MultiPolygon::new(vec![
+                match lake("a", 400.0, 420.0).geometry {
+                    Geometry::Polygon(area) => area,
+                    _ => unreachable!(),
+                },
+                match lake("b", 440.0, 470.0).geometry {
+                    Geometry::Polygon(area) => area,
+                    _ => unreachable!(),
+                },
+            ])),
+            ..lake("w", 0.0, 0.0)
+        };
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&water],
+        }]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].merged_parts, 2);
+        assert!(
+            (found[0].overlap_m - 30.0).abs() < 1.0,
+            "{}",
+            found[0].overlap_m
+        );
+        assert!((found[0].total_overlap_m - 50.0).abs() < 1.0);
+    }
+
+    /// A line following a valley crosses its river again and again, and each is a sighting.
+    #[test]
+    fn crossings_of_one_water_further_apart_than_the_merge_distance_stay_separate() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let water = Water {
+            geometry: Geometry::MultiLineString(MultiLineString::new(vec![
+                match river("a", 100.0, "river").geometry {
+                    Geometry::LineString(line) => line,
+                    _ => unreachable!(),
+                },
+                match river("b", 900.0, "river").geometry {
+                    Geometry::LineString(line) => line,
+                    _ => unreachable!(),
+                },
+            ])),
+            ..river("w", 0.0, "river")
+        };
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&water],
+        }]);
+
+        assert_eq!(found.len(), 2);
+        assert_ne!(found[0].crossing_id, found[1].crossing_id);
+    }
+
+    /// Merging is transitive: parts each within the merge distance of the next are one
+    /// crossing, however far apart the first and last are.
+    #[test]
+    fn a_chain_of_parts_each_near_the_next_is_one_crossing() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let water = Water {
+            geometry: Geometry::MultiLineString(MultiLineString::new(
+                [300.0, 380.0, 460.0, 540.0]
+                    .into_iter()
+                    .map(|at_m| match river("part", at_m, "river").geometry {
+                        Geometry::LineString(line) => line,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            )),
+            ..river("w", 0.0, "river")
+        };
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&water],
+        }]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].merged_parts, 4);
+        assert!((found[0].frac - 0.3).abs() < 1e-6, "{}", found[0].frac);
+    }
+
+    /// Merging is within one track: two tracks side by side over one river are two crossings,
+    /// however close.
+    #[test]
+    fn parts_of_different_tracks_are_not_merged() {
+        let upper = Rail {
+            line: line_string![
+                (x: east(0.0), y: north(10.0)),
+                (x: east(1_000.0), y: north(10.0)),
+            ],
+            ..rail("upper", 0.0, 0.0, &["u1", "u2"])
+        };
+        let lower = rail("lower", 0.0, 1_000.0, &["l1", "l2"]);
+        let river = river("w", 500.0, "river");
+
+        let found = derive(&[
+            Meeting {
+                rail: &upper,
+                waters: vec![&river],
+            },
+            Meeting {
+                rail: &lower,
+                waters: vec![&river],
+            },
+        ]);
+
+        assert_eq!(found.len(), 2);
+        assert_ne!(found[0].track_id, found[1].track_id);
+    }
+
+    /// A crossing spanning the joint between two segments of one track is one crossing, named
+    /// by the track both segments make up.
+    #[test]
+    fn parts_on_connected_segments_merge_into_one_crossing_of_their_track() {
+        let first = rail("b-first", 0.0, 500.0, &["start", "joint"]);
+        let second = rail("a-second", 500.0, 1_000.0, &["joint", "end"]);
+        let lake = lake("w", 450.0, 560.0);
+
+        let found = derive(&[
+            Meeting {
+                rail: &first,
+                waters: vec![&lake],
+            },
+            Meeting {
+                rail: &second,
+                waters: vec![&lake],
+            },
+        ]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].track_id, "a-second");
+        assert_eq!(found[0].rail_id, "a-second");
+        assert_eq!(found[0].merged_parts, 2);
+    }
+
+    /// What the rows say about the run that made them, so they stay interpretable after it.
+    #[test]
+    fn a_row_carries_its_extract_and_tuning_and_both_its_names() {
+        let rail = rail("r", 0.0, 1_000.0, &[]);
+        let river = river("w", 250.0, "river");
+
+        let found = derive(&[Meeting {
+            rail: &rail,
+            waters: vec![&river],
+        }]);
+
+        let row = &found[0];
+        assert_eq!(row.extract_id, "20260804T152143Z");
+        assert_eq!(row.merge_distance_m, 100.0);
+        assert_eq!(row.min_crossing_m, 5.0);
+        assert_eq!(row.crossing_id.to_string(), "w:r:r@0.250000");
+        assert_eq!(row.crossing_short_id, short_id(&row.crossing_id));
+    }
+
+    /// The rows come back in id order, so the same extract derives the same dataset however
+    /// the meetings happened to be read.
+    #[test]
+    fn the_crossings_do_not_depend_on_the_order_the_meetings_arrive_in() {
+        let first = rail("a", 0.0, 1_000.0, &[]);
+        let second = Rail {
+            line: line_string![
+                (x: east(0.0), y: north(500.0)),
+                (x: east(1_000.0), y: north(500.0)),
+            ],
+            ..rail("b", 0.0, 0.0, &[])
+        };
+        let river = Water {
+            geometry: Geometry::LineString(line_string![
+                (x: east(250.0), y: north(-100.0)),
+                (x: east(250.0), y: north(600.0)),
+            ]),
+            ..river("w", 0.0, "river")
+        };
+        let meetings = [
+            Meeting {
+                rail: &first,
+                waters: vec![&river],
+            },
+            Meeting {
+                rail: &second,
+                waters: vec![&river],
+            },
+        ];
+        let mut reversed = meetings.to_vec();
+        reversed.reverse();
+
+        assert_eq!(derive(&meetings), derive(&reversed));
+    }
+}
@@ -0,0 +1,434 @@
+//! Deriving the silver `water_crossing` dataset: every place a stretch of track meets a body of
+//! water, from one Overture extract.
+//!
+//! The spatial work is split where it is cheapest. Which rail lies in the country, and which
+//! waters each rail segment touches, are joins — SedonaDB answers them over the whole extract
+//! far faster than anything pair by pair. What the overlap of one segment and one water *is*,
+//! and whether it counts as a crossing, is decided in [`crate::overlap`], one segment at a
+//! time, in the country's projected metres.
+//!
+//! The rail is clipped to the country by where its crossings fall rather than by cutting its
+//! lines: a segment crossing the border is read whole, so `frac` is still a fraction of the
+//! segment the reference data holds, and a crossing beyond the border is dropped once it is
+//! known where it is. An extract's window is the country's envelope, which takes in some of
+//! every neighbour.
+//!
+//! A run derives the whole dataset from one extract, and replaces what it produces, so a
+//! country the extract does not cover goes with it.
+
+use std::collections::HashMap;
+
+use geo::Contains;
+use geo_types::{Geometry, Point};
+use medallion::lineage::{self, LineageError, Pending};
+use medallion::{Country, GEOMETRY, GeoRow, Query, Replaced, Root};
+use model::{ExtractManifestRow, WaterCrossingRow};
+use serde::Deserialize;
+
+use crate::extract::{self, ExtractError, ExtractId};
+use crate::overlap::{self, Meeting, OverlapError, Rail, RailFlag, Tuning, Water};
+
+/// What one run derived.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CrossingsOutcome {
+    pub extract_id: String,
+    /// Rail segments in the country.
+    pub rails: usize,
+    /// Waters some rail segment touches.
+    pub waters: usize,
+    /// Crossings written, after collapsing and clipping to the country.
+    pub crossings: usize,
+    pub partitions: Replaced,
+}
+
+/// A failure deriving the water crossings.
+#[derive(Debug, thiserror::Error)]
+pub enum CrossingsError {
+    #[error("reading the extract: {0}")]
+    Query(#[from] medallion::QueryError),
+    #[error("extract {id} was taken over {country}, which the store has no zone for")]
+    UnknownCountry {
+        id: String,
+        country: String,
+        #[source]
+        source: medallion::UnknownCountry,
+    },
+    #[error("choosing the extract: {0}")]
+    Extract(#[from] ExtractError),
+    #[error("partitioning the extract: {0}")]
+    Path(#[from] medallion::PathError),
+    #[error("reading the extract's geometry: {0}")]
+    Geo(#[from] medallion::GeoError),
+    #[error("deriving the crossings: {0}")]
+    Overlap(#[from] OverlapError),
+    #[error("rail segment {id} is not a line, so there is nowhere along it to cross")]
+    NotALine { id: String },
+    #[error("extract {id} holds {found} geometries for its {expected} {table} rows")]
+    Misaligned {
+        id: String,
+        table: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("writing the dataset: {0}")]
+    Write(#[from] medallion::TableError),
+    #[error("reading what the dataset was derived from: {0}")]
+    Lineage(#[from] LineageError),
+}
+
+/// One rail segment's attributes as the extract holds them.
+#[derive(Debug, Deserialize)]
+struct StoredRail {
+    id: String,
+    class: Option<String>,
+}
+
+/// One connector a rail segment names.
+#[derive(Debug, Deserialize)]
+struct StoredConnector {
+    id: String,
+    connector_id: String,
+}
+
+/// One of a rail segment's `rail_flags`, unnested.
+#[derive(Debug, Deserialize)]
+struct StoredFlag {
+    id: String,
+    values: Vec<String>,
+    between: Option<Vec<f64>>,
+}
+
+/// One water's attributes as the extract holds them.
+#[derive(Debug, Deserialize)]
+struct StoredWater {
+    id: String,
+    subtype: Option<String>,
+    class: Option<String>,
+}
+
+/// A rail segment and a water it touches.
+#[derive(Debug, Deserialize)]
+struct StoredMeeting {
+    rail_id: String,
+    water_id: String,
+}
+
+/// Derive the crossings in extract `id`, or in the newest extract if none is named, and write
+/// them.
+pub async fn derive(
+    root: &Root,
+    id: Option<&ExtractId>,
+    tuning: Tuning,
+) -> Result<CrossingsOutcome, CrossingsError> {
+    let (query, recorded) = register_extract(root, id).await?;
+    let country = country_of(&recorded)?;
+
+    let area = area_of(&query, country).await?;
+    let rails = rails_in(&query, country, &recorded.extract_id).await?;
+    let waters = waters_met(&query, country, &recorded.extract_id).await?;
+    let meetings = meetings_of(&query, country, &rails, &waters).await?;
+
+    let projector = medallion::Projector::for_country(country)?;
+    let rows: Vec<GeoRow<WaterCrossingRow, Point<f64>>> =
+        overlap::crossings(&meetings, tuning, &recorded.extract_id, &projector)?
+            .into_iter()
+            .filter(|(_, at)| area.iter().any(|area| area.contains(at)))
+            .map(|(row, at)| GeoRow {
+                row,
+                geometry: at,
+                country,
+            })
+            .collect();
+
+    let written = medallion::write_country_rows(root, &rows).await?;
+    Ok(CrossingsOutcome {
+        extract_id: recorded.extract_id,
+        rails: rails.len(),
+        waters: waters.len(),
+        crossings: written.rows,
+        partitions: written.partitions,
+    })
+}
+
+/// Whether deriving from extract `id`, or the newest, has anything to derive: `false` where
+/// neither the extract nor the tuning has changed since the last run — see
+/// [`medallion::lineage::pending`]. A run derives the whole dataset or none of it.
+pub async fn pending(root: &Root, id: Option<&ExtractId>) -> Result<bool, CrossingsError> {
+    register_extract(root, id).await?;
+    Ok(lineage::pending(root, &[model::WATER_CROSSING.info()]).await? != Pending::Nothing)
+}
+
+/// Choose extract `id`, or the newest, and register the themes a run reads from it.
+async fn register_extract(
+    root: &Root,
+    id: Option<&ExtractId>,
+) -> Result<(Query, ExtractManifestRow), CrossingsError> {
+    let recorded = match id {
+        Some(id) => extract::recorded_as(root, id).await?,
+        None => extract::newest(root).await?,
+    };
+    let query = Query::new(root.clone());
+    let extract = root
+        .dataset(model::OVERTURE_EXTRACT)
+        .for_id(&recorded.extract_id)?;
+    for (theme, kind) in [
+        ("divisions", "division_area"),
+        ("transportation", "segment"),
+        ("base", "water"),
+    ] {
+        query
+            .register_at(
+                &extract
+                    .clone()
+                    .partition("theme", theme)?
+                    .partition("type", kind)?,
+                kind,
+            )
+            .await?;
+    }
+    Ok((query, recorded))
+}
+
+/// The country an extract was taken over.
+fn country_of(recorded: &ExtractManifestRow) -> Result<Country, CrossingsError> {
+    recorded
+        .country
+        .parse()
+        .map_err(|source| CrossingsError::UnknownCountry {
+            id: recorded.extract_id.clone(),
+            country: recorded.country.clone(),
+            source,
+        })
+}
+
+/// The country's own area, which a crossing has to fall in to be the country's.
+async fn area_of(query: &Query, country: Country) -> Result<Vec<Geometry<f64>>, CrossingsError> {
+    let batches = query
+        .sql(&format!(
+            "SELECT ST_AsBinary({GEOMETRY}) AS {GEOMETRY} FROM division_area
+             WHERE subtype = 'country' AND country = '{}'",
+            country.code()
+        ))
+        .await?;
+    let mut area = Vec::new();
+    for batch in &batches {
+        area.extend(medallion::geometries(batch, GEOMETRY)?);
+    }
+    Ok(area)
+}
+
+/// Every rail segment that reaches into the country, with its connectors and flags.
+async fn rails_in(
+    query: &Query,
+    country: Country,
+    extract_id: &str,
+) -> Result<Vec<Rail>, CrossingsError> {
+    let rail = rail_in("s", country);
+    let stored: Vec<StoredRail> = query
+        .rows(&format!(
+            "SELECT s.id, s.class FROM segment s WHERE {rail} ORDER BY s.id"
+        ))
+        .await?;
+    let lines = geometries_of(
+        query,
+        &format!(
+            "SELECT ST_AsBinary(s.{GEOMETRY}) AS {GEOMETRY} FROM segment s
+             WHERE {rail} ORDER BY s.id"
+        ),
+        extract_id,
+        "segment",
+        stored.len(),
+    )
+    .await?;
+
+    let connectors: Vec<StoredConnector> = query
+        .rows(&format!(
+            "SELECT id, elem['connector_id'] AS connector_id
+             FROM (SELECT s.id, UNNEST(s.connectors) AS elem FROM segment s WHERE {rail}) AS refs"
+        ))
+        .await?;
+    let mut connectors_of: HashMap<String, Vec<String>> = HashMap::new();
+    for connector in connectors {
+        connectors_of
+            .entry(connector.id)
+            .or_default()
+            .push(connector.connector_id);
+    }
+
+    let flags: Vec<StoredFlag> = query
+        .rows(&format!(
+            "SELECT id, flag['values'] AS \"values\", flag['between'] AS between
+             FROM (SELECT s.id, UNNEST(s.rail_flags) AS flag FROM segment s WHERE {rail}) AS flags"
+        ))
+        .await?;
+    let mut flags_of: HashMap<String, Vec<RailFlag>> = HashMap::new();
+    for flag in flags {
+        flags_of.entry(flag.id).or_default().push(RailFlag {
+            values: flag.values,
+            between: flag.between.and_then(|between| match between[..] {
+                [from, to] => Some((from, to)),
+                _ => None,
+            }),
+        });
+    }
+
+    stored
+        .into_iter()
+        .zip(lines)
+        .map(|(rail, line)| {
+            let Geometry::LineString(line) = line else {
+                return Err(CrossingsError::NotALine { id: rail.id });
+            };
+            Ok(Rail {
+                connectors: connectors_of.remove(&rail.id).unwrap_or_default(),
+                flags: flags_of.remove(&rail.id).unwrap_or_default(),
+                id: rail.id,
+                class: rail.class,
+                line,
+            })
+        })
+        .collect()
+}
+
+/// Every water some rail segment in the country touches.
+async fn waters_met(
+    query: &Query,
+    country: Country,
+    extract_id: &str,
+) -> Result<Vec<Water>, CrossingsError> {
+    let met = format!(
+        "id IN (
+           SELECT w.id FROM water w JOIN segment s ON ST_Intersects(s.{GEOMETRY}, w.{GEOMETRY})
+           WHERE {}
+         )",
+        rail_in("s", country)
+    );
+    let stored: Vec<StoredWater> = query
+        .rows(&format!(
+            "SELECT id, subtype, class FROM water WHERE {met} ORDER BY id"
+        ))
+        .await?;
+    let geometries = geometries_of(
+        query,
+        &format!("SELECT ST_AsBinary({GEOMETRY}) AS {GEOMETRY} FROM water WHERE {met} ORDER BY id"),
+        extract_id,
+        "water",
+        stored.len(),
+    )
+    .await?;
+
+    Ok(stored
+        .into_iter()
+        .zip(geometries)
+        .map(|(water, geometry)| Water {
+            id: water.id,
+            subtype: water.subtype,
+            class: water.class,
+            geometry,
+        })
+        .collect())
+}
+
+/// Each rail segment paired with the waters it touches.
+async fn meetings_of<'a>(
+    query: &Query,
+    country: Country,
+    rails: &'a [Rail],
+    waters: &'a [Water],
+) -> Result<Vec<Meeting<'a>>, CrossingsError> {
+    let pairs: Vec<StoredMeeting> = query
+        .rows(&format!(
+            "SELECT s.id AS rail_id, w.id AS water_id
+             FROM segment s JOIN water w ON ST_Intersects(s.{GEOMETRY}, w.{GEOMETRY})
+             WHERE {}
+             ORDER BY rail_id, water_id",
+            rail_in("s", country)
+        ))
+        .await?;
+
+    let water_by_id: HashMap<&str, &Water> = waters
+        .iter()
+        .map(|water| (water.id.as_str(), water))
+        .collect();
+    let mut waters_of: HashMap<String, Vec<&Water>> = HashMap::new();
+    for pair in pairs {
+        if let Some(water) = water_by_id.get(pair.water_id.as_str()) {
+            waters_of.entry(pair.rail_id).or_default().push(water);
+        }
+    }
+
+    Ok(rails
+        .iter()
+        .filter_map(|rail| {
+            waters_of
+                .remove(rail.id.as_str())
+                .map(|waters| Meeting { rail, waters })
+        })
+        .collect())
+}
+
+/// The geometry column of `sql`'s rows, checked to line up with the `expected` attribute
+/// rows read for the same table in the same order.
+async fn geometries_of(
+    query: &Query,
+    sql: &str,
+    extract_id: &str,
+    table: &'static str,
+    expected: usize,
+) -> Result<Vec<Geometry<f64>>, CrossingsError> {
+    let mut geometries = Vec::with_capacity(expected);
+    for batch in &query.sql(sql).await? {
+        geometries.extend(medallion::geometries(batch, GEOMETRY)?);
+    }
+    if geometries.len() != expected {
+        return Err(CrossingsError::Misaligned {
+            id: extract_id.to_string(),
+            table,
+            expected,
+            found: geometries.len(),
+        });
+    }
+    Ok(geometries)
+}
+
+/// A predicate keeping the rail segments, read as `segment`, that reach into `country`'s
+/// area. Reaching in rather than lying within, so a segment over the border is read whole.
+fn rail_in(segment: &str, country: Country) -> String {
+    format!(
+        "{segment}.subtype = 'rail' AND {segment}.id IN (
+           SELECT r.id FROM segment r JOIN division_area a
+             ON ST_Intersects(r.{GEOMETRY}, a.{GEOMETRY})
+           WHERE a.subtype = 'country' AND a.country = '{}'
+         )",
+        country.code()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A segment is read whole if any of it is in the country, so a crossing just over the
+    /// border is found and then dropped, rather than the segment's `frac` being measured along
+    /// a clipped line the reference data does not hold.
+    #[test]
+    fn rail_is_kept_when_it_reaches_into_the_country() {
+        let predicate = rail_in("s", Country::GERMANY);
+
+        assert!(predicate.starts_with("s.subtype = 'rail' AND s.id IN ("));
+        assert!(predicate.contains("ST_Intersects(r.geometry, a.geometry)"));
+        assert!(predicate.contains("a.country = 'DE'"));
+    }
+
+    /// A store nothing has been extracted into has no rail or water to cross, and says so
+    /// rather than writing an empty dataset over the one it has.
+    #[tokio::test]
+    async fn a_store_with_no_extract_derives_nothing() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+
+        let err = derive(&Root::new(tmp.path()), None, Tuning::default()).await;
+
+        assert!(matches!(err, Err(CrossingsError::Extract(_))), "{err:?}");
+        assert!(!tmp.path().join("silver").exists());
+    }
+}